@@ -0,0 +1,108 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use martinez::kv::code_compression::{train_dictionary, CodeCodec};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const PROLOGUE: &[u8] = &[
+    0x60, 0x80, 0x60, 0x40, 0x52, 0x34, 0x80, 0x15, 0x61, 0x00, 0x10, 0x57, 0x60, 0x00, 0x80, 0xfd,
+    0x5b, 0x50,
+];
+const COMMON_OPCODES: &[u8] = &[
+    0x01, 0x02, 0x03, 0x10, 0x11, 0x14, 0x15, 0x16, 0x19, 0x35, 0x36, 0x50, 0x51, 0x52, 0x54, 0x55,
+    0x56, 0x57, 0x5b, 0x80, 0x81, 0x82, 0x90, 0x91, 0xf3, 0xfd,
+];
+
+/// Solidity-like bytecode: a dispatcher over random selectors, and function bodies
+/// made of common opcodes and pushes.
+fn synthetic_contract(rng: &mut StdRng) -> Vec<u8> {
+    let mut code = PROLOGUE.to_vec();
+    let functions = rng.gen_range(2..24);
+    for _ in 0..functions {
+        // DUP1 PUSH4 selector EQ PUSH2 dest JUMPI
+        code.extend_from_slice(&[0x80, 0x63]);
+        code.extend_from_slice(&rng.gen::<[u8; 4]>());
+        code.extend_from_slice(&[0x14, 0x61]);
+        code.extend_from_slice(&rng.gen::<[u8; 2]>());
+        code.push(0x57);
+    }
+    for _ in 0..functions * rng.gen_range(20..200) {
+        if rng.gen_bool(0.2) {
+            let len = rng.gen_range(1..=32);
+            code.push(0x5f + len as u8);
+            code.extend((0..len).map(|_| rng.gen::<u8>()));
+        } else {
+            code.push(COMMON_OPCODES[rng.gen_range(0..COMMON_OPCODES.len())]);
+        }
+    }
+    // metadata hash
+    code.extend_from_slice(&[0xa2, 0x64, 0x69, 0x70, 0x66, 0x73, 0x58, 0x22]);
+    code.extend((0..34).map(|_| rng.gen::<u8>()));
+    code
+}
+
+fn bench_code_compression(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(0);
+    let contracts = (0..2000)
+        .map(|_| synthetic_contract(&mut rng))
+        .collect::<Vec<_>>();
+    let (training, samples) = contracts.split_at(1500);
+    let dictionary = train_dictionary(training).unwrap();
+
+    let codecs = [
+        ("raw", CodeCodec::new(false, None)),
+        ("zstd", CodeCodec::new(true, None)),
+        ("zstd-dict", CodeCodec::new(true, Some(&dictionary))),
+    ];
+
+    let total_size = samples.iter().map(Vec::len).sum::<usize>();
+    let mut group = c.benchmark_group("code_compression");
+    group.throughput(Throughput::Bytes(total_size as u64));
+    for (name, codec) in &codecs {
+        let encoded = samples
+            .iter()
+            .map(|code| codec.encode(code))
+            .collect::<Vec<_>>();
+        let encoded_size = encoded.iter().map(Vec::len).sum::<usize>();
+        println!(
+            "{}: {} -> {} bytes ({:.1}%)",
+            name,
+            total_size,
+            encoded_size,
+            encoded_size as f64 * 100.0 / total_size as f64
+        );
+
+        group.bench_with_input(BenchmarkId::new("encode", name), samples, |b, samples| {
+            b.iter(|| {
+                for code in samples {
+                    black_box(codec.encode(code));
+                }
+            })
+        });
+        // a fresh codec per run, to measure decompression rather than the cache
+        group.bench_with_input(BenchmarkId::new("decode", name), &encoded, |b, encoded| {
+            b.iter_batched(
+                || CodeCodec::new(true, Some(&dictionary)),
+                |codec| {
+                    for value in encoded {
+                        black_box(codec.decode(value).unwrap());
+                    }
+                },
+                criterion::BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(
+            BenchmarkId::new("decode-cached", name),
+            &encoded,
+            |b, encoded| {
+                b.iter(|| {
+                    for value in encoded {
+                        black_box(codec.decode(value).unwrap());
+                    }
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_code_compression);
+criterion_main!(benches);
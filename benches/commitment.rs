@@ -0,0 +1,106 @@
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+};
+use martinez::{
+    commitment::StateRootService,
+    crypto::keccak256,
+    kv::{new_mem_database, tables},
+    models::*,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const ACCOUNTS: usize = 10_000;
+const CHANGED: [usize; 3] = [10, 100, 1000];
+
+fn random_account(rng: &mut StdRng) -> Account {
+    Account {
+        nonce: rng.gen_range(0..1000),
+        balance: U256::from(rng.gen::<u64>()),
+        code_hash: EMPTY_HASH,
+    }
+}
+
+fn bench_state_root(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(0);
+    let accounts = (0..ACCOUNTS)
+        .map(|_| (Address::random_using(&mut rng), random_account(&mut rng)))
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("state_root");
+    group.sample_size(10);
+
+    group.throughput(Throughput::Elements(ACCOUNTS as u64));
+    group.bench_function("from_scratch", |b| {
+        b.iter_batched(
+            || {
+                let mut service = StateRootService::new();
+                for &(address, account) in &accounts {
+                    service.update_account(address, Some(account));
+                }
+                (new_mem_database().unwrap(), service)
+            },
+            |(db, mut service)| {
+                let tx = db.begin_mutable().unwrap();
+                black_box(service.state_root_hash(&tx).unwrap())
+            },
+            BatchSize::PerIteration,
+        )
+    });
+
+    // Trie of all accounts stored, only some of them changed.
+    let db = new_mem_database().unwrap();
+    let tx = db.begin_mutable().unwrap();
+    let mut service = StateRootService::new();
+    for &(address, account) in &accounts {
+        tx.set(tables::HashedAccount, keccak256(address), account)
+            .unwrap();
+        service.update_account(address, Some(account));
+    }
+    service.state_root_hash(&tx).unwrap();
+    tx.commit().unwrap();
+
+    for changed in CHANGED {
+        group.throughput(Throughput::Elements(changed as u64));
+        group.bench_with_input(
+            BenchmarkId::new("incremental", changed),
+            &changed,
+            |b, &changed| {
+                b.iter_batched(
+                    || {
+                        let mut service = StateRootService::new();
+                        for _ in 0..changed {
+                            let (address, _) = accounts[rng.gen_range(0..accounts.len())];
+                            service.update_account(address, Some(random_account(&mut rng)));
+                        }
+                        service
+                    },
+                    |mut service| {
+                        // Dropped without commit, so that every iteration starts from the same trie.
+                        let tx = db.begin_mutable().unwrap();
+                        black_box(service.state_root_hash(&tx).unwrap())
+                    },
+                    BatchSize::PerIteration,
+                )
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_account_rlp(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(0);
+    let account = random_account(&mut rng).to_rlp(H256::random_using(&mut rng));
+
+    let mut group = c.benchmark_group("account_rlp");
+    group.bench_function("rlp_encode", |b| {
+        b.iter(|| rlp::encode(black_box(&account)))
+    });
+    group.bench_function("encode_fixed", |b| {
+        b.iter(|| black_box(&account).encode_fixed())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_state_root, bench_account_rlp);
+criterion_main!(benches);
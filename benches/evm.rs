@@ -0,0 +1,80 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use martinez::{
+    execution::evm::{util::*, StatusCode},
+    models::Revision,
+};
+
+const GAS: i64 = 100_000_000;
+
+/// Loop running `body` `iterations` times. The body must leave the stack as it found it,
+/// with the loop counter on top.
+fn looped(iterations: u16, body: &[u8]) -> Vec<u8> {
+    let [hi, lo] = iterations.to_be_bytes();
+    // PUSH2 iterations, JUMPDEST
+    let mut code = vec![0x61, hi, lo, 0x5b];
+    code.extend_from_slice(body);
+    // PUSH1 1, SWAP1, SUB, DUP1, PUSH1 3, JUMPI, STOP
+    code.extend_from_slice(&[0x60, 0x01, 0x90, 0x03, 0x80, 0x60, 0x03, 0x57, 0x00]);
+    code
+}
+
+/// Arithmetic and memory traffic, like the vector math of a ray tracer.
+fn arithmetic(iterations: u16) -> Vec<u8> {
+    looped(
+        iterations,
+        &[
+            // DUP1, DUP1, MUL, DUP2, ADD
+            0x80, 0x80, 0x02, 0x81, 0x01, //
+            // PUSH1 7, DUP3, MULMOD
+            0x60, 0x07, 0x82, 0x09, //
+            // DUP2, SDIV, PUSH1 0, MSTORE
+            0x81, 0x05, 0x60, 0x00, 0x52, //
+            // PUSH1 0, MLOAD, PUSH1 32, MSTORE
+            0x60, 0x00, 0x51, 0x60, 0x20, 0x52,
+        ],
+    )
+}
+
+fn hashing(iterations: u16) -> Vec<u8> {
+    looped(
+        iterations,
+        &[
+            // DUP1, PUSH1 0, MSTORE
+            0x80, 0x60, 0x00, 0x52, //
+            // PUSH1 64, PUSH1 0, SHA3, PUSH1 32, MSTORE
+            0x60, 0x40, 0x60, 0x00, 0x20, 0x60, 0x20, 0x52,
+        ],
+    )
+}
+
+fn storage(iterations: u16) -> Vec<u8> {
+    looped(
+        iterations,
+        &[
+            // DUP1, DUP1, SSTORE, DUP1, SLOAD, POP
+            0x80, 0x80, 0x55, 0x80, 0x54, 0x50,
+        ],
+    )
+}
+
+fn bench_evm(c: &mut Criterion) {
+    let mut group = c.benchmark_group("evm");
+    for (name, code) in [
+        ("arithmetic", arithmetic(10_000)),
+        ("hashing", hashing(10_000)),
+        ("storage", storage(1_000)),
+    ] {
+        let tester = EvmTester::new()
+            .revision(Revision::London)
+            .gas(GAS)
+            .code(code)
+            .status(StatusCode::Success);
+        group.bench_with_input(BenchmarkId::new("execute", name), &tester, |b, tester| {
+            b.iter(|| tester.clone().check())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_evm);
+criterion_main!(benches);
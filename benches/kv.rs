@@ -0,0 +1,103 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use martinez::{
+    crypto::{keccak256, keccak256_batch},
+    kv::tables,
+    models::*,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const ENTRIES: usize = 100_000;
+
+fn bench_hash_keys(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(0);
+    let addresses = (0..ENTRIES)
+        .map(|_| Address::random_using(&mut rng))
+        .collect::<Vec<_>>();
+    let locations = (0..ENTRIES)
+        .map(|_| H256::random_using(&mut rng))
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("hash_keys");
+    group.throughput(Throughput::Elements(ENTRIES as u64));
+    group.bench_function("address", |b| {
+        b.iter(|| {
+            for address in &addresses {
+                black_box(keccak256(address));
+            }
+        })
+    });
+    group.bench_function("location", |b| {
+        b.iter(|| {
+            for location in &locations {
+                black_box(keccak256(location));
+            }
+        })
+    });
+    group.bench_function("address_batch", |b| {
+        b.iter(|| black_box(keccak256_batch(&addresses)))
+    });
+    group.bench_function("location_batch", |b| {
+        b.iter(|| black_box(keccak256_batch(&locations)))
+    });
+    group.finish();
+}
+
+fn bench_cursor(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(0);
+    let db = martinez::kv::new_mem_database().unwrap();
+
+    let mut accounts = (0..ENTRIES)
+        .map(|_| {
+            (
+                Address::random_using(&mut rng),
+                Account {
+                    nonce: rng.gen_range(0..1000),
+                    balance: U256::from(rng.gen::<u64>()),
+                    code_hash: EMPTY_HASH,
+                },
+            )
+        })
+        .collect::<Vec<_>>();
+    accounts.sort_by_key(|(address, _)| *address);
+    accounts.dedup_by_key(|(address, _)| *address);
+
+    let tx = db.begin_mutable().unwrap();
+    let mut cursor = tx.cursor(tables::Account).unwrap();
+    for (address, account) in &accounts {
+        cursor.append(*address, *account).unwrap();
+    }
+    drop(cursor);
+    tx.commit().unwrap();
+
+    let mut group = c.benchmark_group("cursor");
+    group.throughput(Throughput::Elements(accounts.len() as u64));
+    group.bench_function("walk", |b| {
+        b.iter(|| {
+            let tx = db.begin().unwrap();
+            for entry in tx.cursor(tables::Account).unwrap().walk(None) {
+                black_box(entry.unwrap());
+            }
+        })
+    });
+    group.bench_function("walk_back", |b| {
+        b.iter(|| {
+            let tx = db.begin().unwrap();
+            for entry in tx.cursor(tables::Account).unwrap().walk_back(None) {
+                black_box(entry.unwrap());
+            }
+        })
+    });
+    group.bench_function("seek_exact", |b| {
+        b.iter(|| {
+            let tx = db.begin().unwrap();
+            let mut cursor = tx.cursor(tables::Account).unwrap();
+            for (address, _) in accounts.iter().step_by(10) {
+                black_box(cursor.seek_exact(*address).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_hash_keys, bench_cursor);
+criterion_main!(benches);
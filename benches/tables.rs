@@ -0,0 +1,143 @@
+use bytes::Bytes;
+use criterion::{
+    black_box, criterion_group, criterion_main, measurement::WallTime, BenchmarkGroup, BenchmarkId,
+    Criterion, Throughput,
+};
+use martinez::{
+    kv::{
+        tables::{AccountChange, StorageChange, StorageChangeKey},
+        traits::*,
+    },
+    models::*,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const SAMPLES: usize = 1000;
+
+fn random_u256(rng: &mut StdRng) -> U256 {
+    U256::from_words(rng.gen(), rng.gen())
+}
+
+fn random_account(rng: &mut StdRng) -> Account {
+    Account {
+        nonce: rng.gen_range(0..1000),
+        balance: U256::from(rng.gen::<u64>()),
+        code_hash: if rng.gen_bool(0.1) {
+            H256::random_using(rng)
+        } else {
+            EMPTY_HASH
+        },
+    }
+}
+
+fn random_header(rng: &mut StdRng) -> BlockHeader {
+    BlockHeader {
+        parent_hash: H256::random_using(rng),
+        ommers_hash: H256::random_using(rng),
+        beneficiary: Address::random_using(rng),
+        state_root: H256::random_using(rng),
+        transactions_root: H256::random_using(rng),
+        receipts_root: H256::random_using(rng),
+        logs_bloom: Bloom::random_using(rng),
+        difficulty: U256::from(rng.gen::<u64>()),
+        number: BlockNumber(rng.gen_range(0..15_000_000)),
+        gas_limit: 30_000_000,
+        gas_used: rng.gen_range(0..30_000_000),
+        timestamp: rng.gen(),
+        extra_data: Bytes::from(rng.gen::<[u8; 32]>().to_vec()),
+        mix_hash: H256::random_using(rng),
+        nonce: H64::random_using(rng),
+        base_fee_per_gas: Some(U256::from(rng.gen::<u32>())),
+    }
+}
+
+fn bench_codec<T>(group: &mut BenchmarkGroup<'_, WallTime>, name: &str, values: Vec<T>)
+where
+    T: TableEncode + TableDecode + Clone,
+{
+    let encoded = values
+        .iter()
+        .cloned()
+        .map(|v| v.encode().as_ref().to_vec())
+        .collect::<Vec<_>>();
+
+    group.throughput(Throughput::Elements(values.len() as u64));
+    group.bench_with_input(BenchmarkId::new("encode", name), &values, |b, values| {
+        b.iter_batched(
+            || values.clone(),
+            |values| {
+                for value in values {
+                    black_box(value.encode());
+                }
+            },
+            criterion::BatchSize::SmallInput,
+        )
+    });
+    group.bench_with_input(BenchmarkId::new("decode", name), &encoded, |b, encoded| {
+        b.iter(|| {
+            for value in encoded {
+                black_box(T::decode(value).unwrap());
+            }
+        })
+    });
+}
+
+fn bench_tables(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(0);
+
+    let mut group = c.benchmark_group("tables");
+    bench_codec(
+        &mut group,
+        "header",
+        (0..SAMPLES).map(|_| random_header(&mut rng)).collect(),
+    );
+    bench_codec(
+        &mut group,
+        "account",
+        (0..SAMPLES).map(|_| random_account(&mut rng)).collect(),
+    );
+    bench_codec(
+        &mut group,
+        "storage_value",
+        (0..SAMPLES)
+            .map(|_| (H256::random_using(&mut rng), random_u256(&mut rng)))
+            .collect(),
+    );
+    group.finish();
+
+    let mut group = c.benchmark_group("changesets");
+    bench_codec(
+        &mut group,
+        "account_change",
+        (0..SAMPLES)
+            .map(|_| AccountChange {
+                address: Address::random_using(&mut rng),
+                account: rng.gen_bool(0.9).then(|| random_account(&mut rng)),
+            })
+            .collect(),
+    );
+    bench_codec(
+        &mut group,
+        "storage_change_key",
+        (0..SAMPLES)
+            .map(|_| StorageChangeKey {
+                block_number: BlockNumber(rng.gen_range(0..15_000_000)),
+                address: Address::random_using(&mut rng),
+            })
+            .collect(),
+    );
+    bench_codec(
+        &mut group,
+        "storage_change",
+        (0..SAMPLES)
+            .map(|_| StorageChange {
+                location: H256::random_using(&mut rng),
+                value: random_u256(&mut rng),
+            })
+            .collect(),
+    );
+    group.finish();
+}
+
+criterion_group!(benches, bench_tables);
+criterion_main!(benches);
@@ -6,6 +6,7 @@ use martinez::{
         *,
     },
     crypto::keccak256,
+    execution::{evm::OpCode, tracer::OpcodeCoverage},
     models::*,
     res::chainspec::*,
     *,
@@ -14,12 +15,14 @@ use anyhow::{bail, ensure, format_err};
 use bytes::Bytes;
 use clap::Parser;
 use educe::Educe;
+use itertools::Itertools;
 use maplit::*;
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
+use parking_lot::Mutex;
 use serde::{de, Deserialize};
 use serde_json::{Map, Value};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     convert::TryInto,
     fmt::Debug,
     ops::AddAssign,
@@ -37,6 +40,9 @@ pub static BLOCKCHAIN_DIR: Lazy<PathBuf> = Lazy::new(|| Path::new("BlockchainTes
 pub static TRANSACTION_DIR: Lazy<PathBuf> =
     Lazy::new(|| Path::new("TransactionTests").to_path_buf());
 
+/// Opcodes executed by blockchain tests, if requested.
+static COVERAGE: OnceCell<Mutex<OpcodeCoverage>> = OnceCell::new();
+
 pub static IGNORED_TX_EXCEPTIONS: Lazy<HashSet<String>> = Lazy::new(|| {
     hashset! {
         // This is not checked for now.
@@ -664,16 +670,25 @@ fn blockchain_test(testdata: BlockchainTest) -> anyhow::Result<()> {
     init_pre_state(&testdata.pre, &mut state);
 
     let mut blockchain = Blockchain::new(&mut state, config, genesis_block).unwrap();
+    if COVERAGE.get().is_some() {
+        blockchain.record_coverage();
+    }
 
-    for block in &testdata.blocks {
+    let res = testdata.blocks.iter().try_for_each(|block| {
         let block_common =
             serde_json::from_value::<BlockCommon>(Value::Object(block.clone())).unwrap();
         result_is_expected(
             run_block(&block_common, &mut blockchain),
             block_common.expect_exception,
-        )?;
+        )
+    });
+
+    if let (Some(coverage), Some(test_coverage)) = (COVERAGE.get(), blockchain.take_coverage()) {
+        coverage.lock().merge(&test_coverage);
     }
 
+    res?;
+
     if let Some(expected_hash) = testdata.post_state_hash {
         let state_root = state.state_root_hash();
 
@@ -862,6 +877,9 @@ pub struct Opt {
     pub tests: PathBuf,
     #[clap(long)]
     pub test_names: Vec<String>,
+    /// Print opcodes never executed by the blockchain tests under each revision
+    #[clap(long)]
+    pub coverage: bool,
 }
 
 #[derive(Debug, Default)]
@@ -892,6 +910,20 @@ impl AddAssign<RunResults> for RunResults {
     }
 }
 
+/// Prints the opcodes of each revision left untested, those are where the interpreters
+/// may diverge unnoticed.
+fn print_uncovered(coverage: &OpcodeCoverage) {
+    let mut uncovered = BTreeMap::<Revision, Vec<OpCode>>::new();
+    for (revision, opcode) in coverage.uncovered() {
+        uncovered.entry(revision).or_default().push(opcode);
+    }
+
+    println!("Uncovered opcodes:");
+    for (revision, opcodes) in uncovered {
+        println!("{}: {}", revision, opcodes.iter().join(" "));
+    }
+}
+
 fn exclude_test(p: &Path, root: &Path) -> bool {
     for e in &*EXCLUDED_TESTS {
         if root.join(e) == p {
@@ -920,6 +952,10 @@ async fn run() {
         .with(env_filter)
         .init();
 
+    if opt.coverage {
+        COVERAGE.set(Default::default()).unwrap();
+    }
+
     let root_dir = opt.tests;
     let test_names = Arc::new(opt.test_names.into_iter().collect());
 
@@ -1004,6 +1040,10 @@ async fn run() {
         now.elapsed()
     );
 
+    if let Some(coverage) = COVERAGE.get() {
+        print_uncovered(&coverage.lock());
+    }
+
     if res.failed > 0 {
         std::process::exit(1);
     }
@@ -1,11 +1,54 @@
-use martinez::{binutil::MartinezDataDir, kv::mdbx::*, models::*, stagedsync::stages::*};
+use anyhow::{bail, ensure, format_err};
 use async_trait::async_trait;
 use clap::Parser;
+use ethereum_interfaces::txpool as grpc_txpool;
 use ethnum::U256;
-use jsonrpsee::{core::RpcResult, http_server::HttpServerBuilder, proc_macros::rpc};
+use jsonrpsee::{
+    core::{server::rpc_module::Methods, RpcResult},
+    http_server::{AccessControlBuilder, HttpServerBuilder},
+    proc_macros::rpc,
+    RpcModule,
+};
+use martinez::{
+    accessors,
+    binutil::{init_tracing, AccessMode, MartinezDataDir},
+    consensus::{engine_factory, FinalizationChange},
+    execution::{
+        address::create_address,
+        analysis_cache::AnalysisCache,
+        block_hashes::BlockHashes,
+        processor::ExecutionProcessor,
+        tracer::{MessageKind, Tracer},
+    },
+    kv::{mdbx::*, tables},
+    models::*,
+    sentry::{
+        node_record::LocalNode,
+        sentry_address::SentryAddress,
+        sentry_client::{NodeInfo, PeerInfo, SentryClient},
+        sentry_client_impl::SentryClientImpl,
+    },
+    stagedsync::stages::*,
+    trie::{prove_account, AccountProof},
+    version_string, Buffer,
+};
 use mdbx::EnvironmentKind;
-use std::{future::pending, net::SocketAddr, sync::Arc};
-use tracing_subscriber::{prelude::*, EnvFilter};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    future::pending,
+    net::SocketAddr,
+    ops::RangeInclusive,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{UnixListener, UnixStream},
+    sync::Mutex,
+};
+use tracing::*;
 
 #[derive(Parser)]
 #[clap(name = "Martinez RPC", about = "RPC server for Martinez")]
@@ -13,8 +56,74 @@ pub struct Opt {
     #[clap(long)]
     pub datadir: MartinezDataDir,
 
+    /// Chain whose subdirectory of the datadir to serve, as chosen by the node.
+    #[clap(long = "chain", default_value = "mainnet")]
+    pub chain_name: String,
+
     #[clap(long)]
     pub listen_address: SocketAddr,
+
+    /// History indices directory, if the node keeps them apart from the database.
+    #[clap(long = "db.cold-dir")]
+    pub cold_dir: Option<PathBuf>,
+
+    /// Sentry GRPC service URL as 'http://host:port', used by the admin namespace.
+    #[clap(long = "sentry.api.addr")]
+    pub sentry_api_addr: Option<SentryAddress>,
+
+    /// Datadir of the node, admin_nodeInfo reports the identity stored there when there is no
+    /// sentry.
+    #[clap(long = "node.datadir")]
+    pub node_data_dir: Option<MartinezDataDir>,
+
+    /// Transaction pool GRPC service URL as 'http://host:port', enables the txpool namespace.
+    #[clap(long = "txpool.api.addr")]
+    pub txpool_api_addr: Option<http::Uri>,
+
+    /// Maximum number of transactions returned by txpool_content and txpool_inspect.
+    #[clap(long = "txpool.content-limit", default_value = "10000")]
+    pub txpool_content_limit: usize,
+
+    /// Namespaces to serve. admin needs sentry or node datadir, net_peerCount and txpool need
+    /// their GRPC services.
+    #[clap(
+        long = "http.api",
+        use_delimiter = true,
+        default_value = "eth,net,web3,admin,txpool,ots"
+    )]
+    pub http_api: Vec<String>,
+
+    /// Methods to disable within the served namespaces, e.g. 'eth_getProof'.
+    #[clap(long = "http.disabled-methods", use_delimiter = true)]
+    pub http_disabled_methods: Vec<String>,
+
+    /// Origins allowed to make cross-origin requests, any if empty or '*'.
+    #[clap(long = "http.corsdomain", use_delimiter = true)]
+    pub http_corsdomain: Vec<String>,
+
+    /// Maximum request body size in bytes, limits batch requests as a whole.
+    #[clap(long = "http.max-request-size", default_value = "10485760")]
+    pub http_max_request_size: u32,
+
+    /// Most blocks scanned by one eth_getLogs call or martinez_getLogs page.
+    #[clap(long = "rpc.logs.max-blocks", default_value = "100000")]
+    pub logs_max_blocks: u64,
+
+    /// Most logs returned by one eth_getLogs call or martinez_getLogs page.
+    #[clap(long = "rpc.logs.max-results", default_value = "10000")]
+    pub logs_max_results: usize,
+
+    /// Seconds one eth_getLogs call or martinez_getLogs page may take.
+    #[clap(long = "rpc.logs.timeout", default_value = "10")]
+    pub logs_timeout: u64,
+
+    /// Unix domain socket to serve the same namespaces on, for local tooling.
+    #[clap(long = "ipcpath")]
+    pub ipc_path: Option<PathBuf>,
+
+    /// Print logs as JSON lines.
+    #[clap(long = "log.json")]
+    pub log_json: bool,
 }
 
 #[rpc(server, namespace = "eth")]
@@ -22,7 +131,1019 @@ pub trait EthApi {
     #[method(name = "blockNumber")]
     async fn block_number(&self) -> RpcResult<BlockNumber>;
     #[method(name = "getBalance")]
-    async fn get_balance(&self, address: Address, block_number: BlockNumber) -> RpcResult<U256>;
+    async fn get_balance(
+        &self,
+        address: Address,
+        block_number: BlockNumberOrTag,
+    ) -> RpcResult<U256>;
+    #[method(name = "getProof")]
+    async fn get_proof(
+        &self,
+        address: Address,
+        storage_keys: Vec<H256>,
+        block_number: BlockNumberOrTag,
+    ) -> RpcResult<EIP1186ProofResponse>;
+    #[method(name = "syncing")]
+    async fn syncing(&self) -> RpcResult<SyncingResponse>;
+    #[method(name = "getBlockReceipts")]
+    async fn get_block_receipts(
+        &self,
+        block_number: BlockNumberOrTag,
+    ) -> RpcResult<Option<Vec<ReceiptResponse>>>;
+    #[method(name = "getTransactionReceipt")]
+    async fn get_transaction_receipt(&self, hash: H256) -> RpcResult<Option<ReceiptResponse>>;
+    #[method(name = "getLogs")]
+    async fn get_logs(&self, filter: LogFilter) -> RpcResult<Vec<LogResponse>>;
+}
+
+/// Stages reported by `eth_syncing`, in pipeline order.
+const SYNC_STAGES: &[StageId] = &[
+    HEADERS,
+    TOTAL_GAS_INDEX,
+    BLOCK_HASHES,
+    BODIES,
+    TOTAL_TX_INDEX,
+    SENDERS,
+    EXECUTION,
+    HASH_STATE,
+    INTERMEDIATE_HASHES,
+    ACCOUNT_HISTORY_INDEX,
+    STORAGE_HISTORY_INDEX,
+    LOG_INDEX,
+    CALL_TRACES,
+    TX_LOOKUP,
+    TX_POOL,
+    PRUNE,
+    FINISH,
+];
+
+#[derive(Serialize)]
+pub struct StageProgressResponse {
+    pub stage_name: String,
+    pub block_number: U64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatusResponse {
+    pub starting_block: U64,
+    pub current_block: U64,
+    pub highest_block: U64,
+    /// Progress of each staged sync stage, as in Erigon.
+    pub stages: Vec<StageProgressResponse>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum SyncingResponse {
+    NotSyncing(bool),
+    Syncing(SyncStatusResponse),
+}
+
+fn hex_nodes(proof: Vec<bytes::Bytes>) -> Vec<String> {
+    proof
+        .into_iter()
+        .map(|node| format!("0x{}", hex::encode(node)))
+        .collect()
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageProofResponse {
+    pub key: H256,
+    pub value: U256,
+    pub proof: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EIP1186ProofResponse {
+    pub address: Address,
+    pub balance: U256,
+    pub code_hash: H256,
+    pub nonce: U64,
+    pub storage_hash: H256,
+    pub account_proof: Vec<String>,
+    pub storage_proof: Vec<StorageProofResponse>,
+}
+
+impl From<AccountProof> for EIP1186ProofResponse {
+    fn from(proof: AccountProof) -> Self {
+        let account = proof.account.unwrap_or_default();
+        Self {
+            address: proof.address,
+            balance: account.balance,
+            code_hash: account.code_hash,
+            nonce: account.nonce.into(),
+            storage_hash: proof.storage_hash,
+            account_proof: hex_nodes(proof.proof),
+            storage_proof: proof
+                .storage_proofs
+                .into_iter()
+                .map(|p| StorageProofResponse {
+                    key: p.key,
+                    value: p.value,
+                    proof: hex_nodes(p.proof),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[rpc(server, namespace = "admin")]
+pub trait AdminApi {
+    #[method(name = "peers")]
+    async fn peers(&self) -> RpcResult<Vec<PeerInfoResponse>>;
+    #[method(name = "nodeInfo")]
+    async fn node_info(&self) -> RpcResult<NodeInfoResponse>;
+    #[method(name = "addPeer")]
+    async fn add_peer(&self, enode: String) -> RpcResult<bool>;
+    /// Sentry has no separate trusted peer set, so trusted peers are added as static ones,
+    /// which it keeps connected regardless of the peer limit.
+    #[method(name = "addTrustedPeer")]
+    async fn add_trusted_peer(&self, enode: String) -> RpcResult<bool>;
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerNetworkResponse {
+    pub local_address: String,
+    pub remote_address: String,
+    pub inbound: bool,
+    pub trusted: bool,
+    #[serde(rename = "static")]
+    pub is_static: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerInfoResponse {
+    pub id: String,
+    pub name: String,
+    pub enode: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub enr: String,
+    pub caps: Vec<String>,
+    pub network: PeerNetworkResponse,
+}
+
+impl From<PeerInfo> for PeerInfoResponse {
+    fn from(peer: PeerInfo) -> Self {
+        Self {
+            id: peer.id,
+            name: peer.name,
+            enode: peer.enode,
+            enr: peer.enr,
+            caps: peer.caps,
+            network: PeerNetworkResponse {
+                local_address: peer.local_addr,
+                remote_address: peer.remote_addr,
+                inbound: peer.inbound,
+                trusted: peer.trusted,
+                is_static: peer.is_static,
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodePortsResponse {
+    pub discovery: u32,
+    pub listener: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeInfoResponse {
+    pub id: String,
+    pub name: String,
+    pub enode: String,
+    pub enr: String,
+    pub ports: NodePortsResponse,
+    pub listen_addr: String,
+}
+
+impl From<NodeInfo> for NodeInfoResponse {
+    fn from(info: NodeInfo) -> Self {
+        Self {
+            id: info.id,
+            name: info.name,
+            enode: info.enode,
+            enr: info.enr,
+            ports: NodePortsResponse {
+                discovery: info.discovery_port,
+                listener: info.listener_port,
+            },
+            listen_addr: info.listener_addr,
+        }
+    }
+}
+
+#[rpc(server, namespace = "txpool")]
+pub trait TxPoolApi {
+    #[method(name = "status")]
+    async fn status(&self) -> RpcResult<TxPoolStatusResponse>;
+    #[method(name = "content")]
+    async fn content(&self) -> RpcResult<TxPoolContentResponse<TxResponse>>;
+    #[method(name = "inspect")]
+    async fn inspect(&self) -> RpcResult<TxPoolContentResponse<String>>;
+}
+
+#[derive(Serialize)]
+pub struct TxPoolStatusResponse {
+    pub pending: U64,
+    pub queued: U64,
+}
+
+/// Pool transactions by sender and nonce.
+#[derive(Serialize)]
+pub struct TxPoolContentResponse<T> {
+    pub pending: BTreeMap<Address, BTreeMap<u64, T>>,
+    pub queued: BTreeMap<Address, BTreeMap<u64, T>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxResponse {
+    pub block_hash: Option<H256>,
+    pub block_number: Option<U64>,
+    pub transaction_index: Option<U64>,
+    pub hash: H256,
+    #[serde(rename = "type")]
+    pub tx_type: U64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<U64>,
+    pub from: Address,
+    pub to: Option<Address>,
+    pub nonce: U64,
+    pub value: U256,
+    pub gas: U64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_price: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_fee_per_gas: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_priority_fee_per_gas: Option<U256>,
+    pub input: String,
+    pub v: U64,
+    pub r: H256,
+    pub s: H256,
+}
+
+impl TxResponse {
+    fn new(from: Address, tx: &MessageWithSignature) -> Self {
+        let (gas_price, max_fee_per_gas, max_priority_fee_per_gas, v) = match tx.tx_type() {
+            TxType::Legacy => (
+                Some(tx.max_fee_per_gas()),
+                None,
+                None,
+                YParityAndChainId {
+                    odd_y_parity: tx.signature.odd_y_parity(),
+                    chain_id: tx.chain_id(),
+                }
+                .v(),
+            ),
+            TxType::EIP2930 => (Some(tx.max_fee_per_gas()), None, None, tx.v().into()),
+            TxType::EIP1559 => (
+                None,
+                Some(tx.max_fee_per_gas()),
+                Some(tx.max_priority_fee_per_gas()),
+                tx.v().into(),
+            ),
+        };
+
+        Self {
+            block_hash: None,
+            block_number: None,
+            transaction_index: None,
+            hash: tx.hash(),
+            tx_type: (tx.tx_type() as u64).into(),
+            chain_id: tx.chain_id().map(|chain_id| chain_id.0.into()),
+            from,
+            to: match tx.action() {
+                TransactionAction::Call(to) => Some(to),
+                TransactionAction::Create => None,
+            },
+            nonce: tx.nonce().into(),
+            value: tx.value(),
+            gas: tx.gas_limit().into(),
+            gas_price,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            input: format!("0x{}", hex::encode(tx.input())),
+            v: v.into(),
+            r: tx.r(),
+            s: tx.s(),
+        }
+    }
+}
+
+/// One line summary in the format of `txpool_inspect` in geth.
+fn inspect_tx(tx: &MessageWithSignature) -> String {
+    let to = match tx.action() {
+        TransactionAction::Call(to) => format!("{:?}", to),
+        TransactionAction::Create => "contract creation".to_string(),
+    };
+    format!(
+        "{}: {} wei + {} gas × {} wei",
+        to,
+        tx.value(),
+        tx.gas_limit(),
+        tx.max_fee_per_gas()
+    )
+}
+
+#[rpc(server, namespace = "web3")]
+pub trait Web3Api {
+    #[method(name = "clientVersion")]
+    async fn client_version(&self) -> RpcResult<String>;
+}
+
+#[rpc(server, namespace = "net")]
+pub trait NetApi {
+    #[method(name = "version")]
+    async fn version(&self) -> RpcResult<String>;
+    #[method(name = "peerCount")]
+    async fn peer_count(&self) -> RpcResult<U64>;
+}
+
+#[rpc(server, namespace = "martinez")]
+pub trait MartinezApi {
+    /// Blocks whose data was pruned from each index.
+    #[method(name = "prunedBlocks")]
+    async fn pruned_blocks(&self) -> RpcResult<Vec<PrunedBlocksResponse>>;
+    /// Same as `eth_getLogs`, but returns as much as the limits allow along with a cursor to
+    /// continue from.
+    #[method(name = "getLogs")]
+    async fn get_logs(
+        &self,
+        filter: LogFilter,
+        cursor: Option<String>,
+    ) -> RpcResult<LogsPageResponse>;
+}
+
+/// Data of blocks `from..to` is gone from the tables filled by the stage.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrunedBlocksResponse {
+    pub stage_name: String,
+    pub from: U64,
+    pub to: U64,
+}
+
+#[rpc(server, namespace = "ots")]
+pub trait OtterscanApi {
+    #[method(name = "getBlockDetails")]
+    async fn get_block_details(
+        &self,
+        block_number: BlockNumber,
+    ) -> RpcResult<Option<BlockDetailsResponse>>;
+    #[method(name = "searchTransactionsBefore")]
+    async fn search_transactions_before(
+        &self,
+        address: Address,
+        block_number: BlockNumber,
+        page_size: usize,
+    ) -> RpcResult<TransactionsWithReceiptsResponse>;
+    #[method(name = "searchTransactionsAfter")]
+    async fn search_transactions_after(
+        &self,
+        address: Address,
+        block_number: BlockNumber,
+        page_size: usize,
+    ) -> RpcResult<TransactionsWithReceiptsResponse>;
+    #[method(name = "getTransactionBySenderAndNonce")]
+    async fn get_transaction_by_sender_and_nonce(
+        &self,
+        sender: Address,
+        nonce: u64,
+    ) -> RpcResult<Option<H256>>;
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockResponse {
+    pub number: U64,
+    pub hash: H256,
+    pub parent_hash: H256,
+    pub nonce: H64,
+    pub sha3_uncles: H256,
+    pub logs_bloom: Option<Bloom>,
+    pub transactions_root: H256,
+    pub state_root: H256,
+    pub receipts_root: H256,
+    pub miner: Address,
+    pub difficulty: U256,
+    pub total_difficulty: Option<U256>,
+    pub extra_data: String,
+    pub gas_limit: U64,
+    pub gas_used: U64,
+    pub timestamp: U64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_fee_per_gas: Option<U256>,
+    pub mix_hash: H256,
+    pub uncles: Vec<H256>,
+    pub transaction_count: U64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssuanceResponse {
+    pub block_reward: U256,
+    pub uncle_reward: U256,
+    pub issuance: U256,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockDetailsResponse {
+    pub block: BlockResponse,
+    pub issuance: IssuanceResponse,
+    pub total_fees: U256,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogResponse {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: String,
+    pub block_number: U64,
+    pub block_hash: H256,
+    pub transaction_hash: H256,
+    pub transaction_index: U64,
+    pub log_index: U64,
+    pub removed: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogsPageResponse {
+    pub logs: Vec<LogResponse>,
+    /// Where the next page starts, `None` on the last page.
+    pub cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T: PartialEq> OneOrMany<T> {
+    /// Empty list matches anything.
+    fn matches(&self, value: &T) -> bool {
+        match self {
+            Self::One(v) => v == value,
+            Self::Many(values) => values.is_empty() || values.contains(value),
+        }
+    }
+}
+
+/// Block parameter: a number or one of the `earliest`, `latest`, `safe` and `finalized` tags.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockNumberOrTag {
+    Number(BlockNumber),
+    Earliest,
+    Latest,
+    Safe,
+    Finalized,
+}
+
+impl<'de> Deserialize<'de> for BlockNumberOrTag {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Number(u64),
+            String(String),
+        }
+
+        Ok(match Raw::deserialize(deserializer)? {
+            Raw::Number(number) => Self::Number(BlockNumber(number)),
+            Raw::String(s) => match s.as_str() {
+                "earliest" => Self::Earliest,
+                "latest" => Self::Latest,
+                "safe" => Self::Safe,
+                "finalized" => Self::Finalized,
+                _ => Self::Number(BlockNumber(
+                    s.strip_prefix("0x")
+                        .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+                        .ok_or_else(|| {
+                            serde::de::Error::custom(format!("invalid block number {}", s))
+                        })?,
+                )),
+            },
+        })
+    }
+}
+
+impl BlockNumberOrTag {
+    /// Latest is the last block passed through the whole pipeline, safe and finalized are taken
+    /// from the chain head.
+    fn resolve<K: TransactionKind, E: EnvironmentKind>(
+        self,
+        txn: &MdbxTransaction<'_, K, E>,
+    ) -> anyhow::Result<BlockNumber> {
+        Ok(match self {
+            Self::Number(number) => number,
+            Self::Earliest => BlockNumber(0),
+            Self::Latest => FINISH.get_progress(txn)?.unwrap_or(BlockNumber(0)),
+            Self::Safe | Self::Finalized => {
+                let head = accessors::chain::chain_head::read(txn)?.unwrap_or_default();
+                let (name, block) = if self == Self::Safe {
+                    ("safe", head.safe)
+                } else {
+                    ("finalized", head.finalized)
+                };
+
+                block.ok_or_else(|| format_err!("no {} block", name))?.0
+            }
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFilter {
+    pub from_block: Option<BlockNumberOrTag>,
+    pub to_block: Option<BlockNumberOrTag>,
+    /// Replaces the block range.
+    pub block_hash: Option<H256>,
+    pub address: Option<OneOrMany<Address>>,
+    /// Topics by position, `None` matches any topic.
+    #[serde(default)]
+    pub topics: Vec<Option<OneOrMany<H256>>>,
+}
+
+impl LogFilter {
+    /// Blocks to search, from the latest one by default.
+    fn block_range<K: TransactionKind, E: EnvironmentKind>(
+        &self,
+        txn: &MdbxTransaction<'_, K, E>,
+    ) -> anyhow::Result<RangeInclusive<BlockNumber>> {
+        if let Some(hash) = self.block_hash {
+            let number = txn
+                .get(tables::HeaderNumber, hash)?
+                .ok_or_else(|| format_err!("block {:?} not found", hash))?;
+            return Ok(number..=number);
+        }
+
+        let latest = FINISH.get_progress(txn)?.unwrap_or(BlockNumber(0));
+        let resolve = |block: Option<BlockNumberOrTag>| {
+            block.map(|block| block.resolve(txn)).unwrap_or(Ok(latest))
+        };
+        Ok(resolve(self.from_block)?..=resolve(self.to_block)?.min(latest))
+    }
+
+    fn matches(&self, log: &Log) -> bool {
+        self.address
+            .as_ref()
+            .map(|address| address.matches(&log.address))
+            .unwrap_or(true)
+            && self.topics.iter().enumerate().all(|(position, topics)| {
+                topics
+                    .as_ref()
+                    .map(|topics| {
+                        log.topics
+                            .get(position)
+                            .map(|topic| topics.matches(topic))
+                            .unwrap_or(false)
+                    })
+                    .unwrap_or(true)
+            })
+    }
+}
+
+/// Bounds on the work done by one `eth_getLogs` call or `martinez_getLogs` page.
+#[derive(Clone, Copy, Debug)]
+pub struct LogLimits {
+    pub max_blocks: u64,
+    pub max_results: usize,
+    pub timeout: Duration,
+}
+
+/// Position of a log in the `Log` table, passed to clients as an opaque string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct LogCursor {
+    block_number: BlockNumber,
+    tx_index: TxIndex,
+    /// Index among the logs of the transaction.
+    log_index: usize,
+}
+
+impl LogCursor {
+    fn block_start(block_number: BlockNumber) -> Self {
+        Self {
+            block_number,
+            tx_index: TxIndex(0),
+            log_index: 0,
+        }
+    }
+
+    fn encode(&self) -> String {
+        format!("{}-{}-{}", self.block_number, self.tx_index, self.log_index)
+    }
+
+    fn decode(cursor: &str) -> anyhow::Result<Self> {
+        let mut parts = cursor.splitn(3, '-');
+        let mut next = || {
+            parts
+                .next()
+                .ok_or_else(|| format_err!("malformed log cursor {}", cursor))
+        };
+        Ok(Self {
+            block_number: next()?.parse()?,
+            tx_index: next()?.parse()?,
+            log_index: next()?.parse()?,
+        })
+    }
+}
+
+struct LogsPage {
+    logs: Vec<LogResponse>,
+    next: Option<LogCursor>,
+}
+
+/// Matching logs starting at `start`, until the end of the filtered blocks or one of the
+/// limits is hit.
+fn read_logs<K: TransactionKind, E: EnvironmentKind>(
+    txn: &MdbxTransaction<'_, K, E>,
+    filter: &LogFilter,
+    start: Option<LogCursor>,
+    limits: LogLimits,
+) -> anyhow::Result<LogsPage> {
+    let deadline = Instant::now() + limits.timeout;
+    let range = filter.block_range(txn)?;
+    if range.is_empty() {
+        return Ok(LogsPage {
+            logs: vec![],
+            next: None,
+        });
+    }
+    let start = start.unwrap_or_else(|| LogCursor::block_start(*range.start()));
+    ensure!(
+        range.contains(&start.block_number),
+        "log cursor at block #{} is outside of the filtered blocks",
+        start.block_number
+    );
+
+    let pruned = EXECUTION.get_prune_marks(txn)?;
+    ensure!(
+        pruned.is_empty() || start.block_number >= pruned.to || *range.end() < pruned.from,
+        "logs of blocks #{}..#{} are pruned",
+        pruned.from,
+        pruned.to
+    );
+
+    let scan_to = (*range.end()).min(start.block_number + (limits.max_blocks.max(1) - 1));
+    let next_block = (scan_to < *range.end()).then(|| LogCursor::block_start(scan_to + 1));
+
+    let mut logs = vec![];
+    // Hash and transaction hashes of the block of the last matching log.
+    let mut block: Option<(BlockNumber, H256, Vec<H256>)> = None;
+    let mut current_block = start.block_number;
+    let mut log_index_in_block = 0_u64;
+    let walker = txn
+        .cursor(tables::Log)?
+        .walk(Some((start.block_number, TxIndex(0))));
+    for res in walker {
+        let ((block_number, tx_index), tx_logs) = res?;
+        if block_number > scan_to {
+            break;
+        }
+        if block_number != current_block {
+            if Instant::now() > deadline {
+                return Ok(LogsPage {
+                    logs,
+                    next: Some(LogCursor::block_start(block_number)),
+                });
+            }
+            current_block = block_number;
+            log_index_in_block = 0;
+        }
+
+        for (log_index, log) in tx_logs.iter().enumerate() {
+            let position = LogCursor {
+                block_number,
+                tx_index,
+                log_index,
+            };
+            let index = log_index_in_block;
+            log_index_in_block += 1;
+            if position < start || !filter.matches(log) {
+                continue;
+            }
+            if logs.len() >= limits.max_results {
+                return Ok(LogsPage {
+                    logs,
+                    next: Some(position),
+                });
+            }
+
+            if block.as_ref().map(|(number, ..)| *number) != Some(block_number) {
+                let hash = txn
+                    .get(tables::CanonicalHeader, block_number)?
+                    .ok_or_else(|| format_err!("no canonical hash for block {}", block_number))?;
+                let body =
+                    accessors::chain::block_body::read_without_senders(txn, hash, block_number)?
+                        .ok_or_else(|| {
+                            format_err!("block body {}/{:?} not found", block_number, hash)
+                        })?;
+                block = Some((
+                    block_number,
+                    hash,
+                    body.transactions.iter().map(|tx| tx.hash()).collect(),
+                ));
+            }
+            let (_, block_hash, tx_hashes) = block.as_ref().unwrap();
+
+            logs.push(LogResponse {
+                address: log.address,
+                topics: log.topics.clone(),
+                data: format!("0x{}", hex::encode(&log.data)),
+                block_number: block_number.0.into(),
+                block_hash: *block_hash,
+                transaction_hash: *tx_hashes.get(tx_index.0 as usize).ok_or_else(|| {
+                    format_err!("no transaction {} in block {}", tx_index, block_number)
+                })?,
+                transaction_index: tx_index.0.into(),
+                log_index: index.into(),
+                removed: false,
+            });
+        }
+    }
+
+    Ok(LogsPage {
+        logs,
+        next: next_block,
+    })
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiptResponse {
+    pub transaction_hash: H256,
+    pub transaction_index: U64,
+    pub block_hash: H256,
+    pub block_number: U64,
+    pub from: Address,
+    pub to: Option<Address>,
+    pub cumulative_gas_used: U64,
+    pub gas_used: U64,
+    pub effective_gas_price: U256,
+    pub contract_address: Option<Address>,
+    pub logs: Vec<LogResponse>,
+    pub logs_bloom: Bloom,
+    #[serde(rename = "type")]
+    pub tx_type: U64,
+    pub status: U64,
+    /// Block timestamp, an Otterscan extension.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<U64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionsWithReceiptsResponse {
+    pub txs: Vec<TxResponse>,
+    pub receipts: Vec<ReceiptResponse>,
+    pub first_page: bool,
+    pub last_page: bool,
+}
+
+/// Historical state is read from the changesets after the block, so it is gone if any of them
+/// were pruned.
+fn ensure_state_available<K: TransactionKind, E: EnvironmentKind>(
+    txn: &MdbxTransaction<'_, K, E>,
+    block_number: BlockNumber,
+) -> anyhow::Result<()> {
+    for stage in [ACCOUNT_HISTORY_INDEX, STORAGE_HISTORY_INDEX] {
+        let marks = stage.get_prune_marks(txn)?;
+        ensure!(
+            marks.is_empty() || block_number.0 + 1 >= marks.to.0,
+            "state of block #{} is pruned, available from #{}",
+            block_number,
+            marks.to.0 - 1
+        );
+    }
+
+    Ok(())
+}
+
+fn read_chain_spec<K: TransactionKind, E: EnvironmentKind>(
+    txn: &MdbxTransaction<'_, K, E>,
+) -> anyhow::Result<ChainSpec> {
+    let genesis_hash = txn
+        .get(tables::CanonicalHeader, BlockNumber(0))?
+        .ok_or_else(|| format_err!("genesis block absent"))?;
+    txn.get(tables::Config, genesis_hash)?
+        .ok_or_else(|| format_err!("no chain config for genesis block {:?}", genesis_hash))
+}
+
+/// Canonical block with signed transactions and their senders.
+struct StoredBlock {
+    hash: H256,
+    header: BlockHeader,
+    transactions: Vec<MessageWithSignature>,
+    senders: Vec<Address>,
+    ommers: Vec<BlockHeader>,
+}
+
+impl StoredBlock {
+    fn read<K: TransactionKind, E: EnvironmentKind>(
+        txn: &MdbxTransaction<'_, K, E>,
+        number: BlockNumber,
+    ) -> anyhow::Result<Option<Self>> {
+        let hash = match txn.get(tables::CanonicalHeader, number)? {
+            Some(hash) => hash,
+            None => return Ok(None),
+        };
+        let header = txn
+            .get(tables::Header, (number, hash))?
+            .ok_or_else(|| format_err!("header {}/{:?} not found", number, hash))?;
+        let body = accessors::chain::block_body::read_without_senders(txn, hash, number)?
+            .ok_or_else(|| format_err!("block body {}/{:?} not found", number, hash))?;
+        let senders = accessors::chain::tx_sender::read(txn, hash, number)?;
+
+        Ok(Some(Self {
+            hash,
+            header,
+            transactions: body.transactions,
+            senders,
+            ommers: body.ommers,
+        }))
+    }
+
+    /// Re-executes the block on top of its parent state to get the receipts.
+    fn execute<K: TransactionKind, E: EnvironmentKind>(
+        &self,
+        txn: &MdbxTransaction<'_, K, E>,
+        chain_spec: &ChainSpec,
+        tracer: Option<&mut dyn Tracer>,
+    ) -> anyhow::Result<Vec<Receipt>> {
+        if self.transactions.is_empty() {
+            return Ok(vec![]);
+        }
+        ensure_state_available(txn, BlockNumber(self.header.number.0 - 1))?;
+
+        let body = BlockBodyWithSenders {
+            transactions: self
+                .transactions
+                .iter()
+                .zip(&self.senders)
+                .map(|(tx, &sender)| MessageWithSender {
+                    message: tx.message.clone(),
+                    sender,
+                })
+                .collect(),
+            ommers: self.ommers.clone(),
+        };
+        let header = PartialHeader::from(self.header.clone());
+        let block_spec = chain_spec.collect_block_spec(header.number, header.timestamp);
+        let mut buffer = Buffer::new(txn, BlockNumber(0), Some(BlockNumber(header.number.0 - 1)));
+        let mut analysis_cache = AnalysisCache::default();
+        let mut block_hashes = BlockHashes::default();
+        let mut engine = engine_factory(chain_spec)?;
+
+        let mut processor = ExecutionProcessor::new(
+            &mut buffer,
+            tracer,
+            &mut analysis_cache,
+            &mut *engine,
+            &header,
+            &body,
+            &block_spec,
+        );
+        processor.set_block_hashes(&mut block_hashes);
+        processor.execute_block_no_post_validation()
+    }
+
+    /// Stored receipts, or receipts from re-execution for blocks executed before they were
+    /// persisted.
+    fn receipts<K: TransactionKind, E: EnvironmentKind>(
+        &self,
+        txn: &MdbxTransaction<'_, K, E>,
+        chain_spec: &ChainSpec,
+    ) -> anyhow::Result<Vec<Receipt>> {
+        if let Some(receipts) =
+            accessors::chain::receipts::read(txn, self.hash, self.header.number)?
+        {
+            return Ok(receipts);
+        }
+
+        self.execute(txn, chain_spec, None)
+    }
+
+    fn effective_gas_price(&self, tx: &MessageWithSignature) -> U256 {
+        match self.header.base_fee_per_gas {
+            Some(base_fee_per_gas) => tx.effective_gas_price(base_fee_per_gas),
+            None => tx.max_fee_per_gas(),
+        }
+    }
+
+    fn tx_response(&self, index: usize) -> TxResponse {
+        TxResponse {
+            block_hash: Some(self.hash),
+            block_number: Some(self.header.number.0.into()),
+            transaction_index: Some(index.into()),
+            gas_price: Some(self.effective_gas_price(&self.transactions[index])),
+            ..TxResponse::new(self.senders[index], &self.transactions[index])
+        }
+    }
+
+    fn receipt_responses(&self, receipts: &[Receipt]) -> Vec<ReceiptResponse> {
+        let mut log_index = 0_u64;
+        let mut last_cumulative_gas_used = 0;
+        receipts
+            .iter()
+            .enumerate()
+            .map(|(index, receipt)| {
+                let tx = &self.transactions[index];
+                let from = self.senders[index];
+                let transaction_hash = tx.hash();
+                let (to, contract_address) = match tx.action() {
+                    TransactionAction::Call(to) => (Some(to), None),
+                    TransactionAction::Create => (None, Some(create_address(from, tx.nonce()))),
+                };
+                let gas_used = receipt.cumulative_gas_used - last_cumulative_gas_used;
+                last_cumulative_gas_used = receipt.cumulative_gas_used;
+
+                ReceiptResponse {
+                    transaction_hash,
+                    transaction_index: index.into(),
+                    block_hash: self.hash,
+                    block_number: self.header.number.0.into(),
+                    from,
+                    to,
+                    cumulative_gas_used: receipt.cumulative_gas_used.into(),
+                    gas_used: gas_used.into(),
+                    effective_gas_price: self.effective_gas_price(tx),
+                    contract_address,
+                    logs: receipt
+                        .logs
+                        .iter()
+                        .map(|log| {
+                            let log = LogResponse {
+                                address: log.address,
+                                topics: log.topics.clone(),
+                                data: format!("0x{}", hex::encode(&log.data)),
+                                block_number: self.header.number.0.into(),
+                                block_hash: self.hash,
+                                transaction_hash,
+                                transaction_index: index.into(),
+                                log_index: log_index.into(),
+                                removed: false,
+                            };
+                            log_index += 1;
+                            log
+                        })
+                        .collect(),
+                    logs_bloom: receipt.bloom,
+                    tx_type: (receipt.tx_type as u64).into(),
+                    status: (receipt.success as u64).into(),
+                    timestamp: None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Addresses touched by calls of each transaction, top level calls start a new transaction.
+#[derive(Default)]
+struct TxCallTracer {
+    txs: Vec<HashSet<Address>>,
+}
+
+impl Tracer for TxCallTracer {
+    fn capture_start(
+        &mut self,
+        depth: u16,
+        from: Address,
+        to: Address,
+        _: MessageKind,
+        _: bytes::Bytes,
+        _: u64,
+        _: U256,
+    ) {
+        if depth == 0 {
+            self.txs.push(HashSet::new());
+        }
+        if let Some(addresses) = self.txs.last_mut() {
+            addresses.extend([from, to]);
+        }
+    }
+
+    fn capture_self_destruct(&mut self, caller: Address, beneficiary: Address) {
+        if let Some(addresses) = self.txs.last_mut() {
+            addresses.extend([caller, beneficiary]);
+        }
+    }
 }
 
 pub struct EthApiServerImpl<E>
@@ -30,6 +1151,7 @@ where
     E: EnvironmentKind,
 {
     db: Arc<MdbxEnvironment<E>>,
+    log_limits: LogLimits,
 }
 
 #[async_trait]
@@ -43,42 +1165,887 @@ where
             .unwrap_or(BlockNumber(0)))
     }
 
-    async fn get_balance(&self, address: Address, block_number: BlockNumber) -> RpcResult<U256> {
+    async fn get_balance(
+        &self,
+        address: Address,
+        block_number: BlockNumberOrTag,
+    ) -> RpcResult<U256> {
+        let txn = self.db.begin()?;
+        let block_number = block_number.resolve(&txn)?;
+        ensure_state_available(&txn, block_number)?;
+
         Ok(
-            martinez::accessors::state::account::read(&self.db.begin()?, address, Some(block_number))?
+            martinez::accessors::state::account::read(&txn, address, Some(block_number))?
                 .map(|acc| acc.balance)
                 .unwrap_or(U256::ZERO),
         )
     }
+
+    async fn get_proof(
+        &self,
+        address: Address,
+        storage_keys: Vec<H256>,
+        block_number: BlockNumberOrTag,
+    ) -> RpcResult<EIP1186ProofResponse> {
+        let txn = self.db.begin()?;
+        let block_number = block_number.resolve(&txn)?;
+
+        // Hashed state and intermediate hashes are only kept for the latest block.
+        let latest = HASH_STATE.get_progress(&txn)?.unwrap_or(BlockNumber(0));
+        if block_number != latest {
+            return Err(format_err!(
+                "proofs are only available for the latest block #{}, requested #{}",
+                latest,
+                block_number
+            )
+            .into());
+        }
+
+        Ok(prove_account(&txn, address, &storage_keys)?.into())
+    }
+
+    async fn syncing(&self) -> RpcResult<SyncingResponse> {
+        let txn = self.db.begin()?;
+
+        let highest_block = HEADERS.get_progress(&txn)?.unwrap_or(BlockNumber(0));
+        let current_block = FINISH.get_progress(&txn)?.unwrap_or(BlockNumber(0));
+        if current_block.0 > 0 && current_block >= highest_block {
+            return Ok(SyncingResponse::NotSyncing(false));
+        }
+
+        let mut stages = Vec::with_capacity(SYNC_STAGES.len());
+        for stage in SYNC_STAGES {
+            if let Some(block_number) = stage.get_progress(&txn)? {
+                stages.push(StageProgressResponse {
+                    stage_name: stage.to_string(),
+                    block_number: block_number.0.into(),
+                });
+            }
+        }
+
+        Ok(SyncingResponse::Syncing(SyncStatusResponse {
+            starting_block: 0.into(),
+            current_block: current_block.0.into(),
+            highest_block: highest_block.0.into(),
+            stages,
+        }))
+    }
+
+    async fn get_block_receipts(
+        &self,
+        block_number: BlockNumberOrTag,
+    ) -> RpcResult<Option<Vec<ReceiptResponse>>> {
+        let txn = self.db.begin()?;
+        let block_number = block_number.resolve(&txn)?;
+
+        let block = match StoredBlock::read(&txn, block_number)? {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+        let receipts = block.receipts(&txn, &read_chain_spec(&txn)?)?;
+
+        Ok(Some(block.receipt_responses(&receipts)))
+    }
+
+    async fn get_transaction_receipt(&self, hash: H256) -> RpcResult<Option<ReceiptResponse>> {
+        let txn = self.db.begin()?;
+
+        let block_number = match accessors::chain::tl::read(&txn, hash)? {
+            Some(block_number) => block_number,
+            None => return Ok(None),
+        };
+        let block = match StoredBlock::read(&txn, block_number)? {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+        let receipts = block.receipts(&txn, &read_chain_spec(&txn)?)?;
+
+        Ok(block
+            .receipt_responses(&receipts)
+            .into_iter()
+            .find(|receipt| receipt.transaction_hash == hash))
+    }
+
+    async fn get_logs(&self, filter: LogFilter) -> RpcResult<Vec<LogResponse>> {
+        let txn = self.db.begin()?;
+
+        let range = filter.block_range(&txn)?;
+        let blocks = (range.end().0 + 1).saturating_sub(range.start().0);
+        if blocks > self.log_limits.max_blocks {
+            return Err(format_err!(
+                "query spans {} blocks, over the limit of {}; page through it with martinez_getLogs",
+                blocks,
+                self.log_limits.max_blocks
+            )
+            .into());
+        }
+
+        let page = read_logs(&txn, &filter, None, self.log_limits)?;
+        if page.next.is_some() {
+            return Err(format_err!(
+                "query exceeds the limit of {} results or {:?}; page through it with martinez_getLogs",
+                self.log_limits.max_results,
+                self.log_limits.timeout
+            )
+            .into());
+        }
+
+        Ok(page.logs)
+    }
+}
+
+pub struct Web3ApiServerImpl;
+
+#[async_trait]
+impl Web3ApiServer for Web3ApiServerImpl {
+    async fn client_version(&self) -> RpcResult<String> {
+        Ok(version_string())
+    }
+}
+
+pub struct NetApiServerImpl<E>
+where
+    E: EnvironmentKind,
+{
+    db: Arc<MdbxEnvironment<E>>,
+    sentry: Option<Arc<Mutex<SentryClientImpl>>>,
+}
+
+#[async_trait]
+impl<E> NetApiServer for NetApiServerImpl<E>
+where
+    E: EnvironmentKind,
+{
+    async fn version(&self) -> RpcResult<String> {
+        Ok(read_chain_spec(&self.db.begin()?)?
+            .params
+            .network_id
+            .0
+            .to_string())
+    }
+
+    async fn peer_count(&self) -> RpcResult<U64> {
+        let sentry = self
+            .sentry
+            .as_ref()
+            .ok_or_else(|| format_err!("peer count is not available without sentry"))?;
+
+        Ok(sentry.lock().await.peers().await?.len().into())
+    }
+}
+
+pub struct AdminApiServerImpl {
+    sentry: Option<Arc<Mutex<SentryClientImpl>>>,
+    node_key_dir: Option<PathBuf>,
+}
+
+impl AdminApiServerImpl {
+    fn sentry(&self) -> anyhow::Result<&Arc<Mutex<SentryClientImpl>>> {
+        self.sentry
+            .as_ref()
+            .ok_or_else(|| format_err!("peers are not available without sentry"))
+    }
+}
+
+#[async_trait]
+impl AdminApiServer for AdminApiServerImpl {
+    async fn peers(&self) -> RpcResult<Vec<PeerInfoResponse>> {
+        Ok(self
+            .sentry()?
+            .lock()
+            .await
+            .peers()
+            .await?
+            .into_iter()
+            .map(From::from)
+            .collect())
+    }
+
+    async fn node_info(&self) -> RpcResult<NodeInfoResponse> {
+        if let Some(sentry) = &self.sentry {
+            return Ok(sentry.lock().await.node_info().await?.into());
+        }
+
+        let node = self
+            .node_key_dir
+            .as_deref()
+            .map(LocalNode::load)
+            .transpose()?
+            .flatten()
+            .ok_or_else(|| format_err!("node identity is not available"))?;
+
+        Ok(NodeInfo::from(node).into())
+    }
+
+    async fn add_peer(&self, enode: String) -> RpcResult<bool> {
+        Ok(self.sentry()?.lock().await.add_peer(enode).await?)
+    }
+
+    async fn add_trusted_peer(&self, enode: String) -> RpcResult<bool> {
+        self.add_peer(enode).await
+    }
+}
+
+pub struct TxPoolApiServerImpl {
+    txpool: grpc_txpool::txpool_client::TxpoolClient<tonic::transport::Channel>,
+    content_limit: usize,
+}
+
+impl TxPoolApiServerImpl {
+    async fn content_with<T>(
+        &self,
+        f: impl Fn(Address, &MessageWithSignature) -> T,
+    ) -> anyhow::Result<TxPoolContentResponse<T>> {
+        let reply = self
+            .txpool
+            .clone()
+            .all(grpc_txpool::AllRequest {})
+            .await?
+            .into_inner();
+        ensure!(
+            reply.txs.len() <= self.content_limit,
+            "transaction pool holds {} transactions, above response limit of {}",
+            reply.txs.len(),
+            self.content_limit
+        );
+
+        let mut content = TxPoolContentResponse {
+            pending: BTreeMap::new(),
+            queued: BTreeMap::new(),
+        };
+        for pool_tx in reply.txs {
+            let sender = Address::from(
+                pool_tx
+                    .sender
+                    .ok_or_else(|| format_err!("no sender for pool transaction"))?,
+            );
+            let tx = MessageWithSignature::trie_decode(&pool_tx.rlp_tx)?;
+
+            // Base fee subpool is not executable yet, like queued transactions.
+            let subpool = if pool_tx.txn_type == grpc_txpool::all_reply::TxnType::Pending as i32 {
+                &mut content.pending
+            } else {
+                &mut content.queued
+            };
+            subpool
+                .entry(sender)
+                .or_default()
+                .insert(tx.nonce(), (f)(sender, &tx));
+        }
+
+        Ok(content)
+    }
+}
+
+#[async_trait]
+impl TxPoolApiServer for TxPoolApiServerImpl {
+    async fn status(&self) -> RpcResult<TxPoolStatusResponse> {
+        let reply = self
+            .txpool
+            .clone()
+            .status(grpc_txpool::StatusRequest {})
+            .await
+            .map_err(anyhow::Error::from)?
+            .into_inner();
+
+        Ok(TxPoolStatusResponse {
+            pending: reply.pending_count.into(),
+            queued: (reply.queued_count + reply.base_fee_count).into(),
+        })
+    }
+
+    async fn content(&self) -> RpcResult<TxPoolContentResponse<TxResponse>> {
+        Ok(self.content_with(TxResponse::new).await?)
+    }
+
+    async fn inspect(&self) -> RpcResult<TxPoolContentResponse<String>> {
+        Ok(self.content_with(|_, tx| inspect_tx(tx)).await?)
+    }
+}
+
+async fn call_ipc(methods: &Methods, request: &serde_json::Value) -> anyhow::Result<String> {
+    let (response, _) = methods.raw_json_request(&request.to_string()).await?;
+    Ok(response)
+}
+
+/// Splits a byte stream into top level JSON objects and arrays, looking at every byte once.
+#[derive(Default)]
+struct JsonFramer {
+    buf: Vec<u8>,
+    /// Start of the value being scanned, bytes before it are handed out already.
+    start: usize,
+    /// Bytes of `buf` scanned so far.
+    scanned: usize,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl JsonFramer {
+    fn extend(&mut self, data: &[u8]) {
+        self.buf.drain(..self.start);
+        self.scanned -= self.start;
+        self.start = 0;
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Length of the incomplete value at the end of the buffer.
+    fn pending(&self) -> usize {
+        self.buf.len() - self.start
+    }
+
+    /// Next complete value, `Err` with the offending byte if it cannot start one.
+    fn next_value(&mut self) -> Result<Option<&[u8]>, u8> {
+        while self.scanned < self.buf.len() {
+            let byte = self.buf[self.scanned];
+            self.scanned += 1;
+
+            if self.depth == 0 {
+                match byte {
+                    b'{' | b'[' => {
+                        self.start = self.scanned - 1;
+                        self.depth = 1;
+                    }
+                    b' ' | b'\t' | b'\r' | b'\n' => self.start = self.scanned,
+                    other => return Err(other),
+                }
+            } else if self.in_string {
+                match byte {
+                    _ if self.escaped => self.escaped = false,
+                    b'\\' => self.escaped = true,
+                    b'"' => self.in_string = false,
+                    _ => {}
+                }
+            } else {
+                match byte {
+                    b'"' => self.in_string = true,
+                    b'{' | b'[' => self.depth += 1,
+                    b'}' | b']' => {
+                        self.depth -= 1;
+                        if self.depth == 0 {
+                            let value = self.start..self.scanned;
+                            self.start = self.scanned;
+                            return Ok(Some(&self.buf[value]));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+async fn reply_parse_error(
+    stream: &mut UnixStream,
+    e: impl std::fmt::Display,
+) -> anyhow::Result<()> {
+    let response = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": null,
+        "error": { "code": -32700, "message": format!("Parse error: {}", e) },
+    });
+    stream.write_all(response.to_string().as_bytes()).await?;
+    stream.write_all(b"\n").await?;
+    Ok(())
+}
+
+/// Serves requests sent as a stream of JSON values, replying with one line per request or batch.
+/// Invalid JSON is answered with a parse error, after which the connection is closed as the
+/// stream cannot be resynchronized.
+async fn serve_ipc_connection(
+    mut stream: UnixStream,
+    methods: Methods,
+    max_request_size: usize,
+) -> anyhow::Result<()> {
+    let mut framer = JsonFramer::default();
+    let mut chunk = [0; 4096];
+    loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        framer.extend(&chunk[..n]);
+
+        let mut requests = vec![];
+        loop {
+            match framer.next_value() {
+                Ok(Some(value)) => match serde_json::from_slice::<serde_json::Value>(value) {
+                    Ok(request) => requests.push(request),
+                    Err(e) => return reply_parse_error(&mut stream, e).await,
+                },
+                Ok(None) => break,
+                Err(byte) => {
+                    let e = format_err!("unexpected {:?}", char::from(byte));
+                    return reply_parse_error(&mut stream, e).await;
+                }
+            }
+        }
+        ensure!(
+            framer.pending() <= max_request_size,
+            "request exceeds {} bytes",
+            max_request_size
+        );
+
+        for request in requests {
+            let response = match request {
+                serde_json::Value::Array(batch) => {
+                    let mut responses = Vec::with_capacity(batch.len());
+                    for request in &batch {
+                        responses.push(call_ipc(&methods, request).await?);
+                    }
+                    format!("[{}]", responses.join(","))
+                }
+                request => call_ipc(&methods, &request).await?,
+            };
+            stream.write_all(response.as_bytes()).await?;
+            stream.write_all(b"\n").await?;
+        }
+    }
+}
+
+async fn serve_ipc(listener: UnixListener, methods: Methods, max_request_size: usize) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let methods = methods.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve_ipc_connection(stream, methods, max_request_size).await {
+                        debug!("IPC connection closed: {}", e);
+                    }
+                });
+            }
+            Err(e) => warn!("Failed to accept IPC connection: {}", e),
+        }
+    }
+}
+
+pub struct MartinezApiServerImpl<E>
+where
+    E: EnvironmentKind,
+{
+    db: Arc<MdbxEnvironment<E>>,
+    log_limits: LogLimits,
+}
+
+#[async_trait]
+impl<E> MartinezApiServer for MartinezApiServerImpl<E>
+where
+    E: EnvironmentKind,
+{
+    async fn pruned_blocks(&self) -> RpcResult<Vec<PrunedBlocksResponse>> {
+        let txn = self.db.begin()?;
+
+        let mut pruned = vec![];
+        for stage in SYNC_STAGES {
+            let marks = stage.get_prune_marks(&txn)?;
+            if !marks.is_empty() {
+                pruned.push(PrunedBlocksResponse {
+                    stage_name: stage.to_string(),
+                    from: marks.from.0.into(),
+                    to: marks.to.0.into(),
+                });
+            }
+        }
+
+        Ok(pruned)
+    }
+
+    async fn get_logs(
+        &self,
+        filter: LogFilter,
+        cursor: Option<String>,
+    ) -> RpcResult<LogsPageResponse> {
+        let txn = self.db.begin()?;
+
+        let start = cursor.as_deref().map(LogCursor::decode).transpose()?;
+        let page = read_logs(&txn, &filter, start, self.log_limits)?;
+
+        Ok(LogsPageResponse {
+            logs: page.logs,
+            cursor: page.next.map(|cursor| cursor.encode()),
+        })
+    }
+}
+
+pub struct OtterscanApiServerImpl<E>
+where
+    E: EnvironmentKind,
+{
+    db: Arc<MdbxEnvironment<E>>,
+}
+
+impl<E> OtterscanApiServerImpl<E>
+where
+    E: EnvironmentKind,
+{
+    /// Pages hold whole blocks, so may exceed `page_size`. Transactions are always returned
+    /// newest first.
+    fn search_transactions(
+        &self,
+        address: Address,
+        block_number: BlockNumber,
+        page_size: usize,
+        backwards: bool,
+    ) -> anyhow::Result<TransactionsWithReceiptsResponse> {
+        let txn = self.db.begin()?;
+        let chain_spec = read_chain_spec(&txn)?;
+        let latest = FINISH.get_progress(&txn)?.unwrap_or(BlockNumber(0));
+
+        let blocks = accessors::history::address_activity(&txn, address, BlockNumber(0)..=latest)?
+            .into_iter()
+            .map(|block| block.0);
+        let candidates: Box<dyn Iterator<Item = u64>> = if backwards {
+            // Zero stands for the search start from the latest block.
+            let before = if block_number.0 == 0 {
+                u64::MAX
+            } else {
+                block_number.0
+            };
+            Box::new(blocks.rev().filter(move |&block| block < before))
+        } else {
+            Box::new(blocks.filter(move |&block| block > block_number.0))
+        };
+
+        let mut txs = vec![];
+        let mut receipts = vec![];
+        let mut exhausted = true;
+        for block in candidates {
+            if txs.len() >= page_size {
+                exhausted = false;
+                break;
+            }
+
+            let block = StoredBlock::read(&txn, BlockNumber(block))?
+                .ok_or_else(|| format_err!("indexed block {} not found", block))?;
+            let mut tracer = TxCallTracer::default();
+            let block_receipts = block.execute(&txn, &chain_spec, Some(&mut tracer))?;
+            let block_receipts = block.receipt_responses(&block_receipts);
+            let timestamp = Some(block.header.timestamp.into());
+
+            let mut block_txs = vec![];
+            let mut matching_receipts = vec![];
+            for (index, receipt) in block_receipts.into_iter().enumerate() {
+                let touched = block.senders[index] == address
+                    || tracer
+                        .txs
+                        .get(index)
+                        .map(|addresses| addresses.contains(&address))
+                        .unwrap_or(false);
+                if touched {
+                    block_txs.push(block.tx_response(index));
+                    matching_receipts.push(ReceiptResponse {
+                        timestamp,
+                        ..receipt
+                    });
+                }
+            }
+            if backwards {
+                block_txs.reverse();
+                matching_receipts.reverse();
+            }
+            txs.extend(block_txs);
+            receipts.extend(matching_receipts);
+        }
+
+        if !backwards {
+            txs.reverse();
+            receipts.reverse();
+        }
+
+        Ok(TransactionsWithReceiptsResponse {
+            txs,
+            receipts,
+            first_page: if backwards {
+                block_number.0 == 0
+            } else {
+                exhausted
+            },
+            last_page: if backwards {
+                exhausted
+            } else {
+                block_number.0 == 0
+            },
+        })
+    }
+}
+
+#[async_trait]
+impl<E> OtterscanApiServer for OtterscanApiServerImpl<E>
+where
+    E: EnvironmentKind,
+{
+    async fn get_block_details(
+        &self,
+        block_number: BlockNumber,
+    ) -> RpcResult<Option<BlockDetailsResponse>> {
+        let txn = self.db.begin()?;
+        let chain_spec = read_chain_spec(&txn)?;
+
+        let block = match StoredBlock::read(&txn, block_number)? {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+
+        let header = PartialHeader::from(block.header.clone());
+        let revision = chain_spec
+            .collect_block_spec(header.number, header.timestamp)
+            .revision;
+        let mut block_reward = U256::ZERO;
+        let mut uncle_reward = U256::ZERO;
+        for change in engine_factory(&chain_spec)?.finalize(&header, &block.ommers, revision)? {
+            match change {
+                FinalizationChange::Reward { address, amount } => {
+                    if address == header.beneficiary {
+                        block_reward += amount;
+                    } else {
+                        uncle_reward += amount;
+                    }
+                }
+            }
+        }
+
+        let mut total_fees = U256::ZERO;
+        let mut last_cumulative_gas_used = 0;
+        for (tx, receipt) in block
+            .transactions
+            .iter()
+            .zip(block.receipts(&txn, &chain_spec)?)
+        {
+            total_fees += U256::from(receipt.cumulative_gas_used - last_cumulative_gas_used)
+                * block.effective_gas_price(tx);
+            last_cumulative_gas_used = receipt.cumulative_gas_used;
+        }
+
+        Ok(Some(BlockDetailsResponse {
+            block: BlockResponse {
+                number: block.header.number.0.into(),
+                hash: block.hash,
+                parent_hash: block.header.parent_hash,
+                nonce: block.header.nonce,
+                sha3_uncles: block.header.ommers_hash,
+                logs_bloom: None,
+                transactions_root: block.header.transactions_root,
+                state_root: block.header.state_root,
+                receipts_root: block.header.receipts_root,
+                miner: block.header.beneficiary,
+                difficulty: block.header.difficulty,
+                total_difficulty: accessors::chain::td::read(&txn, block.hash, block_number)?,
+                extra_data: format!("0x{}", hex::encode(&block.header.extra_data)),
+                gas_limit: block.header.gas_limit.into(),
+                gas_used: block.header.gas_used.into(),
+                timestamp: block.header.timestamp.into(),
+                base_fee_per_gas: block.header.base_fee_per_gas,
+                mix_hash: block.header.mix_hash,
+                uncles: block.ommers.iter().map(|ommer| ommer.hash()).collect(),
+                transaction_count: block.transactions.len().into(),
+            },
+            issuance: IssuanceResponse {
+                block_reward,
+                uncle_reward,
+                issuance: block_reward + uncle_reward,
+            },
+            total_fees,
+        }))
+    }
+
+    async fn search_transactions_before(
+        &self,
+        address: Address,
+        block_number: BlockNumber,
+        page_size: usize,
+    ) -> RpcResult<TransactionsWithReceiptsResponse> {
+        Ok(self.search_transactions(address, block_number, page_size, true)?)
+    }
+
+    async fn search_transactions_after(
+        &self,
+        address: Address,
+        block_number: BlockNumber,
+        page_size: usize,
+    ) -> RpcResult<TransactionsWithReceiptsResponse> {
+        Ok(self.search_transactions(address, block_number, page_size, false)?)
+    }
+
+    async fn get_transaction_by_sender_and_nonce(
+        &self,
+        sender: Address,
+        nonce: u64,
+    ) -> RpcResult<Option<H256>> {
+        let txn = self.db.begin()?;
+        let latest = FINISH.get_progress(&txn)?.unwrap_or(BlockNumber(0));
+
+        let nonce_at = |block_number: BlockNumber| -> anyhow::Result<u64> {
+            Ok(
+                accessors::state::account::read(&txn, sender, Some(block_number))?
+                    .map(|account| account.nonce)
+                    .unwrap_or(0),
+            )
+        };
+
+        if nonce_at(latest)? <= nonce {
+            return Ok(None);
+        }
+
+        // First block by the end of which the nonce has been used, looking no further back
+        // than the history kept.
+        let mut low = 0;
+        for stage in [ACCOUNT_HISTORY_INDEX, STORAGE_HISTORY_INDEX] {
+            let marks = stage.get_prune_marks(&txn)?;
+            if !marks.is_empty() {
+                low = low.max(marks.to.0 - 1);
+            }
+        }
+        if low > 0 && nonce_at(BlockNumber(low))? > nonce {
+            return Err(format_err!(
+                "nonce {} of {:?} was used before block #{}, whose history is pruned",
+                nonce,
+                sender,
+                low
+            )
+            .into());
+        }
+        let mut high = latest.0;
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if nonce_at(BlockNumber(mid))? > nonce {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+
+        let block = StoredBlock::read(&txn, BlockNumber(low))?
+            .ok_or_else(|| format_err!("block {} not found", low))?;
+        Ok(block
+            .transactions
+            .iter()
+            .zip(&block.senders)
+            .find(|(tx, &from)| from == sender && tx.nonce() == nonce)
+            .map(|(tx, _)| tx.hash()))
+    }
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let opt = Opt::parse();
+    let mut opt = Opt::parse();
 
-    let env_filter = if std::env::var(EnvFilter::DEFAULT_ENV)
-        .unwrap_or_default()
-        .is_empty()
-    {
-        EnvFilter::new("martinez=info,rpc=info")
+    init_tracing("martinez=info,rpc=info", opt.log_json);
+
+    opt.datadir = opt.datadir.chain_dir(&opt.chain_name);
+    opt.node_data_dir = opt
+        .node_data_dir
+        .map(|node_data_dir| node_data_dir.chain_dir(&opt.chain_name));
+    let _lock = opt.datadir.lock(AccessMode::Reader)?;
+    let db = Arc::new(martinez::kv::open_database_ro::<mdbx::NoWriteMap>(
+        &opt.datadir,
+        opt.cold_dir.as_deref(),
+    )?);
+
+    let sentry = if let Some(sentry_api_addr) = opt.sentry_api_addr {
+        Some(Arc::new(Mutex::new(
+            SentryClientImpl::new(sentry_api_addr).await?,
+        )))
     } else {
-        EnvFilter::from_default_env()
+        None
     };
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::fmt::layer().with_target(false))
-        .with(env_filter)
-        .init();
-
-    let db = Arc::new(
-        martinez::kv::mdbx::MdbxEnvironment::<mdbx::NoWriteMap>::open_ro(
-            mdbx::Environment::new(),
-            &opt.datadir,
-            martinez::kv::tables::CHAINDATA_TABLES.clone(),
-        )?,
-    );
 
-    let server = HttpServerBuilder::default().build(opt.listen_address)?;
-    let _server_handle = server.start(EthApiServerImpl { db }.into_rpc())?;
+    let log_limits = LogLimits {
+        max_blocks: opt.logs_max_blocks,
+        max_results: opt.logs_max_results,
+        timeout: Duration::from_secs(opt.logs_timeout),
+    };
+
+    let mut api = RpcModule::new(());
+    for namespace in &opt.http_api {
+        match namespace.as_str() {
+            "eth" => api.merge(
+                EthApiServerImpl {
+                    db: db.clone(),
+                    log_limits,
+                }
+                .into_rpc(),
+            )?,
+            "net" => api.merge(
+                NetApiServerImpl {
+                    db: db.clone(),
+                    sentry: sentry.clone(),
+                }
+                .into_rpc(),
+            )?,
+            "web3" => api.merge(Web3ApiServerImpl.into_rpc())?,
+            "ots" => api.merge(OtterscanApiServerImpl { db: db.clone() }.into_rpc())?,
+            "martinez" => api.merge(
+                MartinezApiServerImpl {
+                    db: db.clone(),
+                    log_limits,
+                }
+                .into_rpc(),
+            )?,
+            "admin" => {
+                let node_key_dir = opt.node_data_dir.as_ref().map(|dir| dir.node_key_dir());
+                if sentry.is_some() || node_key_dir.is_some() {
+                    api.merge(
+                        AdminApiServerImpl {
+                            sentry: sentry.clone(),
+                            node_key_dir,
+                        }
+                        .into_rpc(),
+                    )?;
+                }
+            }
+            "txpool" => {
+                if let Some(txpool_api_addr) = opt.txpool_api_addr.clone() {
+                    api.merge(
+                        TxPoolApiServerImpl {
+                            txpool: grpc_txpool::txpool_client::TxpoolClient::connect(
+                                txpool_api_addr,
+                            )
+                            .await?,
+                            content_limit: opt.txpool_content_limit,
+                        }
+                        .into_rpc(),
+                    )?;
+                }
+            }
+            other => bail!("unknown RPC namespace {}", other),
+        }
+    }
+
+    for method in &opt.http_disabled_methods {
+        ensure!(
+            api.remove_method(method).is_some(),
+            "cannot disable {}: no such method served",
+            method
+        );
+    }
+
+    let mut access_control = AccessControlBuilder::default();
+    if !opt.http_corsdomain.is_empty() && !opt.http_corsdomain.iter().any(|origin| origin == "*") {
+        access_control = access_control.set_allowed_origins(opt.http_corsdomain.clone())?;
+    }
+
+    let api = Methods::from(api);
+    if let Some(ipc_path) = opt.ipc_path {
+        // Socket file is left behind by an unclean shutdown.
+        if ipc_path.exists() {
+            std::fs::remove_file(&ipc_path)?;
+        }
+        let listener = UnixListener::bind(&ipc_path)?;
+        info!("IPC endpoint opened at {}", ipc_path.display());
+
+        tokio::spawn(serve_ipc(
+            listener,
+            api.clone(),
+            opt.http_max_request_size as usize,
+        ));
+    }
+
+    let server = HttpServerBuilder::default()
+        .max_request_body_size(opt.http_max_request_size)
+        .set_access_control(access_control.build())
+        .build(opt.listen_address)?;
+    let _server_handle = server.start(api)?;
 
     pending().await
 }
@@ -1,22 +1,33 @@
 use martinez::{
-    binutil::MartinezDataDir,
+    binutil::{init_tracing, AccessMode, MartinezDataDir},
     hex_to_bytes,
     kv::{
-        tables::{self, CHAINDATA_TABLES},
+        printers::{self, EntryPrinter},
+        tables::{self, BitmapKey, CHAINDATA_TABLES},
         traits::*,
+        value_codec::ValueCodec,
     },
     models::*,
-    stagedsync,
+    stagedsync::{self, stage::*, stages::*},
     stages::*,
 };
 use anyhow::{bail, ensure, format_err, Context};
 use bytes::Bytes;
 use clap::Parser;
 use itertools::Itertools;
-use std::{borrow::Cow, path::PathBuf, sync::Arc};
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::Debug,
+    hash::Hash,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 use tokio::pin;
 use tracing::*;
-use tracing_subscriber::{prelude::*, EnvFilter};
 
 #[derive(Parser)]
 #[clap(name = "Martinez Toolbox", about = "Utilities for Martinez Ethereum client")]
@@ -24,13 +35,21 @@ struct Opt {
     #[clap(long = "datadir", help = "Database directory path", default_value_t)]
     pub data_dir: MartinezDataDir,
 
+    /// Chain whose subdirectory of the datadir to use.
+    #[clap(long = "chain", default_value = "mainnet")]
+    pub chain_name: String,
+
+    /// Print logs as JSON lines.
+    #[clap(long = "log.json")]
+    pub log_json: bool,
+
     #[clap(subcommand)]
     pub command: OptCommand,
 }
 
 #[derive(Parser)]
 pub enum OptCommand {
-    /// Print database statistics
+    /// Print database statistics, with growth since the previous run
     DbStats {
         /// Whether to print CSV
         #[clap(long)]
@@ -53,6 +72,18 @@ pub enum OptCommand {
         starting_key: Option<Bytes>,
         #[clap(long)]
         max_entries: Option<usize>,
+        /// Print entries as text or as JSON lines
+        #[clap(long, arg_enum, default_value = "text")]
+        format: OutputFormat,
+    },
+
+    /// Re-encode the values of a table stored with another codec
+    DbRecode {
+        #[clap(long)]
+        table: String,
+        /// Codec the values are currently stored with
+        #[clap(long, arg_enum)]
+        from: ValueCodec,
     },
 
     /// Check table equality in two databases
@@ -68,6 +99,15 @@ pub enum OptCommand {
     /// Execute Block Hashes stage
     Blockhashes,
 
+    /// Run or unwind a single stage in isolation
+    Stage {
+        #[clap(subcommand)]
+        command: StageCommand,
+    },
+
+    /// Summarize where sync time was spent, per stage, across all recorded runs
+    StageStats,
+
     /// Execute HeaderDownload stage
     #[clap(name = "download-headers", about = "Run block headers downloader")]
     HeaderDownload {
@@ -94,6 +134,53 @@ pub enum OptCommand {
     ReadStorageChanges {
         block: BlockNumber,
     },
+
+    /// Check that stored logs match header blooms and log indexes in a block range
+    CheckLogs {
+        #[clap(long)]
+        from: BlockNumber,
+        #[clap(long)]
+        to: BlockNumber,
+    },
+
+    /// Summarize accounts and storage changed between two blocks
+    StateDiff {
+        from: BlockNumber,
+        to: BlockNumber,
+        /// Print the summary as text or as JSON
+        #[clap(long, arg_enum, default_value = "text")]
+        format: OutputFormat,
+    },
+}
+
+#[derive(Parser)]
+pub enum StageCommand {
+    /// Execute the stage until it reaches the block
+    Run {
+        /// One of: block-hashes, total-gas-index, total-tx-index, senders, execution, hashstate, interhashes, call-trace-index
+        name: String,
+        #[clap(long)]
+        to: BlockNumber,
+    },
+    /// Unwind the stage down to the block
+    Unwind {
+        /// One of: block-hashes, total-gas-index, total-tx-index, senders, execution, hashstate, interhashes, call-trace-index
+        name: String,
+        #[clap(long)]
+        to: BlockNumber,
+    },
+    /// Print the progress of every stage, as saved by Martinez or Erigon
+    Progress {
+        /// Chaindata directory to read instead of the one of the datadir, e.g. Erigon's
+        #[clap(long, parse(from_os_str))]
+        chaindata: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ArgEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
 }
 
 #[derive(Parser)]
@@ -116,20 +203,32 @@ pub struct HeaderDownloadOpts {
     pub downloader_opts: martinez::downloader::opts::Opts,
 }
 
-async fn blockhashes(data_dir: MartinezDataDir) -> anyhow::Result<()> {
-    std::fs::create_dir_all(&data_dir.0)?;
-
+fn etl_temp_dir(data_dir: &MartinezDataDir) -> anyhow::Result<Arc<tempfile::TempDir>> {
     let etl_temp_path = data_dir.etl_temp_dir();
     let _ = std::fs::remove_dir_all(&etl_temp_path);
     std::fs::create_dir_all(&etl_temp_path)?;
-    let etl_temp_dir =
-        Arc::new(tempfile::tempdir_in(&etl_temp_path).context("failed to create ETL temp dir")?);
+    Ok(Arc::new(
+        tempfile::tempdir_in(&etl_temp_path).context("failed to create ETL temp dir")?,
+    ))
+}
 
+fn open_db_rw(
+    data_dir: &MartinezDataDir,
+) -> anyhow::Result<martinez::kv::mdbx::MdbxEnvironment<mdbx::NoWriteMap>> {
     let env = martinez::kv::mdbx::MdbxEnvironment::<mdbx::NoWriteMap>::open_rw(
         mdbx::Environment::new(),
         &data_dir.chain_data_dir(),
         martinez::kv::tables::CHAINDATA_TABLES.clone(),
     )?;
+    martinez::kv::migrations::ensure_migrated(&env.begin()?)?;
+    Ok(env)
+}
+
+async fn blockhashes(data_dir: MartinezDataDir) -> anyhow::Result<()> {
+    let _lock = data_dir.lock(AccessMode::Writer)?;
+
+    let etl_temp_dir = etl_temp_dir(&data_dir)?;
+    let env = open_db_rw(&data_dir)?;
 
     let mut staged_sync = stagedsync::StagedSync::new();
     staged_sync.push(BlockHashes {
@@ -139,6 +238,256 @@ async fn blockhashes(data_dir: MartinezDataDir) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Builds the stage called `name`, along with the stage that precedes it in the sync loop.
+fn make_stage<'db, E>(
+    name: &str,
+    etl_temp_dir: Arc<tempfile::TempDir>,
+) -> anyhow::Result<(Box<dyn Stage<'db, E>>, StageId)>
+where
+    E: mdbx::EnvironmentKind,
+{
+    Ok(match name {
+        "block-hashes" => (
+            Box::new(BlockHashes {
+                temp_dir: etl_temp_dir,
+            }),
+            HEADERS,
+        ),
+        "total-gas-index" => (Box::new(TotalGasIndex), HEADERS),
+        "total-tx-index" => (Box::new(TotalTxIndex), BODIES),
+        "senders" => (
+            Box::new(SenderRecovery {
+                batch_size: 500_000,
+            }),
+            BODIES,
+        ),
+        "execution" => (
+            Box::new(Execution {
+                batch_size: 5_000_000_000_000,
+                history_batch_size: 250_000_000_000,
+                exit_after_batch: false,
+                batch_until: None,
+                commit_every: None,
+                prune_from: BlockNumber(0),
+                call_trace_stats: false,
+                header_cache: None,
+                code_cache: None,
+            }),
+            SENDERS,
+        ),
+        "hashstate" => (Box::new(HashState::new(etl_temp_dir, None)), EXECUTION),
+        "interhashes" => (Box::new(Interhashes::new(etl_temp_dir, None)), HASH_STATE),
+        "call-trace-index" => (
+            Box::new(CallTraceIndex {
+                temp_dir: etl_temp_dir,
+                flush_interval: 50_000,
+            }),
+            EXECUTION,
+        ),
+        other => bail!("unknown stage: {}", other),
+    })
+}
+
+async fn stage_run(data_dir: MartinezDataDir, name: String, to: BlockNumber) -> anyhow::Result<()> {
+    let _lock = data_dir.lock(AccessMode::Writer)?;
+    let etl_temp_dir = etl_temp_dir(&data_dir)?;
+    let env = open_db_rw(&data_dir)?;
+
+    let (mut stage, previous_stage) = make_stage(&name, etl_temp_dir)?;
+    let stage_id = stage.id();
+
+    let start_time = Instant::now();
+    let mut tx = env.begin_mutable()?;
+    let start_progress = stage_id.get_progress(&tx)?;
+    let previous_progress = previous_stage.get_progress(&tx)?.unwrap_or(BlockNumber(0));
+    if previous_progress < to {
+        warn!(
+            "{} is only at {}, running {} up to it",
+            previous_stage, previous_progress, stage_id
+        );
+    }
+    let to = to.min(previous_progress);
+    info!("Running {} from {:?} to {}", stage_id, start_progress, to);
+
+    let mut restarted = false;
+    loop {
+        let stage_progress = stage_id.get_progress(&tx)?;
+        match stage
+            .execute(
+                &mut tx,
+                StageInput {
+                    restarted,
+                    first_started_at: (start_time, start_progress),
+                    previous_stage: Some((previous_stage, to)),
+                    stage_progress,
+                },
+            )
+            .await?
+        {
+            ExecOutput::Progress {
+                stage_progress,
+                done,
+            } => {
+                stage_id.save_progress(&tx, stage_progress)?;
+                tx.commit()?;
+                info!("{} @ {}", stage_id, stage_progress);
+
+                if done {
+                    break;
+                }
+                tx = env.begin_mutable()?;
+                restarted = true;
+            }
+            ExecOutput::Unwind { unwind_to } => {
+                bail!(
+                    "{} requested an unwind to {}, unwind it and the stages before it first",
+                    stage_id,
+                    unwind_to
+                );
+            }
+        }
+    }
+
+    info!(
+        "Done in {}",
+        stagedsync::format_duration(Instant::now() - start_time, true)
+    );
+    Ok(())
+}
+
+async fn stage_unwind(
+    data_dir: MartinezDataDir,
+    name: String,
+    to: BlockNumber,
+) -> anyhow::Result<()> {
+    let _lock = data_dir.lock(AccessMode::Writer)?;
+    let etl_temp_dir = etl_temp_dir(&data_dir)?;
+    let env = open_db_rw(&data_dir)?;
+
+    let (mut stage, _) = make_stage(&name, etl_temp_dir)?;
+    let stage_id = stage.id();
+
+    let start_time = Instant::now();
+    let mut tx = env.begin_mutable()?;
+    let mut stage_progress = stage_id.get_progress(&tx)?.unwrap_or_default();
+    if stage_progress <= to {
+        info!("{} is already at {}", stage_id, stage_progress);
+        return Ok(());
+    }
+
+    info!("Unwinding {} from {} to {}", stage_id, stage_progress, to);
+    while stage_progress > to {
+        stage_progress = stage
+            .unwind(
+                &mut tx,
+                UnwindInput {
+                    stage_progress,
+                    unwind_to: to,
+                },
+            )
+            .await?
+            .stage_progress;
+        stage_id.save_progress(&tx, stage_progress)?;
+    }
+    tx.commit()?;
+
+    info!(
+        "{} unwound to {} in {}",
+        stage_id,
+        stage_progress,
+        stagedsync::format_duration(Instant::now() - start_time, true)
+    );
+    Ok(())
+}
+
+/// Erigon keeps stage progress in a `SyncStage` table of the same layout, so its databases can
+/// be read as well. Stage names are told apart by whether both clients run them.
+fn stage_progress(data_dir: MartinezDataDir, chaindata: Option<PathBuf>) -> anyhow::Result<()> {
+    let env = martinez::kv::mdbx::MdbxEnvironment::<mdbx::NoWriteMap>::open_ro(
+        mdbx::Environment::new(),
+        &chaindata.unwrap_or_else(|| data_dir.chain_data_dir()),
+        Default::default(),
+    )?;
+    let txn = env.begin()?;
+
+    for res in txn.cursor(tables::SyncStage.erased())?.walk(None) {
+        let (name, progress) = res?;
+        let name = String::from_utf8(name)?;
+        let progress = <BlockNumber as TableDecode>::decode(&progress)?;
+        let note = match StageId::from_name(&name) {
+            Some(stage) if stage.0 != name => format!(" (former name of {})", stage),
+            Some(stage) if !stage.is_erigon_compatible() => " (Martinez only)".to_string(),
+            Some(_) => String::new(),
+            None => " (not run by Martinez)".to_string(),
+        };
+        println!("{} - {}{}", name, progress, note);
+    }
+
+    Ok(())
+}
+
+fn stage_stats(data_dir: MartinezDataDir) -> anyhow::Result<()> {
+    #[derive(Default)]
+    struct Summary {
+        runs: u64,
+        blocks: u64,
+        duration_ms: u64,
+        db_growth: i64,
+    }
+
+    let env = open_db(data_dir)?;
+    let txn = env.begin()?;
+
+    let mut summaries = HashMap::<String, Summary>::new();
+    let mut first_run = None;
+    let mut last_run = None;
+    for res in txn.cursor(tables::StageStats)?.walk(None) {
+        let (_, entry) = res?;
+
+        first_run = first_run.or(Some(entry.started_at));
+        last_run = Some(entry.started_at);
+
+        let summary = summaries.entry(entry.stage).or_default();
+        summary.runs += 1;
+        summary.blocks += entry.to.0 - entry.from.map(|b| b.0).unwrap_or_default();
+        summary.duration_ms += entry.duration_ms;
+        summary.db_growth += entry.db_growth;
+    }
+
+    let (first_run, last_run) = match (first_run, last_run) {
+        (Some(first_run), Some(last_run)) => (first_run, last_run),
+        _ => {
+            println!("No stage runs recorded");
+            return Ok(());
+        }
+    };
+
+    let total_ms = summaries.values().map(|s| s.duration_ms).sum::<u64>();
+    println!(
+        "Stage runs recorded between {} and {} (unix time), {} total",
+        first_run,
+        last_run,
+        stagedsync::format_duration(std::time::Duration::from_millis(total_ms), false)
+    );
+    for (stage, summary) in summaries
+        .into_iter()
+        .sorted_by_key(|(_, s)| std::cmp::Reverse(s.duration_ms))
+    {
+        println!(
+            "{} - {} ({:.1}%) in {} runs, {} blocks, {}{}",
+            stage,
+            stagedsync::format_duration(std::time::Duration::from_millis(summary.duration_ms), false),
+            summary.duration_ms as f64 * 100.0 / total_ms.max(1) as f64,
+            summary.runs,
+            summary.blocks,
+            if summary.db_growth < 0 { "-" } else { "+" },
+            bytesize::ByteSize::b(summary.db_growth.unsigned_abs())
+        );
+    }
+
+    Ok(())
+}
+
 #[allow(unreachable_code)]
 async fn header_download(data_dir: MartinezDataDir, opts: HeaderDownloadOpts) -> anyhow::Result<()> {
     let chains_config = martinez::sentry::chain_config::ChainsConfig::new()?;
@@ -159,14 +508,14 @@ async fn header_download(data_dir: MartinezDataDir, opts: HeaderDownloadOpts) ->
 
     let stage = martinez::stages::HeaderDownload::new(
         chain_config,
-        opts.downloader_opts.headers_mem_limit(),
-        opts.downloader_opts.headers_batch_size,
+        &opts.downloader_opts,
         sentry.clone(),
         sentry_status_provider,
     )?;
 
-    std::fs::create_dir_all(&data_dir.0)?;
+    let _lock = data_dir.lock(AccessMode::Writer)?;
     let db = martinez::kv::new_database(&data_dir.chain_data_dir())?;
+    martinez::kv::migrations::ensure_migrated(&db.begin()?)?;
 
     let mut staged_sync = stagedsync::StagedSync::new();
     staged_sync.push(stage);
@@ -180,38 +529,136 @@ async fn header_download(data_dir: MartinezDataDir, opts: HeaderDownloadOpts) ->
 fn open_db(
     data_dir: MartinezDataDir,
 ) -> anyhow::Result<martinez::kv::mdbx::MdbxEnvironment<mdbx::NoWriteMap>> {
-    martinez::kv::mdbx::MdbxEnvironment::<mdbx::NoWriteMap>::open_ro(
+    let env = martinez::kv::mdbx::MdbxEnvironment::<mdbx::NoWriteMap>::open_ro(
         mdbx::Environment::new(),
         &data_dir.chain_data_dir(),
         CHAINDATA_TABLES.clone(),
+    )?;
+    martinez::kv::migrations::ensure_migrated(&env.begin()?)?;
+    Ok(env)
+}
+
+/// Table sizes saved by `db-stats`, to report growth on the next run.
+#[derive(Serialize, Deserialize)]
+struct TableSizesSnapshot {
+    /// Unix timestamp in seconds.
+    taken_at: u64,
+    sizes: HashMap<String, u64>,
+}
+
+impl TableSizesSnapshot {
+    /// Previous snapshot, if any. An unreadable one is ignored and overwritten.
+    fn load(path: &Path) -> Option<Self> {
+        let data = std::fs::read(path).ok()?;
+        serde_json::from_slice(&data)
+            .map_err(|e| warn!("Ignoring malformed {}: {}", path.display(), e))
+            .ok()
+    }
+
+    fn growth(&self, table: &str, size: u64) -> i64 {
+        size as i64 - self.sizes.get(table).copied().unwrap_or(0) as i64
+    }
+}
+
+fn format_growth(growth: i64) -> String {
+    format!(
+        "{}{}",
+        if growth < 0 { "-" } else { "+" },
+        bytesize::ByteSize::b(growth.unsigned_abs())
     )
 }
 
 fn table_sizes(data_dir: MartinezDataDir, csv: bool) -> anyhow::Result<()> {
+    let snapshot_file = data_dir.db_stats_file();
     let env = open_db(data_dir)?;
 
-    let mut sizes = env.begin()?.table_sizes()?.into_iter().collect::<Vec<_>>();
+    let snapshot = TableSizesSnapshot {
+        taken_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        sizes: env.begin()?.table_sizes()?,
+    };
+    let previous = TableSizesSnapshot::load(&snapshot_file);
+
+    let mut sizes = snapshot
+        .sizes
+        .iter()
+        .map(|(table, &size)| (table, size))
+        .collect::<Vec<_>>();
     sizes.sort_by_key(|(_, size)| *size);
 
     let mut out = Vec::new();
     if csv {
-        out.push("Table,Size".to_string());
-        for (table, size) in &sizes {
-            out.push(format!("{},{}", table, size));
+        if let Some(previous) = &previous {
+            out.push("Table,Size,Growth".to_string());
+            for (table, size) in &sizes {
+                out.push(format!(
+                    "{},{},{}",
+                    table,
+                    size,
+                    previous.growth(table, *size)
+                ));
+            }
+        } else {
+            out.push("Table,Size".to_string());
+            for (table, size) in &sizes {
+                out.push(format!("{},{}", table, size));
+            }
         }
     } else {
         for (table, size) in &sizes {
-            out.push(format!("{} - {}", table, bytesize::ByteSize::b(*size)));
+            let mut line = format!("{} - {}", table, bytesize::ByteSize::b(*size));
+            if let Some(previous) = &previous {
+                line.push_str(&format!(
+                    " ({})",
+                    format_growth(previous.growth(table, *size))
+                ));
+            }
+            out.push(line);
+        }
+        let total = sizes.iter().map(|(_, size)| size).sum::<u64>();
+        out.push(format!("TOTAL: {}", bytesize::ByteSize::b(total)));
+
+        if let Some(previous) = &previous {
+            let elapsed = Duration::from_secs(snapshot.taken_at.saturating_sub(previous.taken_at));
+            out.push(format!(
+                "Growth since the previous run {} ago: {}",
+                stagedsync::format_duration(elapsed, false),
+                format_growth(total as i64 - previous.sizes.values().sum::<u64>() as i64)
+            ));
+            if let Some((table, growth)) = sizes
+                .iter()
+                .map(|(table, size)| (table, previous.growth(table, *size)))
+                .filter(|(_, growth)| *growth > 0)
+                .max_by_key(|(_, growth)| *growth)
+            {
+                let mut line = format!("Fastest growing: {} ({}", table, format_growth(growth));
+                if elapsed.as_secs() > 0 {
+                    line.push_str(&format!(
+                        ", {}/h",
+                        bytesize::ByteSize::b(growth as u64 * 3600 / elapsed.as_secs())
+                    ));
+                }
+                line.push(')');
+                out.push(line);
+            }
         }
-        out.push(format!(
-            "TOTAL: {}",
-            bytesize::ByteSize::b(sizes.into_iter().map(|(_, size)| size).sum())
-        ));
     }
 
     for line in out {
         println!("{}", line);
     }
+
+    // The datadir may be read-only to the operator, stats are still printed.
+    if let Err(e) = serde_json::to_vec_pretty(&snapshot)
+        .map_err(anyhow::Error::from)
+        .and_then(|data| Ok(std::fs::write(&snapshot_file, data)?))
+    {
+        warn!(
+            "Failed to save table sizes to {}: {}",
+            snapshot_file.display(),
+            e
+        );
+    }
+
     Ok(())
 }
 
@@ -236,14 +683,61 @@ fn db_query(data_dir: MartinezDataDir, table: String, key: Bytes) -> anyhow::Res
     Ok(())
 }
 
+/// Decoded form of a raw key or value, falling back to hex.
+fn render_entry_part(raw: &[u8], decoded: Option<anyhow::Result<String>>) -> String {
+    match decoded {
+        Some(Ok(decoded)) => decoded,
+        Some(Err(e)) => format!("0x{} (failed to decode: {})", hex::encode(raw), e),
+        None => format!("0x{}", hex::encode(raw)),
+    }
+}
+
+fn entry_json(
+    index: usize,
+    k: &[u8],
+    v: &[u8],
+    printer: Option<&dyn EntryPrinter>,
+) -> serde_json::Value {
+    let mut entry = serde_json::json!({
+        "index": index,
+        "key": format!("0x{}", hex::encode(k)),
+        "value": format!("0x{}", hex::encode(v)),
+    });
+    if let Some(printer) = printer {
+        for (field, decoded) in [
+            ("decoded_key", printer.key(k)),
+            ("decoded_value", printer.value(v)),
+        ] {
+            entry[field] = match decoded {
+                Ok(decoded) => decoded.into(),
+                Err(e) => serde_json::json!({ "error": e.to_string() }),
+            };
+        }
+    }
+    entry
+}
+
+fn db_recode(data_dir: MartinezDataDir, table: String, from: ValueCodec) -> anyhow::Result<()> {
+    let _lock = data_dir.lock(AccessMode::Writer)?;
+    let env = open_db_rw(&data_dir)?;
+
+    martinez::kv::migrations::recode_values(&env, &table, from)
+}
+
 fn db_walk(
     data_dir: MartinezDataDir,
     table: String,
     starting_key: Option<Bytes>,
     max_entries: Option<usize>,
+    format: OutputFormat,
 ) -> anyhow::Result<()> {
     let env = open_db(data_dir)?;
 
+    let printer = printers::printer(&table);
+    if printer.is_none() {
+        warn!("No decoder for table {}, printing raw entries", table);
+    }
+
     let txn = env.begin_ro_txn()?;
     let db = txn
         .open_db(Some(&table))
@@ -258,14 +752,15 @@ fn db_walk(
     .take(max_entries.unwrap_or(usize::MAX))
     {
         let (k, v) = item?;
-        println!(
-            "{} / {:?} / {:?} / {:?} / {:?}",
-            i,
-            hex::encode(k),
-            hex::encode(&v),
-            Account::decode_for_storage(&v),
-            BlockHeader::decode(&v)
-        );
+        match format {
+            OutputFormat::Text => println!(
+                "{} / {} / {}",
+                i,
+                render_entry_part(&k, printer.map(|printer| printer.key(&k))),
+                render_entry_part(&v, printer.map(|printer| printer.value(&v)))
+            ),
+            OutputFormat::Json => println!("{}", entry_json(i, &k, &v, printer)),
+        }
     }
 
     Ok(())
@@ -465,22 +960,227 @@ fn read_storage_changes(data_dir: MartinezDataDir, block: BlockNumber) -> anyhow
     Ok(())
 }
 
+/// Reads all bitmaps of a log index, restricted to `range`.
+fn read_log_index<'db, K, T>(
+    tx: &martinez::kv::mdbx::MdbxTransaction<'db, mdbx::RO, mdbx::NoWriteMap>,
+    table: T,
+    range: RangeInclusive<BlockNumber>,
+) -> anyhow::Result<HashMap<K, croaring::Treemap>>
+where
+    K: Eq + Hash,
+    BitmapKey<K>: TableDecode,
+    T: Table<Key = BitmapKey<K>, Value = croaring::Treemap>,
+{
+    let mut out = HashMap::<K, croaring::Treemap>::new();
+
+    let walker = tx.cursor(table)?.walk(None);
+    pin!(walker);
+
+    while let Some((BitmapKey { inner, .. }, chunk)) = walker.next().transpose()? {
+        let blocks = out.entry(inner).or_default();
+        for block in chunk
+            .iter()
+            .filter(|&block| range.contains(&BlockNumber(block)))
+        {
+            blocks.add(block);
+        }
+    }
+
+    out.retain(|_, blocks| !blocks.is_empty());
+
+    Ok(out)
+}
+
+/// Compares log index against blocks where each key was found in logs, returns number of mismatching keys.
+fn compare_log_index<K: Debug + Eq + Hash>(
+    index_name: &str,
+    expected: &HashMap<K, croaring::Treemap>,
+    indexed: &HashMap<K, croaring::Treemap>,
+) -> usize {
+    let empty = croaring::Treemap::create();
+    let mut mismatches = 0;
+    for key in expected
+        .keys()
+        .chain(indexed.keys().filter(|k| !expected.contains_key(k)))
+    {
+        let expected = expected.get(key).unwrap_or(&empty);
+        let indexed = indexed.get(key).unwrap_or(&empty);
+
+        if expected != indexed {
+            let missing = expected
+                .iter()
+                .filter(|&block| !indexed.contains(block))
+                .collect::<Vec<_>>();
+            let extra = indexed
+                .iter()
+                .filter(|&block| !expected.contains(block))
+                .collect::<Vec<_>>();
+            warn!(
+                "{} mismatch for {:?}: missing blocks {:?}, extra blocks {:?}",
+                index_name, key, missing, extra
+            );
+            mismatches += 1;
+        }
+    }
+
+    mismatches
+}
+
+fn check_logs(data_dir: MartinezDataDir, from: BlockNumber, to: BlockNumber) -> anyhow::Result<()> {
+    ensure!(from <= to, "empty block range");
+
+    let env = open_db(data_dir)?;
+
+    let tx = env.begin()?;
+
+    let mut addresses = HashMap::<Address, croaring::Treemap>::new();
+    let mut topics = HashMap::<H256, croaring::Treemap>::new();
+    let mut bloom_mismatches = 0;
+    for res in martinez::accessors::chain::canonical_headers(&tx, from..=to)? {
+        let (_, header) = res?;
+        let block_number = header.number;
+
+        let walker = tx
+            .cursor(tables::Log)?
+            .walk(Some((block_number, TxIndex(0))))
+            .take_while(ttw(|((n, _), _)| *n == block_number));
+        pin!(walker);
+
+        let mut logs = Vec::new();
+        while let Some((_, tx_logs)) = walker.next().transpose()? {
+            logs.extend(tx_logs);
+        }
+
+        for log in &logs {
+            addresses
+                .entry(log.address)
+                .or_default()
+                .add(block_number.0);
+            for topic in &log.topics {
+                topics.entry(*topic).or_default().add(block_number.0);
+            }
+        }
+
+        let bloom = logs_bloom(&logs);
+        if bloom != header.logs_bloom {
+            warn!(
+                "Logs bloom mismatch in block {}: {} logs stored, header bloom {:?}, computed {:?}",
+                block_number,
+                logs.len(),
+                header.logs_bloom,
+                bloom
+            );
+            bloom_mismatches += 1;
+        }
+
+        if block_number.0 > from.0 && block_number.0 % 100_000 == 0 {
+            info!("Checked blooms up to block {}", block_number);
+        }
+    }
+
+    let range = from..=to;
+    let address_mismatches = compare_log_index(
+        "LogAddressIndex",
+        &addresses,
+        &read_log_index(&tx, tables::LogAddressIndex, range.clone())?,
+    );
+    let topic_mismatches = compare_log_index(
+        "LogTopicIndex",
+        &topics,
+        &read_log_index(&tx, tables::LogTopicIndex, range)?,
+    );
+
+    ensure!(
+        bloom_mismatches + address_mismatches + topic_mismatches == 0,
+        "MISMATCH DETECTED: {} blooms, {} addresses, {} topics",
+        bloom_mismatches,
+        address_mismatches,
+        topic_mismatches
+    );
+
+    info!(
+        "Check complete. {} blocks, {} addresses, {} topics checked.",
+        to.0 - from.0 + 1,
+        addresses.len(),
+        topics.len()
+    );
+
+    Ok(())
+}
+
+fn state_diff(
+    data_dir: MartinezDataDir,
+    from: BlockNumber,
+    to: BlockNumber,
+    format: OutputFormat,
+) -> anyhow::Result<()> {
+    let env = open_db(data_dir)?;
+
+    let tx = env.begin()?;
+
+    let diff = martinez::accessors::state::diff::read(&tx, from, to)?;
+
+    let created = diff.created().collect::<Vec<_>>();
+    let deleted = diff.deleted().collect::<Vec<_>>();
+    let modified = diff.modified().count();
+    let churn = diff
+        .storage
+        .iter()
+        .map(|(address, slots)| (*address, slots.len()))
+        .sorted_by(|(_, a), (_, b)| b.cmp(a))
+        .collect::<Vec<_>>();
+
+    match format {
+        OutputFormat::Text => {
+            println!(
+                "Accounts: {} created, {} deleted, {} modified",
+                created.len(),
+                deleted.len(),
+                modified
+            );
+            for address in &created {
+                println!("+ {:?}", address);
+            }
+            for address in &deleted {
+                println!("- {:?}", address);
+            }
+            println!(
+                "Storage: {} slots changed in {} contracts",
+                churn.iter().map(|(_, slots)| slots).sum::<usize>(),
+                churn.len()
+            );
+            for (address, slots) in &churn {
+                println!("{:?}: {}", address, slots);
+            }
+        }
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::json!({
+                "from": from.0,
+                "to": to.0,
+                "created": created,
+                "deleted": deleted,
+                "modified": modified,
+                "storage": churn
+                    .iter()
+                    .map(|(address, slots)| {
+                        serde_json::json!({ "address": address, "slots": slots })
+                    })
+                    .collect::<Vec<_>>(),
+            })
+        ),
+    }
+
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let opt: Opt = Opt::parse();
+    let mut opt: Opt = Opt::parse();
 
-    let filter = if std::env::var(EnvFilter::DEFAULT_ENV)
-        .unwrap_or_default()
-        .is_empty()
-    {
-        EnvFilter::new("martinez=info")
-    } else {
-        EnvFilter::from_default_env()
-    };
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::fmt::layer().with_target(false))
-        .with(filter)
-        .init();
+    init_tracing("martinez=info", opt.log_json);
+
+    opt.data_dir = opt.data_dir.chain_dir(&opt.chain_name);
 
     match opt.command {
         OptCommand::DbStats { csv } => table_sizes(opt.data_dir, csv)?,
@@ -490,14 +1190,24 @@ async fn main() -> anyhow::Result<()> {
             table,
             starting_key,
             max_entries,
-        } => db_walk(opt.data_dir, table, starting_key, max_entries)?,
+            format,
+        } => db_walk(opt.data_dir, table, starting_key, max_entries, format)?,
+        OptCommand::DbRecode { table, from } => db_recode(opt.data_dir, table, from)?,
         OptCommand::CheckEqual { db1, db2, table } => check_table_eq(db1, db2, table)?,
+        OptCommand::Stage { command } => match command {
+            StageCommand::Run { name, to } => stage_run(opt.data_dir, name, to).await?,
+            StageCommand::Unwind { name, to } => stage_unwind(opt.data_dir, name, to).await?,
+            StageCommand::Progress { chaindata } => stage_progress(opt.data_dir, chaindata)?,
+        },
+        OptCommand::StageStats => stage_stats(opt.data_dir)?,
         OptCommand::HeaderDownload { opts } => header_download(opt.data_dir, opts).await?,
         OptCommand::ReadBlock { block_number } => read_block(opt.data_dir, block_number)?,
         OptCommand::ReadAccount { address } => read_account(opt.data_dir, address)?,
         OptCommand::ReadAccountChanges { block } => read_account_changes(opt.data_dir, block)?,
         OptCommand::ReadStorage { address } => read_storage(opt.data_dir, address)?,
         OptCommand::ReadStorageChanges { block } => read_storage_changes(opt.data_dir, block)?,
+        OptCommand::CheckLogs { from, to } => check_logs(opt.data_dir, from, to)?,
+        OptCommand::StateDiff { from, to, format } => state_diff(opt.data_dir, from, to, format)?,
     }
 
     Ok(())
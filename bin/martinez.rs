@@ -1,18 +1,35 @@
 use martinez::{
-    binutil::MartinezDataDir,
-    downloader::sentry_status_provider::SentryStatusProvider,
+    accessors::{code_cache::CodeCache, header_cache::HeaderCache},
+    binutil::{init_tracing, AccessMode, DataDirVersion, MartinezDataDir, DATADIR_VERSION},
+    downloader::{
+        body_downloader::AnnouncedBodies, chain_tip_watchdog::ChainTipWatchdog,
+        sentry_request_server::SentryRequestServer, sentry_status_provider::SentryStatusProvider,
+        tx_fetcher::{feed_txpool, TxFetcher},
+        HeaderSliceStore,
+    },
     kv::{
         mdbx::*,
         tables::{self, ErasedTable},
         traits::*,
+        MdbxWithDirHandle,
     },
     models::*,
     sentry::{
+        chain_config::ChainConfig,
+        node_record::{fork_id, LocalNode, NodeKey},
+        peer_policy::PeerPolicy,
+        sentry_client::SentryClient,
         sentry_client_connector::SentryClientConnectorImpl,
+        sentry_client_impl::SentryClientImpl,
         sentry_client_reactor::SentryClientReactor,
     },
+    sentry2::{
+        coordinator::{Coordinator, HeaderDownloader},
+        relay::canonical_blocks,
+    },
     stagedsync::{self, stage::*, stages::*},
     stages::*,
+    task_group::TaskGroup,
     version_string, StageId,
 };
 use anyhow::{bail, format_err, Context};
@@ -21,6 +38,7 @@ use clap::Parser;
 use mdbx::EnvironmentKind;
 use rayon::prelude::*;
 use std::{
+    net::{Ipv4Addr, SocketAddr},
     panic,
     path::PathBuf,
     sync::Arc,
@@ -28,7 +46,11 @@ use std::{
 };
 use tokio::pin;
 use tracing::*;
-use tracing_subscriber::{prelude::*, EnvFilter};
+
+/// Number of headers, and separately canonical hashes, kept in memory.
+const HEADER_CACHE_SIZE: usize = 16 * 1024;
+/// Total size of contract code kept in memory.
+const CODE_CACHE_SIZE: usize = 64 * 1024 * 1024;
 
 #[derive(Parser)]
 #[clap(name = "Martinez", about = "Next-generation Ethereum implementation.")]
@@ -49,6 +71,14 @@ pub struct Opt {
     )]
     pub chain_name: String,
 
+    /// Genesis of a private chain to join instead, as a geth `genesis.json` file.
+    #[clap(long = "genesis", parse(from_os_str))]
+    pub genesis: Option<PathBuf>,
+
+    /// Fork schedule overrides.
+    #[clap(flatten)]
+    pub spec_overrides: martinez::genesis::SpecOverrides,
+
     /// Sentry GRPC service URL
     #[clap(
         long = "sentry.api.addr",
@@ -57,6 +87,11 @@ pub struct Opt {
     )]
     pub sentry_api_addr: martinez::sentry::sentry_address::SentryAddress,
 
+    /// Transaction pool GRPC service URL as 'http://host:port', where transactions received
+    /// from the peers are submitted.
+    #[clap(long = "txpool.api.addr")]
+    pub txpool_api_addr: Option<http::Uri>,
+
     /// Last block where to sync to.
     #[clap(long)]
     pub max_block: Option<BlockNumber>,
@@ -69,6 +104,14 @@ pub struct Opt {
     #[clap(flatten)]
     pub downloader_opts: martinez::downloader::opts::Opts,
 
+    /// Database options.
+    #[clap(flatten)]
+    pub db_opts: martinez::kv::DatabaseOpts,
+
+    /// Peer policy options.
+    #[clap(flatten)]
+    pub peer_policy_opts: martinez::sentry::peer_policy::PeerPolicyOpts,
+
     /// Sender recovery batch size (blocks)
     #[clap(long, default_value = "500000")]
     pub sender_recovery_batch_size: u64,
@@ -89,6 +132,26 @@ pub struct Opt {
     #[clap(long)]
     pub skip_commitment: bool,
 
+    /// Record value transferred and gas used per address in call traces.
+    #[clap(long = "calltraces.stats")]
+    pub call_trace_stats: bool,
+
+    /// Keep account and storage history of only that many latest blocks.
+    #[clap(long = "prune.history")]
+    pub prune_history: Option<u64>,
+
+    /// Keep receipts and logs of only that many latest blocks.
+    #[clap(long = "prune.receipts")]
+    pub prune_receipts: Option<u64>,
+
+    /// Keep transaction lookups of only that many latest blocks.
+    #[clap(long = "prune.txlookup")]
+    pub prune_tx_lookup: Option<u64>,
+
+    /// Keep call traces of only that many latest blocks.
+    #[clap(long = "prune.calltraces")]
+    pub prune_call_traces: Option<u64>,
+
     /// Exit Martinez after sync is complete and there's no progress.
     #[clap(long)]
     pub exit_after_sync: bool,
@@ -96,6 +159,39 @@ pub struct Opt {
     /// Delay applied at the terminating stage.
     #[clap(long, default_value = "2000")]
     pub delay_after_sync: u64,
+
+    /// Compress contract code in the database.
+    #[clap(long = "db.compress-code")]
+    pub compress_code: bool,
+
+    /// Report read transactions open for longer than that many seconds.
+    #[clap(long = "db.max-reader-age")]
+    pub max_reader_age: Option<u64>,
+
+    /// Expire read transactions exceeding the max reader age.
+    #[clap(long = "db.abort-long-readers", requires = "max_reader_age")]
+    pub abort_long_readers: bool,
+
+    /// Print logs as JSON lines.
+    #[clap(long = "log.json")]
+    pub log_json: bool,
+
+    /// Serve the database over the remote KV protocol at this address.
+    #[clap(long = "private.api.addr")]
+    pub private_api_addr: Option<SocketAddr>,
+
+    /// Open the database read-only and only serve it, leaving sync to the process owning the
+    /// datadir.
+    #[clap(long, requires = "private_api_addr", conflicts_with = "erigon_data_dir")]
+    pub readonly: bool,
+
+    /// External IP address announced in the node record.
+    #[clap(long = "p2p.extip", default_value = "127.0.0.1")]
+    pub p2p_ext_ip: Ipv4Addr,
+
+    /// devp2p port announced in the node record.
+    #[clap(long = "p2p.port", default_value = "30303")]
+    pub p2p_port: u16,
 }
 
 #[derive(Debug)]
@@ -122,7 +218,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: StageInput,
-    ) -> anyhow::Result<ExecOutput>
+    ) -> Result<ExecOutput, StageError>
     where
         'db: 'tx,
     {
@@ -176,7 +272,8 @@ where
                         .seek_exact(TableEncode::encode((block_number, canonical_hash)).to_vec())?
                         .unwrap()
                         .1,
-                )?,
+                )
+                .with_context(|| format!("Invalid Erigon header for block #{}", block_number))?,
             )?;
             td_cur.append(
                 (block_number, canonical_hash),
@@ -201,7 +298,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: UnwindInput,
-    ) -> anyhow::Result<UnwindOutput>
+    ) -> Result<UnwindOutput, StageError>
     where
         'db: 'tx,
     {
@@ -262,7 +359,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: StageInput,
-    ) -> anyhow::Result<ExecOutput>
+    ) -> Result<ExecOutput, StageError>
     where
         'db: 'tx,
     {
@@ -331,11 +428,14 @@ where
                             continue;
                         }
 
-                        let body = rlp::decode::<BodyForStorage>(&v)?;
+                        let body = rlp::decode::<BodyForStorageWithOmmers>(&v).with_context(|| {
+                            format!("Invalid Erigon body for block #{}", block_num)
+                        })?;
 
                         let base_tx_id = body.base_tx_id;
 
-                        let tx_amount = usize::try_from(body.tx_amount)?;
+                        let tx_amount = usize::try_from(body.tx_amount)
+                            .context("Erigon tx amount does not fit usize")?;
                         let txs = erigon_tx
                             .cursor(tables::BlockTransaction.erased())?
                             .walk(Some(base_tx_id.encode().to_vec()))
@@ -344,13 +444,14 @@ where
                             .collect::<anyhow::Result<Vec<_>>>()?;
 
                         if txs.len() != tx_amount {
-                            bail!(
+                            return Err(format_err!(
                                 "Invalid tx amount in Erigon for block #{}/{}: {} != {}",
                                 block_num,
                                 block_hash,
                                 tx_amount,
                                 txs.len()
-                            );
+                            )
+                            .into());
                         }
 
                         accum_txs += tx_amount;
@@ -401,8 +502,8 @@ where
                 highest_block = block_num;
                 let body = BodyForStorage {
                     base_tx_id: starting_index,
-                    tx_amount: txs.len().try_into()?,
-                    uncles,
+                    tx_amount: txs.len().try_into().map_err(anyhow::Error::from)?,
+                    uncles: martinez::accessors::chain::ommers::write(tx, &uncles)?,
                 };
 
                 body_cur.append((block_num, block_hash), body)?;
@@ -452,7 +553,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: UnwindInput,
-    ) -> anyhow::Result<UnwindOutput>
+    ) -> Result<UnwindOutput, StageError>
     where
         'db: 'tx,
     {
@@ -497,7 +598,7 @@ where
         &mut self,
         _: &'tx mut MdbxTransaction<'db, RW, E>,
         input: StageInput,
-    ) -> anyhow::Result<ExecOutput>
+    ) -> Result<ExecOutput, StageError>
     where
         'db: 'tx,
     {
@@ -515,7 +616,7 @@ where
         &mut self,
         _: &'tx mut MdbxTransaction<'db, RW, E>,
         input: UnwindInput,
-    ) -> anyhow::Result<UnwindOutput>
+    ) -> Result<UnwindOutput, StageError>
     where
         'db: 'tx,
     {
@@ -525,31 +626,347 @@ where
     }
 }
 
-#[allow(unreachable_code)]
-fn main() -> anyhow::Result<()> {
-    let opt: Opt = Opt::parse();
+fn spawn_kv_server<DB, E>(tasks: &mut TaskGroup, db: Arc<DB>, addr: SocketAddr)
+where
+    DB: std::ops::Deref<Target = MdbxEnvironment<E>> + Send + Sync + 'static,
+    E: EnvironmentKind,
+{
+    info!("Serving remote KV at {}", addr);
+    tasks.spawn("kv server", async move {
+        tonic::transport::Server::builder()
+            .add_service(martinez::kv::remote::kv_server::KvServer::new(
+                martinez::kv::server::KvServer::new(db),
+            ))
+            .serve(addr)
+            .await?;
+        Ok(())
+    });
+}
+
+/// Fails unless the sentry runs with the node key from the datadir, so that the node keeps its
+/// identity across restarts.
+async fn check_sentry_identity(
+    node_key: NodeKey,
+    node_key_dir: PathBuf,
+    sentry_api_addr: martinez::sentry::sentry_address::SentryAddress,
+) -> anyhow::Result<()> {
+    let node_info = loop {
+        let res = async {
+            SentryClientImpl::new(sentry_api_addr.clone())
+                .await?
+                .node_info()
+                .await
+        }
+        .await;
+        match res {
+            Ok(node_info) => break node_info,
+            Err(error) => debug!("Failed to query sentry node info: {:?}", error),
+        }
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    };
+
+    if !node_key.is_enode_of(&node_info.enode) {
+        bail!(
+            "sentry at {} runs as {} instead of {}: start it with --nodekey {}",
+            sentry_api_addr.addr,
+            node_info.enode,
+            hex::encode(node_key.id()),
+            NodeKey::file(&node_key_dir).display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Keeps enforcing the peer policy, reconnecting to the sentry whenever it goes away.
+async fn enforce_peer_policy(
+    policy: PeerPolicy,
+    sentry_api_addr: martinez::sentry::sentry_address::SentryAddress,
+) -> anyhow::Result<()> {
+    loop {
+        let res = async {
+            let mut sentry = SentryClientImpl::new(sentry_api_addr.clone()).await?;
+            policy.run(&mut sentry).await
+        }
+        .await;
+        if let Err(error) = res {
+            warn!("Peer policy enforcement interrupted: {:?}", error);
+        }
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+}
+
+/// Serves the database without writing to it, sync is left to the process owning the datadir.
+async fn run_readonly(opt: Opt) -> anyhow::Result<()> {
+    let db = Arc::new(martinez::kv::open_database_ro::<mdbx::NoWriteMap>(
+        &opt.data_dir.chain_data_dir(),
+        opt.db_opts.cold_dir.as_deref(),
+    )?);
+
+    info!("Database opened read-only, sync is disabled");
+    let mut tasks = TaskGroup::new();
+    if let Some(addr) = opt.private_api_addr {
+        spawn_kv_server(&mut tasks, db, addr);
+    }
+
+    tasks
+        .run_until(async { Ok(tokio::signal::ctrl_c().await?) })
+        .await
+}
+
+async fn run_node<E: EnvironmentKind>(
+    opt: Opt,
+    chain_config: ChainConfig,
+    erigon_db: Option<Arc<MdbxEnvironment<mdbx::NoWriteMap>>>,
+    etl_temp_dir: Arc<tempfile::TempDir>,
+    db: Arc<MdbxWithDirHandle<E>>,
+) -> anyhow::Result<()> {
+    if martinez::kv::migrations::ensure_migrated(&db.begin()?).is_err() {
+        let _readers = opt.data_dir.exclude_readers()?;
+        martinez::kv::migrations::migrate(&db)?;
+    }
+    martinez::kv::code_compression::init(&db, opt.compress_code)?;
+    // Every background component lives in this group, if one of them dies the sync is stopped
+    // and the rest are shut down along with it.
+    let mut tasks = TaskGroup::new();
+    tasks.spawn(
+        "reader monitor",
+        martinez::kv::readers::monitor_readers(
+            db.clone(),
+            martinez::kv::readers::ReaderPolicy {
+                max_age: opt.max_reader_age.map(Duration::from_secs),
+                abort: opt.abort_long_readers,
+            },
+        ),
+    );
+    {
+        let span = span!(Level::INFO, "", " Genesis initialization ");
+        let _g = span.enter();
+        let txn = db.begin_mutable()?;
+        martinez::genesis::initialize_genesis(
+            &txn,
+            &*etl_temp_dir,
+            chain_config.chain_spec().clone(),
+        )?;
+        martinez::genesis::check_chain_spec(
+            &txn,
+            chain_config.chain_spec(),
+            chain_config.genesis_block_hash(),
+        )?;
+        txn.commit()?;
+    }
 
-    let nocolor = std::env::var("RUST_LOG_STYLE")
-        .map(|val| val == "never")
-        .unwrap_or(false);
+    if let Some(addr) = opt.private_api_addr {
+        spawn_kv_server(&mut tasks, db.clone(), addr);
+    }
 
-    // tracing setup
-    let env_filter = if std::env::var(EnvFilter::DEFAULT_ENV)
-        .unwrap_or_default()
-        .is_empty()
+    let node_key_dir = opt.data_dir.create_dir(opt.data_dir.node_key_dir())?;
+    let node_key = NodeKey::load_or_generate(&node_key_dir)?;
     {
-        EnvFilter::new("martinez=info")
+        let head = HEADERS
+            .get_progress(&db.begin()?)?
+            .unwrap_or(BlockNumber(0));
+        let node = LocalNode::update(
+            &node_key_dir,
+            &node_key,
+            opt.p2p_ext_ip,
+            opt.p2p_port,
+            fork_id(&chain_config, head),
+        )?;
+        info!("Node identity: {}", node.enode);
+        info!("Node record: {}", node.enr);
+    }
+
+    let sentry_status_provider = SentryStatusProvider::new(chain_config.clone());
+    // staged sync setup
+    let mut staged_sync = stagedsync::StagedSync::new();
+    let header_cache = Arc::new(HeaderCache::new(HEADER_CACHE_SIZE));
+    tasks.spawn("header cache", {
+        let header_cache = header_cache.clone();
+        let events = staged_sync.subscribe_chain_events();
+        async move {
+            header_cache.run(events).await;
+            Ok(())
+        }
+    });
+    staged_sync.set_min_progress_to_commit_after_stage(1024);
+    staged_sync.set_max_block(opt.max_block);
+    staged_sync.set_exit_after_sync(opt.exit_after_sync);
+    staged_sync.set_delay_after_sync(Some(Duration::from_millis(opt.delay_after_sync)));
+    staged_sync.set_fsync_on_stage_boundary(opt.db_opts.fast_sync_unsafe);
+    let mut body_download = None;
+    if let Some(erigon_db) = erigon_db.clone() {
+        staged_sync.push(ConvertHeaders {
+            db: erigon_db,
+            max_block: opt.max_block,
+            exit_after_progress: opt.increment,
+        });
     } else {
-        EnvFilter::from_default_env()
-    };
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::fmt::layer()
-                .with_target(false)
-                .with_ansi(!nocolor),
-        )
-        .with(env_filter)
-        .init();
+        // sentry setup
+        let mut sentry_reactor = SentryClientReactor::new(
+            Box::new(SentryClientConnectorImpl::new(opt.sentry_api_addr.clone())),
+            sentry_status_provider.current_status_stream(),
+        );
+        sentry_reactor.start()?;
+        let sentry = sentry_reactor.into_shared();
+
+        // the sentry has to announce the identity of this node
+        tasks.spawn(
+            "sentry identity",
+            check_sentry_identity(node_key, node_key_dir, opt.sentry_api_addr.clone()),
+        );
+
+        // keep static peers connected and enforce peer limits
+        if !opt.peer_policy_opts.is_empty() {
+            tasks.spawn(
+                "peer policy",
+                enforce_peer_policy(
+                    PeerPolicy::new(opt.peer_policy_opts.clone()),
+                    opt.sentry_api_addr.clone(),
+                ),
+            );
+        }
+
+        // serve data requests of the peers
+        let request_server =
+            SentryRequestServer::new(db.clone(), sentry.clone(), header_cache.clone());
+        tasks.spawn("sentry request server", async move { request_server.run().await });
+
+        // fetch transactions announced by the peers into the pool
+        if let Some(txpool_api_addr) = opt.txpool_api_addr.clone() {
+            let (pool_tx, pool_rx) = tokio::sync::mpsc::channel(16);
+            let tx_fetcher = TxFetcher::new(sentry.clone(), pool_tx);
+            tasks.spawn("tx fetcher", async move { tx_fetcher.run().await });
+            tasks.spawn("txpool feed", feed_txpool(txpool_api_addr, pool_rx));
+        }
+
+        // keep the bodies of announced blocks for the body download
+        let announced_bodies = AnnouncedBodies::new();
+        tasks.spawn("announced bodies", {
+            let announced_bodies = announced_bodies.clone();
+            let sentry = sentry.clone();
+            async move { announced_bodies.run(sentry).await }
+        });
+        body_download = Some(BodyDownload::new(
+            &opt.downloader_opts,
+            sentry.clone(),
+            announced_bodies,
+        ));
+
+        // announce the blocks reached by staged sync to the peers
+        {
+            let channel = tonic::transport::Endpoint::from(opt.sentry_api_addr.addr.clone())
+                .connect_lazy()?;
+            let mut coordinator = Coordinator::new(
+                vec![martinez::sentry2::coordinator::SentryClient::new(channel)],
+                Arc::new(HeaderDownloader {
+                    bad_headers: Default::default(),
+                }),
+                Default::default(),
+                Some(chain_config.clone()),
+                chain_config
+                    .fork_block_numbers()
+                    .into_iter()
+                    .map(|number| number.0)
+                    .collect(),
+                chain_config.genesis_block_hash(),
+                chain_config.network_id().0,
+            );
+            let (blocks_tx, blocks_rx) = tokio::sync::mpsc::channel(16);
+            tasks.spawn(
+                "canonical blocks",
+                canonical_blocks(db.clone(), staged_sync.subscribe_chain_events(), blocks_tx),
+            );
+            tasks.spawn("sentry health checks", {
+                let coordinator = coordinator.clone();
+                async move { coordinator.run_health_checks().await }
+            });
+            tasks.spawn("block relay", async move {
+                coordinator.relay_canonical_blocks(blocks_rx).await
+            });
+        }
+
+        let mut header_download = HeaderDownload::new(
+            chain_config,
+            &opt.downloader_opts,
+            sentry.clone(),
+            sentry_status_provider,
+        )?;
+        header_download.set_slice_store(Arc::new(HeaderSliceStore::new(
+            opt.data_dir.header_slices_dir(),
+        )?));
+
+        // watch for the sync stalling behind the peers
+        if let Some(stall_timeout) = opt.downloader_opts.stall_timeout() {
+            let mut watchdog = ChainTipWatchdog::new(db.clone(), sentry, stall_timeout);
+            if opt.downloader_opts.restart_on_stall {
+                let restart_signal = Arc::new(tokio::sync::Notify::new());
+                header_download.set_restart_signal(restart_signal.clone());
+                watchdog.set_restart_signal(restart_signal);
+            }
+            tasks.spawn("chain tip watchdog", async move { watchdog.run().await });
+        }
+
+        staged_sync.push(header_download);
+    }
+    staged_sync.push(TotalGasIndex);
+    staged_sync.push(BlockHashes {
+        temp_dir: etl_temp_dir.clone(),
+    });
+    if let Some(erigon_db) = erigon_db {
+        staged_sync.push(ConvertBodies {
+            db: erigon_db,
+            commit_after: Duration::from_secs(120),
+        });
+    } else if let Some(body_download) = body_download {
+        staged_sync.push(body_download);
+    }
+    staged_sync.push(TotalTxIndex);
+    staged_sync.push(SenderRecovery {
+        batch_size: opt.sender_recovery_batch_size.try_into().unwrap(),
+    });
+    staged_sync.push(Execution {
+        batch_size: opt.execution_batch_size.saturating_mul(1_000_000_000_u64),
+        history_batch_size: opt
+            .execution_history_batch_size
+            .saturating_mul(1_000_000_000_u64),
+        exit_after_batch: opt.execution_exit_after_batch,
+        batch_until: None,
+        commit_every: None,
+        prune_from: BlockNumber(0),
+        call_trace_stats: opt.call_trace_stats,
+        header_cache: Some(header_cache),
+        code_cache: Some(Arc::new(CodeCache::new(CODE_CACHE_SIZE))),
+    });
+    if !opt.skip_commitment {
+        staged_sync.push(HashState::new(etl_temp_dir.clone(), None));
+        staged_sync.push(Interhashes::new(etl_temp_dir.clone(), None));
+    }
+    staged_sync.push(CallTraceIndex {
+        temp_dir: etl_temp_dir.clone(),
+        flush_interval: 50_000,
+    });
+    staged_sync.push(Prune {
+        config: PruneConfig {
+            history: opt.prune_history,
+            receipts: opt.prune_receipts,
+            tx_lookup: opt.prune_tx_lookup,
+            call_traces: opt.prune_call_traces,
+        },
+    });
+    staged_sync.push(FinishStage);
+
+    info!("Running staged sync");
+    tasks
+        .run_until(async { Ok(staged_sync.run(&db).await?) })
+        .await
+}
+
+#[allow(unreachable_code)]
+fn main() -> anyhow::Result<()> {
+    let mut opt: Opt = Opt::parse();
+
+    init_tracing("martinez=info", opt.log_json);
 
     std::thread::Builder::new()
         .stack_size(64 * 1024 * 1024)
@@ -562,11 +979,37 @@ fn main() -> anyhow::Result<()> {
             rt.block_on(async move {
                 info!("Starting Martinez ({})", version_string());
 
-                let chains_config = martinez::sentry::chain_config::ChainsConfig::new()?;
-                let chain_config = chains_config.get(&opt.chain_name)?;
+                let mut chain_config = if let Some(genesis_path) = &opt.genesis {
+                    let genesis = martinez::models::GethGenesis::from_json(
+                        &std::fs::read_to_string(genesis_path)
+                            .with_context(|| format!("failed to read {}", genesis_path.display()))?,
+                    )?;
+                    let name = genesis_path
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| "private".to_string());
+                    ChainConfig::new(genesis.into_chain_spec(name)?)
+                } else {
+                    let chains_config = martinez::sentry::chain_config::ChainsConfig::new()?;
+                    chains_config.get(&opt.chain_name)?
+                };
+                if !opt.spec_overrides.is_empty() {
+                    let mut chain_spec = chain_config.chain_spec().clone();
+                    opt.spec_overrides.apply(&mut chain_spec);
+                    chain_config = ChainConfig::new(chain_spec);
+                }
+                opt.data_dir = opt.data_dir.chain_dir(&chain_config.chain_name());
+
+                if opt.readonly {
+                    let lock = opt.data_dir.lock(AccessMode::Reader)?;
+                    if let Some(writer) = lock.writer() {
+                        info!("Attaching to datadir written by process {}", writer);
+                    }
+                    return run_readonly(opt).await;
+                }
 
                 // database setup
-                let erigon_db = if let Some(erigon_data_dir) = opt.erigon_data_dir {
+                let erigon_db = if let Some(erigon_data_dir) = &opt.erigon_data_dir {
                     let erigon_chain_data_dir = erigon_data_dir.join("chaindata");
                     let erigon_db = martinez::kv::mdbx::MdbxEnvironment::<mdbx::NoWriteMap>::open_ro(
                         mdbx::Environment::new(),
@@ -578,7 +1021,15 @@ fn main() -> anyhow::Result<()> {
                     None
                 };
 
-                std::fs::create_dir_all(&opt.data_dir.0)?;
+                let _lock = opt.data_dir.lock(AccessMode::Writer)?;
+                if let DataDirVersion::Outdated(version) = opt.data_dir.check_version()? {
+                    bail!(
+                        "datadir {} has layout version {}, while version {} is required: resync into a new datadir",
+                        opt.data_dir,
+                        version,
+                        DATADIR_VERSION
+                    );
+                }
                 let martinez_chain_data_dir = opt.data_dir.chain_data_dir();
                 let etl_temp_path = opt.data_dir.etl_temp_dir();
                 let _ = std::fs::remove_dir_all(&etl_temp_path);
@@ -587,89 +1038,19 @@ fn main() -> anyhow::Result<()> {
                     tempfile::tempdir_in(&etl_temp_path)
                         .context("failed to create ETL temp dir")?,
                 );
-                let db = martinez::kv::new_database(&martinez_chain_data_dir)?;
-                {
-                    let span = span!(Level::INFO, "", " Genesis initialization ");
-                    let _g = span.enter();
-                    let txn = db.begin_mutable()?;
-                    if martinez::genesis::initialize_genesis(
-                        &txn,
-                        &*etl_temp_dir,
-                        chain_config.chain_spec().clone(),
-                    )? {
-                        txn.commit()?;
-                    }
-                }
-
-                let sentry_status_provider = SentryStatusProvider::new(chain_config.clone());
-                // staged sync setup
-                let mut staged_sync = stagedsync::StagedSync::new();
-                staged_sync.set_min_progress_to_commit_after_stage(1024);
-                staged_sync.set_max_block(opt.max_block);
-                staged_sync.set_exit_after_sync(opt.exit_after_sync);
-                staged_sync.set_delay_after_sync(Some(Duration::from_millis(opt.delay_after_sync)));
-                if let Some(erigon_db) = erigon_db.clone() {
-                    staged_sync.push(ConvertHeaders {
-                        db: erigon_db,
-                        max_block: opt.max_block,
-                        exit_after_progress: opt.increment,
-                    });
-                } else {
-                    // sentry setup
-                    let mut sentry_reactor = SentryClientReactor::new(
-                        Box::new(SentryClientConnectorImpl::new(opt.sentry_api_addr.clone())),
-                        sentry_status_provider.current_status_stream(),
-                    );
-                    sentry_reactor.start()?;
-
-                    staged_sync.push(HeaderDownload::new(
-                        chain_config,
-                        opt.downloader_opts.headers_mem_limit(),
-                        opt.downloader_opts.headers_batch_size,
-                        sentry_reactor.into_shared(),
-                        sentry_status_provider,
-                    )?);
-                }
-                staged_sync.push(TotalGasIndex);
-                staged_sync.push(BlockHashes {
-                    temp_dir: etl_temp_dir.clone(),
-                });
-                if let Some(erigon_db) = erigon_db {
-                    staged_sync.push(ConvertBodies {
-                        db: erigon_db,
-                        commit_after: Duration::from_secs(120),
-                    });
+                if opt.db_opts.no_write_map {
+                    let db = martinez::kv::new_database_with_opts::<mdbx::NoWriteMap>(
+                        &martinez_chain_data_dir,
+                        &opt.db_opts,
+                    )?;
+                    run_node(opt, chain_config, erigon_db, etl_temp_dir, Arc::new(db)).await
                 } else {
-                    // also add body download stage here
-                }
-                staged_sync.push(TotalTxIndex);
-                staged_sync.push(SenderRecovery {
-                    batch_size: opt.sender_recovery_batch_size.try_into().unwrap(),
-                });
-                staged_sync.push(Execution {
-                    batch_size: opt.execution_batch_size.saturating_mul(1_000_000_000_u64),
-                    history_batch_size: opt
-                        .execution_history_batch_size
-                        .saturating_mul(1_000_000_000_u64),
-                    exit_after_batch: opt.execution_exit_after_batch,
-                    batch_until: None,
-                    commit_every: None,
-                    prune_from: BlockNumber(0),
-                });
-                if !opt.skip_commitment {
-                    staged_sync.push(HashState::new(etl_temp_dir.clone(), None));
-                    staged_sync.push(Interhashes::new(etl_temp_dir.clone(), None));
+                    let db = martinez::kv::new_database_with_opts::<mdbx::WriteMap>(
+                        &martinez_chain_data_dir,
+                        &opt.db_opts,
+                    )?;
+                    run_node(opt, chain_config, erigon_db, etl_temp_dir, Arc::new(db)).await
                 }
-                staged_sync.push(CallTraceIndex {
-                    temp_dir: etl_temp_dir.clone(),
-                    flush_interval: 50_000,
-                });
-                staged_sync.push(FinishStage);
-
-                info!("Running staged sync");
-                staged_sync.run(&db).await?;
-
-                Ok(())
             })
         })?
         .join()
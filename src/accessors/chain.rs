@@ -1,8 +1,14 @@
 use crate::{
-    kv::{mdbx::MdbxTransaction, tables},
+    kv::{
+        mdbx::MdbxTransaction,
+        tables::{self, ChainHeadEntry, HeaderKey},
+        traits::TryGenIter,
+    },
     models::*,
 };
+use anyhow::{ensure, format_err};
 use mdbx::{EnvironmentKind, TransactionKind, RW};
+use std::ops::RangeInclusive;
 use tracing::*;
 
 pub mod tx {
@@ -109,7 +115,7 @@ pub mod storage_body {
         let number = number.into();
         trace!("Reading storage body for block {}/{:?}", number, hash);
 
-        tx.get(tables::BlockBody, (number, hash))
+        Ok(tx.get(tables::BlockBody, (number, hash))?)
     }
 
     pub fn has<K, E>(
@@ -142,6 +148,67 @@ pub mod storage_body {
     }
 }
 
+pub mod ommers {
+    use super::*;
+
+    pub fn read<K, E>(
+        tx: &MdbxTransaction<'_, K, E>,
+        keys: &[HeaderKey],
+    ) -> anyhow::Result<Vec<BlockHeader>>
+    where
+        K: TransactionKind,
+        E: EnvironmentKind,
+    {
+        trace!("Reading {} ommers", keys.len());
+
+        keys.iter()
+            .map(|&(number, hash)| {
+                tx.get(tables::Header, (number, hash))?
+                    .ok_or_else(|| format_err!("ommer header {}/{:?} not found", number, hash))
+            })
+            .collect()
+    }
+
+    /// Stores ommer headers unless already known and returns the keys to reference them by.
+    pub fn write<E>(
+        tx: &MdbxTransaction<'_, RW, E>,
+        ommers: &[BlockHeader],
+    ) -> anyhow::Result<Vec<HeaderKey>>
+    where
+        E: EnvironmentKind,
+    {
+        trace!("Writing {} ommers", ommers.len());
+
+        ommers
+            .iter()
+            .map(|ommer| {
+                let key = (ommer.number, ommer.hash());
+                if tx.get(tables::Header, key)?.is_none() {
+                    tx.set(tables::Header, key, ommer.clone())?;
+                }
+                Ok(key)
+            })
+            .collect()
+    }
+
+    /// Removes the ommer headers stored by [`write`]. Headers saved by the header download are
+    /// indexed by hash, those stay.
+    pub fn delete<E>(tx: &MdbxTransaction<'_, RW, E>, keys: &[HeaderKey]) -> anyhow::Result<()>
+    where
+        E: EnvironmentKind,
+    {
+        trace!("Deleting {} ommers", keys.len());
+
+        for &(number, hash) in keys {
+            if tx.get(tables::HeaderNumber, hash)?.is_none() {
+                tx.del(tables::Header, (number, hash), None)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 pub mod block_body {
     use super::*;
 
@@ -157,10 +224,12 @@ pub mod block_body {
         if let Some(body) = super::storage_body::read(tx, hash, number)? {
             let transactions = super::tx::read(tx, body.base_tx_id, body.tx_amount.try_into()?)?;
 
+            let ommers = super::ommers::read(tx, &body.uncles)?;
+
             return Ok(Some((
                 BlockBody {
                     transactions,
-                    ommers: body.uncles,
+                    ommers,
                 },
                 body.base_tx_id,
             )));
@@ -204,6 +273,53 @@ pub mod block_body {
     }
 }
 
+pub mod receipts {
+    use super::*;
+
+    /// Receipts persisted at execution, `None` if the block was executed without storing them.
+    pub fn read<K: TransactionKind, E: EnvironmentKind>(
+        tx: &MdbxTransaction<'_, K, E>,
+        hash: H256,
+        number: impl Into<BlockNumber>,
+    ) -> anyhow::Result<Option<Vec<Receipt>>> {
+        let number = number.into();
+        trace!("Reading receipts for block {}/{:?}", number, hash);
+
+        let Some(stored) = tx.get(tables::Receipt, number)? else {
+            return Ok(None);
+        };
+        let body = super::block_body::read_without_senders(tx, hash, number)?
+            .ok_or_else(|| format_err!("block body {}/{:?} not found", number, hash))?;
+        ensure!(
+            stored.len() == body.transactions.len(),
+            "{} receipts stored for block {} with {} transactions",
+            stored.len(),
+            number,
+            body.transactions.len()
+        );
+
+        let mut log_cursor = tx.cursor(tables::Log)?;
+        stored
+            .into_iter()
+            .zip(body.transactions)
+            .enumerate()
+            .map(|(index, (receipt, transaction))| {
+                let logs = log_cursor
+                    .seek_exact((number, TxIndex(index as u64)))?
+                    .map(|(_, logs)| logs)
+                    .unwrap_or_default();
+                Ok(Receipt::new(
+                    transaction.tx_type(),
+                    receipt.success,
+                    receipt.cumulative_gas_used,
+                    logs,
+                ))
+            })
+            .collect::<anyhow::Result<_>>()
+            .map(Some)
+    }
+}
+
 pub mod td {
     use super::*;
 
@@ -215,7 +331,7 @@ pub mod td {
         let number = number.into();
         trace!("Reading total difficulty at block {}/{:?}", number, hash);
 
-        tx.get(tables::HeadersTotalDifficulty, (number, hash))
+        Ok(tx.get(tables::HeadersTotalDifficulty, (number, hash))?)
     }
 }
 
@@ -250,6 +366,149 @@ pub mod tl {
     }
 }
 
+pub mod chain_head {
+    use super::*;
+
+    /// Depth below the tip beyond which the headers downloader never follows a reorg.
+    pub const FINALITY_DEPTH: u64 = 64;
+
+    pub fn read<K: TransactionKind, E: EnvironmentKind>(
+        tx: &MdbxTransaction<'_, K, E>,
+    ) -> anyhow::Result<Option<ChainHeadEntry>> {
+        trace!("Reading chain head");
+
+        Ok(tx.get(tables::ChainHead, Default::default())?)
+    }
+
+    pub fn write<E: EnvironmentKind>(
+        tx: &MdbxTransaction<'_, RW, E>,
+        head: ChainHeadEntry,
+    ) -> anyhow::Result<()> {
+        trace!("Writing chain head {:?}", head);
+
+        tx.set(tables::ChainHead, Default::default(), head)?;
+
+        Ok(())
+    }
+
+    /// Moves the latest block pointer. Safe and finalized blocks above the new head are dropped.
+    pub fn set_latest<E: EnvironmentKind>(
+        tx: &MdbxTransaction<'_, RW, E>,
+        latest: HeaderKey,
+    ) -> anyhow::Result<()> {
+        let mut head = read(tx)?.unwrap_or_default();
+        head.latest = latest;
+        for block in [&mut head.safe, &mut head.finalized] {
+            if matches!(block, Some((number, _)) if *number > latest.0) {
+                *block = None;
+            }
+        }
+
+        write(tx, head)
+    }
+
+    /// Sets safe and finalized blocks, which must not be above the latest one.
+    pub fn set_safe_and_finalized<E: EnvironmentKind>(
+        tx: &MdbxTransaction<'_, RW, E>,
+        safe: Option<HeaderKey>,
+        finalized: Option<HeaderKey>,
+    ) -> anyhow::Result<()> {
+        let head = read(tx)?.ok_or_else(|| format_err!("chain head not set"))?;
+        let safe_number = safe.map(|(number, _)| number);
+        let finalized_number = finalized.map(|(number, _)| number);
+        ensure!(
+            safe_number <= Some(head.latest.0)
+                && finalized_number <= safe_number.or(Some(head.latest.0)),
+            "invalid fork choice: latest {}, safe {:?}, finalized {:?}",
+            head.latest.0,
+            safe_number,
+            finalized_number
+        );
+
+        write(
+            tx,
+            ChainHeadEntry {
+                safe,
+                finalized,
+                ..head
+            },
+        )
+    }
+
+    /// Moves the head to the executed canonical block `number`. Blocks [`FINALITY_DEPTH`] below
+    /// it become safe and finalized.
+    pub fn set_executed<E: EnvironmentKind>(
+        tx: &MdbxTransaction<'_, RW, E>,
+        number: BlockNumber,
+    ) -> anyhow::Result<()> {
+        let hash = tx
+            .get(tables::CanonicalHeader, number)?
+            .ok_or_else(|| format_err!("no canonical hash for executed block {}", number))?;
+        set_latest(tx, (number, hash))?;
+
+        let finalized_number = BlockNumber(number.0.saturating_sub(FINALITY_DEPTH));
+        let finalized = tx
+            .get(tables::CanonicalHeader, finalized_number)?
+            .map(|hash| (finalized_number, hash));
+
+        set_safe_and_finalized(tx, finalized, finalized)
+    }
+}
+
+/// Walks canonical block hashes in `range`. Fails on the first block missing from the canonical chain.
+pub fn canonical_blocks<'tx, K: TransactionKind, E: EnvironmentKind>(
+    tx: &'tx MdbxTransaction<'_, K, E>,
+    range: RangeInclusive<BlockNumber>,
+) -> anyhow::Result<impl Iterator<Item = anyhow::Result<(BlockNumber, H256)>> + 'tx> {
+    trace!("Walking canonical blocks {:?}", range);
+
+    let mut walker = tx
+        .cursor(tables::CanonicalHeader)?
+        .walk(Some(*range.start()));
+
+    Ok(TryGenIter::from(move |_| {
+        for block_number in range {
+            match walker.next().transpose()? {
+                Some((number, hash)) if number == block_number => yield (number, hash),
+                _ => return Err(format_err!("no canonical hash for block {}", block_number)),
+            }
+        }
+
+        Ok(())
+    }))
+}
+
+/// Same as [`canonical_blocks`], but yields block headers.
+pub fn canonical_headers<'tx, K: TransactionKind, E: EnvironmentKind>(
+    tx: &'tx MdbxTransaction<'_, K, E>,
+    range: RangeInclusive<BlockNumber>,
+) -> anyhow::Result<impl Iterator<Item = anyhow::Result<(H256, BlockHeader)>> + 'tx> {
+    Ok(canonical_blocks(tx, range)?.map(move |res| {
+        let (number, hash) = res?;
+        let header = tx
+            .get(tables::Header, (number, hash))?
+            .ok_or_else(|| format_err!("header {}/{:?} not found", number, hash))?;
+
+        Ok((hash, header))
+    }))
+}
+
+/// Same as [`canonical_blocks`], but yields full blocks with transaction senders.
+pub fn canonical_blocks_with_senders<'tx, K: TransactionKind, E: EnvironmentKind>(
+    tx: &'tx MdbxTransaction<'_, K, E>,
+    range: RangeInclusive<BlockNumber>,
+) -> anyhow::Result<
+    impl Iterator<Item = anyhow::Result<(H256, BlockHeader, BlockBodyWithSenders)>> + 'tx,
+> {
+    Ok(canonical_headers(tx, range)?.map(move |res| {
+        let (hash, header) = res?;
+        let body = block_body::read_with_senders(tx, hash, header.number)?
+            .ok_or_else(|| format_err!("block body {}/{:?} not found", header.number, hash))?;
+
+        Ok((hash, header, body))
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -290,17 +549,23 @@ mod tests {
         let sender2 = Address::random();
         let senders = [sender1, sender2];
 
-        let block1_hash = H256::random();
-        let body = BodyForStorage {
-            base_tx_id: 1.into(),
-            tx_amount: 2,
-            uncles: vec![],
+        let ommer = BlockHeader {
+            number: 0.into(),
+            gas_limit: 5000,
+            ..BlockHeader::empty()
         };
 
         let db = new_mem_database().unwrap();
         let rwtx = db.begin_mutable().unwrap();
         let rwtx = &rwtx;
 
+        let block1_hash = H256::random();
+        let body = BodyForStorage {
+            base_tx_id: 1.into(),
+            tx_amount: 2,
+            uncles: ommers::write(rwtx, &[ommer.clone()]).unwrap(),
+        };
+
         storage_body::write(rwtx, block1_hash, 1, &body).unwrap();
         rwtx.set(tables::CanonicalHeader, 1.into(), block1_hash)
             .unwrap();
@@ -321,5 +586,203 @@ mod tests {
         assert_eq!(block1_hash, recovered_hash);
         assert_eq!(txs, *recovered_txs);
         assert_eq!(senders, *recovered_senders);
+
+        let recovered_block = block_body::read_with_senders(rwtx, block1_hash, 1)
+            .unwrap()
+            .expect("Could not recover block body.");
+        assert_eq!(recovered_block.ommers, vec![ommer]);
+
+        assert_eq!(receipts::read(rwtx, block1_hash, 1).unwrap(), None);
+
+        let log = Log {
+            address: Address::random(),
+            topics: vec![H256::random()],
+            data: Bytes::from_static(b"data"),
+        };
+        rwtx.set(
+            tables::Receipt,
+            1.into(),
+            vec![
+                tables::StoredReceipt {
+                    success: true,
+                    cumulative_gas_used: 21_000,
+                },
+                tables::StoredReceipt {
+                    success: false,
+                    cumulative_gas_used: 50_000,
+                },
+            ],
+        )
+        .unwrap();
+        rwtx.set(tables::Log, (1.into(), 1.into()), vec![log.clone()])
+            .unwrap();
+        assert_eq!(
+            receipts::read(rwtx, block1_hash, 1).unwrap(),
+            Some(vec![
+                Receipt::new(TxType::Legacy, true, 21_000, vec![]),
+                Receipt::new(TxType::Legacy, false, 50_000, vec![log]),
+            ])
+        );
+    }
+
+    #[test]
+    fn delete_ommers() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let ommer = BlockHeader {
+            number: 1.into(),
+            ..BlockHeader::empty()
+        };
+        let downloaded = BlockHeader {
+            number: 2.into(),
+            ..BlockHeader::empty()
+        };
+        tx.set(
+            tables::Header,
+            (downloaded.number, downloaded.hash()),
+            downloaded.clone(),
+        )
+        .unwrap();
+        tx.set(tables::HeaderNumber, downloaded.hash(), downloaded.number)
+            .unwrap();
+
+        let keys = ommers::write(&tx, &[ommer, downloaded]).unwrap();
+        assert_eq!(ommers::read(&tx, &keys).unwrap().len(), 2);
+
+        ommers::delete(&tx, &keys).unwrap();
+        assert_eq!(tx.get(tables::Header, keys[0]).unwrap(), None);
+        assert!(tx.get(tables::Header, keys[1]).unwrap().is_some());
+    }
+
+    #[test]
+    fn canonical_iterators() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let mut headers = vec![];
+        for number in 0..5 {
+            let header = BlockHeader {
+                number: number.into(),
+                ..BlockHeader::empty()
+            };
+            let hash = header.hash();
+            tx.set(tables::CanonicalHeader, number.into(), hash)
+                .unwrap();
+            tx.set(tables::Header, (number.into(), hash), header.clone())
+                .unwrap();
+            storage_body::write(
+                &tx,
+                hash,
+                number,
+                &BodyForStorage {
+                    base_tx_id: 0.into(),
+                    tx_amount: 0,
+                    uncles: vec![],
+                },
+            )
+            .unwrap();
+            headers.push((hash, header));
+        }
+
+        assert_eq!(
+            canonical_blocks(&tx, 1.into()..=3.into())
+                .unwrap()
+                .collect::<anyhow::Result<Vec<_>>>()
+                .unwrap(),
+            headers[1..=3]
+                .iter()
+                .map(|(hash, header)| (header.number, *hash))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            canonical_headers(&tx, 0.into()..=4.into())
+                .unwrap()
+                .collect::<anyhow::Result<Vec<_>>>()
+                .unwrap(),
+            headers
+        );
+        assert_eq!(
+            canonical_blocks_with_senders(&tx, 2.into()..=2.into())
+                .unwrap()
+                .map(|res| res.unwrap().0)
+                .collect::<Vec<_>>(),
+            vec![headers[2].0]
+        );
+        assert_eq!(
+            canonical_blocks(&tx, 3.into()..=2.into()).unwrap().count(),
+            0
+        );
+
+        tx.del(tables::CanonicalHeader, 2.into(), None).unwrap();
+        let mut walker = canonical_blocks(&tx, 0.into()..=4.into()).unwrap();
+        assert!(walker.next().unwrap().is_ok());
+        assert!(walker.next().unwrap().is_ok());
+        assert!(walker.next().unwrap().is_err());
+        assert!(walker.next().is_none());
+
+        assert!(canonical_blocks(&tx, 3.into()..=5.into())
+            .unwrap()
+            .collect::<anyhow::Result<Vec<_>>>()
+            .is_err());
+    }
+
+    #[test]
+    fn chain_head() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let block = |number: u64| (BlockNumber(number), H256::from_low_u64_be(number));
+
+        assert_eq!(chain_head::read(&tx).unwrap(), None);
+        assert!(chain_head::set_safe_and_finalized(&tx, None, None).is_err());
+
+        chain_head::set_latest(&tx, block(10)).unwrap();
+        chain_head::set_safe_and_finalized(&tx, Some(block(8)), Some(block(5))).unwrap();
+        assert!(chain_head::set_safe_and_finalized(&tx, Some(block(11)), None).is_err());
+        assert!(chain_head::set_safe_and_finalized(&tx, Some(block(5)), Some(block(8))).is_err());
+        assert_eq!(
+            chain_head::read(&tx).unwrap(),
+            Some(ChainHeadEntry {
+                latest: block(10),
+                safe: Some(block(8)),
+                finalized: Some(block(5)),
+            })
+        );
+
+        chain_head::set_latest(&tx, block(6)).unwrap();
+        assert_eq!(
+            chain_head::read(&tx).unwrap(),
+            Some(ChainHeadEntry {
+                latest: block(6),
+                safe: None,
+                finalized: Some(block(5)),
+            })
+        );
+
+        assert!(chain_head::set_executed(&tx, BlockNumber(100)).is_err());
+        for number in [0, 36, 40, 100] {
+            tx.set(tables::CanonicalHeader, block(number).0, block(number).1)
+                .unwrap();
+        }
+        chain_head::set_executed(&tx, BlockNumber(100)).unwrap();
+        assert_eq!(
+            chain_head::read(&tx).unwrap(),
+            Some(ChainHeadEntry {
+                latest: block(100),
+                safe: Some(block(36)),
+                finalized: Some(block(36)),
+            })
+        );
+
+        chain_head::set_executed(&tx, BlockNumber(40)).unwrap();
+        assert_eq!(
+            chain_head::read(&tx).unwrap(),
+            Some(ChainHeadEntry {
+                latest: block(40),
+                safe: Some(block(0)),
+                finalized: Some(block(0)),
+            })
+        );
     }
 }
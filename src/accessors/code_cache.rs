@@ -0,0 +1,170 @@
+use super::header_cache::{hit_rate, Counters};
+use crate::{
+    kv::{mdbx::MdbxTransaction, tables},
+    models::*,
+};
+use bytes::Bytes;
+use lru::LruCache;
+use mdbx::{EnvironmentKind, TransactionKind};
+use parking_lot::Mutex;
+use std::fmt;
+
+/// Lookups served by [`CodeCache`] since its creation, and its current size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CodeCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+impl CodeCacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        hit_rate(self.hits, self.misses)
+    }
+}
+
+impl fmt::Display for CodeCacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.1}% of {}, {} contracts taking {}",
+            self.hit_rate() * 100.0,
+            self.hits + self.misses,
+            self.entries,
+            bytesize::ByteSize::b(self.bytes as u64)
+        )
+    }
+}
+
+#[derive(Debug)]
+struct Codes {
+    codes: LruCache<H256, Bytes>,
+    bytes: usize,
+}
+
+/// LRU cache in front of `Code` reads, shared between the users of one database and bounded
+/// by the total size of the cached code.
+///
+/// Code is looked up by its hash, so entries never go stale. Frequently called contracts are
+/// then neither read from the database nor decompressed again for every block.
+#[derive(Debug)]
+pub struct CodeCache {
+    codes: Mutex<Codes>,
+    max_bytes: usize,
+    counters: Counters,
+}
+
+impl CodeCache {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            codes: Mutex::new(Codes {
+                codes: LruCache::unbounded(),
+                bytes: 0,
+            }),
+            max_bytes,
+            counters: Default::default(),
+        }
+    }
+
+    pub fn code<K: TransactionKind, E: EnvironmentKind>(
+        &self,
+        tx: &MdbxTransaction<'_, K, E>,
+        code_hash: H256,
+    ) -> anyhow::Result<Option<Bytes>> {
+        let cached = self.codes.lock().codes.get(&code_hash).cloned();
+        self.counters.record(cached.is_some());
+        if cached.is_some() {
+            return Ok(cached);
+        }
+
+        let code = tx.get(tables::Code, code_hash)?.map(Bytes::from);
+        if let Some(code) = &code {
+            self.insert(code_hash, code.clone());
+        }
+
+        Ok(code)
+    }
+
+    fn insert(&self, code_hash: H256, code: Bytes) {
+        if code.len() > self.max_bytes {
+            return;
+        }
+
+        let mut codes = self.codes.lock();
+        codes.bytes += code.len();
+        if let Some(replaced) = codes.codes.put(code_hash, code) {
+            codes.bytes -= replaced.len();
+        }
+        while codes.bytes > self.max_bytes {
+            let Some((_, evicted)) = codes.codes.pop_lru() else {
+                break;
+            };
+            codes.bytes -= evicted.len();
+        }
+    }
+
+    pub fn stats(&self) -> CodeCacheStats {
+        let (hits, misses) = self.counters.get();
+        let codes = self.codes.lock();
+        CodeCacheStats {
+            hits,
+            misses,
+            entries: codes.codes.len(),
+            bytes: codes.bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv::new_mem_database;
+
+    #[test]
+    fn bounded_by_bytes() {
+        let db = new_mem_database().unwrap();
+        let cache = CodeCache::new(8);
+
+        let tx = db.begin_mutable().unwrap();
+        let codes = [
+            (H256::repeat_byte(1), Bytes::from_static(&[1; 4])),
+            (H256::repeat_byte(2), Bytes::from_static(&[2; 4])),
+            (H256::repeat_byte(3), Bytes::from_static(&[3; 4])),
+            (H256::repeat_byte(4), Bytes::from_static(&[4; 16])),
+        ];
+        for (code_hash, code) in &codes {
+            tx.set(tables::Code, *code_hash, code.clone().into())
+                .unwrap();
+        }
+
+        for (code_hash, code) in &codes[..3] {
+            assert_eq!(cache.code(&tx, *code_hash).unwrap().as_ref(), Some(code));
+        }
+        // code larger than the cache is not kept
+        assert_eq!(
+            cache.code(&tx, codes[3].0).unwrap().as_ref(),
+            Some(&codes[3].1)
+        );
+        assert_eq!(cache.code(&tx, H256::repeat_byte(5)).unwrap(), None);
+
+        // the least recently used code was evicted
+        assert_eq!(
+            cache.code(&tx, codes[2].0).unwrap().as_ref(),
+            Some(&codes[2].1)
+        );
+        assert_eq!(
+            cache.code(&tx, codes[0].0).unwrap().as_ref(),
+            Some(&codes[0].1)
+        );
+        assert_eq!(
+            cache.stats(),
+            CodeCacheStats {
+                hits: 1,
+                misses: 6,
+                entries: 2,
+                bytes: 8,
+            }
+        );
+    }
+}
@@ -0,0 +1,288 @@
+use crate::{
+    kv::{mdbx::MdbxTransaction, tables},
+    models::*,
+    stagedsync::ChainEvent,
+};
+use lru::LruCache;
+use mdbx::{EnvironmentKind, TransactionKind, RO};
+use parking_lot::Mutex;
+use std::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::*;
+
+const STATS_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl Counters {
+    pub(crate) fn record(&self, hit: bool) {
+        if hit {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn get(&self) -> (u64, u64) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// Lookups served by [`HeaderCache`] since its creation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HeaderCacheStats {
+    pub header_hits: u64,
+    pub header_misses: u64,
+    pub canonical_hits: u64,
+    pub canonical_misses: u64,
+}
+
+pub(crate) fn hit_rate(hits: u64, misses: u64) -> f64 {
+    if hits + misses == 0 {
+        0.0
+    } else {
+        hits as f64 / (hits + misses) as f64
+    }
+}
+
+impl HeaderCacheStats {
+    pub fn header_hit_rate(&self) -> f64 {
+        hit_rate(self.header_hits, self.header_misses)
+    }
+
+    pub fn canonical_hit_rate(&self) -> f64 {
+        hit_rate(self.canonical_hits, self.canonical_misses)
+    }
+}
+
+impl fmt::Display for HeaderCacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "headers {:.1}% of {}, canonical hashes {:.1}% of {}",
+            self.header_hit_rate() * 100.0,
+            self.header_hits + self.header_misses,
+            self.canonical_hit_rate() * 100.0,
+            self.canonical_hits + self.canonical_misses,
+        )
+    }
+}
+
+#[derive(Debug)]
+struct CanonicalHashes {
+    hashes: LruCache<BlockNumber, H256>,
+    /// Read transactions older than that may still see unwound hashes.
+    min_txn_id: u64,
+}
+
+/// LRU caches in front of `Header` and `CanonicalHeader` reads, shared between the users of
+/// one database.
+///
+/// Headers are looked up by hash, so entries never go stale. Canonical hashes change on
+/// reorgs and are only cached from read transactions, dropping those above the unwind
+/// point on [`ChainEvent::Unwind`].
+#[derive(Debug)]
+pub struct HeaderCache {
+    headers: Mutex<LruCache<(BlockNumber, H256), BlockHeader>>,
+    canonical: Mutex<CanonicalHashes>,
+    header_counters: Counters,
+    canonical_counters: Counters,
+}
+
+impl HeaderCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            headers: Mutex::new(LruCache::new(capacity)),
+            canonical: Mutex::new(CanonicalHashes {
+                hashes: LruCache::new(capacity),
+                min_txn_id: 0,
+            }),
+            header_counters: Default::default(),
+            canonical_counters: Default::default(),
+        }
+    }
+
+    pub fn header<K: TransactionKind, E: EnvironmentKind>(
+        &self,
+        tx: &MdbxTransaction<'_, K, E>,
+        number: BlockNumber,
+        hash: H256,
+    ) -> anyhow::Result<Option<BlockHeader>> {
+        let cached = self.headers.lock().get(&(number, hash)).cloned();
+        self.header_counters.record(cached.is_some());
+        if cached.is_some() {
+            return Ok(cached);
+        }
+
+        let header = tx.get(tables::Header, (number, hash))?;
+        if let Some(header) = &header {
+            self.headers.lock().put((number, hash), header.clone());
+        }
+
+        Ok(header)
+    }
+
+    pub fn canonical_hash<E: EnvironmentKind>(
+        &self,
+        tx: &MdbxTransaction<'_, RO, E>,
+        number: BlockNumber,
+    ) -> anyhow::Result<Option<H256>> {
+        let cached = self.canonical.lock().hashes.get(&number).copied();
+        self.canonical_counters.record(cached.is_some());
+        if cached.is_some() {
+            return Ok(cached);
+        }
+
+        let hash = tx.get(tables::CanonicalHeader, number)?;
+        if let Some(hash) = hash {
+            let mut canonical = self.canonical.lock();
+            if tx.id() >= canonical.min_txn_id {
+                canonical.hashes.put(number, hash);
+            }
+        }
+
+        Ok(hash)
+    }
+
+    pub fn canonical_header<E: EnvironmentKind>(
+        &self,
+        tx: &MdbxTransaction<'_, RO, E>,
+        number: BlockNumber,
+    ) -> anyhow::Result<Option<BlockHeader>> {
+        let Some(hash) = self.canonical_hash(tx, number)? else {
+            return Ok(None);
+        };
+        self.header(tx, number, hash)
+    }
+
+    /// Forgets canonical hashes after `to`, and does not cache hashes read by transactions
+    /// older than `txn_id`.
+    pub fn unwind(&self, to: BlockNumber, txn_id: u64) {
+        let mut canonical = self.canonical.lock();
+        let unwound = canonical
+            .hashes
+            .iter()
+            .map(|(&number, _)| number)
+            .filter(|&number| number > to)
+            .collect::<Vec<_>>();
+        for number in unwound {
+            canonical.hashes.pop(&number);
+        }
+        canonical.min_txn_id = canonical.min_txn_id.max(txn_id);
+    }
+
+    pub fn stats(&self) -> HeaderCacheStats {
+        let (header_hits, header_misses) = self.header_counters.get();
+        let (canonical_hits, canonical_misses) = self.canonical_counters.get();
+        HeaderCacheStats {
+            header_hits,
+            header_misses,
+            canonical_hits,
+            canonical_misses,
+        }
+    }
+
+    /// Follows the changes of the canonical chain and periodically logs the hit rates, until
+    /// the sender goes away.
+    pub async fn run(&self, mut events: broadcast::Receiver<ChainEvent>) {
+        let mut interval = tokio::time::interval(STATS_INTERVAL);
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(ChainEvent::Unwind { to, txn_id }) => self.unwind(to, txn_id),
+                    Ok(ChainEvent::Head { .. }) => {}
+                    Err(RecvError::Lagged(skipped)) => {
+                        // Unwind points are lost, start over.
+                        warn!("Header cache missed {} chain events", skipped);
+                        self.canonical.lock().hashes.clear();
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                _ = interval.tick() => {
+                    debug!("Header cache hit rates: {}", self.stats());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv::new_mem_database;
+
+    #[test]
+    fn canonical_unwind() {
+        let db = new_mem_database().unwrap();
+        let cache = HeaderCache::new(16);
+
+        let tx = db.begin_mutable().unwrap();
+        for n in 1..=3 {
+            let header = BlockHeader {
+                number: BlockNumber(n),
+                ..BlockHeader::empty()
+            };
+            tx.set(tables::CanonicalHeader, BlockNumber(n), header.hash())
+                .unwrap();
+            tx.set(tables::Header, (BlockNumber(n), header.hash()), header)
+                .unwrap();
+        }
+        tx.commit().unwrap();
+
+        let old_tx = db.begin().unwrap();
+        for n in 1..=3 {
+            assert!(cache
+                .canonical_header(&old_tx, BlockNumber(n))
+                .unwrap()
+                .is_some());
+        }
+        assert!(cache
+            .canonical_header(&old_tx, BlockNumber(3))
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            cache.stats(),
+            HeaderCacheStats {
+                header_hits: 1,
+                header_misses: 3,
+                canonical_hits: 1,
+                canonical_misses: 3,
+            }
+        );
+
+        let tx = db.begin_mutable().unwrap();
+        tx.del(tables::CanonicalHeader, BlockNumber(3), None)
+            .unwrap();
+        let txn_id = tx.id();
+        tx.commit().unwrap();
+        cache.unwind(BlockNumber(2), txn_id);
+
+        // Transaction from before the unwind does not bring the hash back.
+        assert!(cache
+            .canonical_hash(&old_tx, BlockNumber(3))
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            cache
+                .canonical_hash(&db.begin().unwrap(), BlockNumber(3))
+                .unwrap(),
+            None
+        );
+        assert!(cache
+            .canonical_hash(&old_tx, BlockNumber(2))
+            .unwrap()
+            .is_some());
+        assert_eq!(cache.stats().canonical_hits, 2);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{
+    bitmapdb,
+    kv::{mdbx::MdbxTransaction, tables},
+    models::*,
+};
+use croaring::Treemap as RoaringTreemap;
+use mdbx::{EnvironmentKind, TransactionKind};
+use std::ops::RangeInclusive;
+
+/// Blocks within `range` where `address` changed state, made or received a call, or emitted
+/// a log, in ascending order.
+pub fn address_activity<K: TransactionKind, E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, K, E>,
+    address: Address,
+    range: RangeInclusive<BlockNumber>,
+) -> anyhow::Result<Vec<BlockNumber>> {
+    let mut blocks = RoaringTreemap::create();
+    blocks |= bitmapdb::get(tx, tables::AccountHistory, address, range.clone())?;
+    blocks |= bitmapdb::get(tx, tables::CallFromIndex, address, range.clone())?;
+    blocks |= bitmapdb::get(tx, tables::CallToIndex, address, range.clone())?;
+    blocks |= bitmapdb::get(tx, tables::LogAddressIndex, address, range.clone())?;
+
+    // Chunks may extend past the range on both sides.
+    Ok(blocks
+        .iter()
+        .map(BlockNumber)
+        .skip_while(|block| block < range.start())
+        .take_while(|block| block <= range.end())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv::{new_mem_database, tables::BitmapKey};
+
+    fn bitmap(blocks: &[u64]) -> RoaringTreemap {
+        let mut bm = RoaringTreemap::create();
+        for &block in blocks {
+            bm.add(block);
+        }
+        bm
+    }
+
+    #[test]
+    fn merged_indices() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let address = Address::repeat_byte(1);
+        let other = Address::repeat_byte(2);
+        let key = |block_number| BitmapKey {
+            inner: address,
+            block_number: BlockNumber(block_number),
+        };
+
+        tx.set(tables::AccountHistory, key(u64::MAX), bitmap(&[3, 20]))
+            .unwrap();
+        tx.set(tables::CallFromIndex, key(5), bitmap(&[1, 5]))
+            .unwrap();
+        tx.set(tables::CallFromIndex, key(u64::MAX), bitmap(&[12]))
+            .unwrap();
+        tx.set(tables::CallToIndex, key(u64::MAX), bitmap(&[5, 8]))
+            .unwrap();
+        tx.set(tables::LogAddressIndex, key(u64::MAX), bitmap(&[8, 15]))
+            .unwrap();
+        tx.set(
+            tables::CallToIndex,
+            BitmapKey {
+                inner: other,
+                block_number: BlockNumber(u64::MAX),
+            },
+            bitmap(&[4]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            address_activity(&tx, address, BlockNumber(0)..=BlockNumber(u64::MAX)).unwrap(),
+            [1, 3, 5, 8, 12, 15, 20].map(BlockNumber)
+        );
+        assert_eq!(
+            address_activity(&tx, address, BlockNumber(4)..=BlockNumber(15)).unwrap(),
+            [5, 8, 12, 15].map(BlockNumber)
+        );
+        assert_eq!(
+            address_activity(&tx, other, BlockNumber(0)..=BlockNumber(3)).unwrap(),
+            []
+        );
+    }
+}
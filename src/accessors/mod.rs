@@ -1,2 +1,5 @@
 pub mod chain;
+pub mod code_cache;
+pub mod header_cache;
+pub mod history;
 pub mod state;
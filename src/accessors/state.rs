@@ -1,4 +1,5 @@
 use crate::{
+    changeset,
     kv::{mdbx::MdbxTransaction, tables, traits::*},
     models::*,
 };
@@ -13,24 +14,12 @@ pub mod account {
         block_number: Option<BlockNumber>,
     ) -> anyhow::Result<Option<Account>> {
         if let Some(block_number) = block_number {
-            if let Some(block_number) = super::history_index::find_next_block(
-                tx,
-                tables::AccountHistory,
-                address_to_find,
-                block_number,
-            )? {
-                if let Some(tables::AccountChange { address, account }) = tx
-                    .cursor(tables::AccountChangeSet)?
-                    .seek_both_range(block_number, address_to_find)?
-                {
-                    if address == address_to_find {
-                        return Ok(account);
-                    }
-                }
+            if let Some(account) = changeset::find_account_at(tx, block_number, address_to_find)? {
+                return Ok(account);
             }
         }
 
-        tx.get(tables::Account, address_to_find)
+        Ok(tx.get(tables::Account, address_to_find)?)
     }
 }
 
@@ -46,25 +35,10 @@ pub mod storage {
     ) -> anyhow::Result<U256> {
         let location_to_find = u256_to_h256(location_to_find);
         if let Some(block_number) = block_number {
-            if let Some(block_number) = super::history_index::find_next_block(
-                tx,
-                tables::StorageHistory,
-                (address, location_to_find),
-                block_number,
-            )? {
-                if let Some(tables::StorageChange { location, value }) =
-                    tx.cursor(tables::StorageChangeSet)?.seek_both_range(
-                        tables::StorageChangeKey {
-                            block_number,
-                            address,
-                        },
-                        location_to_find,
-                    )?
-                {
-                    if location == location_to_find {
-                        return Ok(value);
-                    }
-                }
+            if let Some(value) =
+                changeset::find_storage_at(tx, block_number, address, location_to_find)?
+            {
+                return Ok(value);
             }
         }
 
@@ -95,10 +69,15 @@ pub mod history_index {
         TK: TransactionKind,
         E: EnvironmentKind,
     {
+        // Chunks are keyed by their last block, so the first chunk ending after
+        // `block_number` is the one that holds the next change.
+        let Some(next_block) = block_number.0.checked_add(1) else {
+            return Ok(None);
+        };
         let mut ch = tx.cursor(table)?;
         if let Some((index_key, change_blocks)) = ch.seek(BitmapKey {
             inner: needle,
-            block_number,
+            block_number: BlockNumber(next_block),
         })? {
             if index_key.inner == needle {
                 return Ok(change_blocks
@@ -112,6 +91,101 @@ pub mod history_index {
     }
 }
 
+pub mod diff {
+    use super::*;
+    use crate::h256_to_u256;
+    use std::collections::BTreeMap;
+
+    /// Value of a state entry at both ends of a block range.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Change<T> {
+        pub before: T,
+        pub after: T,
+    }
+
+    /// Accounts and storage slots whose values differ between two block heights.
+    #[derive(Clone, Debug, Default, PartialEq)]
+    pub struct StateDiff {
+        pub accounts: BTreeMap<Address, Change<Option<Account>>>,
+        pub storage: BTreeMap<Address, BTreeMap<H256, Change<U256>>>,
+    }
+
+    impl StateDiff {
+        pub fn created(&self) -> impl Iterator<Item = Address> + '_ {
+            self.accounts
+                .iter()
+                .filter(|(_, change)| change.before.is_none() && change.after.is_some())
+                .map(|(&address, _)| address)
+        }
+
+        pub fn deleted(&self) -> impl Iterator<Item = Address> + '_ {
+            self.accounts
+                .iter()
+                .filter(|(_, change)| change.before.is_some() && change.after.is_none())
+                .map(|(&address, _)| address)
+        }
+
+        pub fn modified(&self) -> impl Iterator<Item = Address> + '_ {
+            self.accounts
+                .iter()
+                .filter(|(_, change)| change.before.is_some() && change.after.is_some())
+                .map(|(&address, _)| address)
+        }
+    }
+
+    /// Diff between state after block `from` and state after block `to`.
+    ///
+    /// Only entries touched by changesets of blocks `from + 1..=to` are considered,
+    /// and those that ended up with their original value are left out.
+    pub fn read<K: TransactionKind, E: EnvironmentKind>(
+        tx: &MdbxTransaction<'_, K, E>,
+        from: BlockNumber,
+        to: BlockNumber,
+    ) -> anyhow::Result<StateDiff> {
+        anyhow::ensure!(from <= to, "invalid block range {}..{}", from, to);
+
+        // Changesets hold values from before the block, so the first entry
+        // for a key within the range is its value at `from`.
+        let mut accounts = BTreeMap::new();
+        let mut slots = BTreeMap::<Address, BTreeMap<H256, U256>>::new();
+        for changes in crate::changeset::walk(tx, from + 1..=to)? {
+            let changes = changes?;
+            for tables::AccountChange { address, account } in changes.accounts {
+                accounts.entry(address).or_insert(account);
+            }
+            for (address, tables::StorageChange { location, value }) in changes.storage {
+                slots
+                    .entry(address)
+                    .or_default()
+                    .entry(location)
+                    .or_insert(value);
+            }
+        }
+
+        let mut diff = StateDiff::default();
+        for (address, before) in accounts {
+            let after = super::account::read(tx, address, Some(to))?;
+            if before != after {
+                diff.accounts.insert(address, Change { before, after });
+            }
+        }
+        for (address, slots) in slots {
+            let mut changed = BTreeMap::new();
+            for (location, before) in slots {
+                let after = super::storage::read(tx, address, h256_to_u256(location), Some(to))?;
+                if before != after {
+                    changed.insert(location, Change { before, after });
+                }
+            }
+            if !changed.is_empty() {
+                diff.storage.insert(address, changed);
+            }
+        }
+
+        Ok(diff)
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -161,4 +235,125 @@ pub mod tests {
             0.as_u256()
         );
     }
+
+    #[test]
+    fn find_next_block_at_max() {
+        let db = new_mem_database().unwrap();
+        let txn = db.begin_mutable().unwrap();
+
+        let address = Address::from_low_u64_be(1);
+        let last = BlockNumber(u64::MAX);
+        txn.set(
+            tables::AccountHistory,
+            tables::BitmapKey {
+                inner: address,
+                block_number: last,
+            },
+            [5, u64::MAX].into_iter().collect(),
+        )
+        .unwrap();
+
+        let next = |block| {
+            super::history_index::find_next_block(&txn, tables::AccountHistory, address, block)
+                .unwrap()
+        };
+        assert_eq!(next(BlockNumber(0)), Some(BlockNumber(5)));
+        assert_eq!(next(BlockNumber(5)), Some(last));
+        assert_eq!(next(last), None);
+    }
+
+    #[test]
+    fn state_diff() {
+        let db = new_mem_database().unwrap();
+        let txn = db.begin_mutable().unwrap();
+
+        let created = Address::from_low_u64_be(1);
+        let deleted = Address::from_low_u64_be(2);
+        let modified = Address::from_low_u64_be(3);
+        let reverted = Address::from_low_u64_be(4);
+
+        let account = |nonce| Account {
+            nonce,
+            ..Default::default()
+        };
+
+        for (block, address, account) in [
+            (1, created, None),
+            (1, modified, Some(account(1))),
+            (1, reverted, Some(account(1))),
+            (2, deleted, Some(account(1))),
+            (2, modified, Some(account(2))),
+            (2, reverted, Some(account(2))),
+        ] {
+            txn.set(
+                tables::AccountChangeSet,
+                BlockNumber(block),
+                tables::AccountChange { address, account },
+            )
+            .unwrap();
+        }
+        txn.set(tables::Account, created, account(1)).unwrap();
+        txn.set(tables::Account, modified, account(3)).unwrap();
+        txn.set(tables::Account, reverted, account(1)).unwrap();
+
+        let loc1 = H256::from_low_u64_be(1);
+        let loc2 = H256::from_low_u64_be(2);
+        for (block, location, value) in [(1, loc1, 1), (2, loc1, 2), (2, loc2, 5)] {
+            txn.set(
+                tables::StorageChangeSet,
+                tables::StorageChangeKey {
+                    block_number: BlockNumber(block),
+                    address: modified,
+                },
+                tables::StorageChange {
+                    location,
+                    value: value.as_u256(),
+                },
+            )
+            .unwrap();
+        }
+        txn.set(tables::Storage, modified, (loc1, 3.as_u256()))
+            .unwrap();
+        txn.set(tables::Storage, modified, (loc2, 5.as_u256()))
+            .unwrap();
+
+        let diff = super::diff::read(&txn, BlockNumber(0), BlockNumber(2)).unwrap();
+        assert_eq!(diff.created().collect::<Vec<_>>(), vec![created]);
+        assert_eq!(diff.deleted().collect::<Vec<_>>(), vec![deleted]);
+        assert_eq!(diff.modified().collect::<Vec<_>>(), vec![modified]);
+        assert_eq!(
+            diff.accounts[&modified],
+            super::diff::Change {
+                before: Some(account(1)),
+                after: Some(account(3)),
+            }
+        );
+        assert_eq!(diff.storage.len(), 1);
+        assert_eq!(
+            diff.storage[&modified].iter().collect::<Vec<_>>(),
+            vec![(
+                &loc1,
+                &super::diff::Change {
+                    before: 1.as_u256(),
+                    after: 3.as_u256(),
+                }
+            )]
+        );
+
+        let diff = super::diff::read(&txn, BlockNumber(1), BlockNumber(2)).unwrap();
+        assert_eq!(diff.created().count(), 0);
+        assert_eq!(diff.accounts[&modified].before, Some(account(2)));
+        assert_eq!(
+            diff.accounts[&reverted],
+            super::diff::Change {
+                before: Some(account(2)),
+                after: Some(account(1)),
+            }
+        );
+        assert_eq!(
+            diff.storage[&modified].keys().collect::<Vec<_>>(),
+            vec![&loc1]
+        );
+        assert_eq!(diff.storage[&modified][&loc1].before, 2.as_u256());
+    }
 }
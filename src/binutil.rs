@@ -1,12 +1,34 @@
+use anyhow::{bail, Context};
 use derive_more::*;
 use directories::ProjectDirs;
-use std::{fmt::Display, path::PathBuf};
+use std::{
+    fmt::Display,
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+};
+use tracing_subscriber::{fmt::format::FmtSpan, prelude::*, EnvFilter};
 
-#[derive(Debug, Deref, DerefMut, FromStr)]
+/// Version of the datadir layout, bumped whenever an existing datadir needs migration.
+pub const DATADIR_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Deref, DerefMut, FromStr)]
 
 pub struct MartinezDataDir(pub PathBuf);
 
 impl MartinezDataDir {
+    /// Datadir of the chain named `chain`, a subdirectory such as `<datadir>/mainnet`.
+    ///
+    /// Datadirs holding a database directly, as created before per-chain subdirectories, are
+    /// used as is. The database itself rejects a chain other than the one it was created for.
+    pub fn chain_dir(&self, chain: &str) -> Self {
+        if self.chain_data_dir().exists() {
+            return self.clone();
+        }
+
+        Self(self.0.join(chain.to_lowercase()))
+    }
+
     pub fn chain_data_dir(&self) -> PathBuf {
         self.0.join("chaindata")
     }
@@ -14,6 +36,293 @@ impl MartinezDataDir {
     pub fn etl_temp_dir(&self) -> PathBuf {
         self.0.join("etl-temp")
     }
+
+    /// Header slices downloaded but not committed yet, see [`crate::downloader::HeaderSliceStore`].
+    pub fn header_slices_dir(&self) -> PathBuf {
+        self.0.join("header-slices")
+    }
+
+    pub fn snapshots_dir(&self) -> PathBuf {
+        self.0.join("snapshots")
+    }
+
+    pub fn txpool_dir(&self) -> PathBuf {
+        self.0.join("txpool")
+    }
+
+    pub fn node_key_dir(&self) -> PathBuf {
+        self.0.join("nodekey")
+    }
+
+    pub fn keystore_dir(&self) -> PathBuf {
+        self.0.join("keystore")
+    }
+
+    pub fn logs_dir(&self) -> PathBuf {
+        self.0.join("logs")
+    }
+
+    /// Table sizes recorded by the last `db-stats` run of the toolbox.
+    pub fn db_stats_file(&self) -> PathBuf {
+        self.0.join("db-stats.json")
+    }
+
+    /// Creates `dir`, usually one of the subdirectories above, if it does not exist yet.
+    pub fn create_dir(&self, dir: PathBuf) -> anyhow::Result<PathBuf> {
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create directory {}", dir.display()))?;
+        Ok(dir)
+    }
+
+    /// File holding the layout version of the datadir.
+    pub fn version_file(&self) -> PathBuf {
+        self.0.join("VERSION")
+    }
+
+    /// Layout version of the datadir, stamped with [`DATADIR_VERSION`] if it is new.
+    ///
+    /// Datadirs created before the version marker was introduced are reported as version 0.
+    /// Fails if the datadir was written by a newer build.
+    pub fn check_version(&self) -> anyhow::Result<DataDirVersion> {
+        let version_file = self.version_file();
+        let version = if version_file.exists() {
+            let s = std::fs::read_to_string(&version_file)
+                .with_context(|| format!("failed to read {}", version_file.display()))?;
+            s.trim()
+                .parse()
+                .with_context(|| format!("malformed datadir version {:?}", s.trim()))?
+        } else if self.chain_data_dir().exists() {
+            0
+        } else {
+            DATADIR_VERSION
+        };
+
+        if version > DATADIR_VERSION {
+            bail!(
+                "datadir {} has layout version {}, newer than supported version {}",
+                self,
+                version,
+                DATADIR_VERSION
+            );
+        }
+
+        if version < DATADIR_VERSION {
+            return Ok(DataDirVersion::Outdated(version));
+        }
+
+        if !version_file.exists() {
+            self.write_version()?;
+        }
+
+        Ok(DataDirVersion::Current)
+    }
+
+    /// Stamps the datadir with [`DATADIR_VERSION`], to be called once migration is complete.
+    pub fn write_version(&self) -> anyhow::Result<()> {
+        self.create_dir(self.0.clone())?;
+        std::fs::write(self.version_file(), format!("{}\n", DATADIR_VERSION))
+            .with_context(|| format!("failed to write datadir version in {}", self))
+    }
+
+    /// File locked by the writer process, holds its pid.
+    pub fn lock_file(&self) -> PathBuf {
+        self.0.join("LOCK")
+    }
+
+    /// File share-locked by every reader process.
+    pub fn readers_lock_file(&self) -> PathBuf {
+        self.0.join("READERS")
+    }
+
+    fn open_readers_lock_file(&self) -> anyhow::Result<File> {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(self.readers_lock_file())
+            .with_context(|| format!("failed to open readers lock file in datadir {}", self))
+    }
+
+    /// Keeps readers from attaching for as long as the returned file is open, failing if some
+    /// are attached already. The writer holds it while migrating the database, as readers
+    /// would misread the tables being migrated.
+    pub fn exclude_readers(&self) -> anyhow::Result<File> {
+        let file = self.open_readers_lock_file()?;
+        if !try_lock(&file, true)? {
+            bail!(
+                "readers are attached to datadir {}, stop them to let the database be migrated",
+                self
+            );
+        }
+
+        Ok(file)
+    }
+
+    /// Claims the datadir for this process.
+    ///
+    /// There may be only one writer, while any number of readers can attach to a datadir with
+    /// or without a writer. The writer lock is released when the returned guard is dropped.
+    pub fn lock(&self, mode: AccessMode) -> anyhow::Result<DataDirLock> {
+        match mode {
+            AccessMode::Writer => {
+                self.create_dir(self.0.clone())?;
+
+                let mut file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create(true)
+                    .open(self.lock_file())
+                    .with_context(|| format!("failed to open lock file in datadir {}", self))?;
+
+                if !try_lock(&file, true)? {
+                    let owner = read_pid(&mut file)
+                        .map(|pid| format!(" (pid {})", pid))
+                        .unwrap_or_default();
+                    bail!(
+                        "datadir {} is already used by another writer{}, stop it or attach read-only",
+                        self,
+                        owner
+                    );
+                }
+
+                file.set_len(0)?;
+                file.seek(SeekFrom::Start(0))?;
+                write!(file, "{}", std::process::id())?;
+                file.sync_all()?;
+
+                Ok(DataDirLock {
+                    mode,
+                    file: Some(file),
+                    shared: None,
+                    writer: Some(std::process::id()),
+                })
+            }
+            AccessMode::Reader => {
+                if !self.0.is_dir() {
+                    bail!(
+                        "datadir {} does not exist, it has to be created by a writer process first",
+                        self
+                    );
+                }
+
+                let shared = self.open_readers_lock_file()?;
+                if !try_lock(&shared, false)? {
+                    bail!(
+                        "datadir {} is being migrated by its writer, attach once it is done",
+                        self
+                    );
+                }
+
+                let writer = File::open(self.lock_file())
+                    .ok()
+                    .and_then(|mut file| read_pid(&mut file))
+                    .filter(|&pid| process_alive(pid));
+
+                Ok(DataDirLock {
+                    mode,
+                    file: None,
+                    shared: Some(shared),
+                    writer,
+                })
+            }
+        }
+    }
+}
+
+/// Result of [`MartinezDataDir::check_version`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataDirVersion {
+    /// Datadir layout matches this build.
+    Current,
+    /// Datadir was written with an older layout version and has to be migrated.
+    Outdated(u32),
+}
+
+/// How a process uses the datadir.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessMode {
+    /// Exclusive writer: syncs and modifies the database.
+    Writer,
+    /// Shared reader: only reads, alongside a writer or other readers.
+    Reader,
+}
+
+/// Guard returned by [`MartinezDataDir::lock`].
+#[derive(Debug)]
+pub struct DataDirLock {
+    mode: AccessMode,
+    file: Option<File>,
+    /// Readers lock file of a reader, unlocked when closed.
+    shared: Option<File>,
+    writer: Option<u32>,
+}
+
+impl DataDirLock {
+    pub fn mode(&self) -> AccessMode {
+        self.mode
+    }
+
+    /// Pid of the writer process, as seen when the lock was taken.
+    pub fn writer(&self) -> Option<u32> {
+        self.writer
+    }
+}
+
+impl Drop for DataDirLock {
+    fn drop(&mut self) {
+        // The lock itself is released with the file descriptor.
+        if let Some(file) = &self.file {
+            let _ = file.set_len(0);
+        }
+    }
+}
+
+fn read_pid(file: &mut File) -> Option<u32> {
+    let mut s = String::new();
+    file.read_to_string(&mut s).ok()?;
+    s.trim().parse().ok()
+}
+
+#[cfg(unix)]
+fn try_lock(file: &File, exclusive: bool) -> anyhow::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    let operation = if exclusive {
+        libc::LOCK_EX
+    } else {
+        libc::LOCK_SH
+    };
+    if unsafe { libc::flock(file.as_raw_fd(), operation | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+
+    let error = std::io::Error::last_os_error();
+    if error.raw_os_error() == Some(libc::EWOULDBLOCK) {
+        return Ok(false);
+    }
+
+    Err(error).context("failed to lock datadir")
+}
+
+#[cfg(not(unix))]
+fn try_lock(_: &File, _: bool) -> anyhow::Result<bool> {
+    Ok(true)
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    // Signal 0 only checks that the process exists.
+    if unsafe { libc::kill(pid as libc::pid_t, 0) } == 0 {
+        return true;
+    }
+
+    // Process of another user.
+    std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn process_alive(_: u32) -> bool {
+    true
 }
 
 impl Default for MartinezDataDir {
@@ -31,3 +340,112 @@ impl Display for MartinezDataDir {
         write!(f, "{}", self.0.as_os_str().to_str().unwrap())
     }
 }
+
+/// Sets up logging for a binary. `RUST_LOG` overrides `default_filter`.
+///
+/// In JSON mode every line is an object carrying the fields of the enclosing spans,
+/// and closing spans are reported with their durations.
+pub fn init_tracing(default_filter: &str, json: bool) {
+    let env_filter = if std::env::var(EnvFilter::DEFAULT_ENV)
+        .unwrap_or_default()
+        .is_empty()
+    {
+        EnvFilter::new(default_filter)
+    } else {
+        EnvFilter::from_default_env()
+    };
+
+    if json {
+        tracing_subscriber::registry()
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(true)
+                    .with_span_events(FmtSpan::CLOSE),
+            )
+            .with(env_filter)
+            .init();
+    } else {
+        let nocolor = std::env::var("RUST_LOG_STYLE")
+            .map(|val| val == "never")
+            .unwrap_or(false);
+
+        tracing_subscriber::registry()
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .with_target(false)
+                    .with_ansi(!nocolor),
+            )
+            .with(env_filter)
+            .init();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn datadir_version() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let fresh = MartinezDataDir(dir.path().join("fresh"));
+        assert_eq!(fresh.check_version().unwrap(), DataDirVersion::Current);
+        assert_eq!(
+            std::fs::read_to_string(fresh.version_file()).unwrap(),
+            format!("{}\n", DATADIR_VERSION)
+        );
+
+        let legacy = MartinezDataDir(dir.path().join("legacy"));
+        legacy.create_dir(legacy.chain_data_dir()).unwrap();
+        assert_eq!(legacy.check_version().unwrap(), DataDirVersion::Outdated(0));
+        assert!(!legacy.version_file().exists());
+
+        legacy.write_version().unwrap();
+        assert_eq!(legacy.check_version().unwrap(), DataDirVersion::Current);
+
+        std::fs::write(legacy.version_file(), "0").unwrap();
+        assert_eq!(legacy.check_version().unwrap(), DataDirVersion::Outdated(0));
+
+        std::fs::write(legacy.version_file(), format!("{}", DATADIR_VERSION + 1)).unwrap();
+        assert!(legacy.check_version().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn lock_modes() {
+        let dir = tempfile::tempdir().unwrap();
+        let datadir = MartinezDataDir(dir.path().to_path_buf());
+        assert!(datadir.lock(AccessMode::Reader).is_err());
+
+        let writer = datadir.lock(AccessMode::Writer).unwrap();
+        assert!(datadir.lock(AccessMode::Writer).is_err());
+
+        let reader = datadir.lock(AccessMode::Reader).unwrap();
+        let other_reader = datadir.lock(AccessMode::Reader).unwrap();
+        assert_eq!(reader.writer(), Some(std::process::id()));
+        assert!(datadir.exclude_readers().is_err());
+
+        drop(reader);
+        drop(other_reader);
+        let excluded = datadir.exclude_readers().unwrap();
+        assert!(datadir.lock(AccessMode::Reader).is_err());
+
+        drop(excluded);
+        assert!(datadir.lock(AccessMode::Reader).is_ok());
+        drop(writer);
+    }
+
+    #[test]
+    fn chain_dir() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let datadir = MartinezDataDir(dir.path().to_path_buf());
+        assert_eq!(datadir.chain_dir("Mainnet").0, dir.path().join("mainnet"));
+        assert_eq!(datadir.chain_dir("sepolia").0, dir.path().join("sepolia"));
+
+        datadir.create_dir(datadir.chain_data_dir()).unwrap();
+        assert_eq!(datadir.chain_dir("sepolia").0, dir.path());
+    }
+}
@@ -1,9 +1,19 @@
+//! Block bitmaps of index tables, stored in chunks of bounded size.
+//!
+//! A bitmap is split into chunks of at most [`CHUNK_LIMIT`] serialized bytes, each keyed by
+//! the last block it contains, except for the last chunk which is keyed by `u64::MAX`. New
+//! blocks are therefore always merged into the chunk under a well-known key.
+
 use crate::{
-    kv::{mdbx::MdbxTransaction, tables::BitmapKey, traits::*},
+    kv::{
+        mdbx::{MdbxCursor, MdbxTransaction},
+        tables::BitmapKey,
+        traits::*,
+    },
     models::*,
 };
 use croaring::{treemap::NativeSerializer, Treemap as RoaringTreemap};
-use mdbx::{EnvironmentKind, TransactionKind};
+use mdbx::{EnvironmentKind, TransactionKind, RW};
 use std::{iter::Peekable, ops::RangeInclusive};
 use tokio::pin;
 
@@ -52,6 +62,124 @@ where
     Ok(out.unwrap_or_default())
 }
 
+/// Merges `bitmap` into the chunks stored under `key`. Blocks must be newer than those
+/// already stored, since only the last chunk is rewritten.
+pub fn append<T, K>(
+    cursor: &mut MdbxCursor<'_, RW, T>,
+    key: K,
+    mut bitmap: RoaringTreemap,
+) -> anyhow::Result<()>
+where
+    K: Clone,
+    BitmapKey<K>: TableDecode,
+    T: Table<Key = BitmapKey<K>, Value = RoaringTreemap>,
+{
+    if bitmap.is_empty() {
+        return Ok(());
+    }
+
+    if let Some((_, last_chunk)) = cursor.seek_exact(BitmapKey {
+        inner: key.clone(),
+        block_number: BlockNumber(u64::MAX),
+    })? {
+        bitmap |= last_chunk;
+    }
+
+    for (block_number, chunk) in Chunks::new(bitmap, CHUNK_LIMIT).with_keys() {
+        cursor.put(
+            BitmapKey {
+                inner: key.clone(),
+                block_number,
+            },
+            chunk,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Removes blocks after `block_number` from the chunks stored under `key`. The last
+/// remaining chunk is moved under the `u64::MAX` key.
+pub fn truncate<T, K>(
+    cursor: &mut MdbxCursor<'_, RW, T>,
+    key: K,
+    block_number: BlockNumber,
+) -> anyhow::Result<()>
+where
+    K: Clone + PartialEq,
+    BitmapKey<K>: TableDecode,
+    T: Table<Key = BitmapKey<K>, Value = RoaringTreemap>,
+{
+    let mut entry = cursor.seek_exact(BitmapKey {
+        inner: key.clone(),
+        block_number: BlockNumber(u64::MAX),
+    })?;
+
+    while let Some((chunk_key, chunk)) = entry {
+        let kept = chunk
+            .iter()
+            .take_while(|&n| n <= *block_number)
+            .collect::<RoaringTreemap>();
+
+        if chunk_key.block_number == BlockNumber(u64::MAX)
+            && kept.cardinality() == chunk.cardinality()
+        {
+            break;
+        }
+
+        cursor.delete_current()?;
+
+        if !kept.is_empty() {
+            cursor.put(
+                BitmapKey {
+                    inner: key,
+                    block_number: BlockNumber(u64::MAX),
+                },
+                kept,
+            )?;
+            break;
+        }
+
+        entry = cursor
+            .prev()?
+            .filter(|(BitmapKey { inner, .. }, _)| *inner == key);
+    }
+
+    Ok(())
+}
+
+/// Removes blocks before `block_number` from every bitmap of the table, deleting the chunks
+/// left empty.
+pub fn prune<T, K>(
+    cursor: &mut MdbxCursor<'_, RW, T>,
+    block_number: BlockNumber,
+) -> anyhow::Result<()>
+where
+    BitmapKey<K>: TableDecode,
+    T: Table<Key = BitmapKey<K>, Value = RoaringTreemap>,
+{
+    let mut entry = cursor.first()?;
+    while let Some((chunk_key, chunk)) = entry {
+        if chunk_key.block_number < block_number {
+            cursor.delete_current()?;
+        } else if chunk.iter().next().map_or(false, |n| n < *block_number) {
+            let kept = chunk
+                .iter()
+                .skip_while(|&n| n < *block_number)
+                .collect::<RoaringTreemap>();
+            if kept.is_empty() {
+                cursor.delete_current()?;
+            } else {
+                cursor.put(chunk_key, kept)?;
+            }
+        }
+
+        entry = cursor.next()?;
+    }
+
+    Ok(())
+}
+
 pub struct Chunks {
     bm: RoaringTreemap,
     size_limit: usize,
@@ -141,6 +269,7 @@ impl Iterator for ChunksWithKeys {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::kv::tables;
 
     #[test]
     fn chunks() {
@@ -180,4 +309,101 @@ mod tests {
 
         assert_eq!(Chunks::new(RoaringTreemap::create(), N).next(), None);
     }
+
+    #[test]
+    fn append_and_truncate() {
+        let db = crate::kv::new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+        let mut cursor = tx.cursor(tables::CallFromIndex).unwrap();
+        let address = Address::repeat_byte(1);
+
+        let chunk_keys = |tx: &MdbxTransaction<'_, RW, _>| {
+            tx.cursor(tables::CallFromIndex)
+                .unwrap()
+                .walk(None)
+                .map(|res| res.unwrap().0.block_number.0)
+                .collect::<Vec<_>>()
+        };
+        let blocks = |tx: &MdbxTransaction<'_, RW, _>| {
+            get(
+                tx,
+                tables::CallFromIndex,
+                address,
+                BlockNumber(0)..=BlockNumber(u64::MAX),
+            )
+            .unwrap()
+            .iter()
+            .collect::<Vec<_>>()
+        };
+
+        let bitmap = |range: std::ops::Range<u64>| {
+            let mut bm = RoaringTreemap::create();
+            for n in range.step_by(2) {
+                bm.add(n);
+            }
+            bm
+        };
+
+        append(&mut cursor, address, bitmap(0..2000)).unwrap();
+        append(&mut cursor, address, bitmap(2000..4000)).unwrap();
+        let keys = chunk_keys(&tx);
+        assert!(keys.len() > 2);
+        assert_eq!(*keys.last().unwrap(), u64::MAX);
+        assert_eq!(blocks(&tx), (0..4000).step_by(2).collect::<Vec<_>>());
+
+        // Nothing to remove.
+        truncate(&mut cursor, address, BlockNumber(5000)).unwrap();
+        assert_eq!(chunk_keys(&tx), keys);
+
+        // Cut right after the end of a chunk, which becomes the last one.
+        truncate(&mut cursor, address, BlockNumber(keys[1] + 1)).unwrap();
+        assert_eq!(chunk_keys(&tx), vec![keys[0], u64::MAX]);
+        assert_eq!(blocks(&tx), (0..=keys[1]).step_by(2).collect::<Vec<_>>());
+
+        truncate(&mut cursor, address, BlockNumber(10)).unwrap();
+        assert_eq!(chunk_keys(&tx), vec![u64::MAX]);
+        assert_eq!(blocks(&tx), vec![0, 2, 4, 6, 8, 10]);
+
+        append(&mut cursor, address, bitmap(12..16)).unwrap();
+        assert_eq!(blocks(&tx), vec![0, 2, 4, 6, 8, 10, 12, 14]);
+    }
+
+    #[test]
+    fn prune_chunks() {
+        let db = crate::kv::new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+        let mut cursor = tx.cursor(tables::CallFromIndex).unwrap();
+
+        let mut bm = RoaringTreemap::create();
+        for n in (0..4000).step_by(2) {
+            bm.add(n);
+        }
+        for address in [Address::repeat_byte(1), Address::repeat_byte(2)] {
+            append(&mut cursor, address, bm.clone()).unwrap();
+        }
+        let mut single = RoaringTreemap::create();
+        single.add(5);
+        append(&mut cursor, Address::repeat_byte(3), single).unwrap();
+
+        prune(&mut cursor, BlockNumber(3001)).unwrap();
+        for address in [Address::repeat_byte(1), Address::repeat_byte(2)] {
+            assert_eq!(
+                get(
+                    &tx,
+                    tables::CallFromIndex,
+                    address,
+                    BlockNumber(0)..=BlockNumber(u64::MAX),
+                )
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+                (3002..4000).step_by(2).collect::<Vec<_>>()
+            );
+        }
+        assert!(tx
+            .cursor(tables::CallFromIndex)
+            .unwrap()
+            .walk(None)
+            .all(|res| res.unwrap().0.inner != Address::repeat_byte(3)));
+    }
 }
@@ -31,6 +31,9 @@ pub mod param {
     // https://eips.ethereum.org/EIPS/eip-170
     pub const MAX_CODE_SIZE: usize = 0x6000;
 
+    // https://eips.ethereum.org/EIPS/eip-3860
+    pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
+
     pub const BLOCK_REWARD_FRONTIER: u128 = 5 * ETHER;
     pub const BLOCK_REWARD_BYZANTIUM: u128 = 3 * ETHER;
     pub const BLOCK_REWARD_CONSTANTINOPLE: u128 = 2 * ETHER;
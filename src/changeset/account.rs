@@ -1,28 +1,23 @@
-use crate::kv::tables::AccountChange;
-
 use super::*;
+use crate::kv::tables::AccountChange;
 
-#[async_trait]
 impl HistoryKind for AccountHistory {
     type Key = Address;
-    type Value = EncodedAccount;
+    type Value = Option<Account>;
     type IndexChunkKey = Address;
     type IndexTable = tables::AccountHistory;
     type ChangeSetTable = tables::AccountChangeSet;
-    type EncodedStream<'cs> = impl EncodedStream<'cs, Self::ChangeSetTable>;
 
     fn index_chunk_key(key: Self::Key) -> Self::IndexChunkKey {
         key
     }
-    async fn find<'tx, C>(
-        cursor: &mut C,
+
+    fn find<K: TransactionKind>(
+        cursor: &mut MdbxCursor<'_, K, Self::ChangeSetTable>,
         block_number: BlockNumber,
         needle: Self::Key,
-    ) -> anyhow::Result<Option<Self::Value>>
-    where
-        C: CursorDupSort<'tx, Self::ChangeSetTable>,
-    {
-        if let Some(v) = cursor.seek_both_range(block_number, needle).await? {
+    ) -> anyhow::Result<Option<Self::Value>> {
+        if let Some(v) = cursor.seek_both_range(block_number, needle)? {
             let (_, (address, account)) = Self::decode(block_number, v);
 
             if address == needle {
@@ -33,16 +28,11 @@ impl HistoryKind for AccountHistory {
         Ok(None)
     }
 
-    fn encode(block_number: BlockNumber, s: &ChangeSet<Self>) -> Self::EncodedStream<'_> {
-        s.iter().map(move |(address, account)| {
-            (
-                block_number,
-                AccountChange {
-                    address: *address,
-                    account: account.clone(),
-                },
-            )
-        })
+    fn encode(block_number: BlockNumber, changes: &ChangeSet<Self>) -> Vec<ChangeSetEntry<Self>> {
+        changes
+            .iter()
+            .map(|(&address, &account)| (block_number, AccountChange { address, account }))
+            .collect()
     }
 
     fn decode(
@@ -56,36 +46,65 @@ impl HistoryKind for AccountHistory {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ethereum_types::Address;
-    use hex_literal::hex;
+    use crate::kv::new_mem_database;
+
+    fn test_changes() -> AccountChangeSet {
+        (0..3_u64)
+            .map(|i| {
+                (
+                    Address::from_low_u64_be(0xbe82 + i),
+                    (i > 0).then(|| Account {
+                        nonce: i,
+                        balance: U256::from(i * 1000),
+                        ..Default::default()
+                    }),
+                )
+            })
+            .collect()
+    }
 
     #[test]
     fn account_encoding() {
-        let mut ch = ChangeSet::<AccountHistory>::default();
-
-        for (i, val) in vec![
-            &hex!("f7f6db1eb17c6d582078e0ffdd0c") as &[u8],
-            &hex!("b1e9b5c16355eede662031dd621d08faf4ea") as &[u8],
-            &hex!("862cf52b74f1cea41ddd8ffa4b3e7c7790") as &[u8],
-        ]
-        .into_iter()
-        .enumerate()
-        {
-            let address = format!("0xBe828AD8B538D1D691891F6c725dEdc5989abBc{}", i)
-                .parse::<Address>()
-                .unwrap();
-
-            ch.insert((address, val.iter().copied().collect()));
-        }
+        let ch = test_changes();
 
         let mut ch2 = AccountChangeSet::new();
 
         for (k, v) in AccountHistory::encode(1.into(), &ch) {
-            let (_, change) = AccountHistory::decode(k, v);
+            let (block_number, (address, account)) = AccountHistory::decode(k, v);
+            assert_eq!(block_number, BlockNumber(1));
 
-            ch2.insert(change);
+            ch2.insert(address, account);
         }
 
         assert_eq!(ch, ch2);
     }
+
+    #[test]
+    fn account_find() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let ch = test_changes();
+
+        let mut c = tx.cursor(tables::AccountChangeSet).unwrap();
+        for (k, v) in AccountHistory::encode(1.into(), &ch) {
+            c.upsert(k, v).unwrap();
+        }
+
+        for (address, account) in ch {
+            assert_eq!(
+                AccountHistory::find(&mut c, 1.into(), address).unwrap(),
+                Some(account)
+            );
+            assert_eq!(
+                AccountHistory::find(&mut c, 2.into(), address).unwrap(),
+                None
+            );
+        }
+
+        assert_eq!(
+            AccountHistory::find(&mut c, 1.into(), Address::from_low_u64_be(1)).unwrap(),
+            None
+        );
+    }
 }
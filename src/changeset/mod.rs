@@ -1,14 +1,21 @@
 use crate::{
-    kv::{tables::BitmapKey, *},
+    accessors::state::history_index,
+    kv::{
+        mdbx::*,
+        tables::{self, BitmapKey},
+        traits::*,
+    },
     models::*,
-    CursorDupSort,
 };
-use async_trait::async_trait;
-use roaring::RoaringTreemap;
-use std::{collections::BTreeSet, fmt::Debug};
+use croaring::Treemap as RoaringTreemap;
+use mdbx::{EnvironmentKind, TransactionKind};
+use std::{collections::BTreeMap, fmt::Debug};
 
 mod account;
 mod storage;
+mod walker;
+
+pub use self::walker::*;
 
 pub struct AccountHistory;
 pub struct StorageHistory;
@@ -16,47 +23,196 @@ pub struct StorageHistory;
 pub type AccountChangeSet = ChangeSet<AccountHistory>;
 pub type StorageChangeSet = ChangeSet<StorageHistory>;
 
-pub trait EncodedStream<'cs, T: Table>: Iterator<Item = (T::Key, T::Value)> + Send + 'cs {}
-impl<'cs, S, T> EncodedStream<'cs, T> for S
-where
-    S: Iterator<Item = (T::Key, T::Value)> + Send + 'cs,
-    T: Table,
-{
-}
-
-pub trait ChangeKey: Eq + Ord + Debug {}
-impl<T> ChangeKey for T where T: Eq + Ord + Debug {}
-
 pub type Change<K, V> = (K, V);
 
-pub type ChangeSet<K> = BTreeSet<Change<<K as HistoryKind>::Key, <K as HistoryKind>::Value>>;
+/// Changes of a single block, keyed by what changed.
+pub type ChangeSet<K> = BTreeMap<<K as HistoryKind>::Key, <K as HistoryKind>::Value>;
+
+pub type ChangeSetEntry<K> = (
+    <<K as HistoryKind>::ChangeSetTable as Table>::Key,
+    <<K as HistoryKind>::ChangeSetTable as Table>::Value,
+);
 
-#[async_trait]
-pub trait HistoryKind: Send {
-    type Key: Eq + Ord + Sync;
-    type Value: Debug + Sync;
-    type ChangeSetTable: DupSort;
-    type IndexChunkKey: Clone + PartialEq + Send + Sync;
+pub trait HistoryKind: Sized {
+    type Key: Copy + Eq + Ord;
+    type Value: Clone + Debug;
+    type ChangeSetTable: DupSort + Default;
+    type IndexChunkKey: Copy + PartialEq;
     type IndexTable: Table<
             Key = BitmapKey<Self::IndexChunkKey>,
             Value = RoaringTreemap,
             SeekKey = BitmapKey<Self::IndexChunkKey>,
         > + Default;
-    type EncodedStream<'cs>: EncodedStream<'cs, Self::ChangeSetTable>;
 
     fn index_chunk_key(key: Self::Key) -> Self::IndexChunkKey;
-    async fn find<'tx, C>(
-        cursor: &mut C,
+    /// Value of `needle` before `block_number`, if the block changed it.
+    fn find<K: TransactionKind>(
+        cursor: &mut MdbxCursor<'_, K, Self::ChangeSetTable>,
         block_number: BlockNumber,
         needle: Self::Key,
-    ) -> anyhow::Result<Option<Self::Value>>
-    where
-        C: CursorDupSort<'tx, Self::ChangeSetTable>;
+    ) -> anyhow::Result<Option<Self::Value>>;
     /// Encode changes into DB keys and values
-    fn encode(block_number: BlockNumber, changes: &ChangeSet<Self>) -> Self::EncodedStream<'_>;
+    fn encode(block_number: BlockNumber, changes: &ChangeSet<Self>) -> Vec<ChangeSetEntry<Self>>;
     /// Decode `Change` from DB keys and values
     fn decode(
         k: <Self::ChangeSetTable as Table>::Key,
         v: <Self::ChangeSetTable as Table>::Value,
     ) -> (BlockNumber, Change<Self::Key, Self::Value>);
 }
+
+/// Value of `needle` as of the end of `block_number`.
+/// `None` if it hasn't changed since, so the current value applies.
+pub fn find_at<H, K, E>(
+    tx: &MdbxTransaction<'_, K, E>,
+    block_number: BlockNumber,
+    needle: H::Key,
+) -> anyhow::Result<Option<H::Value>>
+where
+    H: HistoryKind,
+    BitmapKey<H::IndexChunkKey>: TableObject,
+    K: TransactionKind,
+    E: EnvironmentKind,
+{
+    if let Some(change_block) = history_index::find_next_block(
+        tx,
+        H::IndexTable::default(),
+        H::index_chunk_key(needle),
+        block_number,
+    )? {
+        return H::find(
+            &mut tx.cursor(H::ChangeSetTable::default())?,
+            change_block,
+            needle,
+        );
+    }
+
+    Ok(None)
+}
+
+/// Account as of the end of `block_number`, `None` if it hasn't changed since.
+pub fn find_account_at<K: TransactionKind, E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, K, E>,
+    block_number: BlockNumber,
+    address: Address,
+) -> anyhow::Result<Option<Option<Account>>> {
+    find_at::<AccountHistory, _, _>(tx, block_number, address)
+}
+
+/// Storage value as of the end of `block_number`, `None` if it hasn't changed since.
+pub fn find_storage_at<K: TransactionKind, E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, K, E>,
+    block_number: BlockNumber,
+    address: Address,
+    location: H256,
+) -> anyhow::Result<Option<U256>> {
+    find_at::<StorageHistory, _, _>(tx, block_number, (address, location))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv::new_mem_database;
+
+    fn index_chunk(blocks: &[u64]) -> RoaringTreemap {
+        let mut bm = RoaringTreemap::create();
+        for &block in blocks {
+            bm.add(block);
+        }
+        bm
+    }
+
+    #[test]
+    fn find_account_across_index_chunks() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let address = Address::from_low_u64_be(0xacc);
+        let account = |nonce| {
+            Some(Account {
+                nonce,
+                ..Default::default()
+            })
+        };
+
+        for (block_number, nonce) in [(5, 0), (10, 1), (20, 2)] {
+            let changes = [(address, account(nonce))]
+                .into_iter()
+                .collect::<AccountChangeSet>();
+            for (k, v) in AccountHistory::encode(BlockNumber(block_number), &changes) {
+                tx.set(tables::AccountChangeSet, k, v).unwrap();
+            }
+        }
+        for (last_block, blocks) in [(10, &[5, 10][..]), (u64::MAX, &[20][..])] {
+            tx.set(
+                tables::AccountHistory,
+                BitmapKey {
+                    inner: address,
+                    block_number: BlockNumber(last_block),
+                },
+                index_chunk(blocks),
+            )
+            .unwrap();
+        }
+
+        for (block_number, expected) in [
+            (0, Some(account(0))),
+            (4, Some(account(0))),
+            (5, Some(account(1))),
+            (9, Some(account(1))),
+            (10, Some(account(2))),
+            (19, Some(account(2))),
+            (20, None),
+            (100, None),
+        ] {
+            assert_eq!(
+                find_account_at(&tx, BlockNumber(block_number), address).unwrap(),
+                expected,
+                "block {}",
+                block_number
+            );
+        }
+
+        assert_eq!(
+            find_account_at(&tx, BlockNumber(0), Address::from_low_u64_be(1)).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn find_storage_at_block() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let address = Address::from_low_u64_be(0xacc);
+        let location = H256::from_low_u64_be(1);
+
+        let changes = [((address, location), U256::from(7_u64))]
+            .into_iter()
+            .collect::<StorageChangeSet>();
+        for (k, v) in StorageHistory::encode(BlockNumber(3), &changes) {
+            tx.set(tables::StorageChangeSet, k, v).unwrap();
+        }
+        tx.set(
+            tables::StorageHistory,
+            BitmapKey {
+                inner: (address, location),
+                block_number: BlockNumber(u64::MAX),
+            },
+            index_chunk(&[3]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            find_storage_at(&tx, BlockNumber(2), address, location).unwrap(),
+            Some(U256::from(7_u64))
+        );
+        assert_eq!(
+            find_storage_at(&tx, BlockNumber(3), address, location).unwrap(),
+            None
+        );
+        assert_eq!(
+            find_storage_at(&tx, BlockNumber(2), address, H256::zero()).unwrap(),
+            None
+        );
+    }
+}
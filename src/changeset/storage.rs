@@ -1,40 +1,29 @@
 use super::*;
-use crate::{
-    kv::tables::{StorageChange, StorageChangeKey},
-    CursorDupSort,
-};
-use ethereum_types::*;
+use crate::kv::tables::{StorageChange, StorageChangeKey};
 
-#[async_trait]
 impl HistoryKind for StorageHistory {
     type Key = (Address, H256);
     type Value = U256;
     type IndexChunkKey = (Address, H256);
     type IndexTable = tables::StorageHistory;
     type ChangeSetTable = tables::StorageChangeSet;
-    type EncodedStream<'cs> = impl EncodedStream<'cs, Self::ChangeSetTable>;
 
     fn index_chunk_key((address, location): Self::Key) -> Self::IndexChunkKey {
         (address, location)
     }
-    async fn find<'tx, C>(
-        cursor: &mut C,
+
+    fn find<K: TransactionKind>(
+        cursor: &mut MdbxCursor<'_, K, Self::ChangeSetTable>,
         block_number: BlockNumber,
         (address, location): Self::Key,
-    ) -> anyhow::Result<Option<Self::Value>>
-    where
-        C: CursorDupSort<'tx, Self::ChangeSetTable>,
-    {
-        if let Some(v) = cursor
-            .seek_both_range(
-                StorageChangeKey {
-                    block_number,
-                    address,
-                },
-                location,
-            )
-            .await?
-        {
+    ) -> anyhow::Result<Option<Self::Value>> {
+        if let Some(v) = cursor.seek_both_range(
+            StorageChangeKey {
+                block_number,
+                address,
+            },
+            location,
+        )? {
             if v.location == location {
                 return Ok(Some(v.value));
             }
@@ -43,16 +32,19 @@ impl HistoryKind for StorageHistory {
         Ok(None)
     }
 
-    fn encode(block_number: BlockNumber, changes: &ChangeSet<Self>) -> Self::EncodedStream<'_> {
-        changes.iter().map(move |&((address, location), value)| {
-            (
-                StorageChangeKey {
-                    block_number,
-                    address,
-                },
-                StorageChange { location, value },
-            )
-        })
+    fn encode(block_number: BlockNumber, changes: &ChangeSet<Self>) -> Vec<ChangeSetEntry<Self>> {
+        changes
+            .iter()
+            .map(|(&(address, location), &value)| {
+                (
+                    StorageChangeKey {
+                        block_number,
+                        address,
+                    },
+                    StorageChange { location, value },
+                )
+            })
+            .collect()
     }
 
     fn decode(
@@ -69,14 +61,8 @@ impl HistoryKind for StorageHistory {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{
-        crypto::*,
-        h256_to_u256,
-        kv::{self, traits::*},
-    };
-    use ethereum_types::{Address, H256};
+    use crate::{crypto::*, h256_to_u256, kv};
     use hex_literal::hex;
-    use std::future::Future;
 
     const NUM_OF_CHANGES: &[usize] = &[1, 3, 10, 100];
 
@@ -85,7 +71,7 @@ mod tests {
     }
 
     fn empty_value_generator(_: usize) -> U256 {
-        U256::zero()
+        U256::ZERO
     }
 
     fn get_test_data_at_index(i: usize, j: usize) -> <StorageHistory as HistoryKind>::Key {
@@ -119,15 +105,15 @@ mod tests {
                 for j in 0..num_of_keys {
                     let key = get_test_data_at_index(i, j);
                     let val = (value_generator)(j);
-                    ch.insert((key, val));
+                    ch.insert(key, val);
                 }
             }
 
             let mut ch2 = StorageChangeSet::new();
 
             for (k, v) in StorageHistory::encode(0.into(), &ch) {
-                let (_, change) = StorageHistory::decode(k, v);
-                ch2.insert(change);
+                let (_, (key, value)) = StorageHistory::decode(k, v);
+                ch2.insert(key, value);
             }
 
             assert_eq!(ch, ch2)
@@ -146,8 +132,8 @@ mod tests {
         run_test(&f, 100, 1000);
     }
 
-    #[tokio::test]
-    async fn encoding_storage_new_without_not_default_incarnation_walk() {
+    #[test]
+    fn encoding_storage_new_without_not_default_incarnation_walk() {
         let f = |num_of_elements, num_of_keys| {
             let mut ch = StorageChangeSet::new();
 
@@ -155,15 +141,16 @@ mod tests {
                 for j in 0..num_of_keys {
                     let key = get_test_data_at_index(i, j);
                     let val = hash_value_generator(j);
-                    ch.insert((key, val));
+                    ch.insert(key, val);
                 }
             }
 
-            for ((_, transformed), original) in StorageHistory::encode(0.into(), &ch)
+            for ((_, transformed), (&key, &value)) in StorageHistory::encode(0.into(), &ch)
+                .into_iter()
                 .map(|(k, v)| StorageHistory::decode(k, v))
                 .zip(&ch)
             {
-                assert_eq!(transformed, *original);
+                assert_eq!(transformed, (key, value));
             }
         };
 
@@ -179,62 +166,53 @@ mod tests {
         run_test(&f, 5, 1000);
     }
 
-    #[tokio::test]
-    async fn encoding_storage_new_without_not_default_incarnation_find() {
+    #[test]
+    fn encoding_storage_new_without_not_default_incarnation_find() {
         let db = kv::new_mem_database().unwrap();
-        let tx = db.begin_mutable().await.unwrap();
+        let tx = db.begin_mutable().unwrap();
 
         let tx = &tx;
-        let f = |num_of_elements, num_of_keys| async move {
+        let f = |num_of_elements, num_of_keys| {
             let mut ch = StorageChangeSet::new();
 
             for i in 0..num_of_elements {
                 for j in 0..num_of_keys {
                     let key = get_test_data_at_index(i, j);
                     let val = hash_value_generator(j);
-                    ch.insert((key, val));
+                    ch.insert(key, val);
                 }
             }
 
-            let mut c = tx
-                .mutable_cursor_dupsort(&tables::StorageChangeSet)
-                .await
-                .unwrap();
+            let mut c = tx.cursor(tables::StorageChangeSet).unwrap();
 
             for (k, v) in StorageHistory::encode(1.into(), &ch) {
-                c.put(k, v).await.unwrap()
+                c.upsert(k, v).unwrap()
             }
 
-            for v in ch {
+            for (key, value) in ch {
                 assert_eq!(
-                    v.1,
-                    StorageHistory::find(&mut c, 1.into(), v.0)
-                        .await
+                    value,
+                    StorageHistory::find(&mut c, 1.into(), key)
                         .unwrap()
                         .unwrap()
                 )
             }
 
-            let mut c = tx
-                .mutable_cursor_dupsort(&tables::StorageChangeSet)
-                .await
-                .unwrap();
-
-            while c.first().await.unwrap().is_some() {
-                c.delete_current().await.unwrap();
+            while c.first().unwrap().is_some() {
+                c.delete_current().unwrap();
             }
         };
 
         for &v in NUM_OF_CHANGES[..NUM_OF_CHANGES.len() - 2].iter() {
-            run_test_async(f, v, 1).await;
+            run_test(f, v, 1);
         }
 
         for &v in NUM_OF_CHANGES[..NUM_OF_CHANGES.len() - 2].iter() {
-            run_test_async(f, v, 5).await;
+            run_test(f, v, 5);
         }
 
-        run_test_async(f, 50, 1000).await;
-        run_test_async(f, 100, 1000).await;
+        run_test(f, 50, 1000);
+        run_test(f, 100, 1000);
     }
 
     fn run_test<F: Fn(usize, usize)>(f: F, elements: usize, keys: usize) {
@@ -242,24 +220,12 @@ mod tests {
         (f)(elements, keys);
     }
 
-    async fn run_test_async<F: Fn(usize, usize) -> Fut, Fut: Future<Output = ()>>(
-        f: F,
-        elements: usize,
-        keys: usize,
-    ) {
-        println!("elements: {}, keys: {}", elements, keys);
-        (f)(elements, keys).await;
-    }
-
-    #[tokio::test]
-    async fn multiple_incarnations_of_the_same_contract() {
-        let env = crate::kv::new_mem_database().unwrap();
-        let tx = env.begin_mutable().await.unwrap();
+    #[test]
+    fn multiple_incarnations_of_the_same_contract() {
+        let env = kv::new_mem_database().unwrap();
+        let tx = env.begin_mutable().unwrap();
 
-        let mut cs = tx
-            .mutable_cursor_dupsort(&tables::StorageChangeSet)
-            .await
-            .unwrap();
+        let mut cs = tx.cursor(tables::StorageChangeSet).unwrap();
 
         let contract_a = Address::from(hex!("6f0e0cdac6c716a00bd8db4d0eee4f2bfccf8e6a"));
         let contract_b = Address::from(hex!("c5acb79c258108f288288bc26f7820d06f45f08c"));
@@ -288,12 +254,24 @@ mod tests {
             "0000000000000000000000000000000000000000000000000000000000000000"
         ));
 
-        let val1 = hex!("33bf0d0c348a2ef1b3a12b6a535e1e25a56d3624e45603e469626d80fd78c762").into();
-        let val2 = hex!("0000000000000000000000000000000000000000000000000000000000000459").into();
-        let val3 = hex!("0000000000000000000000000000002506e4b566c5be7dd44e8e2fc7b1f6a99c").into();
-        let val4 = hex!("207a386cdf40716455365db189633e822d3a7598558901f2255e64cb5e424714").into();
-        let val5 = hex!("0000000000000000000000000000000000000000000000000000000000000000").into();
-        let val6 = hex!("ec89478783348038046b42cc126a3c4e351977b5f4cf5e3c4f4d8385adbf8046").into();
+        let val1 = h256_to_u256(H256(hex!(
+            "33bf0d0c348a2ef1b3a12b6a535e1e25a56d3624e45603e469626d80fd78c762"
+        )));
+        let val2 = h256_to_u256(H256(hex!(
+            "0000000000000000000000000000000000000000000000000000000000000459"
+        )));
+        let val3 = h256_to_u256(H256(hex!(
+            "0000000000000000000000000000002506e4b566c5be7dd44e8e2fc7b1f6a99c"
+        )));
+        let val4 = h256_to_u256(H256(hex!(
+            "207a386cdf40716455365db189633e822d3a7598558901f2255e64cb5e424714"
+        )));
+        let val5 = h256_to_u256(H256(hex!(
+            "0000000000000000000000000000000000000000000000000000000000000000"
+        )));
+        let val6 = h256_to_u256(H256(hex!(
+            "ec89478783348038046b42cc126a3c4e351977b5f4cf5e3c4f4d8385adbf8046"
+        )));
 
         let ch = vec![
             ((contract_a, key1), val1),
@@ -306,17 +284,12 @@ mod tests {
         .into_iter()
         .collect::<ChangeSet<StorageHistory>>();
 
-        let mut c = tx
-            .mutable_cursor_dupsort(&tables::StorageChangeSet)
-            .await
-            .unwrap();
         for (k, v) in StorageHistory::encode(1.into(), &ch) {
-            c.put(k, v).await.unwrap()
+            cs.upsert(k, v).unwrap()
         }
 
         assert_eq!(
             StorageHistory::find(&mut cs, 1.into(), (contract_a, key1))
-                .await
                 .unwrap()
                 .unwrap(),
             val1
@@ -324,7 +297,6 @@ mod tests {
 
         assert_eq!(
             StorageHistory::find(&mut cs, 1.into(), (contract_b, key3))
-                .await
                 .unwrap()
                 .unwrap(),
             val3
@@ -332,23 +304,18 @@ mod tests {
 
         assert_eq!(
             StorageHistory::find(&mut cs, 1.into(), (contract_a, key5))
-                .await
                 .unwrap()
                 .unwrap(),
             val5
         );
 
         assert_eq!(
-            StorageHistory::find(&mut cs, 1.into(), (contract_d, key1))
-                .await
-                .unwrap(),
+            StorageHistory::find(&mut cs, 1.into(), (contract_d, key1)).unwrap(),
             None
         );
 
         assert_eq!(
-            StorageHistory::find(&mut cs, 1.into(), (contract_b, key7))
-                .await
-                .unwrap(),
+            StorageHistory::find(&mut cs, 1.into(), (contract_b, key7)).unwrap(),
             None
         );
     }
@@ -0,0 +1,147 @@
+use super::*;
+use crate::kv::tables::{AccountChange, StorageChange};
+use std::ops::RangeInclusive;
+
+/// Changes made by a single block, with the values from before the block.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BlockChanges {
+    pub block_number: BlockNumber,
+    pub accounts: Vec<AccountChange>,
+    pub storage: Vec<(Address, StorageChange)>,
+}
+
+/// Walks account and storage changesets in lockstep, yielding the changes of `blocks` block by block.
+/// Blocks that changed nothing are skipped.
+pub fn walk<'tx, K: TransactionKind, E: EnvironmentKind>(
+    tx: &'tx MdbxTransaction<'_, K, E>,
+    blocks: RangeInclusive<BlockNumber>,
+) -> anyhow::Result<impl Iterator<Item = anyhow::Result<BlockChanges>> + 'tx> {
+    let (start, end) = blocks.into_inner();
+    let mut accounts = tx.cursor(tables::AccountChangeSet)?.walk(Some(start));
+    let mut storage = tx.cursor(tables::StorageChangeSet)?.walk(Some(start));
+
+    Ok(TryGenIter::from(move |_| {
+        let mut next_account = accounts.next().transpose()?;
+        let mut next_storage = storage.next().transpose()?;
+
+        loop {
+            let block_number = match (&next_account, &next_storage) {
+                (Some((a, _)), Some((s, _))) => std::cmp::min(*a, s.block_number),
+                (Some((a, _)), None) => *a,
+                (None, Some((s, _))) => s.block_number,
+                (None, None) => break,
+            };
+            if block_number > end {
+                break;
+            }
+
+            let mut changes = BlockChanges {
+                block_number,
+                ..Default::default()
+            };
+            while let Some((account_block, change)) = next_account.take() {
+                if account_block != block_number {
+                    next_account = Some((account_block, change));
+                    break;
+                }
+                changes.accounts.push(change);
+                next_account = accounts.next().transpose()?;
+            }
+            while let Some((key, change)) = next_storage.take() {
+                if key.block_number != block_number {
+                    next_storage = Some((key, change));
+                    break;
+                }
+                changes.storage.push((key.address, change));
+                next_storage = storage.next().transpose()?;
+            }
+
+            yield changes;
+        }
+
+        Ok(())
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv::new_mem_database;
+
+    #[test]
+    fn walk_in_lockstep() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let address = |n| Address::from_low_u64_be(n);
+        let location = |n| H256::from_low_u64_be(n);
+
+        for (block_number, a) in [(1, 1), (1, 2), (3, 1), (6, 3)] {
+            let changes = [(address(a), None)]
+                .into_iter()
+                .collect::<AccountChangeSet>();
+            for (k, v) in AccountHistory::encode(BlockNumber(block_number), &changes) {
+                tx.set(tables::AccountChangeSet, k, v).unwrap();
+            }
+        }
+        for (block_number, a, l) in [(2, 1, 1), (3, 1, 1), (3, 2, 2), (6, 3, 3)] {
+            let changes = [((address(a), location(l)), U256::from(l))]
+                .into_iter()
+                .collect::<StorageChangeSet>();
+            for (k, v) in StorageHistory::encode(BlockNumber(block_number), &changes) {
+                tx.set(tables::StorageChangeSet, k, v).unwrap();
+            }
+        }
+
+        let changes = walk(&tx, BlockNumber(1)..=BlockNumber(5))
+            .unwrap()
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+
+        let account_change = |a| AccountChange {
+            address: address(a),
+            account: None,
+        };
+        let storage_change = |a, l| {
+            (
+                address(a),
+                StorageChange {
+                    location: location(l),
+                    value: U256::from(l),
+                },
+            )
+        };
+        assert_eq!(
+            changes,
+            vec![
+                BlockChanges {
+                    block_number: BlockNumber(1),
+                    accounts: vec![account_change(1), account_change(2)],
+                    storage: vec![],
+                },
+                BlockChanges {
+                    block_number: BlockNumber(2),
+                    accounts: vec![],
+                    storage: vec![storage_change(1, 1)],
+                },
+                BlockChanges {
+                    block_number: BlockNumber(3),
+                    accounts: vec![account_change(1)],
+                    storage: vec![storage_change(1, 1), storage_change(2, 2)],
+                },
+            ]
+        );
+
+        assert_eq!(
+            walk(&tx, BlockNumber(4)..=BlockNumber(5)).unwrap().count(),
+            0
+        );
+        assert_eq!(
+            walk(&tx, BlockNumber(6)..=BlockNumber(6))
+                .unwrap()
+                .map(|changes| changes.unwrap().block_number)
+                .collect::<Vec<_>>(),
+            vec![BlockNumber(6)]
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Compact big-endian encoding of integers and hashes: leading zero bytes are stripped on
+//! encoding and restored on decoding, so zero encodes to an empty slice.
+//!
+//! Shared by table codecs, account storage encoding, commitment and RLP serialization.
+
+use crate::kv::tables::TooLong;
+use arrayvec::ArrayVec;
+use ethereum_types::H256;
+use ethnum::U256;
+
+/// `v` without leading zero bytes.
+pub fn zeroless_view(v: &impl AsRef<[u8]>) -> &[u8] {
+    let v = v.as_ref();
+    &v[v.iter().take_while(|&&b| b == 0).count()..]
+}
+
+/// Number of bytes in compact encoding of `v`.
+pub fn compact_len_u64(v: u64) -> usize {
+    ((u64::BITS - v.leading_zeros() + 7) / 8) as usize
+}
+
+/// Number of bytes in compact encoding of `v`.
+pub fn compact_len_u256(v: U256) -> usize {
+    ((U256::BITS - v.leading_zeros() + 7) / 8) as usize
+}
+
+fn encode_compact<const LEN: usize>(v: [u8; LEN]) -> ArrayVec<u8, LEN> {
+    zeroless_view(&v).iter().copied().collect()
+}
+
+fn decode_compact<const LEN: usize>(b: &[u8]) -> anyhow::Result<[u8; LEN]> {
+    if b.len() > LEN {
+        return Err(TooLong::<LEN> { got: b.len() }.into());
+    }
+
+    let mut v = [0; LEN];
+    v[LEN - b.len()..].copy_from_slice(b);
+    Ok(v)
+}
+
+pub fn encode_compact_u64(v: u64) -> ArrayVec<u8, 8> {
+    encode_compact(v.to_be_bytes())
+}
+
+pub fn encode_compact_u256(v: U256) -> ArrayVec<u8, 32> {
+    encode_compact(v.to_be_bytes())
+}
+
+pub fn encode_compact_h256(v: H256) -> ArrayVec<u8, 32> {
+    encode_compact(v.0)
+}
+
+/// Decodes compact `u64`, leading zeroes in input are allowed.
+pub fn decode_compact_u64(b: &[u8]) -> anyhow::Result<u64> {
+    Ok(u64::from_be_bytes(decode_compact(b)?))
+}
+
+/// Decodes compact `U256`, leading zeroes in input are allowed.
+pub fn decode_compact_u256(b: &[u8]) -> anyhow::Result<U256> {
+    Ok(U256::from_be_bytes(decode_compact(b)?))
+}
+
+/// Decodes compact `H256`, leading zeroes in input are allowed.
+pub fn decode_compact_h256(b: &[u8]) -> anyhow::Result<H256> {
+    Ok(H256(decode_compact(b)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes_literal::bytes;
+    use hex_literal::hex;
+    use proptest::prelude::*;
+
+    #[test]
+    fn zeroless_view_test() {
+        assert_eq!(
+            zeroless_view(&H256::from(hex!(
+                "0000000000000000000000000000000000000000000000000000000000000000"
+            ))),
+            &bytes!("") as &[u8]
+        );
+        assert_eq!(
+            zeroless_view(&H256::from(hex!(
+                "000000000000000000000000000000000000000000000000000000000004bc00"
+            ))),
+            &bytes!("04bc00") as &[u8]
+        );
+    }
+
+    #[test]
+    fn compact_u64() {
+        for (v, encoded) in [
+            (0, &[] as &[u8]),
+            (1, &[1]),
+            (0xff, &[0xff]),
+            (0x100, &[1, 0]),
+            (0x04bc00, &[4, 0xbc, 0]),
+            (u64::MAX, &[0xff; 8]),
+        ] {
+            assert_eq!(&encode_compact_u64(v)[..], encoded);
+            assert_eq!(compact_len_u64(v), encoded.len());
+            assert_eq!(decode_compact_u64(encoded).unwrap(), v);
+        }
+
+        assert_eq!(decode_compact_u64(&[0, 0, 1]).unwrap(), 1);
+        assert!(decode_compact_u64(&[1; 9]).is_err());
+    }
+
+    #[test]
+    fn compact_u256() {
+        for (v, encoded) in [
+            (U256::ZERO, &[] as &[u8]),
+            (U256::ONE, &[1]),
+            (U256::from(0x8000_u64), &[0x80, 0]),
+            (U256::MAX, &[0xff; 32]),
+        ] {
+            assert_eq!(&encode_compact_u256(v)[..], encoded);
+            assert_eq!(compact_len_u256(v), encoded.len());
+            assert_eq!(decode_compact_u256(encoded).unwrap(), v);
+        }
+
+        assert!(decode_compact_u256(&[1; 33]).is_err());
+    }
+
+    #[test]
+    fn compact_h256() {
+        let v = H256(hex!(
+            "000000000000000000000000000000000000000000000000000000000004bc00"
+        ));
+        assert_eq!(&encode_compact_h256(v)[..], &hex!("04bc00"));
+        assert_eq!(decode_compact_h256(&hex!("04bc00")).unwrap(), v);
+        assert_eq!(&encode_compact_h256(H256::zero())[..], &[] as &[u8]);
+        assert_eq!(decode_compact_h256(&[]).unwrap(), H256::zero());
+    }
+
+    proptest! {
+        #[test]
+        fn compact_u64_roundtrip(v: u64) {
+            let encoded = encode_compact_u64(v);
+            prop_assert_eq!(encoded.len(), compact_len_u64(v));
+            prop_assert!(encoded.first().map(|&b| b != 0).unwrap_or(true));
+            prop_assert_eq!(decode_compact_u64(&encoded).unwrap(), v);
+        }
+
+        #[test]
+        fn compact_u256_roundtrip(v: [u8; 32]) {
+            let v = U256::from_be_bytes(v);
+            let encoded = encode_compact_u256(v);
+            prop_assert_eq!(encoded.len(), compact_len_u256(v));
+            prop_assert_eq!(&encoded[..], zeroless_view(&v.to_be_bytes()));
+            prop_assert_eq!(decode_compact_u256(&encoded).unwrap(), v);
+        }
+    }
+}
@@ -8,7 +8,7 @@ pub struct StartedInterrupt<'a, R> {
 }
 
 impl<'a, R> StartedInterrupt<'a, R> {
-    fn resume(self) -> Interrupt<'a, R> {
+    pub(crate) fn resume(self) -> Interrupt<'a, R> {
         resume_interrupt(self.inner, ResumeData::Empty)
     }
 }
@@ -18,7 +18,7 @@ pub struct LoadBranchInterrupt<'a, R> {
 }
 
 impl<'a, R> LoadBranchInterrupt<'a, R> {
-    fn resume(self, resume_data: BranchData) -> Interrupt<'a, R> {
+    pub(crate) fn resume(self, resume_data: BranchData) -> Interrupt<'a, R> {
         resume_interrupt(self.inner, ResumeData::BranchData(resume_data))
     }
 }
@@ -27,7 +27,7 @@ pub struct LoadAccountInterrupt<'a, R> {
 }
 
 impl<'a, R> LoadAccountInterrupt<'a, R> {
-    fn resume(self, resume_data: FilledAccount) -> Interrupt<'a, R> {
+    pub(crate) fn resume(self, resume_data: FilledAccount) -> Interrupt<'a, R> {
         resume_interrupt(self.inner, ResumeData::FilledAccount(resume_data))
     }
 }
@@ -37,7 +37,7 @@ pub struct LoadStorageInterrupt<'a, R> {
 }
 
 impl<'a, R> LoadStorageInterrupt<'a, R> {
-    fn resume(self, resume_data: FilledStorage) -> Interrupt<'a, R> {
+    pub(crate) fn resume(self, resume_data: FilledStorage) -> Interrupt<'a, R> {
         resume_interrupt(self.inner, ResumeData::FilledStorage(resume_data))
     }
 }
@@ -47,7 +47,7 @@ pub struct BranchUpdateInterrupt<'a, R> {
 }
 
 impl<'a, R> BranchUpdateInterrupt<'a, R> {
-    fn resume(self) -> Interrupt<'a, R> {
+    pub(crate) fn resume(self) -> Interrupt<'a, R> {
         resume_interrupt(self.inner, ResumeData::Empty)
     }
 }
@@ -94,6 +94,7 @@ pub enum Interrupt<'a, R> {
     },
 }
 
+#[derive(Debug)]
 pub enum InterruptData {
     LoadBranch {
         prefix: Vec<u8>,
@@ -116,7 +117,12 @@ fn resume_interrupt<R>(
     mut inner: InnerCoroutine<'_, R>,
     resume_data: ResumeData,
 ) -> Interrupt<'_, R> {
-    match Pin::new(&mut *inner).resume(resume_data) {
+    #[cfg(not(feature = "trace"))]
+    let state = Pin::new(&mut *inner).resume(resume_data);
+    #[cfg(feature = "trace")]
+    let state = crate::interrupt_trace::resume("commitment", Pin::new(&mut *inner), resume_data);
+
+    match state {
         GeneratorState::Yielded(interrupt_data) => match interrupt_data {
             InterruptData::LoadBranch { prefix } => Interrupt::LoadBranch {
                 interrupt: LoadBranchInterrupt { inner },
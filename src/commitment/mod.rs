@@ -1,13 +1,18 @@
 pub mod gen;
 pub mod rlputil;
+mod state_root;
+
+pub use self::state_root::StateRootService;
 
 use self::rlputil::*;
-use crate::{crypto::keccak256, models::*, u256_to_h256, zeroless_view};
+use crate::{codec::encode_compact_u256, crypto::keccak256, models::*};
+use anyhow::{ensure, format_err};
 use array_macro::array;
 use arrayvec::ArrayVec;
 use bytes::{BufMut, BytesMut};
 use derive_more::From;
 use gen::*;
+use rayon::prelude::*;
 use sha3::{Digest, Keccak256};
 use std::{
     collections::HashMap,
@@ -17,6 +22,15 @@ use std::{
 };
 use tracing::trace;
 
+// Parts of a cell stored in branch data, each is prefixed with its length.
+const HASHED_KEY_PART: u8 = 1;
+const ACCOUNT_PLAIN_PART: u8 = 2;
+const STORAGE_PLAIN_PART: u8 = 4;
+const HASH_PART: u8 = 8;
+
+/// Branches with fewer children are hashed on the current thread, handing them to the thread pool costs more.
+const PARALLEL_HASHING_MIN_CELLS: u32 = 4;
+
 #[derive(Clone, Debug)]
 pub struct Cell {
     h: Option<H256>,              // Cell hash
@@ -56,8 +70,8 @@ impl Cell {
             } else {
                 (0, 1)
             };
-            let storage_val = self.storage.map(u256_to_h256).unwrap_or_default();
-            let val = RlpSerializableBytes(zeroless_view(&storage_val));
+            let storage_val = encode_compact_u256(self.storage.unwrap_or_default());
+            let val = RlpSerializableBytes(&storage_val);
             let total_len = kp + kl + val.double_rlp_len();
             let pt = generate_struct_len(total_len).len();
             if total_len + pt < KECCAK_LENGTH {
@@ -67,75 +81,204 @@ impl Cell {
         KECCAK_LENGTH + 1
     }
 
-    // fn account_for_hashing(&self, storage_root_hash: H256) -> ArrayVec<u8, 128> {
-    //     let mut buffer = ArrayVec::new();
-
-    //     let mut balanceBytes = 0;
-    //     if self.balance >= 128 {
-    //         balanceBytes = ((U256::BITS - self.balance.leading_zeros() + 7) / 8) as u8;
-    //     }
-
-    //     let mut nonceBytes = 0;
-    //     if self.nonce < 128 && self.nonce != 0 {
-    //         nonceBytes = 0;
-    //     } else {
-    //         nonceBytes = (((U256::BITS - self.nonce.leading_zeros()) + 7) / 8) as u8;
-    //     }
-
-    //     let mut structLength = balanceBytes + nonceBytes + 2;
-    //     structLength += 66; // Two 32-byte arrays + 2 prefixes
-
-    //     if structLength < 56 {
-    //         buffer.try_push(192 + structLength).unwrap();
-    //     } else {
-    //         let lengthBytes = ((u8::BITS - structLength.leading_zeros() + 7) / 8) as u8;
-    //         buffer.try_push(247 + lengthBytes).unwrap();
-
-    //         let mut i = lengthBytes;
-    //         while i > 0 {
-    //             buffer.try_push(structLength as u8);
-    //             structLength >>= 8;
-    //             i -= 1;
-    //         }
-    //     }
-
-    // // Encoding nonce
-    // if cell.Nonce < 128 && cell.Nonce != 0 {
-    // 	buffer[pos] = byte(cell.Nonce)
-    // } else {
-    // 	buffer[pos] = byte(128 + nonceBytes)
-    // 	var nonce = cell.Nonce
-    // 	for i := nonceBytes; i > 0; i-- {
-    // 		buffer[pos+i] = byte(nonce)
-    // 		nonce >>= 8
-    // 	}
-    // }
-    // pos += 1 + nonceBytes
-
-    // // Encoding balance
-    // if cell.Balance.LtUint64(128) && !cell.Balance.IsZero() {
-    // 	buffer[pos] = byte(cell.Balance.Uint64())
-    // 	pos++
-    // } else {
-    // 	buffer[pos] = byte(128 + balanceBytes)
-    // 	pos++
-    // 	cell.Balance.WriteToSlice(buffer[pos : pos+balanceBytes])
-    // 	pos += balanceBytes
-    // }
-
-    // // Encoding Root and CodeHash
-    // buffer[pos] = 128 + 32
-    // pos++
-    // copy(buffer[pos:], storageRootHash[:length.Hash])
-    // pos += 32
-    // buffer[pos] = 128 + 32
-    // pos++
-    // copy(buffer[pos:], cell.CodeHash[:])
-    // pos += 32
-    // return pos
-
-    //     buffer
-    // }
+    fn compute_hash(&mut self, depth: usize) -> H256 {
+        let cell = self;
+        let mut storage_root = None;
+        if let Some((address, location)) = cell.spk {
+            let mut spk = [0; 52];
+            // storage plain key is the address followed by the location
+            spk[..20].copy_from_slice(&address.0);
+            spk[20..].copy_from_slice(&location.0);
+            let hashed_key_offset = depth.saturating_sub(64);
+            let singleton = depth <= 64;
+            cell.down_hashed_key.clear();
+            cell.down_hashed_key
+                .try_extend_from_slice(&hash_key(&spk, hashed_key_offset))
+                .unwrap();
+            cell.down_hashed_key.push(16); // Add terminator
+            if singleton {
+                trace!(
+                    "leafHashWithKeyVal(singleton) for [{}]=>[{:?}]",
+                    hex::encode(&cell.down_hashed_key[..64 - hashed_key_offset + 1]),
+                    cell.storage
+                );
+                storage_root = Some(H256::from_slice(
+                    &leaf_hash_with_key_val(
+                        &cell.down_hashed_key[..64 - hashed_key_offset + 1],
+                        RlpSerializableBytes(&cell.storage.unwrap().to_be_bytes()),
+                        true,
+                    )[1..],
+                ));
+            } else {
+                trace!(
+                    "leafHashWithKeyVal for [{}]=>[{:?}]",
+                    hex::encode(&cell.down_hashed_key[..64 - hashed_key_offset + 1]),
+                    cell.storage
+                );
+                return H256::from_slice(&leaf_hash_with_key_val(
+                    &cell.down_hashed_key[..64 - hashed_key_offset + 1],
+                    RlpSerializableBytes(&cell.storage.unwrap().to_be_bytes()),
+                    false,
+                ));
+            }
+        }
+        if let Some(apk) = cell.apk {
+            cell.down_hashed_key.clear();
+            cell.down_hashed_key
+                .try_extend_from_slice(&hash_key(&apk.0, depth))
+                .unwrap();
+            cell.down_hashed_key.push(16); // Add terminator
+
+            let storage_root = storage_root.unwrap_or_else(|| {
+                if !cell.extension.is_empty() {
+                    // Extension
+                    let h = cell.h.expect("computeCellHash extension without hash");
+                    trace!(
+                        "extension_hash for [{}]=>[{:?}]\n",
+                        hex::encode(&cell.extension),
+                        h
+                    );
+                    extension_hash(&cell.extension, h)
+                } else if let Some(h) = cell.h {
+                    h
+                } else {
+                    EMPTY_ROOT
+                }
+            });
+            let account_rlp = cell.account_for_hashing(storage_root);
+            trace!(
+                "accountLeafHashWithKey for [{}]=>[{}]\n",
+                hex::encode(&cell.down_hashed_key[..65 - depth]),
+                hex::encode(&account_rlp)
+            );
+            return account_leaf_hash_with_key(
+                &cell.down_hashed_key[..65 - depth],
+                RlpEncodableBytes(&account_rlp),
+            );
+        }
+
+        if !cell.extension.is_empty() {
+            // Extension
+            let h = cell.h.expect("computeCellHash extension without hash");
+            trace!(
+                "extension_hash for [{}]=>[{:?}]",
+                hex::encode(&cell.extension),
+                h
+            );
+            extension_hash(&cell.extension, h)
+        } else if let Some(h) = cell.h {
+            h
+        } else {
+            EMPTY_ROOT
+        }
+    }
+
+    fn fill_empty(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Restores hashed key below the cell for leaves, so that other keys with common prefix can be unfolded from it.
+    fn derive_hashed_keys(&mut self, depth: usize) {
+        if !self.down_hashed_key.is_empty() || depth >= 64 {
+            return;
+        }
+
+        let hashed_key = if let Some(apk) = self.apk {
+            hash_key(&apk.0, depth)
+        } else if let Some((address, location)) = self.spk {
+            let mut spk = [0; ADDRESS_LENGTH + KECCAK_LENGTH];
+            spk[..ADDRESS_LENGTH].copy_from_slice(&address.0);
+            spk[ADDRESS_LENGTH..].copy_from_slice(&location.0);
+            hash_key(&spk, depth)
+        } else {
+            return;
+        };
+
+        self.down_hashed_key
+            .try_extend_from_slice(&hashed_key)
+            .unwrap();
+        self.down_hashed_key.push(16); // Add terminator
+    }
+
+    /// Appends parts of the cell persisted in branch data, returns flags of the parts written.
+    fn encode_fields(&self, out: &mut Vec<u8>) -> u8 {
+        let mut field_bits = 0;
+        if !self.extension.is_empty() && self.spk.is_none() {
+            field_bits |= HASHED_KEY_PART;
+            put_part(out, &self.extension);
+        }
+        if let Some(apk) = self.apk {
+            field_bits |= ACCOUNT_PLAIN_PART;
+            put_part(out, &apk.0);
+        }
+        if let Some((address, location)) = self.spk {
+            field_bits |= STORAGE_PLAIN_PART;
+            let mut spk = [0; ADDRESS_LENGTH + KECCAK_LENGTH];
+            spk[..ADDRESS_LENGTH].copy_from_slice(&address.0);
+            spk[ADDRESS_LENGTH..].copy_from_slice(&location.0);
+            put_part(out, &spk);
+        }
+        if let Some(h) = self.h {
+            field_bits |= HASH_PART;
+            put_part(out, &h.0);
+        }
+        field_bits
+    }
+
+    /// Fills the cell from parts written by `encode_fields`, returns number of bytes consumed.
+    fn fill_from_fields(&mut self, data: &[u8], field_bits: u8) -> anyhow::Result<usize> {
+        let mut rest = data;
+
+        self.extension.clear();
+        self.down_hashed_key.clear();
+        if field_bits & HASHED_KEY_PART != 0 {
+            let extension = take_part(&mut rest)?;
+            self.extension
+                .try_extend_from_slice(extension)
+                .map_err(|_| format_err!("extension too long: {}", extension.len()))?;
+            self.down_hashed_key
+                .try_extend_from_slice(extension)
+                .unwrap();
+        }
+
+        self.apk = if field_bits & ACCOUNT_PLAIN_PART != 0 {
+            Some(Address::from_slice(take_fixed_part(
+                &mut rest,
+                ADDRESS_LENGTH,
+            )?))
+        } else {
+            None
+        };
+
+        self.spk = if field_bits & STORAGE_PLAIN_PART != 0 {
+            let spk = take_fixed_part(&mut rest, ADDRESS_LENGTH + KECCAK_LENGTH)?;
+            Some((
+                Address::from_slice(&spk[..ADDRESS_LENGTH]),
+                H256::from_slice(&spk[ADDRESS_LENGTH..]),
+            ))
+        } else {
+            None
+        };
+
+        self.h = if field_bits & HASH_PART != 0 {
+            Some(H256::from_slice(take_fixed_part(&mut rest, KECCAK_LENGTH)?))
+        } else {
+            None
+        };
+
+        Ok(data.len() - rest.len())
+    }
+
+    /// RLP of the account leaf value.
+    fn account_for_hashing(&self, storage_root: H256) -> ArrayVec<u8, MAX_RLP_ACCOUNT_LEN> {
+        RlpAccount {
+            nonce: self.nonce,
+            balance: self.balance,
+            storage_root,
+            code_hash: self.code_hash,
+        }
+        .encode_fixed()
+    }
 }
 
 #[derive(Debug)]
@@ -267,7 +410,55 @@ impl CellGrid {
     }
 }
 
-fn hash_key(plain_key: &[u8], hashed_key_offset: usize) -> ArrayVec<u8, 32> {
+// Part lengths are uvarints, but no part is longer than 127 bytes, so they always take a single byte.
+fn put_part(out: &mut Vec<u8>, part: &[u8]) {
+    assert!(part.len() < 0x80);
+    out.push(part.len() as u8);
+    out.extend_from_slice(part);
+}
+
+fn take_part<'a>(data: &mut &'a [u8]) -> anyhow::Result<&'a [u8]> {
+    let (&len, rest) = data
+        .split_first()
+        .ok_or_else(|| format_err!("missing part length"))?;
+    ensure!(len < 0x80, "part length does not fit into single byte");
+    let len = len as usize;
+    ensure!(
+        rest.len() >= len,
+        "part truncated: {} < {}",
+        rest.len(),
+        len
+    );
+
+    let (part, rest) = rest.split_at(len);
+    *data = rest;
+    Ok(part)
+}
+
+fn take_fixed_part<'a>(data: &mut &'a [u8], expected_len: usize) -> anyhow::Result<&'a [u8]> {
+    let part = take_part(data)?;
+    ensure!(
+        part.len() == expected_len,
+        "invalid part length: {} != {}",
+        part.len(),
+        expected_len
+    );
+    Ok(part)
+}
+
+fn key_nibbles(hashed_key: H256) -> ArrayVec<u8, 128> {
+    hashed_key
+        .0
+        .iter()
+        .flat_map(|&b| [b >> 4, b & 0xf])
+        .collect()
+}
+
+fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+fn hash_key(plain_key: &[u8], hashed_key_offset: usize) -> ArrayVec<u8, 64> {
     let hash_buf = keccak256(plain_key).0;
     let mut hash_buf = &hash_buf[hashed_key_offset / 2..];
     let mut dest = ArrayVec::new();
@@ -301,16 +492,7 @@ pub struct HexPatriciaHashed {
     before_bitmap: [u16; 128], // For each row, bitmap of cells that were present before modification
     mod_bitmap: [u16; 128],    // For each row, bitmap of cells that were modified (not deleted)
     del_bitmap: [u16; 128],    // For each row, bitmap of cells that were deleted
-    // Function used to load branch node and fill up the cells
-    // For each cell, it sets the cell type, clears the modified flag, fills the hash,
-    // and for the extension, account, and leaf type, the `l` and `k`
-    // branchFn: Box<dyn Fn(prefix: &[u8]) -> func(prefix []byte) ([]byte, error)
-    // Function used to fetch account with given plain key. It loads
-    // accountFn func(plainKey []byte, cell *Cell) error
-    // Function used to fetch account with given plain key
-    // storageFn       func(plainKey []byte, cell *Cell) error
-    // keccak          keccakState
-    // keccak2         keccakState
+    // Branch nodes, accounts and storage are loaded through `LoadBranch`, `LoadAccount` and `LoadStorage` interrupts
     account_key_len: usize,
     byte_array_writer: BytesMut,
     key_prefix: ArrayVec<u8, 1>,
@@ -346,6 +528,14 @@ struct CellPosition {
     col: usize,
 }
 
+/// Row that is unfolded from a stored branch node.
+#[derive(Clone, Copy, Debug)]
+struct BranchToLoad {
+    row: usize,
+    deleted: bool,
+    depth: usize,
+}
+
 #[derive(Clone, Debug)]
 pub struct UpdateFlags {
     pub code: bool,
@@ -376,14 +566,14 @@ impl HexPatriciaHashed {
         if let Some(root) = self.grid.cell_mut(None).h {
             root
         } else {
-            self.compute_cell_hash(None, 0)
+            self.grid.root.compute_hash(0)
         }
     }
 
     pub fn process_updates(
         &mut self,
         updates: Vec<ProcessUpdateArg>,
-    ) -> StartedInterrupt<'_, HashMap<Vec<u8>, Vec<u8>>> {
+    ) -> StartedInterrupt<'_, anyhow::Result<HashMap<Vec<u8>, Vec<u8>>>> {
         let inner = move |_| {
             let mut branch_node_updates = HashMap::new();
 
@@ -400,19 +590,68 @@ impl HexPatriciaHashed {
                     hex::encode(&self.current_key),
                     update
                 );
+                let hashed_key = key_nibbles(hashed_key);
 
                 // Keep folding until the currentKey is the prefix of the key we modify
-                while self.need_folding(hashed_key) {
+                while self.need_folding(&hashed_key) {
                     let (branch_node_update, update_key) = self.fold();
                     if let Some(branch_node_update) = branch_node_update {
                         branch_node_updates.insert(update_key, branch_node_update);
                     }
                 }
+
+                // Now unfold until we step on an empty cell
+                loop {
+                    let unfolding = self.need_unfolding(&hashed_key);
+                    if unfolding == 0 {
+                        break;
+                    }
+
+                    let Some(branch) = self.unfold(&hashed_key, unfolding) else {
+                        continue;
+                    };
+
+                    let prefix = hex_to_compact(&self.current_key);
+                    let ResumeData::BranchData(BranchData(branch_data)) =
+                        (yield InterruptData::LoadBranch { prefix }) else {
+                        unreachable!()
+                    };
+
+                    for pos in self.unfold_branch_node(branch, &branch_data)? {
+                        let cell = self.grid.grid_cell_mut(pos).clone();
+                        if let Some(apk) = cell.apk {
+                            let ResumeData::FilledAccount(FilledAccount(cell)) =
+                                (yield InterruptData::LoadAccount {
+                                    plain_key: apk.0.to_vec(),
+                                    cell,
+                                }) else {
+                                unreachable!()
+                            };
+                            *self.grid.grid_cell_mut(pos) = cell;
+                        } else if let Some((address, location)) = cell.spk {
+                            let mut plain_key = address.0.to_vec();
+                            plain_key.extend_from_slice(&location.0);
+                            let ResumeData::FilledStorage(FilledStorage(cell)) =
+                                (yield InterruptData::LoadStorage { plain_key, cell }) else {
+                                unreachable!()
+                            };
+                            *self.grid.grid_cell_mut(pos) = cell;
+                        }
+                    }
+                }
+
+                self.update_cell(&hashed_key, &plain_key, update);
             }
 
-            yield InterruptData::LoadBranch { prefix: vec![] };
+            // Fold everything up to the root
+            while self.active_rows > 0 {
+                let (branch_node_update, update_key) = self.fold();
+                if let Some(branch_node_update) = branch_node_update {
+                    branch_node_updates.insert(update_key, branch_node_update);
+                }
+            }
 
-            branch_node_updates
+            Ok(branch_node_updates)
         };
 
         StartedInterrupt {
@@ -420,113 +659,241 @@ impl HexPatriciaHashed {
         }
     }
 
-    fn compute_cell_hash(&mut self, pos: Option<CellPosition>, depth: usize) -> H256 {
-        let hash = EMPTY_ROOT;
+    fn need_folding(&self, hashed_key: &[u8]) -> bool {
+        !hashed_key.starts_with(&self.current_key[..])
+    }
 
-        let cell = self.grid.cell_mut(pos);
-        let mut storage_root = None;
-        if let Some((address, location)) = cell.spk {
-            let mut spk = [0; 52];
-            // ????
-            spk[..20].copy_from_slice(&address.0);
-            spk[20..].copy_from_slice(&location.0);
-            let hashed_key_offset = depth.saturating_sub(64);
-            let singleton = depth <= 64;
-            cell.down_hashed_key.clear();
-            cell.down_hashed_key
-                .try_extend_from_slice(&hash_key(&spk, hashed_key_offset))
-                .unwrap();
-            cell.down_hashed_key[64 - hashed_key_offset] = 16; // Add terminator
-            if singleton {
-                trace!(
-                    "leafHashWithKeyVal(singleton) for [{}]=>[{:?}]",
-                    hex::encode(&cell.down_hashed_key[..64 - hashed_key_offset + 1]),
-                    cell.storage
-                );
-                storage_root = Some(H256::from_slice(
-                    &leaf_hash_with_key_val(
-                        &cell.down_hashed_key[..64 - hashed_key_offset + 1],
-                        RlpSerializableBytes(&cell.storage.unwrap().to_be_bytes()),
-                        true,
-                    )[1..],
-                ));
-            } else {
-                trace!(
-                    "leafHashWithKeyVal for [{}]=>[{:?}]",
-                    hex::encode(&cell.down_hashed_key[..64 - hashed_key_offset + 1]),
-                    cell.storage
-                );
-                return H256::from_slice(&leaf_hash_with_key_val(
-                    &cell.down_hashed_key[..64 - hashed_key_offset + 1],
-                    RlpSerializableBytes(&cell.storage.unwrap().to_be_bytes()),
-                    false,
-                ));
+    /// Number of nibbles the grid has to be unfolded by to reach the cell of `hashed_key`, 0 if none.
+    fn need_unfolding(&self, hashed_key: &[u8]) -> usize {
+        let (cell, depth) = if self.active_rows == 0 {
+            let root = &self.grid.root;
+            if root.down_hashed_key.is_empty() && root.h.is_none() {
+                // Root is either empty or not loaded yet, row 0 is unfolded in both cases
+                return 1;
             }
+            (root, 0)
+        } else {
+            let Some(&col) = hashed_key.get(self.current_key.len()) else {
+                return 0;
+            };
+            (
+                &self.grid.grid[self.active_rows - 1][col as usize],
+                self.depths[self.active_rows - 1],
+            )
+        };
+
+        if cell.down_hashed_key.is_empty() {
+            // Either an empty cell or a branch node that has to be loaded
+            return if cell.h.is_none() { 0 } else { 1 };
         }
-        if let Some(apk) = cell.apk {
-            cell.down_hashed_key.clear();
-            cell.down_hashed_key
-                .try_extend_from_slice(&hash_key(&apk.0, depth))
-                .unwrap();
-            cell.down_hashed_key[64 - depth] = 16; // Add terminator
 
-            let storage_root = storage_root.unwrap_or_else(|| {
-                if !cell.extension.is_empty() {
-                    // Extension
-                    let h = cell.h.expect("computeCellHash extension without hash");
-                    trace!(
-                        "extension_hash for [{}]=>[{:?}]\n",
-                        hex::encode(&cell.extension),
-                        h
-                    );
-                    extension_hash(&cell.extension, h)
-                } else if let Some(h) = cell.h {
-                    h
-                } else {
-                    EMPTY_ROOT
-                }
-            });
-            let account_rlp = rlp::encode(&RlpAccount {
-                storage_root,
-                nonce: cell.nonce,
-                balance: cell.balance,
-                code_hash: cell.code_hash,
+        let cpl = common_prefix_len(
+            &hashed_key[depth..],
+            &cell.down_hashed_key[..cell.down_hashed_key.len() - 1],
+        );
+        let mut unfolding = cpl + 1;
+        if depth < 64 && depth + unfolding > 64 {
+            // Make sure unfolding always breaks at the level where storage subtrees start
+            unfolding = 64 - depth;
+        }
+        unfolding
+    }
+
+    /// Adds a row under the cell of `hashed_key` in the last active row.
+    ///
+    /// If the cell is a branch node, it has to be loaded and passed to `unfold_branch_node`.
+    fn unfold(&mut self, hashed_key: &[u8], unfolding: usize) -> Option<BranchToLoad> {
+        let (up_cell, up_depth, modified, deleted) = if self.active_rows == 0 {
+            (None, 0, self.root_mod, self.root_del)
+        } else {
+            let up_row = self.active_rows - 1;
+            let up_depth = self.depths[up_row];
+            let col = hashed_key[up_depth - 1];
+            self.current_key.push(col);
+            let bit = 1_u16 << col;
+            (
+                Some(CellPosition {
+                    row: up_row,
+                    col: col as usize,
+                }),
+                up_depth,
+                self.mod_bitmap[up_row] & bit != 0,
+                self.del_bitmap[up_row] & bit != 0,
+            )
+        };
+
+        let row = self.active_rows;
+        for cell in &mut self.grid.grid[row] {
+            cell.fill_empty();
+        }
+        self.before_bitmap[row] = 0;
+        self.mod_bitmap[row] = 0;
+        self.del_bitmap[row] = 0;
+
+        let up = self.grid.cell_mut(up_cell);
+        let down_hashed_key = up.down_hashed_key.clone();
+        let mut branch = None;
+        let depth = if down_hashed_key.is_empty() {
+            let depth = up_depth + 1;
+            if up_cell.is_some() || !self.root_checked || up.h.is_some() {
+                branch = Some(BranchToLoad {
+                    row,
+                    deleted,
+                    depth,
+                });
+            }
+            depth
+        } else {
+            let depth_increment = unfolding.min(down_hashed_key.len());
+            let depth = up_depth + depth_increment;
+            let nibble = down_hashed_key[depth_increment - 1];
+            let bit = 1_u16 << nibble;
+            self.before_bitmap[row] = bit;
+            if modified {
+                self.mod_bitmap[row] = bit;
+            }
+            if deleted {
+                self.del_bitmap[row] = bit;
+            }
+            let cell = Some(CellPosition {
+                row,
+                col: nibble as usize,
             });
-            trace!(
-                "accountLeafHashWithKey for [{}]=>[{}]\n",
-                hex::encode(&cell.down_hashed_key[..65 - depth]),
-                hex::encode(&account_rlp)
+            self.grid
+                .fill_from_upper_cell(cell, up_cell, depth, depth_increment);
+            if row >= 64 {
+                self.grid.cell_mut(cell).apk = None;
+            }
+            self.current_key
+                .try_extend_from_slice(&down_hashed_key[..depth_increment - 1])
+                .unwrap();
+            depth
+        };
+
+        self.depths[row] = depth;
+        self.active_rows += 1;
+
+        branch
+    }
+
+    /// Fills the row from stored branch node, returns cells whose accounts or storage have to be loaded.
+    fn unfold_branch_node(
+        &mut self,
+        BranchToLoad {
+            row,
+            deleted,
+            depth,
+        }: BranchToLoad,
+        branch_data: &[u8],
+    ) -> anyhow::Result<Vec<CellPosition>> {
+        trace!(
+            "unfoldBranchNode [{}], row={}, depth={}, deleted={}",
+            hex::encode(&self.current_key),
+            row,
+            depth,
+            deleted
+        );
+        if branch_data.is_empty() {
+            ensure!(
+                self.current_key.is_empty(),
+                "missing branch node at [{}]",
+                hex::encode(&self.current_key)
             );
-            account_leaf_hash_with_key(
-                &cell.down_hashed_key[..65 - depth],
-                RlpEncodableBytes(&account_rlp),
+            // Special case - empty or deleted root
+            self.root_checked = true;
+            return Ok(vec![]);
+        }
+        if self.current_key.is_empty() {
+            self.root_checked = true;
+        }
+
+        ensure!(branch_data.len() >= 2, "branch node too short");
+        let bitmap = u16::from_be_bytes([branch_data[0], branch_data[1]]);
+        let fields_pos = 2;
+        let mut pos = fields_pos + (bitmap.count_ones() as usize + 1) / 2;
+        ensure!(branch_data.len() >= pos, "branch node too short");
+
+        self.before_bitmap[row] = bitmap;
+        if deleted {
+            // All cells come as deleted
+            self.del_bitmap[row] = bitmap;
+        }
+
+        let mut to_load = Vec::new();
+        let mut bitset = bitmap;
+        let mut j = 0;
+        while bitset != 0 {
+            let bit = bitset & 0_u16.overflowing_sub(bitset).0;
+            let nibble = bit.trailing_zeros() as usize;
+            let mut field_bits = branch_data[fields_pos + j / 2];
+            if j % 2 == 1 {
+                field_bits >>= 4;
+            }
+            let cell_pos = CellPosition { row, col: nibble };
+            let cell = self.grid.grid_cell_mut(cell_pos);
+            pos += cell.fill_from_fields(&branch_data[pos..], field_bits & 0xf)?;
+            cell.derive_hashed_keys(depth);
+            if cell.apk.is_some() || cell.spk.is_some() {
+                to_load.push(cell_pos);
+            }
+            bitset ^= bit;
+            j += 1;
+        }
+
+        Ok(to_load)
+    }
+
+    /// Applies update to the cell of `hashed_key` in the last active row.
+    fn update_cell(&mut self, hashed_key: &[u8], plain_key: &[u8], update: Update) {
+        let row = self.active_rows - 1;
+        let col = hashed_key[self.current_key.len()];
+        let bit = 1_u16 << col;
+        if update.flags.delete {
+            self.del_bitmap[row] |= bit;
+            self.mod_bitmap[row] &= !bit;
+            trace!(
+                "delete delBitmap[{}]={:#018b}, modBitmap[{}]={:#018b}",
+                row,
+                self.del_bitmap[row],
+                row,
+                self.mod_bitmap[row]
             );
+            return;
+        }
+
+        self.mod_bitmap[row] |= bit;
+        self.del_bitmap[row] &= !bit;
+        trace!(
+            "update modBitmap[{}]={:#018b}, delBitmap[{}]={:#018b}",
+            row,
+            self.mod_bitmap[row],
+            row,
+            self.del_bitmap[row]
+        );
+
+        let cell = self.grid.grid_cell_mut(CellPosition {
+            row,
+            col: col as usize,
+        });
+        if plain_key.len() == ADDRESS_LENGTH {
+            cell.apk = Some(Address::from_slice(plain_key));
+            if update.flags.balance {
+                cell.balance = update.balance;
+            }
+            if update.flags.nonce {
+                cell.nonce = update.nonce;
+            }
+            if update.flags.code {
+                cell.code_hash = H256(update.code_hash_or_storage);
+            }
+        } else {
+            let (address, location) = plain_key.split_at(ADDRESS_LENGTH);
+            cell.spk = Some((Address::from_slice(address), H256::from_slice(location)));
+            if update.flags.storage {
+                cell.storage = Some(U256::from_be_bytes(update.code_hash_or_storage));
+            }
         }
-        // buf := []byte{0x80 + 32}
-        // if cell.extLen > 0 {
-        //     // Extension
-        //     if cell.hl > 0 {
-        //         if hph.trace {
-        //             fmt.Printf("extensionHash for [%x]=>[%x]\n", cell.extension[:cell.extLen], cell.h[:cell.hl])
-        //         }
-        //         if buf, err = hph.extensionHash(buf, cell.extension[:cell.extLen], cell.h[:cell.hl]); err != nil {
-        //             return nil, err
-        //         }
-        //     } else {
-        //         return nil, fmt.Errorf("computeCellHash extension without hash")
-        //     }
-        // } else if cell.hl > 0 {
-        //     buf = append(buf, cell.h[:cell.hl]...)
-        // } else {
-        //     buf = append(buf, EmptyRootHash...)
-        // }
-        // return buf, nil
-
-        hash
-    }
-
-    fn need_folding(&self, hashed_key: H256) -> bool {
-        !hashed_key[..].starts_with(&self.current_key[..])
+        cell.derive_hashed_keys(self.depths[row]);
     }
 
     pub(crate) fn fold(&mut self) -> (Option<Vec<u8>>, Vec<u8>) {
@@ -570,7 +937,7 @@ impl HexPatriciaHashed {
             self.del_bitmap[row]
         );
 
-        let bitmap = (self.before_bitmap[row] | self.mod_bitmap[row]) ^ self.del_bitmap[row];
+        let bitmap = (self.before_bitmap[row] | self.mod_bitmap[row]) & !self.del_bitmap[row];
         let parts_count = bitmap.count_ones();
         match parts_count {
             0 => {
@@ -671,17 +1038,29 @@ impl HexPatriciaHashed {
                     bitset ^= bit;
                 }
                 // Parts bitmap
-                let mut branch_data = branch_data.get_or_insert_with(Vec::new);
+                let branch_data = branch_data.insert(Vec::new());
                 branch_data.extend_from_slice(&bitmap.to_be_bytes());
-                let fields_pos = 2;
-                // Add field flags
-                let zeroes = (parts_count + 1) / 2;
-
-                if zeroes > 0 {
-                    for _ in 0..zeroes {
-                        branch_data.push(0);
-                    }
-                }
+                let fields_pos = branch_data.len();
+                // Add field flags, 4 bits per part
+                branch_data.resize(fields_pos + (parts_count as usize + 1) / 2, 0);
+
+                // Children do not depend on each other, so they can be hashed in parallel
+                let has_child = |&(nibble, _): &(usize, _)| bitmap & (1_u16 << nibble) != 0;
+                let cell_hashes = if parts_count >= PARALLEL_HASHING_MIN_CELLS {
+                    self.grid.grid[row]
+                        .par_iter_mut()
+                        .enumerate()
+                        .filter(has_child)
+                        .map(|(_, cell)| cell.compute_hash(depth))
+                        .collect::<Vec<_>>()
+                } else {
+                    self.grid.grid[row]
+                        .iter_mut()
+                        .enumerate()
+                        .filter(has_child)
+                        .map(|(_, cell)| cell.compute_hash(depth))
+                        .collect::<Vec<_>>()
+                };
 
                 let mut hasher = Keccak256::new();
                 hasher.update(&rlputil::generate_struct_len(total_branch_len));
@@ -698,8 +1077,7 @@ impl HexPatriciaHashed {
                     }
                     last_nibble = nibble + 1;
                     let cell_pos = CellPosition { row, col: nibble };
-                    let cell_hash = self.compute_cell_hash(Some(cell_pos), depth);
-                    let cell = self.grid.grid_cell_mut(cell_pos);
+                    let cell_hash = cell_hashes[j];
                     trace!(
                         "{}: computeCellHash({},{},depth={})=[{:?}]",
                         nibble,
@@ -708,78 +1086,53 @@ impl HexPatriciaHashed {
                         depth,
                         cell_hash
                     );
-                    //     if _, err = hph.keccak2.Write(cellHash); err != nil {
-                    //         return nil, nil, err
-                    //     }
-                    //     var fieldBits PartFlags
-                    //     if cell.extLen > 0 && cell.spl == 0 {
-                    //         fieldBits |= HASHEDKEY_PART
-                    //         n := binary.PutUvarint(hph.numBuf[:], uint64(cell.extLen))
-                    //         branchData = append(branchData, hph.numBuf[:n]...)
-                    //         branchData = append(branchData, cell.extension[:cell.extLen]...)
-                    //     }
-                    //     if cell.apl > 0 {
-                    //         fieldBits |= ACCOUNT_PLAIN_PART
-                    //         n := binary.PutUvarint(hph.numBuf[:], uint64(cell.apl))
-                    //         branchData = append(branchData, hph.numBuf[:n]...)
-                    //         branchData = append(branchData, cell.apk[:cell.apl]...)
-                    //     }
-                    //     if cell.spl > 0 {
-                    //         fieldBits |= STORAGE_PLAIN_PART
-                    //         n := binary.PutUvarint(hph.numBuf[:], uint64(cell.spl))
-                    //         branchData = append(branchData, hph.numBuf[:n]...)
-                    //         branchData = append(branchData, cell.spk[:cell.spl]...)
-                    //     }
-                    //     if cell.hl > 0 {
-                    //         fieldBits |= HASH_PART
-                    //         n := binary.PutUvarint(hph.numBuf[:], uint64(cell.hl))
-                    //         branchData = append(branchData, hph.numBuf[:n]...)
-                    //         branchData = append(branchData, cell.h[:cell.hl]...)
-                    //     }
-                    //     if j%2 == 1 {
-                    //         fieldBits <<= 4
-                    //     }
-                    //     branchData[fieldsPos+(j/2)] |= byte(fieldBits)
-                    bitset ^= bit;
+                    hasher.update(&[0x80 + KECCAK_LENGTH as u8]);
+                    hasher.update(&cell_hash);
 
+                    let mut field_bits =
+                        self.grid.grid_cell_mut(cell_pos).encode_fields(branch_data);
+                    if j % 2 == 1 {
+                        field_bits <<= 4;
+                    }
+                    branch_data[fields_pos + j / 2] |= field_bits;
+
+                    bitset ^= bit;
                     j += 1;
                 }
-                // for i := lastNibble; i < 17; i++ {
-                //     if _, err := hph.keccak2.Write(&[0x80]); err != nil {
-                //         return nil, nil, err
-                //     }
-                //     if hph.trace {
-                //         fmt.Printf("%x: empty(%d,%x)\n", i, row, i)
-                //     }
-                // }
-                // upCell.extLen = depth - upDepth - 1
-                // if upCell.extLen > 0 {
-                //     copy(upCell.extension[:], hph.currentKey[upDepth:hph.currentKeyLen])
-                // }
-                // if depth < 64 {
-                //     upCell.apl = 0
-                // }
-                // upCell.spl = 0
-                // upCell.hl = 32
-                // if _, err := hph.keccak2.Read(upCell.h[:]); err != nil {
-                //     return nil, nil, err
-                // }
-                // if hph.trace {
-                //     fmt.Printf("} [%x]\n", upCell.h[:])
-                // }
-                // hph.activeRows--
-                // if upDepth > 0 {
-                //     hph.currentKeyLen = upDepth - 1
-                // } else {
-                //     hph.currentKeyLen = 0
-                // }
+                for i in last_nibble..17 {
+                    hasher.update(&[0x80]);
+                    trace!("{}: empty({},{})", i, row, i);
+                }
+
+                let up_cell = self.grid.cell_mut(up_cell);
+                up_cell.extension.clear();
+                up_cell.down_hashed_key.clear();
+                if depth > up_depth + 1 {
+                    let extension = &self.current_key[up_depth..depth - 1];
+                    up_cell.extension.try_extend_from_slice(extension).unwrap();
+                    up_cell
+                        .down_hashed_key
+                        .try_extend_from_slice(extension)
+                        .unwrap();
+                }
+                if depth < 64 {
+                    up_cell.apk = None;
+                }
+                up_cell.spk = None;
+                up_cell.h = Some(H256::from_slice(&hasher.finalize()));
+                trace!("}} [{:?}]", up_cell.h);
+
+                self.active_rows -= 1;
+                if let Some(new_current_key_len) = up_depth.checked_sub(1) {
+                    self.current_key.truncate(new_current_key_len);
+                } else {
+                    self.current_key.clear();
+                }
             }
         }
-        // if branchData != nil {
-        //     if hph.trace {
-        //         fmt.Printf("fold: update key: %x\n", updateKey)
-        //     }
-        // }
+        if branch_data.is_some() {
+            trace!("fold: update key: {}", hex::encode(&update_key));
+        }
         (branch_data, update_key)
     }
 }
@@ -812,7 +1165,7 @@ fn hex_to_compact(key: &[u8]) -> Vec<u8> {
     let mut buf = vec![0; buf_len];
     buf[0] = zero_byte;
 
-    let key = &key[..key_pos];
+    let key = &key[key_pos..];
     let mut key_len = key.len();
     if has_term(key) {
         key_len -= 1;
@@ -821,79 +1174,53 @@ fn hex_to_compact(key: &[u8]) -> Vec<u8> {
     let mut key_index = 0;
     let mut buf_index = 1;
     while key_index < key_len {
-        key_index += 2;
-        buf_index += 1;
-
         if key_index == key_len - 1 {
             buf[buf_index] &= 0x0f
         } else {
             buf[buf_index] = key[key_index + 1]
         }
-        buf[buf_index] |= key[key_index] << 4
+        buf[buf_index] |= key[key_index] << 4;
+
+        key_index += 2;
+        buf_index += 1;
     }
 
     buf
 }
 
-fn account_leaf_hash_with_key(key: &[u8], val: impl RlpSerializable) -> H256 {
-    // // Compute the total length of binary representation
-    // var kp, kl int
-    // // Write key
-    // var compactLen int
-    // var ni int
-    // var compact0 byte
-    // if hasTerm(key) {
-    // 	compactLen = (len(key)-1)/2 + 1
-    // 	if len(key)&1 == 0 {
-    // 		compact0 = 48 + key[0] // Odd (1<<4) + first nibble
-    // 		ni = 1
-    // 	} else {
-    // 		compact0 = 32
-    // 	}
-    // } else {
-    // 	compactLen = len(key)/2 + 1
-    // 	if len(key)&1 == 1 {
-    // 		compact0 = 16 + key[0] // Odd (1<<4) + first nibble
-    // 		ni = 1
-    // 	}
-    // }
-    // if compactLen > 1 {
-    // 	hph.keyPrefix[0] = byte(128 + compactLen)
-    // 	kp = 1
-    // 	kl = compactLen
-    // } else {
-    // 	kl = 1
-    // }
-    // var err error
-    // var buf []byte
-    // if buf, err = hph.completeLeafHash(kp, kl, compactLen, key, compact0, ni, val, true); err != nil {
-    // 	return nil, err
-    // }
-    // return buf, nil
-    todo!()
-}
-
-fn extension_hash(key: &[u8], hash: H256) -> H256 {
-    // Compute the total length of binary representation
-    // Write key
-    let mut compact_len = 0;
-    let mut ni = 0;
-    let mut compact0 = 0;
+/// Length of compact encoding of hex `key`, its first byte and position of the first nibble not in it.
+fn compact_key_header(key: &[u8]) -> (usize, u8, usize) {
     if has_term(key) {
-        compact_len = (key.len() - 1) / 2 + 1;
+        let compact_len = (key.len() - 1) / 2 + 1;
         if key.len() & 1 == 0 {
-            compact0 = 0x30 + key[0]; // Odd: (3<<4) + first nibble
-            ni = 1;
+            (compact_len, 0x30 + key[0], 1) // Odd: (3<<4) + first nibble
         } else {
-            compact0 = 0x20;
+            (compact_len, 0x20, 0)
         }
     } else {
-        compact_len = key.len() / 2 + 1;
+        let compact_len = key.len() / 2 + 1;
         if key.len() & 1 == 1 {
-            compact0 = 0x10 + key[0]; // Odd: (1<<4) + first nibble
-            ni = 1;
+            (compact_len, 0x10 + key[0], 1) // Odd: (1<<4) + first nibble
+        } else {
+            (compact_len, 0, 0)
         }
     }
+}
+
+fn account_leaf_hash_with_key(key: &[u8], val: impl RlpSerializable) -> H256 {
+    let (compact_len, compact0, ni) = compact_key_header(key);
+    let (kp, kl) = if compact_len > 1 {
+        (Some(0x80 + compact_len as u8), compact_len)
+    } else {
+        (None, 1)
+    };
+    H256::from_slice(&complete_leaf_hash(kp, kl, compact_len, key, compact0, ni, val, true)[1..])
+}
+
+fn extension_hash(key: &[u8], hash: H256) -> H256 {
+    // Compute the total length of binary representation
+    // Write key
+    let (compact_len, compact0, mut ni) = compact_key_header(key);
     let (kp, kl) = if compact_len > 1 {
         (Some(0x80 + compact_len as u8), compact_len)
     } else {
@@ -908,7 +1235,7 @@ fn extension_hash(key: &[u8], hash: H256) -> H256 {
     }
     hasher.update(&[compact0]);
     if compact_len > 1 {
-        for i in 1..compact_len {
+        for _ in 1..compact_len {
             hasher.update(&[key[ni] * 16 + key[ni + 1]]);
             ni += 2
         }
@@ -919,6 +1246,7 @@ fn extension_hash(key: &[u8], hash: H256) -> H256 {
     H256::from_slice(&hasher.finalize())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn complete_leaf_hash(
     kp: Option<u8>,
     kl: usize,
@@ -940,7 +1268,7 @@ fn complete_leaf_hash(
             buf.put_u8(kp);
         }
         buf.put_u8(compact0);
-        for i in 1..compact_len {
+        for _ in 1..compact_len {
             buf.put_u8(key[ni] * 16 + key[ni + 1]);
             ni += 2
         }
@@ -954,7 +1282,7 @@ fn complete_leaf_hash(
             hasher.update(&[kp]);
         }
         hasher.update(&[compact0]);
-        for i in 1..compact_len {
+        for _ in 1..compact_len {
             hasher.update(&[key[ni] * 16 + key[ni + 1]]);
             ni += 2;
         }
@@ -986,3 +1314,131 @@ fn leaf_hash_with_key_val(
     };
     complete_leaf_hash(kp, kl, compact_len, key, compact0, ni, val, singleton)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    #[test]
+    fn compact_keys() {
+        assert_eq!(hex_to_compact(&[]), vec![0x00]);
+        assert_eq!(hex_to_compact(&[1, 2]), vec![0x00, 0x12]);
+        assert_eq!(hex_to_compact(&[1, 2, 3]), vec![0x11, 0x23]);
+        assert_eq!(hex_to_compact(&[1, 2, 16]), vec![0x20, 0x12]);
+        assert_eq!(hex_to_compact(&[1, 2, 3, 16]), vec![0x31, 0x23]);
+    }
+
+    #[test]
+    fn cell_fields_roundtrip() {
+        let cells = [
+            Cell {
+                h: Some(H256::repeat_byte(0xaa)),
+                extension: [1, 2, 3].into_iter().collect(),
+                ..Default::default()
+            },
+            Cell {
+                apk: Some(Address::repeat_byte(0xbb)),
+                h: Some(H256::repeat_byte(0xcc)),
+                ..Default::default()
+            },
+            Cell {
+                spk: Some((Address::repeat_byte(0xdd), H256::repeat_byte(0xee))),
+                // not stored for storage cells
+                extension: [4].into_iter().collect(),
+                ..Default::default()
+            },
+        ];
+
+        for cell in cells {
+            let mut data = vec![];
+            let field_bits = cell.encode_fields(&mut data);
+            data.push(0xff);
+
+            let mut decoded = Cell::default();
+            assert_eq!(
+                decoded.fill_from_fields(&data, field_bits).unwrap(),
+                data.len() - 1
+            );
+            assert_eq!(decoded.h, cell.h);
+            assert_eq!(decoded.apk, cell.apk);
+            assert_eq!(decoded.spk, cell.spk);
+            if cell.spk.is_none() {
+                assert_eq!(decoded.extension, cell.extension);
+                assert_eq!(decoded.down_hashed_key, cell.extension);
+            } else {
+                assert!(decoded.extension.is_empty());
+            }
+        }
+
+        assert!(Cell::default().fill_from_fields(&[], HASH_PART).is_err());
+        assert!(Cell::default()
+            .fill_from_fields(&[31; 32], HASH_PART)
+            .is_err());
+    }
+
+    #[test]
+    fn unfold_stored_branch() {
+        let mut hph = HexPatriciaHashed::default();
+        let address = Address::repeat_byte(0x11);
+
+        let mut branch_data = 0b1000_0000_0000_0110_u16.to_be_bytes().to_vec();
+        branch_data.extend_from_slice(&[HASH_PART | (ACCOUNT_PLAIN_PART << 4), 0]);
+        put_part(&mut branch_data, &H256::repeat_byte(0x22).0);
+        put_part(&mut branch_data, &address.0);
+        put_part(&mut branch_data, &[0xf; 3]);
+
+        // Cell 15 has no parts yet, so trailing bytes are left alone.
+        assert!(hph
+            .unfold_branch_node(
+                BranchToLoad {
+                    row: 0,
+                    deleted: false,
+                    depth: 1,
+                },
+                &branch_data,
+            )
+            .is_ok());
+
+        branch_data[3] = HASHED_KEY_PART | HASH_PART;
+        branch_data.extend_from_slice(&hex!(
+            "20 3333333333333333333333333333333333333333333333333333333333333333"
+        ));
+        let to_load = hph
+            .unfold_branch_node(
+                BranchToLoad {
+                    row: 0,
+                    deleted: true,
+                    depth: 1,
+                },
+                &branch_data,
+            )
+            .unwrap();
+
+        assert!(hph.root_checked);
+        assert_eq!(hph.before_bitmap[0], 0b1000_0000_0000_0110);
+        assert_eq!(hph.del_bitmap[0], 0b1000_0000_0000_0110);
+        assert_eq!(to_load, vec![CellPosition { row: 0, col: 2 }]);
+
+        let cell = &hph.grid.grid[0][1];
+        assert_eq!(cell.h, Some(H256::repeat_byte(0x22)));
+        assert_eq!(cell.apk, None);
+        let cell = &hph.grid.grid[0][2];
+        assert_eq!(cell.apk, Some(address));
+        assert_eq!(cell.h, None);
+        let cell = &hph.grid.grid[0][15];
+        assert_eq!(&cell.extension[..], &[0xf; 3]);
+        assert_eq!(cell.h, Some(H256::repeat_byte(0x33)));
+
+        assert!(hph
+            .unfold_branch_node(
+                BranchToLoad {
+                    row: 0,
+                    deleted: false,
+                    depth: 1,
+                },
+                &branch_data[..branch_data.len() - 1],
+            )
+            .is_err());
+    }
+}
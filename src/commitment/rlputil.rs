@@ -1,15 +1,12 @@
-use crate::zeroless_view;
 use arrayvec::ArrayVec;
-use bytes::BufMut;
-use ethnum::U256;
-use num_traits::PrimInt;
-use std::{borrow::Borrow, io::Write};
+use std::io::Write;
 
 // generateRlpPrefixLenDouble calculates the length of RLP prefix to encode a string of bytes of length l "twice",
 // meaning that it is the prefix for rlp(rlp(data))
 pub(crate) fn generate_rlp_prefix_len_double(l: usize, first_byte: u8) -> usize {
     if l < 2 {
-        // first_byte only matters when there is 1 byte to encode
+        // first_byte only matters when there is 1 byte to encode,
+        // pass 0x80 for empty string
         if first_byte >= 0x80 {
             2
         } else {
@@ -123,8 +120,9 @@ fn generate_byte_array_len_double(l: usize) -> ArrayVec<u8, 8> {
     buffer
 }
 
-fn generate_rlp_prefix_len(l: usize) -> usize {
-    if l < 2 {
+fn generate_rlp_prefix_len(l: usize, first_byte: u8) -> usize {
+    if l == 1 && first_byte < 0x80 {
+        // single byte below 0x80 is its own encoding
         0
     } else if l < 56 {
         1
@@ -137,12 +135,13 @@ fn generate_rlp_prefix_len(l: usize) -> usize {
     }
 }
 
-// // RlpSerializable is a value that can be double-RLP coded.
+/// Value that can be double-RLP coded.
 pub trait RlpSerializable {
     fn to_double_rlp<W: Write>(&self, w: &mut W);
     fn double_rlp_len(&self) -> usize;
 }
 
+/// Byte string that is written as `rlp(rlp(bytes))`.
 pub struct RlpSerializableBytes<'a>(pub &'a [u8]);
 
 impl<'a> RlpSerializable for RlpSerializableBytes<'a> {
@@ -150,14 +149,12 @@ impl<'a> RlpSerializable for RlpSerializableBytes<'a> {
         encode_bytes_as_rlp_to_writer(self.0, w, generate_byte_array_len_double)
     }
     fn double_rlp_len(&self) -> usize {
-        if let Some(&first_byte) = self.0.get(0) {
-            generate_rlp_prefix_len_double(self.0.len(), first_byte) + self.0.len()
-        } else {
-            0
-        }
+        generate_rlp_prefix_len_double(self.0.len(), self.0.first().copied().unwrap_or(0x80))
+            + self.0.len()
     }
 }
 
+/// Byte string that is written as `rlp(bytes)`, e.g. when `bytes` already is an RLP encoded structure.
 pub struct RlpEncodableBytes<'a>(pub &'a [u8]);
 
 impl<'a> RlpSerializable for RlpEncodableBytes<'a> {
@@ -166,7 +163,8 @@ impl<'a> RlpSerializable for RlpEncodableBytes<'a> {
     }
 
     fn double_rlp_len(&self) -> usize {
-        generate_rlp_prefix_len(self.0.len()) + self.0.len()
+        generate_rlp_prefix_len(self.0.len(), self.0.first().copied().unwrap_or(0x80))
+            + self.0.len()
     }
 }
 
@@ -175,8 +173,8 @@ fn encode_bytes_as_rlp_to_writer(
     w: &mut impl Write,
     prefix_gen_func: fn(usize) -> ArrayVec<u8, 8>,
 ) {
-    // > 1 byte, write a prefix or prefixes first
-    if source.len() > 1 || (source.len() == 1 && source[0] >= 0x80) {
+    // unless it's a single byte below 0x80, write a prefix or prefixes first
+    if source.len() != 1 || source[0] >= 0x80 {
         let prefix_buf = prefix_gen_func(source.len());
 
         w.write_all(&prefix_buf).unwrap();
@@ -207,4 +205,75 @@ pub(crate) fn generate_struct_len(l: usize) -> ArrayVec<u8, 4> {
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn check(data: &[u8]) {
+        let single = rlp::encode(&data).to_vec();
+        let double = rlp::encode(&&*single).to_vec();
+
+        let mut out = vec![];
+        RlpEncodableBytes(data).to_double_rlp(&mut out);
+        assert_eq!(out, single, "single RLP, len {}", data.len());
+        assert_eq!(
+            RlpEncodableBytes(data).double_rlp_len(),
+            single.len(),
+            "single RLP length, len {}",
+            data.len()
+        );
+
+        let mut out = vec![];
+        RlpSerializableBytes(data).to_double_rlp(&mut out);
+        assert_eq!(out, double, "double RLP, len {}", data.len());
+        assert_eq!(
+            RlpSerializableBytes(data).double_rlp_len(),
+            double.len(),
+            "double RLP length, len {}",
+            data.len()
+        );
+    }
+
+    #[test]
+    fn single_byte() {
+        for b in 0..=u8::MAX {
+            check(&[b]);
+        }
+    }
+
+    #[test]
+    fn length_classes() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for len in [
+            0, 2, 3, 54, 55, 56, 57, 253, 254, 255, 256, 257, 65532, 65533, 65534, 65535, 65536,
+            65537, 100_000,
+        ] {
+            let data = (0..len).map(|_| rng.gen()).collect::<Vec<u8>>();
+            check(&data);
+        }
+    }
+
+    #[test]
+    fn struct_len() {
+        for len in [0, 1, 55, 56, 255, 256, 65535, 65536, 100_000] {
+            let mut s = rlp::RlpStream::new_list(1);
+            s.append_raw(&vec![0; len], 1);
+            let out = s.out();
+
+            assert_eq!(
+                &generate_struct_len(len)[..],
+                &out[..out.len() - len],
+                "len {}",
+                len
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn random_bytes(data in prop::collection::vec(any::<u8>(), 0..1024)) {
+            check(&data);
+        }
+    }
+}
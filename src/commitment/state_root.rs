@@ -0,0 +1,768 @@
+use super::{gen::*, *};
+use crate::{
+    changeset,
+    codec::compact_len_u256,
+    consensus::ValidationError,
+    crypto::keccak256_batch,
+    execution::continuation::interrupt::{Interrupt as ExecutionInterrupt, StateRootHashInterrupt},
+    kv::{mdbx::*, tables},
+    State,
+};
+use anyhow::bail;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ops::RangeInclusive,
+};
+
+/// Answers `StateRootHash` interrupts of block execution.
+///
+/// Account and storage changes reported through `UpdateAccount`/`UpdateStorage` interrupts are
+/// accumulated here until the root is requested, or picked up from the changesets of executed
+/// blocks. Then they are folded into `HexPatriciaHashed`, which loads untouched branches from
+/// `CommitmentBranch` and writes modified ones back, so that every subsequent root computation
+/// only touches the changed part of the trie. Untouched leaves are loaded from the plain state.
+#[derive(Debug, Default)]
+pub struct StateRootService {
+    hph: HexPatriciaHashed,
+    /// Keyed by plain keys, which are hashed in a batch when the root is requested.
+    accounts: BTreeMap<Address, Option<Account>>,
+    storage: BTreeMap<(Address, H256), U256>,
+    /// Slots put into the trie by this service, which may not be in the database yet.
+    trie_storage: HashMap<Address, BTreeSet<H256>>,
+}
+
+impl StateRootService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update_account(&mut self, address: Address, current: Option<Account>) {
+        self.accounts.insert(address, current);
+    }
+
+    pub fn update_storage(&mut self, address: Address, location: U256, current: U256) {
+        self.storage
+            .insert((address, u256_to_h256(location)), current);
+    }
+
+    /// Deletes every slot of `address` from the trie, both the pending ones and the ones
+    /// committed already.
+    pub fn erase_storage<E: EnvironmentKind>(
+        &mut self,
+        tx: &MdbxTransaction<'_, RW, E>,
+        address: Address,
+    ) -> anyhow::Result<()> {
+        let mut locations = self.trie_storage.remove(&address).unwrap_or_default();
+        for res in tx.cursor(tables::Storage)?.walk_dup(address) {
+            locations.insert(res?.0);
+        }
+        for (_, value) in self
+            .storage
+            .range_mut((address, H256::zero())..=(address, H256::repeat_byte(0xff)))
+        {
+            *value = U256::ZERO;
+        }
+        for location in locations {
+            self.storage.insert((address, location), U256::ZERO);
+        }
+
+        Ok(())
+    }
+
+    /// Picks up the changes of executed `blocks` from their changesets, with the current values
+    /// from the plain state.
+    pub fn update_from_changesets<E: EnvironmentKind>(
+        &mut self,
+        tx: &MdbxTransaction<'_, RW, E>,
+        blocks: RangeInclusive<BlockNumber>,
+    ) -> anyhow::Result<()> {
+        for changes in changeset::walk(tx, blocks)? {
+            let changes = changes?;
+            for change in changes.accounts {
+                let current = tx.get(tables::Account, change.address)?;
+                self.accounts.insert(change.address, current);
+            }
+            for (address, change) in changes.storage {
+                let current = read_storage(tx, address, change.location)?.unwrap_or(U256::ZERO);
+                self.storage.insert((address, change.location), current);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn has_pending_updates(&self) -> bool {
+        !self.accounts.is_empty() || !self.storage.is_empty()
+    }
+
+    fn drain_updates(&mut self) -> Vec<ProcessUpdateArg> {
+        let mut updates = Vec::with_capacity(self.accounts.len() + self.storage.len());
+
+        let accounts = std::mem::take(&mut self.accounts);
+        let hashed_keys = keccak256_batch(&accounts.keys().collect::<Vec<_>>());
+        for (hashed_key, (address, account)) in hashed_keys.into_iter().zip(accounts) {
+            let update = if let Some(account) = account {
+                Update {
+                    flags: UpdateFlags {
+                        code: true,
+                        delete: false,
+                        balance: true,
+                        nonce: true,
+                        storage: false,
+                    },
+                    balance: account.balance,
+                    nonce: account.nonce,
+                    code_hash_or_storage: account.code_hash.0,
+                    val_length: KECCAK_LENGTH,
+                }
+            } else {
+                deletion()
+            };
+
+            updates.push(ProcessUpdateArg {
+                hashed_key,
+                plain_key: address.0.to_vec(),
+                update,
+            });
+        }
+
+        let storage = std::mem::take(&mut self.storage);
+        for (&(address, location), &value) in &storage {
+            if value == U256::ZERO {
+                if let Some(locations) = self.trie_storage.get_mut(&address) {
+                    locations.remove(&location);
+                }
+            } else {
+                self.trie_storage
+                    .entry(address)
+                    .or_default()
+                    .insert(location);
+            }
+        }
+        let plain_keys = storage
+            .keys()
+            .map(|&(address, location)| storage_plain_key(address, location))
+            .collect::<Vec<_>>();
+        let hashed_keys = keccak256_batch(&plain_keys);
+        for ((hashed_key, plain_key), value) in hashed_keys
+            .into_iter()
+            .zip(plain_keys)
+            .zip(storage.into_values())
+        {
+            let update = if value == U256::ZERO {
+                deletion()
+            } else {
+                let v = u256_to_h256(value);
+                Update {
+                    flags: UpdateFlags {
+                        code: false,
+                        delete: false,
+                        balance: false,
+                        nonce: false,
+                        storage: true,
+                    },
+                    balance: U256::ZERO,
+                    nonce: 0,
+                    code_hash_or_storage: v.0,
+                    val_length: compact_len_u256(value),
+                }
+            };
+
+            updates.push(ProcessUpdateArg {
+                hashed_key,
+                plain_key: plain_key.to_vec(),
+                update,
+            });
+        }
+
+        updates.sort_by_key(|arg| arg.hashed_key);
+
+        updates
+    }
+
+    /// Folds accumulated changes into the trie and returns the new state root.
+    pub fn state_root_hash<E: EnvironmentKind>(
+        &mut self,
+        tx: &MdbxTransaction<'_, RW, E>,
+    ) -> anyhow::Result<H256> {
+        let updates = self.drain_updates();
+
+        if !updates.is_empty() {
+            let mut interrupt = self.hph.process_updates(updates).resume();
+            let branch_updates = loop {
+                interrupt = match interrupt {
+                    Interrupt::LoadBranch { interrupt, prefix } => {
+                        let branch = load_branch(tx, prefix)?.unwrap_or_default();
+                        interrupt.resume(BranchData(branch))
+                    }
+                    Interrupt::LoadAccount {
+                        interrupt,
+                        plain_key,
+                        mut cell,
+                    } => {
+                        let address = Address::from_slice(&plain_key);
+                        if let Some(account) = tx.get(tables::Account, address)? {
+                            cell.nonce = account.nonce;
+                            cell.balance = account.balance;
+                            cell.code_hash = account.code_hash;
+                        }
+                        interrupt.resume(FilledAccount(cell))
+                    }
+                    Interrupt::LoadStorage {
+                        interrupt,
+                        plain_key,
+                        mut cell,
+                    } => {
+                        let (address, location) = plain_key.split_at(ADDRESS_LENGTH);
+                        cell.storage = read_storage(
+                            tx,
+                            Address::from_slice(address),
+                            H256::from_slice(location),
+                        )?;
+                        interrupt.resume(FilledStorage(cell))
+                    }
+                    Interrupt::BranchUpdate {
+                        interrupt,
+                        update_key,
+                        branch_node,
+                    } => {
+                        save_branch(tx, update_key, branch_node)?;
+                        interrupt.resume()
+                    }
+                    Interrupt::Complete { result, .. } => break result?,
+                };
+            };
+
+            for (update_key, branch_node) in branch_updates {
+                save_branch(tx, update_key, branch_node)?;
+            }
+        }
+
+        Ok(self.hph.root_hash())
+    }
+
+    /// Computes the state root and resumes the execution pipeline with it.
+    pub fn answer<E: EnvironmentKind>(
+        &mut self,
+        tx: &MdbxTransaction<'_, RW, E>,
+        interrupt: StateRootHashInterrupt,
+    ) -> anyhow::Result<ExecutionInterrupt> {
+        let root = self.state_root_hash(tx)?;
+        trace!("Serving state root hash {:?}", root);
+        Ok(interrupt.resume(root))
+    }
+
+    /// Drives block execution to completion. Reads and writes are served by `state`, and state
+    /// changes are recorded on the way so that `StateRootHash` is answered with the root over
+    /// everything written so far.
+    pub fn execute<S: State, E: EnvironmentKind>(
+        &mut self,
+        tx: &MdbxTransaction<'_, RW, E>,
+        state: &mut S,
+        mut interrupt: ExecutionInterrupt,
+    ) -> anyhow::Result<Result<(), Box<ValidationError>>> {
+        loop {
+            interrupt = match interrupt {
+                ExecutionInterrupt::ReadAccount { interrupt, address } => {
+                    interrupt.resume(state.read_account(address)?)
+                }
+                ExecutionInterrupt::ReadStorage {
+                    interrupt,
+                    address,
+                    location,
+                } => interrupt.resume(state.read_storage(address, location)?),
+                ExecutionInterrupt::ReadCode {
+                    interrupt,
+                    code_hash,
+                } => interrupt.resume(state.read_code(code_hash)?),
+                ExecutionInterrupt::EraseStorage { interrupt, address } => {
+                    self.erase_storage(tx, address)?;
+                    state.erase_storage(address)?;
+                    interrupt.resume(())
+                }
+                ExecutionInterrupt::ReadHeader {
+                    interrupt,
+                    block_number,
+                    block_hash,
+                } => interrupt.resume(Box::new(state.read_header(block_number, block_hash)?)),
+                ExecutionInterrupt::ReadBody {
+                    interrupt,
+                    block_number,
+                    block_hash,
+                } => interrupt.resume(Box::new(state.read_body(block_number, block_hash)?)),
+                ExecutionInterrupt::ReadTotalDifficulty {
+                    interrupt,
+                    block_number,
+                    block_hash,
+                } => interrupt.resume(state.total_difficulty(block_number, block_hash)?),
+                ExecutionInterrupt::BeginBlock {
+                    interrupt,
+                    block_number,
+                } => {
+                    state.begin_block(block_number);
+                    interrupt.resume(())
+                }
+                ExecutionInterrupt::UpdateAccount {
+                    interrupt,
+                    address,
+                    initial,
+                    current,
+                } => {
+                    self.update_account(address, current);
+                    state.update_account(address, initial, current);
+                    interrupt.resume(())
+                }
+                ExecutionInterrupt::UpdateCode {
+                    interrupt,
+                    code_hash,
+                    code,
+                } => {
+                    state.update_code(code_hash, code)?;
+                    interrupt.resume(())
+                }
+                ExecutionInterrupt::UpdateStorage {
+                    interrupt,
+                    address,
+                    location,
+                    initial,
+                    current,
+                } => {
+                    self.update_storage(address, location, current);
+                    state.update_storage(address, location, initial, current)?;
+                    interrupt.resume(())
+                }
+                ExecutionInterrupt::StateRootHash { interrupt } => self.answer(tx, interrupt)?,
+                ExecutionInterrupt::Complete { result, .. } => return Ok(result),
+                _ => bail!("block tree interrupts are not served by state root computation"),
+            }
+        }
+    }
+}
+
+fn deletion() -> Update {
+    Update {
+        flags: UpdateFlags {
+            code: false,
+            delete: true,
+            balance: false,
+            nonce: false,
+            storage: false,
+        },
+        balance: U256::ZERO,
+        nonce: 0,
+        code_hash_or_storage: [0; 32],
+        val_length: 0,
+    }
+}
+
+fn storage_plain_key(address: Address, location: H256) -> [u8; ADDRESS_LENGTH + KECCAK_LENGTH] {
+    let mut plain_key = [0; ADDRESS_LENGTH + KECCAK_LENGTH];
+    plain_key[..ADDRESS_LENGTH].copy_from_slice(&address.0);
+    plain_key[ADDRESS_LENGTH..].copy_from_slice(&location.0);
+    plain_key
+}
+
+fn read_storage<E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, RW, E>,
+    address: Address,
+    location: H256,
+) -> anyhow::Result<Option<U256>> {
+    Ok(tx
+        .cursor(tables::Storage)?
+        .seek_both_range(address, location)?
+        .filter(|&(l, _)| l == location)
+        .map(|(_, v)| v))
+}
+
+/// Branch nodes are keyed by compact encoding of their prefix, an empty node means deletion.
+fn load_branch<E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, RW, E>,
+    prefix: Vec<u8>,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    tx.get(tables::CommitmentBranch, prefix)
+}
+
+fn save_branch<E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, RW, E>,
+    update_key: Vec<u8>,
+    branch_node: Vec<u8>,
+) -> anyhow::Result<()> {
+    if branch_node.is_empty() {
+        tx.del(tables::CommitmentBranch, update_key, None)?;
+    } else {
+        tx.set(tables::CommitmentBranch, update_key, branch_node)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        execution::continuation::{
+            interrupt::StartedInterrupt, interrupt_data::InterruptData, resume_data::ResumeData,
+            InnerCoroutine,
+        },
+        kv::new_mem_database,
+        InMemoryState,
+    };
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    #[test]
+    fn root_matches_reference() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let root = Arc::new(Mutex::new(None));
+        let inner: InnerCoroutine = Box::pin({
+            let root = root.clone();
+            move |_: ResumeData| {
+                for block in 1..=2_u64 {
+                    yield InterruptData::BeginBlock {
+                        block_number: BlockNumber(block),
+                    };
+                    for i in 1..=20_u64 {
+                        let address = Address::from_low_u64_be(i);
+                        // Second block removes an account and changes the others.
+                        let current = (block == 1 || i != 1).then(|| Account {
+                            nonce: i * block,
+                            balance: U256::from(i * 1000 + block),
+                            ..Default::default()
+                        });
+                        yield InterruptData::UpdateAccount {
+                            address,
+                            initial: None,
+                            current,
+                        };
+                        if i % 3 == 0 {
+                            for location in 0..i {
+                                // Second block clears every other slot.
+                                let current = if block == 2 && location % 2 == 0 {
+                                    U256::ZERO
+                                } else {
+                                    U256::from(location + block)
+                                };
+                                yield InterruptData::UpdateStorage {
+                                    address,
+                                    location: location.into(),
+                                    initial: U256::ZERO,
+                                    current,
+                                };
+                            }
+                        }
+                    }
+                    let resume = yield InterruptData::StateRootHash;
+                    if let ResumeData::Hash(hash) = resume {
+                        root.lock().replace(hash);
+                    }
+                }
+                Ok(())
+            }
+        });
+
+        let mut service = StateRootService::new();
+        let mut state = InMemoryState::new();
+        let mut interrupt = StartedInterrupt::from(inner).resume(());
+        // Stop at each block's root to compare it against the one built from scratch.
+        for _ in 1..=2 {
+            interrupt = loop {
+                interrupt = match interrupt {
+                    ExecutionInterrupt::StateRootHash { interrupt } => {
+                        break service.answer(&tx, interrupt).unwrap();
+                    }
+                    other => other,
+                };
+                interrupt = match interrupt {
+                    ExecutionInterrupt::BeginBlock {
+                        interrupt,
+                        block_number,
+                    } => {
+                        state.begin_block(block_number);
+                        interrupt.resume(())
+                    }
+                    ExecutionInterrupt::UpdateAccount {
+                        interrupt,
+                        address,
+                        initial,
+                        current,
+                    } => {
+                        service.update_account(address, current);
+                        state.update_account(address, initial, current);
+                        interrupt.resume(())
+                    }
+                    ExecutionInterrupt::UpdateStorage {
+                        interrupt,
+                        address,
+                        location,
+                        initial,
+                        current,
+                    } => {
+                        service.update_storage(address, location, current);
+                        state
+                            .update_storage(address, location, initial, current)
+                            .unwrap();
+                        interrupt.resume(())
+                    }
+                    _ => unreachable!(),
+                };
+            };
+            assert_eq!(root.lock().take(), Some(state.state_root_hash()));
+            assert!(!service.has_pending_updates());
+        }
+
+        assert!(matches!(
+            interrupt,
+            ExecutionInterrupt::Complete { result: Ok(()), .. }
+        ));
+    }
+
+    #[test]
+    fn execute_answers_state_root() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let address = Address::from_low_u64_be(1);
+        let account = Account {
+            nonce: 1,
+            ..Default::default()
+        };
+        let root = Arc::new(Mutex::new(None));
+        let inner: InnerCoroutine = Box::pin({
+            let root = root.clone();
+            move |_: ResumeData| {
+                yield InterruptData::BeginBlock {
+                    block_number: BlockNumber(1),
+                };
+                yield InterruptData::UpdateAccount {
+                    address,
+                    initial: None,
+                    current: Some(account),
+                };
+                yield InterruptData::UpdateStorage {
+                    address,
+                    location: U256::ONE,
+                    initial: U256::ZERO,
+                    current: U256::ONE,
+                };
+                if let ResumeData::Hash(hash) = (yield InterruptData::StateRootHash) {
+                    root.lock().replace(hash);
+                }
+                Ok(())
+            }
+        });
+
+        let mut service = StateRootService::new();
+        let mut state = InMemoryState::new();
+        let result = service
+            .execute(&tx, &mut state, StartedInterrupt::from(inner).resume(()))
+            .unwrap();
+        assert!(result.is_ok());
+        assert_eq!(state.read_account(address).unwrap(), Some(account));
+        assert_eq!(root.lock().take(), Some(state.state_root_hash()));
+    }
+
+    #[test]
+    fn erase_committed_storage() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let address = Address::from_low_u64_be(1);
+        let account = Account {
+            nonce: 1,
+            ..Default::default()
+        };
+        let root = Arc::new(Mutex::new(None));
+        let inner: InnerCoroutine = Box::pin({
+            let root = root.clone();
+            move |_: ResumeData| {
+                yield InterruptData::BeginBlock {
+                    block_number: BlockNumber(1),
+                };
+                yield InterruptData::UpdateAccount {
+                    address,
+                    initial: None,
+                    current: Some(account),
+                };
+                for location in 1..=3_u64 {
+                    yield InterruptData::UpdateStorage {
+                        address,
+                        location: location.into(),
+                        initial: U256::ZERO,
+                        current: location.into(),
+                    };
+                }
+                // The slots are in the trie from now on.
+                yield InterruptData::StateRootHash;
+
+                yield InterruptData::BeginBlock {
+                    block_number: BlockNumber(2),
+                };
+                yield InterruptData::EraseStorage { address };
+                yield InterruptData::UpdateStorage {
+                    address,
+                    location: U256::from(5_u64),
+                    initial: U256::ZERO,
+                    current: U256::ONE,
+                };
+                if let ResumeData::Hash(hash) = (yield InterruptData::StateRootHash) {
+                    root.lock().replace(hash);
+                }
+                Ok(())
+            }
+        });
+
+        let mut service = StateRootService::new();
+        let mut state = InMemoryState::new();
+        let result = service
+            .execute(&tx, &mut state, StartedInterrupt::from(inner).resume(()))
+            .unwrap();
+        assert!(result.is_ok());
+        assert_eq!(state.storage_size(address), 1);
+        assert_eq!(root.lock().take(), Some(state.state_root_hash()));
+    }
+
+    #[test]
+    fn update_from_changesets() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let account = |n: u64| Account {
+            nonce: n,
+            balance: U256::from(n),
+            ..Default::default()
+        };
+        let contract = Address::from_low_u64_be(1);
+        let location = H256::from_low_u64_be;
+
+        let mut service = StateRootService::new();
+        let mut reference = InMemoryState::new();
+
+        // Block 1 creates the accounts and the storage of the contract.
+        reference.begin_block(BlockNumber(1));
+        for n in 1..=3 {
+            let address = Address::from_low_u64_be(n);
+            tx.set(tables::Account, address, account(n)).unwrap();
+            tx.set(
+                tables::AccountChangeSet,
+                BlockNumber(1),
+                tables::AccountChange {
+                    address,
+                    account: None,
+                },
+            )
+            .unwrap();
+            reference.update_account(address, None, Some(account(n)));
+        }
+        for l in 1..=4 {
+            tx.set(tables::Storage, contract, (location(l), U256::from(l)))
+                .unwrap();
+            tx.set(
+                tables::StorageChangeSet,
+                tables::StorageChangeKey {
+                    block_number: BlockNumber(1),
+                    address: contract,
+                },
+                tables::StorageChange {
+                    location: location(l),
+                    value: U256::ZERO,
+                },
+            )
+            .unwrap();
+            reference
+                .update_storage(contract, U256::from(l), U256::ZERO, U256::from(l))
+                .unwrap();
+        }
+
+        service
+            .update_from_changesets(&tx, BlockNumber(1)..=BlockNumber(1))
+            .unwrap();
+        assert_eq!(
+            service.state_root_hash(&tx).unwrap(),
+            reference.state_root_hash()
+        );
+
+        // Block 2 erases the storage and changes one other account, the rest is left alone.
+        reference.begin_block(BlockNumber(2));
+        tx.del(tables::Storage, contract, None).unwrap();
+        for l in 1..=4 {
+            tx.set(
+                tables::StorageChangeSet,
+                tables::StorageChangeKey {
+                    block_number: BlockNumber(2),
+                    address: contract,
+                },
+                tables::StorageChange {
+                    location: location(l),
+                    value: U256::from(l),
+                },
+            )
+            .unwrap();
+        }
+        reference.erase_storage(contract).unwrap();
+
+        let address = Address::from_low_u64_be(3);
+        tx.set(tables::Account, address, account(30)).unwrap();
+        tx.set(
+            tables::AccountChangeSet,
+            BlockNumber(2),
+            tables::AccountChange {
+                address,
+                account: Some(account(3)),
+            },
+        )
+        .unwrap();
+        reference.update_account(address, Some(account(3)), Some(account(30)));
+
+        service
+            .update_from_changesets(&tx, BlockNumber(2)..=BlockNumber(2))
+            .unwrap();
+        assert_eq!(
+            service.state_root_hash(&tx).unwrap(),
+            reference.state_root_hash()
+        );
+    }
+
+    #[test]
+    fn resume_from_stored_branch() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        // Account is not in the trie, so deleting it leaves the root branch intact.
+        let address = Address::from_low_u64_be(1);
+        let nibble = keccak256(address).0[0] >> 4;
+        let mut children = [(nibble + 1) % 16, (nibble + 2) % 16];
+        children.sort_unstable();
+        let hashes = [H256::repeat_byte(0xaa), H256::repeat_byte(0xbb)];
+
+        let mut branch = ((1_u16 << children[0]) | (1_u16 << children[1]))
+            .to_be_bytes()
+            .to_vec();
+        branch.push(HASH_PART | (HASH_PART << 4));
+        for h in &hashes {
+            branch.push(KECCAK_LENGTH as u8);
+            branch.extend_from_slice(&h.0);
+        }
+        tx.set(tables::CommitmentBranch, vec![0], branch.clone())
+            .unwrap();
+
+        let mut service = StateRootService::new();
+        service.update_account(address, None);
+        let root = service.state_root_hash(&tx).unwrap();
+
+        let mut expected = rlp::RlpStream::new_list(17);
+        for i in 0..17 {
+            if let Some(j) = children.iter().position(|&c| c as usize == i) {
+                expected.append(&hashes[j].as_bytes());
+            } else {
+                expected.append_empty_data();
+            }
+        }
+        assert_eq!(root, keccak256(expected.out()));
+        assert_eq!(
+            tx.get(tables::CommitmentBranch, vec![0]).unwrap(),
+            Some(branch)
+        );
+    }
+}
@@ -1,19 +1,28 @@
 use super::*;
 use crate::{chain::protocol_param::param, models::*, state::*};
 use anyhow::Context;
-use std::time::SystemTime;
+use std::sync::Arc;
 
 #[derive(Debug)]
 pub struct ConsensusEngineBase {
-    chain_id: ChainId,
+    params: Params,
     eip1559_block: Option<BlockNumber>,
+    allowed_future_drift: u64,
+    clock: Arc<dyn Clock>,
 }
 
 impl ConsensusEngineBase {
-    pub fn new(chain_id: ChainId, eip1559_block: Option<BlockNumber>) -> Self {
+    pub fn new(
+        params: Params,
+        eip1559_block: Option<BlockNumber>,
+        allowed_future_drift: u64,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
         Self {
-            chain_id,
+            params,
             eip1559_block,
+            allowed_future_drift,
+            clock,
         }
     }
 
@@ -24,10 +33,8 @@ impl ConsensusEngineBase {
         with_future_timestamp_check: bool,
     ) -> anyhow::Result<()> {
         if with_future_timestamp_check {
-            let now = SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)?
-                .as_secs();
-            if header.timestamp > now {
+            let now = self.clock.now();
+            if header.timestamp > now.saturating_add(self.allowed_future_drift) {
                 return Err(ValidationError::FutureBlock {
                     now,
                     got: header.timestamp,
@@ -44,18 +51,24 @@ impl ConsensusEngineBase {
             .into());
         }
 
-        if header.gas_limit < 5000 {
-            return Err(ValidationError::InvalidGasLimit.into());
-        }
-
         // https://github.com/ethereum/go-ethereum/blob/v1.9.25/consensus/ethash/consensus.go#L267
         // https://eips.ethereum.org/EIPS/eip-1985
-        if header.gas_limit > i64::MAX.try_into().unwrap() {
-            return Err(ValidationError::InvalidGasLimit.into());
+        const MAX_GAS_LIMIT: u64 = i64::MAX as u64;
+        if !(self.params.min_gas_limit..=MAX_GAS_LIMIT).contains(&header.gas_limit) {
+            return Err(ValidationError::GasLimitOutOfBounds {
+                min: self.params.min_gas_limit,
+                max: MAX_GAS_LIMIT,
+                got: header.gas_limit,
+            }
+            .into());
         }
 
-        if header.extra_data.len() > 32 {
-            return Err(ValidationError::ExtraDataTooLong.into());
+        if header.extra_data.len() > self.params.maximum_extra_data_size {
+            return Err(ValidationError::ExtraDataTooLong {
+                max: self.params.maximum_extra_data_size,
+                got: header.extra_data.len(),
+            }
+            .into());
         }
 
         if header.timestamp <= parent.timestamp {
@@ -78,8 +91,12 @@ impl ConsensusEngineBase {
         } else {
             parent_gas_limit - header.gas_limit
         };
-        if gas_delta >= parent_gas_limit / 1024 {
-            return Err(ValidationError::InvalidGasLimit.into());
+        if gas_delta >= parent_gas_limit / self.params.gas_limit_bound_divisor {
+            return Err(ValidationError::InvalidGasLimit {
+                parent: parent_gas_limit,
+                got: header.gas_limit,
+            }
+            .into());
         }
 
         let expected_base_fee_per_gas = self.expected_base_fee_per_gas(header, parent);
@@ -213,21 +230,33 @@ impl ConsensusEngineBase {
         }
 
         if block.ommers.len() > 2 {
-            return Err(ValidationError::TooManyOmmers.into());
+            return Err(ValidationError::TooManyOmmers {
+                got: block.ommers.len(),
+            }
+            .into());
         }
 
         if block.ommers.len() == 2 && block.ommers[0] == block.ommers[1] {
-            return Err(ValidationError::DuplicateOmmer.into());
+            return Err(ValidationError::DuplicateOmmer {
+                hash: block.ommers[1].hash(),
+            }
+            .into());
         }
 
-        let parent = self
-            .get_parent_header(state, &block.header)?
-            .ok_or(ValidationError::UnknownParent)?;
+        let parent = self.get_parent_header(state, &block.header)?.ok_or(
+            ValidationError::UnknownParent {
+                number: block.header.number,
+                parent_hash: block.header.parent_hash,
+            },
+        )?;
 
         for ommer in &block.ommers {
-            let ommer_parent = self
-                .get_parent_header(state, ommer)?
-                .ok_or(ValidationError::UnknownParent)?;
+            let ommer_parent =
+                self.get_parent_header(state, ommer)?
+                    .ok_or(ValidationError::UnknownParent {
+                        number: ommer.number,
+                        parent_hash: ommer.parent_hash,
+                    })?;
 
             self.validate_block_header(ommer, &ommer_parent, false)
                 .context(ValidationError::InvalidOmmerHeader)?;
@@ -240,17 +269,17 @@ impl ConsensusEngineBase {
                 state,
                 &mut old_ommers,
             )? {
-                return Err(ValidationError::NotAnOmmer.into());
+                return Err(ValidationError::NotAnOmmer { hash: ommer.hash() }.into());
             }
             for oo in old_ommers {
                 if oo == *ommer {
-                    return Err(ValidationError::DuplicateOmmer.into());
+                    return Err(ValidationError::DuplicateOmmer { hash: oo.hash() }.into());
                 }
             }
         }
 
         for txn in &block.transactions {
-            pre_validate_transaction(txn, self.chain_id, block.header.base_fee_per_gas)?;
+            pre_validate_transaction(txn, self.params.chain_id, block.header.base_fee_per_gas)?;
         }
 
         Ok(())
@@ -260,7 +289,179 @@ impl ConsensusEngineBase {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::res::chainspec::MAINNET;
+    use crate::res::chainspec::{MAINNET, RINKEBY, ROPSTEN};
+
+    fn engine(chain_spec: &ChainSpec) -> ConsensusEngineBase {
+        engine_with_clock(chain_spec, Arc::new(SystemClock))
+    }
+
+    fn engine_with_clock(chain_spec: &ChainSpec, clock: Arc<dyn Clock>) -> ConsensusEngineBase {
+        ConsensusEngineBase::new(
+            chain_spec.params.clone(),
+            chain_spec.consensus.eip1559_block,
+            chain_spec.consensus.allowed_future_drift,
+            clock,
+        )
+    }
+
+    fn parent_header() -> BlockHeader {
+        BlockHeader {
+            number: BlockNumber(1000),
+            gas_limit: 8_000_000,
+            timestamp: 1_000_000,
+            ..BlockHeader::empty()
+        }
+    }
+
+    fn child_header(parent: &BlockHeader) -> BlockHeader {
+        BlockHeader {
+            parent_hash: parent.hash(),
+            number: BlockNumber(parent.number.0 + 1),
+            gas_limit: parent.gas_limit,
+            timestamp: parent.timestamp + 15,
+            ..BlockHeader::empty()
+        }
+    }
+
+    fn validation_error(res: anyhow::Result<()>) -> Option<ValidationError> {
+        res.err()
+            .map(|e| e.downcast::<ValidationError>().expect("validation error"))
+    }
+
+    #[test]
+    fn validate_gas_limit() {
+        for chain_spec in [&*MAINNET, &*ROPSTEN, &*RINKEBY] {
+            let engine = engine(chain_spec);
+            let params = &chain_spec.params;
+
+            let parent = parent_header();
+            let bound = parent.gas_limit / params.gas_limit_bound_divisor;
+
+            for (gas_limit, error) in [
+                (parent.gas_limit, None),
+                (parent.gas_limit + bound - 1, None),
+                (parent.gas_limit - (bound - 1), None),
+                (
+                    parent.gas_limit + bound,
+                    Some(ValidationError::InvalidGasLimit {
+                        parent: parent.gas_limit,
+                        got: parent.gas_limit + bound,
+                    }),
+                ),
+                (
+                    parent.gas_limit - bound,
+                    Some(ValidationError::InvalidGasLimit {
+                        parent: parent.gas_limit,
+                        got: parent.gas_limit - bound,
+                    }),
+                ),
+            ] {
+                let header = BlockHeader {
+                    gas_limit,
+                    ..child_header(&parent)
+                };
+                assert_eq!(
+                    validation_error(engine.validate_block_header(&header, &parent, false)),
+                    error,
+                    "chain {:?}, gas limit {}",
+                    params.chain_id,
+                    gas_limit
+                );
+            }
+
+            let parent = BlockHeader {
+                gas_limit: params.min_gas_limit,
+                ..parent_header()
+            };
+            let header = BlockHeader {
+                gas_limit: params.min_gas_limit - 1,
+                ..child_header(&parent)
+            };
+            assert_eq!(
+                validation_error(engine.validate_block_header(&header, &parent, false)),
+                Some(ValidationError::GasLimitOutOfBounds {
+                    min: params.min_gas_limit,
+                    max: i64::MAX as u64,
+                    got: params.min_gas_limit - 1,
+                }),
+                "chain {:?}",
+                params.chain_id
+            );
+        }
+    }
+
+    #[test]
+    fn validate_future_timestamp() {
+        for chain_spec in [&*MAINNET, &*ROPSTEN, &*RINKEBY] {
+            let drift = chain_spec.consensus.allowed_future_drift;
+            let parent = parent_header();
+            let now = parent.timestamp + 100;
+            let clock = Arc::new(ManualClock::new(now));
+            let engine = engine_with_clock(chain_spec, clock.clone());
+
+            let header = BlockHeader {
+                timestamp: now + drift,
+                ..child_header(&parent)
+            };
+            assert_eq!(
+                validation_error(engine.validate_block_header(&header, &parent, true)),
+                None
+            );
+
+            let header = BlockHeader {
+                timestamp: now + drift + 1,
+                ..child_header(&parent)
+            };
+            assert_eq!(
+                validation_error(engine.validate_block_header(&header, &parent, true)),
+                Some(ValidationError::FutureBlock {
+                    now,
+                    got: now + drift + 1,
+                })
+            );
+            // Not checked unless asked for.
+            assert_eq!(
+                validation_error(engine.validate_block_header(&header, &parent, false)),
+                None
+            );
+
+            clock.advance(1);
+            assert_eq!(
+                validation_error(engine.validate_block_header(&header, &parent, true)),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn validate_extra_data() {
+        for chain_spec in [&*MAINNET, &*ROPSTEN, &*RINKEBY] {
+            let engine = engine(chain_spec);
+            let max = chain_spec.params.maximum_extra_data_size;
+
+            let parent = parent_header();
+            for (len, error) in [
+                (0, None),
+                (max, None),
+                (
+                    max + 1,
+                    Some(ValidationError::ExtraDataTooLong { max, got: max + 1 }),
+                ),
+            ] {
+                let header = BlockHeader {
+                    extra_data: vec![0xab; len].into(),
+                    ..child_header(&parent)
+                };
+                assert_eq!(
+                    validation_error(engine.validate_block_header(&header, &parent, false)),
+                    error,
+                    "chain {:?}, extra data length {}",
+                    chain_spec.params.chain_id,
+                    len
+                );
+            }
+        }
+    }
 
     #[test]
     fn validate_max_fee_per_gas() {
@@ -270,25 +471,37 @@ mod tests {
             (
                 500_000_000_u64,
                 700_000_000_u64,
-                ValidationError::MaxFeeLessThanBase,
+                ValidationError::MaxFeeLessThanBase {
+                    max_fee_per_gas: 700_000_000_u64.into(),
+                    base_fee_per_gas: base_fee_per_gas.into(),
+                },
                 false,
             ),
             (
                 3_000_000_000_u64,
                 2_000_000_000_u64,
-                ValidationError::MaxPriorityFeeGreaterThanMax,
+                ValidationError::MaxPriorityFeeGreaterThanMax {
+                    max_priority_fee_per_gas: 3_000_000_000_u64.into(),
+                    max_fee_per_gas: 2_000_000_000_u64.into(),
+                },
                 false,
             ),
             (
                 2_000_000_000_u64,
                 2_000_000_000_u64,
-                ValidationError::MaxPriorityFeeGreaterThanMax,
+                ValidationError::MaxPriorityFeeGreaterThanMax {
+                    max_priority_fee_per_gas: 2_000_000_000_u64.into(),
+                    max_fee_per_gas: 2_000_000_000_u64.into(),
+                },
                 true,
             ),
             (
                 1_000_000_000_u64,
                 2_000_000_000_u64,
-                ValidationError::MaxPriorityFeeGreaterThanMax,
+                ValidationError::MaxPriorityFeeGreaterThanMax {
+                    max_priority_fee_per_gas: 1_000_000_000_u64.into(),
+                    max_fee_per_gas: 2_000_000_000_u64.into(),
+                },
                 true,
             ),
         ] {
@@ -1,12 +1,31 @@
 use crate::{
     consensus::*,
-    execution::{analysis_cache::AnalysisCache, processor::ExecutionProcessor},
+    execution::{
+        analysis_cache::AnalysisCache,
+        processor::ExecutionProcessor,
+        tracer::{OpcodeCoverage, Tracer},
+    },
     models::*,
     state::*,
 };
 use anyhow::Context;
+use lru::LruCache;
 use std::{collections::HashMap, convert::TryFrom};
 
+/// Blocks whose transaction senders are kept, so that they are not recovered again on reorgs.
+const SENDERS_CACHE_BLOCKS: usize = 256;
+/// Senders of transactions known before they are included, e.g. from the transaction pool.
+const KNOWN_TX_SENDERS: usize = 32 * 1024;
+
+/// What [`Blockchain::insert_block`] did with a valid block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InsertBlockOutcome {
+    /// The block was executed and stored, and made canonical if its chain is the heaviest.
+    Inserted,
+    /// The block had been inserted before, and was left as is.
+    AlreadyKnown,
+}
+
 #[derive(Debug)]
 pub struct Blockchain<'state> {
     state: &'state mut InMemoryState,
@@ -14,6 +33,9 @@ pub struct Blockchain<'state> {
     engine: Box<dyn Consensus>,
     bad_blocks: HashMap<H256, ValidationError>,
     receipts: Vec<Receipt>,
+    coverage: Option<OpcodeCoverage>,
+    block_senders: LruCache<H256, Vec<Address>>,
+    tx_senders: LruCache<H256, Address>,
 }
 
 impl<'state> Blockchain<'state> {
@@ -22,12 +44,7 @@ impl<'state> Blockchain<'state> {
         config: ChainSpec,
         genesis_block: Block,
     ) -> anyhow::Result<Blockchain<'state>> {
-        Self::new_with_consensus(
-            state,
-            engine_factory(config.clone())?,
-            config,
-            genesis_block,
-        )
+        Self::new_with_consensus(state, engine_factory(&config)?, config, genesis_block)
     }
 
     pub fn new_with_consensus(
@@ -47,20 +64,49 @@ impl<'state> Blockchain<'state> {
             config,
             bad_blocks: Default::default(),
             receipts: Default::default(),
+            coverage: None,
+            block_senders: LruCache::new(SENDERS_CACHE_BLOCKS),
+            tx_senders: LruCache::new(KNOWN_TX_SENDERS),
         })
     }
 
-    pub fn insert_block(&mut self, block: Block, check_state_root: bool) -> anyhow::Result<()> {
-        self.engine
-            .validate_block_header(&block.header, &mut self.state, true)?;
-        self.engine.pre_validate_block(&block, &mut self.state)?;
+    /// Transactions whose senders are already known, e.g. from the transaction pool.
+    /// Their senders are not recovered when a block including them is inserted.
+    pub fn add_known_senders(&mut self, senders: impl IntoIterator<Item = (H256, Address)>) {
+        for (tx_hash, sender) in senders {
+            self.tx_senders.put(tx_hash, sender);
+        }
+    }
+
+    /// Starts recording the opcodes executed by the inserted blocks.
+    pub fn record_coverage(&mut self) {
+        self.coverage.get_or_insert_with(Default::default);
+    }
 
+    /// Opcodes executed since recording started.
+    pub fn take_coverage(&mut self) -> Option<OpcodeCoverage> {
+        self.coverage.take()
+    }
+
+    pub fn insert_block(
+        &mut self,
+        block: Block,
+        check_state_root: bool,
+    ) -> anyhow::Result<InsertBlockOutcome> {
         let hash = block.header.hash();
+        if self.state.contains_block(block.header.number, hash) {
+            return Ok(InsertBlockOutcome::AlreadyKnown);
+        }
         if let Some(error) = self.bad_blocks.get(&hash) {
             return Err(error.clone().into());
         }
 
-        let b = BlockWithSenders::from(block.clone());
+        self.engine
+            .validate_block_header(&block.header, &mut self.state, true)?;
+        self.engine.pre_validate_block(&block, &mut self.state)?;
+
+        let senders = self.recover_senders(hash, &block.transactions)?;
+        let b = BlockWithSenders::new(block.clone(), senders);
 
         let ancestor = self.canonical_ancestor(&b.header, hash)?;
 
@@ -119,7 +165,7 @@ impl<'state> Blockchain<'state> {
             self.re_execute_canonical_chain(ancestor, current_canonical_block)?;
         }
 
-        Ok(())
+        Ok(InsertBlockOutcome::Inserted)
     }
 
     fn execute_block(
@@ -132,12 +178,19 @@ impl<'state> Blockchain<'state> {
             ommers: block.ommers.clone(),
         };
 
-        let block_spec = self.config.collect_block_spec(block.header.number);
+        let block_spec = self
+            .config
+            .collect_block_spec(block.header.number, block.header.timestamp);
+
+        let mut tracer = self
+            .coverage
+            .as_mut()
+            .map(|coverage| coverage.tracer(block_spec.revision));
 
         let mut analysis_cache = AnalysisCache::default();
         let processor = ExecutionProcessor::new(
             self.state,
-            None,
+            tracer.as_mut().map(|tracer| tracer as &mut dyn Tracer),
             &mut analysis_cache,
             &mut *self.engine,
             &block.header,
@@ -172,17 +225,7 @@ impl<'state> Blockchain<'state> {
         assert!(ancestor <= tip);
         for block_number in ancestor + 1..=tip {
             let hash = self.state.canonical_hash(block_number).unwrap();
-            let body = self
-                .state
-                .read_body_with_senders(block_number, hash)?
-                .unwrap();
-            let header = self.state.read_header(block_number, hash)?.unwrap();
-
-            let block = BlockWithSenders {
-                header: header.into(),
-                transactions: body.transactions,
-                ommers: body.ommers,
-            };
+            let block = self.read_block_with_senders(block_number, hash)?;
 
             let _ = self.execute_block(&block, false).unwrap();
         }
@@ -206,7 +249,7 @@ impl<'state> Blockchain<'state> {
     }
 
     fn intermediate_chain(
-        &self,
+        &mut self,
         block_number: impl Into<BlockNumber>,
         mut hash: H256,
         canonical_ancestor: impl Into<BlockNumber>,
@@ -216,18 +259,8 @@ impl<'state> Blockchain<'state> {
         let mut chain =
             Vec::with_capacity(usize::try_from(block_number.0 - canonical_ancestor.0).unwrap());
         for block_number in (canonical_ancestor + 1..=block_number).rev() {
-            let body = self
-                .state
-                .read_body_with_senders(block_number, hash)?
-                .unwrap();
-            let header = self.state.read_header(block_number, hash)?.unwrap().into();
-
             let block = WithHash {
-                inner: BlockWithSenders {
-                    header,
-                    transactions: body.transactions,
-                    ommers: body.ommers,
-                },
+                inner: self.read_block_with_senders(block_number, hash)?,
                 hash,
             };
 
@@ -241,6 +274,60 @@ impl<'state> Blockchain<'state> {
         Ok(chain)
     }
 
+    fn read_block_with_senders(
+        &mut self,
+        block_number: BlockNumber,
+        hash: H256,
+    ) -> anyhow::Result<BlockWithSenders> {
+        let header = self.state.read_header(block_number, hash)?.unwrap();
+        let body = self.state.read_body(block_number, hash)?.unwrap();
+        let senders = self.recover_senders(hash, &body.transactions)?;
+
+        Ok(BlockWithSenders::new(
+            Block {
+                header,
+                transactions: body.transactions,
+                ommers: body.ommers,
+            },
+            senders,
+        ))
+    }
+
+    /// Senders of the block transactions, recovering only the ones not seen before.
+    fn recover_senders(
+        &mut self,
+        hash: H256,
+        transactions: &[MessageWithSignature],
+    ) -> anyhow::Result<Vec<Address>> {
+        if let Some(senders) = self.block_senders.get(&hash) {
+            return Ok(senders.clone());
+        }
+
+        let senders = if self.tx_senders.is_empty() {
+            MessageWithSignature::recover_senders(transactions)?
+        } else {
+            let known = transactions
+                .iter()
+                .map(|tx| self.tx_senders.get(&tx.hash()).copied())
+                .collect::<Vec<_>>();
+            let unknown = transactions
+                .iter()
+                .zip(&known)
+                .filter(|(_, sender)| sender.is_none())
+                .map(|(tx, _)| tx.clone())
+                .collect::<Vec<_>>();
+            let mut recovered = MessageWithSignature::recover_senders(&unknown)?.into_iter();
+            known
+                .into_iter()
+                .map(|sender| sender.or_else(|| recovered.next()))
+                .collect::<Option<Vec<_>>>()
+                .unwrap()
+        };
+
+        self.block_senders.put(hash, senders.clone());
+        Ok(senders)
+    }
+
     fn canonical_ancestor(
         &self,
         header: &PartialHeader,
@@ -254,7 +341,80 @@ impl<'state> Blockchain<'state> {
         let parent = self
             .state
             .read_header(BlockNumber(header.number.0 - 1), header.parent_hash)?
-            .ok_or(ValidationError::UnknownParent)?;
+            .ok_or(ValidationError::UnknownParent {
+                number: header.number,
+                parent_hash: header.parent_hash,
+            })?;
         self.canonical_ancestor(&parent.into(), header.parent_hash)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::res::chainspec::MAINNET;
+
+    #[test]
+    fn known_senders() {
+        let mut state = InMemoryState::default();
+        let genesis_block = Block {
+            header: BlockHeader::empty(),
+            transactions: vec![],
+            ommers: vec![],
+        };
+        let mut blockchain = Blockchain::new(&mut state, MAINNET.clone(), genesis_block).unwrap();
+
+        let tx = MessageWithSignature {
+            message: Message::Legacy {
+                chain_id: None,
+                nonce: 0,
+                gas_price: U256::ZERO,
+                gas_limit: 21_000,
+                action: TransactionAction::Create,
+                value: U256::ZERO,
+                input: Default::default(),
+            },
+            signature: MessageSignature::new(
+                false,
+                H256::from_low_u64_be(1),
+                H256::from_low_u64_be(1),
+            )
+            .unwrap(),
+        };
+        let sender = Address::repeat_byte(0xaa);
+        let txs = [tx.clone(), tx.clone()];
+
+        blockchain.add_known_senders([(tx.hash(), sender)]);
+        let block_hash = H256::repeat_byte(1);
+        assert_eq!(
+            blockchain.recover_senders(block_hash, &txs).unwrap(),
+            vec![sender, sender]
+        );
+
+        // the block senders are cached after the transaction is forgotten
+        blockchain.tx_senders.clear();
+        assert_eq!(
+            blockchain.recover_senders(block_hash, &txs).unwrap(),
+            vec![sender, sender]
+        );
+    }
+
+    #[test]
+    fn insert_known_block() {
+        let genesis_block = Block {
+            header: BlockHeader::empty(),
+            transactions: vec![],
+            ommers: vec![],
+        };
+
+        let mut state = InMemoryState::default();
+        let mut blockchain =
+            Blockchain::new(&mut state, MAINNET.clone(), genesis_block.clone()).unwrap();
+
+        // the genesis block would not pass validation if it was executed again
+        assert_eq!(
+            blockchain.insert_block(genesis_block, true).unwrap(),
+            InsertBlockOutcome::AlreadyKnown
+        );
+    }
+}
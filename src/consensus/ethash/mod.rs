@@ -2,7 +2,7 @@ use self::difficulty::BlockDifficultyBombData;
 use super::{base::ConsensusEngineBase, *};
 use crate::{chain::protocol_param::param, h256_to_u256};
 use ::ethash::LightDAG;
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, sync::Arc};
 
 pub mod difficulty;
 
@@ -20,7 +20,7 @@ pub struct Ethash {
 impl Ethash {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
-        chain_id: ChainId,
+        params: Params,
         eip1559_block: Option<BlockNumber>,
         duration_limit: u64,
         block_reward: BTreeMap<BlockNumber, U256>,
@@ -28,9 +28,11 @@ impl Ethash {
         byzantium_formula: Option<BlockNumber>,
         difficulty_bomb: Option<DifficultyBomb>,
         skip_pow_verification: bool,
+        allowed_future_drift: u64,
+        clock: Arc<dyn Clock>,
     ) -> Self {
         Self {
-            base: ConsensusEngineBase::new(chain_id, eip1559_block),
+            base: ConsensusEngineBase::new(params, eip1559_block, allowed_future_drift, clock),
             duration_limit,
             block_reward,
             homestead_formula,
@@ -52,10 +54,13 @@ impl Consensus for Ethash {
         state: &mut dyn State,
         with_future_timestamp_check: bool,
     ) -> anyhow::Result<()> {
-        let parent = self
-            .base
-            .get_parent_header(state, header)?
-            .ok_or(ValidationError::UnknownParent)?;
+        let parent =
+            self.base
+                .get_parent_header(state, header)?
+                .ok_or(ValidationError::UnknownParent {
+                    number: header.number,
+                    parent_hash: header.parent_hash,
+                })?;
 
         self.base
             .validate_block_header(header, &parent, with_future_timestamp_check)?;
@@ -76,7 +81,11 @@ impl Consensus for Ethash {
                 }),
         );
         if difficulty != header.difficulty {
-            return Err(ValidationError::WrongDifficulty.into());
+            return Err(ValidationError::WrongDifficulty {
+                expected: difficulty,
+                got: header.difficulty,
+            }
+            .into());
         }
 
         Ok(())
@@ -133,8 +142,4 @@ impl Consensus for Ethash {
 
         Ok(changes)
     }
-
-    fn get_beneficiary(&self, header: &BlockHeader) -> anyhow::Result<Address> {
-        Ok(header.beneficiary)
-    }
 }
@@ -1,11 +1,13 @@
 mod base;
 mod blockchain;
+mod clock;
 mod ethash;
 
-pub use self::{blockchain::*, ethash::*};
+pub use self::{blockchain::*, clock::*, ethash::*};
 use crate::{models::*, State};
 use anyhow::bail;
-use std::fmt::{Debug, Display};
+use std::{fmt::Debug, sync::Arc};
+use thiserror::Error;
 
 #[derive(Debug)]
 pub enum FinalizationChange {
@@ -29,8 +31,11 @@ pub trait Consensus: Debug + Send + Sync + 'static {
         with_future_timestamp_check: bool,
     ) -> anyhow::Result<()>;
 
-    /// Validates the seal of the header
-    fn validate_seal(&self, header: &BlockHeader) -> anyhow::Result<()>;
+    /// Validates the seal of the header. Engines without seals accept any.
+    fn validate_seal(&self, header: &BlockHeader) -> anyhow::Result<()> {
+        let _ = header;
+        Ok(())
+    }
 
     /// Finalizes block execution by applying changes in the state of accounts or of the consensus itself
     ///
@@ -40,113 +45,141 @@ pub trait Consensus: Debug + Send + Sync + 'static {
         block: &PartialHeader,
         ommers: &[BlockHeader],
         revision: Revision,
-    ) -> anyhow::Result<Vec<FinalizationChange>>;
+    ) -> anyhow::Result<Vec<FinalizationChange>> {
+        let _ = (block, ommers, revision);
+        Ok(vec![])
+    }
 
     /// See [YP] Section 11.3 "Reward Application".
-    fn get_beneficiary(&self, header: &BlockHeader) -> anyhow::Result<Address>;
+    fn get_beneficiary(&self, header: &BlockHeader) -> anyhow::Result<Address> {
+        Ok(header.beneficiary)
+    }
 }
 
 #[allow(clippy::large_enum_variant)]
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Error)]
 pub enum ValidationError {
-    FutureBlock {
-        now: u64,
-        got: u64,
-    }, // Block has a timestamp in the future
+    #[error("block timestamp {got} is in the future, local time is {now}")]
+    FutureBlock { now: u64, got: u64 }, // Block has a timestamp in the future
 
     // See [YP] Section 4.3.2 "Holistic Validity", Eq (31)
-    WrongStateRoot {
-        expected: H256,
-        got: H256,
-    }, // wrong Hr
-    WrongOmmersHash {
-        expected: H256,
-        got: H256,
-    }, // wrong Ho
-    WrongTransactionsRoot {
-        expected: H256,
-        got: H256,
-    }, // wrong Ht
-    WrongReceiptsRoot {
-        expected: H256,
-        got: H256,
-    }, // wrong He
-    WrongLogsBloom {
-        expected: Bloom,
-        got: Bloom,
-    }, // wrong Hb
+    #[error("wrong state root: expected {expected:?}, got {got:?}")]
+    WrongStateRoot { expected: H256, got: H256 }, // wrong Hr
+    #[error("wrong ommers hash: expected {expected:?}, got {got:?}")]
+    WrongOmmersHash { expected: H256, got: H256 }, // wrong Ho
+    #[error("wrong transactions root: expected {expected:?}, got {got:?}")]
+    WrongTransactionsRoot { expected: H256, got: H256 }, // wrong Ht
+    #[error("wrong receipts root: expected {expected:?}, got {got:?}")]
+    WrongReceiptsRoot { expected: H256, got: H256 }, // wrong He
+    #[error("wrong logs bloom: expected {expected:?}, got {got:?}")]
+    WrongLogsBloom { expected: Bloom, got: Bloom }, // wrong Hb
 
     // See [YP] Section 4.3.4 "Block Header Validity", Eq (50)
-    UnknownParent,   // P(H) = ∅ ∨ Hi ≠ P(H)Hi + 1
-    WrongDifficulty, // Hd ≠ D(H)
-    GasAboveLimit {
-        used: u64,
-        limit: u64,
-    }, // Hg > Hl
-    InvalidGasLimit, // |Hl-P(H)Hl|≥P(H)Hl/1024 ∨ Hl<5000
-    InvalidTimestamp {
-        parent: u64,
-        current: u64,
-    }, // Hs ≤ P(H)Hs
-    ExtraDataTooLong, // ‖Hx‖ > 32
+    #[error("unknown parent {parent_hash:?} of block {number}")]
+    UnknownParent {
+        number: BlockNumber,
+        parent_hash: H256,
+    }, // P(H) = ∅ ∨ Hi ≠ P(H)Hi + 1
+    #[error("wrong parent hash: expected {expected:?}, got {got:?}")]
+    WrongParentHash { expected: H256, got: H256 },
+    #[error("wrong block number: expected {expected}, got {got}")]
+    WrongBlockNumber {
+        expected: BlockNumber,
+        got: BlockNumber,
+    },
+    #[error("wrong difficulty: expected {expected}, got {got}")]
+    WrongDifficulty { expected: U256, got: U256 }, // Hd ≠ D(H)
+    #[error("gas used {used} is above gas limit {limit}")]
+    GasAboveLimit { used: u64, limit: u64 }, // Hg > Hl
+    #[error("gas limit {got} is outside of [{min}, {max}]")]
+    GasLimitOutOfBounds { min: u64, max: u64, got: u64 }, // Hl<5000
+    #[error("gas limit {got} changed too much from parent gas limit {parent}")]
+    InvalidGasLimit { parent: u64, got: u64 }, // |Hl-P(H)Hl|≥P(H)Hl/1024
+    #[error("timestamp {current} is not after parent timestamp {parent}")]
+    InvalidTimestamp { parent: u64, current: u64 }, // Hs ≤ P(H)Hs
+    #[error("extra data is {got} bytes long, at most {max} allowed")]
+    ExtraDataTooLong { max: usize, got: usize }, // ‖Hx‖ > 32
+    #[error("wrong DAO fork extra data")]
     WrongDaoExtraData, // see EIP-779
+    #[error("wrong base fee: expected {expected:?}, got {got:?}")]
     WrongBaseFee {
         expected: Option<U256>,
         got: Option<U256>,
     }, // see EIP-1559
-    InvalidSeal,     // Nonce or mix_hash
+    #[error("invalid seal: mix hash or nonce does not match the difficulty")]
+    InvalidSeal, // Nonce or mix_hash
 
     // See [YP] Section 6.2 "Execution", Eq (58)
+    #[error("missing sender")]
     MissingSender, // S(T) = ∅
-    SenderNoEOA {
-        sender: Address,
-    }, // EIP-3607: σ[S(T)]c ≠ KEC( () )
+    #[error("sender {sender:?} is not an EOA")]
+    SenderNoEOA { sender: Address }, // EIP-3607: σ[S(T)]c ≠ KEC( () )
+    #[error("wrong nonce for {account:?}: expected {expected}, got {got}")]
     WrongNonce {
         account: Address,
         expected: u64,
         got: u64,
     }, // Tn ≠ σ[S(T)]n
-    IntrinsicGas,  // g0 > Tg
+    #[error("gas limit {gas_limit} is below intrinsic gas {intrinsic_gas}")]
+    IntrinsicGas { intrinsic_gas: u128, gas_limit: u64 }, // g0 > Tg
+    #[error("insufficient funds of {account:?}: {available} available, {required} required")]
     InsufficientFunds {
         account: Address,
         available: U512,
         required: U512,
     }, // v0 > σ[S(T)]b
-    BlockGasLimitExceeded {
-        available: u64,
-        required: u64,
-    }, // Tg > BHl - l(BR)u
-    MaxFeeLessThanBase, // max_fee_per_gas < base_fee_per_gas (EIP-1559)
-    MaxPriorityFeeGreaterThanMax, // max_priority_fee_per_gas > max_fee_per_gas (EIP-1559)
+    #[error("block gas limit exceeded: {available} available, {required} required")]
+    BlockGasLimitExceeded { available: u64, required: u64 }, // Tg > BHl - l(BR)u
+    #[error("max fee per gas {max_fee_per_gas} is less than base fee {base_fee_per_gas}")]
+    MaxFeeLessThanBase {
+        max_fee_per_gas: U256,
+        base_fee_per_gas: U256,
+    }, // max_fee_per_gas < base_fee_per_gas (EIP-1559)
+    #[error(
+        "max priority fee per gas {max_priority_fee_per_gas} is greater than max fee per gas {max_fee_per_gas}"
+    )]
+    MaxPriorityFeeGreaterThanMax {
+        max_priority_fee_per_gas: U256,
+        max_fee_per_gas: U256,
+    }, // max_priority_fee_per_gas > max_fee_per_gas (EIP-1559)
+    #[error("init code is {got} bytes long, at most {max} allowed")]
+    InitCodeTooLarge { max: usize, got: usize }, // EIP-3860
 
     // See [YP] Section 11.1 "Ommer Validation", Eq (157)
-    TooManyOmmers,      // ‖BU‖ > 2
+    #[error("too many ommers: {got}, at most 2 allowed")]
+    TooManyOmmers { got: usize }, // ‖BU‖ > 2
+    #[error("invalid ommer header")]
     InvalidOmmerHeader, // ¬V(U)
-    NotAnOmmer,         // ¬k(U, P(BH)H, 6)
-    DuplicateOmmer,     // not well covered by the YP actually
+    #[error("ommer {hash:?} is not a kin of the block")]
+    NotAnOmmer { hash: H256 }, // ¬k(U, P(BH)H, 6)
+    #[error("duplicate ommer {hash:?}")]
+    DuplicateOmmer { hash: H256 }, // not well covered by the YP actually
 
     // See [YP] Section 11.2 "Transaction Validation", Eq (160)
+    #[error("wrong block gas: expected {expected}, got {got}")]
     WrongBlockGas {
         expected: u64,
         got: u64,
         transactions: Vec<(usize, u64)>,
     }, // BHg ≠ l(BR)u
 
+    #[error("invalid signature")]
     InvalidSignature, // EIP-2
 
-    WrongChainId, // EIP-155
-
+    #[error(
+        "wrong chain id: expected {}, got {got}",
+        .expected.map(|id| id.to_string()).unwrap_or_else(|| "none before EIP-155".to_string())
+    )]
+    WrongChainId {
+        /// `None` if replay protected transactions are not allowed yet.
+        expected: Option<ChainId>,
+        got: ChainId,
+    }, // EIP-155
+
+    #[error("unsupported transaction type")]
     UnsupportedTransactionType, // EIP-2718
 }
 
-impl Display for ValidationError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
-    }
-}
-
-impl std::error::Error for ValidationError {}
-
 pub fn pre_validate_transaction(
     txn: &Message,
     canonical_chain_id: ChainId,
@@ -154,26 +187,35 @@ pub fn pre_validate_transaction(
 ) -> Result<(), ValidationError> {
     if let Some(chain_id) = txn.chain_id() {
         if chain_id != canonical_chain_id {
-            return Err(ValidationError::WrongChainId);
+            return Err(ValidationError::WrongChainId {
+                expected: Some(canonical_chain_id),
+                got: chain_id,
+            });
         }
     }
 
     if let Some(base_fee_per_gas) = base_fee_per_gas {
         if txn.max_fee_per_gas() < base_fee_per_gas {
-            return Err(ValidationError::MaxFeeLessThanBase);
+            return Err(ValidationError::MaxFeeLessThanBase {
+                max_fee_per_gas: txn.max_fee_per_gas(),
+                base_fee_per_gas,
+            });
         }
     }
 
     // https://github.com/ethereum/EIPs/pull/3594
     if txn.max_priority_fee_per_gas() > txn.max_fee_per_gas() {
-        return Err(ValidationError::MaxPriorityFeeGreaterThanMax);
+        return Err(ValidationError::MaxPriorityFeeGreaterThanMax {
+            max_priority_fee_per_gas: txn.max_priority_fee_per_gas(),
+            max_fee_per_gas: txn.max_fee_per_gas(),
+        });
     }
 
     Ok(())
 }
 
-pub fn engine_factory(chain_config: ChainSpec) -> anyhow::Result<Box<dyn Consensus>> {
-    Ok(match chain_config.consensus.seal_verification {
+pub fn engine_factory(chain_config: &ChainSpec) -> anyhow::Result<Box<dyn Consensus>> {
+    Ok(match chain_config.consensus.seal_verification.clone() {
         SealVerificationParams::Ethash {
             duration_limit,
             block_reward,
@@ -182,7 +224,7 @@ pub fn engine_factory(chain_config: ChainSpec) -> anyhow::Result<Box<dyn Consens
             difficulty_bomb,
             skip_pow_verification,
         } => Box::new(Ethash::new(
-            chain_config.params.chain_id,
+            chain_config.params.clone(),
             chain_config.consensus.eip1559_block,
             duration_limit,
             block_reward,
@@ -190,6 +232,8 @@ pub fn engine_factory(chain_config: ChainSpec) -> anyhow::Result<Box<dyn Consens
             byzantium_formula,
             difficulty_bomb,
             skip_pow_verification,
+            chain_config.consensus.allowed_future_drift,
+            Arc::new(SystemClock),
         )),
         _ => bail!("unsupported consensus engine"),
     })
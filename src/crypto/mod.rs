@@ -3,10 +3,17 @@ use ethereum_types::{Address, H256};
 use hash256_std_hasher::Hash256StdHasher;
 use hash_db::Hasher;
 use hex_literal::hex;
-use secp256k1::{PublicKey, SECP256K1};
+use rayon::prelude::*;
+use secp256k1::{
+    ecdsa::{RecoverableSignature, RecoveryId},
+    Message, PublicKey, Secp256k1, VerifyOnly, SECP256K1,
+};
 use sha3::{Digest, Keccak256};
 
 pub mod blake2;
+mod keccak_batch;
+
+pub use self::keccak_batch::keccak256_batch;
 
 /// Concrete `Hasher` impl for the Keccak-256 hash
 #[derive(Default, Debug, Clone, PartialEq)]
@@ -33,21 +40,32 @@ where
     triehash::trie_root::<KeccakHasher, _, _, _>(input)
 }
 
-/// Generates a trie root hash for a vector of values
-pub fn ordered_trie_root<I, V>(input: I) -> H256
-where
-    I: IntoIterator<Item = V>,
-    V: AsRef<[u8]>,
-{
-    triehash::ordered_trie_root::<KeccakHasher, I>(input)
-}
-
+/// Encoding of transactions and receipts as values of their block tries.
 pub trait TrieEncode {
     fn trie_encode(&self) -> Bytes;
 }
 
-pub fn root_hash<T: TrieEncode>(values: &[T]) -> H256 {
-    ordered_trie_root(values.iter().map(TrieEncode::trie_encode))
+impl<T: TrieEncode + ?Sized> TrieEncode for &T {
+    fn trie_encode(&self) -> Bytes {
+        (**self).trie_encode()
+    }
+}
+
+/// Already encoded value.
+impl TrieEncode for Bytes {
+    fn trie_encode(&self) -> Bytes {
+        self.clone()
+    }
+}
+
+/// Root of the trie keyed by RLP of each item's index, as the transactions and receipts
+/// roots of block headers.
+pub fn ordered_trie_root<I>(items: I) -> H256
+where
+    I: IntoIterator,
+    I::Item: TrieEncode,
+{
+    triehash::ordered_trie_root::<KeccakHasher, _>(items.into_iter().map(|item| item.trie_encode()))
 }
 
 pub fn is_valid_signature(r: H256, s: H256) -> bool {
@@ -78,6 +96,42 @@ pub fn pubkey_to_address(pubkey: &secp256k1::PublicKey) -> Address {
     Address::from_slice(&keccak256(&pubkey.serialize_uncompressed()[1..]).0[12..])
 }
 
+thread_local! {
+    static SECP256K1_VERIFY: Secp256k1<VerifyOnly> = Secp256k1::verification_only();
+}
+
+/// Runs `f` with the verification context of the current thread, which is allocated once
+/// instead of for every signature.
+pub fn with_secp256k1<R>(f: impl FnOnce(&Secp256k1<VerifyOnly>) -> R) -> R {
+    SECP256K1_VERIFY.with(f)
+}
+
+/// Recovers the address that signed `hash` with compact signature `r || s`.
+pub fn recover_signer(
+    hash: H256,
+    signature: &[u8; 64],
+    odd_y_parity: bool,
+) -> Result<Address, secp256k1::Error> {
+    let signature =
+        RecoverableSignature::from_compact(signature, RecoveryId::from_i32(odd_y_parity.into())?)?;
+    let message = Message::from_slice(hash.as_bytes())?;
+    let public = with_secp256k1(|secp| secp.recover_ecdsa(&message, &signature))?;
+    Ok(pubkey_to_address(&public))
+}
+
+/// Recovers signers of many `(hash, signature, odd_y_parity)` entries in parallel.
+///
+/// libsecp256k1 has no batch ECDSA verification, so entries are spread across threads,
+/// each reusing its own context.
+pub fn recover_signers(
+    signatures: &[(H256, [u8; 64], bool)],
+) -> Vec<Result<Address, secp256k1::Error>> {
+    signatures
+        .par_iter()
+        .map(|(hash, signature, odd_y_parity)| recover_signer(*hash, signature, *odd_y_parity))
+        .collect()
+}
+
 pub fn keccak256(data: impl AsRef<[u8]>) -> H256 {
     H256::from_slice(&Keccak256::digest(data.as_ref()))
 }
@@ -85,6 +139,7 @@ pub fn keccak256(data: impl AsRef<[u8]>) -> H256 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::{Receipt, TxType};
     use hex_literal::hex;
     use secp256k1::SECP256K1;
 
@@ -101,4 +156,193 @@ mod tests {
             hex!("5D6C3f4c505385f4F99057C06F0e265FFc16E829").into()
         );
     }
+
+    #[test]
+    fn ordered_root_is_keyed_by_rlp_index() {
+        assert_eq!(
+            ordered_trie_root(Vec::<Bytes>::new()),
+            crate::models::EMPTY_ROOT
+        );
+
+        // Indices from 0x80 on take two bytes, and 0 is the empty string.
+        for len in [1, 2, 127, 128, 129, 300] {
+            let items = (0..len)
+                .map(|i: u32| Bytes::from(keccak256(i.to_be_bytes()).0.to_vec()))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                ordered_trie_root(&items),
+                trie_root(
+                    items
+                        .iter()
+                        .enumerate()
+                        .map(|(index, item)| (rlp::encode(&(index as u64)), item))
+                ),
+                "{} items",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn ordered_root_of_mainnet_block() {
+        // https://etherscan.io/block/13143465
+        let transactions = [
+            hex!(
+                "02f9027501824f9185050a3d0b5d8523a9e38cf883124f8094a57bd00134b285"
+                "0b2a1c55860c9e9ea100fdd6cf80b902041cff79cd0000000000000000000000"
+                "00aa2ec16d77cfc057fb9c516282fef9da9de1e9870000000000000000000000"
+                "0000000000000000000000000000000000000000400000000000000000000000"
+                "0000000000000000000000000000000000000001844f0c7c0a00000000000000"
+                "000000000000000000000000000000000000000000000001f400000000000000"
+                "0000000000c02aaa39b223fe8d0a0e5c4f27ead9083c756cc200000000000000"
+                "0000000000a0b86991c6218b36c1d19d4a2e9eb0ce3606eb4800000000000000"
+                "000000000056178a0d5f301baf6cf3e1cd53d9863437345bf900000000000000"
+                "0000000000000000000000000000000000002386f26fc1000000000000000000"
+                "00000000000000000000000000000000a2a15d09519be0000000000000000000"
+                "0000000000000000000000000000daadf45a4bb3477575600000000000000000"
+                "00000000000000000000000000000000003453af3f6dd9600000000000000000"
+                "00000000000000000000003f994c7f39b6af041a3c5532700000000000000000"
+                "00000000000000000000000000000000000de0b6b3a764000000000000000000"
+                "0000000000000000000000000000000000000000006130319200000000000000"
+                "0000000000000000000000000000000000000000000000000000000000000000"
+                "000000000000000000000000000000000000000000c080a09a8548ba3759730f"
+                "e25be0412c0b183ec975d15da2f12653d5f0a2016ca01f27a06d93f2176bfda9"
+                "18c06365e507c6c66a16d30b9e76d2d8e5a7f2802e3bcc6593"
+            )
+            .to_vec(),
+            hex!(
+                "02f90216018304ddeb850156ba09808529f7bcba808304e200940000006daea1"
+                "723962647b7e189d311d757fb79380b901a4178979ae00000000000000000000"
+                "00000000000476fde29330084b2b0b08a9f7d2ac6f2b00000000000000000000"
+                "0000000000000000000000000000000000000000000000000000000000000000"
+                "0000000000000000000000000000000000000000006000000000000000000000"
+                "000000000000000000000000000000000000000001048803dbee000000000000"
+                "00000000000000000000000000000000003411811118647e0000000000000000"
+                "000000000000000000000000000000000000000000037d698685000000000000"
+                "00000000000000000000000000000000000000000000000000a0000000000000"
+                "0000000000000000006daea1723962647b7e189d311d757fb793000000000000"
+                "0000000000000000000000000000000000000000000061303168000000000000"
+                "0000000000000000000000000000000000000000000000000002000000000000"
+                "000000000000a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48000000000000"
+                "00000000000031c8eacbffdd875c74b94b077895bd78cf1e64a3000000000000"
+                "00000000000000000000000000000000000000000000c001a09f56a8c52a7e8e"
+                "37ecd8c8bff54a414a92d349ea72a5389b1f3ed0f86c3248bea07aa4b9e5ff16"
+                "553ea28fb8f2701a56c2c60e69810377ebbaabadddcae0677168"
+            )
+            .to_vec(),
+            hex!(
+                "02f90216018304ddec850156ba09808529f7bcba808304e200940000006daea1"
+                "723962647b7e189d311d757fb79380b901a4178979ae00000000000000000000"
+                "00000000005c9426e6910f22f0c00ed3690a4884dd6e00000000000000000000"
+                "0000000000000000000000000000000000000000000000000000000000000000"
+                "0000000000000000000000000000000000000000006000000000000000000000"
+                "0000000000000000000000000000000000000000010438ed1739000000000000"
+                "00000000000000000000000000000000023bb2f4021291c00000000000000000"
+                "000000000000000000000000000000000000000000038277eafc000000000000"
+                "00000000000000000000000000000000000000000000000000a0000000000000"
+                "0000000000000000006daea1723962647b7e189d311d757fb793000000000000"
+                "0000000000000000000000000000000000000000000061303186000000000000"
+                "0000000000000000000000000000000000000000000000000002000000000000"
+                "000000000000d417144312dbf50465b1c641d016962017ef6240000000000000"
+                "000000000000a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48000000000000"
+                "00000000000000000000000000000000000000000000c001a0e0af864ce72e37"
+                "55ef3e1c0eb8e665300f9374fc0856ebf54340bcf8daddfdf4a0488a890a71fb"
+                "95db2088e8c261149b1ca33902b039eb461805261cd2180b38bb"
+            )
+            .to_vec(),
+            hex!(
+                "02f902b4018203f884773594008520f823e84c8302cfad94e592427a0aece92d"
+                "e3edee1f18e0157c0586156480b90244ac9650d8000000000000000000000000"
+                "0000000000000000000000000000000000000020000000000000000000000000"
+                "0000000000000000000000000000000000000002000000000000000000000000"
+                "0000000000000000000000000000000000000040000000000000000000000000"
+                "0000000000000000000000000000000000000180000000000000000000000000"
+                "0000000000000000000000000000000000000104414bf3890000000000000000"
+                "00000000515d7e9d75e2b76db60f8a051cd890eba23286bc0000000000000000"
+                "00000000c02aaa39b223fe8d0a0e5c4f27ead9083c756cc20000000000000000"
+                "000000000000000000000000000000000000000000000bb80000000000000000"
+                "0000000000000000000000000000000000000000000000000000000000000000"
+                "0000000000000000000000000000000000000000613035800000000000000000"
+                "0000000000000000000000000000001043561a88293000000000000000000000"
+                "0000000000000000000000000000000001addc207d623fcc0000000000000000"
+                "0000000000000000000000000000000000000000000000000000000000000000"
+                "0000000000000000000000000000000000000000000000000000000000000000"
+                "000000000000000000000000000000000000004449404b7c0000000000000000"
+                "0000000000000000000000000000000001addc207d623fcc0000000000000000"
+                "00000000ac569e0f62c7cbbb987518692aae056a0ae1dd180000000000000000"
+                "0000000000000000000000000000000000000000c080a022c1771e804cd2da13"
+                "2d19b10c558847860f6a61e6ca6f1eec54ee747d374055a041a2f6fcd021e297"
+                "7d5d555ed1413632638a0bf1b7e86b8c46ab9dd77effdb71"
+            )
+            .to_vec(),
+            hex!(
+                "02f8b4018305a9f9847735940085293605aa008303d09094a0b86991c6218b36"
+                "c1d19d4a2e9eb0ce3606eb4880b844a9059cbb0000000000000000000000005c"
+                "874f13a92f5c35aec3d7bc07c630a92a79289a00000000000000000000000000"
+                "0000000000000000000000000000003b9aca00c001a06dd51acdc109fcbe29eb"
+                "f526c4e93cef449dad611e0123cfa221770d1619aa55a07112d13e643b228816"
+                "6ae49770b7df778f749f45f28c1f33c8d6914b5f65a4f2"
+            )
+            .to_vec(),
+            hex!(
+                "02f8b20182036d8473a20d0085226f4988d982d9c594e66b3aa360bb78468c00"
+                "bebe163630269db3324f80b844095ea7b30000000000000000000000007a250d"
+                "5630b4cf539739df2c5dacb4c659f2488dffffffffffffffffffffffffffffff"
+                "ffffffffffffffffffffffffffffffffffc080a0629b9b5baed83904eed041b3"
+                "8d3debc572cc7ad4be55aac679a470e77d1152dea029eacf16c562d6ffd996ce"
+                "f9f96bb03b58ad775e28b68831f0e8fbeec42d60bd"
+            )
+            .to_vec(),
+            hex!(
+                "02f8980123843b9aca008528dcc4b35e8306300694ca414feacd26006c3748a1"
+                "87bedf455bef5fc57d88011c37937e080000a4a0712d68000000000000000000"
+                "0000000000000000000000000000000000000000000002c080a070bcb39ac6f5"
+                "40498c3adfdf3a23ecce5cf7b4f75b0674c157da02350edf8ed4a040e997c09d"
+                "ef486888c34e77565cce82b348d0035e2ea36bf125252f7895ff3c"
+            )
+            .to_vec(),
+        ];
+        assert_eq!(
+            ordered_trie_root(transactions.into_iter().map(Bytes::from)),
+            H256(hex!(
+                "6aaee4a301af3f721f01f886c50db6ff354487e0a3b713601797a439475ade0c"
+            ))
+        );
+
+        // Receipts root of every post-Byzantium block holding a single plain transfer.
+        assert_eq!(
+            ordered_trie_root([Receipt::new(TxType::Legacy, true, 21_000, vec![])]),
+            H256(hex!(
+                "056b23fbba480696b65fe5a59b8f2148a1299103c4f57df839233af2cf4ca2d2"
+            ))
+        );
+    }
+
+    #[test]
+    fn recover_signed() {
+        let hashes = (0..16_u8).map(|i| keccak256([i])).collect::<Vec<_>>();
+        let keys = hashes.iter().map(|_| generate_key()).collect::<Vec<_>>();
+
+        let signatures = hashes
+            .iter()
+            .zip(&keys)
+            .map(|(hash, key)| {
+                let (rec, sig) = SECP256K1
+                    .sign_ecdsa_recoverable(&Message::from_slice(hash.as_bytes()).unwrap(), key)
+                    .serialize_compact();
+                (*hash, sig, rec.to_i32() == 1)
+            })
+            .collect::<Vec<_>>();
+
+        for ((recovered, (hash, sig, odd)), key) in recover_signers(&signatures)
+            .into_iter()
+            .zip(&signatures)
+            .zip(&keys)
+        {
+            let expected = pubkey_to_address(&to_pubkey(key));
+            assert_eq!(recovered.unwrap(), expected);
+            assert_eq!(recover_signer(*hash, sig, *odd).unwrap(), expected);
+            assert_ne!(recover_signer(*hash, sig, !*odd).ok(), Some(expected));
+        }
+    }
 }
@@ -0,0 +1,447 @@
+use crate::{
+    models::*,
+    sentry::{
+        messages::{BlockBodyType, EthMessageId, GetBlockBodiesMessage, Message},
+        sentry_client::PeerFilter,
+        sentry_client_reactor::*,
+    },
+};
+use anyhow::bail;
+use lru::LruCache;
+use parking_lot::Mutex;
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio_stream::StreamExt;
+use tracing::*;
+
+/// Maximum number of bodies requested from a peer at once.
+const MAX_BODIES_REQUEST: usize = 128;
+/// Maximum number of requests in flight.
+const MAX_REQUESTS: usize = 16;
+/// Bodies of announced blocks kept until the download gets to them.
+const ANNOUNCED_BODIES: usize = 1024;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const TICK_INTERVAL: Duration = Duration::from_millis(500);
+
+fn body_matches(header: &BlockHeader, body: &BlockBody) -> bool {
+    Block::ommers_hash(&body.ommers) == header.ommers_hash
+        && Block::transactions_root(&body.transactions) == header.transactions_root
+}
+
+/// Bodies of the blocks announced by the peers with `NewBlock`, kept until the body download
+/// gets to them, so that they are not requested again.
+#[derive(Clone, Debug)]
+pub struct AnnouncedBodies {
+    bodies: Arc<Mutex<LruCache<H256, BlockBody>>>,
+}
+
+impl Default for AnnouncedBodies {
+    fn default() -> Self {
+        Self {
+            bodies: Arc::new(Mutex::new(LruCache::new(ANNOUNCED_BODIES))),
+        }
+    }
+}
+
+impl AnnouncedBodies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn insert(&self, hash: H256, body: BlockBody) {
+        self.bodies.lock().put(hash, body);
+    }
+
+    fn take(&self, hash: H256) -> Option<BlockBody> {
+        self.bodies.lock().pop(&hash)
+    }
+
+    /// Keeps the bodies of the blocks announced by the peers.
+    pub async fn run(&self, sentry: SentryClientReactorShared) -> anyhow::Result<()> {
+        let mut messages = sentry
+            .read()
+            .await
+            .receive_messages(EthMessageId::NewBlock)?;
+        while let Some(message) = messages.next().await {
+            if let Message::NewBlock(message) = message.message {
+                let block = *message.block;
+                self.insert(block.header.hash(), block.into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Outcome of a `BlockBodies` response.
+#[derive(Debug, PartialEq)]
+enum Delivery {
+    Accepted,
+    /// Not a response to a pending request, e.g. one which timed out already.
+    Unexpected,
+    /// A body does not match the header of the block it was requested for.
+    Invalid,
+}
+
+#[derive(Debug)]
+struct Request {
+    blocks: Vec<BlockNumber>,
+    sent_at: Instant,
+}
+
+/// Bookkeeping of the download of the bodies of a range of blocks.
+#[derive(Debug)]
+struct DownloadState {
+    headers: BTreeMap<BlockNumber, (H256, BlockHeader)>,
+    bodies: BTreeMap<BlockNumber, BlockBody>,
+    requests: HashMap<u64, Request>,
+    in_flight: HashSet<BlockNumber>,
+}
+
+impl DownloadState {
+    /// Bodies of the blocks without transactions and ommers are known from their headers alone.
+    fn new(headers: Vec<(BlockNumber, H256, BlockHeader)>) -> Self {
+        let mut bodies = BTreeMap::new();
+        for (number, _, header) in &headers {
+            if header.transactions_root == EMPTY_ROOT && header.ommers_hash == EMPTY_LIST_HASH {
+                bodies.insert(
+                    *number,
+                    BlockBody {
+                        transactions: vec![],
+                        ommers: vec![],
+                    },
+                );
+            }
+        }
+
+        Self {
+            headers: headers
+                .into_iter()
+                .map(|(number, hash, header)| (number, (hash, header)))
+                .collect(),
+            bodies,
+            requests: Default::default(),
+            in_flight: Default::default(),
+        }
+    }
+
+    fn missing(&self) -> impl Iterator<Item = (BlockNumber, H256)> + '_ {
+        self.headers
+            .iter()
+            .filter(|(number, _)| !self.bodies.contains_key(number))
+            .map(|(&number, (hash, _))| (number, *hash))
+    }
+
+    fn is_complete(&self) -> bool {
+        self.bodies.len() == self.headers.len()
+    }
+
+    /// Takes the announced bodies of the blocks still missing.
+    fn on_announced(&mut self, announced: &AnnouncedBodies) {
+        for (number, hash) in self.missing().collect::<Vec<_>>() {
+            if let Some(body) = announced.take(hash) {
+                if body_matches(&self.headers[&number].1, &body) {
+                    self.bodies.insert(number, body);
+                }
+            }
+        }
+    }
+
+    /// Requests for the missing bodies which are not in flight, returns them to send.
+    fn schedule(&mut self, now: Instant, next_request_id: &mut u64) -> Vec<(u64, Vec<H256>)> {
+        let mut pending = self
+            .missing()
+            .filter(|(number, _)| !self.in_flight.contains(number))
+            .collect::<Vec<_>>()
+            .into_iter();
+
+        let mut scheduled = vec![];
+        while self.requests.len() < MAX_REQUESTS {
+            let chunk = pending
+                .by_ref()
+                .take(MAX_BODIES_REQUEST)
+                .collect::<Vec<_>>();
+            if chunk.is_empty() {
+                break;
+            }
+
+            let request_id = *next_request_id;
+            *next_request_id += 1;
+            let blocks = chunk.iter().map(|&(number, _)| number).collect::<Vec<_>>();
+            self.in_flight.extend(blocks.iter().copied());
+            self.requests.insert(
+                request_id,
+                Request {
+                    blocks,
+                    sent_at: now,
+                },
+            );
+            scheduled.push((
+                request_id,
+                chunk.into_iter().map(|(_, hash)| hash).collect(),
+            ));
+        }
+
+        scheduled
+    }
+
+    /// Bodies come in the order of the request, peers may leave out the trailing ones.
+    fn on_bodies(&mut self, request_id: u64, bodies: Vec<BlockBodyType>) -> Delivery {
+        let Entry::Occupied(entry) = self.requests.entry(request_id) else {
+            return Delivery::Unexpected;
+        };
+        let request = entry.remove();
+        for number in &request.blocks {
+            self.in_flight.remove(number);
+        }
+
+        if bodies.len() > request.blocks.len() {
+            return Delivery::Invalid;
+        }
+
+        let mut received = Vec::with_capacity(bodies.len());
+        for (number, body) in request.blocks.into_iter().zip(bodies) {
+            let body = BlockBody {
+                transactions: body.transactions,
+                ommers: body.ommers,
+            };
+            if !body_matches(&self.headers[&number].1, &body) {
+                return Delivery::Invalid;
+            }
+            received.push((number, body));
+        }
+        self.bodies.extend(received);
+
+        Delivery::Accepted
+    }
+
+    /// Releases the blocks of the requests which were not answered in time.
+    fn on_timeout(&mut self, now: Instant) -> usize {
+        let expired = self
+            .requests
+            .iter()
+            .filter(|(_, request)| now.saturating_duration_since(request.sent_at) > REQUEST_TIMEOUT)
+            .map(|(&request_id, _)| request_id)
+            .collect::<Vec<_>>();
+
+        for request_id in &expired {
+            if let Some(request) = self.requests.remove(request_id) {
+                for number in request.blocks {
+                    self.in_flight.remove(&number);
+                }
+            }
+        }
+        expired.len()
+    }
+
+    fn into_bodies(self) -> Vec<BlockBody> {
+        self.bodies.into_values().collect()
+    }
+}
+
+/// Downloads the bodies of blocks with known headers.
+///
+/// Bodies which need no request are not requested: empty bodies are made up from the header,
+/// and the bodies of blocks announced with `NewBlock` are taken from [`AnnouncedBodies`].
+/// eth/66 has no message listing the transaction hashes of a block, so other bodies are not
+/// assembled from pooled transactions but requested with `GetBlockBodies`.
+#[derive(Debug)]
+pub struct BodyDownloader {
+    sentry: SentryClientReactorShared,
+    announced: AnnouncedBodies,
+    next_request_id: u64,
+}
+
+impl BodyDownloader {
+    pub fn new(sentry: SentryClientReactorShared, announced: AnnouncedBodies) -> Self {
+        Self {
+            sentry,
+            announced,
+            next_request_id: 0,
+        }
+    }
+
+    /// Bodies of the blocks of `headers`, in the same order.
+    pub async fn download(
+        &mut self,
+        headers: Vec<(BlockNumber, H256, BlockHeader)>,
+    ) -> anyhow::Result<Vec<BlockBody>> {
+        let mut state = DownloadState::new(headers);
+        state.on_announced(&self.announced);
+        if state.is_complete() {
+            return Ok(state.into_bodies());
+        }
+
+        let mut messages = self
+            .sentry
+            .read()
+            .await
+            .receive_messages(EthMessageId::BlockBodies)?;
+        let mut ticker = tokio::time::interval(TICK_INTERVAL);
+        while !state.is_complete() {
+            let requests = state.schedule(Instant::now(), &mut self.next_request_id);
+            self.send_requests(requests).await?;
+
+            tokio::select! {
+                message = messages.next() => {
+                    let Some(message) = message else {
+                        bail!("BodyDownloader: sentry message stream ended");
+                    };
+                    let Message::BlockBodies(bodies) = message.message else {
+                        continue;
+                    };
+
+                    match state.on_bodies(bodies.request_id, bodies.block_bodies) {
+                        Delivery::Accepted => {}
+                        Delivery::Unexpected => {
+                            debug!("BodyDownloader: unexpected response {}", bodies.request_id);
+                        }
+                        Delivery::Invalid => {
+                            if let Some(peer_id) = message.from_peer_id {
+                                warn!("BodyDownloader: invalid bodies from {:?}", peer_id);
+                                self.sentry.read().await.penalize_peer(peer_id).await?;
+                            }
+                        }
+                    }
+                }
+                _ = ticker.tick() => {
+                    let expired = state.on_timeout(Instant::now());
+                    if expired > 0 {
+                        debug!("BodyDownloader: {} requests timed out", expired);
+                    }
+                    state.on_announced(&self.announced);
+                }
+            }
+        }
+
+        Ok(state.into_bodies())
+    }
+
+    async fn send_requests(&self, requests: Vec<(u64, Vec<H256>)>) -> anyhow::Result<()> {
+        if requests.is_empty() {
+            return Ok(());
+        }
+
+        let sentry = self.sentry.read().await;
+        for (request_id, block_hashes) in requests {
+            let message = Message::GetBlockBodies(GetBlockBodiesMessage {
+                request_id,
+                block_hashes,
+            });
+            if let Err(error) = sentry.try_send_message(message, PeerFilter::Random(1)) {
+                match error.downcast_ref::<SendMessageError>() {
+                    // The request times out and is sent again.
+                    Some(SendMessageError::SendQueueFull) => {
+                        debug!("BodyDownloader: request send queue is full");
+                    }
+                    _ => return Err(error),
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    fn block(number: u64, ommers: Vec<BlockHeader>) -> (BlockNumber, H256, BlockHeader, BlockBody) {
+        let header = BlockHeader {
+            number: BlockNumber(number),
+            ommers_hash: Block::ommers_hash(&ommers),
+            transactions_root: EMPTY_ROOT,
+            ..BlockHeader::empty()
+        };
+        let body = BlockBody {
+            transactions: vec![],
+            ommers,
+        };
+        (header.number, header.hash(), header, body)
+    }
+
+    fn ommer(tag: &'static [u8]) -> BlockHeader {
+        BlockHeader {
+            extra_data: Bytes::from_static(tag),
+            ..BlockHeader::empty()
+        }
+    }
+
+    #[test]
+    fn download_state() {
+        let blocks = [
+            block(1, vec![]),
+            block(2, vec![ommer(b"a")]),
+            block(3, vec![ommer(b"b")]),
+            block(4, vec![ommer(b"c")]),
+        ];
+        let to_body_type = |body: &BlockBody| BlockBodyType {
+            transactions: body.transactions.clone(),
+            ommers: body.ommers.clone(),
+        };
+
+        let mut state = DownloadState::new(
+            blocks
+                .iter()
+                .map(|(number, hash, header, _)| (*number, *hash, header.clone()))
+                .collect(),
+        );
+
+        // The empty body is not requested, the announced one is taken as is.
+        let announced = AnnouncedBodies::new();
+        announced.insert(blocks[3].1, blocks[3].3.clone());
+        state.on_announced(&announced);
+        assert!(announced.take(blocks[3].1).is_none());
+
+        let now = Instant::now();
+        let mut next_request_id = 0;
+        let requests = state.schedule(now, &mut next_request_id);
+        assert_eq!(requests, vec![(0, vec![blocks[1].1, blocks[2].1])]);
+        assert!(state.schedule(now, &mut next_request_id).is_empty());
+
+        // A body not matching its header fails the request.
+        assert_eq!(
+            state.on_bodies(0, vec![to_body_type(&blocks[2].3)]),
+            Delivery::Invalid
+        );
+        assert_eq!(
+            state.on_bodies(0, vec![to_body_type(&blocks[1].3)]),
+            Delivery::Unexpected
+        );
+
+        // A partial response leaves the rest to the next request.
+        let requests = state.schedule(now, &mut next_request_id);
+        assert_eq!(requests, vec![(1, vec![blocks[1].1, blocks[2].1])]);
+        assert_eq!(
+            state.on_bodies(1, vec![to_body_type(&blocks[1].3)]),
+            Delivery::Accepted
+        );
+        assert!(!state.is_complete());
+
+        // Unanswered requests are sent again after the timeout.
+        let requests = state.schedule(now, &mut next_request_id);
+        assert_eq!(requests, vec![(2, vec![blocks[2].1])]);
+        assert_eq!(state.on_timeout(now + REQUEST_TIMEOUT), 0);
+        assert_eq!(state.on_timeout(now + REQUEST_TIMEOUT * 2), 1);
+        let requests = state.schedule(now, &mut next_request_id);
+        assert_eq!(requests, vec![(3, vec![blocks[2].1])]);
+        assert_eq!(
+            state.on_bodies(3, vec![to_body_type(&blocks[2].3)]),
+            Delivery::Accepted
+        );
+
+        assert!(state.is_complete());
+        assert_eq!(
+            state.into_bodies(),
+            blocks
+                .into_iter()
+                .map(|(_, _, _, body)| body)
+                .collect::<Vec<_>>()
+        );
+    }
+}
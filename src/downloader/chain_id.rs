@@ -1,5 +0,0 @@
-use serde::Deserialize;
-
-#[derive(Deserialize, Clone)]
-#[serde(transparent)]
-pub struct ChainId(pub u32);
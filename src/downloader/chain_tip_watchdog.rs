@@ -0,0 +1,260 @@
+use crate::{
+    kv::mdbx::*,
+    models::*,
+    sentry::{
+        messages::{EthMessageId, Message},
+        sentry_client::PeerId,
+        sentry_client_reactor::*,
+    },
+    stagedsync::stages::*,
+    StageId,
+};
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::Notify;
+use tokio_stream::StreamExt;
+use tracing::*;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Stages reported in the stall diagnostics.
+const DIAGNOSTIC_STAGES: [StageId; 5] = [HEADERS, BLOCK_HASHES, SENDERS, EXECUTION, FINISH];
+
+#[derive(Debug, PartialEq)]
+struct Stall {
+    local_head: BlockNumber,
+    peers_head: BlockNumber,
+    stalled_for: Duration,
+    /// Peers not announcing anything above the local head.
+    lagging_peers: Vec<PeerId>,
+}
+
+/// Detects that the canonical chain stopped advancing while the peers report higher heads.
+#[derive(Debug)]
+struct StallDetector {
+    stall_timeout: Duration,
+    local_head: BlockNumber,
+    local_head_changed_at: Instant,
+    peer_heads: HashMap<PeerId, (BlockNumber, Instant)>,
+}
+
+impl StallDetector {
+    fn new(stall_timeout: Duration, now: Instant) -> Self {
+        Self {
+            stall_timeout,
+            local_head: BlockNumber(0),
+            local_head_changed_at: now,
+            peer_heads: HashMap::new(),
+        }
+    }
+
+    fn on_local_head(&mut self, head: BlockNumber, now: Instant) {
+        if head != self.local_head {
+            self.local_head = head;
+            self.local_head_changed_at = now;
+        }
+    }
+
+    fn on_peer_head(&mut self, peer_id: PeerId, head: BlockNumber, now: Instant) {
+        let entry = self.peer_heads.entry(peer_id).or_insert((head, now));
+        entry.0 = std::cmp::max(entry.0, head);
+        entry.1 = now;
+    }
+
+    fn check(&mut self, now: Instant) -> Option<Stall> {
+        let stalled_for = now.saturating_duration_since(self.local_head_changed_at);
+        if stalled_for < self.stall_timeout {
+            return None;
+        }
+
+        // forget the peers which went silent
+        let stall_timeout = self.stall_timeout;
+        self.peer_heads
+            .retain(|_, (_, seen_at)| now.saturating_duration_since(*seen_at) < stall_timeout);
+
+        let peers_head = self.peer_heads.values().map(|(head, _)| *head).max()?;
+        if peers_head <= self.local_head {
+            return None;
+        }
+
+        let lagging_peers = self
+            .peer_heads
+            .iter()
+            .filter(|(_, (head, _))| *head <= self.local_head)
+            .map(|(peer_id, _)| *peer_id)
+            .collect();
+
+        // wait for another full period before reporting again
+        self.local_head_changed_at = now;
+
+        Some(Stall {
+            local_head: self.local_head,
+            peers_head,
+            stalled_for,
+            lagging_peers,
+        })
+    }
+}
+
+/// Watches the staged sync progress without relying on a consensus layer,
+/// and intervenes if the node falls behind the peers.
+#[derive(Debug)]
+pub struct ChainTipWatchdog<DB> {
+    db: Arc<DB>,
+    sentry: SentryClientReactorShared,
+    stall_timeout: Duration,
+    restart_signal: Option<Arc<Notify>>,
+}
+
+impl<DB, E> ChainTipWatchdog<DB>
+where
+    DB: Deref<Target = MdbxEnvironment<E>> + Send + Sync,
+    E: EnvironmentKind,
+{
+    pub fn new(db: Arc<DB>, sentry: SentryClientReactorShared, stall_timeout: Duration) -> Self {
+        Self {
+            db,
+            sentry,
+            stall_timeout,
+            restart_signal: None,
+        }
+    }
+
+    /// Notified to restart the headers downloader on a stall.
+    pub fn set_restart_signal(&mut self, signal: Arc<Notify>) -> &mut Self {
+        self.restart_signal = Some(signal);
+        self
+    }
+
+    pub async fn run(&self) -> anyhow::Result<()> {
+        let mut announces = {
+            let sentry = self.sentry.read().await;
+            sentry
+                .receive_messages(EthMessageId::NewBlockHashes)?
+                .merge(sentry.receive_messages(EthMessageId::NewBlock)?)
+        };
+
+        let mut detector = StallDetector::new(self.stall_timeout, Instant::now());
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+
+        loop {
+            tokio::select! {
+                message = announces.next() => {
+                    let Some(message) = message else {
+                        return Ok(());
+                    };
+                    let Some(peer_id) = message.from_peer_id else {
+                        continue;
+                    };
+                    let head = match message.message {
+                        Message::NewBlockHashes(message) => message.ids.iter().map(|id| id.number).max(),
+                        Message::NewBlock(message) => Some(message.block.header.number),
+                        _ => None,
+                    };
+                    if let Some(head) = head {
+                        detector.on_peer_head(peer_id, head, Instant::now());
+                    }
+                }
+                _ = interval.tick() => {
+                    let local_head = tokio::task::block_in_place(|| self.local_head())?;
+                    detector.on_local_head(local_head, Instant::now());
+                    if let Some(stall) = detector.check(Instant::now()) {
+                        self.on_stall(stall).await?;
+                    }
+                }
+            }
+        }
+    }
+
+    fn local_head(&self) -> anyhow::Result<BlockNumber> {
+        let tx = self.db.begin()?;
+        Ok(FINISH.get_progress(&tx)?.unwrap_or_default())
+    }
+
+    fn log_diagnostics(&self, stall: &Stall) -> anyhow::Result<()> {
+        warn!(
+            "ChainTipWatchdog: no new canonical blocks for {:?}, local head {}, peers head {}",
+            stall.stalled_for, stall.local_head.0, stall.peers_head.0
+        );
+
+        let tx = self.db.begin()?;
+        for stage_id in DIAGNOSTIC_STAGES {
+            let progress = stage_id.get_progress(&tx)?;
+            info!(
+                "ChainTipWatchdog: stage {} progress {:?}",
+                stage_id, progress
+            );
+        }
+        Ok(())
+    }
+
+    async fn on_stall(&self, stall: Stall) -> anyhow::Result<()> {
+        tokio::task::block_in_place(|| self.log_diagnostics(&stall))?;
+
+        // free the slots of the useless peers, so that the sentry finds new ones
+        if !stall.lagging_peers.is_empty() {
+            info!(
+                "ChainTipWatchdog: rotating {} lagging peers",
+                stall.lagging_peers.len()
+            );
+            let sentry = self.sentry.read().await;
+            for peer_id in stall.lagging_peers {
+                sentry.penalize_peer(peer_id).await?;
+            }
+        }
+
+        if let Some(restart_signal) = &self.restart_signal {
+            info!("ChainTipWatchdog: restarting the headers downloader");
+            restart_signal.notify_one();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_stall() {
+        let timeout = Duration::from_secs(60);
+        let start = Instant::now();
+        let mut detector = StallDetector::new(timeout, start);
+        let peer1 = PeerId::repeat_byte(1);
+        let peer2 = PeerId::repeat_byte(2);
+
+        detector.on_local_head(BlockNumber(100), start);
+        detector.on_peer_head(peer1, BlockNumber(100), start);
+        detector.on_peer_head(peer2, BlockNumber(110), start);
+        assert_eq!(detector.check(start + timeout / 2), None);
+
+        // progressing
+        detector.on_local_head(BlockNumber(105), start + timeout / 2);
+        assert_eq!(detector.check(start + timeout), None);
+
+        let now = start + timeout + timeout / 2;
+        detector.on_peer_head(peer1, BlockNumber(105), now);
+        detector.on_peer_head(peer2, BlockNumber(120), now);
+        assert_eq!(
+            detector.check(now),
+            Some(Stall {
+                local_head: BlockNumber(105),
+                peers_head: BlockNumber(120),
+                stalled_for: timeout,
+                lagging_peers: vec![peer1],
+            })
+        );
+        // reported once per period
+        assert_eq!(detector.check(now), None);
+
+        // no stall if the peers are not ahead
+        let mut detector = StallDetector::new(timeout, start);
+        detector.on_local_head(BlockNumber(100), start);
+        detector.on_peer_head(peer1, BlockNumber(100), start + timeout);
+        assert_eq!(detector.check(start + timeout), None);
+    }
+}
@@ -0,0 +1,14 @@
+use crate::kv::KvError;
+use thiserror::Error;
+
+/// Failure of the downloader stages.
+#[derive(Debug, Error)]
+pub enum DownloadError {
+    #[error("unwind finalize command expected in the unwind request")]
+    MissingUnwindFinalize,
+    #[error(transparent)]
+    Kv(#[from] KvError),
+    /// Failures of the header and body downloaders, which report [`anyhow::Error`].
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
@@ -1,9 +1,12 @@
 use super::{
-    downloader_forky, downloader_linear, downloader_preverified,
-    headers::header_slices::HeaderSlices, stages::fork_switch_command::ForkSwitchCommand,
-    ui::ui_system::UISystemShared, verification::header_slice_verifier::HeaderSliceVerifier,
+    downloader_follow, downloader_forky, downloader_linear, downloader_preverified,
+    headers::{header_slice_store::HeaderSliceStore, header_slices::HeaderSlices},
+    stages::{follow_reorg_command::FollowReorgCommand, fork_switch_command::ForkSwitchCommand},
+    ui::ui_system::UISystemShared,
+    verification::header_slice_verifier::HeaderSliceVerifier,
 };
 use crate::{
+    downloader::{opts::RequestBatchingOpts, DownloadError},
     kv::mdbx::MdbxTransaction,
     models::*,
     sentry::{chain_config::ChainConfig, sentry_client_reactor::*},
@@ -20,6 +23,8 @@ pub struct Downloader {
     downloader_preverified: downloader_preverified::DownloaderPreverified,
     downloader_linear: downloader_linear::DownloaderLinear,
     downloader_forky: downloader_forky::DownloaderForky,
+    downloader_follow: downloader_follow::DownloaderFollow,
+    slice_store: Option<Arc<HeaderSliceStore>>,
     genesis_block_hash: H256,
 }
 
@@ -40,7 +45,12 @@ pub struct DownloaderRunState {
 #[derive(Clone)]
 pub struct DownloaderUnwindRequest {
     pub unwind_to_block_num: BlockNumber,
-    finalize: Arc<Mutex<Option<ForkSwitchCommand>>>,
+    finalize: Arc<Mutex<Option<UnwindFinalizeCommand>>>,
+}
+
+enum UnwindFinalizeCommand {
+    ForkSwitch(ForkSwitchCommand),
+    FollowReorg(FollowReorgCommand),
 }
 
 impl Debug for DownloaderRunState {
@@ -60,7 +70,18 @@ impl From<ForkSwitchCommand> for DownloaderUnwindRequest {
     fn from(command: ForkSwitchCommand) -> Self {
         Self {
             unwind_to_block_num: command.connection_block_num(),
-            finalize: Arc::new(Mutex::new(Some(command))),
+            finalize: Arc::new(Mutex::new(Some(UnwindFinalizeCommand::ForkSwitch(command)))),
+        }
+    }
+}
+
+impl From<FollowReorgCommand> for DownloaderUnwindRequest {
+    fn from(command: FollowReorgCommand) -> Self {
+        Self {
+            unwind_to_block_num: command.connection_block_num(),
+            finalize: Arc::new(Mutex::new(Some(UnwindFinalizeCommand::FollowReorg(
+                command,
+            )))),
         }
     }
 }
@@ -70,6 +91,7 @@ impl Downloader {
         chain_config: ChainConfig,
         verifier: Box<dyn HeaderSliceVerifier>,
         mem_limit: usize,
+        request_batching_opts: RequestBatchingOpts,
         sentry: SentryClientReactorShared,
     ) -> anyhow::Result<Self> {
         let verifier = Arc::new(verifier);
@@ -78,6 +100,7 @@ impl Downloader {
             verifier.preverified_hashes_config(&chain_config.chain_name())?,
             mem_limit,
             sentry.clone(),
+            request_batching_opts,
         );
 
         let downloader_linear = downloader_linear::DownloaderLinear::new(
@@ -85,20 +108,39 @@ impl Downloader {
             verifier.clone(),
             mem_limit,
             sentry.clone(),
+            request_batching_opts,
+        );
+
+        let downloader_forky = downloader_forky::DownloaderForky::new(
+            chain_config.clone(),
+            verifier.clone(),
+            sentry.clone(),
+            request_batching_opts,
         );
 
-        let downloader_forky =
-            downloader_forky::DownloaderForky::new(chain_config.clone(), verifier, sentry);
+        let downloader_follow =
+            downloader_follow::DownloaderFollow::new(chain_config.clone(), verifier, sentry);
 
         let instance = Self {
             downloader_preverified,
             downloader_linear,
             downloader_forky,
+            downloader_follow,
+            slice_store: None,
             genesis_block_hash: chain_config.genesis_block_hash(),
         };
         Ok(instance)
     }
 
+    /// Keeps the slices downloaded by the linear and preverified downloaders in `slice_store`,
+    /// so that a restart resumes with them.
+    pub fn set_slice_store(&mut self, slice_store: Arc<HeaderSliceStore>) {
+        self.downloader_preverified
+            .set_slice_store(slice_store.clone());
+        self.downloader_linear.set_slice_store(slice_store.clone());
+        self.slice_store = Some(slice_store);
+    }
+
     pub async fn run<'downloader, 'db: 'downloader, E: EnvironmentKind>(
         &'downloader self,
         db_transaction: &'downloader MdbxTransaction<'db, RW, E>,
@@ -106,7 +148,7 @@ impl Downloader {
         max_blocks_count: usize,
         previous_run_state: Option<DownloaderRunState>,
         ui_system: UISystemShared,
-    ) -> anyhow::Result<DownloaderReport> {
+    ) -> Result<DownloaderReport, DownloadError> {
         let mut max_blocks_count = max_blocks_count;
 
         let preverified_report = self
@@ -139,6 +181,33 @@ impl Downloader {
             .await?;
         max_blocks_count -= linear_report.loaded_count;
 
+        if downloader_follow::DownloaderFollow::is_near_top(
+            linear_report.final_block_num,
+            linear_report.estimated_top_block_num,
+        ) {
+            let follow_report = self
+                .downloader_follow
+                .run(
+                    db_transaction,
+                    linear_report.final_block_num,
+                    linear_report.estimated_top_block_num,
+                )
+                .await?;
+
+            return Ok(DownloaderReport {
+                final_block_num: follow_report.final_block_num,
+                target_final_block_num: linear_report.target_final_block_num,
+                run_state: DownloaderRunState {
+                    estimated_top_block_num: Some(follow_report.estimated_top_block_num),
+                    forky_header_slices: None,
+                    forky_fork_header_slices: None,
+                    unwind_request: follow_report
+                        .reorg_command
+                        .map(DownloaderUnwindRequest::from),
+                },
+            });
+        }
+
         let forky_report = self
             .downloader_forky
             .run(
@@ -177,18 +246,28 @@ impl Downloader {
         &'downloader self,
         db_transaction: &'downloader MdbxTransaction<'db, RW, E>,
         unwind_to_block_num: BlockNumber,
-    ) -> anyhow::Result<()> {
-        super::stages::SaveStage::unwind(unwind_to_block_num, db_transaction)
+    ) -> Result<(), DownloadError> {
+        super::stages::SaveStage::unwind(unwind_to_block_num, db_transaction)?;
+
+        // stored slices past the unwind point would be restored on the next run
+        if let Some(slice_store) = &self.slice_store {
+            slice_store.remove_above(unwind_to_block_num)?;
+        }
+        Ok(())
     }
 
     pub fn unwind_finalize<'downloader, 'db: 'downloader, E: EnvironmentKind>(
         &'downloader self,
         db_transaction: &'downloader MdbxTransaction<'db, RW, E>,
         unwind_request: DownloaderUnwindRequest,
-    ) -> anyhow::Result<()> {
+    ) -> Result<(), DownloadError> {
         let Some(finalize) = unwind_request.finalize.lock().take() else {
-            anyhow::bail!("unwind_finalize: finalize command expected in unwind_request");
+            return Err(DownloadError::MissingUnwindFinalize);
         };
-        finalize.execute(db_transaction)
+        match finalize {
+            UnwindFinalizeCommand::ForkSwitch(command) => command.execute(db_transaction)?,
+            UnwindFinalizeCommand::FollowReorg(command) => command.execute(db_transaction)?,
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,544 @@
+use mdbx::{EnvironmentKind, TransactionKind, RW};
+
+use super::{
+    headers::{header::BlockHeader, header_slices::HEADER_SLICE_SIZE},
+    stages::{follow_reorg_command::FollowReorgCommand, SaveStage},
+    verification::header_slice_verifier::HeaderSliceVerifier,
+};
+use crate::{
+    accessors::chain::chain_head::FINALITY_DEPTH,
+    kv::{mdbx::MdbxTransaction, tables},
+    models::*,
+    sentry::{
+        block_id::BlockId,
+        chain_config::ChainConfig,
+        messages::{
+            BlockHashAndNumber, EthMessageId, GetBlockHeadersMessage, GetBlockHeadersMessageParams,
+            Message,
+        },
+        sentry_client::{MessageFromPeer, PeerFilter, PeerId},
+        sentry_client_reactor::*,
+    },
+};
+use futures_core::Stream;
+use std::{
+    collections::{HashMap, HashSet},
+    pin::Pin,
+    sync::{atomic::*, Arc},
+    time::Duration,
+};
+use tokio_stream::StreamExt;
+use tracing::*;
+
+/// Forks deeper than this are not switched to in the follow mode.
+const MAX_REORG_DEPTH: u64 = FINALITY_DEPTH;
+
+/// If the announced blocks are further than this from the local tip,
+/// the batch downloaders are better suited to catch up.
+const MAX_TIP_DISTANCE: u64 = HEADER_SLICE_SIZE as u64;
+
+/// Limits the memory taken by the headers which parents are not known yet.
+const MAX_ORPHANS_COUNT: usize = 1024;
+
+const FOLLOW_TIMEOUT: Duration = Duration::from_secs(15);
+
+type MessageStream = Pin<Box<dyn Stream<Item = MessageFromPeer> + Send>>;
+
+/// Top-of-chain mode: instead of downloading the skeleton slices,
+/// listen to the new block announcements, request only the missing headers near the tip,
+/// and switch to short forks if they have more difficulty.
+#[derive(Debug)]
+pub struct DownloaderFollow {
+    chain_config: ChainConfig,
+    verifier: Arc<Box<dyn HeaderSliceVerifier>>,
+    sentry: SentryClientReactorShared,
+    last_request_id: AtomicU64,
+}
+
+pub struct DownloaderFollowReport {
+    pub loaded_count: usize,
+    pub final_block_num: BlockNumber,
+    pub estimated_top_block_num: BlockNumber,
+    pub reorg_command: Option<FollowReorgCommand>,
+}
+
+impl DownloaderFollow {
+    pub fn new(
+        chain_config: ChainConfig,
+        verifier: Arc<Box<dyn HeaderSliceVerifier>>,
+        sentry: SentryClientReactorShared,
+    ) -> Self {
+        Self {
+            chain_config,
+            verifier,
+            sentry,
+            last_request_id: 0.into(),
+        }
+    }
+
+    /// Checks if the downloaded chain is close enough to the top to continue in the follow mode.
+    /// The initial sync always starts with the batch downloaders.
+    pub fn is_near_top(start_block_num: BlockNumber, estimated_top_block_num: BlockNumber) -> bool {
+        (start_block_num.0 > 0)
+            && (start_block_num.0 + MAX_TIP_DISTANCE >= estimated_top_block_num.0)
+    }
+
+    pub async fn run<'downloader, 'db: 'downloader, E: EnvironmentKind>(
+        &'downloader self,
+        db_transaction: &'downloader MdbxTransaction<'db, RW, E>,
+        start_block_num: BlockNumber,
+        estimated_top_block_num: BlockNumber,
+    ) -> anyhow::Result<DownloaderFollowReport> {
+        let mut chain = FollowChain::load(
+            self.chain_config.clone(),
+            self.verifier.clone(),
+            db_transaction,
+        )?;
+        chain.on_estimated_top_block_num(estimated_top_block_num);
+
+        let mut message_stream = {
+            let sentry = self.sentry.read().await;
+            Self::receive_messages(&sentry)?
+        };
+
+        let deadline = tokio::time::Instant::now() + FOLLOW_TIMEOUT;
+        while let Ok(Some(message_from_peer)) =
+            tokio::time::timeout_at(deadline, message_stream.next()).await
+        {
+            let peer_id = message_from_peer.from_peer_id;
+            match message_from_peer.message {
+                Message::NewBlockHashes(message) => {
+                    chain.on_announce(&message.ids, peer_id, db_transaction)?
+                }
+                Message::NewBlock(message) => {
+                    let header = BlockHeader::from(message.block.header);
+                    chain.on_headers(vec![header], peer_id, db_transaction)?
+                }
+                Message::BlockHeaders(message) => {
+                    let headers = message.headers.into_iter().map(BlockHeader::from);
+                    chain.on_headers(headers.collect(), peer_id, db_transaction)?
+                }
+                message => warn!(
+                    "DownloaderFollow: unexpected message {:?}",
+                    message.eth_id()
+                ),
+            }
+
+            self.send_requests(&mut chain).await?;
+
+            if chain.is_done() {
+                break;
+            }
+        }
+
+        let final_block_num = BlockNumber(chain.canonical_tip_num.0 + 1);
+        let report = DownloaderFollowReport {
+            loaded_count: final_block_num.0.saturating_sub(start_block_num.0) as usize,
+            final_block_num,
+            estimated_top_block_num: chain.top_announced_block_num,
+            reorg_command: chain.take_reorg_command(),
+        };
+
+        Ok(report)
+    }
+
+    fn receive_messages(sentry: &SentryClientReactor) -> anyhow::Result<MessageStream> {
+        let new_block_hashes = sentry.receive_messages(EthMessageId::NewBlockHashes)?;
+        let new_blocks = sentry.receive_messages(EthMessageId::NewBlock)?;
+        let block_headers = sentry.receive_messages(EthMessageId::BlockHeaders)?;
+        let stream = new_block_hashes.merge(new_blocks).merge(block_headers);
+        Ok(Box::pin(stream))
+    }
+
+    async fn send_requests(&self, chain: &mut FollowChain) -> anyhow::Result<()> {
+        let sentry = self.sentry.read().await;
+
+        for request in std::mem::take(&mut chain.requests) {
+            let message = GetBlockHeadersMessage {
+                request_id: self.last_request_id.fetch_add(1, Ordering::SeqCst),
+                params: GetBlockHeadersMessageParams {
+                    start_block: request.start_block,
+                    limit: request.limit,
+                    skip: 0,
+                    reverse: 1,
+                },
+            };
+            let peer_filter = request
+                .peer_id
+                .map_or(PeerFilter::Random(1), PeerFilter::PeerId);
+
+            let result = sentry.try_send_message(Message::GetBlockHeaders(message), peer_filter);
+            if let Err(error) = result {
+                match error.downcast_ref::<SendMessageError>() {
+                    Some(SendMessageError::SendQueueFull) => {
+                        debug!("DownloaderFollow: request send queue is full");
+                    }
+                    _ => return Err(error),
+                }
+            }
+        }
+
+        for peer_id in std::mem::take(&mut chain.bad_peers) {
+            sentry.penalize_peer(peer_id).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Requests `limit` headers going back from `start_block`.
+struct HeadersRequest {
+    start_block: BlockId,
+    limit: u64,
+    peer_id: Option<PeerId>,
+}
+
+/// Links the headers received near the tip to the saved chain.
+struct FollowChain {
+    chain_config: ChainConfig,
+    verifier: Arc<Box<dyn HeaderSliceVerifier>>,
+    tip: BlockHeader,
+    tip_total_difficulty: U256,
+    canonical_tip_num: BlockNumber,
+    top_announced_block_num: BlockNumber,
+    // orphan headers by their parent hash
+    orphans: HashMap<H256, Vec<BlockHeader>>,
+    orphans_count: usize,
+    requested: HashSet<H256>,
+    requests: Vec<HeadersRequest>,
+    bad_peers: Vec<PeerId>,
+    saved_count: usize,
+    reorg: Option<(BlockNumber, Vec<BlockHeader>)>,
+}
+
+impl FollowChain {
+    fn load<E: EnvironmentKind>(
+        chain_config: ChainConfig,
+        verifier: Arc<Box<dyn HeaderSliceVerifier>>,
+        tx: &MdbxTransaction<'_, RW, E>,
+    ) -> anyhow::Result<Self> {
+        let Some(tip_hash) = tx.get(tables::LastHeader, Default::default())? else {
+            anyhow::bail!("DownloaderFollow: the last header is not saved");
+        };
+        let Some(tip) = Self::load_header_by_hash(tip_hash, tx)? else {
+            anyhow::bail!("DownloaderFollow: the last header {:?} is not found", tip_hash);
+        };
+        let Some(tip_total_difficulty) =
+            tx.get(tables::HeadersTotalDifficulty, (tip.number(), tip_hash))? else {
+            anyhow::bail!("DownloaderFollow: total difficulty of the last header {:?} is not found", tip_hash);
+        };
+
+        Ok(Self {
+            chain_config,
+            verifier,
+            canonical_tip_num: tip.number(),
+            top_announced_block_num: tip.number(),
+            tip,
+            tip_total_difficulty,
+            orphans: HashMap::new(),
+            orphans_count: 0,
+            requested: HashSet::new(),
+            requests: Vec::new(),
+            bad_peers: Vec::new(),
+            saved_count: 0,
+            reorg: None,
+        })
+    }
+
+    fn load_header_by_hash<K: TransactionKind, E: EnvironmentKind>(
+        hash: H256,
+        tx: &MdbxTransaction<'_, K, E>,
+    ) -> anyhow::Result<Option<BlockHeader>> {
+        let Some(block_num) = tx.get(tables::HeaderNumber, hash)? else {
+            return Ok(None);
+        };
+        let header = tx.get(tables::Header, (block_num, hash))?;
+        Ok(header.map(|header| BlockHeader::new(header, hash)))
+    }
+
+    fn is_near_tip(&self, block_num: BlockNumber) -> bool {
+        let tip_num = self.tip.number().0;
+        (block_num.0 + MAX_REORG_DEPTH > tip_num) && (block_num.0 <= tip_num + MAX_TIP_DISTANCE)
+    }
+
+    fn on_estimated_top_block_num(&mut self, block_num: BlockNumber) {
+        self.top_announced_block_num = std::cmp::max(self.top_announced_block_num, block_num);
+    }
+
+    fn on_announce<E: EnvironmentKind>(
+        &mut self,
+        ids: &[BlockHashAndNumber],
+        peer_id: Option<PeerId>,
+        tx: &MdbxTransaction<'_, RW, E>,
+    ) -> anyhow::Result<()> {
+        for id in ids {
+            self.on_estimated_top_block_num(id.number);
+
+            if !self.is_near_tip(id.number) || self.requested.contains(&id.hash) {
+                continue;
+            }
+            if tx.get(tables::HeaderNumber, id.hash)?.is_some() {
+                continue;
+            }
+
+            // get the announced header together with the missing headers between it and the tip
+            let limit = id.number.0.saturating_sub(self.tip.number().0).max(1);
+            self.request(id.hash, limit, peer_id);
+        }
+        Ok(())
+    }
+
+    fn request(&mut self, hash: H256, limit: u64, peer_id: Option<PeerId>) {
+        self.requested.insert(hash);
+        self.requests.push(HeadersRequest {
+            start_block: BlockId::Hash(hash),
+            limit,
+            peer_id,
+        });
+    }
+
+    fn on_headers<E: EnvironmentKind>(
+        &mut self,
+        mut headers: Vec<BlockHeader>,
+        peer_id: Option<PeerId>,
+        tx: &MdbxTransaction<'_, RW, E>,
+    ) -> anyhow::Result<()> {
+        // link parents first, the responses come in the reverse order
+        headers.sort_by_key(|header| header.number());
+
+        for header in headers {
+            let mut pending = vec![header];
+            while let Some(header) = pending.pop() {
+                if let Some(hash) = self.link_header(header, peer_id, tx)? {
+                    if let Some(children) = self.orphans.remove(&hash) {
+                        self.orphans_count -= children.len();
+                        pending.extend(children);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves the header if its parent is known, and returns its hash in this case.
+    fn link_header<E: EnvironmentKind>(
+        &mut self,
+        header: BlockHeader,
+        peer_id: Option<PeerId>,
+        tx: &MdbxTransaction<'_, RW, E>,
+    ) -> anyhow::Result<Option<H256>> {
+        let hash = header.hash();
+        self.requested.remove(&hash);
+
+        if !self.is_near_tip(header.number()) || tx.get(tables::HeaderNumber, hash)?.is_some() {
+            return Ok(None);
+        }
+
+        let parent_hash = header.parent_hash();
+        let Some(parent) = Self::load_header_by_hash(parent_hash, tx)? else {
+            self.add_orphan(header, peer_id);
+            return Ok(None);
+        };
+
+        if let Err(error) =
+            self.verifier
+                .verify_link(&header, &parent, self.chain_config.chain_spec())
+        {
+            warn!(
+                "DownloaderFollow: invalid header {} {:?}: {}",
+                header.number().0,
+                hash,
+                error
+            );
+            self.bad_peers.extend(peer_id);
+            return Ok(None);
+        }
+
+        let Some(parent_total_difficulty) =
+            tx.get(tables::HeadersTotalDifficulty, (parent.number(), parent_hash))? else {
+            warn!("DownloaderFollow: total difficulty of {:?} is not found", parent_hash);
+            return Ok(None);
+        };
+        let total_difficulty = parent_total_difficulty + header.difficulty();
+
+        SaveStage::save_header(header.clone(), false, tx)?;
+        tx.set(
+            tables::HeadersTotalDifficulty,
+            (header.number(), hash),
+            total_difficulty,
+        )?;
+        self.saved_count += 1;
+
+        if total_difficulty > self.tip_total_difficulty {
+            self.switch_tip(header, total_difficulty, tx)?;
+        }
+
+        Ok(Some(hash))
+    }
+
+    fn add_orphan(&mut self, header: BlockHeader, peer_id: Option<PeerId>) {
+        if self.orphans_count >= MAX_ORPHANS_COUNT {
+            debug!("DownloaderFollow: too many orphan headers");
+            return;
+        }
+
+        let parent_hash = header.parent_hash();
+        if !self.requested.contains(&parent_hash) {
+            let limit = header
+                .number()
+                .0
+                .saturating_sub(self.tip.number().0 + 1)
+                .clamp(1, MAX_TIP_DISTANCE);
+            self.request(parent_hash, limit, peer_id);
+        }
+
+        self.orphans.entry(parent_hash).or_default().push(header);
+        self.orphans_count += 1;
+    }
+
+    fn switch_tip<E: EnvironmentKind>(
+        &mut self,
+        header: BlockHeader,
+        total_difficulty: U256,
+        tx: &MdbxTransaction<'_, RW, E>,
+    ) -> anyhow::Result<()> {
+        let extends_tip = header.parent_hash() == self.tip.hash();
+
+        if let Some((_, reorg_headers)) = &mut self.reorg {
+            if !extends_tip {
+                debug!("DownloaderFollow: ignoring a fork of a pending reorg");
+                return Ok(());
+            }
+            reorg_headers.push(header.clone());
+        } else if extends_tip {
+            SaveStage::update_canonical_chain_header(&header, tx)?;
+            self.canonical_tip_num = header.number();
+        } else {
+            let Some(reorg) = Self::find_fork(&header, tx)? else {
+                warn!(
+                    "DownloaderFollow: fork at {} {:?} is too deep to switch",
+                    header.number().0,
+                    header.hash()
+                );
+                return Ok(());
+            };
+            info!(
+                "DownloaderFollow: switching to a fork at {} connected at {}",
+                header.number().0,
+                reorg.0 .0
+            );
+            self.reorg = Some(reorg);
+        }
+
+        self.tip = header;
+        self.tip_total_difficulty = total_difficulty;
+        Ok(())
+    }
+
+    /// Finds the canonical block the fork of `header` is connected to,
+    /// and returns it together with the fork headers.
+    fn find_fork<E: EnvironmentKind>(
+        header: &BlockHeader,
+        tx: &MdbxTransaction<'_, RW, E>,
+    ) -> anyhow::Result<Option<(BlockNumber, Vec<BlockHeader>)>> {
+        let mut fork = vec![header.clone()];
+        let mut parent_hash = header.parent_hash();
+        loop {
+            let Some(parent) = Self::load_header_by_hash(parent_hash, tx)? else {
+                return Ok(None);
+            };
+            if tx.get(tables::CanonicalHeader, parent.number())? == Some(parent_hash) {
+                fork.reverse();
+                return Ok(Some((parent.number(), fork)));
+            }
+            if fork.len() as u64 >= MAX_REORG_DEPTH {
+                return Ok(None);
+            }
+            parent_hash = parent.parent_hash();
+            fork.push(parent);
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        let is_far_behind =
+            self.top_announced_block_num.0 > self.canonical_tip_num.0 + MAX_TIP_DISTANCE;
+        let has_settled = (self.saved_count > 0) && self.requested.is_empty();
+        self.reorg.is_some() || is_far_behind || has_settled
+    }
+
+    fn take_reorg_command(&mut self) -> Option<FollowReorgCommand> {
+        self.reorg.take().map(|(connection_block_num, headers)| {
+            FollowReorgCommand::new(connection_block_num, headers)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::verification::header_slice_verifier_mock::HeaderSliceVerifierMock, *};
+    use crate::{kv::new_mem_database, sentry::chain_config::ChainsConfig};
+    use bytes::Bytes;
+
+    fn make_header(parent: &BlockHeader, difficulty: u64, fork_id: u8) -> BlockHeader {
+        let mut header = crate::models::BlockHeader::empty();
+        header.number = BlockNumber(parent.number().0 + 1);
+        header.parent_hash = parent.hash();
+        header.difficulty = U256::from(difficulty);
+        header.extra_data = Bytes::from(vec![fork_id]);
+        BlockHeader::from(header)
+    }
+
+    fn make_chain<E: EnvironmentKind>(tx: &MdbxTransaction<'_, RW, E>) -> FollowChain {
+        let chain_config = ChainsConfig::new().unwrap().get("mainnet").unwrap();
+        let verifier: Arc<Box<dyn HeaderSliceVerifier>> =
+            Arc::new(Box::new(HeaderSliceVerifierMock::new(|header| {
+                header.number().0
+            })));
+        FollowChain::load(chain_config, verifier, tx).unwrap()
+    }
+
+    #[test]
+    fn extend_and_reorg() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let genesis = BlockHeader::from(crate::models::BlockHeader::empty());
+        SaveStage::save_header(genesis.clone(), true, &tx).unwrap();
+
+        let mut chain = make_chain(&tx);
+
+        // an orphan is kept until its parent arrives
+        let block1 = make_header(&genesis, 1, 0);
+        let block2 = make_header(&block1, 1, 0);
+        chain.on_headers(vec![block2.clone()], None, &tx).unwrap();
+        assert_eq!(chain.canonical_tip_num, BlockNumber(0));
+        assert!(chain.requested.contains(&block1.hash()));
+
+        chain.on_headers(vec![block1.clone()], None, &tx).unwrap();
+        assert_eq!(chain.canonical_tip_num, BlockNumber(2));
+        assert!(chain.requested.is_empty());
+        assert!(chain.is_done());
+
+        // a lighter fork doesn't change the tip
+        let fork1 = make_header(&genesis, 1, 1);
+        chain.on_headers(vec![fork1.clone()], None, &tx).unwrap();
+        assert!(chain.reorg.is_none());
+
+        // a heavier fork causes a reorg
+        let fork2 = make_header(&fork1, 5, 1);
+        chain.on_headers(vec![fork2.clone()], None, &tx).unwrap();
+        let command = chain.take_reorg_command().unwrap();
+        assert_eq!(command.connection_block_num(), BlockNumber(0));
+
+        SaveStage::unwind(BlockNumber(0), &tx).unwrap();
+        command.execute(&tx).unwrap();
+        assert_eq!(
+            tx.get(tables::CanonicalHeader, BlockNumber(2)).unwrap(),
+            Some(fork2.hash())
+        );
+        assert_eq!(
+            tx.get(tables::LastHeader, Default::default()).unwrap(),
+            Some(fork2.hash())
+        );
+    }
+}
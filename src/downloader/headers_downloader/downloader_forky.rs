@@ -8,6 +8,8 @@ use super::{
         header_slices::{
             is_block_num_aligned_to_slice_start, HeaderSlice, HeaderSliceStatus, HeaderSlices,
         },
+        peer_rotation::PeerRotation,
+        request_batching::RequestBatching,
     },
     headers_ui::HeaderSlicesView,
     stages::{fork_switch_command::ForkSwitchCommand, *},
@@ -15,6 +17,7 @@ use super::{
     verification::header_slice_verifier::HeaderSliceVerifier,
 };
 use crate::{
+    downloader::opts::RequestBatchingOpts,
     kv,
     kv::{mdbx::MdbxTransaction, tables::HeaderKey},
     models::BlockNumber,
@@ -27,6 +30,7 @@ pub struct DownloaderForky {
     chain_config: ChainConfig,
     verifier: Arc<Box<dyn HeaderSliceVerifier>>,
     sentry: SentryClientReactorShared,
+    request_batching_opts: RequestBatchingOpts,
 }
 
 pub struct DownloaderForkyReport {
@@ -42,11 +46,13 @@ impl DownloaderForky {
         chain_config: ChainConfig,
         verifier: Arc<Box<dyn HeaderSliceVerifier>>,
         sentry: SentryClientReactorShared,
+        request_batching_opts: RequestBatchingOpts,
     ) -> Self {
         Self {
             chain_config,
             verifier,
             sentry,
+            request_batching_opts,
         }
     }
 
@@ -141,19 +147,35 @@ impl DownloaderForky {
     ) {
         let sentry = self.sentry.clone();
 
+        let peer_rotation = Arc::new(PeerRotation::new());
+        let request_batching = Arc::new(RequestBatching::new(
+            self.request_batching_opts,
+            header_slices::HEADER_SLICE_SIZE,
+        ));
         let fetch_request_stage = FetchRequestStage::new(
             header_slices.clone(),
             sentry.clone(),
-            header_slices::HEADER_SLICE_SIZE,
+            peer_rotation.clone(),
+            request_batching.clone(),
+        );
+        let fetch_receive_stage = FetchReceiveStage::new(
+            header_slices.clone(),
+            sentry.clone(),
+            peer_rotation.clone(),
+            request_batching.clone(),
+        );
+        let retry_stage = RetryStage::new(
+            header_slices.clone(),
+            sentry.clone(),
+            peer_rotation.clone(),
+            request_batching,
         );
-        let fetch_receive_stage = FetchReceiveStage::new(header_slices.clone(), sentry.clone());
-        let retry_stage = RetryStage::new(header_slices.clone());
         let verify_slices_stage = VerifySlicesStage::new(
             header_slices.clone(),
             self.chain_config.clone(),
             self.verifier.clone(),
         );
-        let penalize_stage = PenalizeStage::new(header_slices.clone(), sentry);
+        let penalize_stage = PenalizeStage::new(header_slices.clone(), sentry, peer_rotation);
 
         stages.insert_with_group_name(fetch_request_stage, group_name);
         stages.insert_with_group_name(fetch_receive_stage, group_name);
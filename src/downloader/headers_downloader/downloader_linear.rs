@@ -1,11 +1,14 @@
 use super::{
     downloader_stage_loop::DownloaderStageLoop,
     headers::{
+        header_slice_store::HeaderSliceStore,
         header_slices,
         header_slices::{
             align_block_num_to_slice_start, is_block_num_aligned_to_slice_start, HeaderSliceStatus,
             HeaderSlices,
         },
+        peer_rotation::PeerRotation,
+        request_batching::RequestBatching,
     },
     headers_ui::HeaderSlicesView,
     stages::*,
@@ -13,6 +16,7 @@ use super::{
     verification::header_slice_verifier::HeaderSliceVerifier,
 };
 use crate::{
+    downloader::opts::RequestBatchingOpts,
     kv::mdbx::MdbxTransaction,
     models::BlockNumber,
     sentry::{chain_config::ChainConfig, sentry_client_reactor::*},
@@ -27,6 +31,8 @@ pub struct DownloaderLinear {
     verifier: Arc<Box<dyn HeaderSliceVerifier>>,
     mem_limit: usize,
     sentry: SentryClientReactorShared,
+    request_batching_opts: RequestBatchingOpts,
+    slice_store: Option<Arc<HeaderSliceStore>>,
 }
 
 pub struct DownloaderLinearReport {
@@ -42,15 +48,22 @@ impl DownloaderLinear {
         verifier: Arc<Box<dyn HeaderSliceVerifier>>,
         mem_limit: usize,
         sentry: SentryClientReactorShared,
+        request_batching_opts: RequestBatchingOpts,
     ) -> Self {
         Self {
             chain_config,
             verifier,
             mem_limit,
             sentry,
+            request_batching_opts,
+            slice_store: None,
         }
     }
 
+    pub fn set_slice_store(&mut self, slice_store: Arc<HeaderSliceStore>) {
+        self.slice_store = Some(slice_store);
+    }
+
     async fn estimate_top_block_num(
         &self,
         start_block_num: BlockNumber,
@@ -131,19 +144,46 @@ impl DownloaderLinear {
             start_block_num,
             final_block_num,
         ));
+        if let Some(slice_store) = &self.slice_store {
+            // the internal verification holds, the link to the chain is checked again
+            let restored_count =
+                header_slices.restore(slice_store, HeaderSliceStatus::VerifiedInternally)?;
+            if restored_count > 0 {
+                debug!(
+                    "DownloaderLinear: restored {} stored slices",
+                    restored_count
+                );
+            }
+        }
         let sentry = self.sentry.clone();
 
         let header_slices_view = HeaderSlicesView::new(header_slices.clone(), "DownloaderLinear");
         let _header_slices_view_scope =
             UISystemViewScope::new(&ui_system, Box::new(header_slices_view));
 
+        let peer_rotation = Arc::new(PeerRotation::new());
+        let request_batching = Arc::new(RequestBatching::new(
+            self.request_batching_opts,
+            header_slices::HEADER_SLICE_SIZE,
+        ));
         let fetch_request_stage = FetchRequestStage::new(
             header_slices.clone(),
             sentry.clone(),
-            header_slices::HEADER_SLICE_SIZE,
+            peer_rotation.clone(),
+            request_batching.clone(),
+        );
+        let fetch_receive_stage = FetchReceiveStage::new(
+            header_slices.clone(),
+            sentry.clone(),
+            peer_rotation.clone(),
+            request_batching.clone(),
+        );
+        let retry_stage = RetryStage::new(
+            header_slices.clone(),
+            sentry.clone(),
+            peer_rotation.clone(),
+            request_batching,
         );
-        let fetch_receive_stage = FetchReceiveStage::new(header_slices.clone(), sentry.clone());
-        let retry_stage = RetryStage::new(header_slices.clone());
         let verify_slices_stage = VerifySlicesStage::new(
             header_slices.clone(),
             self.chain_config.clone(),
@@ -156,13 +196,17 @@ impl DownloaderLinear {
             start_block_parent_header,
             HeaderSliceStatus::Invalid,
         );
-        let penalize_stage = PenalizeStage::new(header_slices.clone(), sentry.clone());
-        let save_stage = SaveStage::new(
+        let penalize_stage =
+            PenalizeStage::new(header_slices.clone(), sentry.clone(), peer_rotation);
+        let mut save_stage = SaveStage::new(
             header_slices.clone(),
             db_transaction,
             save_stage::SaveOrder::Monotonic,
             true,
         );
+        if let Some(slice_store) = &self.slice_store {
+            save_stage.set_slice_store(slice_store.clone());
+        }
         let refill_stage = RefillStage::new(header_slices.clone());
 
         let refill_stage_is_over = refill_stage.is_over_check();
@@ -179,6 +223,10 @@ impl DownloaderLinear {
 
         stages.run(refill_stage_is_over).await;
 
+        if let Some(slice_store) = &self.slice_store {
+            header_slices.persist(slice_store)?;
+        }
+
         let report = DownloaderLinearReport {
             loaded_count: (header_slices.min_block_num().0 - start_block_num.0) as usize,
             final_block_num: header_slices.min_block_num(),
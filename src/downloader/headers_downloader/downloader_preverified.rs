@@ -3,22 +3,31 @@ use mdbx::{EnvironmentKind, RW};
 use super::{
     downloader_stage_loop::DownloaderStageLoop,
     headers::{
+        header_slice_store::HeaderSliceStore,
         header_slices,
-        header_slices::{align_block_num_to_slice_start, HeaderSlices},
+        header_slices::{align_block_num_to_slice_start, HeaderSliceStatus, HeaderSlices},
+        peer_rotation::PeerRotation,
+        request_batching::RequestBatching,
     },
     headers_ui::HeaderSlicesView,
     stages::*,
     ui::ui_system::{UISystemShared, UISystemViewScope},
     verification::preverified_hashes_config::PreverifiedHashesConfig,
 };
-use crate::{kv::mdbx::MdbxTransaction, models::BlockNumber, sentry::sentry_client_reactor::*};
+use crate::{
+    downloader::opts::RequestBatchingOpts, kv::mdbx::MdbxTransaction, models::BlockNumber,
+    sentry::sentry_client_reactor::*,
+};
 use std::sync::Arc;
+use tracing::*;
 
 #[derive(Debug)]
 pub struct DownloaderPreverified {
     preverified_hashes_config: PreverifiedHashesConfig,
     mem_limit: usize,
     sentry: SentryClientReactorShared,
+    request_batching_opts: RequestBatchingOpts,
+    slice_store: Option<Arc<HeaderSliceStore>>,
 }
 
 pub struct DownloaderPreverifiedReport {
@@ -33,14 +42,21 @@ impl DownloaderPreverified {
         preverified_hashes_config: PreverifiedHashesConfig,
         mem_limit: usize,
         sentry: SentryClientReactorShared,
+        request_batching_opts: RequestBatchingOpts,
     ) -> Self {
         Self {
             preverified_hashes_config,
             mem_limit,
             sentry,
+            request_batching_opts,
+            slice_store: None,
         }
     }
 
+    pub fn set_slice_store(&mut self, slice_store: Arc<HeaderSliceStore>) {
+        self.slice_store = Some(slice_store);
+    }
+
     fn target_final_block_num(&self) -> BlockNumber {
         if self.preverified_hashes_config.is_empty() {
             return BlockNumber(0);
@@ -80,6 +96,17 @@ impl DownloaderPreverified {
             start_block_num,
             final_block_num,
         ));
+        if let Some(slice_store) = &self.slice_store {
+            // their hashes are checked again, which is as cheap as trusting the stored status
+            let restored_count =
+                header_slices.restore(slice_store, HeaderSliceStatus::Downloaded)?;
+            if restored_count > 0 {
+                debug!(
+                    "DownloaderPreverified: restored {} stored slices",
+                    restored_count
+                );
+            }
+        }
         let sentry = self.sentry.clone();
 
         let header_slices_view =
@@ -87,24 +114,44 @@ impl DownloaderPreverified {
         let _header_slices_view_scope =
             UISystemViewScope::new(&ui_system, Box::new(header_slices_view));
 
+        let peer_rotation = Arc::new(PeerRotation::new());
+        let request_batching = Arc::new(RequestBatching::new(
+            self.request_batching_opts,
+            header_slices::HEADER_SLICE_SIZE + 1,
+        ));
         let fetch_request_stage = FetchRequestStage::new(
             header_slices.clone(),
             sentry.clone(),
-            header_slices::HEADER_SLICE_SIZE + 1,
+            peer_rotation.clone(),
+            request_batching.clone(),
+        );
+        let fetch_receive_stage = FetchReceiveStage::new(
+            header_slices.clone(),
+            sentry.clone(),
+            peer_rotation.clone(),
+            request_batching.clone(),
+        );
+        let retry_stage = RetryStage::new(
+            header_slices.clone(),
+            sentry.clone(),
+            peer_rotation.clone(),
+            request_batching,
         );
-        let fetch_receive_stage = FetchReceiveStage::new(header_slices.clone(), sentry.clone());
-        let retry_stage = RetryStage::new(header_slices.clone());
         let verify_stage = VerifyPreverifiedStage::new(
             header_slices.clone(),
             self.preverified_hashes_config.clone(),
         );
-        let penalize_stage = PenalizeStage::new(header_slices.clone(), sentry.clone());
-        let save_stage = SaveStage::new(
+        let penalize_stage =
+            PenalizeStage::new(header_slices.clone(), sentry.clone(), peer_rotation);
+        let mut save_stage = SaveStage::new(
             header_slices.clone(),
             db_transaction,
             save_stage::SaveOrder::Monotonic,
             true,
         );
+        if let Some(slice_store) = &self.slice_store {
+            save_stage.set_slice_store(slice_store.clone());
+        }
         let refill_stage = RefillStage::new(header_slices.clone());
         let top_block_estimate_stage = TopBlockEstimateStage::new(sentry.clone());
 
@@ -125,6 +172,10 @@ impl DownloaderPreverified {
 
         stages.run(refill_stage_is_over).await;
 
+        if let Some(slice_store) = &self.slice_store {
+            header_slices.persist(slice_store)?;
+        }
+
         let report = DownloaderPreverifiedReport {
             loaded_count: (header_slices.min_block_num().0 - start_block_num.0) as usize,
             final_block_num: header_slices.min_block_num(),
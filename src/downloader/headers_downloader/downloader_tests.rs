@@ -10,16 +10,22 @@ use super::{
     verification::header_slice_verifier_mock::HeaderSliceVerifierMock,
 };
 use crate::{
+    downloader::opts::RequestBatchingOpts,
     kv,
+    kv::tables,
     models::*,
     sentry::{
-        chain_config, sentry_client_connector,
+        chain_config,
+        sentry_client::SentryClient,
+        sentry_client_connector,
         sentry_client_connector::SentryClientConnectorTest,
         sentry_client_mock::SentryClientMock,
         sentry_client_reactor::{SentryClientReactor, SentryClientReactorShared},
+        sentry_client_simulated::{PeerBehavior, SentryClientSimulated},
     },
 };
 use bytes::{Buf, BufMut, BytesMut};
+use proptest::prelude::*;
 use std::{
     mem::size_of,
     sync::{Arc, Once},
@@ -33,10 +39,10 @@ fn make_chain_config() -> chain_config::ChainConfig {
 }
 
 fn make_sentry_reactor(
-    sentry: SentryClientMock,
+    sentry: Box<dyn SentryClient>,
     current_status_stream: sentry_client_connector::StatusStream,
 ) -> SentryClientReactorShared {
-    let sentry_connector = Box::new(SentryClientConnectorTest::new(Box::new(sentry)));
+    let sentry_connector = Box::new(SentryClientConnectorTest::new(sentry));
     let sentry_reactor = SentryClientReactor::new(sentry_connector, current_status_stream);
     sentry_reactor.into_shared()
 }
@@ -45,12 +51,12 @@ async fn run_downloader(
     downloader: Downloader,
     sentry: SentryClientReactorShared,
     previous_run_state: Option<DownloaderRunState>,
+    db: &kv::MdbxWithDirHandle,
 ) -> anyhow::Result<DownloaderReport> {
     {
         sentry.write().await.start()?;
     }
 
-    let db = kv::new_mem_database()?;
     let db_transaction = db.begin_mutable()?;
 
     let ui_system = Arc::new(AsyncMutex::new(UISystem::new()));
@@ -101,7 +107,7 @@ struct DownloaderTest {
 impl DownloaderTest {
     pub fn new(
         chain_config: chain_config::ChainConfig,
-        sentry: SentryClientMock,
+        sentry: Box<dyn SentryClient>,
         verifier: HeaderSliceVerifierMock,
         previous_run_state: Option<DownloaderRunState>,
         expected_report: Option<DownloaderReport>,
@@ -114,6 +120,7 @@ impl DownloaderTest {
             chain_config,
             Box::new(verifier),
             byte_unit::n_mib_bytes!(50) as usize,
+            RequestBatchingOpts::default(),
             sentry_reactor.clone(),
         )?;
 
@@ -195,10 +202,12 @@ impl DownloaderTest {
     }
 
     pub async fn run(self) -> anyhow::Result<()> {
+        let db = kv::new_mem_database()?;
         let report = run_downloader(
             self.downloader,
             self.sentry_reactor,
             self.previous_run_state,
+            &db,
         )
         .await?;
 
@@ -215,7 +224,7 @@ async fn noop() {
     let chain_config = make_chain_config();
     let sentry = SentryClientMock::new();
     let verifier = HeaderSliceVerifierMock::new(HeaderGenerator::header_id);
-    let test = DownloaderTest::new(chain_config, sentry, verifier, None, None).unwrap();
+    let test = DownloaderTest::new(chain_config, Box::new(sentry), verifier, None, None).unwrap();
     test.run().await.unwrap();
 }
 
@@ -264,7 +273,7 @@ impl<'t> DownloaderTestDecl<'t> {
 
         DownloaderTest::new(
             chain_config,
-            sentry,
+            Box::new(sentry),
             verifier,
             Some(previous_run_state),
             Some(expected_report),
@@ -671,3 +680,124 @@ async fn fork_connect_and_discard() {
     };
     test.run().await.unwrap();
 }
+
+/// Offset of the header IDs of the competing branch served by PeerBehavior::ReorgSpam peers,
+/// so that it never links to the canonical chain.
+const FORK_ID_OFFSET: u64 = 1_000_000;
+
+/// Runs the downloader against simulated peers serving a chain of `slices_count` slices
+/// after the known genesis slice, and checks that the whole chain is saved as canonical.
+async fn converge_with_peers(
+    behaviors: Vec<PeerBehavior>,
+    slices_count: usize,
+) -> anyhow::Result<()> {
+    let chain_config = make_chain_config();
+    let mut generator = HeaderGenerator::new(chain_config.clone());
+
+    let genesis_slice = HeaderSlice {
+        start_block_num: BlockNumber(0),
+        status: HeaderSliceStatus::Saved,
+        headers: Some(generator.generate_slice_headers(BlockNumber(0))),
+        ..Default::default()
+    };
+
+    let mut chain = Vec::<crate::models::BlockHeader>::new();
+    let mut fork = Vec::<crate::models::BlockHeader>::new();
+    for i in 1..=slices_count {
+        let start_block_num = BlockNumber((i * header_slices::HEADER_SLICE_SIZE) as u64);
+        let headers = generator.generate_slice_headers(start_block_num);
+        chain.extend(headers.into_iter().map(|header| header.header));
+
+        let mut fork_headers = generator.generate_slice_headers(start_block_num);
+        let fork_start_id = start_block_num.0 + FORK_ID_OFFSET;
+        generator.mark_slice_headers_ids(
+            fork_start_id,
+            fork_start_id + header_slices::HEADER_SLICE_SIZE as u64,
+            fork_headers.as_mut_slice(),
+        );
+        fork.extend(fork_headers.into_iter().map(|header| header.header));
+    }
+
+    let sentry = SentryClientSimulated::new(chain.clone(), fork, &behaviors);
+    let verifier = HeaderSliceVerifierMock::new(HeaderGenerator::header_id);
+    let previous_run_state = DownloaderRunState {
+        estimated_top_block_num: Some(BlockNumber(10_000)),
+        forky_header_slices: Some(Arc::new(HeaderSlices::from_slices_vec(
+            vec![genesis_slice],
+            None,
+            Some(slices_count + 1),
+            None,
+        ))),
+        forky_fork_header_slices: None,
+        unwind_request: None,
+    };
+    let test = DownloaderTest::new(
+        chain_config,
+        Box::new(sentry),
+        verifier,
+        Some(previous_run_state),
+        None,
+    )?;
+
+    let db = kv::new_mem_database()?;
+    run_downloader(
+        test.downloader,
+        test.sentry_reactor,
+        test.previous_run_state,
+        &db,
+    )
+    .await?;
+
+    let tx = db.begin()?;
+    for header in &chain {
+        let hash = tx.get(tables::CanonicalHeader, header.number)?;
+        anyhow::ensure!(
+            hash == Some(header.hash()),
+            "block {} is not canonical: {:?}",
+            header.number.0,
+            hash
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn converge_with_wrong_headers() {
+    let behaviors = vec![PeerBehavior::WrongHeaders, PeerBehavior::Honest];
+    converge_with_peers(behaviors, 3).await.unwrap();
+}
+
+#[tokio::test]
+async fn converge_with_reorg_spam() {
+    let behaviors = vec![PeerBehavior::ReorgSpam, PeerBehavior::Honest];
+    converge_with_peers(behaviors, 3).await.unwrap();
+}
+
+fn peer_behavior() -> impl Strategy<Value = PeerBehavior> {
+    prop_oneof![
+        Just(PeerBehavior::Honest),
+        Just(PeerBehavior::WrongHeaders),
+        Just(PeerBehavior::Stalling),
+        Just(PeerBehavior::Duplicates),
+        Just(PeerBehavior::ReorgSpam),
+    ]
+}
+
+proptest! {
+    // every case runs the downloader, and waits for retries if some peer is stalling
+    #![proptest_config(ProptestConfig::with_cases(8))]
+
+    #[test]
+    fn converges_with_adversarial_peers(
+        behaviors in prop::collection::vec(peer_behavior(), 0..4),
+        honest_index in 0..4_usize,
+        slices_count in 1..6_usize,
+    ) {
+        // at least one peer serves the canonical chain
+        let mut behaviors = behaviors;
+        behaviors.insert(honest_index.min(behaviors.len()), PeerBehavior::Honest);
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(converge_with_peers(behaviors, slices_count)).unwrap();
+    }
+}
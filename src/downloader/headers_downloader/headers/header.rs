@@ -4,6 +4,9 @@ use crate::{
 };
 use bytes::Bytes;
 
+/// RLP length of all header fields except extra_data.
+const RLP_FIXED_FIELDS_MAX_LEN: usize = 600;
+
 #[derive(Clone, Debug)]
 pub struct BlockHeader {
     pub header: BaseBlockHeader,
@@ -69,6 +72,11 @@ impl BlockHeader {
             .unwrap_or_else(|| Self::hash_compute(&self.rlp_repr()))
     }
 
+    /// Upper bound of the memory taken by the header once its RLP representation is cached.
+    pub fn mem_size(&self) -> usize {
+        std::mem::size_of::<Self>() + RLP_FIXED_FIELDS_MAX_LEN + 2 * self.header.extra_data.len()
+    }
+
     #[cfg(test)]
     pub fn set_hash_cached(&mut self, value: Option<H256>) {
         self.hash_cached = value;
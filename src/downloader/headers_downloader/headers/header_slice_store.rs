@@ -0,0 +1,120 @@
+use super::{header::BlockHeader, header_slices::HeaderSliceStatus};
+use crate::models::{BlockHeader as BaseBlockHeader, BlockNumber};
+use anyhow::{format_err, Context};
+use rlp::{Rlp, RlpStream};
+use std::{fs, io::Write, path::PathBuf};
+
+/// Header slices kept on disk between downloader runs, one file per slice.
+///
+/// Each slice is written to a temporary file which is then renamed, so a slice is durable
+/// as soon as it is stored, independently of the stage transaction committed at the end of a run.
+#[derive(Debug)]
+pub struct HeaderSliceStore {
+    dir: PathBuf,
+}
+
+impl HeaderSliceStore {
+    pub fn new(dir: PathBuf) -> anyhow::Result<Self> {
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create directory {}", dir.display()))?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, start_block_num: BlockNumber) -> PathBuf {
+        self.dir.join(format!("{:012}.rlp", start_block_num.0))
+    }
+
+    pub fn save(
+        &self,
+        start_block_num: BlockNumber,
+        status: HeaderSliceStatus,
+        headers: &[BlockHeader],
+    ) -> anyhow::Result<()> {
+        let mut stream = RlpStream::new_list(2);
+        stream.append(&(char::from(status) as u8));
+        stream.begin_list(headers.len());
+        for header in headers {
+            stream.append(&header.header);
+        }
+
+        let path = self.path(start_block_num);
+        let tmp_path = path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(&stream.out())?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    pub fn load(
+        &self,
+        start_block_num: BlockNumber,
+    ) -> anyhow::Result<Option<(HeaderSliceStatus, Vec<BlockHeader>)>> {
+        let path = self.path(start_block_num);
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+
+        let rlp = Rlp::new(&data);
+        let status_code = rlp.val_at::<u8>(0)?;
+        let status = HeaderSliceStatus::try_from(char::from(status_code))?;
+        let headers = rlp
+            .list_at::<BaseBlockHeader>(1)?
+            .into_iter()
+            .map(BlockHeader::from)
+            .collect::<Vec<_>>();
+        if headers.first().map(BlockHeader::number) != Some(start_block_num) {
+            return Err(format_err!(
+                "stored header slice {} does not start at its block",
+                path.display()
+            ));
+        }
+        Ok(Some((status, headers)))
+    }
+
+    /// Stored slices and the block they start at.
+    fn slices(&self) -> anyhow::Result<Vec<(BlockNumber, PathBuf)>> {
+        let mut slices = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let start_block_num = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<u64>().ok());
+            if let Some(start) = start_block_num {
+                slices.push((BlockNumber(start), path));
+            }
+        }
+        Ok(slices)
+    }
+
+    /// Deletes the slices starting before `block_num`, which are committed to the database already.
+    pub fn remove_below(&self, block_num: BlockNumber) -> anyhow::Result<usize> {
+        let mut count = 0;
+        for (start_block_num, path) in self.slices()? {
+            if start_block_num < block_num {
+                fs::remove_file(path)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Deletes the slices holding headers past `block_num`, which an unwind discarded.
+    pub fn remove_above(&self, block_num: BlockNumber) -> anyhow::Result<usize> {
+        let mut count = 0;
+        for (start_block_num, path) in self.slices()? {
+            let discarded = start_block_num > block_num
+                || self.load(start_block_num)?.map_or(false, |(_, headers)| {
+                    headers.last().map(BlockHeader::number) > Some(block_num)
+                });
+            if discarded {
+                fs::remove_file(path)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
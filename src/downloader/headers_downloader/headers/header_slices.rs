@@ -1,8 +1,9 @@
-use super::header::BlockHeader;
-use crate::{models::BlockNumber, sentry::sentry_client::PeerId};
+use super::{header::BlockHeader, header_slice_store::HeaderSliceStore};
+use crate::{consensus::ValidationError, models::BlockNumber, sentry::sentry_client::PeerId};
 use parking_lot::RwLock;
 use std::{
     collections::{HashMap, VecDeque},
+    ops::DerefMut,
     sync::{
         atomic::{AtomicU64, AtomicUsize, Ordering},
         Arc,
@@ -38,9 +39,12 @@ pub struct HeaderSlice {
     pub status: HeaderSliceStatus,
     pub headers: Option<Vec<BlockHeader>>,
     pub from_peer_id: Option<PeerId>,
+    pub to_peer_id: Option<PeerId>,
     pub request_time: Option<time::Instant>,
     pub request_attempt: u16,
     pub refetch_attempt: u16,
+    /// Why the slice failed verification, if known.
+    pub invalid_reason: Option<ValidationError>,
 }
 
 struct HeaderSliceStatusWatch {
@@ -56,9 +60,15 @@ struct HeaderSliceStatusWatch {
 /// HeaderSlice 0: headers 0-192
 /// HeaderSlice 1: headers 192-384
 /// HeaderSlice 2: headers 384-576
+///
+/// Besides max_slices, the memory taken by the headers is accounted against mem_limit,
+/// since extra_data size varies a lot between chains.
 pub struct HeaderSlices {
     slices: RwLock<VecDeque<Arc<RwLock<HeaderSlice>>>>,
     max_slices: usize,
+    mem_limit: usize,
+    mem_usage: AtomicUsize,
+    headers_count: AtomicUsize,
     max_block_num: AtomicU64,
     final_block_num: BlockNumber,
     state_watches: HashMap<HeaderSliceStatus, HeaderSliceStatusWatch>,
@@ -80,12 +90,15 @@ impl HeaderSlices {
             (total_block_num + HEADER_SLICE_SIZE - 1) / HEADER_SLICE_SIZE,
         );
 
-        Self::from_slices_vec(
-            Vec::new(),
-            Some(start_block_num),
-            Some(max_slices),
-            Some(final_block_num),
-        )
+        Self {
+            mem_limit,
+            ..Self::from_slices_vec(
+                Vec::new(),
+                Some(start_block_num),
+                Some(max_slices),
+                Some(final_block_num),
+            )
+        }
     }
 
     #[allow(clippy::needless_range_loop)]
@@ -123,6 +136,8 @@ impl HeaderSlices {
         );
 
         let state_watches = Self::make_state_watches_from_slices(&slices);
+        let mem_usage = slices.iter().map(slice_mem_size).sum();
+        let headers_count = slices.iter().map(HeaderSlice::len).sum();
 
         let slice_locks =
             VecDeque::from_iter(slices.into_iter().map(|slice| Arc::new(RwLock::new(slice))));
@@ -130,6 +145,9 @@ impl HeaderSlices {
         Self {
             slices: RwLock::new(slice_locks),
             max_slices,
+            mem_limit: usize::MAX,
+            mem_usage: AtomicUsize::new(mem_usage),
+            headers_count: AtomicUsize::new(headers_count),
             max_block_num: AtomicU64::new(max_block_num),
             final_block_num,
             state_watches,
@@ -140,6 +158,9 @@ impl HeaderSlices {
         Self {
             slices: RwLock::new(VecDeque::new()),
             max_slices,
+            mem_limit: usize::MAX,
+            mem_usage: AtomicUsize::new(0),
+            headers_count: AtomicUsize::new(0),
             max_block_num: AtomicU64::new(0),
             final_block_num: BlockNumber(0),
             state_watches: Self::make_state_watches_from_slices(&[]),
@@ -275,7 +296,8 @@ impl HeaderSlices {
         while cursor < slices.len() {
             let current_status = slices[cursor].read().status;
             if current_status == status {
-                slices.remove(cursor);
+                let removed_slice_lock = slices.remove(cursor).unwrap();
+                self.forget_mem_usage(&removed_slice_lock.read());
                 count += 1;
             } else {
                 cursor += 1;
@@ -291,7 +313,7 @@ impl HeaderSlices {
         let initial_len = slices.len();
         let mut count = 0;
 
-        for _ in initial_len..self.max_slices {
+        for _ in initial_len..self.max_slices_within_mem_limit() {
             let max_block_num = self.max_block_num();
             if max_block_num >= self.final_block_num {
                 break;
@@ -315,6 +337,8 @@ impl HeaderSlices {
         let mut slices = self.slices.write();
         slices.clear();
         self.max_block_num.store(0, ATOMIC_ORDERING);
+        self.mem_usage.store(0, ATOMIC_ORDERING);
+        self.headers_count.store(0, ATOMIC_ORDERING);
 
         for watch in self.state_watches.values() {
             watch.count.store(0, ATOMIC_ORDERING);
@@ -324,6 +348,10 @@ impl HeaderSlices {
     pub fn reset_to_single_slice(&self, initial_slice: HeaderSlice) {
         let start_block_num = initial_slice.start_block_num;
         let status = initial_slice.status;
+        self.mem_usage
+            .store(slice_mem_size(&initial_slice), ATOMIC_ORDERING);
+        self.headers_count
+            .store(initial_slice.len(), ATOMIC_ORDERING);
 
         let mut slices = self.slices.write();
         slices.clear();
@@ -393,12 +421,68 @@ impl HeaderSlices {
         let mut slices = self.slices.write();
         while slices.len() > self.max_slices {
             let removed_slice_lock = slices.pop_front().unwrap();
+            self.forget_mem_usage(&removed_slice_lock.read());
             let removed_status = removed_slice_lock.read().status;
             let status_watch = &self.state_watches[&removed_status];
             status_watch.count.fetch_sub(1, ATOMIC_ORDERING);
         }
     }
 
+    /// Stores slices that were downloaded, but not saved yet,
+    /// so that the next run (possibly after a restart) does not need to fetch them again.
+    pub fn persist(&self, store: &HeaderSliceStore) -> anyhow::Result<usize> {
+        let mut count = 0;
+        for slice_lock in self.slices.read().iter() {
+            let slice = slice_lock.read();
+            let is_downloaded = matches!(
+                slice.status,
+                HeaderSliceStatus::Downloaded
+                    | HeaderSliceStatus::VerifiedInternally
+                    | HeaderSliceStatus::Verified
+            );
+            let Some(headers) = slice.headers.as_ref().filter(|_| is_downloaded) else {
+                continue;
+            };
+
+            store.save(slice.start_block_num, slice.status, headers)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Fills Empty slices with the stored headers, and drops the stored slices below the window.
+    ///
+    /// Slices stored as Downloaded are restored as such. Slices which passed verification get
+    /// `verified_status`: the chain might have changed since they were linked to it,
+    /// so the downloader has to check at least their link again.
+    pub fn restore(
+        &self,
+        store: &HeaderSliceStore,
+        verified_status: HeaderSliceStatus,
+    ) -> anyhow::Result<usize> {
+        store.remove_below(self.min_block_num())?;
+
+        let mut count = 0;
+        for slice_lock in self.slices.read().iter() {
+            let mut slice = slice_lock.write();
+            if slice.status != HeaderSliceStatus::Empty {
+                continue;
+            }
+            let Some((status, headers)) = store.load(slice.start_block_num)? else {
+                continue;
+            };
+
+            let status = match status {
+                HeaderSliceStatus::Downloaded => HeaderSliceStatus::Downloaded,
+                _ => verified_status,
+            };
+            self.set_slice_headers(slice.deref_mut(), Some(headers));
+            self.set_slice_status(slice.deref_mut(), status);
+            count += 1;
+        }
+        Ok(count)
+    }
+
     pub fn has_one_of_statuses(&self, statuses: &[HeaderSliceStatus]) -> bool {
         statuses
             .iter()
@@ -424,6 +508,70 @@ impl HeaderSlices {
         new_status_watch.count.fetch_add(1, ATOMIC_ORDERING);
     }
 
+    /// Replaces the slice headers, keeping track of the memory they take.
+    pub fn set_slice_headers(&self, slice: &mut HeaderSlice, headers: Option<Vec<BlockHeader>>) {
+        self.forget_mem_usage(slice);
+        slice.headers = headers;
+        self.mem_usage
+            .fetch_add(slice_mem_size(slice), ATOMIC_ORDERING);
+        self.headers_count.fetch_add(slice.len(), ATOMIC_ORDERING);
+    }
+
+    pub fn take_slice_headers(&self, slice: &mut HeaderSlice) -> Option<Vec<BlockHeader>> {
+        self.forget_mem_usage(slice);
+        slice.headers.take()
+    }
+
+    fn forget_mem_usage(&self, slice: &HeaderSlice) {
+        self.mem_usage
+            .fetch_sub(slice_mem_size(slice), ATOMIC_ORDERING);
+        self.headers_count.fetch_sub(slice.len(), ATOMIC_ORDERING);
+    }
+
+    /// Drops headers of the oldest Saved slices until the memory usage fits the limit.
+    /// The most recent Saved slice is kept, because the next slices are linked to it.
+    pub fn evict_saved_headers(&self) -> usize {
+        let slices = self.slices.read();
+        let Some(last_saved_index) = slices
+            .iter()
+            .rposition(|slice| slice.read().status == HeaderSliceStatus::Saved) else {
+            return 0;
+        };
+
+        let mut count = 0;
+        for slice_lock in slices.iter().take(last_saved_index) {
+            if self.mem_usage() <= self.mem_limit {
+                break;
+            }
+            let mut slice = slice_lock.write();
+            if (slice.status == HeaderSliceStatus::Saved) && slice.headers.is_some() {
+                self.set_slice_headers(slice.deref_mut(), None);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// How many slices fit into mem_limit judging by the average size of the headers received so far.
+    fn max_slices_within_mem_limit(&self) -> usize {
+        let headers_count = self.headers_count.load(ATOMIC_ORDERING);
+        if headers_count == 0 {
+            return self.max_slices;
+        }
+
+        let avg_header_size = self.mem_usage() / headers_count;
+        let max_slices = self.mem_limit / avg_header_size.max(1) / HEADER_SLICE_SIZE;
+        std::cmp::min(self.max_slices, max_slices.max(1))
+    }
+
+    pub fn mem_usage(&self) -> usize {
+        self.mem_usage.load(ATOMIC_ORDERING)
+    }
+
+    pub fn mem_limit(&self) -> usize {
+        self.mem_limit
+    }
+
     pub fn watch_status_changes(&self, status: HeaderSliceStatus) -> watch::Receiver<usize> {
         let status_watch = &self.state_watches[&status];
         status_watch.receiver.clone()
@@ -495,6 +643,13 @@ fn estimate_max_slices_for_mem_limit(mem_limit: usize) -> usize {
     mem_limit / std::mem::size_of::<BlockHeader>() / HEADER_SLICE_SIZE
 }
 
+fn slice_mem_size(slice: &HeaderSlice) -> usize {
+    slice
+        .headers
+        .as_ref()
+        .map_or(0, |headers| headers.iter().map(BlockHeader::mem_size).sum())
+}
+
 impl HeaderSlice {
     pub fn len(&self) -> usize {
         self.headers.as_ref().map_or(0, |headers| headers.len())
@@ -547,3 +702,122 @@ impl TryFrom<char> for HeaderSliceStatus {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::BlockHeader as BaseBlockHeader;
+    use tempfile::TempDir;
+
+    fn downloaded_slice(start_block_num: BlockNumber, status: HeaderSliceStatus) -> HeaderSlice {
+        let headers = (0..HEADER_SLICE_SIZE as u64)
+            .map(|i| {
+                BlockHeader::from(BaseBlockHeader {
+                    number: BlockNumber(start_block_num.0 + i),
+                    ..BaseBlockHeader::empty()
+                })
+            })
+            .collect();
+        HeaderSlice {
+            start_block_num,
+            status,
+            headers: Some(headers),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn persist_and_restore() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = HeaderSliceStore::new(temp_dir.path().to_path_buf()).unwrap();
+
+        let slices = HeaderSlices::from_slices_vec(
+            vec![
+                downloaded_slice(BlockNumber(0), HeaderSliceStatus::Saved),
+                downloaded_slice(BlockNumber(192), HeaderSliceStatus::Verified),
+                HeaderSlice {
+                    start_block_num: BlockNumber(384),
+                    status: HeaderSliceStatus::Waiting,
+                    ..Default::default()
+                },
+                downloaded_slice(BlockNumber(576), HeaderSliceStatus::Downloaded),
+                downloaded_slice(BlockNumber(768), HeaderSliceStatus::VerifiedInternally),
+            ],
+            None,
+            None,
+            None,
+        );
+        assert_eq!(slices.persist(&store).unwrap(), 3);
+
+        let restored = HeaderSlices::new(usize::MAX, BlockNumber(384), BlockNumber(960));
+        assert_eq!(
+            restored
+                .restore(&store, HeaderSliceStatus::VerifiedInternally)
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            restored.clone_statuses(),
+            vec![
+                HeaderSliceStatus::Empty,
+                HeaderSliceStatus::Downloaded,
+                HeaderSliceStatus::VerifiedInternally,
+            ]
+        );
+        let slice_lock = restored.find_by_start_block_num(BlockNumber(576)).unwrap();
+        let slice = slice_lock.read();
+        assert_eq!(slice.len(), HEADER_SLICE_SIZE);
+        assert_eq!(
+            slice.headers.as_ref().unwrap()[0].number(),
+            BlockNumber(576)
+        );
+
+        // The slice below the window is gone from the store.
+        assert!(store.load(BlockNumber(192)).unwrap().is_none());
+        assert!(store.load(BlockNumber(576)).unwrap().is_some());
+
+        // Unwinding discards the slices past the unwind point, partially or entirely.
+        assert_eq!(store.remove_above(BlockNumber(767)).unwrap(), 1);
+        assert!(store.load(BlockNumber(576)).unwrap().is_some());
+        assert!(store.load(BlockNumber(768)).unwrap().is_none());
+        assert_eq!(store.remove_above(BlockNumber(700)).unwrap(), 1);
+        assert!(store.load(BlockNumber(576)).unwrap().is_none());
+    }
+
+    #[test]
+    fn mem_usage_accounting() {
+        let slices = HeaderSlices::from_slices_vec(
+            vec![
+                downloaded_slice(BlockNumber(0), HeaderSliceStatus::Saved),
+                downloaded_slice(BlockNumber(192), HeaderSliceStatus::Saved),
+                downloaded_slice(BlockNumber(384), HeaderSliceStatus::Downloaded),
+            ],
+            None,
+            None,
+            None,
+        );
+        let slice_size = slices.mem_usage() / 3;
+        assert!(slice_size >= HEADER_SLICE_SIZE * std::mem::size_of::<BlockHeader>());
+
+        let slice_lock = slices.find_by_start_block_num(BlockNumber(384)).unwrap();
+        let headers = slices.take_slice_headers(&mut slice_lock.write());
+        assert_eq!(slices.mem_usage(), 2 * slice_size);
+
+        let mut headers = headers.unwrap();
+        headers[0].header.extra_data = vec![0; 1000].into();
+        slices.set_slice_headers(&mut slice_lock.write(), Some(headers));
+        assert_eq!(slices.mem_usage(), 3 * slice_size + 2000);
+
+        // the most recent saved slice is kept
+        let slices = HeaderSlices {
+            mem_limit: slice_size,
+            ..slices
+        };
+        assert_eq!(slices.evict_saved_headers(), 1);
+        assert_eq!(slices.mem_usage(), 2 * slice_size + 2000);
+        assert!(slices.clone_slices_vec()[1].headers.is_some());
+
+        slices.remove(HeaderSliceStatus::Saved);
+        assert_eq!(slices.mem_usage(), slice_size + 2000);
+    }
+}
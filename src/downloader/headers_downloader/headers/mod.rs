@@ -1,3 +1,6 @@
 pub mod header;
 pub mod header_slice_status_watch;
+pub mod header_slice_store;
 pub mod header_slices;
+pub mod peer_rotation;
+pub mod request_batching;
@@ -0,0 +1,229 @@
+use crate::sentry::sentry_client::PeerId;
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// A peer is penalized after this number of timed out requests in a row.
+pub const MAX_CONSECUTIVE_TIMEOUTS: u16 = 3;
+
+/// Cooldown after the first penalty, doubled by each following one.
+const BASE_COOLDOWN: Duration = Duration::from_secs(10);
+const MAX_COOLDOWN: Duration = Duration::from_secs(600);
+
+/// Peers that responded to our header requests.
+///
+/// Requests are spread among them round-robin, and retries of timed out requests go to a different peer.
+/// Penalized peers are skipped until their cooldown expires, the cooldown grows with every penalty
+/// not followed by a response.
+/// Until some peer responds, requests are sent to random peers.
+#[derive(Default)]
+pub struct PeerRotation {
+    state: Mutex<PeerRotationState>,
+}
+
+#[derive(Default)]
+struct PeerRotationState {
+    peers: Vec<PeerId>,
+    timeouts: HashMap<PeerId, u16>,
+    cooldowns: HashMap<PeerId, Cooldown>,
+    next_index: usize,
+}
+
+struct Cooldown {
+    penalties: u32,
+    until: Instant,
+}
+
+impl PeerRotationState {
+    fn is_cooling(&self, peer_id: &PeerId, now: Instant) -> bool {
+        self.cooldowns
+            .get(peer_id)
+            .map_or(false, |cooldown| now < cooldown.until)
+    }
+}
+
+impl PeerRotation {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn on_response(&self, peer_id: PeerId) {
+        let mut state = self.state.lock();
+        if !state.peers.contains(&peer_id) {
+            state.peers.push(peer_id);
+        }
+        state.timeouts.remove(&peer_id);
+        state.cooldowns.remove(&peer_id);
+    }
+
+    /// Returns true if the peer failed to respond too many times in a row.
+    /// Such peer is put on cooldown and should be penalized.
+    pub fn on_timeout(&self, peer_id: PeerId) -> bool {
+        self.on_timeout_at(peer_id, Instant::now())
+    }
+
+    fn on_timeout_at(&self, peer_id: PeerId, now: Instant) -> bool {
+        let mut state = self.state.lock();
+        let timeouts = state.timeouts.entry(peer_id).or_default();
+        *timeouts += 1;
+        if *timeouts < MAX_CONSECUTIVE_TIMEOUTS {
+            return false;
+        }
+
+        Self::cool_down(&mut state, peer_id, now);
+        true
+    }
+
+    /// Takes the peer out of the rotation until its cooldown expires.
+    pub fn penalize(&self, peer_id: PeerId) {
+        Self::cool_down(&mut self.state.lock(), peer_id, Instant::now());
+    }
+
+    fn cool_down(state: &mut PeerRotationState, peer_id: PeerId, now: Instant) {
+        state.timeouts.remove(&peer_id);
+        let cooldown = state.cooldowns.entry(peer_id).or_insert(Cooldown {
+            penalties: 0,
+            until: now,
+        });
+        let duration = BASE_COOLDOWN
+            .checked_mul(1 << cooldown.penalties.min(16))
+            .map_or(MAX_COOLDOWN, |duration| duration.min(MAX_COOLDOWN));
+        cooldown.penalties += 1;
+        cooldown.until = now + duration;
+    }
+
+    pub fn remove(&self, peer_id: PeerId) {
+        let mut state = self.state.lock();
+        state.timeouts.remove(&peer_id);
+        state.cooldowns.remove(&peer_id);
+        state.peers.retain(|peer| *peer != peer_id);
+    }
+
+    /// Picks the next peer in the rotation, skipping the excluded one unless it is the only choice.
+    /// Peers on cooldown are never picked.
+    pub fn next_peer(&self, exclude: Option<PeerId>) -> Option<PeerId> {
+        self.next_peer_at(exclude, Instant::now())
+    }
+
+    fn next_peer_at(&self, exclude: Option<PeerId>, now: Instant) -> Option<PeerId> {
+        let mut state = self.state.lock();
+        let count = state.peers.len();
+        let mut excluded = None;
+        for _ in 0..count {
+            let peer_id = state.peers[state.next_index % count];
+            state.next_index = (state.next_index + 1) % count;
+            if state.is_cooling(&peer_id, now) {
+                continue;
+            }
+            if Some(peer_id) != exclude {
+                return Some(peer_id);
+            }
+            excluded = Some(peer_id);
+        }
+        excluded
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().peers.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_robin() {
+        let rotation = PeerRotation::new();
+        assert_eq!(rotation.next_peer(None), None);
+
+        let peers = [
+            PeerId::repeat_byte(1),
+            PeerId::repeat_byte(2),
+            PeerId::repeat_byte(3),
+        ];
+        for peer_id in peers {
+            rotation.on_response(peer_id);
+        }
+        rotation.on_response(peers[0]);
+        assert_eq!(rotation.len(), 3);
+
+        assert_eq!(rotation.next_peer(None), Some(peers[0]));
+        assert_eq!(rotation.next_peer(None), Some(peers[1]));
+        assert_eq!(rotation.next_peer(Some(peers[2])), Some(peers[0]));
+        assert_eq!(rotation.next_peer(None), Some(peers[1]));
+
+        rotation.remove(peers[1]);
+        rotation.remove(peers[2]);
+        assert_eq!(rotation.next_peer(Some(peers[0])), Some(peers[0]));
+    }
+
+    #[test]
+    fn consecutive_timeouts() {
+        let rotation = PeerRotation::new();
+        let peer_id = PeerId::repeat_byte(1);
+        rotation.on_response(peer_id);
+
+        assert!(!rotation.on_timeout(peer_id));
+        assert!(!rotation.on_timeout(peer_id));
+        rotation.on_response(peer_id);
+
+        for _ in 1..MAX_CONSECUTIVE_TIMEOUTS {
+            assert!(!rotation.on_timeout(peer_id));
+        }
+        assert!(rotation.on_timeout(peer_id));
+        assert_eq!(rotation.next_peer(None), None);
+    }
+
+    #[test]
+    fn cooldown_backoff() {
+        let rotation = PeerRotation::new();
+        let peers = [PeerId::repeat_byte(1), PeerId::repeat_byte(2)];
+        for peer_id in peers {
+            rotation.on_response(peer_id);
+        }
+
+        let penalize = |now| {
+            for _ in 1..MAX_CONSECUTIVE_TIMEOUTS {
+                assert!(!rotation.on_timeout_at(peers[0], now));
+            }
+            assert!(rotation.on_timeout_at(peers[0], now));
+        };
+
+        let start = Instant::now();
+        penalize(start);
+        assert_eq!(rotation.len(), 2);
+        for _ in 0..3 {
+            assert_eq!(rotation.next_peer_at(None, start), Some(peers[1]));
+        }
+        // The excluded peer is still preferred to a cooling one.
+        assert_eq!(rotation.next_peer_at(Some(peers[1]), start), Some(peers[1]));
+        assert_eq!(
+            rotation.next_peer_at(Some(peers[1]), start + BASE_COOLDOWN),
+            Some(peers[0])
+        );
+
+        // Penalized again without responding in between, the cooldown doubles.
+        let again = start + BASE_COOLDOWN;
+        penalize(again);
+        let expired = again + BASE_COOLDOWN * 2;
+        assert_eq!(
+            rotation.next_peer_at(Some(peers[1]), expired - Duration::from_secs(1)),
+            Some(peers[1])
+        );
+        assert_eq!(
+            rotation.next_peer_at(Some(peers[1]), expired),
+            Some(peers[0])
+        );
+
+        // A response clears the penalties.
+        rotation.on_response(peers[0]);
+        penalize(expired);
+        assert_eq!(
+            rotation.next_peer_at(Some(peers[1]), expired + BASE_COOLDOWN),
+            Some(peers[0])
+        );
+    }
+}
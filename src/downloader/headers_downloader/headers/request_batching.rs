@@ -0,0 +1,195 @@
+use super::header_slices::HEADER_SLICE_SIZE;
+use crate::{downloader::opts::RequestBatchingOpts, models::BlockNumber};
+use parking_lot::Mutex;
+use std::{collections::HashMap, time};
+use tracing::*;
+
+/// Requests that got no response for this long are forgotten.
+const MAX_PENDING_AGE: time::Duration = time::Duration::from_secs(60);
+
+/// Peers serve at most that many headers per request, larger batches always get partial responses.
+const MAX_HEADERS_SERVE: usize = 1024;
+
+/// Decides how many consecutive slices are requested from a peer in a single GetBlockHeaders request.
+///
+/// The batch grows by one slice while peers return complete responses within the target latency,
+/// and is halved on partial responses (e.g. near the chain tip), slow responses and timeouts.
+pub struct RequestBatching {
+    opts: RequestBatchingOpts,
+    slice_size: usize,
+    max_slices: usize,
+    state: Mutex<RequestBatchingState>,
+}
+
+struct RequestBatchingState {
+    batch_size: usize,
+    pending: HashMap<BlockNumber, PendingRequest>,
+}
+
+struct PendingRequest {
+    slices_count: usize,
+    limit: usize,
+    time: time::Instant,
+}
+
+impl RequestBatching {
+    /// `slice_size` is the number of headers requested for each slice,
+    /// it can overlap the next slice by a few headers.
+    /// The batch never exceeds what a peer serves in a single response, for that `slice_size`.
+    pub fn new(opts: RequestBatchingOpts, slice_size: usize) -> Self {
+        let served_slices = (MAX_HEADERS_SERVE.saturating_sub(slice_size) / HEADER_SLICE_SIZE) + 1;
+        let max_slices = opts.max_slices.clamp(1, served_slices);
+        if max_slices < opts.max_slices {
+            warn!(
+                "Peers serve at most {} headers per request, requesting at most {} slices at once",
+                MAX_HEADERS_SERVE, max_slices
+            );
+        }
+
+        Self {
+            opts,
+            slice_size,
+            max_slices,
+            state: Mutex::new(RequestBatchingState {
+                batch_size: 1,
+                pending: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn slice_size(&self) -> usize {
+        self.slice_size
+    }
+
+    /// How many slices to put into the next request.
+    pub fn batch_size(&self) -> usize {
+        self.state.lock().batch_size
+    }
+
+    /// How many headers to request for a batch of slices.
+    pub fn limit(&self, slices_count: usize) -> usize {
+        (slices_count.max(1) - 1) * HEADER_SLICE_SIZE + self.slice_size
+    }
+
+    pub fn on_request(&self, start_block_num: BlockNumber, slices_count: usize) {
+        let now = time::Instant::now();
+        let mut state = self.state.lock();
+        state
+            .pending
+            .retain(|_, request| now.duration_since(request.time) < MAX_PENDING_AGE);
+        state.pending.insert(
+            start_block_num,
+            PendingRequest {
+                slices_count,
+                limit: self.limit(slices_count),
+                time: now,
+            },
+        );
+    }
+
+    /// Adapts the batch size to the response.
+    /// Returns the number of slices the request covered, if the request is known.
+    pub fn on_response(&self, start_block_num: BlockNumber, headers_count: usize) -> Option<usize> {
+        let mut state = self.state.lock();
+        let request = state.pending.remove(&start_block_num)?;
+
+        let is_complete = headers_count >= request.limit;
+        let is_fast = request.time.elapsed() <= self.opts.target_latency;
+        state.batch_size = if is_complete && is_fast {
+            (state.batch_size + 1).min(self.max_slices)
+        } else {
+            (state.batch_size / 2).max(1)
+        };
+
+        Some(request.slices_count)
+    }
+
+    pub fn on_timeout(&self) {
+        let mut state = self.state.lock();
+        state.batch_size = (state.batch_size / 2).max(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batching(max_slices: usize) -> RequestBatching {
+        RequestBatching::new(
+            RequestBatchingOpts {
+                max_slices,
+                target_latency: time::Duration::from_secs(60),
+            },
+            HEADER_SLICE_SIZE,
+        )
+    }
+
+    fn complete_request(batching: &RequestBatching, start_block_num: BlockNumber) -> usize {
+        let slices_count = batching.batch_size();
+        batching.on_request(start_block_num, slices_count);
+        batching
+            .on_response(start_block_num, batching.limit(slices_count))
+            .unwrap()
+    }
+
+    #[test]
+    fn grows_on_complete_responses() {
+        let batching = batching(3);
+        assert_eq!(batching.batch_size(), 1);
+        assert_eq!(complete_request(&batching, BlockNumber(0)), 1);
+        assert_eq!(batching.batch_size(), 2);
+        assert_eq!(complete_request(&batching, BlockNumber(192)), 2);
+        assert_eq!(batching.batch_size(), 3);
+        assert_eq!(complete_request(&batching, BlockNumber(576)), 3);
+        assert_eq!(batching.batch_size(), 3);
+    }
+
+    #[test]
+    fn shrinks_on_partial_responses_and_timeouts() {
+        let batching = batching(4);
+        for i in 0..3 {
+            complete_request(&batching, BlockNumber(i * HEADER_SLICE_SIZE as u64));
+        }
+        assert_eq!(batching.batch_size(), 4);
+
+        batching.on_request(BlockNumber(10_000), 4);
+        assert_eq!(batching.on_response(BlockNumber(10_000), 100), Some(4));
+        assert_eq!(batching.batch_size(), 2);
+
+        batching.on_timeout();
+        assert_eq!(batching.batch_size(), 1);
+        batching.on_timeout();
+        assert_eq!(batching.batch_size(), 1);
+
+        assert_eq!(batching.on_response(BlockNumber(10_000), 100), None);
+    }
+
+    #[test]
+    fn bounded_by_served_headers() {
+        for (slice_size, max_slices) in [(HEADER_SLICE_SIZE, 5), (HEADER_SLICE_SIZE + 1, 5)] {
+            let batching = RequestBatching::new(
+                RequestBatchingOpts {
+                    max_slices: 100,
+                    target_latency: time::Duration::from_secs(60),
+                },
+                slice_size,
+            );
+            for i in 0..10 {
+                complete_request(&batching, BlockNumber(i * HEADER_SLICE_SIZE as u64));
+            }
+            assert_eq!(batching.batch_size(), max_slices);
+            assert!(batching.limit(batching.batch_size()) <= MAX_HEADERS_SERVE);
+        }
+
+        let batching = RequestBatching::new(RequestBatchingOpts::default(), MAX_HEADERS_SERVE);
+        complete_request(&batching, BlockNumber(0));
+        assert_eq!(batching.batch_size(), 1);
+    }
+
+    #[test]
+    fn limit_with_overlap() {
+        let batching = RequestBatching::new(RequestBatchingOpts::default(), HEADER_SLICE_SIZE + 1);
+        assert_eq!(batching.limit(1), HEADER_SLICE_SIZE + 1);
+        assert_eq!(batching.limit(3), 3 * HEADER_SLICE_SIZE + 1);
+    }
+}
@@ -7,6 +7,7 @@ use super::{
     ui_view::UIView,
 };
 use crate::models::BlockNumber;
+use bytesize::ByteSize;
 use crossterm::{cursor, style, terminal, QueueableCommand};
 use std::{
     cell::RefCell,
@@ -42,6 +43,7 @@ impl UIView for HeaderSlicesView {
         let max_block_num = self.header_slices.max_block_num();
         let final_block_num = self.header_slices.final_block_num();
         let counters = self.header_slices.status_counters();
+        let mem_usage = format_mem_usage(&self.header_slices);
         let statuses = self.header_slices.clone_statuses();
 
         // speed
@@ -81,6 +83,7 @@ impl UIView for HeaderSlicesView {
 
         // counters
         stdout.queue(style::Print(format_counters(counters)))?;
+        stdout.queue(style::Print(mem_usage))?;
         stdout.queue(terminal::Clear(terminal::ClearType::UntilNewLine))?;
         stdout.queue(cursor::MoveToNextLine(1))?;
 
@@ -102,3 +105,11 @@ fn format_counters(counters: Vec<(HeaderSliceStatus, usize)>) -> String {
     }
     line
 }
+
+fn format_mem_usage(header_slices: &HeaderSlices) -> String {
+    let mem_usage = ByteSize(header_slices.mem_usage() as u64);
+    match header_slices.mem_limit() {
+        usize::MAX => std::format!("mem: {}", mem_usage),
+        mem_limit => std::format!("mem: {} of {}", mem_usage, ByteSize(mem_limit as u64)),
+    }
+}
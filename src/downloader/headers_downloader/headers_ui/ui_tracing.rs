@@ -7,6 +7,7 @@ use super::{
     ui_view::UIView,
 };
 use crate::models::BlockNumber;
+use bytesize::ByteSize;
 use std::{cell::RefCell, sync::Arc};
 use tracing::*;
 
@@ -38,6 +39,7 @@ impl UIView for HeaderSlicesView {
         let max_block_num = self.header_slices.max_block_num();
         let final_block_num = self.header_slices.final_block_num();
         let counters = self.header_slices.status_counters();
+        let mem_usage = format_mem_usage(&self.header_slices);
 
         // speed
         let mut speed_counter = self.speed_counter.borrow_mut();
@@ -52,7 +54,7 @@ impl UIView for HeaderSlicesView {
 
         // counters
         let counters_str = format_counters(counters);
-        debug!("{}", counters_str);
+        debug!("{}{}", counters_str, mem_usage);
 
         Ok(())
     }
@@ -65,3 +67,11 @@ fn format_counters(counters: Vec<(HeaderSliceStatus, usize)>) -> String {
     }
     line
 }
+
+fn format_mem_usage(header_slices: &HeaderSlices) -> String {
+    let mem_usage = ByteSize(header_slices.mem_usage() as u64);
+    match header_slices.mem_limit() {
+        usize::MAX => std::format!("mem: {}", mem_usage),
+        mem_limit => std::format!("mem: {} of {}", mem_usage, ByteSize(mem_limit as u64)),
+    }
+}
@@ -7,6 +7,9 @@ mod headers;
 mod headers_ui;
 mod stages;
 
+pub use headers::header_slice_store::HeaderSliceStore;
+
+mod downloader_follow;
 mod downloader_forky;
 mod downloader_linear;
 mod downloader_preverified;
@@ -1,6 +1,8 @@
 use super::headers::{
     header::BlockHeader,
-    header_slices::{HeaderSlice, HeaderSliceStatus, HeaderSlices},
+    header_slices::{HeaderSlice, HeaderSliceStatus, HeaderSlices, HEADER_SLICE_SIZE},
+    peer_rotation::PeerRotation,
+    request_batching::RequestBatching,
 };
 use crate::sentry::{
     messages::{BlockHeadersMessage, EthMessageId, Message},
@@ -25,18 +27,28 @@ struct BlockHeadersMessageFromPeer {
 type BlockHeadersMessageStream = Pin<Box<dyn Stream<Item = BlockHeadersMessageFromPeer> + Send>>;
 
 /// Receives the slices, and sets Downloaded status.
+/// A response to a batched request is split into its slices.
 pub struct FetchReceiveStage {
     header_slices: Arc<HeaderSlices>,
     sentry: SentryClientReactorShared,
+    peer_rotation: Arc<PeerRotation>,
+    request_batching: Arc<RequestBatching>,
     is_over: Arc<AtomicBool>,
     message_stream: Mutex<Option<BlockHeadersMessageStream>>,
 }
 
 impl FetchReceiveStage {
-    pub fn new(header_slices: Arc<HeaderSlices>, sentry: SentryClientReactorShared) -> Self {
+    pub fn new(
+        header_slices: Arc<HeaderSlices>,
+        sentry: SentryClientReactorShared,
+        peer_rotation: Arc<PeerRotation>,
+        request_batching: Arc<RequestBatching>,
+    ) -> Self {
         Self {
             header_slices,
             sentry,
+            peer_rotation,
+            request_batching,
             is_over: Arc::new(false.into()),
             message_stream: Mutex::new(None),
         }
@@ -73,27 +85,51 @@ impl FetchReceiveStage {
         }
 
         let start_block_num = headers[0].number;
-        let slice_lock_opt = self.header_slices.find_by_start_block_num(start_block_num);
-
-        match slice_lock_opt {
-            Some(slice_lock) => {
-                let mut slice = slice_lock.write();
-                let slice_status = slice.status;
-                if slice_status == HeaderSliceStatus::Waiting {
-                    let from_peer_id = message_from_peer.from_peer_id;
-                    let headers: Vec<BlockHeader> =
-                        headers.into_iter().map(BlockHeader::from).collect();
-                    self.update_slice(slice.deref_mut(), headers, from_peer_id);
-                } else {
-                    debug!("FetchReceiveStage ignores a headers slice that we didn't request starting at: {:?}; status = {:?}", start_block_num, slice_status);
-                }
-            }
-            None => {
+        let headers_count = headers.len();
+        let slices_count = self
+            .request_batching
+            .on_response(start_block_num, headers_count)
+            .unwrap_or(1);
+        let slice_size = self.request_batching.slice_size();
+        let from_peer_id = message_from_peer.from_peer_id;
+
+        let headers: Vec<BlockHeader> = headers.into_iter().map(BlockHeader::from).collect();
+        for i in 0..slices_count {
+            let offset = i * HEADER_SLICE_SIZE;
+            let slice_start_block_num = start_block_num + offset as u64;
+            let slice_lock_opt = self
+                .header_slices
+                .find_by_start_block_num(slice_start_block_num);
+
+            let Some(slice_lock) = slice_lock_opt else {
                 debug!(
                     "FetchReceiveStage ignores a headers slice that we didn't request starting at: {:?}",
-                    start_block_num
+                    slice_start_block_num
                 );
+                continue;
+            };
+
+            let mut slice = slice_lock.write();
+            let slice_status = slice.status;
+            if slice_status != HeaderSliceStatus::Waiting {
+                debug!("FetchReceiveStage ignores a headers slice that we didn't request starting at: {:?}; status = {:?}", slice_start_block_num, slice_status);
+                continue;
+            }
+
+            // the first slice is verified even if incomplete, the rest are requested again
+            if (i > 0) && (offset + slice_size > headers_count) {
+                slice.request_time = None;
+                self.header_slices
+                    .set_slice_status(slice.deref_mut(), HeaderSliceStatus::Empty);
+                continue;
             }
+
+            let end = std::cmp::min(offset + slice_size, headers_count);
+            self.update_slice(
+                slice.deref_mut(),
+                headers[offset..end].to_vec(),
+                from_peer_id,
+            );
         }
     }
 
@@ -103,8 +139,11 @@ impl FetchReceiveStage {
         headers: Vec<BlockHeader>,
         from_peer_id: Option<PeerId>,
     ) {
-        slice.headers = Some(headers);
+        self.header_slices.set_slice_headers(slice, Some(headers));
         slice.from_peer_id = from_peer_id;
+        if let Some(peer_id) = from_peer_id {
+            self.peer_rotation.on_response(peer_id);
+        }
         self.header_slices
             .set_slice_status(slice, HeaderSliceStatus::Downloaded);
     }
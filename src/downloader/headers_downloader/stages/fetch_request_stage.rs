@@ -1,6 +1,8 @@
 use super::headers::{
     header_slice_status_watch::HeaderSliceStatusWatch,
-    header_slices::{HeaderSliceStatus, HeaderSlices},
+    header_slices::{HeaderSlice, HeaderSliceStatus, HeaderSlices},
+    peer_rotation::PeerRotation,
+    request_batching::RequestBatching,
 };
 use crate::{
     models::BlockNumber,
@@ -11,19 +13,22 @@ use crate::{
         sentry_client_reactor::*,
     },
 };
-use parking_lot::RwLockUpgradableReadGuard;
+use parking_lot::{RwLock, RwLockUpgradableReadGuard};
 use std::{
-    ops::{ControlFlow, DerefMut},
+    ops::DerefMut,
     sync::{atomic::*, Arc},
     time,
 };
 use tracing::*;
 
 /// Sends requests to P2P via sentry to get the slices. Slices become Waiting.
+/// Requests are spread among the peers that responded before, and a retry goes to a different peer.
+/// Consecutive slices are requested in batches sized by RequestBatching.
 pub struct FetchRequestStage {
     header_slices: Arc<HeaderSlices>,
     sentry: SentryClientReactorShared,
-    slice_size: usize,
+    peer_rotation: Arc<PeerRotation>,
+    request_batching: Arc<RequestBatching>,
     pending_watch: HeaderSliceStatusWatch,
     last_request_id: AtomicU64,
 }
@@ -32,12 +37,14 @@ impl FetchRequestStage {
     pub fn new(
         header_slices: Arc<HeaderSlices>,
         sentry: SentryClientReactorShared,
-        slice_size: usize,
+        peer_rotation: Arc<PeerRotation>,
+        request_batching: Arc<RequestBatching>,
     ) -> Self {
         Self {
             header_slices: header_slices.clone(),
             sentry,
-            slice_size,
+            peer_rotation,
+            request_batching,
             pending_watch: HeaderSliceStatusWatch::new(
                 HeaderSliceStatus::Empty,
                 header_slices,
@@ -73,42 +80,76 @@ impl FetchRequestStage {
     }
 
     fn request_pending(&self, sentry: &SentryClientReactor) -> anyhow::Result<()> {
-        let result = self.header_slices.try_fold((), |_, slice_lock| {
-            let slice = slice_lock.upgradable_read();
-            if slice.status == HeaderSliceStatus::Empty {
-                let request_id = self.last_request_id.fetch_add(1, Ordering::SeqCst);
-
-                let block_num = slice.start_block_num;
-                let limit = self.slice_size as u64;
-
-                let result = self.request(request_id, block_num, limit, sentry);
-                match result {
-                    Err(error) => match error.downcast_ref::<SendMessageError>() {
-                        Some(SendMessageError::SendQueueFull) => {
-                            debug!("FetchRequestStage: request send queue is full");
-                            return ControlFlow::Break(Ok(()));
-                        }
-                        Some(SendMessageError::ReactorStopped) => {
-                            return ControlFlow::Break(Err(error))
-                        }
-                        None => return ControlFlow::Break(Err(error)),
-                    },
-                    Ok(_) => {
-                        let mut slice = RwLockUpgradableReadGuard::upgrade(slice);
-                        slice.request_time = Some(time::Instant::now());
-                        self.header_slices
-                            .set_slice_status(slice.deref_mut(), HeaderSliceStatus::Waiting);
-                    }
+        for batch in self.pending_batches() {
+            if let Err(error) = self.request_batch(&batch, sentry) {
+                if matches!(
+                    error.downcast_ref::<SendMessageError>(),
+                    Some(SendMessageError::SendQueueFull)
+                ) {
+                    debug!("FetchRequestStage: request send queue is full");
+                    return Ok(());
                 }
+                return Err(error);
             }
-            ControlFlow::Continue(())
+        }
+        Ok(())
+    }
+
+    /// Groups consecutive Empty slices into batches that are requested at once.
+    fn pending_batches(&self) -> Vec<Vec<Arc<RwLock<HeaderSlice>>>> {
+        let batch_size = self.request_batching.batch_size();
+        let mut batches = Vec::<Vec<Arc<RwLock<HeaderSlice>>>>::new();
+        let mut is_consecutive = false;
+        self.header_slices.for_each(|slice_lock| {
+            if slice_lock.read().status != HeaderSliceStatus::Empty {
+                is_consecutive = false;
+                return;
+            }
+            match batches.last_mut() {
+                Some(batch) if is_consecutive && (batch.len() < batch_size) => {
+                    batch.push(slice_lock.clone())
+                }
+                _ => batches.push(vec![slice_lock.clone()]),
+            }
+            is_consecutive = true;
         });
+        batches
+    }
 
-        if let ControlFlow::Break(break_result) = result {
-            break_result
-        } else {
-            Ok(())
+    fn request_batch(
+        &self,
+        batch: &[Arc<RwLock<HeaderSlice>>],
+        sentry: &SentryClientReactor,
+    ) -> anyhow::Result<()> {
+        // the statuses might have changed since the batch was formed
+        let slices = batch
+            .iter()
+            .map(|slice_lock| slice_lock.upgradable_read())
+            .take_while(|slice| slice.status == HeaderSliceStatus::Empty)
+            .collect::<Vec<_>>();
+        let Some(first_slice) = slices.first() else {
+            return Ok(());
+        };
+
+        let request_id = self.last_request_id.fetch_add(1, Ordering::SeqCst);
+
+        let block_num = first_slice.start_block_num;
+        let limit = self.request_batching.limit(slices.len()) as u64;
+        let peer_id = self.peer_rotation.next_peer(first_slice.to_peer_id);
+        let peer_filter = peer_id.map_or(PeerFilter::Random(1), PeerFilter::PeerId);
+
+        self.request(request_id, block_num, limit, peer_filter, sentry)?;
+        self.request_batching.on_request(block_num, slices.len());
+
+        let request_time = time::Instant::now();
+        for slice in slices {
+            let mut slice = RwLockUpgradableReadGuard::upgrade(slice);
+            slice.request_time = Some(request_time);
+            slice.to_peer_id = peer_id;
+            self.header_slices
+                .set_slice_status(slice.deref_mut(), HeaderSliceStatus::Waiting);
         }
+        Ok(())
     }
 
     fn request(
@@ -116,6 +157,7 @@ impl FetchRequestStage {
         request_id: u64,
         block_num: BlockNumber,
         limit: u64,
+        peer_filter: PeerFilter,
         sentry: &SentryClientReactor,
     ) -> anyhow::Result<()> {
         let message = GetBlockHeadersMessage {
@@ -127,7 +169,7 @@ impl FetchRequestStage {
                 reverse: 0,
             },
         };
-        sentry.try_send_message(Message::GetBlockHeaders(message), PeerFilter::Random(1))
+        sentry.try_send_message(Message::GetBlockHeaders(message), peer_filter)
     }
 
     pub fn can_proceed_check(&self) -> impl Fn() -> bool {
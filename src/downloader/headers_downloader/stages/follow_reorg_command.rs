@@ -0,0 +1,36 @@
+use mdbx::{EnvironmentKind, RW};
+
+use super::{headers::header::BlockHeader, SaveStage};
+use crate::{kv::mdbx::MdbxTransaction, models::BlockNumber};
+
+/// Makes a short fork found in the follow mode canonical.
+/// It is executed after the canonical chain is unwound to the connection block.
+pub struct FollowReorgCommand {
+    connection_block_num: BlockNumber,
+    headers: Vec<BlockHeader>,
+}
+
+impl FollowReorgCommand {
+    /// `headers` must be already saved, ordered by the block number,
+    /// and the first one must be a child of the connection block.
+    pub fn new(connection_block_num: BlockNumber, headers: Vec<BlockHeader>) -> Self {
+        Self {
+            connection_block_num,
+            headers,
+        }
+    }
+
+    pub fn connection_block_num(&self) -> BlockNumber {
+        self.connection_block_num
+    }
+
+    pub fn execute<'tx, 'db: 'tx, E: EnvironmentKind>(
+        self,
+        tx: &'tx MdbxTransaction<'db, RW, E>,
+    ) -> anyhow::Result<()> {
+        for header in &self.headers {
+            SaveStage::update_canonical_chain_header(header, tx)?;
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,13 @@
 use super::{
     fork_switch_command::ForkSwitchCommand,
     headers::{
+        header::BlockHeader,
         header_slice_status_watch::{HeaderSliceStatusWatch, HeaderSliceStatusWatchSelector},
         header_slices::*,
     },
-    verification::header_slice_verifier::HeaderSliceVerifier,
+    verification::header_slice_verifier::{is_fork_link_error, HeaderSliceVerifier},
 };
-use crate::{models::*, sentry::chain_config::ChainConfig};
+use crate::{consensus::ValidationError, models::*, sentry::chain_config::ChainConfig};
 use parking_lot::{RwLock, RwLockUpgradableReadGuard};
 use std::{
     ops::{ControlFlow, DerefMut, Range},
@@ -97,7 +98,9 @@ impl ForkModeStage {
         }
 
         let fork_start = canonical_continuation_slice.start_block_num;
-        let fork_initial_slice_headers = canonical_continuation_slice.headers.take();
+        let fork_initial_slice_headers = self
+            .header_slices
+            .take_slice_headers(canonical_continuation_slice.deref_mut());
         let fork_initial_slice_peer = canonical_continuation_slice.from_peer_id;
         self.refetch_canonical_slice(canonical_continuation_slice.deref_mut());
 
@@ -138,9 +141,13 @@ impl ForkModeStage {
             && (updated_count < pending_count)
         {
             let continuation_slice_lock = canonical_continuation_slice_lock_opt.unwrap();
-            did_extend_canonical = self.try_extend_canonical(continuation_slice_lock.clone());
-            if !did_extend_canonical {
-                self.refetch_canonical_slice(continuation_slice_lock.write().deref_mut());
+            match self.try_extend_canonical(continuation_slice_lock.clone()) {
+                Ok(()) => did_extend_canonical = true,
+                Err(error) => Self::reject_slice(
+                    &self.header_slices,
+                    continuation_slice_lock.write().deref_mut(),
+                    error,
+                ),
             }
             updated_count += 1;
         }
@@ -154,9 +161,13 @@ impl ForkModeStage {
             && (fork_updated_count < fork_pending_count)
         {
             let continuation_slice_lock = fork_continuation_slice_lock_opt.unwrap();
-            did_extend_fork = self.try_extend_fork(continuation_slice_lock.clone());
-            if !did_extend_fork {
-                self.refetch_fork_slice(continuation_slice_lock.write().deref_mut());
+            match self.try_extend_fork(continuation_slice_lock.clone()) {
+                Ok(()) => did_extend_fork = true,
+                Err(error) => Self::reject_slice(
+                    &self.fork_header_slices,
+                    continuation_slice_lock.write().deref_mut(),
+                    error,
+                ),
             }
             fork_updated_count += 1;
         }
@@ -169,7 +180,10 @@ impl ForkModeStage {
                 let canonical_range_difficulty =
                     self.canonical_range_difficulty(connection_block_num);
                 if fork_range_difficulty > canonical_range_difficulty {
-                    self.switch_to_fork(connection_block_num);
+                    match self.verify_fork_chain(connection_block_num) {
+                        Ok(()) => self.switch_to_fork(connection_block_num),
+                        Err((slice_start, error)) => self.invalidate_fork_slice(slice_start, error),
+                    }
                 } else {
                     self.discard_fork();
                 }
@@ -221,64 +235,139 @@ impl ForkModeStage {
     fn refetch_canonical_slice(&self, slice: &mut HeaderSlice) {
         self.header_slices
             .set_slice_status(slice, HeaderSliceStatus::Empty);
-        slice.headers = None;
+        self.header_slices.set_slice_headers(slice, None);
         slice.refetch_attempt += 1;
     }
 
     fn refetch_fork_slice(&self, slice: &mut HeaderSlice) {
         self.fork_header_slices
             .set_slice_status(slice, HeaderSliceStatus::Empty);
-        slice.headers = None;
+        self.fork_header_slices.set_slice_headers(slice, None);
         slice.refetch_attempt += 1;
     }
 
-    fn try_extend_canonical(&mut self, continuation_slice_lock: Arc<RwLock<HeaderSlice>>) -> bool {
-        let Some(end_slice_lock) = self.find_canonical_last_slice() else { return false };
+    /// Refetches a continuation slice which does not link.
+    /// If it follows the parent hash, but breaks the other rules, it is marked invalid instead,
+    /// so that its peer is penalized and it is refetched from another one.
+    fn reject_slice(
+        header_slices: &HeaderSlices,
+        slice: &mut HeaderSlice,
+        error: Option<ValidationError>,
+    ) {
+        match error {
+            Some(error) if !is_fork_link_error(&error) => {
+                debug!(
+                    "ForkModeStage: slice at {} is invalid: {}",
+                    slice.start_block_num.0, error
+                );
+                slice.invalid_reason = Some(error);
+                header_slices.set_slice_status(slice, HeaderSliceStatus::Invalid);
+            }
+            _ => {
+                header_slices.set_slice_status(slice, HeaderSliceStatus::Empty);
+                header_slices.set_slice_headers(slice, None);
+                slice.refetch_attempt += 1;
+            }
+        }
+    }
+
+    fn try_extend_canonical(
+        &mut self,
+        continuation_slice_lock: Arc<RwLock<HeaderSlice>>,
+    ) -> Result<(), Option<ValidationError>> {
+        let Some(end_slice_lock) = self.find_canonical_last_slice() else { return Err(None) };
         let end_slice = end_slice_lock.read();
         let continuation_slice = continuation_slice_lock.upgradable_read();
 
-        if self.verify_slices_link(&continuation_slice, &end_slice) {
-            let mut continuation_slice_mut = RwLockUpgradableReadGuard::upgrade(continuation_slice);
-            let continuation_slice = continuation_slice_mut.deref_mut();
-
-            self.header_slices
-                .set_slice_status(continuation_slice, HeaderSliceStatus::Verified);
-            continuation_slice.refetch_attempt = 0;
-            self.canonical_range.end = continuation_slice.block_num_range().end;
-            true
-        } else {
-            false
-        }
+        self.verify_slices_link(&continuation_slice, &end_slice)?;
+
+        let mut continuation_slice_mut = RwLockUpgradableReadGuard::upgrade(continuation_slice);
+        let continuation_slice = continuation_slice_mut.deref_mut();
+
+        self.header_slices
+            .set_slice_status(continuation_slice, HeaderSliceStatus::Verified);
+        continuation_slice.refetch_attempt = 0;
+        self.canonical_range.end = continuation_slice.block_num_range().end;
+        Ok(())
     }
 
-    fn try_extend_fork(&mut self, continuation_slice_lock: Arc<RwLock<HeaderSlice>>) -> bool {
-        let Some(end_slice_lock) = self.find_fork_first_slice() else { return false };
+    fn try_extend_fork(
+        &mut self,
+        continuation_slice_lock: Arc<RwLock<HeaderSlice>>,
+    ) -> Result<(), Option<ValidationError>> {
+        let Some(end_slice_lock) = self.find_fork_first_slice() else { return Err(None) };
         let continuation_slice = continuation_slice_lock.upgradable_read();
         let end_slice = end_slice_lock.read();
 
-        if self.verify_slices_link(&end_slice, &continuation_slice) {
-            let mut continuation_slice_mut = RwLockUpgradableReadGuard::upgrade(continuation_slice);
-            let continuation_slice = continuation_slice_mut.deref_mut();
-
-            self.fork_header_slices
-                .set_slice_status(continuation_slice, HeaderSliceStatus::Verified);
-            continuation_slice.refetch_attempt = 0;
-            self.fork_range.start = continuation_slice.block_num_range().start;
-            true
-        } else {
-            false
-        }
+        self.verify_slices_link(&end_slice, &continuation_slice)?;
+
+        let mut continuation_slice_mut = RwLockUpgradableReadGuard::upgrade(continuation_slice);
+        let continuation_slice = continuation_slice_mut.deref_mut();
+
+        self.fork_header_slices
+            .set_slice_status(continuation_slice, HeaderSliceStatus::Verified);
+        continuation_slice.refetch_attempt = 0;
+        self.fork_range.start = continuation_slice.block_num_range().start;
+        Ok(())
     }
 
-    fn verify_slices_link(&self, child_slice: &HeaderSlice, parent_slice: &HeaderSlice) -> bool {
-        let Some(child_headers) = &child_slice.headers else { return false; };
-        let Some(parent_headers) = &parent_slice.headers else { return false; };
+    fn verify_slices_link(
+        &self,
+        child_slice: &HeaderSlice,
+        parent_slice: &HeaderSlice,
+    ) -> Result<(), Option<ValidationError>> {
+        let Some(child_headers) = &child_slice.headers else { return Err(None); };
+        let Some(parent_headers) = &parent_slice.headers else { return Err(None); };
 
-        let Some(child) = child_headers.first() else { return false };
-        let Some(parent) = parent_headers.last() else { return false };
+        let Some(child) = child_headers.first() else { return Err(None) };
+        let Some(parent) = parent_headers.last() else { return Err(None) };
 
         self.verifier
             .verify_link(child, parent, self.chain_config.chain_spec())
+            .map_err(Some)
+    }
+
+    /// Verify that the fork headers link one by one from the connection block to the fork end,
+    /// including across the slice boundaries.
+    /// On failure returns the start of the first slice which does not link.
+    fn verify_fork_chain(
+        &self,
+        connection_block_num: BlockNumber,
+    ) -> Result<(), (BlockNumber, ValidationError)> {
+        let chain_spec = self.chain_config.chain_spec();
+        let mut parent = Option::<BlockHeader>::None;
+        let mut num = connection_block_num;
+
+        while self.fork_range.contains(&num) {
+            let Some(slice_lock) = self.fork_header_slices.find_by_block_num(num) else {
+                warn!("verify_fork_chain invalid state: slice not found");
+                break;
+            };
+            let slice = slice_lock.read();
+
+            let Some(slice_headers) = slice.headers.as_ref() else {
+                warn!("verify_fork_chain invalid state: slice headers not present");
+                break;
+            };
+
+            let index = (num.0 - slice.start_block_num.0) as usize;
+            let headers = slice_headers.get(index..).unwrap_or_default();
+
+            let mut slice_parent = parent.as_ref();
+            for header in headers {
+                if let Some(slice_parent) = slice_parent {
+                    self.verifier
+                        .verify_link(header, slice_parent, chain_spec)
+                        .map_err(|error| (slice.start_block_num, error))?;
+                }
+                slice_parent = Some(header);
+            }
+
+            parent = headers.last().cloned().or(parent);
+            num = slice.block_num_range().end;
+        }
+
+        Ok(())
     }
 
     fn find_fork_connection_block_num(&self) -> Option<BlockNumber> {
@@ -412,6 +501,34 @@ impl ForkModeStage {
         self.pending_switch_to_fork_command = Some(command);
     }
 
+    /// Marks the fork slice starting at `slice_start` invalid, so that it is refetched
+    /// from another peer, and refetches the fork slices below it, which were linked to it.
+    /// The fork is discarded if it does not link right after its initial slice.
+    fn invalidate_fork_slice(&mut self, slice_start: BlockNumber, error: ValidationError) {
+        warn!(
+            "ForkModeStage: fork slice at {} does not link: {}",
+            slice_start.0, error
+        );
+
+        let slice_end = BlockNumber(slice_start.0 + HEADER_SLICE_SIZE as u64);
+        if slice_end >= self.fork_range.end {
+            self.discard_fork();
+            return;
+        }
+
+        self.fork_header_slices.for_each(|slice_lock| {
+            let mut slice = slice_lock.write();
+            if slice.start_block_num < slice_start {
+                self.refetch_fork_slice(slice.deref_mut());
+            } else if slice.start_block_num == slice_start {
+                slice.invalid_reason = Some(error.clone());
+                self.fork_header_slices
+                    .set_slice_status(slice.deref_mut(), HeaderSliceStatus::Invalid);
+            }
+        });
+        self.fork_range.start = slice_end;
+    }
+
     fn discard_fork(&mut self) {
         self.fork_header_slices.clear();
         self.fork_range.start = self.fork_range.end;
@@ -479,3 +596,176 @@ impl super::stage::Stage for ForkModeStage {
         Box::new(Self::can_proceed_check(self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{super::verification::header_slice_verifier::make_ethash_verifier, *};
+    use crate::sentry::chain_config::ChainsConfig;
+    use bytes::Bytes;
+
+    /// Headers following `parent`, with the difficulty the verifier expects,
+    /// unless it is off by `difficulty_delta` for the first header.
+    fn make_chain(
+        parent: &BlockHeader,
+        count: usize,
+        fork_id: u8,
+        difficulty_delta: u64,
+        chain_config: &ChainConfig,
+    ) -> Vec<BlockHeader> {
+        let verifier = make_ethash_verifier();
+        let mut headers = Vec::<BlockHeader>::with_capacity(count);
+        for i in 0..count {
+            let parent = headers.last().unwrap_or(parent);
+            let mut header = crate::models::BlockHeader::empty();
+            header.number = BlockNumber(parent.number().0 + 1);
+            header.parent_hash = parent.hash();
+            header.timestamp = parent.timestamp() + 13;
+            header.extra_data = Bytes::from(vec![fork_id]);
+            header.difficulty = match verifier.verify_link(
+                &BlockHeader::from(header.clone()),
+                parent,
+                chain_config.chain_spec(),
+            ) {
+                Err(ValidationError::WrongDifficulty { expected, .. }) => expected,
+                other => panic!("unexpected link verification result: {:?}", other),
+            };
+            if i == 0 {
+                header.difficulty += U256::from(difficulty_delta);
+            }
+            headers.push(BlockHeader::from(header));
+        }
+        headers
+    }
+
+    fn genesis() -> BlockHeader {
+        let mut header = crate::models::BlockHeader::empty();
+        header.difficulty = U256::from(0x400000000_u64);
+        BlockHeader::from(header)
+    }
+
+    fn make_stage(fork_headers: Vec<BlockHeader>, chain_config: ChainConfig) -> ForkModeStage {
+        let slices = fork_headers
+            .chunks(HEADER_SLICE_SIZE)
+            .map(|headers| HeaderSlice {
+                start_block_num: headers[0].number(),
+                status: HeaderSliceStatus::Verified,
+                headers: Some(headers.to_vec()),
+                ..Default::default()
+            })
+            .collect();
+
+        ForkModeStage::new(
+            Arc::new(HeaderSlices::empty(3)),
+            Arc::new(HeaderSlices::from_slices_vec(slices, None, None, None)),
+            chain_config,
+            Arc::new(make_ethash_verifier()),
+        )
+    }
+
+    fn slice_state(stage: &ForkModeStage, start: u64) -> (HeaderSliceStatus, bool, u16) {
+        let slice_lock = stage
+            .fork_header_slices
+            .find_by_start_block_num(BlockNumber(start))
+            .unwrap();
+        let slice = slice_lock.read();
+        (slice.status, slice.headers.is_some(), slice.refetch_attempt)
+    }
+
+    #[test]
+    fn fork_breaking_link_at_slice_boundary() {
+        let chain_config = ChainsConfig::new().unwrap().get("mainnet").unwrap();
+        let genesis = genesis();
+
+        // blocks 192.. follow another block 191 than the one in the first slice
+        let mut headers = vec![genesis.clone()];
+        headers.extend(make_chain(&genesis, 191, 0, 0, &chain_config));
+        let other = make_chain(&headers[190], 1, 1, 0, &chain_config);
+        headers.extend(make_chain(&other[0], 384, 1, 0, &chain_config));
+        assert_eq!(headers.len(), 3 * HEADER_SLICE_SIZE);
+
+        let mut stage = make_stage(headers, chain_config);
+        assert_eq!(stage.fork_range, BlockNumber(0)..BlockNumber(576));
+
+        let (slice_start, error) = stage.verify_fork_chain(BlockNumber(0)).unwrap_err();
+        assert_eq!(slice_start, BlockNumber(192));
+        assert!(is_fork_link_error(&error));
+
+        stage.invalidate_fork_slice(slice_start, error);
+        assert_eq!(stage.fork_range.start, BlockNumber(384));
+        assert_eq!(slice_state(&stage, 0), (HeaderSliceStatus::Empty, false, 1));
+        assert_eq!(
+            slice_state(&stage, 192),
+            (HeaderSliceStatus::Invalid, true, 0)
+        );
+        assert_eq!(
+            slice_state(&stage, 384),
+            (HeaderSliceStatus::Verified, true, 0)
+        );
+        assert!(!stage.is_done());
+
+        // the fork does not link right after its initial slice
+        let error = ValidationError::WrongParentHash {
+            expected: H256::zero(),
+            got: H256::zero(),
+        };
+        stage.invalidate_fork_slice(BlockNumber(384), error);
+        assert!(stage.is_done());
+    }
+
+    #[test]
+    fn fork_with_bad_difficulty() {
+        let chain_config = ChainsConfig::new().unwrap().get("mainnet").unwrap();
+        let genesis = genesis();
+
+        let mut headers = vec![genesis.clone()];
+        headers.extend(make_chain(&genesis, 199, 0, 0, &chain_config));
+        let parent = headers.last().unwrap().clone();
+        headers.extend(make_chain(&parent, 376, 0, 1, &chain_config));
+
+        let mut stage = make_stage(headers.clone(), chain_config.clone());
+        let (slice_start, error) = stage.verify_fork_chain(BlockNumber(0)).unwrap_err();
+        assert_eq!(slice_start, BlockNumber(192));
+        assert!(matches!(error, ValidationError::WrongDifficulty { .. }));
+        assert!(!is_fork_link_error(&error));
+
+        stage.invalidate_fork_slice(slice_start, error.clone());
+        assert_eq!(
+            slice_state(&stage, 192),
+            (HeaderSliceStatus::Invalid, true, 0)
+        );
+
+        // a continuation slice with a bad header is invalid, one from another branch is refetched
+        let stage = make_stage(headers, chain_config);
+        let slice_lock = stage
+            .fork_header_slices
+            .find_by_start_block_num(BlockNumber(384))
+            .unwrap();
+        ForkModeStage::reject_slice(
+            &stage.fork_header_slices,
+            slice_lock.write().deref_mut(),
+            Some(error),
+        );
+        assert_eq!(
+            slice_state(&stage, 384),
+            (HeaderSliceStatus::Invalid, true, 0)
+        );
+        assert!(slice_lock.read().invalid_reason.is_some());
+
+        let slice_lock = stage
+            .fork_header_slices
+            .find_by_start_block_num(BlockNumber(192))
+            .unwrap();
+        ForkModeStage::reject_slice(
+            &stage.fork_header_slices,
+            slice_lock.write().deref_mut(),
+            Some(ValidationError::WrongParentHash {
+                expected: H256::zero(),
+                got: H256::zero(),
+            }),
+        );
+        assert_eq!(
+            slice_state(&stage, 192),
+            (HeaderSliceStatus::Empty, false, 1)
+        );
+    }
+}
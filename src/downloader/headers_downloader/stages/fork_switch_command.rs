@@ -73,7 +73,8 @@ impl ForkSwitchCommand {
             // promote the status
             self.header_slices
                 .set_slice_status(slice, fork_slice.status);
-            slice.headers = fork_slice.headers.take();
+            let headers = self.fork_header_slices.take_slice_headers(fork_slice);
+            self.header_slices.set_slice_headers(slice, headers);
             slice.refetch_attempt = 0;
 
             num = BlockNumber(num.0 + slice.len() as u64);
@@ -99,7 +100,7 @@ impl ForkSwitchCommand {
             // reset the status
             self.header_slices
                 .set_slice_status(slice, HeaderSliceStatus::Empty);
-            slice.headers = None;
+            self.header_slices.set_slice_headers(slice, None);
             slice.refetch_attempt = 0;
 
             num = BlockNumber(num.0 + len as u64);
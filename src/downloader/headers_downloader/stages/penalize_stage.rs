@@ -1,24 +1,35 @@
 use super::headers::{
     header_slice_status_watch::HeaderSliceStatusWatch,
     header_slices::{HeaderSliceStatus, HeaderSlices},
+    peer_rotation::PeerRotation,
+};
+use crate::{
+    consensus::ValidationError,
+    sentry::{sentry_client::PeerId, sentry_client_reactor::*},
 };
-use crate::sentry::{sentry_client::PeerId, sentry_client_reactor::*};
 use parking_lot::RwLockUpgradableReadGuard;
 use std::{collections::HashSet, ops::DerefMut, sync::Arc};
 use tracing::*;
 
 /// Penalize peers for sending us headers that failed to verify, and mark the related slices as Empty for retry.
+/// Penalized peers are removed from the rotation.
 pub struct PenalizeStage {
     header_slices: Arc<HeaderSlices>,
     sentry: SentryClientReactorShared,
+    peer_rotation: Arc<PeerRotation>,
     pending_watch: HeaderSliceStatusWatch,
 }
 
 impl PenalizeStage {
-    pub fn new(header_slices: Arc<HeaderSlices>, sentry: SentryClientReactorShared) -> Self {
+    pub fn new(
+        header_slices: Arc<HeaderSlices>,
+        sentry: SentryClientReactorShared,
+        peer_rotation: Arc<PeerRotation>,
+    ) -> Self {
         Self {
             header_slices: header_slices.clone(),
             sentry,
+            peer_rotation,
             pending_watch: HeaderSliceStatusWatch::new(
                 HeaderSliceStatus::Invalid,
                 header_slices,
@@ -52,8 +63,19 @@ impl PenalizeStage {
         self.header_slices.for_each(|slice_lock| {
             let slice = slice_lock.read();
             if slice.status == HeaderSliceStatus::Invalid {
+                if let Some(reason) = &slice.invalid_reason {
+                    if !is_peer_fault(reason) {
+                        debug!("PenalizeStage: not penalizing for slice at {}: {}", slice.start_block_num.0, reason);
+                        return;
+                    }
+                }
                 match slice.from_peer_id {
-                    Some(from_peer_id) => { peers.insert(from_peer_id); }
+                    Some(from_peer_id) => {
+                        if let Some(reason) = &slice.invalid_reason {
+                            debug!("PenalizeStage: peer {:?} sent an invalid slice at {}: {}", from_peer_id, slice.start_block_num.0, reason);
+                        }
+                        peers.insert(from_peer_id);
+                    }
                     None => warn!("PenalizeStage: got an invalid headers slice from an unknown peer starting at: {:?}", slice.start_block_num),
                 }
             }
@@ -68,7 +90,9 @@ impl PenalizeStage {
                 let mut slice = RwLockUpgradableReadGuard::upgrade(slice);
                 self.header_slices
                     .set_slice_status(slice.deref_mut(), HeaderSliceStatus::Empty);
-                slice.headers = None;
+                self.header_slices
+                    .set_slice_headers(slice.deref_mut(), None);
+                slice.invalid_reason = None;
             }
         });
     }
@@ -76,6 +100,7 @@ impl PenalizeStage {
     async fn penalize_peers(&self, peers: HashSet<PeerId>) -> anyhow::Result<()> {
         let sentry = self.sentry.read().await;
         for peer_id in peers {
+            self.peer_rotation.penalize(peer_id);
             sentry.penalize_peer(peer_id).await?;
         }
         Ok(())
@@ -87,6 +112,17 @@ impl PenalizeStage {
     }
 }
 
+/// Whether a verification failure proves the sending peer misbehaved.
+///
+/// Headers from the future may be fine once our clock catches up, and an unknown parent
+/// only means we are missing data, so these are retried without a penalty.
+fn is_peer_fault(reason: &ValidationError) -> bool {
+    !matches!(
+        reason,
+        ValidationError::FutureBlock { .. } | ValidationError::UnknownParent { .. }
+    )
+}
+
 #[async_trait::async_trait]
 impl super::stage::Stage for PenalizeStage {
     async fn execute(&mut self) -> anyhow::Result<()> {
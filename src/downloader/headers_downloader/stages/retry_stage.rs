@@ -1,22 +1,37 @@
 use super::headers::{
     header_slice_status_watch::HeaderSliceStatusWatch,
     header_slices::{HeaderSlice, HeaderSliceStatus, HeaderSlices},
+    peer_rotation::PeerRotation,
+    request_batching::RequestBatching,
 };
+use crate::sentry::{sentry_client::PeerId, sentry_client_reactor::*};
 use parking_lot::RwLockUpgradableReadGuard;
-use std::{ops::DerefMut, sync::Arc, time, time::Duration};
+use std::{collections::HashSet, ops::DerefMut, sync::Arc, time, time::Duration};
 use tracing::*;
 
 /// Handles timeouts. If a slice is Waiting for too long, we need to request it again.
-/// Status is updated to Empty (the slice will be processed by the FetchRequestStage again).
+/// Status is updated to Empty (the slice will be processed by the FetchRequestStage again, and sent to another peer).
+/// Peers that don't respond several times in a row are penalized.
 pub struct RetryStage {
     header_slices: Arc<HeaderSlices>,
+    sentry: SentryClientReactorShared,
+    peer_rotation: Arc<PeerRotation>,
+    request_batching: Arc<RequestBatching>,
     pending_watch: HeaderSliceStatusWatch,
 }
 
 impl RetryStage {
-    pub fn new(header_slices: Arc<HeaderSlices>) -> Self {
+    pub fn new(
+        header_slices: Arc<HeaderSlices>,
+        sentry: SentryClientReactorShared,
+        peer_rotation: Arc<PeerRotation>,
+        request_batching: Arc<RequestBatching>,
+    ) -> Self {
         Self {
             header_slices: header_slices.clone(),
+            sentry,
+            peer_rotation,
+            request_batching,
             pending_watch: HeaderSliceStatusWatch::new(
                 HeaderSliceStatus::Waiting,
                 header_slices,
@@ -31,22 +46,40 @@ impl RetryStage {
         // don't retry more often than once per 1 sec
         tokio::time::sleep(Duration::from_secs(1)).await;
 
-        let count = self.reset_pending()?;
+        let (count, unresponsive_peers) = self.reset_pending()?;
         if count > 0 {
             debug!("RetryStage: did reset {} slices for retry", count);
+            self.request_batching.on_timeout();
+        }
+        if !unresponsive_peers.is_empty() {
+            warn!(
+                "RetryStage: penalizing {} unresponsive peers: {:?}",
+                unresponsive_peers.len(),
+                unresponsive_peers
+            );
+            let sentry = self.sentry.read().await;
+            for peer_id in unresponsive_peers {
+                sentry.penalize_peer(peer_id).await?;
+            }
         }
         Ok(())
     }
 
-    fn reset_pending(&self) -> anyhow::Result<usize> {
+    fn reset_pending(&self) -> anyhow::Result<(usize, HashSet<PeerId>)> {
         let now = time::Instant::now();
         let mut count: usize = 0;
+        let mut unresponsive_peers = HashSet::<PeerId>::new();
         self.header_slices.for_each(|slice_lock| {
             let slice = slice_lock.upgradable_read();
             if (slice.status == HeaderSliceStatus::Waiting)
                 && RetryStage::is_waiting_timeout_expired(&slice, &now)
             {
                 let mut slice = RwLockUpgradableReadGuard::upgrade(slice);
+                if let Some(peer_id) = slice.to_peer_id {
+                    if self.peer_rotation.on_timeout(peer_id) {
+                        unresponsive_peers.insert(peer_id);
+                    }
+                }
                 slice.request_time = None;
                 slice.request_attempt += 1;
                 self.header_slices
@@ -54,7 +87,7 @@ impl RetryStage {
                 count += 1;
             }
         });
-        Ok(count)
+        Ok((count, unresponsive_peers))
     }
 
     fn is_waiting_timeout_expired(slice: &HeaderSlice, now: &time::Instant) -> bool {
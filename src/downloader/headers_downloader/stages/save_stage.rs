@@ -1,6 +1,7 @@
 use super::headers::{
     header::BlockHeader,
     header_slice_status_watch::HeaderSliceStatusWatch,
+    header_slice_store::HeaderSliceStore,
     header_slices::{HeaderSlice, HeaderSliceStatus, HeaderSlices},
 };
 use crate::{
@@ -11,6 +12,7 @@ use crate::{
     models::*,
 };
 use anyhow::format_err;
+use bytesize::ByteSize;
 use mdbx::{EnvironmentKind, RW};
 use parking_lot::RwLock;
 use std::{
@@ -36,6 +38,7 @@ where
     db_transaction: &'tx MdbxTransaction<'db, RW, E>,
     order: SaveOrder,
     is_canonical_chain: bool,
+    slice_store: Option<Arc<HeaderSliceStore>>,
     pending_watch: HeaderSliceStatusWatch,
     remaining_count: Arc<AtomicUsize>,
 }
@@ -55,6 +58,7 @@ where
             db_transaction,
             order,
             is_canonical_chain,
+            slice_store: None,
             pending_watch: HeaderSliceStatusWatch::new(
                 HeaderSliceStatus::Verified,
                 header_slices,
@@ -64,6 +68,13 @@ where
         }
     }
 
+    /// Also stores every saved slice, which keeps it across a restart
+    /// until the transaction saving it is committed.
+    pub fn set_slice_store(&mut self, slice_store: Arc<HeaderSliceStore>) -> &mut Self {
+        self.slice_store = Some(slice_store);
+        self
+    }
+
     pub async fn execute(&mut self) -> anyhow::Result<()> {
         // initially remaining_count = 0, so we wait for any verified slices to try to save them
         // since we want to save headers sequentially, there might be some remaining slices
@@ -82,6 +93,15 @@ where
         };
         debug!("SaveStage: saved {} slices", saved_count);
 
+        let evicted_count = self.header_slices.evict_saved_headers();
+        if evicted_count > 0 {
+            debug!(
+                "SaveStage: evicted headers of {} saved slices, memory usage {}",
+                evicted_count,
+                ByteSize(self.header_slices.mem_usage() as u64)
+            );
+        }
+
         self.set_remaining_count(pending_count - saved_count);
 
         Ok(())
@@ -168,6 +188,10 @@ where
 
         let mut slice = slice_lock.write();
 
+        if let Some(slice_store) = &self.slice_store {
+            slice_store.save(slice.start_block_num, HeaderSliceStatus::Verified, &headers)?;
+        }
+
         // put the detached headers back
         slice.headers = Some(headers);
 
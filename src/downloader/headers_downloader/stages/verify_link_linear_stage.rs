@@ -5,9 +5,9 @@ use super::{
         header_slices,
         header_slices::{HeaderSlice, HeaderSliceStatus, HeaderSlices},
     },
-    verification::header_slice_verifier::HeaderSliceVerifier,
+    verification::header_slice_verifier::{is_fork_link_error, HeaderSliceVerifier},
 };
-use crate::{models::*, sentry::chain_config::ChainConfig};
+use crate::{consensus::ValidationError, models::*, sentry::chain_config::ChainConfig};
 use parking_lot::{RwLock, RwLockUpgradableReadGuard};
 use std::{
     ops::{ControlFlow, DerefMut},
@@ -141,49 +141,77 @@ impl VerifyLinkLinearStage {
     fn verify_pending_slice(&mut self, slice_lock: Arc<RwLock<HeaderSlice>>) -> bool {
         let slice = slice_lock.upgradable_read();
 
-        let is_verified = self.verify_slice_link(&slice, &self.last_verified_header);
+        let result = self.verify_slice_link(&slice, &self.last_verified_header);
 
         let mut slice = RwLockUpgradableReadGuard::upgrade(slice);
-        if is_verified {
-            self.header_slices
-                .set_slice_status(slice.deref_mut(), HeaderSliceStatus::Verified);
-            if let Some(last_verified_header) = slice.headers.as_ref().unwrap().iter().last() {
-                self.last_verified_header = Some(last_verified_header.clone());
+        match result {
+            Ok(()) => {
+                self.header_slices
+                    .set_slice_status(slice.deref_mut(), HeaderSliceStatus::Verified);
+                if let Some(last_verified_header) = slice.headers.as_ref().unwrap().iter().last() {
+                    self.last_verified_header = Some(last_verified_header.clone());
+                }
+                true
+            }
+            // the slice follows the parent hash, but breaks the rules: refetch it from another peer
+            Err(Some(error)) if !is_fork_link_error(&error) => {
+                debug!(
+                    "VerifyLinkLinearStage: slice at {} is invalid: {}",
+                    slice.start_block_num.0, error
+                );
+                slice.invalid_reason = Some(error);
+                self.header_slices
+                    .set_slice_status(slice.deref_mut(), HeaderSliceStatus::Invalid);
+                false
+            }
+            Err(error) => {
+                if let Some(error) = error {
+                    debug!(
+                        "VerifyLinkLinearStage: slice at {} does not link: {}",
+                        slice.start_block_num.0, error
+                    );
+                }
+                self.header_slices
+                    .set_slice_status(slice.deref_mut(), self.invalid_status);
+                false
             }
-        } else {
-            self.header_slices
-                .set_slice_status(slice.deref_mut(), self.invalid_status);
         }
-
-        is_verified
     }
 
-    fn verify_slice_link(&self, slice: &HeaderSlice, parent: &Option<BlockHeader>) -> bool {
+    fn verify_slice_link(
+        &self,
+        slice: &HeaderSlice,
+        parent: &Option<BlockHeader>,
+    ) -> Result<(), Option<ValidationError>> {
         let Some(headers) = slice.headers.as_ref() else {
-            return false;
+            return Err(None);
         };
 
         if headers.is_empty() {
-            return false;
+            return Err(None);
         }
         if headers.len() != header_slices::HEADER_SLICE_SIZE {
-            return false;
+            return Err(None);
         }
 
         let child = &headers[0];
 
         // for the genesis header we just verify its hash
         if child.number() == BlockNumber(0) {
-            return child.hash() == self.chain_config.genesis_block_hash();
+            return if child.hash() == self.chain_config.genesis_block_hash() {
+                Ok(())
+            } else {
+                Err(None)
+            };
         }
         // otherwise we expect that we have a verified parent
-        if parent.is_none() {
-            return false;
-        }
-        let parent = parent.as_ref().unwrap();
+        let Some(parent) = parent.as_ref() else {
+            return Err(None);
+        };
 
         self.verifier
             .verify_link(child, parent, self.chain_config.chain_spec())
+            .map_err(Some)
     }
 
     pub fn can_proceed_check(&self) -> impl Fn() -> bool {
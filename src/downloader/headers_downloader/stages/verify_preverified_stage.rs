@@ -136,7 +136,7 @@ impl VerifyPreverifiedStage {
             return false;
         }
 
-        header_slice_verifier::verify_slice_is_linked_by_parent_hash(headers)
+        header_slice_verifier::verify_slice_is_linked_by_parent_hash(headers).is_ok()
     }
 
     fn preverified_hash(&self, block_num: u64) -> Option<&H256> {
@@ -5,9 +5,12 @@ use super::{
     },
     verification::{header_slice_verifier::HeaderSliceVerifier, parallel::map_parallel},
 };
-use crate::sentry::chain_config::ChainConfig;
+use crate::{
+    consensus::{Clock, SystemClock, ValidationError},
+    sentry::chain_config::ChainConfig,
+};
 use parking_lot::RwLock;
-use std::{ops::DerefMut, sync::Arc, time::SystemTime};
+use std::{ops::DerefMut, sync::Arc};
 use tracing::*;
 
 /// Verifies the block structure and sequence rules in each slice and sets VerifiedInternally status.
@@ -58,23 +61,35 @@ impl VerifySlicesStage {
 
             let slices_verified = self.verify_slices_parallel(&slices_batch).await;
 
-            for (i, slice_lock) in slices_batch.iter().enumerate() {
+            for (slice_lock, result) in slices_batch.iter().zip(slices_verified) {
                 let mut slice = slice_lock.write();
-                let is_verified = slices_verified[i];
 
-                if is_verified {
-                    self.header_slices
-                        .set_slice_status(slice.deref_mut(), HeaderSliceStatus::VerifiedInternally);
-                } else {
-                    self.header_slices
-                        .set_slice_status(slice.deref_mut(), HeaderSliceStatus::Invalid);
+                match result {
+                    Ok(()) => {
+                        self.header_slices.set_slice_status(
+                            slice.deref_mut(),
+                            HeaderSliceStatus::VerifiedInternally,
+                        );
+                    }
+                    Err(reason) => {
+                        debug!(
+                            "VerifySlicesStage: slice at {} is invalid: {:?}",
+                            slice.start_block_num.0, reason
+                        );
+                        slice.invalid_reason = reason;
+                        self.header_slices
+                            .set_slice_status(slice.deref_mut(), HeaderSliceStatus::Invalid);
+                    }
                 }
             }
         }
     }
 
-    async fn verify_slices_parallel(&self, slices: &[Arc<RwLock<HeaderSlice>>]) -> Vec<bool> {
-        map_parallel(Vec::from(slices), |slice_lock| -> bool {
+    async fn verify_slices_parallel(
+        &self,
+        slices: &[Arc<RwLock<HeaderSlice>>],
+    ) -> Vec<Result<(), Option<ValidationError>>> {
+        map_parallel(Vec::from(slices), |slice_lock| {
             let mut slice = slice_lock.write();
             Self::prepare_slice_hashes(&mut slice);
             self.verify_slice(&slice)
@@ -90,24 +105,29 @@ impl VerifySlicesStage {
         }
     }
 
-    fn now_timestamp() -> u64 {
-        SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
+    fn max_timestamp(&self) -> u64 {
+        let allowed_future_drift = self
+            .chain_config
+            .chain_spec()
+            .consensus
+            .allowed_future_drift;
+        SystemClock.now().saturating_add(allowed_future_drift)
     }
 
-    fn verify_slice(&self, slice: &HeaderSlice) -> bool {
+    /// Fails with `None` if the slice has no headers to verify.
+    fn verify_slice(&self, slice: &HeaderSlice) -> Result<(), Option<ValidationError>> {
         let Some(headers) = slice.headers.as_ref() else {
-            return false;
+            return Err(None);
         };
 
-        self.verifier.verify_slice(
-            headers,
-            slice.start_block_num,
-            Self::now_timestamp(),
-            self.chain_config.chain_spec(),
-        )
+        self.verifier
+            .verify_slice(
+                headers,
+                slice.start_block_num,
+                self.max_timestamp(),
+                self.chain_config.chain_spec(),
+            )
+            .map_err(Some)
     }
 
     pub fn can_proceed_check(&self) -> impl Fn() -> bool {
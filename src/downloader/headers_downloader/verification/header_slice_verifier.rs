@@ -2,7 +2,10 @@ use super::{
     super::headers::header::BlockHeader, preverified_hashes_config::PreverifiedHashesConfig,
 };
 use crate::{
-    consensus::difficulty::{canonical_difficulty, BlockDifficultyBombData},
+    consensus::{
+        difficulty::{canonical_difficulty, BlockDifficultyBombData},
+        ValidationError,
+    },
     models::{switch_is_active, BlockNumber, ChainSpec, SealVerificationParams, EMPTY_LIST_HASH},
 };
 use std::fmt::Debug;
@@ -13,7 +16,7 @@ pub trait HeaderSliceVerifier: Send + Sync + Debug {
         child: &BlockHeader,
         parent: &BlockHeader,
         chain_spec: &ChainSpec,
-    ) -> bool;
+    ) -> Result<(), ValidationError>;
 
     fn verify_slice(
         &self,
@@ -21,7 +24,7 @@ pub trait HeaderSliceVerifier: Send + Sync + Debug {
         start_block_num: BlockNumber,
         max_timestamp: u64,
         chain_spec: &ChainSpec,
-    ) -> bool;
+    ) -> Result<(), ValidationError>;
 
     fn preverified_hashes_config(
         &self,
@@ -42,12 +45,12 @@ impl HeaderSliceVerifier for EthashHeaderSliceVerifier {
         child: &BlockHeader,
         parent: &BlockHeader,
         chain_spec: &ChainSpec,
-    ) -> bool {
-        verify_link_by_parent_hash(child, parent)
-            && verify_link_block_nums(child, parent)
-            && verify_link_timestamps(child, parent)
-            && verify_link_difficulties(child, parent, chain_spec)
-            && verify_link_pow(child, parent)
+    ) -> Result<(), ValidationError> {
+        verify_link_by_parent_hash(child, parent)?;
+        verify_link_block_nums(child, parent)?;
+        verify_link_timestamps(child, parent)?;
+        verify_link_difficulties(child, parent, chain_spec)?;
+        verify_link_pow(child, parent)
     }
 
     fn verify_slice(
@@ -56,12 +59,12 @@ impl HeaderSliceVerifier for EthashHeaderSliceVerifier {
         start_block_num: BlockNumber,
         max_timestamp: u64,
         chain_spec: &ChainSpec,
-    ) -> bool {
-        verify_slice_is_linked_by_parent_hash(headers)
-            && verify_slice_block_nums(headers, start_block_num)
-            && verify_slice_timestamps(headers, max_timestamp)
-            && verify_slice_difficulties(headers, chain_spec)
-            && verify_slice_pow(headers)
+    ) -> Result<(), ValidationError> {
+        verify_slice_is_linked_by_parent_hash(headers)?;
+        verify_slice_block_nums(headers, start_block_num)?;
+        verify_slice_timestamps(headers, max_timestamp)?;
+        verify_slice_difficulties(headers, chain_spec)?;
+        verify_slice_pow(headers)
     }
 
     fn preverified_hashes_config(
@@ -72,29 +75,56 @@ impl HeaderSliceVerifier for EthashHeaderSliceVerifier {
     }
 }
 
-fn verify_link_by_parent_hash(child: &BlockHeader, parent: &BlockHeader) -> bool {
+fn verify_link_by_parent_hash(
+    child: &BlockHeader,
+    parent: &BlockHeader,
+) -> Result<(), ValidationError> {
     let given_parent_hash = child.parent_hash();
     let expected_parent_hash = parent.hash();
-    given_parent_hash == expected_parent_hash
+    if given_parent_hash != expected_parent_hash {
+        return Err(ValidationError::WrongParentHash {
+            expected: expected_parent_hash,
+            got: given_parent_hash,
+        });
+    }
+    Ok(())
 }
 
-fn verify_link_block_nums(child: &BlockHeader, parent: &BlockHeader) -> bool {
-    let given_block_num = child.number().0;
-    let expected_block_num = parent.number().0 + 1;
-    given_block_num == expected_block_num
+fn verify_link_block_nums(
+    child: &BlockHeader,
+    parent: &BlockHeader,
+) -> Result<(), ValidationError> {
+    let given_block_num = child.number();
+    let expected_block_num = BlockNumber(parent.number().0 + 1);
+    if given_block_num != expected_block_num {
+        return Err(ValidationError::WrongBlockNumber {
+            expected: expected_block_num,
+            got: given_block_num,
+        });
+    }
+    Ok(())
 }
 
-fn verify_link_timestamps(child: &BlockHeader, parent: &BlockHeader) -> bool {
+fn verify_link_timestamps(
+    child: &BlockHeader,
+    parent: &BlockHeader,
+) -> Result<(), ValidationError> {
     let parent_timestamp = parent.timestamp();
     let child_timestamp = child.timestamp();
-    parent_timestamp < child_timestamp
+    if parent_timestamp >= child_timestamp {
+        return Err(ValidationError::InvalidTimestamp {
+            parent: parent_timestamp,
+            current: child_timestamp,
+        });
+    }
+    Ok(())
 }
 
 fn verify_link_difficulties(
     child: &BlockHeader,
     parent: &BlockHeader,
     chain_spec: &ChainSpec,
-) -> bool {
+) -> Result<(), ValidationError> {
     let (&byzantium_formula, &homestead_formula, difficulty_bomb) =
         match &chain_spec.consensus.seal_verification {
             SealVerificationParams::Ethash {
@@ -123,12 +153,24 @@ fn verify_link_difficulties(
                 delay_to: bomb.get_delay_to(child.number()),
             }),
     );
-    given_child_difficulty == expected_child_difficulty
+    if given_child_difficulty != expected_child_difficulty {
+        return Err(ValidationError::WrongDifficulty {
+            expected: expected_child_difficulty,
+            got: given_child_difficulty,
+        });
+    }
+    Ok(())
 }
 
-fn verify_link_pow(_child: &BlockHeader, _parent: &BlockHeader) -> bool {
+fn verify_link_pow(_child: &BlockHeader, _parent: &BlockHeader) -> Result<(), ValidationError> {
     // TODO: verify_link_pow
-    true
+    Ok(())
+}
+
+/// Whether a failed link only shows that the child is on another branch,
+/// rather than a header breaking the chain rules.
+pub fn is_fork_link_error(error: &ValidationError) -> bool {
+    matches!(error, ValidationError::WrongParentHash { .. })
 }
 
 fn enumerate_sequential_pairs(
@@ -140,55 +182,74 @@ fn enumerate_sequential_pairs(
 }
 
 /// Verify that all blocks in the slice are linked by the parent_hash field.
-pub fn verify_slice_is_linked_by_parent_hash(headers: &[BlockHeader]) -> bool {
+pub fn verify_slice_is_linked_by_parent_hash(
+    headers: &[BlockHeader],
+) -> Result<(), ValidationError> {
     enumerate_sequential_pairs(headers)
-        .all(|(parent, child)| verify_link_by_parent_hash(child, parent))
+        .try_for_each(|(parent, child)| verify_link_by_parent_hash(child, parent))
 }
 
 /// Verify that block numbers start from the expected
 /// slice.start_block_num and increase sequentially.
-fn verify_slice_block_nums(headers: &[BlockHeader], start_block_num: BlockNumber) -> bool {
+fn verify_slice_block_nums(
+    headers: &[BlockHeader],
+    start_block_num: BlockNumber,
+) -> Result<(), ValidationError> {
     if headers.is_empty() {
-        return true;
+        return Ok(());
     }
 
     for (parent, child) in enumerate_sequential_pairs(headers) {
-        if !verify_link_block_nums(child, parent) {
-            return false;
-        }
+        verify_link_block_nums(child, parent)?;
     }
 
     // verify the first block number
     let first = &headers[0];
     let first_block_num = first.number();
-    first_block_num == start_block_num
+    if first_block_num != start_block_num {
+        return Err(ValidationError::WrongBlockNumber {
+            expected: start_block_num,
+            got: first_block_num,
+        });
+    }
+    Ok(())
 }
 
 /// Verify that timestamps are in the past and increase monotonically.
-fn verify_slice_timestamps(headers: &[BlockHeader], max_timestamp: u64) -> bool {
+fn verify_slice_timestamps(
+    headers: &[BlockHeader],
+    max_timestamp: u64,
+) -> Result<(), ValidationError> {
     if headers.is_empty() {
-        return true;
+        return Ok(());
     }
 
     for (parent, child) in enumerate_sequential_pairs(headers) {
-        if !verify_link_timestamps(child, parent) {
-            return false;
-        }
+        verify_link_timestamps(child, parent)?;
     }
 
     let last = headers.last().unwrap();
     let last_timestamp = last.timestamp();
-    last_timestamp < max_timestamp
+    if last_timestamp >= max_timestamp {
+        return Err(ValidationError::FutureBlock {
+            now: max_timestamp,
+            got: last_timestamp,
+        });
+    }
+    Ok(())
 }
 
 /// Verify that difficulty field is calculated properly.
-fn verify_slice_difficulties(headers: &[BlockHeader], chain_spec: &ChainSpec) -> bool {
+fn verify_slice_difficulties(
+    headers: &[BlockHeader],
+    chain_spec: &ChainSpec,
+) -> Result<(), ValidationError> {
     enumerate_sequential_pairs(headers)
-        .all(|(parent, child)| verify_link_difficulties(child, parent, chain_spec))
+        .try_for_each(|(parent, child)| verify_link_difficulties(child, parent, chain_spec))
 }
 
 /// Verify the headers proof-of-work.
-fn verify_slice_pow(_headers: &[BlockHeader]) -> bool {
+fn verify_slice_pow(_headers: &[BlockHeader]) -> Result<(), ValidationError> {
     // TODO: verify_slice_pow
-    true
+    Ok(())
 }
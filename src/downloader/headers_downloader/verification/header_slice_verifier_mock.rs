@@ -2,7 +2,10 @@ use super::{
     super::headers::header::BlockHeader, header_slice_verifier::HeaderSliceVerifier,
     preverified_hashes_config::PreverifiedHashesConfig,
 };
-use crate::models::{BlockNumber, ChainSpec};
+use crate::{
+    consensus::ValidationError,
+    models::{BlockNumber, ChainSpec},
+};
 use std::fmt::{Debug, Formatter};
 
 pub struct HeaderSliceVerifierMock {
@@ -27,20 +30,28 @@ impl HeaderSliceVerifier for HeaderSliceVerifierMock {
         child: &BlockHeader,
         parent: &BlockHeader,
         _chain_spec: &ChainSpec,
-    ) -> bool {
+    ) -> Result<(), ValidationError> {
         let child_id = (self.header_id)(child);
         let parent_id = (self.header_id)(parent);
-        child_id == parent_id + 1
+        if child_id != parent_id + 1 {
+            return Err(ValidationError::WrongParentHash {
+                expected: parent.hash(),
+                got: child.parent_hash(),
+            });
+        }
+        verify_block_num(child, BlockNumber(parent.number().0 + 1))
     }
 
     fn verify_slice(
         &self,
-        _headers: &[BlockHeader],
-        _start_block_num: BlockNumber,
+        headers: &[BlockHeader],
+        start_block_num: BlockNumber,
         _max_timestamp: u64,
         _chain_spec: &ChainSpec,
-    ) -> bool {
-        true
+    ) -> Result<(), ValidationError> {
+        headers.iter().enumerate().try_for_each(|(i, header)| {
+            verify_block_num(header, BlockNumber(start_block_num.0 + i as u64))
+        })
     }
 
     fn preverified_hashes_config(
@@ -50,3 +61,13 @@ impl HeaderSliceVerifier for HeaderSliceVerifierMock {
         Ok(PreverifiedHashesConfig::empty())
     }
 }
+
+fn verify_block_num(header: &BlockHeader, expected: BlockNumber) -> Result<(), ValidationError> {
+    if header.number() != expected {
+        return Err(ValidationError::WrongBlockNumber {
+            expected,
+            got: header.number(),
+        });
+    }
+    Ok(())
+}
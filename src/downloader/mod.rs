@@ -1,9 +1,15 @@
+pub mod body_downloader;
+pub mod chain_tip_watchdog;
+mod error;
 pub mod opts;
+pub mod sentry_request_server;
 pub mod sentry_status_provider;
+pub mod tx_fetcher;
 pub mod ui;
 
 mod headers_downloader;
 
+pub use self::error::DownloadError;
 pub use headers_downloader::{
     downloader::{
         Downloader as HeadersDownloader, DownloaderReport as HeadersDownloaderReport,
@@ -11,4 +17,5 @@ pub use headers_downloader::{
         DownloaderUnwindRequest as HeadersDownloaderUnwindRequest,
     },
     verification::header_slice_verifier,
+    HeaderSliceStore,
 };
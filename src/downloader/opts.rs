@@ -14,9 +14,68 @@ pub struct Opts {
         default_value = "100000"
     )]
     pub headers_batch_size: usize,
+    #[clap(
+        long = "downloader.bodies-batch-size",
+        help = "How many block bodies to download per stage run.",
+        default_value = "10000"
+    )]
+    pub bodies_batch_size: usize,
+    #[clap(
+        long = "downloader.stall-timeout",
+        help = "Seconds without new canonical blocks while peers are ahead, after which the sync is considered stalled. 0 disables the watchdog.",
+        default_value = "300"
+    )]
+    pub stall_timeout_secs: u64,
+    #[clap(
+        long = "downloader.restart-on-stall",
+        help = "Restart the headers downloader when the sync is stalled."
+    )]
+    pub restart_on_stall: bool,
+    #[clap(
+        long = "downloader.headers-request-max-slices",
+        help = "Maximum number of header slices to request from a peer at once, up to the 5 that fit into a peer response. The number adapts to peer response sizes and latency.",
+        default_value = "5"
+    )]
+    pub headers_request_max_slices: usize,
+    #[clap(
+        long = "downloader.headers-request-target-latency",
+        help = "Milliseconds within which a peer is expected to respond, otherwise fewer header slices are requested at once.",
+        default_value = "2000"
+    )]
+    pub headers_request_target_latency_ms: u64,
+}
+
+/// How many header slices are requested from a peer at once.
+#[derive(Clone, Copy, Debug)]
+pub struct RequestBatchingOpts {
+    pub max_slices: usize,
+    pub target_latency: std::time::Duration,
+}
+
+impl Default for RequestBatchingOpts {
+    fn default() -> Self {
+        Self {
+            max_slices: 1,
+            target_latency: std::time::Duration::from_secs(2),
+        }
+    }
 }
 
 impl Opts {
+    pub fn stall_timeout(&self) -> Option<std::time::Duration> {
+        (self.stall_timeout_secs > 0)
+            .then(|| std::time::Duration::from_secs(self.stall_timeout_secs))
+    }
+
+    pub fn headers_request_batching(&self) -> RequestBatchingOpts {
+        RequestBatchingOpts {
+            max_slices: self.headers_request_max_slices,
+            target_latency: std::time::Duration::from_millis(
+                self.headers_request_target_latency_ms,
+            ),
+        }
+    }
+
     pub fn headers_mem_limit(&self) -> usize {
         byte_unit::n_mib_bytes!(self.headers_mem_limit_mb as u128)
             .try_into()
@@ -0,0 +1,342 @@
+use crate::{
+    accessors::{chain, header_cache::HeaderCache},
+    kv::{mdbx::*, tables},
+    models::*,
+    sentry::{
+        block_id::BlockId,
+        messages::{
+            BlockBodiesMessage, BlockBodyType, BlockHeadersMessage, BlockReceipts, EthMessageId,
+            GetBlockHeadersMessageParams, Message, ReceiptsMessage,
+        },
+        sentry_client::{PeerFilter, PeerId},
+        sentry_client_reactor::*,
+    },
+};
+use std::{ops::Deref, sync::Arc};
+use tokio_stream::StreamExt;
+use tracing::*;
+
+/// Responses are cut after exceeding this size, the peer will request the rest.
+const SOFT_RESPONSE_LIMIT: usize = 2 * 1024 * 1024;
+
+const MAX_HEADERS_SERVE: usize = 1024;
+const MAX_BODIES_SERVE: usize = 1024;
+const MAX_RECEIPTS_SERVE: usize = 1024;
+
+/// Answers the data requests of the peers from the local database.
+#[derive(Debug)]
+pub struct SentryRequestServer<DB> {
+    db: Arc<DB>,
+    sentry: SentryClientReactorShared,
+    header_cache: Arc<HeaderCache>,
+}
+
+impl<DB, E> SentryRequestServer<DB>
+where
+    DB: Deref<Target = MdbxEnvironment<E>> + Send + Sync,
+    E: EnvironmentKind,
+{
+    pub fn new(
+        db: Arc<DB>,
+        sentry: SentryClientReactorShared,
+        header_cache: Arc<HeaderCache>,
+    ) -> Self {
+        Self {
+            db,
+            sentry,
+            header_cache,
+        }
+    }
+
+    pub async fn run(&self) -> anyhow::Result<()> {
+        let mut requests = {
+            let sentry = self.sentry.read().await;
+            sentry
+                .receive_messages(EthMessageId::GetBlockHeaders)?
+                .merge(sentry.receive_messages(EthMessageId::GetBlockBodies)?)
+                .merge(sentry.receive_messages(EthMessageId::GetReceipts)?)
+        };
+
+        while let Some(request) = requests.next().await {
+            let Some(peer_id) = request.from_peer_id else {
+                continue;
+            };
+
+            let eth_id = request.message.eth_id();
+            let response = match tokio::task::block_in_place(|| self.respond(request.message)) {
+                Ok(Some(response)) => response,
+                Ok(None) => continue,
+                Err(error) => {
+                    warn!(
+                        "SentryRequestServer: failed to serve {:?} to {:?}: {:?}",
+                        eth_id, peer_id, error
+                    );
+                    continue;
+                }
+            };
+
+            self.send_response(response, peer_id).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn send_response(&self, response: Message, peer_id: PeerId) -> anyhow::Result<()> {
+        let sentry = self.sentry.read().await;
+        let result = sentry.try_send_message(response, PeerFilter::PeerId(peer_id));
+        match result {
+            Err(error) => match error.downcast_ref::<SendMessageError>() {
+                Some(SendMessageError::SendQueueFull) => {
+                    debug!("SentryRequestServer: response send queue is full");
+                    Ok(())
+                }
+                _ => Err(error),
+            },
+            Ok(_) => Ok(()),
+        }
+    }
+
+    fn respond(&self, request: Message) -> anyhow::Result<Option<Message>> {
+        let tx = self.db.begin()?;
+        let response = match request {
+            Message::GetBlockHeaders(request) => Message::BlockHeaders(BlockHeadersMessage {
+                request_id: request.request_id,
+                headers: read_headers(&tx, &self.header_cache, &request.params)?,
+            }),
+            Message::GetBlockBodies(request) => Message::BlockBodies(BlockBodiesMessage {
+                request_id: request.request_id,
+                block_bodies: read_bodies(&tx, &request.block_hashes)?,
+            }),
+            Message::GetReceipts(request) => Message::Receipts(ReceiptsMessage {
+                request_id: request.request_id,
+                receipts: read_receipts(&tx, &request.block_hashes)?,
+            }),
+            _ => return Ok(None),
+        };
+        Ok(Some(response))
+    }
+}
+
+fn read_headers<E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, RO, E>,
+    header_cache: &HeaderCache,
+    params: &GetBlockHeadersMessageParams,
+) -> anyhow::Result<Vec<BlockHeader>> {
+    let first = match params.start_block {
+        BlockId::Hash(hash) => match tx.get(tables::HeaderNumber, hash)? {
+            Some(number) => header_cache.header(tx, number, hash)?,
+            None => None,
+        },
+        BlockId::Number(number) => header_cache.canonical_header(tx, number)?,
+    };
+    let Some(first) = first.filter(|_| params.limit > 0) else {
+        return Ok(vec![]);
+    };
+
+    let limit = std::cmp::min(params.limit as usize, MAX_HEADERS_SERVE);
+    let step = params.skip.saturating_add(1);
+
+    let mut size = 0;
+    let mut number = first.number;
+    let mut headers = vec![first];
+    while headers.len() < limit && size < SOFT_RESPONSE_LIMIT {
+        let next = if params.reverse != 0 {
+            number.0.checked_sub(step)
+        } else {
+            number.0.checked_add(step)
+        };
+        let Some(next) = next else {
+            break;
+        };
+        number = BlockNumber(next);
+
+        let Some(header) = header_cache.canonical_header(tx, number)? else {
+            break;
+        };
+        size += rlp::encode(&header).len();
+        headers.push(header);
+    }
+
+    Ok(headers)
+}
+
+fn read_bodies<K: TransactionKind, E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, K, E>,
+    hashes: &[H256],
+) -> anyhow::Result<Vec<BlockBodyType>> {
+    let hashes = &hashes[..hashes.len().min(MAX_BODIES_SERVE)];
+    let numbers = tx.get_many(tables::HeaderNumber, hashes.iter().copied())?;
+
+    let mut size = 0;
+    let mut bodies = vec![];
+    for (&hash, number) in hashes.iter().zip(numbers) {
+        if size >= SOFT_RESPONSE_LIMIT {
+            break;
+        }
+
+        // unknown blocks are skipped
+        let Some(number) = number else {
+            continue;
+        };
+        let Some(body) = chain::block_body::read_without_senders(tx, hash, number)? else {
+            continue;
+        };
+
+        let body = BlockBodyType {
+            transactions: body.transactions,
+            ommers: body.ommers,
+        };
+        size += rlp::encode(&body).len();
+        bodies.push(body);
+    }
+
+    Ok(bodies)
+}
+
+fn read_receipts<K: TransactionKind, E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, K, E>,
+    hashes: &[H256],
+) -> anyhow::Result<Vec<BlockReceipts>> {
+    let hashes = &hashes[..hashes.len().min(MAX_RECEIPTS_SERVE)];
+    let numbers = tx.get_many(tables::HeaderNumber, hashes.iter().copied())?;
+
+    let mut size = 0;
+    let mut receipts = vec![];
+    for (&hash, number) in hashes.iter().zip(numbers) {
+        if size >= SOFT_RESPONSE_LIMIT {
+            break;
+        }
+
+        // receipts are stored by number, only for the canonical chain
+        let Some(number) = number else {
+            continue;
+        };
+        if tx.get(tables::CanonicalHeader, number)? != Some(hash) {
+            continue;
+        }
+        // blocks which are not executed yet or whose receipts are pruned are skipped
+        let Some(block_receipts) = chain::receipts::read(tx, hash, number)? else {
+            continue;
+        };
+
+        let block_receipts = BlockReceipts {
+            receipts: block_receipts,
+        };
+        size += rlp::encode(&block_receipts).len();
+        receipts.push(block_receipts);
+    }
+
+    Ok(receipts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv::new_mem_database;
+
+    #[test]
+    fn serve_headers() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let mut hashes = vec![];
+        for i in 0..10 {
+            let header = BlockHeader {
+                number: BlockNumber(i),
+                ..BlockHeader::empty()
+            };
+            let hash = header.hash();
+            tx.set(tables::Header, (header.number, hash), header)
+                .unwrap();
+            tx.set(tables::HeaderNumber, hash, BlockNumber(i)).unwrap();
+            tx.set(tables::CanonicalHeader, BlockNumber(i), hash)
+                .unwrap();
+            hashes.push(hash);
+        }
+        tx.commit().unwrap();
+
+        let tx = db.begin().unwrap();
+        let header_cache = HeaderCache::new(4);
+        let numbers = |params| {
+            read_headers(&tx, &header_cache, &params)
+                .unwrap()
+                .into_iter()
+                .map(|header| header.number.0)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            numbers(GetBlockHeadersMessageParams {
+                start_block: BlockId::Number(BlockNumber(2)),
+                limit: 3,
+                skip: 1,
+                reverse: 0,
+            }),
+            vec![2, 4, 6]
+        );
+        assert_eq!(
+            numbers(GetBlockHeadersMessageParams {
+                start_block: BlockId::Hash(hashes[3]),
+                limit: 10,
+                skip: 0,
+                reverse: 1,
+            }),
+            vec![3, 2, 1, 0]
+        );
+        assert_eq!(
+            numbers(GetBlockHeadersMessageParams {
+                start_block: BlockId::Number(BlockNumber(8)),
+                limit: 10,
+                skip: 0,
+                reverse: 0,
+            }),
+            vec![8, 9]
+        );
+        assert!(numbers(GetBlockHeadersMessageParams {
+            start_block: BlockId::Hash(H256::repeat_byte(1)),
+            limit: 1,
+            skip: 0,
+            reverse: 0,
+        })
+        .is_empty());
+
+        assert!(read_bodies(&tx, &hashes).unwrap().is_empty());
+    }
+
+    #[test]
+    fn serve_stored_receipts() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let mut hashes = vec![];
+        for i in 0..3 {
+            let header = BlockHeader {
+                number: BlockNumber(i),
+                ..BlockHeader::empty()
+            };
+            let hash = header.hash();
+            tx.set(tables::HeaderNumber, hash, BlockNumber(i)).unwrap();
+            tx.set(tables::CanonicalHeader, BlockNumber(i), hash)
+                .unwrap();
+            chain::storage_body::write(
+                &tx,
+                hash,
+                i,
+                &BodyForStorage {
+                    base_tx_id: 0.into(),
+                    tx_amount: 0,
+                    uncles: vec![],
+                },
+            )
+            .unwrap();
+            hashes.push(hash);
+        }
+        // receipts of blocks 1 and 2 are pruned or not executed yet, the last hash is unknown
+        tx.set(tables::Receipt, BlockNumber(0), vec![]).unwrap();
+        hashes.push(H256::repeat_byte(1));
+
+        let receipts = read_receipts(&tx, &hashes).unwrap();
+        assert_eq!(receipts.len(), 1);
+        assert!(receipts[0].receipts.is_empty());
+    }
+}
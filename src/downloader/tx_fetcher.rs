@@ -0,0 +1,386 @@
+use crate::{
+    crypto::TrieEncode,
+    models::*,
+    sentry::{
+        messages::{EthMessageId, GetPooledTransactionsMessage, Message},
+        sentry_client::{PeerFilter, PeerId},
+        sentry_client_reactor::*,
+    },
+};
+use ethereum_interfaces::txpool::{txpool_client::TxpoolClient, AddRequest};
+use lru::LruCache;
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
+use tokio_stream::StreamExt;
+use tracing::*;
+
+/// Maximum number of transactions requested from a peer at once.
+const MAX_TX_FETCH: usize = 256;
+/// Announcements queued per peer, the rest are dropped.
+const MAX_PEER_ANNOUNCES: usize = 4096;
+/// Hashes of recently seen transactions, which are not fetched again.
+const KNOWN_TXS: usize = 32 * 1024;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const TICK_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug)]
+struct Request {
+    peer_id: PeerId,
+    hashes: Vec<H256>,
+    sent_at: Instant,
+}
+
+/// Bookkeeping of the transaction fetcher: which peers announced which transactions, and
+/// what is being requested from whom.
+///
+/// Every announced transaction is requested from one peer at a time, and each peer has at
+/// most one request in flight. If the peer does not deliver the transaction, the next peer
+/// which announced it is asked.
+#[derive(Debug)]
+struct FetcherState {
+    /// Announced transactions to the peers which announced them, in announcement order.
+    announced: HashMap<H256, VecDeque<PeerId>>,
+    peer_announces: HashMap<PeerId, VecDeque<H256>>,
+    requests: HashMap<u64, Request>,
+    in_flight: HashSet<H256>,
+    busy_peers: HashSet<PeerId>,
+    known: LruCache<H256, ()>,
+    next_request_id: u64,
+}
+
+impl FetcherState {
+    fn new() -> Self {
+        Self {
+            announced: Default::default(),
+            peer_announces: Default::default(),
+            requests: Default::default(),
+            in_flight: Default::default(),
+            busy_peers: Default::default(),
+            known: LruCache::new(KNOWN_TXS),
+            next_request_id: 0,
+        }
+    }
+
+    fn on_announce(&mut self, peer_id: PeerId, hashes: impl IntoIterator<Item = H256>) {
+        let queue = self.peer_announces.entry(peer_id).or_default();
+        for hash in hashes {
+            if self.known.contains(&hash) {
+                continue;
+            }
+
+            if queue.len() >= MAX_PEER_ANNOUNCES {
+                debug!("TxFetcher: too many announcements from {:?}", peer_id);
+                break;
+            }
+
+            let peers = self.announced.entry(hash).or_default();
+            if !peers.contains(&peer_id) {
+                peers.push_back(peer_id);
+                queue.push_back(hash);
+            }
+        }
+    }
+
+    /// Transactions which arrived, requested or not.
+    fn on_transactions(&mut self, hashes: impl IntoIterator<Item = H256>) {
+        for hash in hashes {
+            self.known.put(hash, ());
+            self.announced.remove(&hash);
+        }
+    }
+
+    /// Assigns pending announcements to idle peers, returns requests to send.
+    fn schedule(&mut self, now: Instant) -> Vec<(PeerId, u64, Vec<H256>)> {
+        let mut scheduled = vec![];
+        for (&peer_id, queue) in &mut self.peer_announces {
+            if self.busy_peers.contains(&peer_id) {
+                continue;
+            }
+
+            let mut hashes = vec![];
+            let mut skipped = VecDeque::new();
+            while hashes.len() < MAX_TX_FETCH {
+                let Some(hash) = queue.pop_front() else {
+                    break;
+                };
+
+                // Fetched, or given up meanwhile.
+                let Some(peers) = self.announced.get(&hash) else {
+                    continue;
+                };
+
+                // Only the first peer in line is asked, the others wait for it to fail.
+                if self.in_flight.contains(&hash) || peers.front() != Some(&peer_id) {
+                    skipped.push_back(hash);
+                    continue;
+                }
+
+                hashes.push(hash);
+            }
+            skipped.append(queue);
+            *queue = skipped;
+
+            if hashes.is_empty() {
+                continue;
+            }
+
+            let request_id = self.next_request_id;
+            self.next_request_id += 1;
+            self.in_flight.extend(hashes.iter().copied());
+            self.busy_peers.insert(peer_id);
+            self.requests.insert(
+                request_id,
+                Request {
+                    peer_id,
+                    hashes: hashes.clone(),
+                    sent_at: now,
+                },
+            );
+            scheduled.push((peer_id, request_id, hashes));
+        }
+
+        self.peer_announces.retain(|_, queue| !queue.is_empty());
+
+        scheduled
+    }
+
+    /// Response to a request, returns whether it was expected.
+    fn on_delivery(&mut self, peer_id: PeerId, request_id: u64, delivered: &[H256]) -> bool {
+        let Entry::Occupied(entry) = self.requests.entry(request_id) else {
+            return false;
+        };
+        if entry.get().peer_id != peer_id {
+            return false;
+        }
+        let request = entry.remove();
+
+        self.on_transactions(delivered.iter().copied());
+        self.finish(request);
+
+        true
+    }
+
+    /// Fails the requests which were not answered in time, returns their peers.
+    fn on_timeout(&mut self, now: Instant) -> Vec<PeerId> {
+        let expired = self
+            .requests
+            .iter()
+            .filter(|(_, request)| now.saturating_duration_since(request.sent_at) > REQUEST_TIMEOUT)
+            .map(|(&request_id, _)| request_id)
+            .collect::<Vec<_>>();
+
+        let mut peers = vec![];
+        for request_id in expired {
+            if let Some(request) = self.requests.remove(&request_id) {
+                peers.push(request.peer_id);
+                self.finish(request);
+            }
+        }
+        peers
+    }
+
+    /// Releases the peer and passes undelivered transactions on to the next announcer.
+    fn finish(&mut self, request: Request) {
+        self.busy_peers.remove(&request.peer_id);
+        for hash in request.hashes {
+            self.in_flight.remove(&hash);
+            if let Entry::Occupied(mut entry) = self.announced.entry(hash) {
+                entry
+                    .get_mut()
+                    .retain(|&peer_id| peer_id != request.peer_id);
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+            }
+        }
+    }
+}
+
+/// Fetches transactions announced by the peers with eth/65+ `NewPooledTransactionHashes`,
+/// requesting each of them once, and hands received transactions over to the pool.
+#[derive(Debug)]
+pub struct TxFetcher {
+    sentry: SentryClientReactorShared,
+    pool: mpsc::Sender<Vec<MessageWithSignature>>,
+}
+
+impl TxFetcher {
+    /// Transactions, both fetched and broadcast by the peers, are sent to `pool`.
+    pub fn new(
+        sentry: SentryClientReactorShared,
+        pool: mpsc::Sender<Vec<MessageWithSignature>>,
+    ) -> Self {
+        Self { sentry, pool }
+    }
+
+    pub async fn run(&self) -> anyhow::Result<()> {
+        let mut messages = {
+            let sentry = self.sentry.read().await;
+            sentry
+                .receive_messages(EthMessageId::NewPooledTransactionHashes)?
+                .merge(sentry.receive_messages(EthMessageId::PooledTransactions)?)
+                .merge(sentry.receive_messages(EthMessageId::Transactions)?)
+        };
+
+        let mut state = FetcherState::new();
+        let mut ticker = tokio::time::interval(TICK_INTERVAL);
+        loop {
+            tokio::select! {
+                message = messages.next() => {
+                    let Some(message) = message else {
+                        break;
+                    };
+                    let Some(peer_id) = message.from_peer_id else {
+                        continue;
+                    };
+
+                    let transactions = match message.message {
+                        Message::NewPooledTransactionHashes(message) => {
+                            state.on_announce(peer_id, message.ids);
+                            continue;
+                        }
+                        Message::PooledTransactions(message) => {
+                            let hashes = message
+                                .transactions
+                                .iter()
+                                .map(|tx| tx.hash())
+                                .collect::<Vec<_>>();
+                            if !state.on_delivery(peer_id, message.request_id, &hashes) {
+                                debug!(
+                                    "TxFetcher: unexpected response {} from {:?}",
+                                    message.request_id, peer_id
+                                );
+                            }
+                            message.transactions
+                        }
+                        Message::Transactions(message) => {
+                            state.on_transactions(
+                                message.transactions.iter().map(|tx| tx.hash()),
+                            );
+                            message.transactions
+                        }
+                        _ => continue,
+                    };
+
+                    if !transactions.is_empty() && self.pool.send(transactions).await.is_err() {
+                        break;
+                    }
+                }
+                _ = ticker.tick() => {
+                    for peer_id in state.on_timeout(Instant::now()) {
+                        debug!("TxFetcher: request to {:?} timed out", peer_id);
+                    }
+                }
+            }
+
+            self.send_requests(state.schedule(Instant::now())).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn send_requests(&self, requests: Vec<(PeerId, u64, Vec<H256>)>) -> anyhow::Result<()> {
+        if requests.is_empty() {
+            return Ok(());
+        }
+
+        let sentry = self.sentry.read().await;
+        for (peer_id, request_id, tx_hashes) in requests {
+            let message = Message::GetPooledTransactions(GetPooledTransactionsMessage {
+                request_id,
+                tx_hashes,
+            });
+            if let Err(error) = sentry.try_send_message(message, PeerFilter::PeerId(peer_id)) {
+                match error.downcast_ref::<SendMessageError>() {
+                    // The request times out and is retried with another peer.
+                    Some(SendMessageError::SendQueueFull) => {
+                        debug!("TxFetcher: request send queue is full");
+                    }
+                    _ => return Err(error),
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Submits transactions received by [`TxFetcher`] to the transaction pool GRPC service at `addr`.
+pub async fn feed_txpool(
+    addr: http::Uri,
+    mut transactions: mpsc::Receiver<Vec<MessageWithSignature>>,
+) -> anyhow::Result<()> {
+    let mut txpool = TxpoolClient::connect(addr).await?;
+    while let Some(transactions) = transactions.recv().await {
+        let reply = txpool
+            .add(AddRequest {
+                rlp_txs: transactions
+                    .iter()
+                    .map(|tx| tx.trie_encode().to_vec())
+                    .collect(),
+            })
+            .await?
+            .into_inner();
+        for error in reply.errors.into_iter().filter(|error| !error.is_empty()) {
+            debug!("TxFetcher: transaction rejected by the pool: {}", error);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fetch_once_and_fall_back() {
+        let peers = [1, 2].map(PeerId::repeat_byte);
+        let hashes = [1, 2, 3].map(H256::repeat_byte);
+        let now = Instant::now();
+
+        let mut state = FetcherState::new();
+        state.on_announce(peers[0], hashes[..2].to_vec());
+        state.on_announce(peers[1], hashes.to_vec());
+
+        let mut requests = state.schedule(now);
+        requests.sort_by_key(|(peer_id, ..)| *peer_id);
+        assert_eq!(
+            requests
+                .iter()
+                .map(|(peer_id, _, hashes)| (*peer_id, hashes.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (peers[0], hashes[..2].to_vec()),
+                (peers[1], vec![hashes[2]]),
+            ]
+        );
+
+        // Everything is in flight, nothing to request.
+        assert!(state.schedule(now).is_empty());
+
+        // The first peer delivers only one of the transactions.
+        assert!(state.on_delivery(peers[0], requests[0].1, &[hashes[0]]));
+        assert!(!state.on_delivery(peers[0], requests[0].1, &[hashes[0]]));
+
+        // The second peer is busy until its request times out.
+        assert!(state.schedule(now).is_empty());
+        assert_eq!(state.on_timeout(now + REQUEST_TIMEOUT * 2), vec![peers[1]]);
+
+        // Missing transaction is asked from the second peer, the one it did not deliver
+        // itself is given up on.
+        let requests = state.schedule(now + REQUEST_TIMEOUT * 2);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].0, peers[1]);
+        assert_eq!(requests[0].2, vec![hashes[1]]);
+
+        // Received transactions are not fetched again.
+        state.on_delivery(peers[1], requests[0].1, &[hashes[1]]);
+        state.on_announce(peers[0], hashes[..2].to_vec());
+        assert!(state.schedule(now).is_empty());
+        assert!(state.announced.is_empty());
+    }
+}
@@ -1,16 +1,14 @@
 use super::ui_view::UIView;
+use crate::task_group::TaskGroup;
 use parking_lot::Mutex;
 use std::{sync::Arc, time::Duration};
-use tokio::{
-    sync::{mpsc, Mutex as AsyncMutex},
-    task::JoinHandle,
-};
+use tokio::sync::{mpsc, Mutex as AsyncMutex};
 use tracing::*;
 
 pub struct UISystem {
     view_cell: Arc<Mutex<Option<Box<dyn UIView>>>>,
     event_loop: Option<UISystemEventLoop>,
-    event_loop_handle: Option<JoinHandle<()>>,
+    tasks: TaskGroup,
     stop_signal_sender: mpsc::Sender<()>,
 }
 
@@ -40,7 +38,7 @@ impl UISystem {
         Self {
             view_cell: Arc::clone(&view_cell),
             event_loop: Some(event_loop),
-            event_loop_handle: None,
+            tasks: TaskGroup::new(),
             stop_signal_sender,
         }
     }
@@ -50,20 +48,23 @@ impl UISystem {
             .event_loop
             .take()
             .ok_or_else(|| anyhow::format_err!("already started once"))?;
-        let handle = tokio::spawn(async move {
+        // the loop is aborted along with the system, should it be dropped without a stop()
+        self.tasks.spawn("ui event loop", async move {
             let result = event_loop.run().await;
-            if let Err(error) = result {
+            if let Err(error) = &result {
                 error!("UIEventLoop loop died: {:?}", error);
             }
+            result
         });
-        self.event_loop_handle = Some(handle);
         Ok(())
     }
 
     pub async fn stop(&mut self) -> anyhow::Result<()> {
-        if let Some(handle) = self.event_loop_handle.take() {
+        if !self.tasks.is_empty() {
             self.send_stop_signal();
-            handle.await?;
+            while let Some((_, result)) = self.tasks.join_next().await {
+                result?;
+            }
         }
         Ok(())
     }
@@ -1,3 +0,0 @@
-pub trait UIView: Send {
-    fn draw(&self) -> anyhow::Result<()>;
-}
@@ -0,0 +1,133 @@
+use crate::{models::*, State};
+use anyhow::format_err;
+use std::collections::VecDeque;
+
+/// Number of most recent blocks reachable by BLOCKHASH.
+pub const MAX_DISTANCE: u64 = 256;
+
+/// Rolling window of the hashes of the last 256 ancestors of the executed block, serving
+/// BLOCKHASH without walking the headers on every call.
+///
+/// Blocks are expected to be fed in order along one chain. Hashes are read from the
+/// database lazily, so the window only fills up as far back as the executed code asks.
+#[derive(Debug, Default)]
+pub struct BlockHashes {
+    /// Hashes of consecutive blocks, the last one being the parent of `number`.
+    hashes: VecDeque<H256>,
+    /// Block being executed.
+    number: BlockNumber,
+}
+
+impl BlockHashes {
+    /// Moves the window to the ancestors of the block with `header`, keeping the known hashes
+    /// if it follows the previously executed block.
+    pub fn advance(&mut self, header: &PartialHeader) {
+        if header.number.0 != self.number.0 + 1 {
+            self.hashes.clear();
+        }
+        self.number = header.number;
+        if header.number.0 == 0 {
+            return;
+        }
+
+        self.hashes.push_back(header.parent_hash);
+        if self.hashes.len() as u64 > MAX_DISTANCE {
+            self.hashes.pop_front();
+        }
+    }
+
+    /// Hash of the ancestor `block_number`, at most 256 blocks back.
+    pub fn get<S: State>(&mut self, db: &S, block_number: BlockNumber) -> anyhow::Result<H256> {
+        let distance = self
+            .number
+            .0
+            .checked_sub(block_number.0)
+            .filter(|distance| (1..=MAX_DISTANCE).contains(distance))
+            .ok_or_else(|| {
+                format_err!(
+                    "block {} is not a recent ancestor of {}",
+                    block_number,
+                    self.number
+                )
+            })? as usize;
+
+        while self.hashes.len() < distance {
+            let oldest = *self
+                .hashes
+                .front()
+                .ok_or_else(|| format_err!("no parent hash for block {}", self.number))?;
+            let oldest_number = BlockNumber(self.number.0 - self.hashes.len() as u64);
+            let header = db
+                .read_header(oldest_number, oldest)?
+                .ok_or_else(|| format_err!("no header {} {:?}", oldest_number, oldest))?;
+            self.hashes.push_front(header.parent_hash);
+        }
+
+        Ok(self.hashes[self.hashes.len() - distance])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryState;
+
+    #[test]
+    fn rolling_window() {
+        let mut state = InMemoryState::default();
+        let mut hashes = vec![];
+        let mut parent_hash = H256::zero();
+        for number in 0..300 {
+            let header = BlockHeader {
+                number: BlockNumber(number),
+                parent_hash,
+                ..BlockHeader::empty()
+            };
+            parent_hash = header.hash();
+            hashes.push(parent_hash);
+            state.insert_block(
+                Block {
+                    header,
+                    transactions: vec![],
+                    ommers: vec![],
+                },
+                parent_hash,
+            );
+        }
+
+        let header = |number: u64| PartialHeader {
+            number: BlockNumber(number),
+            parent_hash: hashes[number as usize - 1],
+            ..PartialHeader::empty()
+        };
+
+        let mut block_hashes = BlockHashes::default();
+        block_hashes.advance(&header(10));
+        assert_eq!(block_hashes.get(&state, BlockNumber(9)).unwrap(), hashes[9]);
+        assert_eq!(block_hashes.get(&state, BlockNumber(0)).unwrap(), hashes[0]);
+        assert!(block_hashes.get(&state, BlockNumber(10)).is_err());
+
+        // Window follows the chain without reading the database.
+        let empty = InMemoryState::default();
+        for number in 11..=266 {
+            block_hashes.advance(&header(number));
+        }
+        assert_eq!(
+            block_hashes.get(&empty, BlockNumber(10)).unwrap(),
+            hashes[10]
+        );
+        assert_eq!(
+            block_hashes.get(&empty, BlockNumber(265)).unwrap(),
+            hashes[265]
+        );
+        assert!(block_hashes.get(&empty, BlockNumber(9)).is_err());
+
+        // Gap in the blocks starts over.
+        block_hashes.advance(&header(290));
+        assert!(block_hashes.get(&empty, BlockNumber(288)).is_err());
+        assert_eq!(
+            block_hashes.get(&state, BlockNumber(100)).unwrap(),
+            hashes[100]
+        );
+    }
+}
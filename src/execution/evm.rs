@@ -683,7 +683,7 @@ mod tests {
             None,
             &mut AnalysisCache::default(),
             header,
-            &MAINNET.collect_block_spec(header.number),
+            &MAINNET.collect_block_spec(header.number, header.timestamp),
             txn,
             gas,
         )
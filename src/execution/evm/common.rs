@@ -1,7 +1,9 @@
+use crate::util::hexbytes;
 use arrayref::array_ref;
 use bytes::Bytes;
 use ethereum_types::{Address, H256};
 use ethnum::U256;
+use serde::{Deserialize, Serialize};
 use strum_macros::Display;
 
 /// Message status code.
@@ -92,7 +94,7 @@ pub enum StatusCode {
 }
 
 /// The kind of call-like instruction.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CallKind {
     Call,
     DelegateCall,
@@ -103,7 +105,8 @@ pub enum CallKind {
 
 /// The message describing an EVM call,
 /// including a zero-depth call from transaction origin.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct InterpreterMessage {
     /// The kind of the call. For zero-depth calls `CallKind::Call` SHOULD be used.
     pub kind: CallKind,
@@ -124,6 +127,7 @@ pub struct InterpreterMessage {
     pub sender: Address,
 
     /// Message input data.
+    #[serde(with = "hexbytes")]
     pub input_data: Bytes,
 
     /// The amount of Ether transferred with the message.
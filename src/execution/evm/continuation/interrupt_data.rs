@@ -1,11 +1,5 @@
 use super::*;
 
-#[derive(Debug)]
-pub enum Call {
-    Call(InterpreterMessage),
-    Create(CreateMessage),
-}
-
 #[derive(Debug)]
 pub enum InterruptData {
     InstructionStart {
@@ -1,7 +1,7 @@
 use self::{interrupt::*, interrupt_data::*, resume_data::*};
 use super::{
     common::*,
-    host::{AccessStatus, StorageStatus, TxContext},
+    host::{AccessStatus, Call, StorageStatus, TxContext},
     state::ExecutionState,
     *,
 };
@@ -35,7 +35,12 @@ pub(crate) type InnerCoroutine = Pin<
 >;
 
 fn resume_interrupt(mut inner: InnerCoroutine, resume_data: ResumeData) -> Interrupt {
-    match inner.as_mut().resume(resume_data) {
+    #[cfg(not(feature = "trace"))]
+    let state = inner.as_mut().resume(resume_data);
+    #[cfg(feature = "trace")]
+    let state = crate::interrupt_trace::resume("evm", inner.as_mut(), resume_data);
+
+    match state {
         GeneratorState::Yielded(interrupt) => match interrupt {
             InterruptData::InstructionStart { pc, opcode, state } => Interrupt::InstructionStart {
                 interrupt: InstructionStartInterrupt { inner },
@@ -129,3 +134,247 @@ fn resume_interrupt(mut inner: InnerCoroutine, resume_data: ResumeData) -> Inter
         },
     }
 }
+
+impl StartedInterrupt {
+    /// Run execution to completion, answering all interrupts with the provided `Host`.
+    pub fn run_to_completion_with_host<H: Host>(self, host: &mut H) -> Output {
+        let mut interrupt = self.resume(());
+
+        loop {
+            interrupt = match interrupt {
+                Interrupt::InstructionStart { interrupt, .. } => interrupt.resume(None),
+                Interrupt::AccountExists { interrupt, address } => {
+                    let exists = host.account_exists(address);
+                    interrupt.resume(AccountExistsStatus { exists })
+                }
+                Interrupt::GetStorage {
+                    interrupt,
+                    address,
+                    location,
+                } => {
+                    let value = host.get_storage(address, location);
+                    interrupt.resume(StorageValue { value })
+                }
+                Interrupt::SetStorage {
+                    interrupt,
+                    address,
+                    location,
+                    value,
+                } => {
+                    let status = host.set_storage(address, location, value);
+                    interrupt.resume(StorageStatusInfo { status })
+                }
+                Interrupt::GetBalance { interrupt, address } => {
+                    let balance = host.get_balance(address);
+                    interrupt.resume(Balance { balance })
+                }
+                Interrupt::GetCodeSize { interrupt, address } => {
+                    let code_size = host.get_code_size(address);
+                    interrupt.resume(CodeSize { code_size })
+                }
+                Interrupt::GetCodeHash { interrupt, address } => {
+                    let hash = host.get_code_hash(address);
+                    interrupt.resume(CodeHash { hash })
+                }
+                Interrupt::CopyCode {
+                    interrupt,
+                    address,
+                    offset,
+                    max_size,
+                } => {
+                    let mut code = vec![0; max_size];
+                    let copied = host.copy_code(address, offset, &mut code[..]);
+                    code.truncate(copied);
+                    interrupt.resume(Code { code: code.into() })
+                }
+                Interrupt::Selfdestruct {
+                    interrupt,
+                    address,
+                    beneficiary,
+                } => {
+                    host.selfdestruct(address, beneficiary);
+                    interrupt.resume(())
+                }
+                Interrupt::Call {
+                    interrupt,
+                    call_data,
+                } => {
+                    let output = host.call(call_data);
+                    interrupt.resume(CallOutput { output })
+                }
+                Interrupt::GetTxContext { interrupt } => {
+                    let context = host.get_tx_context();
+                    interrupt.resume(TxContextData { context })
+                }
+                Interrupt::GetBlockHash {
+                    interrupt,
+                    block_number,
+                } => {
+                    let hash = host.get_block_hash(block_number);
+                    interrupt.resume(BlockHash { hash })
+                }
+                Interrupt::EmitLog {
+                    interrupt,
+                    address,
+                    data,
+                    topics,
+                } => {
+                    host.emit_log(address, data, &topics);
+                    interrupt.resume(())
+                }
+                Interrupt::AccessAccount { interrupt, address } => {
+                    let status = host.access_account(address);
+                    interrupt.resume(AccessAccountStatus { status })
+                }
+                Interrupt::AccessStorage {
+                    interrupt,
+                    address,
+                    location,
+                } => {
+                    let status = host.access_storage(address, location);
+                    interrupt.resume(AccessStorageStatus { status })
+                }
+                Interrupt::Complete { result, .. } => {
+                    return match result {
+                        Ok(output) => output.into(),
+                        Err(status_code) => Output {
+                            status_code,
+                            gas_left: 0,
+                            output_data: Bytes::new(),
+                            create_address: None,
+                        },
+                    };
+                }
+            }
+        }
+    }
+}
+
+/// `Host` that hands every request over to the handler of resumable execution interrupts,
+/// so that the same handler can serve synchronous execution too.
+pub struct InterruptHandlerHost<F>(pub F);
+
+impl<F> InterruptHandlerHost<F>
+where
+    F: FnMut(InterruptData) -> ResumeData,
+{
+    fn handle(&mut self, interrupt_data: InterruptData) -> ResumeData {
+        (self.0)(interrupt_data)
+    }
+}
+
+impl<F> Host for InterruptHandlerHost<F>
+where
+    F: FnMut(InterruptData) -> ResumeData,
+{
+    fn account_exists(&mut self, address: Address) -> bool {
+        self.handle(InterruptData::AccountExists { address })
+            .into_account_exists_status()
+            .unwrap()
+            .exists
+    }
+
+    fn get_storage(&mut self, address: Address, location: U256) -> U256 {
+        self.handle(InterruptData::GetStorage { address, location })
+            .into_storage_value()
+            .unwrap()
+            .value
+    }
+
+    fn set_storage(&mut self, address: Address, location: U256, value: U256) -> StorageStatus {
+        self.handle(InterruptData::SetStorage {
+            address,
+            location,
+            value,
+        })
+        .into_storage_status_info()
+        .unwrap()
+        .status
+    }
+
+    fn get_balance(&mut self, address: Address) -> U256 {
+        self.handle(InterruptData::GetBalance { address })
+            .into_balance()
+            .unwrap()
+            .balance
+    }
+
+    fn get_code_size(&mut self, address: Address) -> U256 {
+        self.handle(InterruptData::GetCodeSize { address })
+            .into_code_size()
+            .unwrap()
+            .code_size
+    }
+
+    fn get_code_hash(&mut self, address: Address) -> U256 {
+        self.handle(InterruptData::GetCodeHash { address })
+            .into_code_hash()
+            .unwrap()
+            .hash
+    }
+
+    fn copy_code(&mut self, address: Address, offset: usize, buffer: &mut [u8]) -> usize {
+        let code = self
+            .handle(InterruptData::CopyCode {
+                address,
+                offset,
+                max_size: buffer.len(),
+            })
+            .into_code()
+            .unwrap()
+            .code;
+        let copied = code.len().min(buffer.len());
+        buffer[..copied].copy_from_slice(&code[..copied]);
+        copied
+    }
+
+    fn selfdestruct(&mut self, address: Address, beneficiary: Address) {
+        self.handle(InterruptData::Selfdestruct {
+            address,
+            beneficiary,
+        });
+    }
+
+    fn call(&mut self, msg: Call) -> Output {
+        self.handle(InterruptData::Call(msg))
+            .into_call_output()
+            .unwrap()
+            .output
+    }
+
+    fn get_tx_context(&mut self) -> TxContext {
+        self.handle(InterruptData::GetTxContext)
+            .into_tx_context_data()
+            .unwrap()
+            .context
+    }
+
+    fn get_block_hash(&mut self, block_number: u64) -> U256 {
+        self.handle(InterruptData::GetBlockHash { block_number })
+            .into_block_hash()
+            .unwrap()
+            .hash
+    }
+
+    fn emit_log(&mut self, address: Address, data: Bytes, topics: &[U256]) {
+        self.handle(InterruptData::EmitLog {
+            address,
+            data,
+            topics: topics.iter().copied().collect(),
+        });
+    }
+
+    fn access_account(&mut self, address: Address) -> AccessStatus {
+        self.handle(InterruptData::AccessAccount { address })
+            .into_access_account_status()
+            .unwrap()
+            .status
+    }
+
+    fn access_storage(&mut self, address: Address, location: U256) -> AccessStatus {
+        self.handle(InterruptData::AccessStorage { address, location })
+            .into_access_storage_status()
+            .unwrap()
+            .status
+    }
+}
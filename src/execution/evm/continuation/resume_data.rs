@@ -67,7 +67,7 @@ pub struct AccessStorageStatus {
 /// All resumed data variants.
 #[derive(Educe, EnumAsInner, From)]
 #[educe(Debug)]
-pub(crate) enum ResumeData {
+pub enum ResumeData {
     #[from(ignore)]
     Empty,
     StateModifier(#[educe(Debug(false))] StateModifier),
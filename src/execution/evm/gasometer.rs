@@ -0,0 +1,112 @@
+use super::common::StatusCode;
+
+/// Gas accounting of a single execution frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gasometer {
+    gas_left: i64,
+}
+
+impl Gasometer {
+    pub fn new(gas: i64) -> Self {
+        Self { gas_left: gas }
+    }
+
+    /// Gas left in the frame.
+    #[inline(always)]
+    pub fn gas_left(&self) -> i64 {
+        self.gas_left
+    }
+
+    /// Charge `cost`, fails with `OutOfGas` if there is not enough gas left.
+    #[inline(always)]
+    pub fn subtract(&mut self, cost: i64) -> Result<(), StatusCode> {
+        self.gas_left -= cost;
+        if self.gas_left < 0 {
+            return Err(StatusCode::OutOfGas);
+        }
+
+        Ok(())
+    }
+
+    /// Give back gas, e.g. call stipend or gas not spent by a subcall.
+    #[inline(always)]
+    pub fn refund(&mut self, gas: i64) {
+        self.gas_left += gas;
+    }
+
+    /// Fails with `OutOfGas` unless at least `gas` is left, nothing is charged.
+    #[inline(always)]
+    pub fn check(&self, gas: i64) -> Result<(), StatusCode> {
+        if self.gas_left < gas {
+            return Err(StatusCode::OutOfGas);
+        }
+
+        Ok(())
+    }
+
+    /// Maximum gas that can be passed to a subcall since Tangerine Whistle (EIP-150).
+    #[inline(always)]
+    pub fn all_but_one_64th(&self) -> i64 {
+        self.gas_left - self.gas_left / 64
+    }
+}
+
+/// Cost of growing memory from `current_words` to `new_words` 32-byte words.
+#[inline(always)]
+pub fn memory_expansion_cost(current_words: i64, new_words: i64) -> i64 {
+    fn memory_cost(words: i64) -> i64 {
+        3 * words + words * words / 512
+    }
+
+    memory_cost(new_words) - memory_cost(current_words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subtract_and_refund() {
+        let mut gasometer = Gasometer::new(100);
+        gasometer.subtract(60).unwrap();
+        assert_eq!(gasometer.gas_left(), 40);
+        gasometer.subtract(40).unwrap();
+        assert_eq!(gasometer.gas_left(), 0);
+        assert_eq!(gasometer.subtract(1), Err(StatusCode::OutOfGas));
+
+        let mut gasometer = Gasometer::new(0);
+        gasometer.refund(2300);
+        assert_eq!(gasometer.gas_left(), 2300);
+    }
+
+    #[test]
+    fn check() {
+        let gasometer = Gasometer::new(2300);
+        assert_eq!(gasometer.check(2300), Ok(()));
+        assert_eq!(gasometer.check(2301), Err(StatusCode::OutOfGas));
+        assert_eq!(gasometer.gas_left(), 2300);
+    }
+
+    #[test]
+    fn all_but_one_64th() {
+        assert_eq!(Gasometer::new(0).all_but_one_64th(), 0);
+        assert_eq!(Gasometer::new(63).all_but_one_64th(), 63);
+        assert_eq!(Gasometer::new(64).all_but_one_64th(), 63);
+        assert_eq!(Gasometer::new(6400).all_but_one_64th(), 6300);
+    }
+
+    #[test]
+    fn memory_expansion() {
+        assert_eq!(memory_expansion_cost(0, 0), 0);
+        assert_eq!(memory_expansion_cost(0, 1), 3);
+        assert_eq!(memory_expansion_cost(1, 1), 0);
+        // Quadratic component kicks in from 23 words
+        assert_eq!(memory_expansion_cost(0, 22), 66);
+        assert_eq!(memory_expansion_cost(0, 23), 70);
+        assert_eq!(memory_expansion_cost(0, 1024), 3 * 1024 + 2048);
+        assert_eq!(
+            memory_expansion_cost(0, 32) + memory_expansion_cost(32, 64),
+            memory_expansion_cost(0, 64)
+        );
+    }
+}
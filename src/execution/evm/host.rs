@@ -119,6 +119,116 @@ pub trait Host {
     fn access_storage(&mut self, address: Address, key: U256) -> AccessStatus;
 }
 
+/// Calls a `Host` method from instruction code.
+///
+/// Instructions are written once for both interpreters: the synchronous one passes the host
+/// itself, while the resumable one passes `yield`, which turns the call into an interrupt
+/// and takes the result from the data it is resumed with.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! host_call {
+    (yield, $op:ident ( $($arg:expr),* )) => {
+        $crate::host_call!(@yield $op($($arg),*))
+    };
+    (@yield account_exists($address:expr)) => {
+        $crate::host_call!(@resume into_account_exists_status, AccountExists {
+            address: $address,
+        })
+        .exists
+    };
+    (@yield get_storage($address:expr, $location:expr)) => {
+        $crate::host_call!(@resume into_storage_value, GetStorage {
+            address: $address,
+            location: $location,
+        })
+        .value
+    };
+    (@yield set_storage($address:expr, $location:expr, $value:expr)) => {
+        $crate::host_call!(@resume into_storage_status_info, SetStorage {
+            address: $address,
+            location: $location,
+            value: $value,
+        })
+        .status
+    };
+    (@yield get_balance($address:expr)) => {
+        $crate::host_call!(@resume into_balance, GetBalance { address: $address }).balance
+    };
+    (@yield get_code_size($address:expr)) => {
+        $crate::host_call!(@resume into_code_size, GetCodeSize { address: $address }).code_size
+    };
+    (@yield get_code_hash($address:expr)) => {
+        $crate::host_call!(@resume into_code_hash, GetCodeHash { address: $address }).hash
+    };
+    (@yield copy_code($address:expr, $offset:expr, $buffer:expr)) => {{
+        let buffer: &mut [u8] = $buffer;
+        let code = $crate::host_call!(@resume into_code, CopyCode {
+            address: $address,
+            offset: $offset,
+            max_size: buffer.len(),
+        })
+        .code;
+        let copied = ::std::cmp::min(code.len(), buffer.len());
+        buffer[..copied].copy_from_slice(&code[..copied]);
+        copied
+    }};
+    (@yield selfdestruct($address:expr, $beneficiary:expr)) => {
+        $crate::host_call!(@resume_empty Selfdestruct {
+            address: $address,
+            beneficiary: $beneficiary,
+        })
+    };
+    (@yield call($msg:expr)) => {
+        $crate::host_call!(@resume into_call_output, Call($msg)).output
+    };
+    (@yield get_tx_context()) => {
+        $crate::host_call!(@resume into_tx_context_data, GetTxContext).context
+    };
+    (@yield get_block_hash($block_number:expr)) => {
+        $crate::host_call!(@resume into_block_hash, GetBlockHash {
+            block_number: $block_number,
+        })
+        .hash
+    };
+    (@yield emit_log($address:expr, $data:expr, $topics:expr)) => {
+        $crate::host_call!(@resume_empty EmitLog {
+            address: $address,
+            data: $data,
+            topics: $topics.iter().copied().collect(),
+        })
+    };
+    (@yield access_account($address:expr)) => {
+        $crate::host_call!(@resume into_access_account_status, AccessAccount {
+            address: $address,
+        })
+        .status
+    };
+    (@yield access_storage($address:expr, $location:expr)) => {
+        $crate::host_call!(@resume into_access_storage_status, AccessStorage {
+            address: $address,
+            location: $location,
+        })
+        .status
+    };
+    (@resume $into:ident, $($interrupt:tt)*) => {{
+        use $crate::execution::evm::continuation::interrupt_data::InterruptData;
+
+        let resume_data = yield InterruptData::$($interrupt)*;
+        resume_data.$into().unwrap()
+    }};
+    (@resume_empty $($interrupt:tt)*) => {{
+        use $crate::execution::evm::continuation::{
+            interrupt_data::InterruptData, resume_data::ResumeData,
+        };
+
+        let resume_data = yield InterruptData::$($interrupt)*;
+        debug_assert!(matches!(resume_data, ResumeData::Empty));
+    }};
+    ($host:expr, $op:ident ( $($arg:expr),* )) => {
+        $host.$op($($arg),*)
+    };
+}
+
 /// Host that does not support any ops.
 pub struct DummyHost;
 
@@ -1,7 +1,7 @@
 #[doc(hidden)]
 #[macro_export]
 macro_rules! do_call {
-    ($state:expr, $host:expr, $rev:expr, $kind:expr, $is_static:expr) => {{
+    ($state:expr, $host:tt, $rev:expr, $kind:expr, $is_static:expr) => {{
         use std::cmp::min;
         use $crate::{
             execution::evm::{
@@ -29,18 +29,15 @@ macro_rules! do_call {
         $state.stack.push(U256::ZERO); // Assume failure.
 
         if $rev >= Revision::Berlin {
-            if $host.access_account(dst) == AccessStatus::Cold {
-                $state.gas_left -= i64::from(ADDITIONAL_COLD_ACCOUNT_ACCESS_COST);
-                if $state.gas_left < 0 {
-                    return Err(StatusCode::OutOfGas);
-                }
+            if $crate::host_call!($host, access_account(dst)) == AccessStatus::Cold {
+                $state
+                    .gasometer
+                    .subtract(i64::from(ADDITIONAL_COLD_ACCOUNT_ACCESS_COST))?;
             }
         }
 
-        let input_region = memory::get_memory_region($state, input_offset, input_size)
-            .map_err(|_| StatusCode::OutOfGas)?;
-        let output_region = memory::get_memory_region($state, output_offset, output_size)
-            .map_err(|_| StatusCode::OutOfGas)?;
+        let input_region = memory::get_memory_region($state, input_offset, input_size)?;
+        let output_region = memory::get_memory_region($state, output_offset, output_size)?;
 
         let mut msg = InterpreterMessage {
             kind: $kind,
@@ -64,9 +61,7 @@ macro_rules! do_call {
                 value
             },
             input_data: input_region
-                .map(|MemoryRegion { offset, size }| {
-                    $state.memory[offset..offset + size.get()].to_vec().into()
-                })
+                .map(|region| $state.memory[region.range()].to_vec().into())
                 .unwrap_or_default(),
         };
 
@@ -77,14 +72,13 @@ macro_rules! do_call {
                 return Err(StatusCode::StaticModeViolation);
             }
 
-            if (has_value || $rev < Revision::Spurious) && !$host.account_exists(dst) {
+            if (has_value || $rev < Revision::Spurious)
+                && !$crate::host_call!($host, account_exists(dst))
+            {
                 cost += 25000;
             }
         }
-        $state.gas_left -= cost;
-        if $state.gas_left < 0 {
-            return Err(StatusCode::OutOfGas);
-        }
+        $state.gasometer.subtract(cost)?;
 
         if gas < u128::try_from(msg.gas).unwrap() {
             msg.gas = gas.as_usize() as i64;
@@ -92,23 +86,24 @@ macro_rules! do_call {
 
         if $rev >= Revision::Tangerine {
             // TODO: Always true for STATICCALL.
-            msg.gas = min(msg.gas, $state.gas_left - $state.gas_left / 64);
-        } else if msg.gas > $state.gas_left {
-            return Err(StatusCode::OutOfGas);
+            msg.gas = min(msg.gas, $state.gasometer.all_but_one_64th());
+        } else {
+            $state.gasometer.check(msg.gas)?;
         }
 
         if has_value {
             msg.gas += 2300; // Add stipend.
-            $state.gas_left += 2300;
+            $state.gasometer.refund(2300);
         }
 
         $state.return_data.clear();
 
         if $state.message.depth < 1024
-            && !(has_value && $host.get_balance($state.message.recipient) < value)
+            && !(has_value
+                && $crate::host_call!($host, get_balance($state.message.recipient)) < value)
         {
-            let msg_gas = msg.gas;
-            let result = $host.call(Call::Call(msg));
+            $state.gasometer.subtract(msg.gas)?;
+            let result = $crate::host_call!($host, call(Call::Call(msg)));
             $state.return_data = result.output_data.clone();
             *$state.stack.get_mut(0) = if matches!(result.status_code, StatusCode::Success) {
                 U256::ONE
@@ -124,8 +119,7 @@ macro_rules! do_call {
                 }
             }
 
-            let gas_used = msg_gas - result.gas_left;
-            $state.gas_left -= gas_used;
+            $state.gasometer.refund(result.gas_left);
         }
     }};
 }
@@ -133,7 +127,7 @@ macro_rules! do_call {
 #[doc(hidden)]
 #[macro_export]
 macro_rules! do_create {
-    ($state:expr, $host:expr, $rev:expr, $create2:expr) => {{
+    ($state:expr, $host:tt, $rev:expr, $create2:expr) => {{
         use ethnum::U256;
         use $crate::{
             execution::evm::{common::*, host::*, CreateMessage},
@@ -148,18 +142,14 @@ macro_rules! do_create {
         let init_code_offset = $state.stack.pop();
         let init_code_size = $state.stack.pop();
 
-        let region = memory::get_memory_region($state, init_code_offset, init_code_size)
-            .map_err(|_| StatusCode::OutOfGas)?;
+        let region = memory::get_memory_region($state, init_code_offset, init_code_size)?;
 
         let salt = if $create2 {
             let salt = $state.stack.pop();
 
             if let Some(region) = &region {
                 let salt_cost = memory::num_words(region.size.get()) * 6;
-                $state.gas_left -= salt_cost;
-                if $state.gas_left < 0 {
-                    return Err(StatusCode::OutOfGas);
-                }
+                $state.gasometer.subtract(salt_cost)?;
             }
 
             Some(salt)
@@ -171,31 +161,27 @@ macro_rules! do_create {
         $state.return_data.clear();
 
         if $state.message.depth < 1024
-            && !(endowment != 0 && $host.get_balance($state.message.recipient) < endowment)
+            && !(endowment != 0
+                && $crate::host_call!($host, get_balance($state.message.recipient)) < endowment)
         {
             let msg = CreateMessage {
                 gas: if $rev >= Revision::Tangerine {
-                    $state.gas_left - $state.gas_left / 64
+                    $state.gasometer.all_but_one_64th()
                 } else {
-                    $state.gas_left
+                    $state.gasometer.gas_left()
                 },
 
                 salt,
-                initcode: if init_code_size != 0 {
-                    $state.memory[init_code_offset.as_usize()
-                        ..init_code_offset.as_usize() + init_code_size.as_usize()]
-                        .to_vec()
-                        .into()
-                } else {
-                    Bytes::new()
-                },
+                initcode: region
+                    .map(|region| $state.memory[region.range()].to_vec().into())
+                    .unwrap_or_default(),
                 sender: $state.message.recipient,
                 depth: $state.message.depth + 1,
                 endowment,
             };
-            let msg_gas = msg.gas;
-            let result = $host.call(Call::Create(msg));
-            $state.gas_left -= msg_gas - result.gas_left;
+            $state.gasometer.subtract(msg.gas)?;
+            let result = $crate::host_call!($host, call(Call::Create(msg)));
+            $state.gasometer.refund(result.gas_left);
 
             $state.return_data = result.output_data;
             if result.status_code == StatusCode::Success {
@@ -6,12 +6,8 @@ pub(crate) fn ret(state: &mut ExecutionState) -> Result<(), StatusCode> {
     let offset = *state.stack.get(0);
     let size = *state.stack.get(1);
 
-    if let Some(region) =
-        super::memory::get_memory_region(state, offset, size).map_err(|_| StatusCode::OutOfGas)?
-    {
-        state.output_data = state.memory[region.offset..region.offset + region.size.get()]
-            .to_vec()
-            .into();
+    if let Some(region) = super::memory::get_memory_region(state, offset, size)? {
+        state.output_data = state.memory[region.range()].to_vec().into();
     }
 
     Ok(())
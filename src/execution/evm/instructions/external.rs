@@ -16,7 +16,7 @@ pub(crate) fn callvalue(state: &mut ExecutionState) {
 #[doc(hidden)]
 #[macro_export]
 macro_rules! balance {
-    ($state:expr,$host:expr,$rev:expr) => {
+    ($state:expr,$host:tt,$rev:expr) => {
         use $crate::{
             execution::evm::{common::*, host::*, instructions::properties::*},
             models::*,
@@ -25,22 +25,23 @@ macro_rules! balance {
         let address = u256_to_address($state.stack.pop());
 
         if $rev >= Revision::Berlin {
-            if $host.access_account(address) == AccessStatus::Cold {
-                $state.gas_left -= i64::from(ADDITIONAL_COLD_ACCOUNT_ACCESS_COST);
-                if $state.gas_left < 0 {
-                    return Err(StatusCode::OutOfGas);
-                }
+            if $crate::host_call!($host, access_account(address)) == AccessStatus::Cold {
+                $state
+                    .gasometer
+                    .subtract(i64::from(ADDITIONAL_COLD_ACCOUNT_ACCESS_COST))?;
             }
         }
 
-        $state.stack.push($host.get_balance(address));
+        $state
+            .stack
+            .push($crate::host_call!($host, get_balance(address)));
     };
 }
 
 #[doc(hidden)]
 #[macro_export]
 macro_rules! extcodesize {
-    ($state:expr,$host:expr,$rev:expr) => {
+    ($state:expr,$host:tt,$rev:expr) => {
         use $crate::{
             execution::evm::{common::*, host::*, instructions::properties::*},
             models::*,
@@ -49,23 +50,26 @@ macro_rules! extcodesize {
         let address = u256_to_address($state.stack.pop());
 
         if $rev >= Revision::Berlin {
-            if $host.access_account(address) == AccessStatus::Cold {
-                $state.gas_left -= i64::from(ADDITIONAL_COLD_ACCOUNT_ACCESS_COST);
-                if $state.gas_left < 0 {
-                    return Err(StatusCode::OutOfGas);
-                }
+            if $crate::host_call!($host, access_account(address)) == AccessStatus::Cold {
+                $state
+                    .gasometer
+                    .subtract(i64::from(ADDITIONAL_COLD_ACCOUNT_ACCESS_COST))?;
             }
         }
 
-        $state.stack.push($host.get_code_size(address));
+        $state
+            .stack
+            .push($crate::host_call!($host, get_code_size(address)));
     };
 }
 
 #[doc(hidden)]
 #[macro_export]
 macro_rules! push_txcontext {
-    ($state:expr,$host:expr,$accessor:expr) => {
-        $state.stack.push($accessor($host.get_tx_context()));
+    ($state:expr,$host:tt,$accessor:expr) => {
+        $state
+            .stack
+            .push($accessor($crate::host_call!($host, get_tx_context())));
     };
 }
 
@@ -117,27 +121,28 @@ pub(crate) fn basefee_accessor(tx_context: TxContext) -> U256 {
 #[doc(hidden)]
 #[macro_export]
 macro_rules! selfbalance {
-    ($state:expr,$host:expr) => {{
-        $state
-            .stack
-            .push($host.get_balance($state.message.recipient));
+    ($state:expr,$host:tt) => {{
+        $state.stack.push($crate::host_call!(
+            $host,
+            get_balance($state.message.recipient)
+        ));
     }};
 }
 
 #[doc(hidden)]
 #[macro_export]
 macro_rules! blockhash {
-    ($state:expr,$host:expr) => {
+    ($state:expr,$host:tt) => {
         let number = $state.stack.pop();
 
-        let upper_bound = $host.get_tx_context().block_number;
+        let upper_bound = $crate::host_call!($host, get_tx_context()).block_number;
         let lower_bound = upper_bound.saturating_sub(256);
 
         let mut header = U256::ZERO;
         if number <= u128::from(u64::MAX) {
             let n = number.as_u64();
             if (lower_bound..upper_bound).contains(&n) {
-                header = $host.get_block_hash(n);
+                header = $crate::host_call!($host, get_block_hash(n));
             }
         }
 
@@ -148,7 +153,7 @@ macro_rules! blockhash {
 #[doc(hidden)]
 #[macro_export]
 macro_rules! do_log {
-    ($state:expr, $host:expr, $num_topics:expr) => {{
+    ($state:expr, $host:tt, $num_topics:expr) => {{
         use arrayvec::ArrayVec;
 
         if $state.message.is_static {
@@ -158,15 +163,11 @@ macro_rules! do_log {
         let offset = $state.stack.pop();
         let size = $state.stack.pop();
 
-        let region =
-            memory::get_memory_region($state, offset, size).map_err(|_| StatusCode::OutOfGas)?;
+        let region = memory::get_memory_region($state, offset, size)?;
 
         if let Some(region) = &region {
             let cost = region.size.get() as i64 * 8;
-            $state.gas_left -= cost;
-            if $state.gas_left < 0 {
-                return Err(StatusCode::OutOfGas);
-            }
+            $state.gasometer.subtract(cost)?;
         }
 
         let mut topics = ArrayVec::<U256, 4>::new();
@@ -175,21 +176,21 @@ macro_rules! do_log {
         }
 
         let data = if let Some(region) = region {
-            &$state.memory[region.offset..region.offset + region.size.get()]
+            &$state.memory[region.range()]
         } else {
             &[]
         }
         .to_vec()
         .into();
 
-        $host.emit_log($state.message.recipient, data, &*topics);
+        $crate::host_call!($host, emit_log($state.message.recipient, data, &*topics));
     }};
 }
 
 #[doc(hidden)]
 #[macro_export]
 macro_rules! sload {
-    ($state:expr,$host:expr,$rev:expr) => {{
+    ($state:expr,$host:tt,$rev:expr) => {{
         use $crate::{
             execution::evm::{
                 host::*,
@@ -201,27 +202,29 @@ macro_rules! sload {
         let location = $state.stack.pop();
 
         if $rev >= Revision::Berlin {
-            if $host.access_storage($state.message.recipient, location) == AccessStatus::Cold {
+            if $crate::host_call!($host, access_storage($state.message.recipient, location))
+                == AccessStatus::Cold
+            {
                 // The warm storage access cost is already applied (from the cost table).
                 // Here we need to apply additional cold storage access cost.
                 const ADDITIONAL_COLD_SLOAD_COST: u16 = COLD_SLOAD_COST - WARM_STORAGE_READ_COST;
-                $state.gas_left -= i64::from(ADDITIONAL_COLD_SLOAD_COST);
-                if $state.gas_left < 0 {
-                    return Err(StatusCode::OutOfGas);
-                }
+                $state
+                    .gasometer
+                    .subtract(i64::from(ADDITIONAL_COLD_SLOAD_COST))?;
             }
         }
 
-        $state
-            .stack
-            .push($host.get_storage($state.message.recipient, location));
+        $state.stack.push($crate::host_call!(
+            $host,
+            get_storage($state.message.recipient, location)
+        ));
     }};
 }
 
 #[doc(hidden)]
 #[macro_export]
 macro_rules! sstore {
-    ($state:expr, $host:expr, $rev:expr) => {{
+    ($state:expr, $host:tt, $rev:expr) => {{
         use $crate::{
             execution::evm::{
                 host::*,
@@ -235,9 +238,8 @@ macro_rules! sstore {
         }
 
         if $rev >= Revision::Istanbul {
-            if $state.gas_left <= 2300 {
-                return Err(StatusCode::OutOfGas);
-            }
+            // EIP-2200: fail if not more than call stipend is left.
+            $state.gasometer.check(2300 + 1)?;
         }
 
         let location = $state.stack.pop();
@@ -245,12 +247,17 @@ macro_rules! sstore {
 
         let mut cost = 0;
         if $rev >= Revision::Berlin {
-            if $host.access_storage($state.message.recipient, location) == AccessStatus::Cold {
+            if $crate::host_call!($host, access_storage($state.message.recipient, location))
+                == AccessStatus::Cold
+            {
                 cost = COLD_SLOAD_COST;
             }
         }
 
-        cost = match $host.set_storage($state.message.recipient, location, value) {
+        cost = match $crate::host_call!(
+            $host,
+            set_storage($state.message.recipient, location, value)
+        ) {
             StorageStatus::Unchanged | StorageStatus::ModifiedAgain => {
                 if $rev >= Revision::Berlin {
                     cost + WARM_STORAGE_READ_COST
@@ -271,17 +278,14 @@ macro_rules! sstore {
             }
             StorageStatus::Added => cost + 20000,
         };
-        $state.gas_left -= i64::from(cost);
-        if $state.gas_left < 0 {
-            return Err(StatusCode::OutOfGas);
-        }
+        $state.gasometer.subtract(i64::from(cost))?;
     }};
 }
 
 #[doc(hidden)]
 #[macro_export]
 macro_rules! selfdestruct {
-    ($state:expr, $host:expr, $rev:expr) => {{
+    ($state:expr, $host:tt, $rev:expr) => {{
         use $crate::{
             execution::evm::{common::*, host::*, instructions::properties::*},
             models::*,
@@ -294,28 +298,26 @@ macro_rules! selfdestruct {
         let beneficiary = u256_to_address($state.stack.pop());
 
         if $rev >= Revision::Berlin {
-            if $host.access_account(beneficiary) == AccessStatus::Cold {
-                $state.gas_left -= i64::from(COLD_ACCOUNT_ACCESS_COST);
-                if $state.gas_left < 0 {
-                    return Err(StatusCode::OutOfGas);
-                }
+            if $crate::host_call!($host, access_account(beneficiary)) == AccessStatus::Cold {
+                $state
+                    .gasometer
+                    .subtract(i64::from(COLD_ACCOUNT_ACCESS_COST))?;
             }
         }
 
         if $rev >= Revision::Tangerine {
-            if ($rev == Revision::Tangerine || $host.get_balance($state.message.recipient) != 0) {
+            if ($rev == Revision::Tangerine
+                || $crate::host_call!($host, get_balance($state.message.recipient)) != 0)
+            {
                 // After TANGERINE_WHISTLE apply additional cost of
                 // sending value to a non-existing account.
-                if !$host.account_exists(beneficiary) {
-                    $state.gas_left -= 25000;
-                    if $state.gas_left < 0 {
-                        return Err(StatusCode::OutOfGas);
-                    }
+                if !$crate::host_call!($host, account_exists(beneficiary)) {
+                    $state.gasometer.subtract(25000)?;
                 }
             }
         }
 
-        $host.selfdestruct($state.message.recipient, beneficiary);
+        $crate::host_call!($host, selfdestruct($state.message.recipient, beneficiary));
     }};
 }
 
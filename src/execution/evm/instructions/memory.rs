@@ -1,7 +1,7 @@
-use crate::execution::evm::{common::*, state::*};
+use crate::execution::evm::{common::*, gasometer::memory_expansion_cost, state::*};
 use ethnum::U256;
 use sha3::{Digest, Keccak256};
-use std::{cmp::min, num::NonZeroUsize};
+use std::{cmp::min, num::NonZeroUsize, ops::Range};
 
 pub(crate) const MAX_BUFFER_SIZE: u128 = u32::MAX as u128;
 
@@ -19,10 +19,9 @@ pub(crate) fn num_words(size_in_bytes: usize) -> i64 {
 pub(crate) fn mload(state: &mut ExecutionState) -> Result<(), StatusCode> {
     let index = state.stack.pop();
 
-    let region = get_memory_region_u64(state, index, NonZeroUsize::new(32).unwrap())
-        .map_err(|_| StatusCode::OutOfGas)?;
+    let region = get_memory_region_u64(state, index, NonZeroUsize::new(32).unwrap())?;
 
-    let value = u256_from_slice(&state.memory[region.offset..region.offset + region.size.get()]);
+    let value = u256_from_slice(&state.memory[region.range()]);
 
     state.stack.push(value);
 
@@ -34,8 +33,7 @@ pub(crate) fn mstore(state: &mut ExecutionState) -> Result<(), StatusCode> {
     let index = state.stack.pop();
     let value = state.stack.pop();
 
-    let region = get_memory_region_u64(state, index, NonZeroUsize::new(32).unwrap())
-        .map_err(|_| StatusCode::OutOfGas)?;
+    let region = get_memory_region_u64(state, index, NonZeroUsize::new(32).unwrap())?;
 
     state.memory[region.offset..region.offset + 32].copy_from_slice(&value.to_be_bytes());
 
@@ -47,8 +45,7 @@ pub(crate) fn mstore8(state: &mut ExecutionState) -> Result<(), StatusCode> {
     let index = state.stack.pop();
     let value = state.stack.pop();
 
-    let region = get_memory_region_u64(state, index, NonZeroUsize::new(1).unwrap())
-        .map_err(|_| StatusCode::OutOfGas)?;
+    let region = get_memory_region_u64(state, index, NonZeroUsize::new(1).unwrap())?;
 
     let value = (*value.low() as u32 & 0xff) as u8;
 
@@ -65,35 +62,38 @@ pub(crate) fn msize(state: &mut ExecutionState) {
 }
 
 #[inline(never)]
-fn grow_memory(state: &mut ExecutionState, new_size: usize) -> Result<(), ()> {
+fn grow_memory(state: &mut ExecutionState, new_size: usize) -> Result<(), StatusCode> {
     let new_words = num_words(new_size);
     let current_words = (state.memory.len() / 32) as i64;
-    let new_cost = 3 * new_words + new_words * new_words / 512;
-    let current_cost = 3 * current_words + current_words * current_words / 512;
-    let cost = new_cost - current_cost;
 
-    state.gas_left -= cost;
-
-    if state.gas_left < 0 {
-        return Err(());
-    }
+    state
+        .gasometer
+        .subtract(memory_expansion_cost(current_words, new_words))?;
 
     state.memory.grow((new_words * WORD_SIZE) as usize);
 
     Ok(())
 }
 
+/// Checks that memory region fits into the buffer size limit and expands memory to cover it,
+/// charging for the expansion.
+///
+/// Regions that are out of bounds are reported as [`StatusCode::OutOfGas`], since no amount
+/// of gas would be enough to expand memory to them.
 #[inline(always)]
 pub(crate) fn get_memory_region_u64(
     state: &mut ExecutionState,
     offset: U256,
     size: NonZeroUsize,
-) -> Result<MemoryRegion, ()> {
+) -> Result<MemoryRegion, StatusCode> {
     if offset > MAX_BUFFER_SIZE {
-        return Err(());
+        return Err(StatusCode::OutOfGas);
     }
 
-    let new_size = offset.as_usize() + size.get();
+    let new_size = offset
+        .as_usize()
+        .checked_add(size.get())
+        .ok_or(StatusCode::OutOfGas)?;
     let current_size = state.memory.len();
     if new_size > current_size {
         grow_memory(state, new_size)?;
@@ -110,18 +110,25 @@ pub(crate) struct MemoryRegion {
     pub size: NonZeroUsize,
 }
 
+impl MemoryRegion {
+    #[inline(always)]
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.size.get()
+    }
+}
+
 #[inline(always)]
 pub(crate) fn get_memory_region(
     state: &mut ExecutionState,
     offset: U256,
     size: U256,
-) -> Result<Option<MemoryRegion>, ()> {
+) -> Result<Option<MemoryRegion>, StatusCode> {
     if size == 0 {
         return Ok(None);
     }
 
     if size > MAX_BUFFER_SIZE {
-        return Err(());
+        return Err(StatusCode::OutOfGas);
     }
 
     get_memory_region_u64(state, offset, NonZeroUsize::new(size.as_usize()).unwrap()).map(Some)
@@ -132,14 +139,11 @@ pub(crate) fn calldatacopy(state: &mut ExecutionState) -> Result<(), StatusCode>
     let input_index = state.stack.pop();
     let size = state.stack.pop();
 
-    let region = get_memory_region(state, mem_index, size).map_err(|_| StatusCode::OutOfGas)?;
+    let region = get_memory_region(state, mem_index, size)?;
 
     if let Some(region) = &region {
         let copy_cost = num_words(region.size.get()) * 3;
-        state.gas_left -= copy_cost;
-        if state.gas_left < 0 {
-            return Err(StatusCode::OutOfGas);
-        }
+        state.gasometer.subtract(copy_cost)?;
 
         let input_len = u128::try_from(state.message.input_data.len())
             .unwrap()
@@ -166,18 +170,15 @@ pub(crate) fn keccak256(state: &mut ExecutionState) -> Result<(), StatusCode> {
     let index = state.stack.pop();
     let size = state.stack.pop();
 
-    let region = get_memory_region(state, index, size).map_err(|_| StatusCode::OutOfGas)?;
+    let region = get_memory_region(state, index, size)?;
 
     state.stack.push(u256_from_slice(&*Keccak256::digest(
         if let Some(region) = region {
             let w = num_words(region.size.get());
             let cost = w * 6;
-            state.gas_left -= cost;
-            if state.gas_left < 0 {
-                return Err(StatusCode::OutOfGas);
-            }
+            state.gasometer.subtract(cost)?;
 
-            &state.memory[region.offset..region.offset + region.size.get()]
+            &state.memory[region.range()]
         } else {
             &[]
         },
@@ -198,17 +199,14 @@ pub(crate) fn codecopy(state: &mut ExecutionState, code: &[u8]) -> Result<(), St
     let input_index = state.stack.pop();
     let size = state.stack.pop();
 
-    let region = get_memory_region(state, mem_index, size).map_err(|_| StatusCode::OutOfGas)?;
+    let region = get_memory_region(state, mem_index, size)?;
 
     if let Some(region) = region {
         let src = min(U256::from(u128::try_from(code.len()).unwrap()), input_index).as_usize();
         let copy_size = min(region.size.get(), code.len() - src);
 
         let copy_cost = num_words(region.size.get()) * 3;
-        state.gas_left -= copy_cost;
-        if state.gas_left < 0 {
-            return Err(StatusCode::OutOfGas);
-        }
+        state.gasometer.subtract(copy_cost)?;
 
         // TODO: Add unit tests for each combination of conditions.
         if copy_size > 0 {
@@ -227,7 +225,7 @@ pub(crate) fn codecopy(state: &mut ExecutionState, code: &[u8]) -> Result<(), St
 #[doc(hidden)]
 #[macro_export]
 macro_rules! extcodecopy {
-    ($state:expr, $host:expr, $rev:expr) => {
+    ($state:expr, $host:tt, $rev:expr) => {
         use core::cmp::min;
         use $crate::{
             execution::evm::{
@@ -243,23 +241,18 @@ macro_rules! extcodecopy {
         let input_index = $state.stack.pop();
         let size = $state.stack.pop();
 
-        let region =
-            get_memory_region(&mut $state, mem_index, size).map_err(|_| StatusCode::OutOfGas)?;
+        let region = get_memory_region(&mut $state, mem_index, size)?;
 
         if let Some(region) = &region {
             let copy_cost = num_words(region.size.get()) * 3;
-            $state.gas_left -= copy_cost;
-            if $state.gas_left < 0 {
-                return Err(StatusCode::OutOfGas);
-            }
+            $state.gasometer.subtract(copy_cost)?;
         }
 
         if $rev >= Revision::Berlin {
-            if $host.access_account(addr) == AccessStatus::Cold {
-                $state.gas_left -= i64::from(ADDITIONAL_COLD_ACCOUNT_ACCESS_COST);
-                if $state.gas_left < 0 {
-                    return Err(StatusCode::OutOfGas);
-                }
+            if $crate::host_call!($host, access_account(addr)) == AccessStatus::Cold {
+                $state
+                    .gasometer
+                    .subtract(i64::from(ADDITIONAL_COLD_ACCOUNT_ACCESS_COST))?;
             }
         }
 
@@ -267,7 +260,7 @@ macro_rules! extcodecopy {
             let src = min(U256::from(MAX_BUFFER_SIZE), input_index).as_usize();
 
             let mut code = vec![0; region.size.get()];
-            let copied = $host.copy_code(addr, src, &mut code[..]);
+            let copied = $crate::host_call!($host, copy_code(addr, src, &mut code[..]));
             debug_assert!(copied <= code.len());
             code.truncate(copied);
 
@@ -291,7 +284,7 @@ pub(crate) fn returndatacopy(state: &mut ExecutionState) -> Result<(), StatusCod
     let input_index = state.stack.pop();
     let size = state.stack.pop();
 
-    let region = get_memory_region(state, mem_index, size).map_err(|_| StatusCode::OutOfGas)?;
+    let region = get_memory_region(state, mem_index, size)?;
 
     if input_index > u128::try_from(state.return_data.len()).unwrap() {
         return Err(StatusCode::InvalidMemoryAccess);
@@ -304,12 +297,9 @@ pub(crate) fn returndatacopy(state: &mut ExecutionState) -> Result<(), StatusCod
 
     if let Some(region) = region {
         let copy_cost = num_words(region.size.get()) * 3;
-        state.gas_left -= copy_cost;
-        if state.gas_left < 0 {
-            return Err(StatusCode::OutOfGas);
-        }
+        state.gasometer.subtract(copy_cost)?;
 
-        state.memory[region.offset..region.offset + region.size.get()]
+        state.memory[region.range()]
             .copy_from_slice(&state.return_data[src..src + region.size.get()]);
     }
 
@@ -319,7 +309,7 @@ pub(crate) fn returndatacopy(state: &mut ExecutionState) -> Result<(), StatusCod
 #[doc(hidden)]
 #[macro_export]
 macro_rules! extcodehash {
-    ($state:expr,$host:expr,$rev:expr) => {
+    ($state:expr,$host:tt,$rev:expr) => {
         use $crate::{
             execution::evm::{common::*, host::*, instructions::properties::*},
             models::*,
@@ -328,14 +318,15 @@ macro_rules! extcodehash {
         let addr = u256_to_address($state.stack.pop());
 
         if $rev >= Revision::Berlin {
-            if $host.access_account(addr) == AccessStatus::Cold {
-                $state.gas_left -= i64::from(ADDITIONAL_COLD_ACCOUNT_ACCESS_COST);
-                if $state.gas_left < 0 {
-                    return Err(StatusCode::OutOfGas);
-                }
+            if $crate::host_call!($host, access_account(addr)) == AccessStatus::Cold {
+                $state
+                    .gasometer
+                    .subtract(i64::from(ADDITIONAL_COLD_ACCOUNT_ACCESS_COST))?;
             }
         }
 
-        $state.stack.push($host.get_code_hash(addr));
+        $state
+            .stack
+            .push($crate::host_call!($host, get_code_hash(addr)));
     };
 }
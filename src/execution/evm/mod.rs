@@ -2,15 +2,18 @@ use bytes::Bytes;
 pub use common::{
     CallKind, CreateMessage, InterpreterMessage, Output, StatusCode, SuccessfulOutput,
 };
+pub use gasometer::Gasometer;
 pub use host::Host;
 pub use interpreter::AnalyzedCode;
 pub use opcode::OpCode;
-pub use state::{ExecutionState, Stack};
+pub use state::{ExecutionState, ExecutionStateDump, Stack};
 
 /// Maximum allowed EVM bytecode size.
 pub const MAX_CODE_SIZE: usize = 0x6000;
 
 mod common;
+pub mod continuation;
+pub mod gasometer;
 pub mod host;
 #[macro_use]
 pub mod instructions;
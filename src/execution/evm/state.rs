@@ -1,15 +1,19 @@
-use super::common::InterpreterMessage;
+use super::{
+    common::{InterpreterMessage, StatusCode},
+    gasometer::Gasometer,
+};
+use crate::util::hexbytes;
 use arrayvec::ArrayVec;
 use bytes::{Bytes, BytesMut};
 use derive_more::{Deref, DerefMut};
 use ethnum::U256;
 use getset::{Getters, MutGetters};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 pub const STACK_SIZE: usize = 1024;
 
 /// EVM stack.
-#[derive(Clone, Debug, Default, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Stack(pub ArrayVec<U256, STACK_SIZE>);
 
 impl Stack {
@@ -44,8 +48,26 @@ impl Stack {
         self.len() == 0
     }
 
+    /// Checks that instruction can pop `required` items and, if it `can_overflow`,
+    /// push one more item on top.
+    ///
+    /// `get`, `push`, `pop` and `swap_top` are only valid after this check has passed.
+    #[inline(always)]
+    pub fn check_height(&self, required: usize, can_overflow: bool) -> Result<(), StatusCode> {
+        let len = self.len();
+        if len < required {
+            return Err(StatusCode::StackUnderflow);
+        }
+        if can_overflow && len == STACK_SIZE {
+            return Err(StatusCode::StackOverflow);
+        }
+
+        Ok(())
+    }
+
     #[inline(always)]
     pub fn push(&mut self, v: U256) {
+        debug_assert!(self.len() < STACK_SIZE, "overflow");
         unsafe { self.0.push_unchecked(v) }
     }
 
@@ -94,7 +116,7 @@ impl Default for Memory {
 #[derive(Clone, Debug, Getters, MutGetters)]
 pub struct ExecutionState {
     #[getset(get = "pub", get_mut = "pub")]
-    pub(crate) gas_left: i64,
+    pub(crate) gasometer: Gasometer,
     #[getset(get = "pub", get_mut = "pub")]
     pub(crate) stack: Stack,
     #[getset(get = "pub", get_mut = "pub")]
@@ -108,7 +130,7 @@ pub struct ExecutionState {
 impl ExecutionState {
     pub fn new(message: InterpreterMessage) -> Self {
         Self {
-            gas_left: message.gas,
+            gasometer: Gasometer::new(message.gas),
             stack: Stack::default(),
             memory: Memory::new(),
             message,
@@ -116,6 +138,64 @@ impl ExecutionState {
             output_data: Bytes::new(),
         }
     }
+
+    pub fn gas_left(&self) -> i64 {
+        self.gasometer.gas_left()
+    }
+
+    /// Dump of the state at the start of the instruction at `pc`, as seen by
+    /// [`Tracer::capture_state`](crate::execution::tracer::Tracer::capture_state).
+    pub fn dump(&self, pc: usize) -> ExecutionStateDump {
+        ExecutionStateDump {
+            pc,
+            gas_left: self.gas_left(),
+            stack: self.stack.clone(),
+            memory: Bytes::copy_from_slice(&self.memory),
+            return_data: self.return_data.clone(),
+            message: self.message.clone(),
+        }
+    }
+}
+
+/// Execution state in the middle of a call, serializable so that a diverging execution can be
+/// dumped from a tracer and replayed in isolation with
+/// [`AnalyzedCode::execute_from`](super::AnalyzedCode::execute_from).
+///
+/// Host state, such as storage and balances, is not part of it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionStateDump {
+    /// Offset of the next instruction to execute.
+    pub pc: usize,
+    pub gas_left: i64,
+    pub stack: Stack,
+    #[serde(with = "hexbytes")]
+    pub memory: Bytes,
+    #[serde(with = "hexbytes")]
+    pub return_data: Bytes,
+    /// Message being executed, including the call depth.
+    pub message: InterpreterMessage,
+}
+
+impl ExecutionStateDump {
+    /// Restores the execution state, returns it along with the offset to resume from.
+    pub fn restore(self) -> (ExecutionState, usize) {
+        let mut memory = Memory::new();
+        memory.grow(self.memory.len());
+        memory.copy_from_slice(&self.memory);
+
+        (
+            ExecutionState {
+                gasometer: Gasometer::new(self.gas_left),
+                stack: self.stack,
+                memory,
+                message: self.message,
+                return_data: self.return_data,
+                output_data: Bytes::new(),
+            },
+            self.pc,
+        )
+    }
 }
 
 #[cfg(test)]
@@ -139,4 +219,26 @@ mod tests {
 
         assert_eq!(*stack.get(2), 0xde);
     }
+
+    #[test]
+    fn stack_height() {
+        let mut stack = Stack::default();
+
+        assert_eq!(stack.check_height(0, true), Ok(()));
+        assert_eq!(
+            stack.check_height(1, false),
+            Err(StatusCode::StackUnderflow)
+        );
+
+        for _ in 0..STACK_SIZE {
+            stack.push(U256::ONE);
+        }
+
+        assert_eq!(stack.check_height(STACK_SIZE, false), Ok(()));
+        assert_eq!(stack.check_height(0, true), Err(StatusCode::StackOverflow));
+        assert_eq!(
+            stack.check_height(STACK_SIZE + 1, false),
+            Err(StatusCode::StackUnderflow)
+        );
+    }
 }
@@ -0,0 +1,121 @@
+use crate::{
+    execution::evm::{
+        continuation::{interrupt::*, interrupt_data::*, resume_data::*, InterruptHandlerHost},
+        host::*,
+        util::*,
+        *,
+    },
+    models::*,
+};
+use bytes::Bytes;
+use ethereum_types::Address;
+use ethnum::U256;
+
+fn message() -> InterpreterMessage {
+    InterpreterMessage {
+        kind: CallKind::Call,
+        is_static: false,
+        depth: 0,
+        gas: 100_000,
+        recipient: Address::repeat_byte(0xaa),
+        code_address: Address::repeat_byte(0xaa),
+        sender: Address::zero(),
+        input_data: Bytes::new(),
+        value: U256::ZERO,
+    }
+}
+
+fn code() -> AnalyzedCode {
+    AnalyzedCode::analyze(&Bytecode::new().sstore(1, 0x2a).sload(1).ret_top().build())
+}
+
+#[test]
+fn storage_access_interrupts() {
+    let mut requests = vec![];
+    let mut stored = U256::ZERO;
+
+    let mut interrupt = code()
+        .execute_resumable(false, message(), Revision::Berlin)
+        .resume(());
+    let result = loop {
+        interrupt = match interrupt {
+            Interrupt::AccessStorage {
+                interrupt,
+                address,
+                location,
+            } => {
+                requests.push(("access_storage", address, location));
+                interrupt.resume(AccessStorageStatus {
+                    status: AccessStatus::Cold,
+                })
+            }
+            Interrupt::SetStorage {
+                interrupt,
+                address,
+                location,
+                value,
+            } => {
+                requests.push(("set_storage", address, location));
+                stored = value;
+                interrupt.resume(StorageStatusInfo {
+                    status: StorageStatus::Added,
+                })
+            }
+            Interrupt::GetStorage {
+                interrupt,
+                address,
+                location,
+            } => {
+                requests.push(("get_storage", address, location));
+                interrupt.resume(StorageValue { value: stored })
+            }
+            Interrupt::Complete { result, .. } => break result,
+            _ => unreachable!(),
+        };
+    };
+
+    let address = message().recipient;
+    let location = U256::ONE;
+    assert_eq!(
+        requests,
+        [
+            ("access_storage", address, location),
+            ("set_storage", address, location),
+            ("access_storage", address, location),
+            ("get_storage", address, location),
+        ]
+    );
+
+    let output = result.unwrap();
+    assert!(!output.reverted);
+    assert_eq!(&output.output_data[..], &U256::from(0x2a_u64).to_be_bytes());
+}
+
+#[test]
+fn interrupt_handler_host() {
+    let mut stored = U256::ZERO;
+    let mut host = InterruptHandlerHost(|interrupt_data| match interrupt_data {
+        InterruptData::AccessStorage { .. } => AccessStorageStatus {
+            status: AccessStatus::Cold,
+        }
+        .into(),
+        InterruptData::SetStorage { value, .. } => {
+            stored = value;
+            StorageStatusInfo {
+                status: StorageStatus::Added,
+            }
+            .into()
+        }
+        InterruptData::GetStorage { .. } => StorageValue { value: stored }.into(),
+        other => unreachable!("{:?}", other),
+    });
+
+    let output = code().execute(
+        &mut host,
+        &mut crate::execution::tracer::NoopTracer,
+        message(),
+        Revision::Berlin,
+    );
+    assert_eq!(output.status_code, StatusCode::Success);
+    assert_eq!(&output.output_data[..], &U256::from(0x2a_u64).to_be_bytes());
+}
@@ -0,0 +1,164 @@
+use crate::{
+    execution::evm::{opcode::*, util::*, *},
+    models::*,
+};
+use ethereum_types::*;
+use ethnum::{AsU256, U256};
+use hex_literal::hex;
+
+const CODE_ADDRESS: u64 = 0xa;
+
+/// Copies `size` bytes from `src` of the copy opcode's source into memory at `dest`.
+fn copy(opcode: OpCode, dest: impl AsU256, src: impl AsU256, size: impl AsU256) -> Bytecode {
+    let code = Bytecode::new().pushv(size).pushv(src).pushv(dest);
+    if opcode == OpCode::EXTCODECOPY {
+        code.pushv(CODE_ADDRESS).opcode(opcode)
+    } else {
+        code.opcode(opcode)
+    }
+}
+
+fn tester() -> EvmTester {
+    EvmTester::new()
+        .apply_host_fn(|host, _| {
+            host.accounts
+                .entry(Address::from_low_u64_be(CODE_ADDRESS))
+                .or_default()
+                .code = hex!("aabbcc").to_vec().into();
+        })
+        .input(hex!("aabbcc").to_vec())
+}
+
+#[test]
+fn copy_out_of_bounds_source() {
+    let huge = [
+        U256::from(u64::from(u32::MAX) + 1),
+        U256::from(u64::MAX),
+        U256::MAX,
+    ];
+
+    for opcode in [OpCode::CALLDATACOPY, OpCode::EXTCODECOPY] {
+        tester()
+            .code(copy(opcode, 0, 2, 4).ret(0, 4))
+            .output_data(hex!("cc000000"))
+            .check();
+
+        for src in [3_u64, 4].map(U256::from).into_iter().chain(huge) {
+            tester()
+                .code(copy(opcode, 0, src, 4).ret(0, 4))
+                .output_data([0; 4])
+                .check();
+        }
+    }
+
+    // Source offset is pushed with the same width, so code length does not depend on it.
+    let code_len = copy(OpCode::CODECOPY, 0, 0xff, 4).ret(0, 4).len();
+    tester()
+        .code(copy(OpCode::CODECOPY, 0, code_len - 1, 4).ret(0, 4))
+        .output_data([OpCode::RETURN.to_u8(), 0, 0, 0])
+        .check();
+    for src in [code_len, code_len + 1]
+        .map(|src| src.as_u256())
+        .into_iter()
+        .chain(huge)
+    {
+        tester()
+            .code(copy(OpCode::CODECOPY, 0, src, 4).ret(0, 4))
+            .output_data([0; 4])
+            .check();
+    }
+}
+
+#[test]
+fn copy_memory_expansion_cost() {
+    for opcode in [OpCode::CALLDATACOPY, OpCode::CODECOPY] {
+        // 3 pushes + base cost + 3 per copied word + memory expansion
+        tester()
+            .code(copy(opcode, 0, 0, 33))
+            .gas_used(9 + 3 + 6 + 6)
+            .check();
+
+        // Memory is already expanded, only copy is charged.
+        tester()
+            .code(copy(opcode, 0, 0, 33).append_bc(copy(opcode, 1, 0, 32)))
+            .gas_used(24 + 9 + 3 + 3)
+            .check();
+
+        // Expansion from 2 to 3 words.
+        tester()
+            .code(copy(opcode, 0, 0, 33).append_bc(copy(opcode, 32, 0, 33)))
+            .gas_used(24 + 9 + 3 + 6 + 3)
+            .check();
+
+        // Copying nothing is charged base cost only.
+        tester().code(copy(opcode, 0, 0, 0)).gas_used(9 + 3).check();
+    }
+}
+
+#[test]
+fn zero_size_copy_at_huge_offset() {
+    for opcode in [
+        OpCode::CALLDATACOPY,
+        OpCode::CODECOPY,
+        OpCode::EXTCODECOPY,
+        OpCode::RETURNDATACOPY,
+    ] {
+        for dest in [U256::from(u64::from(u32::MAX) + 1), U256::MAX] {
+            // Return data is empty, so only zero offset is in bounds for it.
+            let src = if opcode == OpCode::RETURNDATACOPY {
+                U256::ZERO
+            } else {
+                U256::MAX
+            };
+
+            tester()
+                .code(copy(opcode, dest, src, 0).opcode(OpCode::MSIZE).ret_top())
+                .status(StatusCode::Success)
+                .output_value(0)
+                .check();
+        }
+    }
+}
+
+#[test]
+fn copy_size_overflow() {
+    let cases = [
+        (U256::ZERO, U256::from(u64::from(u32::MAX) + 1)),
+        (U256::ZERO, U256::MAX),
+        (U256::from(u32::MAX), U256::from(u32::MAX)),
+        (U256::MAX, U256::ONE),
+        // Fits into buffer size limit but is too expensive to expand memory to.
+        (U256::ZERO, U256::from(u32::MAX)),
+    ];
+
+    for opcode in [
+        OpCode::CALLDATACOPY,
+        OpCode::CODECOPY,
+        OpCode::EXTCODECOPY,
+        OpCode::RETURNDATACOPY,
+    ] {
+        for (dest, size) in cases {
+            tester()
+                .code(copy(opcode, dest, 0, size))
+                .gas(1_000_000)
+                .status(StatusCode::OutOfGas)
+                .gas_left(0)
+                .check();
+        }
+    }
+}
+
+#[test]
+fn returndatacopy_zero_size_out_of_range() {
+    tester()
+        .code(copy(OpCode::RETURNDATACOPY, 0, 0, 0))
+        .status(StatusCode::Success)
+        .check();
+
+    for src in [U256::ONE, U256::MAX] {
+        tester()
+            .code(copy(OpCode::RETURNDATACOPY, 0, src, 0))
+            .status(StatusCode::InvalidMemoryAccess)
+            .check();
+    }
+}
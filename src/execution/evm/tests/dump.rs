@@ -0,0 +1,82 @@
+use crate::{
+    execution::{
+        evm::{
+            util::{mocked_host::*, *},
+            *,
+        },
+        tracer::{NoopTracer, Tracer},
+    },
+    models::*,
+};
+use bytes::Bytes;
+use ethereum_types::Address;
+use ethnum::U256;
+
+#[derive(Default)]
+struct DumpTracer {
+    dumps: Vec<ExecutionStateDump>,
+}
+
+impl Tracer for DumpTracer {
+    fn trace_instructions(&self) -> bool {
+        true
+    }
+
+    fn capture_state(&mut self, env: &ExecutionState, pc: usize, _: OpCode, _: u64, _: u16) {
+        self.dumps.push(env.dump(pc));
+    }
+}
+
+#[test]
+fn replay_from_every_instruction() {
+    let code = AnalyzedCode::analyze(
+        &Bytecode::new()
+            .mstore_value(0, 0x2a)
+            .opcode(OpCode::CALLVALUE)
+            .opcode(OpCode::CALLER)
+            .opcode(OpCode::XOR)
+            .pushv(3)
+            .opcode(OpCode::CALLDATALOAD)
+            .opcode(OpCode::ADD)
+            .mstore(32)
+            .ret(0, 64)
+            .build(),
+    );
+    let message = InterpreterMessage {
+        kind: CallKind::Call,
+        is_static: false,
+        depth: 3,
+        gas: 100_000,
+        recipient: Address::repeat_byte(0xaa),
+        code_address: Address::repeat_byte(0xaa),
+        sender: Address::repeat_byte(0xcc),
+        input_data: Bytes::from_static(&[0xde, 0xad, 0xbe, 0xef]),
+        value: U256::from(5_u64),
+    };
+
+    let mut tracer = DumpTracer::default();
+    let output = code.clone().execute(
+        &mut MockedHost::default(),
+        &mut tracer,
+        message,
+        Revision::London,
+    );
+    assert_eq!(output.status_code, StatusCode::Success);
+
+    for dump in tracer.dumps {
+        let json = serde_json::to_string(&dump).unwrap();
+        let restored = serde_json::from_str::<ExecutionStateDump>(&json).unwrap();
+        assert_eq!(restored, dump);
+        assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+
+        let (state, pc) = restored.restore();
+        let replayed = code.clone().execute_from(
+            &mut MockedHost::default(),
+            &mut NoopTracer,
+            state,
+            pc,
+            Revision::London,
+        );
+        assert_eq!(replayed, output, "Replay from {} diverged", json);
+    }
+}
@@ -0,0 +1,78 @@
+//! Random bytecode must never crash the interpreter, only fail with an error status.
+
+use crate::{
+    execution::evm::{opcode::*, util::*},
+    models::*,
+};
+use ethnum::U256;
+use proptest::prelude::*;
+
+const GAS: i64 = 1_000_000;
+
+fn revision() -> impl Strategy<Value = Revision> {
+    (0..Revision::len()).prop_map(|i| Revision::iter().into_iter().nth(i).unwrap())
+}
+
+/// Operands around memory, buffer and stack limits.
+fn operand() -> impl Strategy<Value = U256> {
+    prop_oneof![
+        (0_u64..=64).prop_map(U256::from),
+        Just(U256::from(u32::MAX)),
+        Just(U256::from(u64::from(u32::MAX) + 1)),
+        Just(U256::from(u64::MAX)),
+        Just(U256::from(u128::MAX)),
+        Just(U256::MAX),
+        any::<[u8; 32]>().prop_map(U256::from_be_bytes),
+    ]
+}
+
+/// Valid instructions with operands that are likely to hit edge cases.
+fn instructions() -> impl Strategy<Value = Bytecode> {
+    prop::collection::vec(
+        prop_oneof![
+            operand().prop_map(|v| Bytecode::new().pushv(v)),
+            any::<u8>().prop_map(|op| Bytecode::new().opcode(OpCode(op))),
+        ],
+        0..256,
+    )
+    .prop_map(|instructions| {
+        instructions
+            .into_iter()
+            .fold(Bytecode::new(), |code, instruction| {
+                code.append_bc(instruction)
+            })
+    })
+}
+
+fn execute(code: impl Into<Bytecode>, input: Vec<u8>, revision: Revision) {
+    let output = EvmTester::new()
+        .code(code)
+        .input(input)
+        .revision(revision)
+        .gas(GAS)
+        .check_and_get_result();
+
+    assert!((0..=GAS).contains(&output.gas_left));
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn random_bytes(
+        code in prop::collection::vec(any::<u8>(), 0..1024),
+        input in prop::collection::vec(any::<u8>(), 0..64),
+        revision in revision(),
+    ) {
+        execute(code, input, revision);
+    }
+
+    #[test]
+    fn random_instructions(
+        code in instructions(),
+        input in prop::collection::vec(any::<u8>(), 0..64),
+        revision in revision(),
+    ) {
+        execute(code, input, revision);
+    }
+}
@@ -1,6 +1,11 @@
 mod basefee;
 mod call;
+mod continuation;
+mod copy;
+mod dump;
 mod eip2929;
 mod execute;
+mod fuzz;
+mod instructions;
 mod other;
 mod state;
@@ -18,17 +18,21 @@ fn exec(
     host: &mut MockedHost,
     revision: Revision,
     message: InterpreterMessage,
-    code: Vec<u8>,
+    code: &[u8],
     collect_traces: bool,
+    resumable: bool,
 ) -> Output {
     // Add EIP-2929 tweak.
     if revision >= Revision::Berlin {
         host.access_account(message.sender);
         host.access_account(message.recipient);
     }
-    let code = AnalyzedCode::analyze(&code);
+    let code = AnalyzedCode::analyze(code);
 
-    if collect_traces {
+    if resumable {
+        code.execute_resumable(collect_traces, message, revision)
+            .run_to_completion_with_host(host)
+    } else if collect_traces {
         code.execute(host, &mut StdoutTracer::default(), message, revision)
     } else {
         code.execute(host, &mut NoopTracer, message, revision)
@@ -233,13 +237,26 @@ impl EvmTester {
         for f in self.apply_host_fns {
             (f)(&mut host, &self.message);
         }
+        let mut resumable_host = host.clone();
         let output = exec(
             &mut host,
             self.revision,
             self.message.clone(),
-            self.code,
+            &self.code,
+            self.collect_traces,
+            false,
+        );
+
+        // Both interpreters share instruction code, so they must agree.
+        let resumable_output = exec(
+            &mut resumable_host,
+            self.revision,
+            self.message.clone(),
+            &self.code,
             self.collect_traces,
+            true,
         );
+        assert_eq!(output, resumable_output, "Resumable execution diverged");
 
         if let Some(status_codes) = self.expected_status_codes {
             assert!(
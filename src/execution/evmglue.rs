@@ -1,6 +1,7 @@
 use super::{
     address::*,
     analysis_cache::AnalysisCache,
+    block_hashes::BlockHashes,
     precompiled,
     tracer::{CodeKind, MessageKind, Tracer},
 };
@@ -30,23 +31,26 @@ pub struct CallResult {
     pub output_data: Bytes,
 }
 
-struct Evm<'r, 'state, 'tracer, 'analysis, 'h, 'c, 't, B>
+struct Evm<'r, 'state, 'tracer, 'analysis, 'bh, 'h, 'c, 't, B>
 where
     B: State,
 {
     state: &'state mut IntraBlockState<'r, B>,
     tracer: Option<&'tracer mut dyn Tracer>,
     analysis_cache: &'analysis mut AnalysisCache,
+    block_hashes: Option<&'bh mut BlockHashes>,
     header: &'h PartialHeader,
     block_spec: &'c BlockExecutionSpec,
     txn: &'t MessageWithSender,
     beneficiary: Address,
 }
 
-pub fn execute<'db, 'tracer, 'analysis, B: State>(
+#[allow(clippy::too_many_arguments)]
+pub fn execute<'db, 'tracer, 'analysis, 'bh, B: State>(
     state: &mut IntraBlockState<'db, B>,
     tracer: Option<&'tracer mut dyn Tracer>,
     analysis_cache: &'analysis mut AnalysisCache,
+    block_hashes: Option<&'bh mut BlockHashes>,
     header: &PartialHeader,
     block_spec: &BlockExecutionSpec,
     txn: &MessageWithSender,
@@ -56,6 +60,7 @@ pub fn execute<'db, 'tracer, 'analysis, B: State>(
         header,
         tracer,
         analysis_cache,
+        block_hashes,
         state,
         block_spec,
         txn,
@@ -92,8 +97,8 @@ pub fn execute<'db, 'tracer, 'analysis, B: State>(
     })
 }
 
-impl<'r, 'state, 'tracer, 'analysis, 'h, 'c, 't, B>
-    Evm<'r, 'state, 'tracer, 'analysis, 'h, 'c, 't, B>
+impl<'r, 'state, 'tracer, 'analysis, 'bh, 'h, 'c, 't, B>
+    Evm<'r, 'state, 'tracer, 'analysis, 'bh, 'h, 'c, 't, B>
 where
     B: State,
 {
@@ -140,6 +145,24 @@ where
             );
         };
 
+        let res = self.deploy(message, contract_addr)?;
+
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.capture_end(&res);
+        }
+
+        Ok(res)
+    }
+
+    fn deploy(&mut self, message: CreateMessage, contract_addr: Address) -> anyhow::Result<Output> {
+        let mut res = Output {
+            status_code: StatusCode::Success,
+            gas_left: message.gas,
+            output_data: Bytes::new(),
+            create_address: None,
+        };
+
+        let value = message.endowment;
         if self.state.get_nonce(contract_addr)? != 0
             || self.state.get_code_hash(contract_addr)? != EMPTY_HASH
         {
@@ -261,6 +284,30 @@ where
             )
         }
 
+        let res = self.call_code(message, precompiled, code)?;
+
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.capture_end(&res);
+        }
+
+        Ok(res)
+    }
+
+    fn call_code(
+        &mut self,
+        message: InterpreterMessage,
+        precompiled: bool,
+        code: Option<Bytes>,
+    ) -> anyhow::Result<Output> {
+        let mut res = Output {
+            status_code: StatusCode::Success,
+            gas_left: message.gas,
+            output_data: Bytes::new(),
+            create_address: None,
+        };
+
+        let value = message.value;
+
         // https://eips.ethereum.org/EIPS/eip-161
         if value == 0
             && self.block_spec.revision >= Revision::Spurious
@@ -380,15 +427,15 @@ where
     }
 }
 
-struct EvmHost<'r, 'state, 'tracer, 'analysis, 'h, 'c, 't, 'a, B>
+struct EvmHost<'r, 'state, 'tracer, 'analysis, 'bh, 'h, 'c, 't, 'a, B>
 where
     B: State,
 {
-    inner: &'a mut Evm<'r, 'state, 'tracer, 'analysis, 'h, 'c, 't, B>,
+    inner: &'a mut Evm<'r, 'state, 'tracer, 'analysis, 'bh, 'h, 'c, 't, B>,
 }
 
-impl<'r, 'state, 'tracer, 'analysis, 'h, 'c, 't, 'a, B: State> Host
-    for EvmHost<'r, 'state, 'tracer, 'analysis, 'h, 'c, 't, 'a, B>
+impl<'r, 'state, 'tracer, 'analysis, 'bh, 'h, 'c, 't, 'a, B: State> Host
+    for EvmHost<'r, 'state, 'tracer, 'analysis, 'bh, 'h, 'c, 't, 'a, B>
 {
     fn account_exists(&mut self, address: Address) -> bool {
         if self.inner.block_spec.revision >= Revision::Spurious {
@@ -542,17 +589,11 @@ impl<'r, 'state, 'tracer, 'analysis, 'h, 'c, 't, 'a, B: State> Host
     }
 
     fn selfdestruct(&mut self, address: Address, beneficiary: Address) {
-        self.inner.state.record_selfdestruct(address);
-        let balance = self.inner.state.get_balance(address).unwrap();
-        self.inner
-            .state
-            .add_to_balance(beneficiary, balance)
-            .unwrap();
-        self.inner.state.set_balance(address, 0).unwrap();
+        self.inner.state.selfdestruct(address, beneficiary).unwrap();
 
-        // if let Some(tracer) = &mut self.tracer {
-        //     tracer.capture_self_destruct(address, beneficiary);
-        // }
+        if let Some(tracer) = self.inner.tracer.as_mut() {
+            tracer.capture_self_destruct(address, beneficiary);
+        }
     }
 
     fn call(&mut self, msg: Call) -> Output {
@@ -598,6 +639,14 @@ impl<'r, 'state, 'tracer, 'analysis, 'h, 'c, 't, 'a, B: State> Host
     }
 
     fn get_block_hash(&mut self, block_number: u64) -> U256 {
+        if let Some(block_hashes) = self.inner.block_hashes.as_deref_mut() {
+            return h256_to_u256(
+                block_hashes
+                    .get(self.inner.state.db(), BlockNumber(block_number))
+                    .unwrap(),
+            );
+        }
+
         let base_number = self.inner.header.number;
         let distance = base_number.0 - block_number;
         assert!(distance <= 256);
@@ -657,8 +706,9 @@ mod tests {
             state,
             None,
             &mut AnalysisCache::default(),
+            None,
             header,
-            &MAINNET.collect_block_spec(header.number),
+            &MAINNET.collect_block_spec(header.number, header.timestamp),
             txn,
             gas,
         )
@@ -3,11 +3,14 @@ use crate::{consensus, crypto::*, models::*, State};
 
 pub mod address;
 pub mod analysis_cache;
+pub mod block_hashes;
+pub mod continuation;
 pub mod evm;
 pub mod evmglue;
 pub mod precompiled;
 pub mod processor;
 pub mod tracer;
+pub mod tx_validation;
 
 pub fn execute_block<S: State>(
     state: &mut S,
@@ -16,8 +19,8 @@ pub fn execute_block<S: State>(
     block: &BlockBodyWithSenders,
 ) -> anyhow::Result<Vec<Receipt>> {
     let mut analysis_cache = AnalysisCache::default();
-    let mut engine = consensus::engine_factory(config.clone())?;
-    let config = config.collect_block_spec(header.number);
+    let mut engine = consensus::engine_factory(config)?;
+    let config = config.collect_block_spec(header.number, header.timestamp);
     ExecutionProcessor::new(
         state,
         None,
@@ -34,7 +37,8 @@ pub fn execute_block<S: State>(
 mod tests {
     use super::{address::create_address, *};
     use crate::{
-        chain::protocol_param::param, crypto::root_hash, res::chainspec::MAINNET, InMemoryState,
+        chain::protocol_param::param, crypto::ordered_trie_root, res::chainspec::MAINNET,
+        InMemoryState,
     };
     use hex_literal::hex;
     use sha3::{Digest, Keccak256};
@@ -60,7 +64,7 @@ mod tests {
         ];
 
         assert_eq!(
-            root_hash(&receipts),
+            ordered_trie_root(&receipts),
             hex!("7ea023138ee7d80db04eeec9cf436dc35806b00cc5fe8e5f611fb7cf1b35b177").into()
         )
     }
@@ -88,7 +92,7 @@ mod tests {
             beneficiary: miner,
             gas_limit: 100_000,
             gas_used,
-            receipts_root: root_hash(&receipts),
+            receipts_root: ordered_trie_root(&receipts),
             ..PartialHeader::empty()
         };
 
@@ -176,7 +180,7 @@ mod tests {
         let gas_used = 26_149;
         header.gas_used = gas_used;
         receipts[0].cumulative_gas_used = gas_used;
-        header.receipts_root = root_hash(&receipts);
+        header.receipts_root = ordered_trie_root(&receipts);
 
         let tx = (t)(
             TransactionAction::Call(contract_address),
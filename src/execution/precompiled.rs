@@ -4,12 +4,7 @@ use bytes::{Buf, Bytes};
 use num_bigint::BigUint;
 use num_traits::Zero;
 use ripemd::*;
-use secp256k1::{
-    ecdsa::{RecoverableSignature, RecoveryId},
-    Message, SECP256K1,
-};
 use sha2::*;
-use sha3::*;
 use std::{
     cmp::min,
     convert::TryFrom,
@@ -90,6 +85,7 @@ fn ecrecover_run_inner(mut input: Bytes) -> Option<Bytes> {
     let mut sig = [0; 64];
     sig[..32].copy_from_slice(&r.0);
     sig[32..].copy_from_slice(&s.0);
+    let hash = H256(*array_ref!(input, 0, 32));
 
     let odd = if v == 28 {
         true
@@ -99,15 +95,10 @@ fn ecrecover_run_inner(mut input: Bytes) -> Option<Bytes> {
         return None;
     };
 
-    let sig =
-        RecoverableSignature::from_compact(&sig, RecoveryId::from_i32(odd.into()).ok()?).ok()?;
-
-    let public = &SECP256K1
-        .recover_ecdsa(&Message::from_slice(&input[..32]).ok()?, &sig)
-        .ok()?;
+    let signer = recover_signer(hash, &sig, odd).ok()?;
 
     let mut out = vec![0; 32];
-    out[12..].copy_from_slice(&Keccak256::digest(&public.serialize_uncompressed()[1..])[12..]);
+    out[12..].copy_from_slice(signer.as_bytes());
 
     Some(out.into())
 }
@@ -1,4 +1,10 @@
-use super::{analysis_cache::AnalysisCache, root_hash, tracer::Tracer};
+use super::{
+    analysis_cache::AnalysisCache,
+    block_hashes::BlockHashes,
+    ordered_trie_root,
+    tracer::Tracer,
+    tx_validation::{validate_transaction, ValidationFlags},
+};
 use crate::{
     chain::{
         intrinsic_gas::*,
@@ -15,13 +21,14 @@ use anyhow::Context;
 use std::cmp::min;
 use TransactionAction;
 
-pub struct ExecutionProcessor<'r, 'tracer, 'analysis, 'e, 'h, 'b, 'c, S>
+pub struct ExecutionProcessor<'r, 'tracer, 'analysis, 'bh, 'e, 'h, 'b, 'c, S>
 where
     S: State,
 {
     state: IntraBlockState<'r, S>,
     tracer: Option<&'tracer mut dyn Tracer>,
     analysis_cache: &'analysis mut AnalysisCache,
+    block_hashes: Option<&'bh mut BlockHashes>,
     engine: &'e mut dyn Consensus,
     header: &'h PartialHeader,
     block: &'b BlockBodyWithSenders,
@@ -29,8 +36,8 @@ where
     cumulative_gas_used: u64,
 }
 
-impl<'r, 'tracer, 'analysis, 'e, 'h, 'b, 'c, S>
-    ExecutionProcessor<'r, 'tracer, 'analysis, 'e, 'h, 'b, 'c, S>
+impl<'r, 'tracer, 'analysis, 'bh, 'e, 'h, 'b, 'c, S>
+    ExecutionProcessor<'r, 'tracer, 'analysis, 'bh, 'e, 'h, 'b, 'c, S>
 where
     S: State,
 {
@@ -47,6 +54,7 @@ where
             state: IntraBlockState::new(state),
             tracer,
             analysis_cache,
+            block_hashes: None,
             engine,
             header,
             block,
@@ -55,6 +63,12 @@ where
         }
     }
 
+    /// Serve BLOCKHASH from `block_hashes`, kept across the consecutive blocks of one chain.
+    pub fn set_block_hashes(&mut self, block_hashes: &'bh mut BlockHashes) {
+        block_hashes.advance(self.header);
+        self.block_hashes = Some(block_hashes);
+    }
+
     fn available_gas(&self) -> u64 {
         self.header.gas_limit - self.cumulative_gas_used
     }
@@ -68,44 +82,19 @@ where
     }
 
     pub fn validate_transaction(&mut self, tx: &MessageWithSender) -> anyhow::Result<()> {
-        pre_validate_transaction(
+        let sender_account = Account {
+            nonce: self.state.get_nonce(tx.sender)?,
+            balance: self.state.get_balance(tx.sender)?,
+            code_hash: self.state.get_code_hash(tx.sender)?,
+        };
+        validate_transaction(
             tx,
+            &sender_account,
             self.block_spec.params.chain_id,
+            self.block_spec.revision,
             self.header.base_fee_per_gas,
-        )
-        .expect("Tx must have been prevalidated");
-
-        if self.state.get_code_hash(tx.sender)? != EMPTY_HASH {
-            return Err(ValidationError::SenderNoEOA { sender: tx.sender }.into());
-        }
-
-        let expected_nonce = self.state.get_nonce(tx.sender)?;
-        if expected_nonce != tx.nonce() {
-            return Err(ValidationError::WrongNonce {
-                account: tx.sender,
-                expected: expected_nonce,
-                got: tx.nonce(),
-            }
-            .into());
-        }
-
-        // https://github.com/ethereum/EIPs/pull/3594
-        let max_gas_cost = U512::from(tx.gas_limit())
-            * U512::from(ethereum_types::U256::from(
-                tx.max_fee_per_gas().to_be_bytes(),
-            ));
-        // See YP, Eq (57) in Section 6.2 "Execution"
-        let v0 = max_gas_cost + U512::from(ethereum_types::U256::from(tx.value().to_be_bytes()));
-        let available_balance =
-            ethereum_types::U256::from(self.state.get_balance(tx.sender)?.to_be_bytes()).into();
-        if available_balance < v0 {
-            return Err(ValidationError::InsufficientFunds {
-                account: tx.sender,
-                available: available_balance,
-                required: v0,
-            }
-            .into());
-        }
+            ValidationFlags::BLOCK,
+        )?;
 
         let available_gas = self.available_gas();
         if available_gas < tx.gas_limit() {
@@ -152,7 +141,10 @@ where
         let g0 = intrinsic_gas(txn, rev >= Revision::Homestead, rev >= Revision::Istanbul);
         let gas = u128::from(txn.gas_limit())
             .checked_sub(g0)
-            .ok_or(ValidationError::IntrinsicGas)?
+            .ok_or(ValidationError::IntrinsicGas {
+                intrinsic_gas: g0,
+                gas_limit: txn.gas_limit(),
+            })?
             .try_into()
             .unwrap();
 
@@ -162,6 +154,7 @@ where
             #[allow(clippy::needless_option_as_deref)]
             self.tracer.as_deref_mut(),
             self.analysis_cache,
+            self.block_hashes.as_deref_mut(),
             self.header,
             self.block_spec,
             txn,
@@ -195,13 +188,27 @@ where
         })
     }
 
-    pub fn execute_block_no_post_validation(&mut self) -> anyhow::Result<Vec<Receipt>> {
-        let mut receipts = Vec::with_capacity(self.block.transactions.len());
-
+    /// Applies balance changes and system contract upgrades scheduled for this block.
+    fn apply_upgrades(&mut self) -> anyhow::Result<()> {
         for (&address, &balance) in &self.block_spec.balance_changes {
             self.state.set_balance(address, balance)?;
         }
 
+        for (&address, contract) in &self.block_spec.system_contract_changes {
+            // Precompiles are dispatched by the EVM based on revision.
+            if let Contract::Contract { code } = contract {
+                self.state.set_code(address, code.clone())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn execute_block_no_post_validation(&mut self) -> anyhow::Result<Vec<Receipt>> {
+        let mut receipts = Vec::with_capacity(self.block.transactions.len());
+
+        self.apply_upgrades()?;
+
         for (i, txn) in self.block.transactions.iter().enumerate() {
             self.validate_transaction(txn)
                 .with_context(|| format!("Failed to validate tx #{}", i))?;
@@ -252,7 +259,7 @@ where
         let rev = self.block_spec.revision;
 
         if rev >= Revision::Byzantium {
-            let expected = root_hash(&receipts);
+            let expected = ordered_trie_root(&receipts);
             if expected != self.header.receipts_root {
                 return Err(ValidationError::WrongReceiptsRoot {
                     expected,
@@ -308,6 +315,7 @@ mod tests {
     use bytes::Bytes;
     use bytes_literal::bytes;
     use hex_literal::hex;
+    use std::collections::HashSet;
 
     #[test]
     fn zero_gas_price() {
@@ -337,8 +345,8 @@ mod tests {
 
         let mut state = InMemoryState::default();
         let mut analysis_cache = AnalysisCache::default();
-        let mut engine = engine_factory(MAINNET.clone()).unwrap();
-        let block_spec = MAINNET.collect_block_spec(header.number);
+        let mut engine = engine_factory(&MAINNET).unwrap();
+        let block_spec = MAINNET.collect_block_spec(header.number, header.timestamp);
         let mut processor = ExecutionProcessor::new(
             &mut state,
             None,
@@ -353,6 +361,56 @@ mod tests {
         assert!(receipt.success);
     }
 
+    #[test]
+    fn system_contract_upgrades() {
+        let header = PartialHeader {
+            number: 100.into(),
+            gas_limit: 3_000_000,
+            ..PartialHeader::empty()
+        };
+        let block = Default::default();
+
+        let contract = hex!("000000000000000000000000000000000000beef").into();
+        let funded = hex!("000000000000000000000000000000000000cafe").into();
+        let code = bytes!("600160005500");
+
+        let mut spec = MAINNET.clone();
+        spec.contracts.insert(
+            header.number,
+            maplit::hashmap! { contract => Contract::Contract { code: code.clone() } },
+        );
+        spec.balances.insert(
+            header.number,
+            maplit::hashmap! { funded => U256::from(ETHER) },
+        );
+
+        let mut state = InMemoryState::default();
+        let mut analysis_cache = AnalysisCache::default();
+        let mut engine = engine_factory(&spec).unwrap();
+
+        let block_spec = spec.collect_block_spec(BlockNumber(99), header.timestamp);
+        assert!(block_spec.system_contract_changes.is_empty());
+        assert!(block_spec.balance_changes.is_empty());
+
+        let block_spec = spec.collect_block_spec(header.number, header.timestamp);
+        let mut processor = ExecutionProcessor::new(
+            &mut state,
+            None,
+            &mut analysis_cache,
+            &mut *engine,
+            &header,
+            &block,
+            &block_spec,
+        );
+        processor.execute_block_no_post_validation().unwrap();
+
+        assert_eq!(processor.state.get_code(contract).unwrap(), Some(code));
+        assert_eq!(
+            processor.state.get_balance(funded).unwrap(),
+            U256::from(ETHER)
+        );
+    }
+
     #[test]
     fn eip3607_reject_transactions_from_senders_with_deployed_code() {
         let header = PartialHeader {
@@ -382,8 +440,8 @@ mod tests {
 
         let mut state = InMemoryState::default();
         let mut analysis_cache = AnalysisCache::default();
-        let mut engine = engine_factory(MAINNET.clone()).unwrap();
-        let block_spec = MAINNET.collect_block_spec(header.number);
+        let mut engine = engine_factory(&MAINNET).unwrap();
+        let block_spec = MAINNET.collect_block_spec(header.number, header.timestamp);
         let mut processor = ExecutionProcessor::new(
             &mut state,
             None,
@@ -442,8 +500,8 @@ mod tests {
 
         let mut state = InMemoryState::default();
         let mut analysis_cache = AnalysisCache::default();
-        let mut engine = engine_factory(MAINNET.clone()).unwrap();
-        let block_spec = MAINNET.collect_block_spec(header.number);
+        let mut engine = engine_factory(&MAINNET).unwrap();
+        let block_spec = MAINNET.collect_block_spec(header.number, header.timestamp);
         let mut processor = ExecutionProcessor::new(
             &mut state,
             None,
@@ -560,8 +618,8 @@ mod tests {
 
         let mut state = InMemoryState::default();
         let mut analysis_cache = AnalysisCache::default();
-        let mut engine = engine_factory(MAINNET.clone()).unwrap();
-        let block_spec = MAINNET.collect_block_spec(header.number);
+        let mut engine = engine_factory(&MAINNET).unwrap();
+        let block_spec = MAINNET.collect_block_spec(header.number, header.timestamp);
         let mut processor = ExecutionProcessor::new(
             &mut state,
             None,
@@ -626,6 +684,110 @@ mod tests {
         );
     }
 
+    #[test]
+    fn selfdestruct_edge_cases() {
+        // Istanbul: refunds for selfdestruct, no access lists.
+        let header = PartialHeader {
+            number: 10_000_000.into(),
+            gas_limit: 10_000_000,
+            beneficiary: hex!("5a0b54d5dc17e0aadc383d2db43b0a0d3e029c4c").into(),
+            ..PartialHeader::empty()
+        };
+        let block = Default::default();
+        let sender = hex!("4bf2054ffae7a454a35fd8cf4be21b23b1f25a6f").into();
+        let beneficiary = Address::repeat_byte(0xbe);
+        let self_destructing = hex!("6d20c1c07e56b7098eb8c50ee03ba0f6f498a91d").into();
+
+        let mut state = InMemoryState::default();
+        let mut analysis_cache = AnalysisCache::default();
+        let mut engine = engine_factory(&MAINNET).unwrap();
+        let block_spec = MAINNET.collect_block_spec(header.number, header.timestamp);
+        let mut processor = ExecutionProcessor::new(
+            &mut state,
+            None,
+            &mut analysis_cache,
+            &mut *engine,
+            &header,
+            &block,
+            &block_spec,
+        );
+
+        processor.state().add_to_balance(sender, ETHER).unwrap();
+        // ADDRESS SELFDESTRUCT
+        processor
+            .state()
+            .set_code(self_destructing, hex!("30ff").to_vec().into())
+            .unwrap();
+        processor
+            .state()
+            .add_to_balance(self_destructing, GIGA)
+            .unwrap();
+
+        let t = |action, input, value, nonce| MessageWithSender {
+            message: Message::Legacy {
+                chain_id: None,
+                nonce,
+                gas_price: U256::ZERO,
+                gas_limit: 100_000,
+                action,
+                value,
+                input,
+            },
+            sender,
+        };
+
+        // Destructing to self burns the balance.
+        let receipt = processor
+            .execute_transaction(&t(
+                TransactionAction::Call(self_destructing),
+                Bytes::new(),
+                U256::ZERO,
+                0,
+            ))
+            .unwrap();
+        assert!(receipt.success);
+        assert!(!processor.state().exists(self_destructing).unwrap());
+        assert_eq!(
+            processor.state().get_balance(self_destructing).unwrap(),
+            U256::ZERO
+        );
+        // ADDRESS, SELFDESTRUCT, refund capped at half of the gas used.
+        let first_gas_used = (fee::G_TRANSACTION + 2 + 5_000) / 2;
+        assert_eq!(receipt.cumulative_gas_used, first_gas_used);
+
+        // Contract destructed by its own constructor never gets deployed, its endowment goes
+        // to a new beneficiary, and the destruction is refunded.
+        // PUSH20 beneficiary SELFDESTRUCT
+        let initcode = [&[0x73][..], beneficiary.as_bytes(), &[0xff]].concat();
+        let contract = create_address(sender, 1);
+        let receipt = processor
+            .execute_transaction(&t(
+                TransactionAction::Create,
+                initcode.into(),
+                U256::from(1000),
+                1,
+            ))
+            .unwrap();
+        assert!(receipt.success);
+        assert!(!processor.state().exists(contract).unwrap());
+        assert_eq!(
+            processor.state().get_balance(beneficiary).unwrap(),
+            U256::from(1000)
+        );
+
+        let gas = fee::G_TRANSACTION
+            + fee::G_TX_CREATE
+            + 22 * fee::G_TX_DATA_NON_ZERO_ISTANBUL
+            // PUSH20, SELFDESTRUCT, value sent to a new account
+            + 3
+            + 5_000
+            + 25_000;
+        assert_eq!(
+            receipt.cumulative_gas_used - first_gas_used,
+            gas - fee::R_SELF_DESTRUCT
+        );
+    }
+
     #[test]
     fn out_of_gas_during_account_recreation() {
         let block_number = 2_081_788.into();
@@ -669,8 +831,8 @@ mod tests {
             };
 
         let mut analysis_cache = AnalysisCache::default();
-        let mut engine = engine_factory(MAINNET.clone()).unwrap();
-        let block_spec = MAINNET.collect_block_spec(header.number);
+        let mut engine = engine_factory(&MAINNET).unwrap();
+        let block_spec = MAINNET.collect_block_spec(header.number, header.timestamp);
         let mut processor = ExecutionProcessor::new(
             &mut state,
             None,
@@ -724,8 +886,8 @@ mod tests {
 
         let mut state = InMemoryState::default();
         let mut analysis_cache = AnalysisCache::default();
-        let mut engine = engine_factory(MAINNET.clone()).unwrap();
-        let block_spec = MAINNET.collect_block_spec(header.number);
+        let mut engine = engine_factory(&MAINNET).unwrap();
+        let block_spec = MAINNET.collect_block_spec(header.number, header.timestamp);
         let mut processor = ExecutionProcessor::new(
             &mut state,
             None,
@@ -746,4 +908,143 @@ mod tests {
         // suicide_beneficiary should've been touched and deleted
         assert_eq!(state.read_account(suicide_beneficiary).unwrap(), None);
     }
+
+    #[test]
+    fn touched_empty_accounts() {
+        // Empty account left over by the Shanghai attacks.
+        let empty = hex!("0000000000000000000000000000000000c0ffee").into();
+        let missing = Address::repeat_byte(0xaa);
+        let sha256 = hex!("0000000000000000000000000000000000000002").into();
+        let ripemd = hex!("0000000000000000000000000000000000000003").into();
+        let identity = hex!("0000000000000000000000000000000000000004").into();
+        let sender = hex!("4bf2054ffae7a454a35fd8cf4be21b23b1f25a6f").into();
+        let toucher = hex!("6d20c1c07e56b7098eb8c50ee03ba0f6f498a91d").into();
+
+        // CALL every target with zero value and all the gas left, except for SHA256 and
+        // RIPEMD which get too little gas to run.
+        let mut code = vec![];
+        for (target, gas) in [
+            (empty, None),
+            (missing, None),
+            (identity, None),
+            (sha256, Some(1)),
+            (ripemd, Some(1)),
+        ] {
+            // PUSH1 0 (x5) PUSH20 target
+            code.extend_from_slice(&hex!("60006000600060006000"));
+            code.push(0x73);
+            code.extend_from_slice(target.as_bytes());
+            match gas {
+                // PUSH1 gas
+                Some(gas) => code.extend_from_slice(&[0x60, gas]),
+                // GAS
+                None => code.push(0x5a),
+            }
+            // CALL POP
+            code.extend_from_slice(&hex!("f150"));
+        }
+
+        let miner = hex!("2a65aca4d5fc5b5c859090a6c34d164135398226").into();
+
+        // Executes the call in `block_number` on top of empty `pre` accounts, returns the
+        // destructed accounts.
+        let run = |state: &mut InMemoryState, block_number: u64, pre: &[Address]| {
+            let header = PartialHeader {
+                number: block_number.into(),
+                gas_limit: 4_712_388,
+                beneficiary: miner,
+                ..PartialHeader::empty()
+            };
+            let block = Default::default();
+
+            for &address in pre {
+                state.update_account(address, None, Some(Account::default()));
+            }
+
+            let mut analysis_cache = AnalysisCache::default();
+            let mut engine = engine_factory(&MAINNET).unwrap();
+            let block_spec = MAINNET.collect_block_spec(header.number, header.timestamp);
+            let mut processor = ExecutionProcessor::new(
+                state,
+                None,
+                &mut analysis_cache,
+                &mut *engine,
+                &header,
+                &block,
+                &block_spec,
+            );
+
+            processor.state().add_to_balance(sender, ETHER).unwrap();
+            processor
+                .state()
+                .set_code(toucher, code.clone().into())
+                .unwrap();
+
+            let receipt = processor
+                .execute_transaction(&MessageWithSender {
+                    message: Message::Legacy {
+                        chain_id: None,
+                        nonce: 0,
+                        gas_price: U256::ZERO,
+                        gas_limit: 1_000_000,
+                        action: TransactionAction::Call(toucher),
+                        value: U256::ZERO,
+                        input: Bytes::new(),
+                    },
+                    sender,
+                })
+                .unwrap();
+            assert!(receipt.success);
+
+            // Failed call leaves no touch behind, but for RIPEMD.
+            assert!(!processor.state().touched.contains(&sha256));
+            assert!(processor.state().touched.contains(&ripemd));
+
+            let destructed = processor
+                .state()
+                .incarnations
+                .keys()
+                .copied()
+                .collect::<HashSet<_>>();
+
+            processor
+                .into_state()
+                .write_to_db(block_number.into())
+                .unwrap();
+
+            destructed
+        };
+
+        // Tangerine Whistle: zero-value calls create empty accounts, which is how the
+        // attacks bloated the state.
+        let mut state = InMemoryState::default();
+        assert_eq!(run(&mut state, 2_674_999, &[empty, ripemd]), HashSet::new());
+        for address in [empty, missing, identity, ripemd, miner] {
+            assert_eq!(
+                state.read_account(address).unwrap(),
+                Some(Account::default())
+            );
+        }
+        assert_eq!(state.read_account(sha256).unwrap(), None);
+
+        // Block with the RIPEMD anomaly, see Yellow Paper, Appendix K. Touched empty accounts
+        // are deleted, even the miner receiving no fee, and RIPEMD despite its call running
+        // out of gas.
+        let mut state = InMemoryState::default();
+        assert_eq!(
+            run(&mut state, 2_675_119, &[empty, ripemd]),
+            HashSet::from([empty, identity, ripemd, miner])
+        );
+        for address in [empty, missing, identity, sha256, ripemd, miner] {
+            assert_eq!(state.read_account(address).unwrap(), None);
+        }
+
+        // Missing RIPEMD stays touched after the failed call, yet there is nothing to delete.
+        let mut state = InMemoryState::default();
+        assert_eq!(
+            run(&mut state, 2_675_119, &[empty]),
+            HashSet::from([empty, identity, miner])
+        );
+        assert_eq!(state.read_account(ripemd).unwrap(), None);
+    }
 }
@@ -0,0 +1,106 @@
+use super::*;
+use crate::execution::evm::{
+    instructions::instruction_table::get_instruction_table, ExecutionState, OpCode,
+};
+
+/// Number of times each opcode was executed under each revision.
+#[derive(Clone, Debug)]
+pub struct OpcodeCoverage {
+    counts: [[u64; 256]; Revision::len()],
+}
+
+impl Default for OpcodeCoverage {
+    fn default() -> Self {
+        Self {
+            counts: [[0; 256]; Revision::len()],
+        }
+    }
+}
+
+impl OpcodeCoverage {
+    pub fn record(&mut self, revision: Revision, opcode: OpCode) {
+        self.counts[revision as usize][opcode.to_usize()] += 1;
+    }
+
+    pub fn count(&self, revision: Revision, opcode: OpCode) -> u64 {
+        self.counts[revision as usize][opcode.to_usize()]
+    }
+
+    pub fn merge(&mut self, other: &OpcodeCoverage) {
+        for (counts, other_counts) in self.counts.iter_mut().zip(&other.counts) {
+            for (count, other_count) in counts.iter_mut().zip(other_counts) {
+                *count += other_count;
+            }
+        }
+    }
+
+    /// Opcodes defined in a revision which were never executed under it.
+    pub fn uncovered(&self) -> impl Iterator<Item = (Revision, OpCode)> + '_ {
+        Revision::iter().into_iter().flat_map(move |revision| {
+            let table = get_instruction_table(revision);
+            (0..=u8::MAX)
+                .map(OpCode)
+                .filter(move |&opcode| {
+                    table[opcode.to_usize()].is_some() && self.count(revision, opcode) == 0
+                })
+                .map(move |opcode| (revision, opcode))
+        })
+    }
+
+    /// Tracer recording the opcodes executed under `revision`.
+    pub fn tracer(&mut self, revision: Revision) -> CoverageTracer<'_> {
+        CoverageTracer {
+            coverage: self,
+            revision,
+        }
+    }
+}
+
+/// Tracer which records executed opcodes into [`OpcodeCoverage`].
+#[derive(Debug)]
+pub struct CoverageTracer<'c> {
+    coverage: &'c mut OpcodeCoverage,
+    revision: Revision,
+}
+
+impl Tracer for CoverageTracer<'_> {
+    fn trace_instructions(&self) -> bool {
+        true
+    }
+
+    fn capture_state(&mut self, _: &ExecutionState, _: usize, op: OpCode, _: u64, _: u16) {
+        self.coverage.record(self.revision, op);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uncovered_per_revision() {
+        let mut coverage = OpcodeCoverage::default();
+        let mut other = OpcodeCoverage::default();
+        for revision in Revision::iter() {
+            for opcode in (0..=u8::MAX).map(OpCode) {
+                if opcode != OpCode::SELFBALANCE && opcode != OpCode::PUSH1 {
+                    coverage.record(revision, opcode);
+                }
+            }
+            other.record(revision, OpCode::PUSH1);
+        }
+        coverage.merge(&other);
+        assert_eq!(coverage.count(Revision::London, OpCode::PUSH1), 1);
+        assert_eq!(coverage.count(Revision::London, OpCode::ADD), 1);
+
+        // SELFBALANCE only exists as of Istanbul.
+        assert_eq!(
+            coverage.uncovered().collect::<Vec<_>>(),
+            Revision::iter()
+                .into_iter()
+                .filter(|&revision| revision >= Revision::Istanbul)
+                .map(|revision| (revision, OpCode::SELFBALANCE))
+                .collect::<Vec<_>>()
+        );
+    }
+}
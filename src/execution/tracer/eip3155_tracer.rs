@@ -74,7 +74,7 @@ impl Tracer for StdoutTracer {
                 pc,
                 op: op.0,
                 op_name: op.name(),
-                gas: env.gas_left as u64,
+                gas: env.gas_left() as u64,
                 stack: env.stack.clone(),
                 memory_size: env.memory.len()
             })
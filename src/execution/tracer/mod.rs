@@ -1,10 +1,13 @@
+pub mod coverage_tracer;
 pub mod eip3155_tracer;
 
 use auto_impl::auto_impl;
+pub use coverage_tracer::{CoverageTracer, OpcodeCoverage};
 pub use eip3155_tracer::StdoutTracer;
 
 use crate::{
     execution::evm::{ExecutionState, OpCode},
+    kv::tables::CallTraceStats,
     models::*,
 };
 use bytes::Bytes;
@@ -76,11 +79,16 @@ impl Tracer for NoopTracer {}
 pub struct CallTracerFlags {
     pub from: bool,
     pub to: bool,
+    /// Recorded if enabled with [`CallTracer::with_stats`].
+    pub stats: Option<CallTraceStats>,
 }
 
 #[derive(Debug, Default)]
 pub struct CallTracer {
     addresses: HashMap<Address, CallTracerFlags>,
+    record_stats: bool,
+    /// Callee and gas given to each call in progress.
+    frames: Vec<(Address, u64)>,
 }
 
 impl Tracer for CallTracer {
@@ -89,13 +97,37 @@ impl Tracer for CallTracer {
         _: u16,
         from: Address,
         to: Address,
-        _: MessageKind,
+        kind: MessageKind,
         _: Bytes,
-        _: u64,
-        _: U256,
+        gas: u64,
+        value: U256,
     ) {
         self.addresses.entry(from).or_default().from = true;
         self.addresses.entry(to).or_default().to = true;
+
+        if self.record_stats {
+            // Value of CALLCODE and DELEGATECALL stays with the caller.
+            if matches!(
+                kind,
+                MessageKind::Create
+                    | MessageKind::Call {
+                        call_kind: CallKind::Call,
+                        ..
+                    }
+            ) && value != U256::ZERO
+            {
+                self.stats(from).value_sent += value;
+                self.stats(to).value_received += value;
+            }
+            self.frames.push((to, gas));
+        }
+    }
+
+    fn capture_end(&mut self, output: &Output) {
+        if let Some((to, gas)) = self.frames.pop() {
+            let gas_used = gas.saturating_sub(output.gas_left.max(0) as u64);
+            self.stats(to).gas_used += gas_used;
+        }
     }
 
     fn capture_self_destruct(&mut self, caller: Address, beneficiary: Address) {
@@ -105,6 +137,22 @@ impl Tracer for CallTracer {
 }
 
 impl CallTracer {
+    /// Also accounts value transfers and gas used to the addresses.
+    pub fn with_stats() -> Self {
+        Self {
+            record_stats: true,
+            ..Default::default()
+        }
+    }
+
+    fn stats(&mut self, address: Address) -> &mut CallTraceStats {
+        self.addresses
+            .entry(address)
+            .or_default()
+            .stats
+            .get_or_insert_with(Default::default)
+    }
+
     pub fn into_sorted_iter(&self) -> impl Iterator<Item = (Address, CallTracerFlags)> {
         self.addresses
             .iter()
@@ -0,0 +1,350 @@
+//! Pre-execution transaction checks, see [YP] Section 6.2 "Execution", Eq (58).
+//!
+//! Block execution runs all of them, while transaction pool admission and `eth_call`
+//! relax some through [`ValidationFlags`].
+
+use crate::{
+    chain::{intrinsic_gas::*, protocol_param::param},
+    consensus::*,
+    models::*,
+};
+use ethereum_types::U512;
+
+/// How transaction nonce is checked against sender's account nonce.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NonceCheck {
+    /// Nonce must be the next one for the account.
+    Exact,
+    /// Nonce must not be used yet, gaps are allowed.
+    NotBelow,
+    /// Nonce is not checked.
+    Skip,
+}
+
+/// Checks to run in [`validate_transaction`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ValidationFlags {
+    pub nonce: NonceCheck,
+    /// Sender must be able to pay for gas at max fee and value.
+    pub balance: bool,
+    /// Max fee must cover block base fee.
+    pub base_fee: bool,
+    /// Sender must not have code deployed (EIP-3607).
+    pub sender_eoa: bool,
+}
+
+impl ValidationFlags {
+    /// All checks, as required for transactions included into a block.
+    pub const BLOCK: Self = Self {
+        nonce: NonceCheck::Exact,
+        balance: true,
+        base_fee: true,
+        sender_eoa: true,
+    };
+
+    /// Transaction pool accepts transactions with future nonces.
+    pub const TXPOOL: Self = Self {
+        nonce: NonceCheck::NotBelow,
+        ..Self::BLOCK
+    };
+
+    /// `eth_call` and gas estimation run on behalf of arbitrary accounts, with no fees paid.
+    pub const CALL: Self = Self {
+        nonce: NonceCheck::Skip,
+        balance: false,
+        base_fee: false,
+        sender_eoa: false,
+    };
+}
+
+pub fn validate_transaction(
+    txn: &MessageWithSender,
+    sender_account: &Account,
+    chain_id: ChainId,
+    revision: Revision,
+    base_fee_per_gas: Option<U256>,
+    flags: ValidationFlags,
+) -> Result<(), ValidationError> {
+    pre_validate_transaction(txn, chain_id, base_fee_per_gas.filter(|_| flags.base_fee))?;
+
+    // Chain id is signed into transactions since Spurious Dragon only.
+    if revision < Revision::Spurious {
+        if let Some(got) = txn.chain_id() {
+            return Err(ValidationError::WrongChainId {
+                expected: None,
+                got,
+            });
+        }
+    }
+
+    let g0 = intrinsic_gas(
+        txn,
+        revision >= Revision::Homestead,
+        revision >= Revision::Istanbul,
+    );
+    if u128::from(txn.gas_limit()) < g0 {
+        return Err(ValidationError::IntrinsicGas {
+            intrinsic_gas: g0,
+            gas_limit: txn.gas_limit(),
+        });
+    }
+
+    if revision >= Revision::Shanghai
+        && matches!(txn.action(), TransactionAction::Create)
+        && txn.input().len() > param::MAX_INITCODE_SIZE
+    {
+        return Err(ValidationError::InitCodeTooLarge {
+            max: param::MAX_INITCODE_SIZE,
+            got: txn.input().len(),
+        });
+    }
+
+    if flags.sender_eoa && sender_account.code_hash != EMPTY_HASH {
+        return Err(ValidationError::SenderNoEOA { sender: txn.sender });
+    }
+
+    let nonce_ok = match flags.nonce {
+        NonceCheck::Exact => txn.nonce() == sender_account.nonce,
+        NonceCheck::NotBelow => txn.nonce() >= sender_account.nonce,
+        NonceCheck::Skip => true,
+    };
+    if !nonce_ok {
+        return Err(ValidationError::WrongNonce {
+            account: txn.sender,
+            expected: sender_account.nonce,
+            got: txn.nonce(),
+        });
+    }
+
+    if flags.balance {
+        // https://github.com/ethereum/EIPs/pull/3594
+        let max_gas_cost = U512::from(txn.gas_limit())
+            * U512::from(ethereum_types::U256::from(
+                txn.max_fee_per_gas().to_be_bytes(),
+            ));
+        // See YP, Eq (57) in Section 6.2 "Execution"
+        let v0 = max_gas_cost + U512::from(ethereum_types::U256::from(txn.value().to_be_bytes()));
+        let available_balance =
+            ethereum_types::U256::from(sender_account.balance.to_be_bytes()).into();
+        if available_balance < v0 {
+            return Err(ValidationError::InsufficientFunds {
+                account: txn.sender,
+                available: available_balance,
+                required: v0,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use hex_literal::hex;
+
+    const CHAIN_ID: ChainId = ChainId(1);
+
+    fn txn(nonce: u64, action: TransactionAction, input: Bytes) -> MessageWithSender {
+        MessageWithSender {
+            message: Message::EIP1559 {
+                chain_id: CHAIN_ID,
+                nonce,
+                max_priority_fee_per_gas: U256::ZERO,
+                max_fee_per_gas: U256::from(10 * GIGA),
+                gas_limit: 100_000,
+                action,
+                value: U256::from(ETHER),
+                input,
+                access_list: Default::default(),
+            },
+            sender: hex!("71562b71999873db5b286df957af199ec94617f7").into(),
+        }
+    }
+
+    fn validate(
+        txn: &MessageWithSender,
+        account: &Account,
+        flags: ValidationFlags,
+    ) -> Result<(), ValidationError> {
+        validate_transaction(
+            txn,
+            account,
+            CHAIN_ID,
+            Revision::London,
+            Some(U256::from(20 * GIGA)),
+            flags,
+        )
+    }
+
+    #[test]
+    fn flags() {
+        let account = Account {
+            nonce: 5,
+            balance: U256::from(ETHER),
+            code_hash: EMPTY_HASH,
+        };
+        let call = txn(7, TransactionAction::Call(Address::zero()), Bytes::new());
+
+        assert!(matches!(
+            validate(&call, &account, ValidationFlags::BLOCK),
+            Err(ValidationError::MaxFeeLessThanBase { .. })
+        ));
+        assert!(matches!(
+            validate(
+                &call,
+                &account,
+                ValidationFlags {
+                    base_fee: false,
+                    ..ValidationFlags::BLOCK
+                }
+            ),
+            Err(ValidationError::WrongNonce {
+                expected: 5,
+                got: 7,
+                ..
+            })
+        ));
+        assert!(matches!(
+            validate(
+                &call,
+                &account,
+                ValidationFlags {
+                    base_fee: false,
+                    ..ValidationFlags::TXPOOL
+                }
+            ),
+            Err(ValidationError::InsufficientFunds { .. })
+        ));
+        assert_eq!(validate(&call, &account, ValidationFlags::CALL), Ok(()));
+
+        let contract = Account {
+            code_hash: H256::repeat_byte(0xaa),
+            ..account
+        };
+        assert_eq!(validate(&call, &contract, ValidationFlags::CALL), Ok(()));
+        assert_eq!(
+            validate(
+                &call,
+                &contract,
+                ValidationFlags {
+                    sender_eoa: true,
+                    ..ValidationFlags::CALL
+                }
+            ),
+            Err(ValidationError::SenderNoEOA {
+                sender: call.sender
+            })
+        );
+    }
+
+    #[test]
+    fn chain_id() {
+        let account = Account {
+            balance: U256::from(ETHER),
+            ..Account::default()
+        };
+        let legacy = |chain_id| MessageWithSender {
+            message: Message::Legacy {
+                chain_id,
+                nonce: 0,
+                gas_price: U256::from(GIGA),
+                gas_limit: 21_000,
+                action: TransactionAction::Call(Address::zero()),
+                value: U256::ZERO,
+                input: Bytes::new(),
+            },
+            sender: Address::repeat_byte(0xbb),
+        };
+
+        for (chain_id, revision, res) in [
+            (None, Revision::Homestead, Ok(())),
+            (None, Revision::London, Ok(())),
+            (
+                Some(CHAIN_ID),
+                Revision::Homestead,
+                Err(ValidationError::WrongChainId {
+                    expected: None,
+                    got: CHAIN_ID,
+                }),
+            ),
+            (Some(CHAIN_ID), Revision::Spurious, Ok(())),
+            (
+                Some(ChainId(5)),
+                Revision::London,
+                Err(ValidationError::WrongChainId {
+                    expected: Some(CHAIN_ID),
+                    got: ChainId(5),
+                }),
+            ),
+        ] {
+            assert_eq!(
+                validate_transaction(
+                    &legacy(chain_id),
+                    &account,
+                    CHAIN_ID,
+                    revision,
+                    None,
+                    ValidationFlags::BLOCK
+                ),
+                res,
+                "chain id {:?} at {:?}",
+                chain_id,
+                revision
+            );
+        }
+    }
+
+    #[test]
+    fn intrinsic_gas_and_init_code_size() {
+        let account = Account::default();
+
+        let create = txn(
+            0,
+            TransactionAction::Create,
+            vec![1; param::MAX_INITCODE_SIZE + 1].into(),
+        );
+        assert!(matches!(
+            validate(&create, &account, ValidationFlags::CALL),
+            Err(ValidationError::IntrinsicGas { .. })
+        ));
+
+        let create = MessageWithSender {
+            message: Message::EIP1559 {
+                chain_id: CHAIN_ID,
+                nonce: 0,
+                max_priority_fee_per_gas: U256::ZERO,
+                max_fee_per_gas: U256::ZERO,
+                gas_limit: 1_000_000,
+                action: TransactionAction::Create,
+                value: U256::ZERO,
+                input: create.input().clone(),
+                access_list: Default::default(),
+            },
+            sender: create.sender,
+        };
+        for (revision, res) in [
+            (Revision::London, Ok(())),
+            (
+                Revision::Shanghai,
+                Err(ValidationError::InitCodeTooLarge {
+                    max: param::MAX_INITCODE_SIZE,
+                    got: param::MAX_INITCODE_SIZE + 1,
+                }),
+            ),
+        ] {
+            assert_eq!(
+                validate_transaction(
+                    &create,
+                    &account,
+                    CHAIN_ID,
+                    revision,
+                    None,
+                    ValidationFlags::BLOCK
+                ),
+                res
+            );
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! Logging of the interrupts of resumable computations, enabled by the `trace` feature.
+//!
+//! Every resume is logged with the data it was given and what the generator yielded next,
+//! under one sequence number shared by all generators. The interleaving of several pipelines,
+//! e.g. when one waits for another that never resumes it, can then be read off the log.
+
+use std::{
+    fmt::Debug,
+    ops::{Generator, GeneratorState},
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use tracing::*;
+
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Same as [`Generator::resume`], logging the resume data and the outcome.
+pub(crate) fn resume<G, R>(
+    name: &'static str,
+    generator: Pin<&mut G>,
+    resume_data: R,
+) -> GeneratorState<G::Yield, G::Return>
+where
+    G: Generator<R> + ?Sized,
+    G::Yield: Debug,
+    R: Debug,
+{
+    let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    let generator_id = &*generator as *const G as *const () as usize;
+    trace!(
+        "{} {:#x} #{}: resumed with {:?}",
+        name,
+        generator_id,
+        sequence,
+        resume_data
+    );
+
+    let state = generator.resume(resume_data);
+    match &state {
+        GeneratorState::Yielded(interrupt_data) => trace!(
+            "{} {:#x} #{}: interrupted with {:?}",
+            name,
+            generator_id,
+            sequence,
+            interrupt_data
+        ),
+        GeneratorState::Complete(_) => {
+            trace!("{} {:#x} #{}: complete", name, generator_id, sequence)
+        }
+    }
+
+    state
+}
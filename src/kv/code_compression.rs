@@ -0,0 +1,326 @@
+use super::{mdbx::*, tables, KvError};
+use anyhow::{bail, format_err};
+use bytes::Bytes;
+use lru::LruCache;
+use parking_lot::{Mutex, RwLock};
+use std::{
+    io::{Read, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use tracing::*;
+use zstd::dict::{DecoderDictionary, EncoderDictionary};
+
+const TAG_RAW: u8 = 0;
+const TAG_ZSTD: u8 = 1;
+const TAG_ZSTD_DICT: u8 = 2;
+
+const COMPRESSION_LEVEL: i32 = 19;
+/// Code shorter than that does not get smaller after compression.
+const MIN_COMPRESSED_LEN: usize = 64;
+
+const DICTIONARY_SIZE: usize = 128 * 1024;
+const MIN_TRAINING_SAMPLES: usize = 1000;
+const MAX_TRAINING_SAMPLES: usize = 20_000;
+
+const DECODE_CACHE_SIZE: usize = 4096;
+
+/// Encodes the values of the `Code` table.
+///
+/// Every value is prefixed with a tag telling how it is stored,
+/// so that compression can be turned on and off without a migration.
+pub struct CodeCodec {
+    compress: bool,
+    dictionary: Option<(EncoderDictionary<'static>, DecoderDictionary<'static>)>,
+    /// Keyed by length and checksum of the stored frame, which is compared on hit.
+    decode_cache: Mutex<LruCache<(usize, [u8; 4]), (Bytes, Bytes)>>,
+}
+
+impl std::fmt::Debug for CodeCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CodeCodec")
+            .field("compress", &self.compress)
+            .field("dictionary", &self.dictionary.is_some())
+            .finish()
+    }
+}
+
+impl CodeCodec {
+    pub fn new(compress: bool, dictionary: Option<&[u8]>) -> Self {
+        Self {
+            compress,
+            dictionary: dictionary.map(|dictionary| {
+                (
+                    EncoderDictionary::copy(dictionary, COMPRESSION_LEVEL),
+                    DecoderDictionary::copy(dictionary),
+                )
+            }),
+            decode_cache: Mutex::new(LruCache::new(DECODE_CACHE_SIZE)),
+        }
+    }
+
+    pub fn encode(&self, code: &[u8]) -> Vec<u8> {
+        if self.compress && code.len() >= MIN_COMPRESSED_LEN {
+            if let Ok(encoded) = self.compress(code) {
+                if encoded.len() < code.len() + 1 {
+                    return encoded;
+                }
+            }
+        }
+
+        let mut encoded = Vec::with_capacity(code.len() + 1);
+        encoded.push(TAG_RAW);
+        encoded.extend_from_slice(code);
+        encoded
+    }
+
+    fn compress(&self, code: &[u8]) -> std::io::Result<Vec<u8>> {
+        let tag = if self.dictionary.is_some() {
+            TAG_ZSTD_DICT
+        } else {
+            TAG_ZSTD
+        };
+        let mut encoded = vec![tag];
+        let mut encoder = match &self.dictionary {
+            Some((dictionary, _)) => {
+                zstd::Encoder::with_prepared_dictionary(&mut encoded, dictionary)?
+            }
+            None => zstd::Encoder::new(&mut encoded, COMPRESSION_LEVEL)?,
+        };
+        encoder.include_checksum(true)?;
+        encoder.include_contentsize(true)?;
+        encoder.write_all(code)?;
+        encoder.finish()?;
+        Ok(encoded)
+    }
+
+    pub fn decode(&self, b: &[u8]) -> anyhow::Result<Bytes> {
+        let (&tag, frame) = b
+            .split_first()
+            .ok_or_else(|| format_err!("empty code value"))?;
+        match tag {
+            TAG_RAW => Ok(frame.to_vec().into()),
+            TAG_ZSTD | TAG_ZSTD_DICT => {
+                let cache_key = (frame.len(), checksum(frame));
+                if let Some((cached_frame, code)) = self.decode_cache.lock().get(&cache_key) {
+                    if cached_frame.as_ref() == frame {
+                        return Ok(code.clone());
+                    }
+                }
+
+                let code = self.decompress(tag, frame)?;
+                self.decode_cache
+                    .lock()
+                    .put(cache_key, (frame.to_vec().into(), code.clone()));
+                Ok(code)
+            }
+            other => bail!("unknown code encoding {}", other),
+        }
+    }
+
+    fn decompress(&self, tag: u8, frame: &[u8]) -> anyhow::Result<Bytes> {
+        let mut code = vec![];
+        if tag == TAG_ZSTD_DICT {
+            let (_, dictionary) = self
+                .dictionary
+                .as_ref()
+                .ok_or_else(|| format_err!("code compression dictionary is not loaded"))?;
+            zstd::Decoder::with_prepared_dictionary(frame, dictionary)?.read_to_end(&mut code)?;
+        } else {
+            zstd::Decoder::new(frame)?.read_to_end(&mut code)?;
+        }
+        Ok(code.into())
+    }
+}
+
+/// Content checksum at the end of a zstd frame.
+fn checksum(frame: &[u8]) -> [u8; 4] {
+    let mut out = [0; 4];
+    if frame.len() >= 4 {
+        out.copy_from_slice(&frame[frame.len() - 4..]);
+    }
+    out
+}
+
+/// The `Code` table codec of one environment, with the dictionary stored in its
+/// `CodeDictionary` table. Stores uncompressed code until [`init`] turns compression on.
+#[derive(Debug)]
+pub struct CodeCodecCache {
+    compress: AtomicBool,
+    codec: RwLock<Arc<CodeCodec>>,
+    /// Set once `codec` holds the stored dictionary, which never changes afterwards.
+    has_dictionary: AtomicBool,
+}
+
+impl Default for CodeCodecCache {
+    fn default() -> Self {
+        Self {
+            compress: AtomicBool::new(false),
+            codec: RwLock::new(Arc::new(CodeCodec::new(false, None))),
+            has_dictionary: AtomicBool::new(false),
+        }
+    }
+}
+
+impl CodeCodecCache {
+    /// Codec for a transaction of the environment. Until a dictionary is found, every
+    /// transaction looks it up, as another process may have trained it in the meantime.
+    pub fn resolve<K: TransactionKind, E: EnvironmentKind>(
+        &self,
+        tx: &MdbxTransaction<'_, K, E>,
+    ) -> Result<Arc<CodeCodec>, KvError> {
+        if !self.has_dictionary.load(Ordering::Acquire) {
+            if let Some(dictionary) = tx.get(tables::CodeDictionary, Default::default())? {
+                let mut codec = self.codec.write();
+                if !self.has_dictionary.load(Ordering::Acquire) {
+                    *codec = Arc::new(CodeCodec::new(
+                        self.compress.load(Ordering::Acquire),
+                        Some(&dictionary),
+                    ));
+                    self.has_dictionary.store(true, Ordering::Release);
+                }
+                return Ok(codec.clone());
+            }
+        }
+
+        Ok(self.codec.read().clone())
+    }
+
+    /// Sets whether the code written from now on is compressed.
+    pub fn set_compress(&self, compress: bool) {
+        let mut codec = self.codec.write();
+        self.compress.store(compress, Ordering::Release);
+        *codec = Arc::new(CodeCodec::new(compress, None));
+        self.has_dictionary.store(false, Ordering::Release);
+    }
+}
+
+/// Trains a zstd dictionary on contract code samples.
+pub fn train_dictionary<S: AsRef<[u8]>>(samples: &[S]) -> anyhow::Result<Vec<u8>> {
+    Ok(zstd::dict::from_samples(samples, DICTIONARY_SIZE)?)
+}
+
+/// Sets whether the code written to `db` is compressed. If so, and there is no dictionary
+/// yet, one is trained on the stored code.
+pub fn init<E: EnvironmentKind>(db: &MdbxEnvironment<E>, compress: bool) -> anyhow::Result<()> {
+    db.code_codec().set_compress(compress);
+    if !compress {
+        return Ok(());
+    }
+
+    let tx = db.begin_mutable()?;
+    if tx
+        .get(tables::CodeDictionary, Default::default())?
+        .is_some()
+    {
+        return Ok(());
+    }
+
+    let samples = tx
+        .cursor(tables::Code)?
+        .walk(None)
+        .take(MAX_TRAINING_SAMPLES)
+        .map(|entry| entry.map(|(_, code)| code.0))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    if samples.len() >= MIN_TRAINING_SAMPLES {
+        info!("Training code dictionary on {} contracts", samples.len());
+        let trained = Bytes::from(train_dictionary(&samples)?);
+        tx.set(tables::CodeDictionary, Default::default(), trained)?;
+        tx.commit()?;
+    } else {
+        info!("Not enough contracts to train code dictionary yet, compressing without one");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crypto::keccak256,
+        kv::{new_mem_database, traits::*, value_codec::ValueCodec, CustomTable},
+    };
+
+    #[test]
+    fn roundtrip() {
+        // typical solidity prologue, repeated to be compressible
+        let code = hex::decode("6080604052348015600f57600080fd5b50".repeat(16)).unwrap();
+        let short_code = hex::decode("6080604052").unwrap();
+
+        let dictionary = train_dictionary(&vec![code.clone(); 64]).ok();
+        for codec in [
+            CodeCodec::new(false, None),
+            CodeCodec::new(true, None),
+            CodeCodec::new(true, dictionary.as_deref()),
+        ] {
+            for code in [&code, &short_code] {
+                let encoded = codec.encode(code);
+                assert!(encoded.len() <= code.len() + 1);
+                assert_eq!(codec.decode(&encoded).unwrap().as_ref(), code.as_slice());
+                // cached
+                assert_eq!(codec.decode(&encoded).unwrap().as_ref(), code.as_slice());
+            }
+        }
+
+        assert_eq!(CodeCodec::new(true, None).encode(&code)[0], TAG_ZSTD);
+        assert_eq!(CodeCodec::new(false, None).encode(&code)[0], TAG_RAW);
+        assert!(CodeCodec::new(false, None)
+            .decode(&CodeCodec::new(true, None).encode(&code))
+            .is_ok());
+    }
+
+    #[test]
+    fn init_trains_on_stored_code() {
+        let db = new_mem_database().unwrap();
+        let prologue = hex::decode("6080604052348015600f57600080fd5b50".repeat(8)).unwrap();
+        let tx = db.begin_mutable().unwrap();
+        for n in 0..MIN_TRAINING_SAMPLES as u64 {
+            let mut code = prologue.clone();
+            for i in 0..4_u64 {
+                code.extend_from_slice(keccak256((n * 4 + i).to_be_bytes()).as_bytes());
+            }
+            let code = Bytes::from(code);
+            tx.set(tables::Code, keccak256(&code), code.into()).unwrap();
+        }
+        tx.commit().unwrap();
+
+        init(&db, true).unwrap();
+
+        let tx = db.begin_mutable().unwrap();
+        assert!(tx
+            .get(tables::CodeDictionary, Default::default())
+            .unwrap()
+            .is_some());
+        let code = Bytes::from(prologue.repeat(2));
+        let code_hash = keccak256(&code);
+        tx.set(tables::Code, code_hash, code.clone().into())
+            .unwrap();
+        tx.commit().unwrap();
+
+        let tx = db.begin().unwrap();
+        let stored = tx
+            .cursor(CustomTable::from(tables::Code::const_db_name().to_string()))
+            .unwrap()
+            .with_codec(ValueCodec::Identity)
+            .seek_exact(code_hash.as_bytes().to_vec())
+            .unwrap()
+            .unwrap()
+            .1;
+        assert_eq!(stored[0], TAG_ZSTD_DICT);
+        assert_eq!(
+            tx.get(tables::Code, code_hash).unwrap(),
+            Some(code.clone().into())
+        );
+
+        // an environment with the dictionary decodes it without init
+        db.code_codec().set_compress(false);
+        assert_eq!(
+            db.begin().unwrap().get(tables::Code, code_hash).unwrap(),
+            Some(code.into())
+        );
+    }
+}
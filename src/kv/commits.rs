@@ -0,0 +1,135 @@
+use parking_lot::Mutex;
+use std::{
+    fmt,
+    sync::atomic::{AtomicI64, AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Commits of write transactions in an environment, for tuning the sync mode.
+#[derive(Debug, Default)]
+pub struct CommitMetrics {
+    commits: AtomicU64,
+    duration_us: AtomicU64,
+    max_duration_us: AtomicU64,
+    /// Slowest commit since the last [`CommitMetrics::take_interval`].
+    interval_max_duration_us: AtomicU64,
+    db_growth: AtomicI64,
+    /// Totals as of the last [`CommitMetrics::take_interval`].
+    interval_start: Mutex<CommitStats>,
+}
+
+impl CommitMetrics {
+    pub fn record(&self, duration: Duration, db_growth: Option<i64>) {
+        let duration_us = duration.as_micros() as u64;
+        self.commits.fetch_add(1, Ordering::Relaxed);
+        self.duration_us.fetch_add(duration_us, Ordering::Relaxed);
+        self.max_duration_us
+            .fetch_max(duration_us, Ordering::Relaxed);
+        self.interval_max_duration_us
+            .fetch_max(duration_us, Ordering::Relaxed);
+        if let Some(db_growth) = db_growth {
+            self.db_growth.fetch_add(db_growth, Ordering::Relaxed);
+        }
+    }
+
+    pub fn stats(&self) -> CommitStats {
+        CommitStats {
+            commits: self.commits.load(Ordering::Relaxed),
+            duration: Duration::from_micros(self.duration_us.load(Ordering::Relaxed)),
+            max_duration: Duration::from_micros(self.max_duration_us.load(Ordering::Relaxed)),
+            db_growth: self.db_growth.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Commits made since the previous call, or since the environment was opened.
+    pub fn take_interval(&self) -> CommitStats {
+        let mut start = self.interval_start.lock();
+        let totals = self.stats();
+        let max_duration = self.interval_max_duration_us.swap(0, Ordering::Relaxed);
+
+        let interval = CommitStats {
+            commits: totals.commits - start.commits,
+            duration: totals.duration.saturating_sub(start.duration),
+            max_duration: Duration::from_micros(max_duration),
+            db_growth: totals.db_growth - start.db_growth,
+        };
+        *start = totals;
+        interval
+    }
+}
+
+/// Commits recorded by [`CommitMetrics`], since the environment was opened or over an interval.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CommitStats {
+    pub commits: u64,
+    /// Time spent committing, flushing to disk included.
+    pub duration: Duration,
+    /// Slowest commit.
+    pub max_duration: Duration,
+    /// Change of the used part of the data files, an estimate of bytes written by the commits.
+    pub db_growth: i64,
+}
+
+impl CommitStats {
+    pub fn mean_duration(&self) -> Duration {
+        if self.commits == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_nanos((self.duration.as_nanos() / self.commits as u128) as u64)
+        }
+    }
+}
+
+impl fmt::Display for CommitStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} commits taking {:?} on average, {:?} at most, database {}{}",
+            self.commits,
+            self.mean_duration(),
+            self.max_duration,
+            if self.db_growth < 0 { "-" } else { "+" },
+            bytesize::ByteSize::b(self.db_growth.unsigned_abs())
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        kv::{new_mem_database, tables},
+        models::*,
+    };
+
+    #[test]
+    fn commits_are_recorded() {
+        let db = new_mem_database().unwrap();
+        assert_eq!(db.commit_stats().commits, 0);
+
+        let tx = db.begin_mutable().unwrap();
+        // enough to outgrow the pages freed while creating the tables
+        for i in 0..10_000 {
+            tx.set(
+                tables::CanonicalHeader,
+                BlockNumber(i),
+                H256::repeat_byte(1),
+            )
+            .unwrap();
+        }
+        tx.commit().unwrap();
+
+        // read transactions are not commits
+        drop(db.begin().unwrap());
+
+        let stats = db.commit_stats();
+        assert_eq!(stats.commits, 1);
+        assert!(stats.db_growth > 0);
+
+        let interval = db.take_commit_interval();
+        assert_eq!(interval.commits, 1);
+        assert_eq!(interval.db_growth, stats.db_growth);
+        assert_eq!(interval.max_duration, stats.max_duration);
+        assert_eq!(db.take_commit_interval(), CommitStats::default());
+    }
+}
@@ -0,0 +1,38 @@
+use super::readers::ReaderExpired;
+use thiserror::Error;
+
+pub type BoxedError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Failure of a transaction or cursor operation of [`super::mdbx`].
+///
+/// Opening environments, migrations and the iterators returned by the cursor walks still
+/// report [`anyhow::Error`].
+#[derive(Debug, Error)]
+pub enum KvError {
+    #[error("failed to decode {table} entry{}: {source}", .key.as_ref().map(|key| format!(" at key 0x{}", hex::encode(key))).unwrap_or_default())]
+    Decode {
+        table: String,
+        /// Encoded key of the entry, if known.
+        key: Option<Vec<u8>>,
+        #[source]
+        source: BoxedError,
+    },
+    #[error(transparent)]
+    ReaderExpired(#[from] ReaderExpired),
+    #[error("mdbx error: {0}")]
+    Mdbx(#[from] ::mdbx::Error),
+}
+
+impl KvError {
+    /// Tells a decoding failure of an entry in `table` from other MDBX errors.
+    pub(crate) fn from_mdbx(table: &str, key: Option<&[u8]>, e: ::mdbx::Error) -> Self {
+        match e {
+            ::mdbx::Error::DecodeError(source) => Self::Decode {
+                table: table.to_string(),
+                key: key.map(<[u8]>::to_vec),
+                source,
+            },
+            other => Self::Mdbx(other),
+        }
+    }
+}
@@ -1,9 +1,21 @@
 use crate::kv::{traits::*, *};
 use ::mdbx::{DatabaseFlags, WriteFlags};
 pub use ::mdbx::{EnvironmentKind, TransactionKind, RO, RW};
-use anyhow::Context;
-use std::{collections::HashMap, marker::PhantomData, ops::Deref, path::Path};
+use anyhow::{ensure, Context};
+use code_compression::{CodeCodec, CodeCodecCache};
+use once_cell::sync::OnceCell;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    marker::PhantomData,
+    ops::Deref,
+    path::Path,
+    sync::{atomic::AtomicBool, Arc},
+    time::Instant,
+};
 use tables::*;
+use tracing::{info_span, trace};
+use value_codec::ValueCodec;
 
 #[derive(Clone, Debug)]
 struct TableObjectWrapper<T>(T);
@@ -22,37 +34,84 @@ where
     }
 }
 
+/// Environment holding some of the tables apart from the main one.
+#[derive(Debug)]
+struct ColdEnvironment<E: EnvironmentKind> {
+    inner: ::mdbx::Environment<E>,
+    tables: HashSet<&'static str>,
+}
+
 #[derive(Debug)]
 pub struct MdbxEnvironment<E: EnvironmentKind> {
     inner: ::mdbx::Environment<E>,
+    chart: DatabaseChart,
+    cold: Option<ColdEnvironment<E>>,
+    readers: Arc<readers::ReaderRegistry>,
+    commits: commits::CommitMetrics,
+    code_codec: CodeCodecCache,
+}
+
+fn open_env<E: EnvironmentKind>(
+    mut b: ::mdbx::EnvironmentBuilder<E>,
+    path: &Path,
+    chart: &DatabaseChart,
+    mode: ::mdbx::Mode,
+) -> anyhow::Result<::mdbx::Environment<E>> {
+    b.set_max_dbs(std::cmp::max(chart.len(), 1));
+
+    b.set_flags(::mdbx::EnvironmentFlags {
+        mode,
+        no_rdahead: true,
+        coalesce: true,
+        ..Default::default()
+    });
+
+    b.open(path)
+        .with_context(|| format!("failed to open database at {}", path.display()))
+}
+
+fn create_tables<E: EnvironmentKind>(
+    env: &::mdbx::Environment<E>,
+    chart: &DatabaseChart,
+) -> anyhow::Result<()> {
+    let tx = env.begin_rw_txn()?;
+    for (table, info) in &**chart {
+        tx.create_db(
+            Some(table),
+            if info.dup_sort {
+                DatabaseFlags::DUP_SORT
+            } else {
+                DatabaseFlags::default()
+            },
+        )?;
+    }
+    tx.commit()?;
+
+    Ok(())
 }
 
 impl<E: EnvironmentKind> MdbxEnvironment<E> {
     fn open(
-        mut b: ::mdbx::EnvironmentBuilder<E>,
+        b: ::mdbx::EnvironmentBuilder<E>,
         path: &Path,
         chart: DatabaseChart,
-        ro: bool,
+        mode: ::mdbx::Mode,
     ) -> anyhow::Result<Self> {
-        b.set_max_dbs(std::cmp::max(chart.len(), 1));
-
-        b.set_flags(::mdbx::EnvironmentFlags {
-            mode: if ro {
-                ::mdbx::Mode::ReadOnly
-            } else {
-                ::mdbx::Mode::ReadWrite {
-                    sync_mode: ::mdbx::SyncMode::Durable,
-                }
-            },
-            no_rdahead: true,
-            coalesce: true,
-            ..Default::default()
-        });
+        for (table, info) in &*chart {
+            ensure!(
+                !info.dup_sort || info.codec == ValueCodec::Identity,
+                "table {} sorts duplicates and can not have a value codec",
+                table
+            );
+        }
 
         Ok(Self {
-            inner: b
-                .open(path)
-                .with_context(|| format!("failed to open database at {}", path.display()))?,
+            inner: open_env(b, path, &chart, mode)?,
+            chart,
+            cold: None,
+            readers: Default::default(),
+            commits: Default::default(),
+            code_codec: Default::default(),
         })
     }
 
@@ -61,7 +120,7 @@ impl<E: EnvironmentKind> MdbxEnvironment<E> {
         path: &Path,
         chart: DatabaseChart,
     ) -> anyhow::Result<Self> {
-        Self::open(b, path, chart, true)
+        Self::open(b, path, chart, ::mdbx::Mode::ReadOnly)
     }
 
     pub fn open_rw(
@@ -69,23 +128,98 @@ impl<E: EnvironmentKind> MdbxEnvironment<E> {
         path: &Path,
         chart: DatabaseChart,
     ) -> anyhow::Result<Self> {
-        let s = Self::open(b, path, chart.clone(), false)?;
+        Self::open_rw_with_sync_mode(b, path, chart, ::mdbx::SyncMode::Durable)
+    }
 
-        let tx = s.inner.begin_rw_txn()?;
-        for (table, info) in &*chart {
-            tx.create_db(
-                Some(table),
-                if info.dup_sort {
-                    DatabaseFlags::DUP_SORT
-                } else {
-                    DatabaseFlags::default()
-                },
-            )?;
+    pub fn open_rw_with_sync_mode(
+        b: ::mdbx::EnvironmentBuilder<E>,
+        path: &Path,
+        chart: DatabaseChart,
+        sync_mode: ::mdbx::SyncMode,
+    ) -> anyhow::Result<Self> {
+        let s = Self::open(
+            b,
+            path,
+            chart.clone(),
+            ::mdbx::Mode::ReadWrite { sync_mode },
+        )?;
+
+        create_tables(&s.inner, &chart)?;
+
+        Ok(s)
+    }
+
+    /// Keeps tables of `chart` in an environment of their own at `path`, e.g. rarely written
+    /// history on a large but slow disk. Transactions span both environments.
+    pub fn with_cold_ro(
+        self,
+        b: ::mdbx::EnvironmentBuilder<E>,
+        path: &Path,
+        chart: DatabaseChart,
+    ) -> anyhow::Result<Self> {
+        self.with_cold(b, path, chart, ::mdbx::Mode::ReadOnly)
+    }
+
+    /// Read-write counterpart of [`Self::with_cold_ro`]. Tables moved to the cold environment
+    /// must be empty in the main one, otherwise their contents would be shadowed.
+    pub fn with_cold_rw(
+        self,
+        b: ::mdbx::EnvironmentBuilder<E>,
+        path: &Path,
+        chart: DatabaseChart,
+        sync_mode: ::mdbx::SyncMode,
+    ) -> anyhow::Result<Self> {
+        let s = self.with_cold(
+            b,
+            path,
+            chart.clone(),
+            ::mdbx::Mode::ReadWrite { sync_mode },
+        )?;
+
+        if let Some(cold) = &s.cold {
+            create_tables(&cold.inner, &chart)?;
+        }
+
+        {
+            let tx = s.inner.begin_ro_txn()?;
+            for table in chart.keys() {
+                let db = tx.open_db(Some(table))?;
+                ensure!(
+                    tx.db_stat(&db)?.entries() == 0,
+                    "table {} holds data in the main database and can not be moved to {}",
+                    table,
+                    path.display()
+                );
+            }
         }
-        tx.commit()?;
 
         Ok(s)
     }
+
+    fn with_cold(
+        mut self,
+        b: ::mdbx::EnvironmentBuilder<E>,
+        path: &Path,
+        chart: DatabaseChart,
+        mode: ::mdbx::Mode,
+    ) -> anyhow::Result<Self> {
+        self.cold = Some(ColdEnvironment {
+            inner: open_env(b, path, &chart, mode)?,
+            tables: chart.keys().copied().collect(),
+        });
+
+        Ok(self)
+    }
+
+    /// Flushes both environments to disk.
+    pub fn sync_all(&self, force: bool) -> Result<(), KvError> {
+        self.inner.sync(force)?;
+        if let Some(cold) = &self.cold {
+            cold.inner.sync(force)?;
+        }
+
+        Ok(())
+    }
 }
 
 impl<E: EnvironmentKind> Deref for MdbxEnvironment<E> {
@@ -97,16 +231,82 @@ impl<E: EnvironmentKind> Deref for MdbxEnvironment<E> {
 }
 
 impl<E: EnvironmentKind> MdbxEnvironment<E> {
-    pub fn begin(&self) -> anyhow::Result<MdbxTransaction<'_, RO, E>> {
+    pub fn begin(&self) -> Result<MdbxTransaction<'_, RO, E>, KvError> {
         Ok(MdbxTransaction {
             inner: self.inner.begin_ro_txn()?,
+            cold: self
+                .cold
+                .as_ref()
+                .map(|cold| {
+                    Ok::<_, KvError>(ColdTransaction {
+                        inner: cold.inner.begin_ro_txn()?,
+                        tables: &cold.tables,
+                    })
+                })
+                .transpose()?,
+            chart: &self.chart,
+            reader: Some(self.readers.register()),
+            commits: None,
+            code_codecs: &self.code_codec,
+            code_codec: OnceCell::new(),
         })
     }
 
-    pub fn begin_mutable(&self) -> anyhow::Result<MdbxTransaction<'_, RW, E>> {
-        Ok(MdbxTransaction {
+    pub fn begin_mutable(&self) -> Result<MdbxTransaction<'_, RW, E>, KvError> {
+        let mut tx = MdbxTransaction {
             inner: self.inner.begin_rw_txn()?,
-        })
+            cold: self
+                .cold
+                .as_ref()
+                .map(|cold| {
+                    Ok::<_, KvError>(ColdTransaction {
+                        inner: cold.inner.begin_rw_txn()?,
+                        tables: &cold.tables,
+                    })
+                })
+                .transpose()?,
+            chart: &self.chart,
+            reader: None,
+            commits: None,
+            code_codecs: &self.code_codec,
+            code_codec: OnceCell::new(),
+        };
+        // Sizes are only compared, a failure to read them is not worth failing the transaction.
+        tx.commits = Some((self, self.used_size().ok()));
+
+        Ok(tx)
+    }
+
+    /// Read transactions currently open in this environment.
+    pub fn readers(&self) -> &readers::ReaderRegistry {
+        &self.readers
+    }
+
+    /// Write transactions committed since the environment was opened.
+    pub fn commit_stats(&self) -> commits::CommitStats {
+        self.commits.stats()
+    }
+
+    /// Write transactions committed since the previous call.
+    pub fn take_commit_interval(&self) -> commits::CommitStats {
+        self.commits.take_interval()
+    }
+
+    /// Codec of the `Code` table of this environment.
+    pub fn code_codec(&self) -> &CodeCodecCache {
+        &self.code_codec
+    }
+
+    /// Size of the used pages of the main and the cold environment, as of their last commit.
+    /// Read from the environment info, so unlike [`MdbxTransaction::db_size`] no table is walked.
+    fn used_size(&self) -> anyhow::Result<u64> {
+        let mut total = 0;
+        for env in std::iter::once(&self.inner).chain(self.cold.as_ref().map(|cold| &cold.inner)) {
+            let pages = env.info()?.last_pgno() as u64 + 1;
+            total += pages * env.stat()?.page_size() as u64;
+        }
+
+        Ok(total)
     }
 }
 
@@ -117,6 +317,24 @@ where
     E: EnvironmentKind,
 {
     inner: ::mdbx::Transaction<'env, K, E>,
+    cold: Option<ColdTransaction<'env, K, E>>,
+    chart: &'env DatabaseChart,
+    reader: Option<readers::ReaderGuard>,
+    /// Environment write transactions record their commit in, with its size when they started.
+    commits: Option<(&'env MdbxEnvironment<E>, Option<u64>)>,
+    code_codecs: &'env CodeCodecCache,
+    /// Codec of the `Code` table, resolved on first use.
+    code_codec: OnceCell<Arc<CodeCodec>>,
+}
+
+#[derive(Debug)]
+struct ColdTransaction<'env, K, E>
+where
+    K: TransactionKind,
+    E: EnvironmentKind,
+{
+    inner: ::mdbx::Transaction<'env, K, E>,
+    tables: &'env HashSet<&'static str>,
 }
 
 impl<'env, E> MdbxTransaction<'env, RO, E>
@@ -125,27 +343,32 @@ where
 {
     pub fn table_sizes(&self) -> anyhow::Result<HashMap<String, u64>> {
         let mut out = HashMap::new();
-        let main_db = self.inner.open_db(None)?;
-        let mut cursor = self.inner.cursor(&main_db)?;
-        while let Some((table, _)) = cursor.next_nodup::<Vec<u8>, ()>()? {
-            let table = String::from_utf8(table)?;
-            let db = self
-                .inner
-                .open_db(Some(&table))
-                .with_context(|| format!("failed to open table: {}", table))?;
-            let st = self
-                .inner
-                .db_stat(&db)
-                .with_context(|| format!("failed to get stats for table: {}", table))?;
-
-            out.insert(
-                table,
-                ((st.leaf_pages() + st.branch_pages() + st.overflow_pages())
-                    * st.page_size() as usize) as u64,
-            );
+        for (txn, cold) in self.environments() {
+            let main_db = txn.open_db(None)?;
+            let mut cursor = txn.cursor(&main_db)?;
+            while let Some((table, _)) = cursor.next_nodup::<Vec<u8>, ()>()? {
+                let table = String::from_utf8(table)?;
+                // Moved tables stay empty in the main environment.
+                if !cold && self.is_cold(table.as_str()) {
+                    continue;
+                }
 
-            unsafe {
-                self.inner.close_db(db)?;
+                let db = txn
+                    .open_db(Some(&table))
+                    .with_context(|| format!("failed to open table: {}", table))?;
+                let st = txn
+                    .db_stat(&db)
+                    .with_context(|| format!("failed to get stats for table: {}", table))?;
+
+                out.insert(
+                    table,
+                    ((st.leaf_pages() + st.branch_pages() + st.overflow_pages())
+                        * st.page_size() as usize) as u64,
+                );
+
+                unsafe {
+                    txn.close_db(db)?;
+                }
             }
         }
 
@@ -162,75 +385,208 @@ where
         self.inner.id()
     }
 
-    pub fn cursor<'tx, T>(&'tx self, table: T) -> anyhow::Result<MdbxCursor<'tx, K, T>>
+    /// Total size of all tables, uncommitted changes of this transaction included.
+    pub fn db_size(&self) -> anyhow::Result<u64> {
+        let mut total = 0;
+        for (txn, _) in self.environments() {
+            let main_db = txn.open_db(None)?;
+            let mut cursor = txn.cursor(&main_db)?;
+            while let Some((table, _)) = cursor.next_nodup::<Vec<u8>, ()>()? {
+                let table = String::from_utf8(table)?;
+                let db = txn
+                    .open_db(Some(&table))
+                    .with_context(|| format!("failed to open table: {}", table))?;
+                let st = txn
+                    .db_stat(&db)
+                    .with_context(|| format!("failed to get stats for table: {}", table))?;
+
+                total += ((st.leaf_pages() + st.branch_pages() + st.overflow_pages())
+                    * st.page_size() as usize) as u64;
+            }
+        }
+
+        Ok(total)
+    }
+
+    /// Transactions of the main and the cold environment, the latter marked with `true`.
+    fn environments(&self) -> impl Iterator<Item = (&::mdbx::Transaction<'env, K, E>, bool)> {
+        std::iter::once((&self.inner, false))
+            .chain(self.cold.as_ref().map(|cold| (&cold.inner, true)))
+    }
+
+    fn is_cold(&self, table: &str) -> bool {
+        self.cold
+            .as_ref()
+            .map(|cold| cold.tables.contains(table))
+            .unwrap_or(false)
+    }
+
+    /// Transaction of the environment holding `table`.
+    fn txn(&self, table: &str) -> &::mdbx::Transaction<'env, K, E> {
+        match &self.cold {
+            Some(cold) if cold.tables.contains(table) => &cold.inner,
+            _ => &self.inner,
+        }
+    }
+
+    /// Codec of the values of `table`, tables missing from the chart store them as encoded.
+    fn codec(&self, table: &str) -> Result<TableCodec, KvError> {
+        if table == tables::Code::const_db_name() {
+            return Ok(TableCodec::Code(
+                self.code_codec
+                    .get_or_try_init(|| self.code_codecs.resolve(self))?
+                    .clone(),
+            ));
+        }
+
+        Ok(TableCodec::Value(
+            self.chart
+                .get(table)
+                .map(|info| info.codec)
+                .unwrap_or_default(),
+        ))
+    }
+
+    fn check_reader(&self) -> Result<(), KvError> {
+        if let Some(reader) = &self.reader {
+            reader.check()?;
+        }
+        Ok(())
+    }
+
+    pub fn cursor<'tx, T>(&'tx self, table: T) -> Result<MdbxCursor<'tx, K, T>, KvError>
     where
         'env: 'tx,
         T: Table,
     {
+        self.check_reader()?;
+
         let table_name = table.db_name();
+        let txn = self.txn(table_name.as_ref());
         Ok(MdbxCursor {
-            inner: self
-                .inner
-                .cursor(&self.inner.open_db(Some(table_name.as_ref()))?)?,
+            inner: txn.cursor(&txn.open_db(Some(table_name.as_ref()))?)?,
             t: table.db_name(),
+            codec: self.codec(table_name.as_ref())?,
+            expired: self.reader.as_ref().map(readers::ReaderGuard::expired_flag),
             _marker: PhantomData,
         })
     }
 
-    pub fn get<T: Table>(&self, table: T, key: T::Key) -> anyhow::Result<Option<T::Value>> {
-        Ok(self
-            .inner
-            .get::<TableObjectWrapper<_>>(
-                &self.inner.open_db(Some(table.db_name().as_ref()))?,
-                key.encode().as_ref(),
+    pub fn get<T: Table>(&self, table: T, key: T::Key) -> Result<Option<T::Value>, KvError> {
+        self.check_reader()?;
+
+        let table_name = table.db_name();
+        let key = key.encode();
+        let codec = self.codec(table_name.as_ref())?;
+        let txn = self.txn(table_name.as_ref());
+        txn.get::<Cow<[u8]>>(&txn.open_db(Some(table_name.as_ref()))?, key.as_ref())
+            .map_err(|e| KvError::from_mdbx(table_name.as_ref(), Some(key.as_ref()), e))?
+            .map(|v| decode_value::<T>(table_name.as_ref(), &codec, Some(key.as_ref()), &v))
+            .transpose()
+    }
+
+    /// Values of `keys`, in the order of the keys. Lookups go in key order through one
+    /// cursor, so nearby keys share most of the tree descent.
+    pub fn get_many<T, I>(&self, table: T, keys: I) -> Result<Vec<Option<T::Value>>, KvError>
+    where
+        T: Table,
+        T::Key: TableDecode,
+        I: IntoIterator<Item = T::Key>,
+    {
+        let mut keys = keys
+            .into_iter()
+            .map(TableEncode::encode)
+            .enumerate()
+            .collect::<Vec<_>>();
+        keys.sort_unstable_by(|(_, a), (_, b)| a.as_ref().cmp(b.as_ref()));
+
+        let mut values = std::iter::repeat_with(|| None)
+            .take(keys.len())
+            .collect::<Vec<_>>();
+        let mut cursor = self.cursor(table)?;
+        for (index, key) in keys {
+            values[index] = map_res_inner::<T>(
+                cursor.t.as_ref(),
+                &cursor.codec,
+                cursor.inner.set_key(key.as_ref()),
             )?
-            .map(|v| v.0))
+            .map(|(_, value)| value);
+        }
+
+        Ok(values)
     }
 }
 
 impl<'env, E: EnvironmentKind> MdbxTransaction<'env, RW, E> {
-    pub fn set<T>(&self, table: T, k: T::Key, v: T::Value) -> anyhow::Result<()>
+    pub fn set<T>(&self, table: T, k: T::Key, v: T::Value) -> Result<(), KvError>
     where
         T: Table,
     {
-        Ok(self.inner.put(
-            &self.inner.open_db(Some(table.db_name().as_ref()))?,
+        let txn = self.txn(table.db_name().as_ref());
+        Ok(txn.put(
+            &txn.open_db(Some(table.db_name().as_ref()))?,
             &k.encode(),
-            &v.encode(),
+            self.codec(table.db_name().as_ref())?
+                .encode(v.encode().as_ref()),
             WriteFlags::UPSERT,
         )?)
     }
 
-    pub fn del<T>(&self, table: T, key: T::Key, value: Option<T::Value>) -> anyhow::Result<bool>
+    pub fn del<T>(&self, table: T, key: T::Key, value: Option<T::Value>) -> Result<bool, KvError>
     where
         T: Table,
     {
         let mut vref = None;
         let value = value.map(TableEncode::encode);
+        let codec = self.codec(table.db_name().as_ref())?;
+        let value = value.as_ref().map(|v| codec.encode(v.as_ref()));
 
         if let Some(v) = &value {
             vref = Some(v.as_ref());
         };
-        Ok(self.inner.del(
-            &self.inner.open_db(Some(table.db_name().as_ref()))?,
+        let txn = self.txn(table.db_name().as_ref());
+        Ok(txn.del(
+            &txn.open_db(Some(table.db_name().as_ref()))?,
             key.encode(),
             vref,
         )?)
     }
 
-    pub fn clear_table<T>(&self, table: T) -> anyhow::Result<()>
+    pub fn clear_table<T>(&self, table: T) -> Result<(), KvError>
     where
         T: Table,
     {
-        self.inner
-            .clear_db(&self.inner.open_db(Some(table.db_name().as_ref()))?)?;
+        let txn = self.txn(table.db_name().as_ref());
+        txn.clear_db(&txn.open_db(Some(table.db_name().as_ref()))?)?;
 
         Ok(())
     }
 
-    pub fn commit(self) -> anyhow::Result<()> {
+    pub fn commit(self) -> Result<(), KvError> {
+        let _span = info_span!("commit", txn = self.id()).entered();
+
+        let started = Instant::now();
+        // Sync progress lives in the main environment, so if the process dies in between,
+        // stages redo the work already committed to the cold one.
+        if let Some(cold) = self.cold {
+            cold.inner.commit()?;
+        }
         self.inner.commit()?;
 
+        let elapsed = started.elapsed();
+        if let Some((env, started_at)) = self.commits {
+            let db_growth = started_at.and_then(|started_at| {
+                let size = env.used_size().ok()?;
+                Some(size as i64 - started_at as i64)
+            });
+            trace!(
+                "Committed in {:?}, database grew by {:?} bytes",
+                elapsed,
+                db_growth
+            );
+            env.commits.record(elapsed, db_growth);
+        }
+
         Ok(())
     }
 }
@@ -243,19 +599,65 @@ where
 {
     inner: ::mdbx::Cursor<'txn, K>,
     t: string::String<Bytes>,
+    codec: TableCodec,
+    expired: Option<Arc<AtomicBool>>,
     _marker: PhantomData<T>,
 }
 
-fn map_res_inner<T, E>(
-    v: Result<Option<(TableObjectWrapper<T::Key>, TableObjectWrapper<T::Value>)>, E>,
-) -> anyhow::Result<Option<(T::Key, T::Value)>>
+/// Codec applied to the encoded values of a table.
+#[derive(Clone, Debug)]
+enum TableCodec {
+    Value(ValueCodec),
+    /// Contract code, see [`code_compression`].
+    Code(Arc<CodeCodec>),
+}
+
+impl TableCodec {
+    fn encode<'a>(&self, value: &'a [u8]) -> Cow<'a, [u8]> {
+        match self {
+            Self::Value(codec) => codec.encode(value),
+            Self::Code(codec) => Cow::Owned(codec.encode(value)),
+        }
+    }
+
+    fn decode<'a>(&self, b: &'a [u8]) -> anyhow::Result<Cow<'a, [u8]>> {
+        match self {
+            Self::Value(codec) => codec.decode(b),
+            Self::Code(codec) => Ok(Cow::Owned(codec.decode(b)?.to_vec())),
+        }
+    }
+}
+
+fn decode_value<T>(
+    table: &str,
+    codec: &TableCodec,
+    key: Option<&[u8]>,
+    value: &[u8],
+) -> Result<T::Value, KvError>
+where
+    T: Table,
+{
+    codec
+        .decode(value)
+        .and_then(|value| T::Value::decode(&value))
+        .map_err(|e| KvError::Decode {
+            table: table.to_string(),
+            key: key.map(<[u8]>::to_vec),
+            source: e.into(),
+        })
+}
+
+fn map_res_inner<T>(
+    table: &str,
+    codec: &TableCodec,
+    v: Result<Option<(TableObjectWrapper<T::Key>, Cow<'_, [u8]>)>, ::mdbx::Error>,
+) -> Result<Option<(T::Key, T::Value)>, KvError>
 where
     T: Table,
     <T as Table>::Key: TableDecode,
-    E: std::error::Error + Send + Sync + 'static,
 {
-    if let Some((k, v)) = v? {
-        return Ok(Some((k.0, v.0)));
+    if let Some((k, v)) = v.map_err(|e| KvError::from_mdbx(table, None, e))? {
+        return Ok(Some((k.0, decode_value::<T>(table, codec, None, &v)?)));
     }
 
     Ok(None)
@@ -266,54 +668,85 @@ where
     K: TransactionKind,
     T: Table,
 {
-    pub fn first(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    /// Reads and writes values with `codec` instead of the one of the table, for
+    /// re-encoding the table.
+    pub fn with_codec(mut self, codec: ValueCodec) -> Self {
+        self.codec = TableCodec::Value(codec);
+        self
+    }
+
+    /// Every positioning of the cursor reads the snapshot, so it fails once the read
+    /// transaction has expired.
+    fn check_reader(&self) -> Result<(), KvError> {
+        if let Some(expired) = &self.expired {
+            readers::check_expired(expired)?;
+        }
+        Ok(())
+    }
+
+    pub fn first(&mut self) -> Result<Option<(T::Key, T::Value)>, KvError>
     where
         T::Key: TableDecode,
     {
-        map_res_inner::<T, _>(self.inner.first())
+        self.check_reader()?;
+        map_res_inner::<T>(self.t.as_ref(), &self.codec, self.inner.first())
     }
 
-    pub fn seek(&mut self, key: T::SeekKey) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    pub fn seek(&mut self, key: T::SeekKey) -> Result<Option<(T::Key, T::Value)>, KvError>
     where
         T::Key: TableDecode,
     {
-        map_res_inner::<T, _>(self.inner.set_range(key.encode().as_ref()))
+        self.check_reader()?;
+        map_res_inner::<T>(
+            self.t.as_ref(),
+            &self.codec,
+            self.inner.set_range(key.encode().as_ref()),
+        )
     }
 
-    pub fn seek_exact(&mut self, key: T::Key) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    pub fn seek_exact(&mut self, key: T::Key) -> Result<Option<(T::Key, T::Value)>, KvError>
     where
         T::Key: TableDecode,
     {
-        map_res_inner::<T, _>(self.inner.set_key(key.encode().as_ref()))
+        self.check_reader()?;
+        map_res_inner::<T>(
+            self.t.as_ref(),
+            &self.codec,
+            self.inner.set_key(key.encode().as_ref()),
+        )
     }
 
     #[allow(clippy::should_implement_trait)]
-    pub fn next(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    pub fn next(&mut self) -> Result<Option<(T::Key, T::Value)>, KvError>
     where
         T::Key: TableDecode,
     {
-        map_res_inner::<T, _>(self.inner.next())
+        self.check_reader()?;
+        map_res_inner::<T>(self.t.as_ref(), &self.codec, self.inner.next())
     }
 
-    pub fn prev(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    pub fn prev(&mut self) -> Result<Option<(T::Key, T::Value)>, KvError>
     where
         T::Key: TableDecode,
     {
-        map_res_inner::<T, _>(self.inner.prev())
+        self.check_reader()?;
+        map_res_inner::<T>(self.t.as_ref(), &self.codec, self.inner.prev())
     }
 
-    pub fn last(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    pub fn last(&mut self) -> Result<Option<(T::Key, T::Value)>, KvError>
     where
         T::Key: TableDecode,
     {
-        map_res_inner::<T, _>(self.inner.last())
+        self.check_reader()?;
+        map_res_inner::<T>(self.t.as_ref(), &self.codec, self.inner.last())
     }
 
-    pub fn current(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    pub fn current(&mut self) -> Result<Option<(T::Key, T::Value)>, KvError>
     where
         T::Key: TableDecode,
     {
-        map_res_inner::<T, _>(self.inner.get_current())
+        self.check_reader()?;
+        map_res_inner::<T>(self.t.as_ref(), &self.codec, self.inner.get_current())
     }
 
     pub fn walk(
@@ -388,14 +821,16 @@ where
         &mut self,
         key: T::Key,
         value: T::SeekBothKey,
-    ) -> anyhow::Result<Option<T::Value>>
+    ) -> Result<Option<T::Value>, KvError>
     where
         T::Key: Clone,
     {
-        let res = self.inner.get_both_range::<TableObjectWrapper<T::Value>>(
-            key.encode().as_ref(),
-            value.encode().as_ref(),
-        )?;
+        self.check_reader()?;
+        let key = key.encode();
+        let res = self
+            .inner
+            .get_both_range::<TableObjectWrapper<T::Value>>(key.as_ref(), value.encode().as_ref())
+            .map_err(|e| KvError::from_mdbx(self.t.as_ref(), Some(key.as_ref()), e))?;
 
         if let Some(v) = res {
             return Ok(Some(v.0));
@@ -404,35 +839,40 @@ where
         Ok(None)
     }
 
-    pub fn last_dup(&mut self) -> anyhow::Result<Option<T::Value>>
+    pub fn last_dup(&mut self) -> Result<Option<T::Value>, KvError>
     where
         T::Key: TableDecode,
     {
+        self.check_reader()?;
         Ok(self
             .inner
-            .last_dup::<TableObjectWrapper<T::Value>>()?
+            .last_dup::<TableObjectWrapper<T::Value>>()
+            .map_err(|e| KvError::from_mdbx(self.t.as_ref(), None, e))?
             .map(|v| v.0))
     }
 
-    pub fn next_dup(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    pub fn next_dup(&mut self) -> Result<Option<(T::Key, T::Value)>, KvError>
     where
         T::Key: TableDecode,
     {
-        map_res_inner::<T, _>(self.inner.next_dup())
+        self.check_reader()?;
+        map_res_inner::<T>(self.t.as_ref(), &self.codec, self.inner.next_dup())
     }
 
-    pub fn next_no_dup(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    pub fn next_no_dup(&mut self) -> Result<Option<(T::Key, T::Value)>, KvError>
     where
         T::Key: TableDecode,
     {
-        map_res_inner::<T, _>(self.inner.next_nodup())
+        self.check_reader()?;
+        map_res_inner::<T>(self.t.as_ref(), &self.codec, self.inner.next_nodup())
     }
 
-    pub fn prev_dup(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    pub fn prev_dup(&mut self) -> Result<Option<(T::Key, T::Value)>, KvError>
     where
         T::Key: TableDecode,
     {
-        map_res_inner::<T, _>(self.inner.prev_dup())
+        self.check_reader()?;
+        map_res_inner::<T>(self.t.as_ref(), &self.codec, self.inner.prev_dup())
     }
 
     /// Walk over duplicates for some specific key.
@@ -492,31 +932,31 @@ impl<'txn, T> MdbxCursor<'txn, RW, T>
 where
     T: Table,
 {
-    pub fn put(&mut self, key: T::Key, value: T::Value) -> anyhow::Result<()> {
+    pub fn put(&mut self, key: T::Key, value: T::Value) -> Result<(), KvError> {
         Ok(self.inner.put(
             key.encode().as_ref(),
-            value.encode().as_ref(),
+            self.codec.encode(value.encode().as_ref()).as_ref(),
             WriteFlags::default(),
         )?)
     }
 
-    pub fn upsert(&mut self, key: T::Key, value: T::Value) -> anyhow::Result<()> {
+    pub fn upsert(&mut self, key: T::Key, value: T::Value) -> Result<(), KvError> {
         Ok(self.inner.put(
             key.encode().as_ref(),
-            value.encode().as_ref(),
+            self.codec.encode(value.encode().as_ref()).as_ref(),
             WriteFlags::UPSERT,
         )?)
     }
 
-    pub fn append(&mut self, key: T::Key, value: T::Value) -> anyhow::Result<()> {
+    pub fn append(&mut self, key: T::Key, value: T::Value) -> Result<(), KvError> {
         Ok(self.inner.put(
             key.encode().as_ref(),
-            value.encode().as_ref(),
+            self.codec.encode(value.encode().as_ref()).as_ref(),
             WriteFlags::APPEND,
         )?)
     }
 
-    pub fn delete_current(&mut self) -> anyhow::Result<()> {
+    pub fn delete_current(&mut self) -> Result<(), KvError> {
         self.inner.del(WriteFlags::CURRENT)?;
 
         Ok(())
@@ -527,13 +967,13 @@ impl<'txn, T> MdbxCursor<'txn, RW, T>
 where
     T: DupSort,
 {
-    pub fn delete_current_duplicates(&mut self) -> anyhow::Result<()> {
+    pub fn delete_current_duplicates(&mut self) -> Result<(), KvError> {
         Ok(self.inner.del(WriteFlags::NO_DUP_DATA)?)
     }
-    pub fn append_dup(&mut self, key: T::Key, value: T::Value) -> anyhow::Result<()> {
+    pub fn append_dup(&mut self, key: T::Key, value: T::Value) -> Result<(), KvError> {
         Ok(self.inner.put(
             key.encode().as_ref(),
-            value.encode().as_ref(),
+            self.codec.encode(value.encode().as_ref()).as_ref(),
             WriteFlags::APPEND_DUP,
         )?)
     }
@@ -0,0 +1,506 @@
+use super::{mdbx::*, tables, traits::*, value_codec::ValueCodec, CustomTable};
+use crate::{models::*, stagedsync::stages::RENAMED_STAGES};
+use anyhow::bail;
+use tracing::*;
+
+/// Entries rewritten per committed transaction by [`Migration::Rewrite`].
+const REWRITE_BATCH_SIZE: usize = 100_000;
+
+enum Migration<E: EnvironmentKind> {
+    /// Applied in one transaction, together with its record.
+    Single(fn(&MdbxTransaction<'_, RW, E>) -> anyhow::Result<()>),
+    /// Rewrites every value of a table in place, see [`rewrite_values`].
+    Rewrite {
+        table: &'static str,
+        /// Codec the values are stored with before the migration.
+        from: ValueCodec,
+        rewrite: Rewrite<E>,
+    },
+}
+
+/// Maps a stored value to its new encoding, writing whatever else it needs through the
+/// transaction of its batch.
+type Rewrite<E> = fn(&MdbxTransaction<'_, RW, E>, &[u8]) -> anyhow::Result<Vec<u8>>;
+
+/// Migrations in the order of application. Names must never change.
+fn migrations<E: EnvironmentKind>() -> Vec<(&'static str, Migration<E>)> {
+    vec![
+        (
+            "headers_scale_to_rlp",
+            Migration::Rewrite {
+                table: tables::Header::const_db_name(),
+                from: ValueCodec::Identity,
+                rewrite: scale_to_rlp::<_, BlockHeader>,
+            },
+        ),
+        (
+            "transactions_scale_to_rlp",
+            Migration::Rewrite {
+                table: tables::BlockTransaction::const_db_name(),
+                from: ValueCodec::Identity,
+                rewrite: scale_to_rlp::<_, MessageWithSignature>,
+            },
+        ),
+        (
+            "code_tag_values",
+            Migration::Rewrite {
+                table: tables::Code::const_db_name(),
+                from: ValueCodec::Identity,
+                rewrite: copy_value,
+            },
+        ),
+        (
+            "body_ommers_by_header_key",
+            Migration::Rewrite {
+                table: tables::BlockBody::const_db_name(),
+                from: ValueCodec::Identity,
+                rewrite: body_ommers_by_header_key,
+            },
+        ),
+        (
+            "receipt_values_zstd",
+            Migration::Rewrite {
+                table: tables::Receipt::const_db_name(),
+                from: ValueCodec::Identity,
+                rewrite: copy_value,
+            },
+        ),
+        (
+            "log_values_zstd",
+            Migration::Rewrite {
+                table: tables::Log::const_db_name(),
+                from: ValueCodec::Identity,
+                rewrite: copy_value,
+            },
+        ),
+        ("erigon_stage_names", Migration::Single(erigon_stage_names)),
+    ]
+}
+
+fn is_applied<K: TransactionKind, E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, K, E>,
+    name: &str,
+) -> anyhow::Result<bool> {
+    Ok(tx
+        .get(tables::Migration, name.as_bytes().to_vec())?
+        .is_some())
+}
+
+/// Applies the migrations not yet recorded in the `Migration` table.
+pub fn migrate<E: EnvironmentKind>(db: &MdbxEnvironment<E>) -> anyhow::Result<()> {
+    for (name, migration) in migrations::<E>() {
+        if is_applied(&db.begin()?, name)? {
+            continue;
+        }
+
+        info!("Applying migration {}", name);
+        match migration {
+            Migration::Single(apply) => {
+                let tx = db.begin_mutable()?;
+                apply(&tx)?;
+                tx.set(tables::Migration, name.as_bytes().to_vec(), vec![])?;
+                tx.commit()?;
+            }
+            Migration::Rewrite {
+                table,
+                from,
+                rewrite,
+            } => {
+                rewrite_values(db, name, table, from, rewrite, REWRITE_BATCH_SIZE, true)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Fails if some migration is not applied yet. Only the node applies them, other readers have
+/// to refuse the database until then, as they would misread the tables being migrated.
+pub fn ensure_migrated<K: TransactionKind, E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, K, E>,
+) -> anyhow::Result<()> {
+    let mut pending = vec![];
+    for (name, _) in migrations::<E>() {
+        if !is_applied(tx, name)? {
+            pending.push(name);
+        }
+    }
+
+    if !pending.is_empty() {
+        bail!(
+            "Database has pending migrations ({}), start martinez on it to apply them",
+            pending.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Key of the `Migration` table holding the last key rewritten by an interrupted migration.
+fn progress_key(name: &str) -> Vec<u8> {
+    format!("{}.progress", name).into_bytes()
+}
+
+/// Rewrites every value of `table`, read with the codec `from` and written with the table's own,
+/// in batches of `batch_size` entries, each committed along with the last key it rewrote. The
+/// rewrite is not idempotent, so after an interruption it resumes past that key. The migration
+/// called `name` is recorded with the last batch if `record` is set.
+fn rewrite_values<E: EnvironmentKind>(
+    db: &MdbxEnvironment<E>,
+    name: &str,
+    table: &str,
+    from: ValueCodec,
+    rewrite: Rewrite<E>,
+    batch_size: usize,
+    record: bool,
+) -> anyhow::Result<()> {
+    let table = CustomTable::from(table.to_string());
+    let progress_key = progress_key(name);
+    let mut count = 0_u64;
+    loop {
+        let tx = db.begin_mutable()?;
+        let mut last_key = tx.get(tables::Migration, progress_key.clone())?;
+        let mut rewritten = 0;
+        {
+            let mut cursor = tx.cursor(table.clone())?.with_codec(from);
+            let mut entry = match &last_key {
+                Some(key) => {
+                    cursor.seek(key.clone())?;
+                    cursor.next()?
+                }
+                None => cursor.first()?,
+            };
+            while let Some((key, value)) = entry {
+                tx.set(table.clone(), key.clone(), rewrite(&tx, &value)?)?;
+                last_key = Some(key);
+                rewritten += 1;
+                if rewritten == batch_size {
+                    break;
+                }
+                entry = cursor.next()?;
+            }
+        }
+        count += rewritten as u64;
+
+        let done = rewritten < batch_size;
+        if done {
+            tx.del(tables::Migration, progress_key.clone(), None)?;
+            if record {
+                tx.set(tables::Migration, name.as_bytes().to_vec(), vec![])?;
+            }
+        } else if let Some(last_key) = last_key {
+            tx.set(tables::Migration, progress_key.clone(), last_key)?;
+        }
+        tx.commit()?;
+
+        if done {
+            break;
+        }
+        debug!("Rewrote {} entries of {} so far", count, table.0);
+    }
+    info!("Rewrote {} entries of {}", count, table.0);
+
+    Ok(())
+}
+
+/// Re-encodes a SCALE value to RLP.
+fn scale_to_rlp<E, T>(_: &MdbxTransaction<'_, RW, E>, value: &[u8]) -> anyhow::Result<Vec<u8>>
+where
+    E: EnvironmentKind,
+    T: parity_scale_codec::Decode + rlp::Encodable,
+{
+    let object = <T as parity_scale_codec::Decode>::decode(&mut &*value)?;
+    Ok(rlp::encode(&object).to_vec())
+}
+
+/// Values are re-encoded by the codecs alone.
+fn copy_value<E: EnvironmentKind>(
+    _: &MdbxTransaction<'_, RW, E>,
+    value: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    Ok(value.to_vec())
+}
+
+/// Re-encodes the values of `table` stored with the codec `from` with the codec it has in
+/// [`tables::CHAINDATA_TABLES`]. An interrupted run resumes where it stopped.
+pub fn recode_values<E: EnvironmentKind>(
+    db: &MdbxEnvironment<E>,
+    table: &str,
+    from: ValueCodec,
+) -> anyhow::Result<()> {
+    rewrite_values(
+        db,
+        &format!("recode_{}", table),
+        table,
+        from,
+        copy_value,
+        REWRITE_BATCH_SIZE,
+        false,
+    )
+}
+
+/// Moves progress saved under the former stage names to the Erigon ones.
+fn erigon_stage_names<E: EnvironmentKind>(tx: &MdbxTransaction<'_, RW, E>) -> anyhow::Result<()> {
+    for table in [
+        tables::SyncStage::const_db_name(),
+        tables::PruneProgress::const_db_name(),
+    ] {
+        let table = CustomTable::from(table.to_string());
+        for &(old, new) in RENAMED_STAGES {
+            if let Some(value) = tx.get(table.clone(), old.as_bytes().to_vec())? {
+                tx.set(table.clone(), new.0.as_bytes().to_vec(), value)?;
+                tx.del(table.clone(), old.as_bytes().to_vec(), None)?;
+                info!("Renamed stage {} to {} in {}", old, new, table.0);
+            }
+        }
+    }
+
+    for entry in tx.cursor(tables::StageStats)?.walk(None) {
+        let (key, mut stats) = entry?;
+        if let Some(&(_, new)) = RENAMED_STAGES.iter().find(|(old, _)| *old == stats.stage) {
+            stats.stage = new.to_string();
+            tx.set(tables::StageStats, key, stats)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Moves ommer headers out of block bodies into the `Header` table.
+fn body_ommers_by_header_key<E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, RW, E>,
+    value: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    let body = <BodyForStorageWithOmmers as parity_scale_codec::Decode>::decode(&mut &*value)?;
+    let body = BodyForStorage {
+        base_tx_id: body.base_tx_id,
+        tx_amount: body.tx_amount,
+        uncles: crate::accessors::chain::ommers::write(tx, &body.uncles)?,
+    };
+    Ok(TableEncode::encode(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv::new_mem_database;
+
+    #[test]
+    fn migrate_headers() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let header = BlockHeader {
+            number: BlockNumber(5),
+            ..BlockHeader::empty()
+        };
+        let key = (header.number, header.hash());
+        let raw = CustomTable::from(tables::Header::const_db_name().to_string());
+        tx.set(
+            raw,
+            TableEncode::encode(key).to_vec(),
+            parity_scale_codec::Encode::encode(&header),
+        )
+        .unwrap();
+
+        tx.commit().unwrap();
+
+        migrate(&db).unwrap();
+        assert_eq!(
+            db.begin().unwrap().get(tables::Header, key).unwrap(),
+            Some(header.clone())
+        );
+
+        // applied only once
+        migrate(&db).unwrap();
+        let tx = db.begin().unwrap();
+        assert_eq!(tx.get(tables::Header, key).unwrap(), Some(header));
+        ensure_migrated(&tx).unwrap();
+    }
+
+    #[test]
+    fn resume_rewrite() {
+        let db = new_mem_database().unwrap();
+        let name = "headers_scale_to_rlp";
+        let raw = CustomTable::from(tables::Header::const_db_name().to_string());
+
+        let headers = (1..=5)
+            .map(|number| BlockHeader {
+                number: BlockNumber(number),
+                ..BlockHeader::empty()
+            })
+            .collect::<Vec<_>>();
+        let tx = db.begin_mutable().unwrap();
+        for header in &headers {
+            tx.set(
+                raw.clone(),
+                TableEncode::encode((header.number, header.hash())).to_vec(),
+                parity_scale_codec::Encode::encode(header),
+            )
+            .unwrap();
+        }
+        tx.commit().unwrap();
+        assert!(ensure_migrated(&db.begin().unwrap()).is_err());
+
+        // interrupted after the first batch
+        fn fail_after<E: EnvironmentKind>(
+            tx: &MdbxTransaction<'_, RW, E>,
+            value: &[u8],
+        ) -> anyhow::Result<Vec<u8>> {
+            let header = <BlockHeader as parity_scale_codec::Decode>::decode(&mut &*value)?;
+            if header.number > BlockNumber(2) {
+                bail!("interrupted");
+            }
+            scale_to_rlp::<_, BlockHeader>(tx, value)
+        }
+        assert!(rewrite_values(
+            &db,
+            name,
+            tables::Header::const_db_name(),
+            ValueCodec::Identity,
+            fail_after,
+            2,
+            true
+        )
+        .is_err());
+        assert!(db
+            .begin()
+            .unwrap()
+            .get(tables::Migration, progress_key(name))
+            .unwrap()
+            .is_some());
+
+        rewrite_values(
+            &db,
+            name,
+            tables::Header::const_db_name(),
+            ValueCodec::Identity,
+            scale_to_rlp::<_, BlockHeader>,
+            2,
+            true,
+        )
+        .unwrap();
+        let tx = db.begin().unwrap();
+        for header in headers {
+            assert_eq!(
+                tx.get(tables::Header, (header.number, header.hash()))
+                    .unwrap(),
+                Some(header)
+            );
+        }
+        assert!(is_applied(&tx, name).unwrap());
+        assert_eq!(tx.get(tables::Migration, progress_key(name)).unwrap(), None);
+    }
+
+    #[test]
+    fn migrate_receipt_values() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let receipts = (1..=100)
+            .map(|i| tables::StoredReceipt {
+                success: true,
+                cumulative_gas_used: i * 21_000,
+            })
+            .collect::<Vec<_>>();
+        let raw = CustomTable::from(tables::Receipt::const_db_name().to_string());
+        let key = TableEncode::encode(BlockNumber(1)).to_vec();
+        tx.cursor(raw.clone())
+            .unwrap()
+            .with_codec(ValueCodec::Identity)
+            .put(key.clone(), TableEncode::encode(receipts.clone()).to_vec())
+            .unwrap();
+
+        tx.commit().unwrap();
+
+        migrate(&db).unwrap();
+        let tx = db.begin().unwrap();
+        assert_eq!(
+            tx.get(tables::Receipt, BlockNumber(1)).unwrap(),
+            Some(receipts.clone())
+        );
+
+        let (_, stored) = tx
+            .cursor(raw)
+            .unwrap()
+            .with_codec(ValueCodec::Identity)
+            .seek_exact(key)
+            .unwrap()
+            .unwrap();
+        assert!(stored.len() < TableEncode::encode(receipts).len());
+    }
+
+    #[test]
+    fn migrate_stage_names() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let raw = CustomTable::from(tables::SyncStage::const_db_name().to_string());
+        tx.set(
+            raw,
+            b"SenderRecovery".to_vec(),
+            TableEncode::encode(BlockNumber(42)).to_vec(),
+        )
+        .unwrap();
+
+        tx.commit().unwrap();
+
+        migrate(&db).unwrap();
+        let tx = db.begin().unwrap();
+        assert_eq!(
+            crate::stagedsync::stages::SENDERS
+                .get_progress(&tx)
+                .unwrap(),
+            Some(BlockNumber(42))
+        );
+        assert_eq!(
+            tx.get(
+                CustomTable::from(tables::SyncStage::const_db_name().to_string()),
+                b"SenderRecovery".to_vec()
+            )
+            .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn migrate_body_ommers() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let ommer = BlockHeader {
+            number: BlockNumber(4),
+            ..BlockHeader::empty()
+        };
+        let key = (BlockNumber(5), H256::repeat_byte(5));
+        let raw = CustomTable::from(tables::BlockBody::const_db_name().to_string());
+        tx.set(
+            raw,
+            TableEncode::encode(key).to_vec(),
+            parity_scale_codec::Encode::encode(&BodyForStorageWithOmmers {
+                base_tx_id: 10.into(),
+                tx_amount: 2,
+                uncles: vec![ommer.clone()],
+            }),
+        )
+        .unwrap();
+
+        tx.commit().unwrap();
+
+        migrate(&db).unwrap();
+        let tx = db.begin().unwrap();
+        assert_eq!(
+            tx.get(tables::BlockBody, key).unwrap(),
+            Some(BodyForStorage {
+                base_tx_id: 10.into(),
+                tx_amount: 2,
+                uncles: vec![(ommer.number, ommer.hash())],
+            })
+        );
+        assert_eq!(
+            tx.get(tables::Header, (ommer.number, ommer.hash()))
+                .unwrap(),
+            Some(ommer)
+        );
+    }
+}
@@ -1,16 +1,26 @@
+pub mod code_compression;
+pub mod commits;
+pub mod error;
 pub mod mdbx;
+pub mod migrations;
+pub mod printers;
+pub mod readers;
+pub mod remote;
+pub mod server;
 pub mod tables;
 pub mod traits;
+pub mod value_codec;
 
+pub use self::error::KvError;
 use self::traits::*;
-use crate::kv::tables::CHAINDATA_TABLES;
-use ::mdbx::{Geometry, WriteMap};
+use crate::kv::tables::{CHAINDATA_TABLES, COLD_TABLES};
+use ::mdbx::{EnvironmentKind, Geometry, WriteMap};
 use byte_unit::*;
 use bytes::Bytes;
 use derive_more::Deref;
-use std::{fmt::Debug, ops::Deref};
+use std::{fmt::Debug, ops::Deref, path::PathBuf};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct CustomTable(pub string::String<Bytes>);
 
 impl Table for CustomTable {
@@ -34,33 +44,177 @@ impl DupSort for CustomTable {
 }
 
 #[derive(Debug, Deref)]
-pub struct MdbxWithDirHandle {
+pub struct MdbxWithDirHandle<E: EnvironmentKind = WriteMap> {
     #[deref]
-    inner: mdbx::MdbxEnvironment<WriteMap>,
+    inner: mdbx::MdbxEnvironment<E>,
     _tmpdir: Option<tempfile::TempDir>,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ArgEnum)]
+pub enum SyncMode {
+    /// Fsync on every commit.
+    Durable,
+    /// Meta pages are flushed lazily, the last commits may be lost on a system crash.
+    NoMetaSync,
+    /// No fsync on commit, the database stays consistent on a system crash.
+    SafeNoSync,
+    /// No fsync on commit, the database may be corrupted on a system crash.
+    UtterlyNoSync,
+}
+
+impl From<SyncMode> for ::mdbx::SyncMode {
+    fn from(mode: SyncMode) -> Self {
+        match mode {
+            SyncMode::Durable => Self::Durable,
+            SyncMode::NoMetaSync => Self::NoMetaSync,
+            SyncMode::SafeNoSync => Self::SafeNoSync,
+            SyncMode::UtterlyNoSync => Self::UtterlyNoSync,
+        }
+    }
+}
+
+#[derive(Clone, Debug, clap::Parser)]
+pub struct DatabaseOpts {
+    #[clap(
+        long = "db.sync-mode",
+        help = "How commits are flushed to disk.",
+        arg_enum,
+        default_value = "durable"
+    )]
+    pub sync_mode: SyncMode,
+    #[clap(
+        long = "db.dirty-pages-limit",
+        help = "Dirty pages a write transaction keeps in memory before spilling them to disk."
+    )]
+    pub dirty_pages_limit: Option<u64>,
+    #[clap(
+        long = "db.no-write-map",
+        help = "Write through the system calls instead of the writable memory map."
+    )]
+    pub no_write_map: bool,
+    #[clap(
+        long = "db.fast-sync-unsafe",
+        help = "No fsync between batches, only on stage boundaries. A crash may corrupt the database."
+    )]
+    pub fast_sync_unsafe: bool,
+    #[clap(
+        long = "db.cold-dir",
+        help = "Directory to keep history indices in, apart from the rest of the database.",
+        parse(from_os_str)
+    )]
+    pub cold_dir: Option<PathBuf>,
+    #[clap(
+        long = "db.cold-sync-mode",
+        help = "How commits to the cold directory are flushed to disk, same as the main database if unset.",
+        arg_enum
+    )]
+    pub cold_sync_mode: Option<SyncMode>,
+}
+
+impl Default for DatabaseOpts {
+    fn default() -> Self {
+        Self {
+            sync_mode: SyncMode::Durable,
+            dirty_pages_limit: None,
+            no_write_map: false,
+            fast_sync_unsafe: false,
+            cold_dir: None,
+            cold_sync_mode: None,
+        }
+    }
+}
+
+impl DatabaseOpts {
+    pub fn effective_sync_mode(&self) -> SyncMode {
+        if self.fast_sync_unsafe {
+            SyncMode::UtterlyNoSync
+        } else {
+            self.sync_mode
+        }
+    }
+
+    pub fn effective_cold_sync_mode(&self) -> SyncMode {
+        match self.cold_sync_mode {
+            Some(sync_mode) if !self.fast_sync_unsafe => sync_mode,
+            _ => self.effective_sync_mode(),
+        }
+    }
+}
+
 pub fn new_mem_database() -> anyhow::Result<MdbxWithDirHandle> {
     let tmpdir = tempfile::tempdir()?;
     Ok(MdbxWithDirHandle {
-        inner: new_environment(tmpdir.path(), n_mib_bytes!(64), None)?,
+        inner: new_environment(
+            tmpdir.path(),
+            n_mib_bytes!(64),
+            None,
+            &DatabaseOpts::default(),
+        )?,
         _tmpdir: Some(tmpdir),
     })
 }
 
 pub fn new_database(path: &std::path::Path) -> anyhow::Result<MdbxWithDirHandle> {
+    new_database_with_opts(path, &DatabaseOpts::default())
+}
+
+/// `opts.no_write_map` has to match `E`.
+pub fn new_database_with_opts<E: EnvironmentKind>(
+    path: &std::path::Path,
+    opts: &DatabaseOpts,
+) -> anyhow::Result<MdbxWithDirHandle<E>> {
+    let mut inner = new_environment(path, n_tib_bytes!(4), Some(n_gib_bytes!(4) as usize), opts)?;
+    if let Some(cold_dir) = &opts.cold_dir {
+        std::fs::create_dir_all(cold_dir)?;
+        inner = inner.with_cold_rw(
+            environment_builder(n_tib_bytes!(4), Some(n_gib_bytes!(4) as usize), opts),
+            cold_dir,
+            COLD_TABLES.clone(),
+            opts.effective_cold_sync_mode().into(),
+        )?;
+    }
+
     Ok(MdbxWithDirHandle {
-        inner: new_environment(path, n_tib_bytes!(4), Some(n_gib_bytes!(4) as usize))?,
+        inner,
         _tmpdir: None,
     })
 }
 
-fn new_environment(
+/// Opens the database for reading, `cold_dir` has to match the one it was written with.
+pub fn open_database_ro<E: EnvironmentKind>(
     path: &std::path::Path,
+    cold_dir: Option<&std::path::Path>,
+) -> anyhow::Result<mdbx::MdbxEnvironment<E>> {
+    let mut env =
+        mdbx::MdbxEnvironment::open_ro(::mdbx::Environment::new(), path, CHAINDATA_TABLES.clone())?;
+    if let Some(cold_dir) = cold_dir {
+        env = env.with_cold_ro(::mdbx::Environment::new(), cold_dir, COLD_TABLES.clone())?;
+    }
+    migrations::ensure_migrated(&env.begin()?)?;
+
+    Ok(env)
+}
+
+fn new_environment<E: EnvironmentKind>(
+    path: &std::path::Path,
+    size_upper_limit: u128,
+    growth_step: Option<usize>,
+    opts: &DatabaseOpts,
+) -> anyhow::Result<mdbx::MdbxEnvironment<E>> {
+    mdbx::MdbxEnvironment::open_rw_with_sync_mode(
+        environment_builder(size_upper_limit, growth_step, opts),
+        path,
+        CHAINDATA_TABLES.deref().clone(),
+        opts.effective_sync_mode().into(),
+    )
+}
+
+fn environment_builder<E: EnvironmentKind>(
     size_upper_limit: u128,
     growth_step: Option<usize>,
-) -> anyhow::Result<mdbx::MdbxEnvironment<WriteMap>> {
-    let mut builder = ::mdbx::Environment::<WriteMap>::new();
+    opts: &DatabaseOpts,
+) -> ::mdbx::EnvironmentBuilder<E> {
+    let mut builder = ::mdbx::Environment::<E>::new();
     builder.set_max_dbs(CHAINDATA_TABLES.len());
     builder.set_geometry(Geometry {
         size: Some(0..size_upper_limit.try_into().unwrap_or(usize::MAX)),
@@ -69,5 +223,97 @@ fn new_environment(
         page_size: None,
     });
     builder.set_rp_augment_limit(16 * 256 * 1024);
-    mdbx::MdbxEnvironment::open_rw(builder, path, CHAINDATA_TABLES.deref().clone())
+    if let Some(dirty_pages_limit) = opts.dirty_pages_limit {
+        builder.set_txn_dp_limit(dirty_pages_limit);
+    }
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::*;
+
+    #[test]
+    fn get_many() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+        for n in [1, 3, 5] {
+            tx.set(
+                tables::CanonicalHeader,
+                BlockNumber(n),
+                H256::repeat_byte(n as u8),
+            )
+            .unwrap();
+        }
+
+        assert_eq!(
+            tx.get_many(tables::CanonicalHeader, [5, 2, 1, 5, 6].map(BlockNumber))
+                .unwrap(),
+            vec![
+                Some(H256::repeat_byte(5)),
+                None,
+                Some(H256::repeat_byte(1)),
+                Some(H256::repeat_byte(5)),
+                None,
+            ]
+        );
+        assert!(tx.get_many(tables::CanonicalHeader, []).unwrap().is_empty());
+    }
+
+    #[test]
+    fn cold_tables() {
+        let dir = tempfile::tempdir().unwrap();
+        let opts = DatabaseOpts {
+            cold_dir: Some(dir.path().join("cold")),
+            ..Default::default()
+        };
+        let key = |n| tables::BitmapKey {
+            inner: Address::repeat_byte(n),
+            block_number: BlockNumber(u64::MAX),
+        };
+        let hot_dir = dir.path().join("hot");
+        std::fs::create_dir(&hot_dir).unwrap();
+        let mut bitmap = croaring::Treemap::create();
+        bitmap.add(1);
+
+        {
+            let db = new_database_with_opts::<WriteMap>(&hot_dir, &opts).unwrap();
+            let tx = db.begin_mutable().unwrap();
+            tx.set(tables::CallFromIndex, key(1), bitmap.clone())
+                .unwrap();
+            tx.set(
+                tables::CanonicalHeader,
+                BlockNumber(1),
+                H256::repeat_byte(1),
+            )
+            .unwrap();
+            tx.commit().unwrap();
+
+            let tx = db.begin().unwrap();
+            assert_eq!(
+                tx.get(tables::CallFromIndex, key(1)).unwrap(),
+                Some(bitmap.clone())
+            );
+            let sizes = tx.table_sizes().unwrap();
+            assert!(sizes[tables::CallFromIndex::const_db_name()] > 0);
+        }
+
+        // Index is not in the main environment.
+        let hot = new_database(&hot_dir).unwrap();
+        let tx = hot.begin().unwrap();
+        assert_eq!(tx.get(tables::CallFromIndex, key(1)).unwrap(), None);
+        assert_eq!(
+            tx.get(tables::CanonicalHeader, BlockNumber(1)).unwrap(),
+            Some(H256::repeat_byte(1))
+        );
+        drop(tx);
+
+        // Data written to the main environment would be shadowed, so the split is refused.
+        let tx = hot.begin_mutable().unwrap();
+        tx.set(tables::CallFromIndex, key(2), bitmap).unwrap();
+        tx.commit().unwrap();
+        drop(hot);
+        assert!(new_database_with_opts::<WriteMap>(&hot_dir, &opts).is_err());
+    }
 }
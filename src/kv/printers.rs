@@ -0,0 +1,153 @@
+use super::{
+    tables::{self, ErasedTable},
+    traits::*,
+};
+use maplit::hashmap;
+use once_cell::sync::Lazy;
+use std::{collections::HashMap, fmt::Debug, marker::PhantomData};
+
+/// Renders raw entries of a table in a human readable form.
+pub trait EntryPrinter: Send + Sync {
+    fn key(&self, raw: &[u8]) -> anyhow::Result<String>;
+    fn value(&self, raw: &[u8]) -> anyhow::Result<String>;
+}
+
+/// Decodes entries with the table's own codecs and prints them with `Debug`.
+struct TypedPrinter<T>(PhantomData<T>);
+
+impl<T> EntryPrinter for TypedPrinter<T>
+where
+    T: Table,
+    T::Key: TableDecode + Debug,
+    T::Value: Debug,
+{
+    fn key(&self, raw: &[u8]) -> anyhow::Result<String> {
+        Ok(format!("{:?}", ErasedTable::<T>::decode_key(raw)?))
+    }
+
+    fn value(&self, raw: &[u8]) -> anyhow::Result<String> {
+        Ok(format!("{:?}", ErasedTable::<T>::decode_value(raw)?))
+    }
+}
+
+/// Stage ids are only ever encoded, their keys are plain names.
+struct StageIdPrinter<T>(PhantomData<T>);
+
+impl<T> EntryPrinter for StageIdPrinter<T>
+where
+    T: Table,
+    T::Value: Debug,
+{
+    fn key(&self, raw: &[u8]) -> anyhow::Result<String> {
+        Ok(String::from_utf8(raw.to_vec())?)
+    }
+
+    fn value(&self, raw: &[u8]) -> anyhow::Result<String> {
+        Ok(format!("{:?}", ErasedTable::<T>::decode_value(raw)?))
+    }
+}
+
+macro_rules! printers {
+    ($($table:ident),* $(,)?) => {
+        hashmap! {
+            $(tables::$table::const_db_name() => Box::new(TypedPrinter::<tables::$table>(PhantomData)) as Box<dyn EntryPrinter>,)*
+            tables::SyncStage::const_db_name() => Box::new(StageIdPrinter::<tables::SyncStage>(PhantomData)) as Box<dyn EntryPrinter>,
+            tables::PruneProgress::const_db_name() => Box::new(StageIdPrinter::<tables::PruneProgress>(PhantomData)) as Box<dyn EntryPrinter>,
+        }
+    };
+}
+
+static PRINTERS: Lazy<HashMap<&'static str, Box<dyn EntryPrinter>>> = Lazy::new(|| {
+    printers!(
+        Account,
+        Storage,
+        AccountChangeSet,
+        StorageChangeSet,
+        HashedAccount,
+        HashedStorage,
+        AccountHistory,
+        StorageHistory,
+        Code,
+        TrieAccount,
+        TrieStorage,
+        DbInfo,
+        SnapshotInfo,
+        BittorrentInfo,
+        HeaderNumber,
+        CanonicalHeader,
+        Header,
+        HeadersTotalDifficulty,
+        BlockBody,
+        BlockTransaction,
+        TotalGas,
+        TotalTx,
+        Receipt,
+        Log,
+        LogTopicIndex,
+        LogAddressIndex,
+        CallTraceSet,
+        CallFromIndex,
+        CallToIndex,
+        BlockTransactionLookup,
+        Config,
+        TxSender,
+        LastBlock,
+        Migration,
+        Sequence,
+        LastHeader,
+        Issuance,
+        CodeDictionary,
+        CommitmentBranch,
+        ChainHead,
+        StageStats,
+    )
+});
+
+/// Printer for entries of `table`, if it is one of [`tables::CHAINDATA_TABLES`].
+pub fn printer(table: &str) -> Option<&'static dyn EntryPrinter> {
+    PRINTERS.get(table).map(|printer| &**printer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kv::tables::CHAINDATA_TABLES, models::*, StageId};
+
+    #[test]
+    fn all_tables_have_printers() {
+        for table in CHAINDATA_TABLES.keys() {
+            assert!(printer(table).is_some(), "no printer for {}", table);
+        }
+        assert_eq!(PRINTERS.len(), CHAINDATA_TABLES.len());
+    }
+
+    #[test]
+    fn typed_entries() {
+        let canonical = printer(tables::CanonicalHeader::const_db_name()).unwrap();
+        assert_eq!(
+            canonical.key(BlockNumber(42).encode().as_ref()).unwrap(),
+            format!("{:?}", BlockNumber(42))
+        );
+        assert_eq!(
+            canonical
+                .value(H256::repeat_byte(0xab).encode().as_ref())
+                .unwrap(),
+            format!("{:?}", H256::repeat_byte(0xab))
+        );
+        assert!(canonical.key(&[1, 2, 3]).is_err());
+
+        let sync_stage = printer(tables::SyncStage::const_db_name()).unwrap();
+        assert_eq!(
+            sync_stage
+                .key(StageId("Headers").encode().as_ref())
+                .unwrap(),
+            "Headers"
+        );
+        assert_eq!(
+            sync_stage.value(BlockNumber(7).encode().as_ref()).unwrap(),
+            format!("{:?}", BlockNumber(7))
+        );
+
+        assert!(printer("NoSuchTable").is_none());
+    }
+}
@@ -0,0 +1,223 @@
+use super::mdbx::*;
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tracing::*;
+
+const MONITOR_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Returned by reads in a transaction which was open for too long.
+#[derive(Clone, Debug)]
+pub struct ReaderExpired;
+
+impl Display for ReaderExpired {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "read transaction exceeded max age, renew it")
+    }
+}
+
+impl std::error::Error for ReaderExpired {}
+
+#[derive(Debug)]
+struct ReaderEntry {
+    started_at: Instant,
+    expired: Arc<AtomicBool>,
+}
+
+/// Read transactions open in an environment. Old readers prevent MDBX
+/// from reusing the pages freed after them, so the database keeps growing.
+#[derive(Debug, Default)]
+pub struct ReaderRegistry {
+    next_id: AtomicU64,
+    readers: Mutex<HashMap<u64, ReaderEntry>>,
+}
+
+/// Keeps a reader registered until the transaction is dropped.
+#[derive(Debug)]
+pub struct ReaderGuard {
+    id: u64,
+    registry: Arc<ReaderRegistry>,
+    expired: Arc<AtomicBool>,
+}
+
+impl ReaderGuard {
+    pub fn expired_flag(&self) -> Arc<AtomicBool> {
+        self.expired.clone()
+    }
+
+    pub fn check(&self) -> Result<(), ReaderExpired> {
+        check_expired(&self.expired)
+    }
+}
+
+impl Drop for ReaderGuard {
+    fn drop(&mut self) {
+        self.registry.readers.lock().remove(&self.id);
+    }
+}
+
+pub(crate) fn check_expired(expired: &AtomicBool) -> Result<(), ReaderExpired> {
+    if expired.load(Ordering::Relaxed) {
+        return Err(ReaderExpired);
+    }
+    Ok(())
+}
+
+impl ReaderRegistry {
+    pub fn register(self: &Arc<Self>) -> ReaderGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let expired = Arc::new(AtomicBool::new(false));
+        self.readers.lock().insert(
+            id,
+            ReaderEntry {
+                started_at: Instant::now(),
+                expired: expired.clone(),
+            },
+        );
+        ReaderGuard {
+            id,
+            registry: self.clone(),
+            expired,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.readers.lock().len()
+    }
+
+    pub fn oldest_age(&self, now: Instant) -> Option<Duration> {
+        self.readers
+            .lock()
+            .values()
+            .map(|reader| now.saturating_duration_since(reader.started_at))
+            .max()
+    }
+
+    /// Readers which were expired but are still held open. MDBX read transactions can only be
+    /// closed by their owner, so these keep their snapshot until they are next read from or
+    /// dropped.
+    pub fn expired_count(&self) -> usize {
+        self.readers
+            .lock()
+            .values()
+            .filter(|reader| reader.expired.load(Ordering::Relaxed))
+            .count()
+    }
+
+    /// Makes further reads in the readers older than `max_age` fail, returns how many were expired.
+    pub fn expire_older_than(&self, max_age: Duration, now: Instant) -> usize {
+        let mut expired = 0;
+        for reader in self.readers.lock().values() {
+            if now.saturating_duration_since(reader.started_at) > max_age
+                && !reader.expired.swap(true, Ordering::Relaxed)
+            {
+                expired += 1;
+            }
+        }
+        expired
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReaderPolicy {
+    /// Readers open longer than that are reported.
+    pub max_age: Option<Duration>,
+    /// Expire the readers exceeding `max_age`, instead of only reporting them.
+    pub abort: bool,
+}
+
+/// Periodically reports the free list size, the reader ages and the commits since the last report,
+/// applying `policy` to old readers.
+pub async fn monitor_readers<DB, E>(db: Arc<DB>, policy: ReaderPolicy) -> anyhow::Result<()>
+where
+    DB: Deref<Target = MdbxEnvironment<E>> + Send + Sync,
+    E: EnvironmentKind,
+{
+    let mut interval = tokio::time::interval(MONITOR_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let new_commits = db.take_commit_interval();
+        if new_commits.commits > 0 {
+            info!("Database: {}", new_commits);
+        }
+
+        let now = Instant::now();
+        let free_pages = db.freelist()?;
+        let readers = db.readers();
+        let idle = readers.expired_count();
+        if idle > 0 {
+            warn!(
+                "{} expired read transactions are still held open without being read from",
+                idle
+            );
+        }
+        let oldest = readers.oldest_age(now);
+        debug!(
+            "Free list: {} pages, readers: {}, oldest reader: {:?}",
+            free_pages,
+            readers.count(),
+            oldest
+        );
+
+        if let (Some(max_age), Some(oldest)) = (policy.max_age, oldest) {
+            if oldest > max_age {
+                warn!(
+                    "Read transaction open for {:?} holds back page reuse, free list: {} pages",
+                    oldest, free_pages
+                );
+                if policy.abort {
+                    let expired = readers.expire_older_than(max_age, now);
+                    warn!("Expired {} long-running read transactions", expired);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv::{new_mem_database, tables, KvError};
+
+    #[test]
+    fn expire_long_readers() {
+        let db = new_mem_database().unwrap();
+
+        let old_tx = db.begin().unwrap();
+        let mut cursor = old_tx.cursor(tables::SyncStage).unwrap();
+        assert_eq!(db.readers().count(), 1);
+
+        let later = Instant::now() + Duration::from_secs(60);
+        assert_eq!(
+            db.readers()
+                .expire_older_than(Duration::from_secs(30), later),
+            1
+        );
+
+        assert_eq!(db.readers().expired_count(), 1);
+        assert!(matches!(
+            old_tx.get(tables::SyncStage, crate::StageId("Headers")),
+            Err(KvError::ReaderExpired(_))
+        ));
+        assert!(matches!(cursor.first(), Err(KvError::ReaderExpired(_))));
+        assert!(matches!(
+            cursor.seek(crate::StageId("Headers")),
+            Err(KvError::ReaderExpired(_))
+        ));
+
+        // renewed
+        drop(old_tx);
+        assert_eq!(db.readers().count(), 0);
+        let tx = db.begin().unwrap();
+        assert!(tx.get(tables::SyncStage, crate::StageId("Headers")).is_ok());
+    }
+}
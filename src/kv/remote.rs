@@ -1,8 +1,6 @@
 use self::kv_client::*;
-use super::*;
-use crate::kv::traits::*;
+use super::traits::*;
 use anyhow::Context;
-use async_trait::async_trait;
 pub use ethereum_interfaces::remotekv::{Cursor as GrpcCursor, *};
 use std::{marker::PhantomData, sync::Arc};
 use tokio::sync::{
@@ -24,27 +22,44 @@ pub struct RemoteTransaction {
 
 /// Cursor opened by `RemoteTransaction`.
 #[derive(Debug)]
-pub struct RemoteCursor<'tx, B> {
+pub struct RemoteCursor<'tx, T> {
     transaction: &'tx RemoteTransaction,
     id: u32,
 
     #[allow(unused)]
     drop_handle: OneshotSender<()>,
-    _marker: PhantomData<B>,
+    _marker: PhantomData<T>,
 }
 
-#[async_trait]
-impl<'env> Transaction<'env> for RemoteTransaction {
-    type Cursor<'tx, T: Table> = RemoteCursor<'tx, T>;
-    type CursorDupSort<'tx, T: DupSort> = RemoteCursor<'tx, T>;
+impl RemoteTransaction {
+    pub async fn open<C>(mut client: KvClient<C>) -> anyhow::Result<Self>
+    where
+        C: GrpcService<BoxBody>,
+        <C as GrpcService<BoxBody>>::ResponseBody: Send + Sync + 'static,
+        <<C as GrpcService<BoxBody>>::ResponseBody as Body>::Error:
+            Into<Box<(dyn std::error::Error + Send + Sync + 'static)>> + Send,
+    {
+        trace!("Opening transaction");
+        let (sender, rx) = channel(1);
+        let mut receiver = client.tx(ReceiverStream::new(rx)).await?.into_inner();
+
+        // First message with txid
+        let id = receiver.message().await?.context("no response")?.tx_id;
+
+        trace!("Acquired transaction receiver");
+
+        Ok(Self {
+            id,
+            io: Arc::new(AsyncMutex::new((sender, receiver))),
+        })
+    }
 
-    fn id(&self) -> u64 {
+    pub fn id(&self) -> u64 {
         self.id
     }
 
-    async fn cursor<'tx, T>(&'tx self, table: T) -> anyhow::Result<Self::Cursor<'tx, T>>
+    pub async fn cursor<T>(&self, table: T) -> anyhow::Result<RemoteCursor<'_, T>>
     where
-        'env: 'tx,
         T: Table,
     {
         // - send op open
@@ -100,16 +115,8 @@ impl<'env> Transaction<'env> for RemoteTransaction {
         })
     }
 
-    async fn cursor_dup_sort<'tx, T>(&'tx self, table: T) -> anyhow::Result<Self::Cursor<'tx, T>>
-    where
-        T: DupSort,
-    {
-        self.cursor(table).await
-    }
-
-    async fn get<'tx, T>(&'tx self, table: T, key: T::Key) -> anyhow::Result<Option<T::Value>>
+    pub async fn get<T>(&self, table: T, key: T::Key) -> anyhow::Result<Option<T::Value>>
     where
-        'env: 'tx,
         T: Table,
     {
         self.cursor(table)
@@ -128,32 +135,23 @@ impl<'tx, T: Table> RemoteCursor<'tx, T> {
     ) -> anyhow::Result<Option<Pair>> {
         let mut io = self.transaction.io.lock().await;
 
-        io.0.send(GrpcCursor {
-            op: op as i32,
-            cursor: self.id,
-            k: key.map(|v| v.to_vec().into()).unwrap_or_default(),
-            v: value.map(|v| v.to_vec().into()).unwrap_or_default(),
+        // If the server has closed the stream, the response below says why.
+        let _ =
+            io.0.send(GrpcCursor {
+                op: op as i32,
+                cursor: self.id,
+                k: key.map(|v| v.to_vec().into()).unwrap_or_default(),
+                v: value.map(|v| v.to_vec().into()).unwrap_or_default(),
 
-            bucket_name: Default::default(),
-        })
-        .await?;
+                bucket_name: Default::default(),
+            })
+            .await;
 
         let rsp = io.1.message().await?.context("no response")?;
 
         Ok((!rsp.k.is_empty() || !rsp.v.is_empty()).then_some(rsp))
     }
 
-    async fn op_none(
-        &mut self,
-        op: Op,
-        key: Option<&[u8]>,
-        value: Option<&[u8]>,
-    ) -> anyhow::Result<()> {
-        self.op(op, key, value).await?;
-
-        Ok(())
-    }
-
     async fn op_value(
         &mut self,
         op: Op,
@@ -182,18 +180,15 @@ impl<'tx, T: Table> RemoteCursor<'tx, T> {
 
         Ok(None)
     }
-}
 
-#[async_trait]
-impl<'tx, T: Table> traits::Cursor<'tx, T> for RemoteCursor<'tx, T> {
-    async fn first(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    pub async fn first(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
     where
         T::Key: TableDecode,
     {
         self.op_kv(Op::First, None, None).await
     }
 
-    async fn seek(&mut self, key: T::SeekKey) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    pub async fn seek(&mut self, key: T::SeekKey) -> anyhow::Result<Option<(T::Key, T::Value)>>
     where
         T::Key: TableDecode,
     {
@@ -201,7 +196,7 @@ impl<'tx, T: Table> traits::Cursor<'tx, T> for RemoteCursor<'tx, T> {
             .await
     }
 
-    async fn seek_exact(&mut self, key: T::Key) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    pub async fn seek_exact(&mut self, key: T::Key) -> anyhow::Result<Option<(T::Key, T::Value)>>
     where
         T::Key: TableDecode,
     {
@@ -209,28 +204,29 @@ impl<'tx, T: Table> traits::Cursor<'tx, T> for RemoteCursor<'tx, T> {
             .await
     }
 
-    async fn next(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    #[allow(clippy::should_implement_trait)]
+    pub async fn next(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
     where
         T::Key: TableDecode,
     {
         self.op_kv(Op::Next, None, None).await
     }
 
-    async fn prev(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    pub async fn prev(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
     where
         T::Key: TableDecode,
     {
         self.op_kv(Op::Prev, None, None).await
     }
 
-    async fn last(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    pub async fn last(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
     where
         T::Key: TableDecode,
     {
         self.op_kv(Op::Last, None, None).await
     }
 
-    async fn current(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    pub async fn current(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
     where
         T::Key: TableDecode,
     {
@@ -238,72 +234,42 @@ impl<'tx, T: Table> traits::Cursor<'tx, T> for RemoteCursor<'tx, T> {
     }
 }
 
-#[async_trait]
-impl<'tx, T: DupSort> traits::CursorDupSort<'tx, T> for RemoteCursor<'tx, T> {
-    async fn seek_both_range(
+impl<'tx, T: DupSort> RemoteCursor<'tx, T> {
+    pub async fn seek_both_range(
         &mut self,
         key: T::Key,
         value: T::SeekBothKey,
-    ) -> anyhow::Result<Option<T::Value>>
-    where
-        T::Key: Clone,
-    {
+    ) -> anyhow::Result<Option<T::Value>> {
         self.op_value(
             Op::SeekBoth,
-            Some(key.clone().encode().as_ref()),
+            Some(key.encode().as_ref()),
             Some(value.encode().as_ref()),
         )
         .await
     }
 
-    async fn last_dup(&mut self) -> anyhow::Result<Option<T::Value>>
-    where
-        T::Key: TableDecode,
-    {
+    pub async fn last_dup(&mut self) -> anyhow::Result<Option<T::Value>> {
         self.op_value(Op::LastDup, None, None).await
     }
 
-    async fn next_dup(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    pub async fn next_dup(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
     where
         T::Key: TableDecode,
     {
         self.op_kv(Op::NextDup, None, None).await
     }
-    async fn next_no_dup(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
+
+    pub async fn next_no_dup(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
     where
         T::Key: TableDecode,
     {
         self.op_kv(Op::NextNoDup, None, None).await
     }
 
-    async fn prev_dup(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    pub async fn prev_dup(&mut self) -> anyhow::Result<Option<(T::Key, T::Value)>>
     where
         T::Key: TableDecode,
     {
         self.op_kv(Op::PrevDup, None, None).await
     }
 }
-
-impl RemoteTransaction {
-    pub async fn open<C>(mut client: KvClient<C>) -> anyhow::Result<Self>
-    where
-        C: GrpcService<BoxBody>,
-        <C as GrpcService<BoxBody>>::ResponseBody: Send + Sync + 'static,
-        <<C as GrpcService<BoxBody>>::ResponseBody as Body>::Error:
-            Into<Box<(dyn std::error::Error + Send + Sync + 'static)>> + Send,
-    {
-        trace!("Opening transaction");
-        let (sender, rx) = channel(1);
-        let mut receiver = client.tx(ReceiverStream::new(rx)).await?.into_inner();
-
-        // First message with txid
-        let id = receiver.message().await?.context("no response")?.tx_id;
-
-        trace!("Acquired transaction receiver");
-
-        Ok(Self {
-            id,
-            io: Arc::new(AsyncMutex::new((sender, receiver))),
-        })
-    }
-}
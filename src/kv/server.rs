@@ -1,29 +1,53 @@
-use super::{traits::*, CustomTable};
-use async_trait::async_trait;
-use ethereum_interfaces::{
-    remotekv::{Op, Pair, StateChangeBatch, StateChangeRequest},
-    types::VersionReply,
+use super::{
+    mdbx::*,
+    remote::{kv_server::Kv, GrpcCursor, Op, Pair, StateChangeBatch, StateChangeRequest},
+    CustomTable, KvError,
 };
+use async_trait::async_trait;
+use ethereum_interfaces::types::VersionReply;
 use futures_core::Stream;
-use std::{pin::Pin, sync::Arc};
-use tokio::sync::mpsc::channel;
-use tokio_stream::StreamExt;
-use tonic::Response;
+use std::{collections::HashMap, ops::Deref, pin::Pin, sync::Arc, time::Duration};
+use tokio::sync::mpsc::{channel, Sender};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Response, Status, Streaming};
+use tracing::*;
+
+/// Read transactions are closed after this long by default, like in Erigon.
+pub const DEFAULT_MAX_TX_AGE: Duration = Duration::from_secs(60);
+
+/// Serves the database over the remote KV protocol.
+///
+/// Each `Tx` stream holds one read transaction, and any number of cursors
+/// opened in it are multiplexed over the stream by their ids.
+#[derive(Debug)]
+pub struct KvServer<DB> {
+    db: Arc<DB>,
+    max_tx_age: Duration,
+}
+
+impl<DB> KvServer<DB> {
+    pub fn new(db: Arc<DB>) -> Self {
+        Self::with_max_tx_age(db, DEFAULT_MAX_TX_AGE)
+    }
 
-pub struct KvServer<DB: KV + Send + Sync> {
-    env: Arc<DB>,
+    /// Streams keeping their transaction open longer than `max_tx_age` are aborted.
+    pub fn with_max_tx_age(db: Arc<DB>, max_tx_age: Duration) -> Self {
+        Self { db, max_tx_age }
+    }
 }
 
+type ReplySender = Sender<Result<Pair, Status>>;
+
 #[async_trait]
-impl<DB: KV + Send + Sync> ethereum_interfaces::remotekv::kv_server::Kv for KvServer<DB> {
-    type TxStream =
-        Pin<Box<dyn Stream<Item = Result<Pair, tonic::Status>> + Send + Sync + 'static>>;
-    type StateChangesStream = tokio_stream::Pending<Result<StateChangeBatch, tonic::Status>>;
+impl<DB, E> Kv for KvServer<DB>
+where
+    DB: Deref<Target = MdbxEnvironment<E>> + Send + Sync + 'static,
+    E: EnvironmentKind,
+{
+    type TxStream = Pin<Box<dyn Stream<Item = Result<Pair, Status>> + Send + Sync + 'static>>;
+    type StateChangesStream = tokio_stream::Pending<Result<StateChangeBatch, Status>>;
 
-    async fn version(
-        &self,
-        _: tonic::Request<()>,
-    ) -> Result<Response<VersionReply>, tonic::Status> {
+    async fn version(&self, _: tonic::Request<()>) -> Result<Response<VersionReply>, Status> {
         Ok(Response::new(VersionReply {
             major: 4,
             minor: 0,
@@ -33,177 +57,341 @@ impl<DB: KV + Send + Sync> ethereum_interfaces::remotekv::kv_server::Kv for KvSe
 
     async fn tx(
         &self,
-        request: tonic::Request<tonic::Streaming<ethereum_interfaces::remotekv::Cursor>>,
-    ) -> Result<Response<Self::TxStream>, tonic::Status> {
-        let mut req = request.into_inner();
-        let env = self.env.clone();
-        let (tx, rx) = channel(1);
-        tokio::spawn(async move {
-            let dbtx = env.begin().await.unwrap();
-
-            let mut cursors: Vec<
-                Option<<<DB as KV>::Tx<'_> as Transaction>::CursorDupSort<'_, CustomTable>>,
-            > = vec![];
-
-            fn get_cursor<'db: 'tx, 'tx: 'cur, 'cur, DB: KV>(
-                cursors: &'cur mut Vec<
-                    Option<
-                        <<DB as KV>::Tx<'db> as Transaction<'db>>::CursorDupSort<'tx, CustomTable>,
-                    >,
-                >,
-                id: usize,
-            ) -> Result<
-                &'cur mut <<DB as KV>::Tx<'db> as Transaction<'db>>::CursorDupSort<
-                    'tx,
-                    CustomTable,
-                >,
-                tonic::Status,
-            > {
-                cursors
-                    .get_mut(id)
-                    .ok_or_else(|| tonic::Status::invalid_argument("invalid cursor"))?
-                    .as_mut()
-                    .ok_or_else(|| tonic::Status::invalid_argument("cursor closed"))
-            }
+        request: tonic::Request<Streaming<GrpcCursor>>,
+    ) -> Result<Response<Self::TxStream>, Status> {
+        let requests = request.into_inner();
+        let (reply_tx, reply_rx) = channel(1);
+        let (op_tx, mut op_rx) = channel(1);
+
+        tokio::spawn(forward_requests(
+            requests,
+            op_tx,
+            reply_tx.clone(),
+            self.max_tx_age,
+        ));
 
-            let _ = tx
-                .send(Ok(Pair {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || {
+            let dbtx = match db.begin() {
+                Ok(dbtx) => dbtx,
+                Err(e) => {
+                    let _ = reply_tx.blocking_send(Err(Status::internal(e.to_string())));
+                    return;
+                }
+            };
+
+            if reply_tx
+                .blocking_send(Ok(Pair {
                     tx_id: dbtx.id(),
                     ..Default::default()
                 }))
-                .await;
-
-            while let Some(c) = req.try_next().await.unwrap() {
-                let _ = tx
-                    .send(
-                        async {
-                            let cursor_id = c.cursor;
-                            let cid = cursor_id as usize;
-                            let (k, v): (Vec<u8>, Vec<u8>) =
-                                match Op::from_i32(c.op).ok_or_else(|| {
-                                    tonic::Status::invalid_argument(format!("invalid op: {}", c.op))
-                                })? {
-                                    Op::First => get_cursor::<DB>(&mut cursors, cid)?
-                                        .first()
-                                        .await
-                                        .map_err(|e| tonic::Status::internal(e.to_string()))?,
-                                    Op::FirstDup => {
-                                        return Err(tonic::Status::unimplemented(
-                                            "not implemented",
-                                        ));
-                                    }
-                                    Op::Seek => get_cursor::<DB>(&mut cursors, cid)?
-                                        .seek(c.k.to_vec())
-                                        .await
-                                        .map_err(|e| tonic::Status::internal(e.to_string()))?,
-                                    Op::SeekBoth => get_cursor::<DB>(&mut cursors, cid)?
-                                        .seek_both_range(c.k.to_vec(), c.v.to_vec())
-                                        .await
-                                        .map_err(|e| tonic::Status::internal(e.to_string()))?
-                                        .map(|v| (vec![], v)),
-                                    Op::Current => get_cursor::<DB>(&mut cursors, cid)?
-                                        .current()
-                                        .await
-                                        .map_err(|e| tonic::Status::internal(e.to_string()))?,
-                                    Op::Last => {
-                                        get_cursor::<DB>(&mut cursors, cid)?
-                                            .last()
-                                            .await
-                                            .map_err(|e| tonic::Status::internal(e.to_string()))?
-                                    }
-                                    Op::LastDup => get_cursor::<DB>(&mut cursors, cid)?
-                                        .last_dup()
-                                        .await
-                                        .map_err(|e| tonic::Status::internal(e.to_string()))?
-                                        .map(|v| (vec![], v)),
-                                    Op::Next => {
-                                        get_cursor::<DB>(&mut cursors, cid)?
-                                            .next()
-                                            .await
-                                            .map_err(|e| tonic::Status::internal(e.to_string()))?
-                                    }
-                                    Op::NextDup => get_cursor::<DB>(&mut cursors, cid)?
-                                        .next_dup()
-                                        .await
-                                        .map_err(|e| tonic::Status::internal(e.to_string()))?,
-                                    Op::NextNoDup => get_cursor::<DB>(&mut cursors, cid)?
-                                        .next_no_dup()
-                                        .await
-                                        .map_err(|e| tonic::Status::internal(e.to_string()))?,
-                                    Op::Prev => {
-                                        get_cursor::<DB>(&mut cursors, cid)?
-                                            .prev()
-                                            .await
-                                            .map_err(|e| tonic::Status::internal(e.to_string()))?
-                                    }
-                                    Op::PrevDup => get_cursor::<DB>(&mut cursors, cid)?
-                                        .prev_dup()
-                                        .await
-                                        .map_err(|e| tonic::Status::internal(e.to_string()))?,
-                                    Op::PrevNoDup => {
-                                        return Err(tonic::Status::unimplemented(
-                                            "not implemented",
-                                        ));
-                                    }
-                                    Op::SeekExact => get_cursor::<DB>(&mut cursors, cid)?
-                                        .seek_exact(c.k.to_vec())
-                                        .await
-                                        .map_err(|e| tonic::Status::internal(e.to_string()))?,
-                                    Op::SeekBothExact => {
-                                        return Err(tonic::Status::unimplemented(
-                                            "not implemented",
-                                        ));
-                                    }
-                                    Op::Open => {
-                                        let cursor = dbtx
-                                            .cursor_dup_sort(CustomTable::from(c.bucket_name))
-                                            .await
-                                            .map_err(|e| tonic::Status::internal(e.to_string()))?;
-                                        cursors.push(Some(cursor));
-                                        let cursor_id = u32::try_from(cursors.len() - 1)
-                                            .map_err(|_| tonic::Status::internal("overflow"))?;
-
-                                        return Ok(Pair {
-                                            cursor_id,
-                                            ..Default::default()
-                                        });
-                                    }
-                                    Op::Close => {
-                                        if let Some(cursor) = cursors.get_mut(c.cursor as usize) {
-                                            *cursor = None;
-                                        }
-
-                                        return Ok(Pair {
-                                            cursor_id: c.cursor,
-                                            ..Default::default()
-                                        });
-                                    }
-                                }
-                                .unwrap_or_else(|| (vec![], vec![]));
-
-                            Ok(Pair {
-                                k: k.into(),
-                                v: v.into(),
-                                tx_id: dbtx.id(),
-                                cursor_id,
-                            })
-                        }
-                        .await,
-                    )
-                    .await;
+                .is_err()
+            {
+                return;
+            }
+
+            let mut cursors = Cursors::default();
+            while let Some(request) = op_rx.blocking_recv() {
+                let reply = cursors.handle(&dbtx, request);
+                if reply_tx.blocking_send(reply).is_err() {
+                    break;
+                }
             }
+            trace!("Closing remote transaction {}", dbtx.id());
         });
 
-        Ok(Response::new(Box::pin(
-            tokio_stream::wrappers::ReceiverStream::new(rx),
-        )))
+        Ok(Response::new(Box::pin(ReceiverStream::new(reply_rx))))
     }
 
     async fn state_changes(
         &self,
         request: tonic::Request<StateChangeRequest>,
-    ) -> Result<Response<Self::StateChangesStream>, tonic::Status> {
+    ) -> Result<Response<Self::StateChangesStream>, Status> {
         let _ = request;
         Ok(Response::new(tokio_stream::pending()))
     }
 }
+
+/// Hands the client requests over to the transaction thread until the stream ends
+/// or the transaction gets too old. Dropping `op_tx` closes the transaction.
+async fn forward_requests(
+    mut requests: Streaming<GrpcCursor>,
+    op_tx: Sender<GrpcCursor>,
+    reply_tx: ReplySender,
+    max_tx_age: Duration,
+) {
+    let deadline = tokio::time::Instant::now() + max_tx_age;
+    loop {
+        match tokio::time::timeout_at(deadline, requests.message()).await {
+            Ok(Ok(Some(request))) => {
+                if op_tx.send(request).await.is_err() {
+                    break;
+                }
+            }
+            Ok(Ok(None)) => break,
+            Ok(Err(status)) => {
+                debug!("Remote transaction stream failed: {}", status);
+                break;
+            }
+            Err(_) => {
+                let _ = reply_tx
+                    .send(Err(Status::deadline_exceeded(format!(
+                        "read transaction exceeded max age of {:?}, renew it",
+                        max_tx_age
+                    ))))
+                    .await;
+                break;
+            }
+        }
+    }
+}
+
+/// Cursors opened in one remote transaction, by id.
+#[derive(Default)]
+struct Cursors<'tx> {
+    next_id: u32,
+    open: HashMap<u32, MdbxCursor<'tx, RO, CustomTable>>,
+}
+
+fn status(e: KvError) -> Status {
+    if matches!(e, KvError::ReaderExpired(_)) {
+        return Status::deadline_exceeded(e.to_string());
+    }
+    Status::internal(e.to_string())
+}
+
+impl<'tx> Cursors<'tx> {
+    fn cursor(&mut self, id: u32) -> Result<&mut MdbxCursor<'tx, RO, CustomTable>, Status> {
+        self.open
+            .get_mut(&id)
+            .ok_or_else(|| Status::invalid_argument(format!("unknown cursor {}", id)))
+    }
+
+    fn handle<'env: 'tx, E: EnvironmentKind>(
+        &mut self,
+        dbtx: &'tx MdbxTransaction<'env, RO, E>,
+        c: GrpcCursor,
+    ) -> Result<Pair, Status> {
+        let cursor_id = c.cursor;
+        let op = Op::from_i32(c.op)
+            .ok_or_else(|| Status::invalid_argument(format!("invalid op: {}", c.op)))?;
+        let (k, v) = match op {
+            Op::Open => {
+                let cursor = dbtx
+                    .cursor(CustomTable::from(c.bucket_name))
+                    .map_err(status)?;
+                let cursor_id = self.next_id;
+                self.next_id = self
+                    .next_id
+                    .checked_add(1)
+                    .ok_or_else(|| Status::resource_exhausted("too many cursors"))?;
+                self.open.insert(cursor_id, cursor);
+                trace!(
+                    "Opened cursor {} in remote transaction {}",
+                    cursor_id,
+                    dbtx.id()
+                );
+
+                return Ok(Pair {
+                    cursor_id,
+                    tx_id: dbtx.id(),
+                    ..Default::default()
+                });
+            }
+            Op::Close => {
+                self.open.remove(&cursor_id);
+
+                return Ok(Pair {
+                    cursor_id,
+                    tx_id: dbtx.id(),
+                    ..Default::default()
+                });
+            }
+            Op::First => self.cursor(cursor_id)?.first().map_err(status)?,
+            Op::Seek => self.cursor(cursor_id)?.seek(c.k.to_vec()).map_err(status)?,
+            Op::SeekExact => self
+                .cursor(cursor_id)?
+                .seek_exact(c.k.to_vec())
+                .map_err(status)?,
+            Op::SeekBoth => self
+                .cursor(cursor_id)?
+                .seek_both_range(c.k.to_vec(), c.v.to_vec())
+                .map_err(status)?
+                .map(|v| (c.k.to_vec(), v)),
+            Op::Current => self.cursor(cursor_id)?.current().map_err(status)?,
+            Op::Last => self.cursor(cursor_id)?.last().map_err(status)?,
+            Op::LastDup => self
+                .cursor(cursor_id)?
+                .last_dup()
+                .map_err(status)?
+                .map(|v| (vec![], v)),
+            Op::Next => self.cursor(cursor_id)?.next().map_err(status)?,
+            Op::NextDup => self.cursor(cursor_id)?.next_dup().map_err(status)?,
+            Op::NextNoDup => self.cursor(cursor_id)?.next_no_dup().map_err(status)?,
+            Op::Prev => self.cursor(cursor_id)?.prev().map_err(status)?,
+            Op::PrevDup => self.cursor(cursor_id)?.prev_dup().map_err(status)?,
+            Op::FirstDup | Op::PrevNoDup | Op::SeekBothExact => {
+                return Err(Status::unimplemented(format!(
+                    "op {:?} is not implemented",
+                    op
+                )));
+            }
+        }
+        .unwrap_or_default();
+
+        Ok(Pair {
+            k: k.into(),
+            v: v.into(),
+            tx_id: dbtx.id(),
+            cursor_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        kv::{
+            new_mem_database,
+            remote::{kv_client::KvClient, kv_server::KvServer as GrpcKvServer, RemoteTransaction},
+            tables,
+        },
+        models::*,
+    };
+    use tokio_stream::wrappers::TcpListenerStream;
+
+    async fn serve<DB, E>(server: KvServer<DB>) -> KvClient<tonic::transport::Channel>
+    where
+        DB: Deref<Target = MdbxEnvironment<E>> + Send + Sync + 'static,
+        E: EnvironmentKind,
+    {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(GrpcKvServer::new(server))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        KvClient::connect(format!("http://{}", addr)).await.unwrap()
+    }
+
+    fn fill_db<E: EnvironmentKind>(db: &MdbxEnvironment<E>) -> (Address, Vec<(H256, U256)>) {
+        let tx = db.begin_mutable().unwrap();
+        for n in 0..10 {
+            tx.set(
+                tables::CanonicalHeader,
+                BlockNumber(n),
+                H256::from_low_u64_be(n + 100),
+            )
+            .unwrap();
+        }
+
+        let address = Address::from_low_u64_be(0x42);
+        let slots = (1..=3)
+            .map(|n| (H256::from_low_u64_be(n), U256::from(n * 10)))
+            .collect::<Vec<_>>();
+        let mut cursor = tx.cursor(tables::Storage).unwrap();
+        for slot in &slots {
+            cursor.upsert(address, *slot).unwrap();
+        }
+        drop(cursor);
+        tx.commit().unwrap();
+
+        (address, slots)
+    }
+
+    #[tokio::test]
+    async fn multiplexed_cursors() {
+        let db = Arc::new(new_mem_database().unwrap());
+        let (address, slots) = fill_db(&db);
+        let client = serve(KvServer::new(db.clone())).await;
+
+        let tx = RemoteTransaction::open(client).await.unwrap();
+
+        let mut forward = tx.cursor(tables::CanonicalHeader).await.unwrap();
+        let mut backward = tx.cursor(tables::CanonicalHeader).await.unwrap();
+        let mut storage = tx.cursor(tables::Storage).await.unwrap();
+
+        assert_eq!(
+            forward.first().await.unwrap(),
+            Some((BlockNumber(0), H256::from_low_u64_be(100)))
+        );
+        assert_eq!(
+            backward.last().await.unwrap(),
+            Some((BlockNumber(9), H256::from_low_u64_be(109)))
+        );
+        assert_eq!(
+            storage.seek_both_range(address, slots[1].0).await.unwrap(),
+            Some(slots[1])
+        );
+
+        // Interleaved moves do not disturb each other.
+        for n in 1..5 {
+            assert_eq!(
+                forward.next().await.unwrap(),
+                Some((BlockNumber(n), H256::from_low_u64_be(n + 100)))
+            );
+            assert_eq!(
+                backward.prev().await.unwrap(),
+                Some((BlockNumber(9 - n), H256::from_low_u64_be(109 - n)))
+            );
+        }
+        assert_eq!(storage.next_dup().await.unwrap(), Some((address, slots[2])));
+        assert_eq!(storage.next_dup().await.unwrap(), None);
+
+        drop(backward);
+        assert_eq!(
+            tx.get(tables::CanonicalHeader, BlockNumber(7))
+                .await
+                .unwrap(),
+            Some(H256::from_low_u64_be(107))
+        );
+        assert_eq!(
+            tx.get(tables::CanonicalHeader, BlockNumber(10))
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            forward.current().await.unwrap(),
+            Some((BlockNumber(4), H256::from_low_u64_be(104)))
+        );
+    }
+
+    #[tokio::test]
+    async fn read_tx_timeout() {
+        let db = Arc::new(new_mem_database().unwrap());
+        fill_db(&db);
+        let client = serve(KvServer::with_max_tx_age(
+            db.clone(),
+            Duration::from_millis(200),
+        ))
+        .await;
+
+        let tx = RemoteTransaction::open(client.clone()).await.unwrap();
+        let mut cursor = tx.cursor(tables::CanonicalHeader).await.unwrap();
+        assert!(cursor.first().await.unwrap().is_some());
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        let status = cursor
+            .next()
+            .await
+            .unwrap_err()
+            .downcast::<Status>()
+            .unwrap();
+        assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
+
+        // The old transaction is released and a new one can be opened.
+        drop(cursor);
+        drop(tx);
+        let tx = RemoteTransaction::open(client).await.unwrap();
+        assert_eq!(
+            tx.get(tables::CanonicalHeader, BlockNumber(0))
+                .await
+                .unwrap(),
+            Some(H256::from_low_u64_be(100))
+        );
+    }
+}
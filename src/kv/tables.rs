@@ -1,5 +1,5 @@
-use super::*;
-use crate::{models::*, zeroless_view, StageId};
+use super::{value_codec::ValueCodec, *};
+use crate::{codec::*, models::*, StageId};
 use anyhow::{bail, format_err};
 use arrayref::array_ref;
 use arrayvec::ArrayVec;
@@ -102,6 +102,9 @@ macro_rules! decl_table {
 #[derive(Clone, Debug, Default, Deserialize)]
 pub struct TableInfo {
     pub dup_sort: bool,
+    /// Applied to the encoded values by the cursors of the table.
+    #[serde(default)]
+    pub codec: ValueCodec,
 }
 
 impl traits::TableEncode for Vec<u8> {
@@ -132,6 +135,25 @@ impl traits::TableDecode for Bytes {
     }
 }
 
+/// Contract code, stored through the code codec of the environment,
+/// see [`super::code_compression`].
+#[derive(Clone, Debug, Default, Deref, From, Into, PartialEq, Eq)]
+pub struct ContractCode(pub Bytes);
+
+impl TableEncode for ContractCode {
+    type Encoded = Bytes;
+
+    fn encode(self) -> Self::Encoded {
+        self.0
+    }
+}
+
+impl TableDecode for ContractCode {
+    fn decode(b: &[u8]) -> anyhow::Result<Self> {
+        Ok(Self(b.to_vec().into()))
+    }
+}
+
 #[derive(Clone, Debug, Default, Deref, DerefMut, PartialEq, Eq, PartialOrd, Ord)]
 pub struct VariableVec<const LEN: usize> {
     pub inner: ArrayVec<u8, LEN>,
@@ -305,10 +327,30 @@ macro_rules! scale_table_object {
 }
 
 scale_table_object!(BodyForStorage);
-scale_table_object!(BlockHeader);
-scale_table_object!(MessageWithSignature);
 scale_table_object!(Vec<crate::models::Log>);
 
+/// Canonical encoding, stored bytes can be served to the peers as is.
+macro_rules! rlp_table_object {
+    ($ty:ty) => {
+        impl TableEncode for $ty {
+            type Encoded = Vec<u8>;
+
+            fn encode(self) -> Self::Encoded {
+                ::rlp::encode(&self).to_vec()
+            }
+        }
+
+        impl TableDecode for $ty {
+            fn decode(b: &[u8]) -> anyhow::Result<Self> {
+                Ok(::rlp::decode(b)?)
+            }
+        }
+    };
+}
+
+rlp_table_object!(BlockHeader);
+rlp_table_object!(MessageWithSignature);
+
 macro_rules! ron_table_object {
     ($ty:ident) => {
         impl TableEncode for $ty {
@@ -399,21 +441,15 @@ impl TableEncode for U256 {
     type Encoded = VariableVec<KECCAK_LENGTH>;
 
     fn encode(self) -> Self::Encoded {
-        self.to_be_bytes()
-            .into_iter()
-            .skip_while(|&v| v == 0)
-            .collect()
+        Self::Encoded {
+            inner: encode_compact_u256(self),
+        }
     }
 }
 
 impl TableDecode for U256 {
     fn decode(b: &[u8]) -> anyhow::Result<Self> {
-        if b.len() > KECCAK_LENGTH {
-            return Err(TooLong::<KECCAK_LENGTH> { got: b.len() }.into());
-        }
-        let mut v = [0; 32];
-        v[KECCAK_LENGTH - b.len()..].copy_from_slice(b);
-        Ok(Self::from_be_bytes(v))
+        decode_compact_u256(b)
     }
 }
 
@@ -491,6 +527,32 @@ impl TableDecode for BitmapKey<Address> {
     }
 }
 
+impl TableEncode for BitmapKey<H256> {
+    type Encoded = [u8; KECCAK_LENGTH + BLOCK_NUMBER_LENGTH];
+
+    fn encode(self) -> Self::Encoded {
+        let mut out = [0; KECCAK_LENGTH + BLOCK_NUMBER_LENGTH];
+        out[..KECCAK_LENGTH].copy_from_slice(&self.inner.encode());
+        out[KECCAK_LENGTH..].copy_from_slice(&self.block_number.encode());
+        out
+    }
+}
+
+impl TableDecode for BitmapKey<H256> {
+    fn decode(b: &[u8]) -> anyhow::Result<Self> {
+        if b.len() != KECCAK_LENGTH + BLOCK_NUMBER_LENGTH {
+            return Err(
+                InvalidLength::<{ KECCAK_LENGTH + BLOCK_NUMBER_LENGTH }> { got: b.len() }.into(),
+            );
+        }
+
+        Ok(Self {
+            inner: H256::decode(&b[..KECCAK_LENGTH])?,
+            block_number: BlockNumber::decode(&b[KECCAK_LENGTH..])?,
+        })
+    }
+}
+
 impl TableEncode for BitmapKey<(Address, H256)> {
     type Encoded = [u8; ADDRESS_LENGTH + KECCAK_LENGTH + BLOCK_NUMBER_LENGTH];
 
@@ -702,48 +764,205 @@ pub type HeaderKey = (BlockNumber, H256);
 struct CallTraceSetFlags {
     flag_from: bool,
     flag_to: bool,
+    /// Entry is followed by [`CallTraceStats`].
+    flag_stats: bool,
     #[skip]
-    unused: B6,
+    unused: B5,
 }
 
-#[derive(Clone, Copy, Debug)]
+/// Value and gas accounted to an address within a block.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CallTraceStats {
+    pub value_sent: U256,
+    pub value_received: U256,
+    /// Gas used by calls into the address, including their subcalls.
+    pub gas_used: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct CallTraceSetEntry {
     pub address: Address,
     pub from: bool,
     pub to: bool,
+    /// Only recorded if enabled in execution.
+    pub stats: Option<CallTraceStats>,
 }
 
+const CALL_TRACE_SET_ENTRY_MAX_LEN: usize = ADDRESS_LENGTH + 1 + (1 + KECCAK_LENGTH) * 2 + 1 + 8;
+
 impl TableEncode for CallTraceSetEntry {
-    type Encoded = [u8; ADDRESS_LENGTH + 1];
+    type Encoded = VariableVec<CALL_TRACE_SET_ENTRY_MAX_LEN>;
 
     fn encode(self) -> Self::Encoded {
-        let mut v = [0; ADDRESS_LENGTH + 1];
-        v[..ADDRESS_LENGTH].copy_from_slice(&self.address.encode());
+        let mut v = VariableVec::default();
+        v.try_extend_from_slice(&self.address.encode()).unwrap();
 
         let mut field_set = CallTraceSetFlags::default();
         field_set.set_flag_from(self.from);
         field_set.set_flag_to(self.to);
-        v[ADDRESS_LENGTH] = field_set.into_bytes()[0];
+        field_set.set_flag_stats(self.stats.is_some());
+        v.push(field_set.into_bytes()[0]);
+
+        if let Some(stats) = self.stats {
+            for field in [
+                &encode_compact_u256(stats.value_sent)[..],
+                &encode_compact_u256(stats.value_received)[..],
+                &encode_compact_u64(stats.gas_used)[..],
+            ] {
+                v.push(field.len() as u8);
+                v.try_extend_from_slice(field).unwrap();
+            }
+        }
 
         v
     }
 }
 
+/// Splits off a field prefixed with its one byte length.
+fn split_length_prefixed<'a>(b: &mut &'a [u8]) -> anyhow::Result<&'a [u8]> {
+    let (&len, rest) = b
+        .split_first()
+        .ok_or_else(|| format_err!("length prefix missing"))?;
+    if rest.len() < len as usize {
+        bail!("field of {} bytes truncated to {}", len, rest.len());
+    }
+
+    let (field, rest) = rest.split_at(len as usize);
+    *b = rest;
+    Ok(field)
+}
+
 impl TableDecode for CallTraceSetEntry {
     fn decode(b: &[u8]) -> anyhow::Result<Self> {
-        if b.len() != ADDRESS_LENGTH + 1 {
-            return Err(InvalidLength::<{ ADDRESS_LENGTH + 1 }> { got: b.len() }.into());
+        if b.len() < ADDRESS_LENGTH + 1 {
+            return Err(TooShort::<{ ADDRESS_LENGTH + 1 }> { got: b.len() }.into());
         }
 
         let field_set = CallTraceSetFlags::from_bytes([b[ADDRESS_LENGTH]]);
+        let mut rest = &b[ADDRESS_LENGTH + 1..];
+
+        // Entries written without stats have the original fixed length layout.
+        let stats = if field_set.flag_stats() {
+            Some(CallTraceStats {
+                value_sent: decode_compact_u256(split_length_prefixed(&mut rest)?)?,
+                value_received: decode_compact_u256(split_length_prefixed(&mut rest)?)?,
+                gas_used: decode_compact_u64(split_length_prefixed(&mut rest)?)?,
+            })
+        } else {
+            None
+        };
+
+        if !rest.is_empty() {
+            bail!("{} excess bytes in call trace entry", rest.len());
+        }
+
         Ok(Self {
             address: Address::decode(&b[..ADDRESS_LENGTH])?,
             from: field_set.flag_from(),
             to: field_set.flag_to(),
+            stats,
         })
     }
 }
 
+/// Latest, safe and finalized canonical blocks as chosen by fork choice.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    ::parity_scale_codec::Encode,
+    ::parity_scale_codec::Decode,
+)]
+pub struct ChainHeadEntry {
+    pub latest: HeaderKey,
+    pub safe: Option<HeaderKey>,
+    pub finalized: Option<HeaderKey>,
+}
+
+scale_table_object!(ChainHeadEntry);
+
+/// Receipt fields that can not be derived from the block: transaction type is taken from the
+/// body, logs and bloom from [`Log`] table.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    ::parity_scale_codec::Encode,
+    ::parity_scale_codec::Decode,
+)]
+pub struct StoredReceipt {
+    pub success: bool,
+    #[codec(compact)]
+    pub cumulative_gas_used: u64,
+}
+
+scale_table_object!(Vec<StoredReceipt>);
+
+/// One completed run of a sync stage.
+#[derive(Clone, Debug, PartialEq, ::parity_scale_codec::Encode, ::parity_scale_codec::Decode)]
+pub struct StageStatsEntry {
+    pub stage: String,
+    pub from: Option<BlockNumber>,
+    pub to: BlockNumber,
+    /// Unix timestamp in seconds.
+    pub started_at: u64,
+    pub duration_ms: u64,
+    /// Change of total table size, an estimate of bytes written by the stage.
+    pub db_growth: i64,
+}
+
+scale_table_object!(StageStatsEntry);
+
+/// Blocks whose entries were removed from the tables filled by a stage, see
+/// [`crate::stages::PruneConfig`].
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    ::parity_scale_codec::Encode,
+    ::parity_scale_codec::Decode,
+)]
+pub struct PruneMarks {
+    /// First pruned block.
+    pub from: BlockNumber,
+    /// First block after the pruned ones, data of blocks `from..to` is gone.
+    pub to: BlockNumber,
+}
+
+scale_table_object!(PruneMarks);
+
+impl PruneMarks {
+    pub fn is_empty(&self) -> bool {
+        self.from >= self.to
+    }
+
+    pub fn contains(&self, block: BlockNumber) -> bool {
+        self.from <= block && block < self.to
+    }
+
+    /// Smallest range covering both the marks and `from..to`.
+    pub fn extend(self, from: BlockNumber, to: BlockNumber) -> Self {
+        if self.is_empty() {
+            Self { from, to }
+        } else if from >= to {
+            self
+        } else {
+            Self {
+                from: self.from.min(from),
+                to: self.to.max(to),
+            }
+        }
+    }
+}
+
 decl_table!(Account => Address => crate::models::Account);
 decl_table!(Storage => Address => (H256, U256));
 decl_table!(AccountChangeSet => AccountChangeKey => AccountChange);
@@ -752,7 +971,7 @@ decl_table!(HashedAccount => H256 => crate::models::Account);
 decl_table!(HashedStorage => H256 => (H256, U256));
 decl_table!(AccountHistory => BitmapKey<Address> => RoaringTreemap);
 decl_table!(StorageHistory => BitmapKey<(Address, H256)> => RoaringTreemap);
-decl_table!(Code => H256 => Bytes);
+decl_table!(Code => H256 => ContractCode);
 decl_table!(TrieAccount => Vec<u8> => Vec<u8>);
 decl_table!(TrieStorage => Vec<u8> => Vec<u8>);
 decl_table!(DbInfo => Vec<u8> => Vec<u8>);
@@ -767,8 +986,9 @@ decl_table!(BlockTransaction => TxIndex => MessageWithSignature);
 decl_table!(TotalGas => BlockNumber => u64);
 decl_table!(TotalTx => BlockNumber => u64);
 decl_table!(Log => (BlockNumber, TxIndex) => Vec<crate::models::Log>);
-decl_table!(LogTopicIndex => Vec<u8> => RoaringTreemap);
-decl_table!(LogAddressIndex => Vec<u8> => RoaringTreemap);
+decl_table!(Receipt => BlockNumber => Vec<StoredReceipt>);
+decl_table!(LogTopicIndex => BitmapKey<H256> => RoaringTreemap);
+decl_table!(LogAddressIndex => BitmapKey<Address> => RoaringTreemap);
 decl_table!(CallTraceSet => BlockNumber => CallTraceSetEntry);
 decl_table!(CallFromIndex => BitmapKey<Address> => RoaringTreemap);
 decl_table!(CallToIndex => BitmapKey<Address> => RoaringTreemap);
@@ -781,6 +1001,11 @@ decl_table!(Migration => Vec<u8> => Vec<u8>);
 decl_table!(Sequence => Vec<u8> => Vec<u8>);
 decl_table!(LastHeader => VariableVec<0> => H256);
 decl_table!(Issuance => Vec<u8> => Vec<u8>);
+decl_table!(CodeDictionary => VariableVec<0> => Bytes);
+decl_table!(CommitmentBranch => Vec<u8> => Vec<u8>);
+decl_table!(ChainHead => VariableVec<0> => ChainHeadEntry);
+decl_table!(StageStats => u64 => StageStatsEntry);
+decl_table!(PruneProgress => StageId => PruneMarks);
 
 pub type DatabaseChart = Arc<HashMap<&'static str, TableInfo>>;
 
@@ -789,16 +1014,20 @@ pub static CHAINDATA_TABLES: Lazy<Arc<HashMap<&'static str, TableInfo>>> = Lazy:
         Account::const_db_name() => TableInfo::default(),
         Storage::const_db_name() => TableInfo {
             dup_sort: true,
+            ..Default::default()
         },
         AccountChangeSet::const_db_name() => TableInfo {
             dup_sort: true,
+            ..Default::default()
         },
         StorageChangeSet::const_db_name() => TableInfo {
             dup_sort: true,
+            ..Default::default()
         },
         HashedAccount::const_db_name() => TableInfo::default(),
         HashedStorage::const_db_name() => TableInfo {
             dup_sort: true,
+            ..Default::default()
         },
         AccountHistory::const_db_name() => TableInfo::default(),
         StorageHistory::const_db_name() => TableInfo::default(),
@@ -816,11 +1045,19 @@ pub static CHAINDATA_TABLES: Lazy<Arc<HashMap<&'static str, TableInfo>>> = Lazy:
         BlockTransaction::const_db_name() => TableInfo::default(),
         TotalGas::const_db_name() => TableInfo::default(),
         TotalTx::const_db_name() => TableInfo::default(),
-        Log::const_db_name() => TableInfo::default(),
+        Log::const_db_name() => TableInfo {
+            codec: ValueCodec::Zstd,
+            ..Default::default()
+        },
+        Receipt::const_db_name() => TableInfo {
+            codec: ValueCodec::Zstd,
+            ..Default::default()
+        },
         LogTopicIndex::const_db_name() => TableInfo::default(),
         LogAddressIndex::const_db_name() => TableInfo::default(),
         CallTraceSet::const_db_name() => TableInfo {
             dup_sort: true,
+            ..Default::default()
         },
         CallFromIndex::const_db_name() => TableInfo::default(),
         CallToIndex::const_db_name() => TableInfo::default(),
@@ -833,9 +1070,31 @@ pub static CHAINDATA_TABLES: Lazy<Arc<HashMap<&'static str, TableInfo>>> = Lazy:
         Sequence::const_db_name() => TableInfo::default(),
         LastHeader::const_db_name() => TableInfo::default(),
         Issuance::const_db_name() => TableInfo::default(),
+        CodeDictionary::const_db_name() => TableInfo::default(),
+        CommitmentBranch::const_db_name() => TableInfo::default(),
+        ChainHead::const_db_name() => TableInfo::default(),
+        StageStats::const_db_name() => TableInfo::default(),
+        PruneProgress::const_db_name() => TableInfo::default(),
     })
 });
 
+/// History indices, rarely read and written in bulk, which may be kept on a slower disk.
+pub static COLD_TABLES: Lazy<DatabaseChart> = Lazy::new(|| {
+    Arc::new(
+        [
+            AccountHistory::const_db_name(),
+            StorageHistory::const_db_name(),
+            LogTopicIndex::const_db_name(),
+            LogAddressIndex::const_db_name(),
+            CallFromIndex::const_db_name(),
+            CallToIndex::const_db_name(),
+        ]
+        .into_iter()
+        .map(|table| (table, CHAINDATA_TABLES[table].clone()))
+        .collect(),
+    )
+});
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -877,4 +1136,38 @@ mod tests {
 
         assert_eq!(Vec::<crate::models::Log>::decode(&encoded).unwrap(), input);
     }
+
+    #[test]
+    fn call_trace_set_entry() {
+        let address = Address::from([0xaa; 20]);
+        let entry = CallTraceSetEntry {
+            address,
+            from: true,
+            to: false,
+            stats: None,
+        };
+        let encoded = entry.encode();
+        assert_eq!(encoded.as_ref(), [&[0xaa; 20] as &[u8], &[0b01]].concat());
+        assert_eq!(CallTraceSetEntry::decode(encoded.as_ref()).unwrap(), entry);
+
+        let entry = CallTraceSetEntry {
+            to: true,
+            stats: Some(CallTraceStats {
+                value_sent: U256::ZERO,
+                value_received: U256::from(0x0100_u64),
+                gas_used: 21_000,
+            }),
+            ..entry
+        };
+        let encoded = entry.encode();
+        assert_eq!(
+            encoded.as_ref(),
+            [&[0xaa; 20] as &[u8], &[0b111, 0, 2, 1, 0, 2, 0x52, 0x08]].concat()
+        );
+        assert_eq!(CallTraceSetEntry::decode(encoded.as_ref()).unwrap(), entry);
+
+        assert!(
+            CallTraceSetEntry::decode(&encoded.as_ref()[..encoded.as_ref().len() - 1]).is_err()
+        );
+    }
 }
@@ -0,0 +1,138 @@
+use anyhow::{bail, format_err};
+use serde::Deserialize;
+use std::borrow::Cow;
+
+const TAG_RAW: u8 = 0;
+const TAG_ZSTD: u8 = 1;
+const TAG_DELTA_ZSTD: u8 = 2;
+
+const COMPRESSION_LEVEL: i32 = 3;
+/// Values shorter than that do not get smaller after compression.
+const MIN_COMPRESSED_LEN: usize = 64;
+
+/// How the values of a table are stored, on top of their typed encoding.
+///
+/// Every codec but [`ValueCodec::Identity`] prefixes values with a tag telling how they
+/// are stored, so tables can be switched between the compressing codecs without a migration.
+/// Switching from or to the identity codec re-encodes the table, see
+/// [`super::migrations::recode_values`]. Tables with sorted duplicates keep the identity codec,
+/// as their values are compared by MDBX.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, clap::ArgEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueCodec {
+    /// Values are stored as encoded.
+    Identity,
+    /// Values are zstd compressed.
+    Zstd,
+    /// Every byte is replaced with its difference from the previous one before
+    /// compression, which suits values made of sorted numbers.
+    Delta,
+}
+
+impl Default for ValueCodec {
+    fn default() -> Self {
+        Self::Identity
+    }
+}
+
+impl ValueCodec {
+    pub fn encode<'a>(&self, value: &'a [u8]) -> Cow<'a, [u8]> {
+        let tag = match self {
+            Self::Identity => return Cow::Borrowed(value),
+            Self::Zstd => TAG_ZSTD,
+            Self::Delta => TAG_DELTA_ZSTD,
+        };
+
+        if value.len() >= MIN_COMPRESSED_LEN {
+            let compressed = if tag == TAG_DELTA_ZSTD {
+                zstd::encode_all(delta(value).as_slice(), COMPRESSION_LEVEL)
+            } else {
+                zstd::encode_all(value, COMPRESSION_LEVEL)
+            };
+            if let Ok(compressed) = compressed {
+                if compressed.len() < value.len() {
+                    let mut encoded = Vec::with_capacity(compressed.len() + 1);
+                    encoded.push(tag);
+                    encoded.extend_from_slice(&compressed);
+                    return Cow::Owned(encoded);
+                }
+            }
+        }
+
+        let mut encoded = Vec::with_capacity(value.len() + 1);
+        encoded.push(TAG_RAW);
+        encoded.extend_from_slice(value);
+        Cow::Owned(encoded)
+    }
+
+    pub fn decode<'a>(&self, b: &'a [u8]) -> anyhow::Result<Cow<'a, [u8]>> {
+        if *self == Self::Identity {
+            return Ok(Cow::Borrowed(b));
+        }
+
+        let (&tag, data) = b
+            .split_first()
+            .ok_or_else(|| format_err!("empty {:?} value", self))?;
+        Ok(match tag {
+            TAG_RAW => Cow::Borrowed(data),
+            TAG_ZSTD => Cow::Owned(zstd::decode_all(data)?),
+            TAG_DELTA_ZSTD => Cow::Owned(undelta(zstd::decode_all(data)?)),
+            other => bail!("unknown value encoding {}", other),
+        })
+    }
+}
+
+fn delta(value: &[u8]) -> Vec<u8> {
+    let mut prev = 0_u8;
+    value
+        .iter()
+        .map(|&b| {
+            let d = b.wrapping_sub(prev);
+            prev = b;
+            d
+        })
+        .collect()
+}
+
+fn undelta(mut value: Vec<u8>) -> Vec<u8> {
+    let mut prev = 0_u8;
+    for b in &mut value {
+        *b = b.wrapping_add(prev);
+        prev = *b;
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let numbers = (1000_u64..1100)
+            .flat_map(|n| n.to_be_bytes())
+            .collect::<Vec<_>>();
+        let short = vec![1, 2, 3];
+
+        for codec in [ValueCodec::Identity, ValueCodec::Zstd, ValueCodec::Delta] {
+            for value in [&numbers, &short, &vec![]] {
+                let encoded = codec.encode(value);
+                assert_eq!(codec.decode(&encoded).unwrap().as_ref(), value.as_slice());
+            }
+        }
+
+        assert_eq!(ValueCodec::Zstd.encode(&numbers)[0], TAG_ZSTD);
+        assert_eq!(ValueCodec::Zstd.encode(&short)[0], TAG_RAW);
+        assert_eq!(ValueCodec::Delta.encode(&numbers)[0], TAG_DELTA_ZSTD);
+
+        // Compressing codecs read each other's values.
+        assert_eq!(
+            ValueCodec::Zstd
+                .decode(&ValueCodec::Delta.encode(&numbers))
+                .unwrap()
+                .as_ref(),
+            numbers.as_slice()
+        );
+        assert!(ValueCodec::Zstd.decode(&[]).is_err());
+    }
+}
@@ -9,6 +9,7 @@
     type_alias_impl_trait,
     adt_const_params
 )]
+#![cfg_attr(feature = "simd-keccak", feature(portable_simd))]
 #![recursion_limit = "256"]
 #![allow(
     dead_code,
@@ -23,20 +24,29 @@ pub mod accessors;
 pub mod binutil;
 mod bitmapdb;
 pub mod chain;
+pub mod changeset;
+pub mod codec;
+pub mod commitment;
 pub mod consensus;
 pub mod crypto;
 pub mod downloader;
 pub mod etl;
 pub mod execution;
+#[cfg(feature = "trace")]
+mod interrupt_trace;
 pub mod kv;
+pub mod mining;
 pub mod models;
 pub mod res;
 pub mod sentry;
+pub mod sentry2;
 pub mod stagedsync;
 pub mod stages;
 mod state;
+pub mod task_group;
 pub mod trie;
 pub(crate) mod util;
+pub mod witness;
 
 pub use stagedsync::stages::StageId;
 pub use state::*;
@@ -0,0 +1,136 @@
+use crate::{chain::protocol_param::param, models::*};
+use anyhow::bail;
+use bytes::Bytes;
+
+/// Gas limit the produced blocks converge to, unless configured otherwise.
+pub const DEFAULT_GAS_LIMIT: u64 = 30_000_000;
+
+/// Validated settings of the locally produced blocks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MiningConfig {
+    pub etherbase: Address,
+    pub extra_data: Bytes,
+    /// Gas limit target, reached over several blocks within the bounds of the chain.
+    pub gas_limit: u64,
+}
+
+impl MiningConfig {
+    /// Checks the settings against the chain they are going to produce blocks for.
+    pub fn new(
+        chain_spec: &ChainSpec,
+        etherbase: Address,
+        extra_data: Bytes,
+        gas_limit: u64,
+    ) -> anyhow::Result<Self> {
+        let max = chain_spec.params.maximum_extra_data_size;
+        if extra_data.len() > max {
+            bail!(
+                "Miner extra data is {} bytes long, {} allows at most {}",
+                extra_data.len(),
+                chain_spec.name,
+                max
+            );
+        }
+
+        let min = chain_spec.params.min_gas_limit;
+        if gas_limit < min {
+            bail!(
+                "Miner gas limit {} is below the minimum of {}",
+                gas_limit,
+                min
+            );
+        }
+
+        Ok(Self {
+            etherbase,
+            extra_data,
+            gas_limit,
+        })
+    }
+
+    /// Gas limit of the child of `parent`: as close to the target as the parent allows.
+    pub fn next_gas_limit(&self, chain_spec: &ChainSpec, parent: &BlockHeader) -> u64 {
+        let number = parent.number + 1;
+
+        let mut parent_gas_limit = parent.gas_limit;
+        if chain_spec.consensus.eip1559_block == Some(number) {
+            parent_gas_limit *= param::ELASTICITY_MULTIPLIER;
+        }
+
+        // Header validation rejects a change of exactly the bound.
+        let max_delta =
+            (parent_gas_limit / chain_spec.params.gas_limit_bound_divisor).saturating_sub(1);
+        if self.gas_limit > parent_gas_limit {
+            self.gas_limit.min(parent_gas_limit + max_delta)
+        } else {
+            self.gas_limit
+                .max(parent_gas_limit - max_delta)
+                .max(chain_spec.params.min_gas_limit)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::res::chainspec::MAINNET;
+
+    #[test]
+    fn extra_data() {
+        let config = |extra_data: Vec<u8>| {
+            MiningConfig::new(
+                &MAINNET,
+                Address::zero(),
+                extra_data.into(),
+                DEFAULT_GAS_LIMIT,
+            )
+        };
+
+        assert!(config(vec![0; MAINNET.params.maximum_extra_data_size]).is_ok());
+        assert!(config(vec![0; MAINNET.params.maximum_extra_data_size + 1]).is_err());
+    }
+
+    #[test]
+    fn gas_limit_moves_towards_target() {
+        let config = |gas_limit| {
+            MiningConfig::new(&MAINNET, Address::zero(), Bytes::new(), gas_limit).unwrap()
+        };
+        let parent = BlockHeader {
+            number: BlockNumber(1_000_000),
+            gas_limit: 10_240_000,
+            ..BlockHeader::empty()
+        };
+
+        assert_eq!(
+            config(20_000_000).next_gas_limit(&MAINNET, &parent),
+            10_249_999
+        );
+        assert_eq!(
+            config(10_000_000).next_gas_limit(&MAINNET, &parent),
+            10_230_001
+        );
+        assert_eq!(
+            config(10_245_000).next_gas_limit(&MAINNET, &parent),
+            10_245_000
+        );
+
+        assert!(MiningConfig::new(
+            &MAINNET,
+            Address::zero(),
+            Bytes::new(),
+            MAINNET.params.min_gas_limit - 1
+        )
+        .is_err());
+
+        // The London fork block doubles the gas limit of its parent.
+        let parent = BlockHeader {
+            number: BlockNumber(MAINNET.consensus.eip1559_block.unwrap().0 - 1),
+            gas_limit: 15_000_000,
+            ..BlockHeader::empty()
+        };
+        assert_eq!(
+            config(DEFAULT_GAS_LIMIT).next_gas_limit(&MAINNET, &parent),
+            DEFAULT_GAS_LIMIT
+        );
+    }
+}
@@ -0,0 +1,6 @@
+//! Block production: settings of the locally built blocks and the ommers they include.
+
+pub mod config;
+pub mod ommers;
+
+pub use self::config::*;
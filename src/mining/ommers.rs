@@ -0,0 +1,141 @@
+use crate::{
+    accessors::chain,
+    kv::{mdbx::MdbxTransaction, tables},
+    models::*,
+};
+use mdbx::{EnvironmentKind, TransactionKind};
+use std::collections::{BTreeMap, HashSet};
+
+/// Generations an ommer may be behind the block including it, see [YP] Section 11.1.
+pub const MAX_OMMER_DEPTH: u64 = 6;
+/// Ommers a block may include.
+pub const MAX_OMMERS: usize = 2;
+
+/// Oldest ommer a block with the given number may include, genesis has no parent to branch off.
+fn oldest_ommer(number: BlockNumber) -> BlockNumber {
+    BlockNumber(number.0.saturating_sub(MAX_OMMER_DEPTH).max(1))
+}
+
+/// Up to [`MAX_OMMERS`] ommers for the child of `parent`, closest generations first as they
+/// pay the most. Candidates are the non-canonical headers stored within [`MAX_OMMER_DEPTH`]
+/// generations, so the header table bounds them by depth and nothing else has to be pruned.
+/// They must branch off an ancestor and not be included already.
+pub fn select<K: TransactionKind, E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, K, E>,
+    parent: &BlockHeader,
+) -> anyhow::Result<Vec<BlockHeader>> {
+    let number = parent.number + 1;
+    let oldest = oldest_ommer(number);
+
+    let mut ancestors = HashSet::new();
+    let mut included = HashSet::new();
+    let mut ancestor = parent.clone();
+    let mut hash = parent.hash();
+    loop {
+        ancestors.insert(hash);
+        // The parent of the oldest ommer is the last ancestor to know about.
+        if ancestor.number < oldest {
+            break;
+        }
+        let Some(body) = chain::storage_body::read(tx, hash, ancestor.number)? else {
+            break;
+        };
+        included.extend(body.uncles.into_iter().map(|(_, hash)| hash));
+
+        let parent_key = (BlockNumber(ancestor.number.0 - 1), ancestor.parent_hash);
+        let Some(header) = tx.get(tables::Header, parent_key)? else {
+            break;
+        };
+        hash = ancestor.parent_hash;
+        ancestor = header;
+    }
+
+    let mut candidates = BTreeMap::<BlockNumber, Vec<(H256, BlockHeader)>>::new();
+    for res in tx.cursor(tables::Header)?.walk(Some(oldest)) {
+        let ((header_number, hash), header) = res?;
+        if header_number >= number {
+            break;
+        }
+        if !ancestors.contains(&hash)
+            && !included.contains(&hash)
+            && ancestors.contains(&header.parent_hash)
+        {
+            candidates
+                .entry(header_number)
+                .or_default()
+                .push((hash, header));
+        }
+    }
+
+    Ok(candidates
+        .into_values()
+        .rev()
+        .flatten()
+        .map(|(_, header)| header)
+        .take(MAX_OMMERS)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv::new_mem_database;
+    use bytes::Bytes;
+
+    fn child(parent: &BlockHeader, tag: &'static [u8]) -> BlockHeader {
+        BlockHeader {
+            parent_hash: parent.hash(),
+            number: parent.number + 1,
+            extra_data: Bytes::from_static(tag),
+            ..BlockHeader::empty()
+        }
+    }
+
+    #[test]
+    fn select_ommers() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let genesis = BlockHeader::empty();
+        let c1 = child(&genesis, b"c1");
+        let c2 = child(&c1, b"c2");
+        let c3 = child(&c2, b"c3");
+
+        let sibling = child(&c1, b"sibling");
+        let included = child(&c1, b"included");
+        let nephew = child(&c2, b"nephew");
+        let orphan = BlockHeader {
+            number: BlockNumber(3),
+            parent_hash: H256::repeat_byte(0xaa),
+            ..BlockHeader::empty()
+        };
+
+        for header in [&genesis, &c1, &c2, &c3] {
+            let key = (header.number, header.hash());
+            tx.set(tables::Header, key, header.clone()).unwrap();
+            tx.set(tables::CanonicalHeader, header.number, header.hash())
+                .unwrap();
+            let uncles = if header == &c3 {
+                vec![(included.number, included.hash())]
+            } else {
+                vec![]
+            };
+            let body = BodyForStorage {
+                base_tx_id: TxIndex(0),
+                tx_amount: 0,
+                uncles,
+            };
+            chain::storage_body::write(&tx, header.hash(), header.number, &body).unwrap();
+        }
+        for header in [&sibling, &included, &nephew, &orphan] {
+            tx.set(
+                tables::Header,
+                (header.number, header.hash()),
+                header.clone(),
+            )
+            .unwrap();
+        }
+
+        assert_eq!(select(&tx, &c3).unwrap(), vec![nephew, sibling]);
+    }
+}
@@ -1,5 +1,4 @@
-use crate::{kv::tables::VariableVec, models::*, util::*};
-use anyhow::bail;
+use crate::{codec::*, kv::tables::VariableVec, models::*, util::*};
 use arrayvec::ArrayVec;
 use bytes::{Buf, Bytes};
 use educe::*;
@@ -23,6 +22,48 @@ pub struct RlpAccount {
     pub code_hash: H256,
 }
 
+/// Upper bound of `RlpAccount` encoding length: list header, nonce, balance and two hashes.
+pub const MAX_RLP_ACCOUNT_LEN: usize = 2 + (1 + 8) + (1 + 32) + (1 + KECCAK_LENGTH) * 2;
+
+impl RlpAccount {
+    /// Same bytes as `rlp::encode`, but written into a stack buffer.
+    pub fn encode_fixed(&self) -> ArrayVec<u8, MAX_RLP_ACCOUNT_LEN> {
+        fn rlp_len(compact: &[u8]) -> usize {
+            if compact.len() == 1 && compact[0] < 0x80 {
+                1
+            } else {
+                1 + compact.len()
+            }
+        }
+
+        fn put(out: &mut ArrayVec<u8, MAX_RLP_ACCOUNT_LEN>, compact: &[u8]) {
+            if compact.len() != 1 || compact[0] >= 0x80 {
+                out.push(0x80 + compact.len() as u8);
+            }
+            out.try_extend_from_slice(compact).unwrap();
+        }
+
+        let nonce = encode_compact_u64(self.nonce);
+        let balance = encode_compact_u256(self.balance);
+
+        let payload_len = rlp_len(&nonce) + rlp_len(&balance) + (1 + KECCAK_LENGTH) * 2;
+
+        let mut out = ArrayVec::new();
+        if payload_len < 56 {
+            out.push(0xc0 + payload_len as u8);
+        } else {
+            // Payload is never longer than 255 bytes
+            out.push(0xf7 + 1);
+            out.push(payload_len as u8);
+        }
+        put(&mut out, &nonce);
+        put(&mut out, &balance);
+        put(&mut out, &self.storage_root.0);
+        put(&mut out, &self.code_hash.0);
+        out
+    }
+}
+
 impl Default for Account {
     fn default() -> Self {
         Self {
@@ -44,15 +85,6 @@ pub struct SerializedAccount {
     pub storage: HashMap<U256, U256>,
 }
 
-fn bytes_to_u64(buf: &[u8]) -> u64 {
-    let mut decoded = [0u8; 8];
-    for (i, b) in buf.iter().rev().enumerate() {
-        decoded[i] = *b;
-    }
-
-    u64::from_le_bytes(decoded)
-}
-
 #[allow(dead_code)]
 #[bitfield]
 #[derive(Clone, Copy, Debug, Default)]
@@ -68,17 +100,13 @@ pub const MAX_ACCOUNT_LEN: usize = 1 + (1 + 32) + (1 + 32) + (1 + 8);
 pub type EncodedAccount = VariableVec<MAX_ACCOUNT_LEN>;
 
 impl Account {
-    fn write_compact<const LEN: usize>(input: &[u8; LEN]) -> ArrayVec<u8, LEN> {
-        input.iter().copied().skip_while(|v| *v == 0).collect()
-    }
-
     pub fn encode_for_storage(&self) -> EncodedAccount {
         let mut buffer = EncodedAccount::default();
 
         let mut field_set = AccountStorageFlags::default(); // start with first bit set to 0
         buffer.push(0);
         if self.nonce != 0 {
-            let b = Self::write_compact(&self.nonce.to_be_bytes());
+            let b = encode_compact_u64(self.nonce);
             field_set.set_nonce_len(b.len().try_into().unwrap());
             buffer.try_extend_from_slice(&b[..]).unwrap();
         }
@@ -93,7 +121,7 @@ impl Account {
 
         // Encoding balance
         if self.balance != 0 {
-            let b = Self::write_compact(&self.balance.to_be_bytes());
+            let b = encode_compact_u256(self.balance);
             buffer.try_extend_from_slice(&b[..]).unwrap();
         }
 
@@ -114,7 +142,7 @@ impl Account {
 
         let decode_length = field_set.nonce_len();
         if decode_length > 0 {
-            a.nonce = bytes_to_u64(&enc[..decode_length.into()]);
+            a.nonce = decode_compact_u64(&enc[..decode_length.into()])?;
             enc.advance(decode_length.into());
         }
 
@@ -123,10 +151,7 @@ impl Account {
             enc.advance(KECCAK_LENGTH);
         }
 
-        if enc.len() > 32 {
-            bail!("balance cannot be longer than 32 bytes")
-        }
-        a.balance = U256::from_be_bytes(static_left_pad(enc));
+        a.balance = decode_compact_u256(enc)?;
 
         Ok(Some(a))
     }
@@ -223,4 +248,50 @@ mod tests {
             hex!("00"),
         )
     }
+
+    #[test]
+    fn rlp_account() {
+        for (account, expected) in [
+            (
+                RlpAccount {
+                    nonce: 0,
+                    balance: U256::ZERO,
+                    storage_root: EMPTY_ROOT,
+                    code_hash: EMPTY_HASH,
+                },
+                &hex!("f8448080a056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421a0c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470") as &[u8],
+            ),
+            (
+                RlpAccount {
+                    nonce: 1,
+                    balance: 0x7f.as_u256(),
+                    storage_root: EMPTY_ROOT,
+                    code_hash: EMPTY_HASH,
+                },
+                &hex!("f844017fa056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421a0c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"),
+            ),
+            (
+                RlpAccount {
+                    nonce: 0x80,
+                    balance: 1_000_000_000_000_000_000_u64.as_u256(),
+                    storage_root: EMPTY_ROOT,
+                    code_hash: keccak256(&[1, 2, 3]),
+                },
+                &hex!("f84d8180880de0b6b3a7640000a056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421a0f1885eda54b7a053318cd41e2093220dab15d65381b1157a3633a83bfd5c9239"),
+            ),
+            (
+                RlpAccount {
+                    nonce: u64::MAX,
+                    balance: U256::MAX,
+                    storage_root: H256::repeat_byte(0xff),
+                    code_hash: H256::repeat_byte(0xff),
+                },
+                &hex!("f86c88ffffffffffffffffa0ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffa0ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffa0ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"),
+            ),
+        ] {
+            let encoded = account.encode_fixed();
+            assert_eq!(&encoded[..], expected);
+            assert_eq!(&encoded[..], &rlp::encode(&account)[..]);
+        }
+    }
 }
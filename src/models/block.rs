@@ -37,7 +37,7 @@ impl Block {
     pub fn transactions_root<I: IntoIterator<Item = T>, T: Borrow<MessageWithSignature>>(
         iter: I,
     ) -> H256 {
-        ordered_trie_root(iter.into_iter().map(|r| r.borrow().trie_encode()))
+        ordered_trie_root(iter.into_iter().map(|tx| tx.borrow().trie_encode()))
     }
 }
 
@@ -48,18 +48,17 @@ pub struct BlockWithSenders {
     pub ommers: Vec<BlockHeader>,
 }
 
-impl From<Block> for BlockWithSenders {
-    fn from(block: Block) -> Self {
+impl BlockWithSenders {
+    /// Pairs the transactions of the block with their senders, which are already known.
+    pub fn new(block: Block, senders: Vec<Address>) -> Self {
+        assert_eq!(block.transactions.len(), senders.len());
         let transactions = block
             .transactions
             .into_iter()
-            .map(|tx| {
-                let sender = tx.recover_sender().unwrap();
-
-                MessageWithSender {
-                    message: tx.message,
-                    sender,
-                }
+            .zip(senders)
+            .map(|(tx, sender)| MessageWithSender {
+                message: tx.message,
+                sender,
             })
             .collect();
 
@@ -71,6 +70,13 @@ impl From<Block> for BlockWithSenders {
     }
 }
 
+impl From<Block> for BlockWithSenders {
+    fn from(block: Block) -> Self {
+        let senders = MessageWithSignature::recover_senders(&block.transactions).unwrap();
+        Self::new(block, senders)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, RlpEncodable, RlpDecodable)]
 pub struct BlockBody {
     pub transactions: Vec<MessageWithSignature>,
@@ -92,8 +98,17 @@ pub struct BlockBodyWithSenders {
     pub ommers: Vec<BlockHeader>,
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Encode, Decode, RlpDecodable)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Encode, Decode)]
 pub struct BodyForStorage {
+    pub base_tx_id: TxIndex,
+    pub tx_amount: u64,
+    /// Ommer headers are kept in the `Header` table and referenced by number and hash.
+    pub uncles: Vec<(BlockNumber, H256)>,
+}
+
+/// Body with full ommer headers, as stored by Erigon and by earlier versions of the database.
+#[derive(Clone, Debug, PartialEq, Encode, Decode, RlpDecodable)]
+pub struct BodyForStorageWithOmmers {
     pub base_tx_id: TxIndex,
     pub tx_amount: u64,
     pub uncles: Vec<BlockHeader>,
@@ -1,8 +1,10 @@
 use crate::{models::*, util::*};
 use bytes::Bytes;
+use derive_more::Display;
 use serde::*;
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt,
     time::Duration,
 };
 
@@ -13,7 +15,9 @@ pub struct BlockExecutionSpec {
     pub revision: Revision,
     pub active_transitions: HashSet<Revision>,
     pub params: Params,
+    /// Contracts deployed or replaced at the start of this block.
     pub system_contract_changes: HashMap<Address, Contract>,
+    /// Balances set at the start of this block.
     pub balance_changes: HashMap<Address, U256>,
 }
 
@@ -33,51 +37,57 @@ pub struct ChainSpec {
 }
 
 impl ChainSpec {
-    pub fn collect_block_spec(&self, block_number: impl Into<BlockNumber>) -> BlockExecutionSpec {
+    /// Revision active at `block_number`, considering forks scheduled by block number only.
+    pub fn revision_at(&self, block_number: impl Into<BlockNumber>) -> Revision {
         let block_number = block_number.into();
-        let mut revision = Revision::Frontier;
-        let mut active_transitions = HashSet::new();
-        for (fork, r) in [
-            (self.upgrades.london, Revision::London),
-            (self.upgrades.berlin, Revision::Berlin),
-            (self.upgrades.istanbul, Revision::Istanbul),
-            (self.upgrades.petersburg, Revision::Petersburg),
-            (self.upgrades.constantinople, Revision::Constantinople),
-            (self.upgrades.byzantium, Revision::Byzantium),
-            (self.upgrades.spurious, Revision::Spurious),
-            (self.upgrades.tangerine, Revision::Tangerine),
-            (self.upgrades.homestead, Revision::Homestead),
-        ] {
-            if let Some(fork_block) = fork {
-                if block_number >= fork_block {
-                    if block_number == fork_block {
-                        active_transitions.insert(r);
-                    }
-                    if revision == Revision::Frontier {
-                        revision = r;
-                    }
-
-                    break;
-                }
-            }
-        }
+        self.upgrades
+            .block_schedule()
+            .into_iter()
+            .filter_map(|(fork, revision)| {
+                fork.filter(|&fork| block_number >= fork).map(|_| revision)
+            })
+            .max()
+            .unwrap_or(Revision::Frontier)
+    }
+
+    /// Revision active in the block with the given number and timestamp, considering forks
+    /// scheduled by timestamp as well.
+    pub fn revision_at_timestamp(
+        &self,
+        block_number: impl Into<BlockNumber>,
+        timestamp: u64,
+    ) -> Revision {
+        self.upgrades
+            .timestamp_schedule()
+            .into_iter()
+            .filter_map(|(fork, revision)| fork.filter(|&fork| timestamp >= fork).map(|_| revision))
+            .fold(self.revision_at(block_number), Ord::max)
+    }
+
+    pub fn collect_block_spec(
+        &self,
+        block_number: impl Into<BlockNumber>,
+        timestamp: u64,
+    ) -> BlockExecutionSpec {
+        let block_number = block_number.into();
+        let revision = self.revision_at_timestamp(block_number, timestamp);
+        // Several revisions may activate at the same block.
+        let active_transitions = self
+            .upgrades
+            .block_schedule()
+            .into_iter()
+            .filter_map(|(fork, revision)| (fork == Some(block_number)).then(|| revision))
+            .collect();
 
         BlockExecutionSpec {
             revision,
             active_transitions,
             params: self.params.clone(),
-            system_contract_changes: self.contracts.iter().fold(
-                HashMap::new(),
-                |mut acc, (bn, contracts)| {
-                    if block_number >= *bn {
-                        for (addr, contract) in contracts {
-                            acc.insert(*addr, contract.clone());
-                        }
-                    }
-
-                    acc
-                },
-            ),
+            system_contract_changes: self
+                .contracts
+                .get(&block_number)
+                .cloned()
+                .unwrap_or_default(),
             balance_changes: self
                 .balances
                 .get(&block_number)
@@ -86,6 +96,35 @@ impl ChainSpec {
         }
     }
 
+    /// Forks scheduled differently by `new`.
+    pub fn fork_schedule_changes(&self, new: &ChainSpec) -> Vec<ForkChange> {
+        let block_changes = self
+            .upgrades
+            .block_schedule()
+            .into_iter()
+            .zip(new.upgrades.block_schedule())
+            .map(|((old, revision), (new, _))| ForkChange {
+                revision,
+                old: old.map(ForkActivation::Block),
+                new: new.map(ForkActivation::Block),
+            });
+        let timestamp_changes = self
+            .upgrades
+            .timestamp_schedule()
+            .into_iter()
+            .zip(new.upgrades.timestamp_schedule())
+            .map(|((old, revision), (new, _))| ForkChange {
+                revision,
+                old: old.map(ForkActivation::Timestamp),
+                new: new.map(ForkActivation::Timestamp),
+            });
+
+        block_changes
+            .chain(timestamp_changes)
+            .filter(|change| change.old != change.new)
+            .collect()
+    }
+
     pub fn gather_forks(&self) -> BTreeSet<BlockNumber> {
         let mut forks = [
             self.upgrades.homestead,
@@ -113,6 +152,54 @@ impl ChainSpec {
     }
 }
 
+/// Block or timestamp a fork activates at.
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq)]
+pub enum ForkActivation {
+    #[display(fmt = "block {}", _0)]
+    Block(BlockNumber),
+    #[display(fmt = "timestamp {}", _0)]
+    Timestamp(u64),
+}
+
+/// Fork scheduled differently by two chain specs, `None` if not scheduled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ForkChange {
+    pub revision: Revision,
+    pub old: Option<ForkActivation>,
+    pub new: Option<ForkActivation>,
+}
+
+impl ForkChange {
+    /// Whether the fork is active at the block with the given number and timestamp according
+    /// to either schedule.
+    pub fn is_active_at(&self, block_number: BlockNumber, timestamp: u64) -> bool {
+        [self.old, self.new]
+            .into_iter()
+            .flatten()
+            .any(|activation| match activation {
+                ForkActivation::Block(fork) => block_number >= fork,
+                ForkActivation::Timestamp(fork) => timestamp >= fork,
+            })
+    }
+}
+
+impl fmt::Display for ForkChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let activation = |activation: Option<ForkActivation>| {
+            activation
+                .map(|activation| activation.to_string())
+                .unwrap_or_else(|| "never".to_string())
+        };
+        write!(
+            f,
+            "{} moved from {} to {}",
+            self.revision,
+            activation(self.old),
+            activation(self.new)
+        )
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct DifficultyBomb {
     pub delays: BTreeMap<BlockNumber, BlockNumber>,
@@ -143,6 +230,9 @@ pub struct ConsensusParams {
         with = "::serde_with::rust::unwrap_or_skip"
     )]
     pub eip1559_block: Option<BlockNumber>,
+    /// How many seconds ahead of the local clock a header timestamp may be.
+    #[serde(default)]
+    pub allowed_future_drift: u64,
 }
 
 pub fn switch_is_active(switch: Option<BlockNumber>, block_number: BlockNumber) -> bool {
@@ -152,7 +242,10 @@ pub fn switch_is_active(switch: Option<BlockNumber>, block_number: BlockNumber)
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub enum SealVerificationParams {
     Clique {
-        #[serde(deserialize_with = "deserialize_period_as_duration")]
+        #[serde(
+            deserialize_with = "deserialize_period_as_duration",
+            serialize_with = "serialize_period_as_u64"
+        )]
         period: Duration,
         epoch: u64,
     },
@@ -265,6 +358,33 @@ pub struct Upgrades {
         with = "::serde_with::rust::unwrap_or_skip"
     )]
     pub london: Option<BlockNumber>,
+    /// Timestamp, not block number, of the Shanghai fork.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub shanghai: Option<u64>,
+}
+
+impl Upgrades {
+    fn block_schedule(&self) -> [(Option<BlockNumber>, Revision); 9] {
+        [
+            (self.homestead, Revision::Homestead),
+            (self.tangerine, Revision::Tangerine),
+            (self.spurious, Revision::Spurious),
+            (self.byzantium, Revision::Byzantium),
+            (self.constantinople, Revision::Constantinople),
+            (self.petersburg, Revision::Petersburg),
+            (self.istanbul, Revision::Istanbul),
+            (self.berlin, Revision::Berlin),
+            (self.london, Revision::London),
+        ]
+    }
+
+    fn timestamp_schedule(&self) -> [(Option<u64>, Revision); 1] {
+        [(self.shanghai, Revision::Shanghai)]
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -272,6 +392,19 @@ pub struct Params {
     pub chain_id: ChainId,
     pub network_id: NetworkId,
     pub min_gas_limit: u64,
+    /// Gas limit may change by less than `parent_gas_limit / gas_limit_bound_divisor` per block.
+    #[serde(default = "default_gas_limit_bound_divisor")]
+    pub gas_limit_bound_divisor: u64,
+    #[serde(default = "default_maximum_extra_data_size")]
+    pub maximum_extra_data_size: usize,
+}
+
+const fn default_gas_limit_bound_divisor() -> u64 {
+    1024
+}
+
+const fn default_maximum_extra_data_size() -> usize {
+    32
 }
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
@@ -343,6 +476,20 @@ pub struct Genesis {
     pub gas_limit: u64,
     pub timestamp: u64,
     pub seal: Seal,
+    /// Nonces, code and storage of accounts at genesis, their balances are in
+    /// [`ChainSpec::balances`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub accounts: BTreeMap<Address, GenesisAccount>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct GenesisAccount {
+    #[serde(default)]
+    pub nonce: u64,
+    #[serde(default, with = "hexbytes", skip_serializing_if = "Bytes::is_empty")]
+    pub code: Bytes,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<U256, U256>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -398,6 +545,13 @@ impl<'de> de::Visitor<'de> for DeserializePeriodAsDuration {
     }
 }
 
+fn serialize_period_as_u64<S>(period: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_u64(period.as_millis() as u64)
+}
+
 fn deserialize_period_as_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
 where
     D: de::Deserializer<'de>,
@@ -430,6 +584,7 @@ mod tests {
                         epoch: 30_000,
                     },
                     eip1559_block: Some(8897988.into()),
+                    allowed_future_drift: 0,
                 },
                 upgrades: Upgrades {
                     homestead: Some(1.into()),
@@ -441,11 +596,14 @@ mod tests {
                     istanbul: Some(5435345.into()),
                     berlin: Some(8290928.into()),
                     london: Some(8897988.into()),
+                    shanghai: None,
                 },
                 params: Params {
                     chain_id: ChainId(4),
                     network_id: NetworkId(4),
                     min_gas_limit: 5000,
+                    gas_limit_bound_divisor: 1024,
+                    maximum_extra_data_size: 65535,
                 },
                 genesis: Genesis {
                     number: BlockNumber(0),
@@ -464,6 +622,7 @@ mod tests {
                             hex!("b279182d99e65703f0076e4812653aab85fca0f0").into(),
                         ],
                     },
+                    accounts: Default::default(),
                 },
                 contracts: Default::default(),
                 balances: btreemap! {
@@ -489,6 +648,150 @@ mod tests {
         );
     }
 
+    #[test]
+    fn stored_chainspec_roundtrip() {
+        for chainspec in [&*MAINNET, &*ROPSTEN, &*RINKEBY] {
+            let stored = ron::to_string(chainspec).unwrap();
+            assert_eq!(ron::from_str::<ChainSpec>(&stored).unwrap(), *chainspec);
+        }
+    }
+
+    #[test]
+    fn params_defaults() {
+        assert_eq!(
+            ron::from_str::<Params>("(chain_id: 1, network_id: 1, min_gas_limit: 5000)").unwrap(),
+            Params {
+                chain_id: ChainId(1),
+                network_id: NetworkId(1),
+                min_gas_limit: 5000,
+                gas_limit_bound_divisor: 1024,
+                maximum_extra_data_size: 32,
+            }
+        );
+    }
+
+    #[test]
+    fn fork_schedule_changes() {
+        let mut new = MAINNET.clone();
+        assert_eq!(MAINNET.fork_schedule_changes(&new), vec![]);
+
+        new.upgrades.london = None;
+        new.upgrades.shanghai = Some(1_700_000_000);
+        let changes = MAINNET.fork_schedule_changes(&new);
+        assert_eq!(
+            changes,
+            vec![
+                ForkChange {
+                    revision: Revision::London,
+                    old: Some(ForkActivation::Block(12965000.into())),
+                    new: None,
+                },
+                ForkChange {
+                    revision: Revision::Shanghai,
+                    old: None,
+                    new: Some(ForkActivation::Timestamp(1_700_000_000)),
+                },
+            ]
+        );
+        assert!(changes[0].is_active_at(12965000.into(), 0));
+        assert!(!changes[1].is_active_at(12965000.into(), 1_600_000_000));
+        assert_eq!(
+            changes[0].to_string(),
+            "London moved from block 12965000 to never"
+        );
+    }
+
+    #[test]
+    fn mainnet_revisions() {
+        for (block_number, revision) in [
+            (0, Revision::Frontier),
+            (1_149_999, Revision::Frontier),
+            (1_150_000, Revision::Homestead),
+            (2_462_999, Revision::Homestead),
+            (2_463_000, Revision::Tangerine),
+            (2_674_999, Revision::Tangerine),
+            (2_675_000, Revision::Spurious),
+            (4_369_999, Revision::Spurious),
+            (4_370_000, Revision::Byzantium),
+            (7_279_999, Revision::Byzantium),
+            (7_280_000, Revision::Petersburg),
+            (9_068_999, Revision::Petersburg),
+            (9_069_000, Revision::Istanbul),
+            (12_243_999, Revision::Istanbul),
+            (12_244_000, Revision::Berlin),
+            (12_964_999, Revision::Berlin),
+            (12_965_000, Revision::London),
+            (u64::MAX, Revision::London),
+        ] {
+            assert_eq!(
+                MAINNET.revision_at(block_number),
+                revision,
+                "{}",
+                block_number
+            );
+            assert_eq!(
+                MAINNET.revision_at_timestamp(block_number, u64::MAX),
+                revision,
+                "{}",
+                block_number
+            );
+        }
+    }
+
+    #[test]
+    fn active_transitions() {
+        assert_eq!(
+            MAINNET
+                .collect_block_spec(BlockNumber(7_280_000), 0)
+                .active_transitions,
+            hashset! { Revision::Constantinople, Revision::Petersburg }
+        );
+        assert_eq!(
+            MAINNET
+                .collect_block_spec(BlockNumber(12_965_000), 0)
+                .active_transitions,
+            hashset! { Revision::London }
+        );
+        assert!(MAINNET
+            .collect_block_spec(BlockNumber(12_965_001), 0)
+            .active_transitions
+            .is_empty());
+    }
+
+    #[test]
+    fn sparse_and_timestamp_upgrades() {
+        let mut spec = MAINNET.clone();
+        spec.upgrades = Upgrades {
+            berlin: Some(10.into()),
+            shanghai: Some(1_000),
+            ..Default::default()
+        };
+
+        assert_eq!(spec.revision_at(BlockNumber(9)), Revision::Frontier);
+        assert_eq!(spec.revision_at(BlockNumber(10)), Revision::Berlin);
+        assert_eq!(
+            spec.revision_at_timestamp(BlockNumber(9), 999),
+            Revision::Frontier
+        );
+        assert_eq!(
+            spec.revision_at_timestamp(BlockNumber(9), 1_000),
+            Revision::Shanghai
+        );
+        assert_eq!(
+            spec.revision_at_timestamp(BlockNumber(10), 999),
+            Revision::Berlin
+        );
+        assert_eq!(
+            spec.revision_at_timestamp(BlockNumber(10), 1_000),
+            Revision::Shanghai
+        );
+        assert_eq!(spec.revision_at(BlockNumber(u64::MAX)), Revision::Berlin);
+        assert_eq!(
+            spec.collect_block_spec(BlockNumber(11), 1_000).revision,
+            Revision::Shanghai
+        );
+    }
+
     #[test]
     fn distinct_block_numbers() {
         assert_eq!(
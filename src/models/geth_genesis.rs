@@ -0,0 +1,466 @@
+use crate::{models::*, util::*};
+use anyhow::{bail, format_err};
+use bytes::Bytes;
+use serde::*;
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+const MAX_ETHASH_EXTRA_DATA_SIZE: usize = 32;
+const MAX_CLIQUE_EXTRA_DATA_SIZE: usize = 65535;
+const CLIQUE_VANITY_LEN: usize = 32;
+const CLIQUE_SEAL_LEN: usize = 65;
+
+/// Ethash or Clique chain configuration of a `genesis.json` file.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GethChainConfig {
+    pub chain_id: ChainId,
+    pub homestead_block: Option<BlockNumber>,
+    pub eip150_block: Option<BlockNumber>,
+    pub eip155_block: Option<BlockNumber>,
+    pub eip158_block: Option<BlockNumber>,
+    pub byzantium_block: Option<BlockNumber>,
+    pub constantinople_block: Option<BlockNumber>,
+    pub petersburg_block: Option<BlockNumber>,
+    pub istanbul_block: Option<BlockNumber>,
+    pub muir_glacier_block: Option<BlockNumber>,
+    pub berlin_block: Option<BlockNumber>,
+    pub london_block: Option<BlockNumber>,
+    pub arrow_glacier_block: Option<BlockNumber>,
+    pub gray_glacier_block: Option<BlockNumber>,
+    pub shanghai_time: Option<u64>,
+    pub clique: Option<GethCliqueConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct GethCliqueConfig {
+    /// Seconds between blocks.
+    pub period: u64,
+    pub epoch: u64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct GethGenesisAccount {
+    #[serde(deserialize_with = "deserialize_u256")]
+    pub balance: U256,
+    #[serde(default, deserialize_with = "deserialize_hexstr_as_u64")]
+    pub nonce: u64,
+    #[serde(default, with = "hexbytes")]
+    pub code: Bytes,
+    #[serde(default)]
+    pub storage: HashMap<H256, H256>,
+}
+
+/// Genesis of a chain, as read by `geth init`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GethGenesis {
+    pub config: GethChainConfig,
+    #[serde(default, deserialize_with = "deserialize_hexstr_as_u64")]
+    pub nonce: u64,
+    #[serde(default, deserialize_with = "deserialize_hexstr_as_u64")]
+    pub timestamp: u64,
+    #[serde(default, with = "hexbytes")]
+    pub extra_data: Bytes,
+    #[serde(deserialize_with = "deserialize_hexstr_as_u64")]
+    pub gas_limit: u64,
+    #[serde(deserialize_with = "deserialize_u256")]
+    pub difficulty: U256,
+    #[serde(default)]
+    pub mix_hash: H256,
+    #[serde(default)]
+    pub coinbase: Address,
+    #[serde(default, deserialize_with = "deserialize_alloc")]
+    pub alloc: BTreeMap<Address, GethGenesisAccount>,
+}
+
+impl GethGenesis {
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Chain spec of the chain named `name` starting from this genesis.
+    pub fn into_chain_spec(self, name: impl Into<String>) -> anyhow::Result<ChainSpec> {
+        let config = &self.config;
+        if config.eip155_block != config.eip158_block {
+            bail!("EIP-155 and EIP-158 activated at different blocks are not supported");
+        }
+
+        let upgrades = Upgrades {
+            homestead: config.homestead_block,
+            tangerine: config.eip150_block,
+            spurious: config.eip158_block,
+            byzantium: config.byzantium_block,
+            constantinople: config.constantinople_block,
+            petersburg: config.petersburg_block,
+            istanbul: config.istanbul_block,
+            berlin: config.berlin_block,
+            london: config.london_block,
+            shanghai: config.shanghai_time,
+        };
+
+        let (seal_verification, seal, maximum_extra_data_size, allowed_future_drift) =
+            if let Some(clique) = &config.clique {
+                (
+                    SealVerificationParams::Clique {
+                        // Same value as the period of a chain spec file.
+                        period: Duration::from_millis(clique.period),
+                        epoch: clique.epoch,
+                    },
+                    self.clique_seal()?,
+                    MAX_CLIQUE_EXTRA_DATA_SIZE,
+                    0,
+                )
+            } else {
+                if self.extra_data.len() > MAX_ETHASH_EXTRA_DATA_SIZE {
+                    bail!(
+                        "extra data longer than {} bytes",
+                        MAX_ETHASH_EXTRA_DATA_SIZE
+                    );
+                }
+                (
+                    self.ethash_params(),
+                    Seal::Ethash {
+                        vanity: self.extra_data.clone(),
+                        difficulty: self.difficulty,
+                        nonce: H64::from_low_u64_be(self.nonce),
+                        mix_hash: self.mix_hash,
+                    },
+                    MAX_ETHASH_EXTRA_DATA_SIZE,
+                    15,
+                )
+            };
+
+        let mut balances = HashMap::new();
+        let mut accounts = BTreeMap::new();
+        for (address, account) in self.alloc {
+            let balance = account.balance;
+            if balance != 0 {
+                balances.insert(address, balance);
+            }
+
+            let genesis_account = GenesisAccount {
+                nonce: account.nonce,
+                code: account.code,
+                storage: account
+                    .storage
+                    .into_iter()
+                    .map(|(location, value)| (h256_to_u256(location), h256_to_u256(value)))
+                    .filter(|&(_, value)| value != 0)
+                    .collect(),
+            };
+            // Empty accounts are allocated too, they exist in the genesis state like the others.
+            if genesis_account != GenesisAccount::default() || balance == 0 {
+                accounts.insert(address, genesis_account);
+            }
+        }
+
+        Ok(ChainSpec {
+            name: name.into(),
+            consensus: ConsensusParams {
+                seal_verification,
+                eip1559_block: config.london_block,
+                allowed_future_drift,
+            },
+            upgrades,
+            params: Params {
+                chain_id: config.chain_id,
+                network_id: NetworkId(config.chain_id.0),
+                min_gas_limit: 5000,
+                gas_limit_bound_divisor: 1024,
+                maximum_extra_data_size,
+            },
+            genesis: Genesis {
+                number: BlockNumber(0),
+                author: self.coinbase,
+                gas_limit: self.gas_limit,
+                timestamp: self.timestamp,
+                seal,
+                accounts,
+            },
+            contracts: Default::default(),
+            balances: if balances.is_empty() {
+                BTreeMap::new()
+            } else {
+                [(BlockNumber(0), balances)].into()
+            },
+            p2p: P2PParams {
+                bootnodes: vec![],
+                preverified_hashes: vec![],
+            },
+        })
+    }
+
+    fn clique_seal(&self) -> anyhow::Result<Seal> {
+        let extra_data = &self.extra_data;
+        let signers_len = extra_data
+            .len()
+            .checked_sub(CLIQUE_VANITY_LEN + CLIQUE_SEAL_LEN)
+            .filter(|len| len % Address::len_bytes() == 0)
+            .ok_or_else(|| format_err!("extra data is not a Clique vanity, signers and seal"))?;
+
+        let score = if self.difficulty == 1 {
+            BlockScore::NoTurn
+        } else if self.difficulty == 2 {
+            BlockScore::InTurn
+        } else {
+            bail!(
+                "Clique genesis difficulty {} is not 1 or 2",
+                self.difficulty
+            );
+        };
+
+        Ok(Seal::Clique {
+            vanity: H256::from_slice(&extra_data[..CLIQUE_VANITY_LEN]),
+            score,
+            signers: extra_data[CLIQUE_VANITY_LEN..CLIQUE_VANITY_LEN + signers_len]
+                .chunks(Address::len_bytes())
+                .map(Address::from_slice)
+                .collect(),
+        })
+    }
+
+    fn ethash_params(&self) -> SealVerificationParams {
+        let config = &self.config;
+        let ether = |n: u64| n.as_u256() * 1_000_000_000_000_000_000_u64.as_u256();
+
+        let mut block_reward = BTreeMap::from([(BlockNumber(0), ether(5))]);
+        if let Some(byzantium) = config.byzantium_block {
+            block_reward.insert(byzantium, ether(3));
+        }
+        if let Some(constantinople) = config.constantinople_block {
+            block_reward.insert(constantinople, ether(2));
+        }
+
+        let delays = [
+            (config.byzantium_block, 3_000_000),
+            (config.constantinople_block, 5_000_000),
+            (config.muir_glacier_block, 9_000_000),
+            (config.london_block, 9_700_000),
+            (config.arrow_glacier_block, 10_700_000),
+            (config.gray_glacier_block, 11_400_000),
+        ]
+        .into_iter()
+        .filter_map(|(fork, delay)| fork.map(|fork| (fork, BlockNumber(delay))))
+        .collect::<BTreeMap<_, _>>();
+
+        SealVerificationParams::Ethash {
+            duration_limit: 13,
+            block_reward,
+            homestead_formula: config.homestead_block,
+            byzantium_formula: config.byzantium_block,
+            difficulty_bomb: (!delays.is_empty()).then(|| DifficultyBomb { delays }),
+            skip_pow_verification: false,
+        }
+    }
+}
+
+/// Hex with `0x` prefix or decimal string.
+fn deserialize_u256<'de, D>(deserializer: D) -> Result<U256, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+
+    if let Some(stripped) = s.strip_prefix("0x") {
+        U256::from_str_radix(stripped, 16)
+    } else {
+        U256::from_str_radix(&s, 10)
+    }
+    .map_err(|e| de::Error::custom(format!("{}/{}", e, s)))
+}
+
+/// Addresses of `alloc` may lack the `0x` prefix.
+fn deserialize_alloc<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<Address, GethGenesisAccount>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    HashMap::<String, GethGenesisAccount>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(address, account)| {
+            let address = address
+                .strip_prefix("0x")
+                .unwrap_or(&address)
+                .parse::<Address>()
+                .map_err(|e| de::Error::custom(format!("{}/{}", e, address)))?;
+            Ok((address, account))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{crypto::keccak256, state::genesis::GenesisState, State};
+    use hex_literal::hex;
+
+    #[test]
+    fn import_clique_genesis() {
+        let chain_spec = GethGenesis::from_json(
+            r#"{
+                "config": {
+                    "chainId": 1337,
+                    "homesteadBlock": 0,
+                    "eip150Block": 0,
+                    "eip155Block": 0,
+                    "eip158Block": 0,
+                    "byzantiumBlock": 0,
+                    "constantinopleBlock": 0,
+                    "petersburgBlock": 0,
+                    "istanbulBlock": 0,
+                    "berlinBlock": 0,
+                    "londonBlock": 0,
+                    "clique": { "period": 5, "epoch": 30000 }
+                },
+                "difficulty": "1",
+                "gasLimit": "0x1c9c380",
+                "timestamp": "0x62a0b6a0",
+                "extraData": "0x000000000000000000000000000000000000000000000000000000000000000042eb768f2244c8811c63729a21a3569731535f060000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+                "alloc": {
+                    "42eb768f2244c8811c63729a21a3569731535f06": {
+                        "balance": "1000000000000000000000"
+                    },
+                    "0x00000000000000000000000000000000000000aa": {
+                        "balance": "0x0",
+                        "nonce": "0x1",
+                        "code": "0x6000",
+                        "storage": {
+                            "0x0000000000000000000000000000000000000000000000000000000000000001": "0x0000000000000000000000000000000000000000000000000000000000000002"
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap()
+        .into_chain_spec("devnet")
+        .unwrap();
+
+        let signer = Address::from(hex!("42eb768f2244c8811c63729a21a3569731535f06"));
+        let contract = Address::from_low_u64_be(0xaa);
+        assert_eq!(chain_spec.params.chain_id, ChainId(1337));
+        assert_eq!(chain_spec.revision_at(0), Revision::London);
+        assert_eq!(
+            chain_spec.genesis.seal,
+            Seal::Clique {
+                vanity: H256::zero(),
+                score: BlockScore::NoTurn,
+                signers: vec![signer],
+            }
+        );
+        assert_eq!(
+            chain_spec.balances[&BlockNumber(0)],
+            [(
+                signer,
+                1000.as_u256() * 1_000_000_000_000_000_000_u64.as_u256()
+            )]
+            .into()
+        );
+        assert_eq!(
+            chain_spec.genesis.accounts,
+            [(
+                contract,
+                GenesisAccount {
+                    nonce: 1,
+                    code: Bytes::from_static(&hex!("6000")),
+                    storage: [(1.as_u256(), 2.as_u256())].into(),
+                }
+            )]
+            .into()
+        );
+
+        let genesis = GenesisState::new(chain_spec);
+        let state = genesis.initial_state();
+        let account = state.read_account(contract).unwrap().unwrap();
+        assert_eq!(account.nonce, 1);
+        assert_eq!(account.code_hash, keccak256(hex!("6000")));
+        assert_eq!(
+            state.read_storage(contract, 1.as_u256()).unwrap(),
+            2.as_u256()
+        );
+
+        let header = genesis.header(&state);
+        assert_eq!(header.extra_data.len(), 32 + 20 + 65);
+        assert_eq!(header.base_fee_per_gas, Some(1_000_000_000.as_u256()));
+    }
+
+    #[test]
+    fn rejects_malformed_clique_extra_data() {
+        let mut genesis = GethGenesis {
+            config: GethChainConfig {
+                chain_id: ChainId(1337),
+                clique: Some(GethCliqueConfig {
+                    period: 15,
+                    epoch: 30000,
+                }),
+                ..Default::default()
+            },
+            nonce: 0,
+            timestamp: 0,
+            extra_data: vec![0; 32 + 19 + 65].into(),
+            gas_limit: 5000,
+            difficulty: 1.as_u256(),
+            mix_hash: H256::zero(),
+            coinbase: Address::zero(),
+            alloc: Default::default(),
+        };
+        assert!(genesis.clone().into_chain_spec("devnet").is_err());
+
+        genesis.extra_data = vec![0; 32 + 20 + 65].into();
+        assert!(genesis.into_chain_spec("devnet").is_ok());
+    }
+
+    #[test]
+    fn keeps_empty_accounts() {
+        let empty = Address::from_low_u64_be(0xbb);
+        let genesis = GethGenesis {
+            config: GethChainConfig {
+                chain_id: ChainId(1337),
+                ..Default::default()
+            },
+            nonce: 0,
+            timestamp: 0,
+            extra_data: Bytes::new(),
+            gas_limit: 5000,
+            difficulty: 1.as_u256(),
+            mix_hash: H256::zero(),
+            coinbase: Address::zero(),
+            alloc: [(empty, GethGenesisAccount::default())].into(),
+        };
+        let chain_spec = genesis.into_chain_spec("devnet").unwrap();
+        assert_eq!(
+            chain_spec.genesis.accounts,
+            [(empty, GenesisAccount::default())].into()
+        );
+
+        let state = GenesisState::new(chain_spec).initial_state();
+        assert_eq!(state.read_account(empty).unwrap(), Some(Account::default()));
+    }
+
+    #[test]
+    fn golden_genesis_hashes() {
+        for (name, json, hash) in [
+            (
+                "goerli",
+                include_str!("../res/genesis/goerli.json"),
+                hex!("bf7e331f7f7c1dd2e05159666b3bf8bc7a8a3a9eb1d518969eab529dd9b88c1a"),
+            ),
+            (
+                "rinkeby",
+                include_str!("../res/genesis/rinkeby.json"),
+                hex!("6341fd3daf94b748c72ced5a5b26028f2474f5f00d824504e4fa37a75767e177"),
+            ),
+        ] {
+            let chain_spec = GethGenesis::from_json(json)
+                .unwrap()
+                .into_chain_spec(name)
+                .unwrap();
+            let genesis = GenesisState::new(chain_spec);
+            let state = genesis.initial_state();
+            assert_eq!(genesis.header(&state).hash(), H256(hash), "{}", name);
+        }
+    }
+}
@@ -2,6 +2,7 @@ mod account;
 mod block;
 mod bloom;
 mod chainspec;
+mod geth_genesis;
 mod header;
 mod log;
 mod receipt;
@@ -9,8 +10,8 @@ mod revision;
 mod transaction;
 
 pub use self::{
-    account::*, block::*, bloom::*, chainspec::*, header::*, log::*, receipt::*, revision::*,
-    transaction::*,
+    account::*, block::*, bloom::*, chainspec::*, geth_genesis::*, header::*, log::*, receipt::*,
+    revision::*, transaction::*,
 };
 
 use derive_more::*;
@@ -1,5 +1,5 @@
 use crate::{
-    crypto::{is_valid_signature, TrieEncode},
+    crypto::{is_valid_signature, recover_signer, recover_signers, TrieEncode},
     models::*,
     util::*,
 };
@@ -9,10 +9,6 @@ use educe::Educe;
 use hex_literal::hex;
 use parity_scale_codec::{Compact, Decode, Encode, EncodeAsRef, EncodeLike, Input};
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
-use secp256k1::{
-    ecdsa::{RecoverableSignature, RecoveryId},
-    Message as SecpMessage, SECP256K1,
-};
 use serde::*;
 use sha3::*;
 use std::{borrow::Cow, cmp::min};
@@ -777,7 +773,7 @@ impl Message {
         )
     }
 
-    pub(crate) fn effective_gas_price(&self, base_fee_per_gas: U256) -> U256 {
+    pub fn effective_gas_price(&self, base_fee_per_gas: U256) -> U256 {
         self.priority_fee_per_gas(base_fee_per_gas) + base_fee_per_gas
     }
 }
@@ -799,21 +795,39 @@ impl MessageWithSignature {
         self.signature.s
     }
 
-    pub fn recover_sender(&self) -> anyhow::Result<Address> {
+    fn compact_signature(&self) -> [u8; 64] {
         let mut sig = [0u8; 64];
 
         sig[..32].copy_from_slice(self.r().as_bytes());
         sig[32..].copy_from_slice(self.s().as_bytes());
 
-        let rec = RecoveryId::from_i32(self.v() as i32)?;
+        sig
+    }
+
+    pub fn recover_sender(&self) -> anyhow::Result<Address> {
+        Ok(recover_signer(
+            self.message.hash(),
+            &self.compact_signature(),
+            self.signature.odd_y_parity,
+        )?)
+    }
 
-        let public = &SECP256K1.recover_ecdsa(
-            &SecpMessage::from_slice(self.message.hash().as_bytes())?,
-            &RecoverableSignature::from_compact(&sig, rec)?,
-        )?;
+    /// Recovers senders of all transactions, in parallel.
+    pub fn recover_senders(txs: &[Self]) -> anyhow::Result<Vec<Address>> {
+        let signatures = txs
+            .iter()
+            .map(|tx| {
+                (
+                    tx.message.hash(),
+                    tx.compact_signature(),
+                    tx.signature.odd_y_parity,
+                )
+            })
+            .collect::<Vec<_>>();
 
-        let address_slice = &Keccak256::digest(&public.serialize_uncompressed()[1..])[12..];
-        Ok(Address::from_slice(address_slice))
+        Ok(recover_signers(&signatures)
+            .into_iter()
+            .collect::<Result<_, _>>()?)
     }
 }
 
@@ -2,9 +2,12 @@ pub mod block_id;
 pub mod chain_config;
 mod message_decoder;
 pub mod messages;
+pub mod node_record;
+pub mod peer_policy;
 pub mod sentry_address;
 pub mod sentry_client;
 pub mod sentry_client_connector;
 pub mod sentry_client_impl;
 pub mod sentry_client_mock;
 pub mod sentry_client_reactor;
+pub mod sentry_client_simulated;
@@ -0,0 +1,361 @@
+//! Persistent devp2p identity of the node: secp256k1 node key and its signed Ethereum Node
+//! Record (ENR, [EIP-778]) advertising the `eth` fork id ([EIP-2124]).
+//!
+//! [EIP-778]: https://eips.ethereum.org/EIPS/eip-778
+//! [EIP-2124]: https://eips.ethereum.org/EIPS/eip-2124
+
+use super::{chain_config::ChainConfig, sentry_client::NodeInfo};
+use crate::{crypto::keccak256, models::*};
+use anyhow::{ensure, Context};
+use ethereum_forkid::{ForkFilter, ForkId};
+use rlp::RlpStream;
+use secp256k1::{Message, PublicKey, SecretKey, SECP256K1};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    path::{Path, PathBuf},
+};
+
+/// Node key file, hex encoded as in Geth.
+const KEY_FILE: &str = "key";
+/// Last announced identity of the node.
+const LOCAL_NODE_FILE: &str = "node.json";
+
+/// Encoded records larger than that are invalid.
+pub const MAX_RECORD_SIZE: usize = 300;
+
+/// Secret key identifying the node in devp2p.
+#[derive(Clone, Copy)]
+pub struct NodeKey(SecretKey);
+
+impl std::fmt::Debug for NodeKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("NodeKey").field(&self.id()).finish()
+    }
+}
+
+impl NodeKey {
+    pub fn new(secret_key: SecretKey) -> Self {
+        Self(secret_key)
+    }
+
+    /// File holding the key stored in `dir`, which the sentry takes as `--nodekey`.
+    pub fn file(dir: &Path) -> PathBuf {
+        dir.join(KEY_FILE)
+    }
+
+    /// Reads the key stored in `dir`, if any.
+    pub fn load(dir: &Path) -> anyhow::Result<Option<Self>> {
+        let path = Self::file(dir);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let s = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read node key {}", path.display()))?;
+        let secret_key = SecretKey::from_slice(&hex::decode(s.trim())?)
+            .with_context(|| format!("invalid node key in {}", path.display()))?;
+
+        Ok(Some(Self(secret_key)))
+    }
+
+    /// Reads the key stored in `dir`, generating and persisting a new one on first start, so
+    /// that peers see the same identity across restarts.
+    pub fn load_or_generate(dir: &Path) -> anyhow::Result<Self> {
+        if let Some(key) = Self::load(dir)? {
+            return Ok(key);
+        }
+
+        let key = Self(crate::crypto::generate_key());
+
+        let path = Self::file(dir);
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options
+            .open(&path)
+            .with_context(|| format!("failed to create node key {}", path.display()))?;
+        file.write_all(hex::encode(key.0.secret_bytes()).as_bytes())?;
+        file.sync_all()?;
+
+        Ok(key)
+    }
+
+    pub fn secret_key(&self) -> &SecretKey {
+        &self.0
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey::from_secret_key(SECP256K1, &self.0)
+    }
+
+    /// Node id: uncompressed public key without the prefix byte.
+    pub fn id(&self) -> H512 {
+        H512::from_slice(&self.public_key().serialize_uncompressed()[1..])
+    }
+
+    pub fn enode(&self, addr: SocketAddr) -> String {
+        format!("enode://{}@{}", hex::encode(self.id()), addr)
+    }
+
+    /// Whether `enode` is an URL of the node with this key.
+    pub fn is_enode_of(&self, enode: &str) -> bool {
+        enode
+            .strip_prefix("enode://")
+            .and_then(|s| s.split('@').next())
+            .and_then(|id| hex::decode(id).ok())
+            .map_or(false, |id| id == self.id().as_bytes())
+    }
+}
+
+/// `eth` fork id of the chain with the given head, as advertised in status and node records.
+pub fn fork_id(chain_config: &ChainConfig, head: BlockNumber) -> ForkId {
+    ForkFilter::new(
+        head.0,
+        chain_config.genesis_block_hash(),
+        chain_config.fork_block_numbers().into_iter().map(|n| n.0),
+    )
+    .current()
+}
+
+/// Contents of a node record using the "v4" identity scheme.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodeRecord {
+    pub seq: u64,
+    pub ip: Option<Ipv4Addr>,
+    pub tcp: Option<u16>,
+    pub udp: Option<u16>,
+    pub fork_id: Option<ForkId>,
+}
+
+impl NodeRecord {
+    /// Appends sequence number and key/value pairs sorted by key.
+    fn append_content(&self, s: &mut RlpStream, key: &NodeKey) {
+        s.append(&self.seq);
+        if let Some(fork_id) = &self.fork_id {
+            s.append(&b"eth".as_ref());
+            s.begin_list(1);
+            s.append(fork_id);
+        }
+        s.append(&b"id".as_ref());
+        s.append(&b"v4".as_ref());
+        if let Some(ip) = self.ip {
+            s.append(&b"ip".as_ref());
+            s.append(&ip.octets().as_ref());
+        }
+        s.append(&b"secp256k1".as_ref());
+        s.append(&key.public_key().serialize().as_ref());
+        if let Some(tcp) = self.tcp {
+            s.append(&b"tcp".as_ref());
+            s.append(&tcp);
+        }
+        if let Some(udp) = self.udp {
+            s.append(&b"udp".as_ref());
+            s.append(&udp);
+        }
+    }
+
+    fn content_len(&self) -> usize {
+        1 + 2
+            * (2 + self.fork_id.is_some() as usize
+                + self.ip.is_some() as usize
+                + self.tcp.is_some() as usize
+                + self.udp.is_some() as usize)
+    }
+
+    /// RLP of the record signed with `key`.
+    pub fn encode(&self, key: &NodeKey) -> anyhow::Result<Vec<u8>> {
+        let mut content = RlpStream::new_list(self.content_len());
+        self.append_content(&mut content, key);
+        let hash = keccak256(content.out());
+        let signature = SECP256K1
+            .sign_ecdsa(&Message::from_slice(hash.as_bytes())?, key.secret_key())
+            .serialize_compact();
+
+        let mut s = RlpStream::new_list(self.content_len() + 1);
+        s.append(&signature.as_ref());
+        self.append_content(&mut s, key);
+        let out = s.out().to_vec();
+        ensure!(
+            out.len() <= MAX_RECORD_SIZE,
+            "node record is {} bytes, over the limit of {}",
+            out.len(),
+            MAX_RECORD_SIZE
+        );
+
+        Ok(out)
+    }
+
+    /// Text form of the record signed with `key`, `enr:` followed by URL-safe base64.
+    pub fn to_text(&self, key: &NodeKey) -> anyhow::Result<String> {
+        Ok(format!(
+            "enr:{}",
+            base64::encode_config(self.encode(key)?, base64::URL_SAFE_NO_PAD)
+        ))
+    }
+}
+
+/// Identity the node announces, persisted next to the node key so that the record sequence
+/// number only grows, and other processes can report it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocalNode {
+    pub id: H512,
+    pub seq: u64,
+    pub enode: String,
+    pub enr: String,
+    pub ip: Ipv4Addr,
+    pub tcp_port: u16,
+    pub udp_port: u16,
+}
+
+impl LocalNode {
+    /// Reads the identity last announced by the node owning `dir`.
+    pub fn load(dir: &Path) -> anyhow::Result<Option<Self>> {
+        let path = dir.join(LOCAL_NODE_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            serde_json::from_slice(&std::fs::read(&path)?)
+                .with_context(|| format!("malformed node identity in {}", path.display()))?,
+        ))
+    }
+
+    /// Signs a record for the current address and fork id. The sequence number of the stored
+    /// identity is bumped if anything changed since it was announced.
+    pub fn update(
+        dir: &Path,
+        key: &NodeKey,
+        ip: Ipv4Addr,
+        port: u16,
+        fork_id: ForkId,
+    ) -> anyhow::Result<Self> {
+        let previous = Self::load(dir)?.filter(|node| node.id == key.id());
+
+        let mut record = NodeRecord {
+            seq: previous.as_ref().map(|node| node.seq).unwrap_or(1),
+            ip: Some(ip),
+            tcp: Some(port),
+            udp: Some(port),
+            fork_id: Some(fork_id),
+        };
+        let mut enr = record.to_text(key)?;
+        if let Some(previous) = previous {
+            if previous.enr == enr {
+                return Ok(previous);
+            }
+
+            record.seq += 1;
+            enr = record.to_text(key)?;
+        }
+
+        let node = Self {
+            id: key.id(),
+            seq: record.seq,
+            enode: key.enode(SocketAddrV4::new(ip, port).into()),
+            enr,
+            ip,
+            tcp_port: port,
+            udp_port: port,
+        };
+        std::fs::write(dir.join(LOCAL_NODE_FILE), serde_json::to_vec_pretty(&node)?)?;
+
+        Ok(node)
+    }
+}
+
+impl From<LocalNode> for NodeInfo {
+    fn from(node: LocalNode) -> Self {
+        Self {
+            id: hex::encode(node.id),
+            name: crate::version_string(),
+            enode: node.enode,
+            enr: node.enr,
+            discovery_port: node.udp_port.into(),
+            listener_port: node.tcp_port.into(),
+            listener_addr: SocketAddrV4::new(node.ip, node.tcp_port).to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sentry::chain_config::ChainsConfig;
+    use ethereum_forkid::ForkHash;
+    use hex_literal::hex;
+
+    #[test]
+    fn eip778_example() {
+        let key = NodeKey::new(
+            SecretKey::from_slice(&hex!(
+                "b71c71a67e1177ad4e901695e1b4b9ee17ae16c6668d313eac2f96dbcda3f291"
+            ))
+            .unwrap(),
+        );
+        let record = NodeRecord {
+            seq: 1,
+            ip: Some(Ipv4Addr::LOCALHOST),
+            tcp: None,
+            udp: Some(30303),
+            fork_id: None,
+        };
+
+        assert_eq!(
+            record.to_text(&key).unwrap(),
+            "enr:-IS4QHCYrYZbAKWCBRlAy5zzaDZXJBGkcnh4MHcBFZntXNFrdvJjX04jRzjzCBOonrkTfj499SZuOh8R33Ls8RRcy5wBgmlkgnY0gmlwhH8AAAGJc2VjcDI1NmsxoQPKY0yuDUmstAHYpMa2_oxVtw0RW_QAdpzBQA8yWM0xOIN1ZHCCdl8"
+        );
+        assert_eq!(
+            key.id(),
+            H512(hex!("ca634cae0d49acb401d8a4c6b6fe8c55b70d115bf400769cc1400f3258cd31387574077f301b421bc84df7266c44e9e6d569fc56be00812904767bf5ccd1fc7f"))
+        );
+    }
+
+    #[test]
+    fn mainnet_fork_id() {
+        let mainnet = ChainsConfig::new().unwrap().get("mainnet").unwrap();
+
+        for (head, hash, next) in [
+            (0, hex!("fc64ec04"), 1_150_000),
+            (1_150_000, hex!("97c2c34c"), 1_920_000),
+        ] {
+            assert_eq!(
+                fork_id(&mainnet, BlockNumber(head)),
+                ForkId {
+                    hash: ForkHash(hash),
+                    next
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn local_node_seq() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = NodeKey::load_or_generate(dir.path()).unwrap();
+        assert_eq!(NodeKey::load(dir.path()).unwrap().unwrap().id(), key.id());
+
+        let fork_id = ForkId {
+            hash: ForkHash(hex!("fc64ec04")),
+            next: 1_150_000,
+        };
+        let node =
+            LocalNode::update(dir.path(), &key, Ipv4Addr::LOCALHOST, 30303, fork_id).unwrap();
+        assert_eq!(node.seq, 1);
+        assert!(key.is_enode_of(&node.enode));
+        assert!(!NodeKey::new(crate::crypto::generate_key()).is_enode_of(&node.enode));
+        assert_eq!(
+            LocalNode::update(dir.path(), &key, Ipv4Addr::LOCALHOST, 30303, fork_id).unwrap(),
+            node
+        );
+
+        let node =
+            LocalNode::update(dir.path(), &key, Ipv4Addr::LOCALHOST, 30304, fork_id).unwrap();
+        assert_eq!(node.seq, 2);
+        assert_eq!(LocalNode::load(dir.path()).unwrap(), Some(node));
+    }
+}
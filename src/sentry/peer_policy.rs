@@ -0,0 +1,342 @@
+//! Peer set management on top of the sentry: static peers are kept connected, connection
+//! limits and network restrictions are enforced by kicking peers, with trusted and static
+//! peers exempt from both.
+
+use super::sentry_client::{PeerId, PeerInfo, SentryClient};
+use crate::crypto::keccak256;
+use anyhow::{bail, format_err};
+use clap::Parser;
+use ethereum_types::H512;
+use std::{
+    collections::HashSet,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    time::Duration,
+};
+use tracing::*;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Parser, Clone, Debug, Default)]
+pub struct PeerPolicyOpts {
+    #[clap(
+        long = "p2p.staticpeers",
+        help = "Comma separated enode URLs of peers to always stay connected to.",
+        use_delimiter = true
+    )]
+    pub static_peers: Vec<Enode>,
+    #[clap(
+        long = "p2p.trustedpeers",
+        help = "Comma separated enode URLs of peers exempt from the connection limits and network restrictions.",
+        use_delimiter = true
+    )]
+    pub trusted_peers: Vec<Enode>,
+    #[clap(
+        long = "p2p.max-inbound",
+        help = "Maximum number of peers which connected to us."
+    )]
+    pub max_inbound: Option<usize>,
+    #[clap(
+        long = "p2p.max-outbound",
+        help = "Maximum number of peers we connected to."
+    )]
+    pub max_outbound: Option<usize>,
+    #[clap(
+        long = "p2p.netrestrict",
+        help = "Comma separated CIDR masks of networks peers may connect from, any if empty.",
+        use_delimiter = true
+    )]
+    pub netrestrict: Vec<Cidr>,
+}
+
+impl PeerPolicyOpts {
+    /// Whether there is anything to enforce.
+    pub fn is_empty(&self) -> bool {
+        self.static_peers.is_empty()
+            && self.max_inbound.is_none()
+            && self.max_outbound.is_none()
+            && self.netrestrict.is_empty()
+    }
+}
+
+/// Node address in `enode://<node id>@<ip>:<port>` form.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Enode {
+    pub pubkey: H512,
+    pub addr: SocketAddr,
+    url: String,
+}
+
+impl Enode {
+    /// Peer id used by the sentry: hash of the public key.
+    pub fn peer_id(&self) -> PeerId {
+        keccak256(self.pubkey)
+    }
+}
+
+impl FromStr for Enode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s
+            .strip_prefix("enode://")
+            .ok_or_else(|| format_err!("enode URL {} must start with enode://", s))?;
+        let (pubkey, addr) = rest
+            .split_once('@')
+            .ok_or_else(|| format_err!("enode URL {} has no address", s))?;
+        let pubkey = hex::decode(pubkey)?;
+        if pubkey.len() != H512::len_bytes() {
+            bail!("enode URL {} has malformed node id", s);
+        }
+        // Discovery port is not needed to connect.
+        let addr = addr.split('?').next().unwrap_or_default().parse()?;
+
+        Ok(Self {
+            pubkey: H512::from_slice(&pubkey),
+            addr,
+            url: s.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for Enode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.url)
+    }
+}
+
+/// Network in CIDR notation, e.g. `10.0.0.0/8`. Single address without mask is allowed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cidr {
+    pub addr: IpAddr,
+    pub prefix: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        fn masked(v: u128, bits: u8, prefix: u8) -> u128 {
+            if prefix == 0 {
+                0
+            } else {
+                v >> (bits - prefix)
+            }
+        }
+
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                masked(u32::from(net).into(), 32, self.prefix)
+                    == masked(u32::from(ip).into(), 32, self.prefix)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                masked(net.into(), 128, self.prefix) == masked(ip.into(), 128, self.prefix)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = s.split_once('/').unwrap_or((s, ""));
+        let addr = IpAddr::from_str(addr)?;
+        let bits = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = if prefix.is_empty() {
+            bits
+        } else {
+            prefix.parse()?
+        };
+        if prefix > bits {
+            bail!("CIDR mask {} is longer than the address", s);
+        }
+
+        Ok(Self { addr, prefix })
+    }
+}
+
+/// Changes to the peer set needed to comply with the policy.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PeerActions {
+    /// Static peers to connect to.
+    pub connect: Vec<Enode>,
+    /// Peers to disconnect.
+    pub kick: Vec<PeerId>,
+}
+
+#[derive(Debug)]
+pub struct PeerPolicy {
+    opts: PeerPolicyOpts,
+    exempt: HashSet<PeerId>,
+}
+
+impl PeerPolicy {
+    pub fn new(opts: PeerPolicyOpts) -> Self {
+        let exempt = opts
+            .static_peers
+            .iter()
+            .chain(&opts.trusted_peers)
+            .map(Enode::peer_id)
+            .collect();
+
+        Self { opts, exempt }
+    }
+
+    pub fn actions(&self, peers: &[PeerInfo]) -> PeerActions {
+        let mut actions = PeerActions::default();
+        let mut connected = HashSet::new();
+        let mut inbound = 0;
+        let mut outbound = 0;
+
+        for peer in peers {
+            let Ok(peer_id) = PeerId::from_str(&peer.id) else {
+                debug!("Skipping peer with malformed id {}", peer.id);
+                continue;
+            };
+            connected.insert(peer_id);
+
+            if self.exempt.contains(&peer_id) {
+                continue;
+            }
+
+            if !self.opts.netrestrict.is_empty() {
+                let allowed = peer
+                    .remote_addr
+                    .parse::<SocketAddr>()
+                    .map(|addr| {
+                        self.opts
+                            .netrestrict
+                            .iter()
+                            .any(|net| net.contains(addr.ip()))
+                    })
+                    .unwrap_or(false);
+                if !allowed {
+                    actions.kick.push(peer_id);
+                    continue;
+                }
+            }
+
+            let (count, limit) = if peer.inbound {
+                (&mut inbound, self.opts.max_inbound)
+            } else {
+                (&mut outbound, self.opts.max_outbound)
+            };
+            *count += 1;
+            if limit.map(|limit| *count > limit).unwrap_or(false) {
+                actions.kick.push(peer_id);
+            }
+        }
+
+        actions.connect = self
+            .opts
+            .static_peers
+            .iter()
+            .filter(|enode| !connected.contains(&enode.peer_id()))
+            .cloned()
+            .collect();
+
+        actions
+    }
+
+    /// Periodically applies the policy through `sentry`, until it fails.
+    pub async fn run(&self, sentry: &mut dyn SentryClient) -> anyhow::Result<()> {
+        loop {
+            let actions = self.actions(&sentry.peers().await?);
+
+            for enode in actions.connect {
+                if !sentry.add_peer(enode.to_string()).await? {
+                    debug!("Sentry did not accept static peer {}", enode);
+                }
+            }
+
+            for peer_id in actions.kick {
+                debug!("Disconnecting peer {:?} by policy", peer_id);
+                sentry.penalize_peer(peer_id).await?;
+            }
+
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enode(n: u8, addr: &str) -> Enode {
+        format!("enode://{}@{}", hex::encode([n; 64]), addr)
+            .parse()
+            .unwrap()
+    }
+
+    fn peer(enode: &Enode, remote_addr: &str, inbound: bool) -> PeerInfo {
+        PeerInfo {
+            id: format!("{:x}", enode.peer_id()),
+            name: String::new(),
+            enode: enode.to_string(),
+            enr: String::new(),
+            caps: vec![],
+            local_addr: String::new(),
+            remote_addr: remote_addr.to_string(),
+            inbound,
+            trusted: false,
+            is_static: false,
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let enode = enode(1, "1.2.3.4:30303?discport=30301");
+        assert_eq!(enode.pubkey, H512::repeat_byte(1));
+        assert_eq!(enode.addr, "1.2.3.4:30303".parse().unwrap());
+        assert!("enode://01@1.2.3.4:30303".parse::<Enode>().is_err());
+
+        let net = "10.1.0.0/16".parse::<Cidr>().unwrap();
+        assert!(net.contains("10.1.2.3".parse().unwrap()));
+        assert!(!net.contains("10.2.0.1".parse().unwrap()));
+        assert!(!net.contains("::1".parse().unwrap()));
+        assert!("0.0.0.0/0"
+            .parse::<Cidr>()
+            .unwrap()
+            .contains("8.8.8.8".parse().unwrap()));
+        assert!("::1"
+            .parse::<Cidr>()
+            .unwrap()
+            .contains("::1".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn actions() {
+        let static_peer = enode(1, "10.0.0.1:30303");
+        let trusted = enode(2, "8.8.8.8:30303");
+        let local = [3, 4, 5].map(|n| enode(n, "10.0.0.2:30303"));
+        let remote = enode(6, "8.8.4.4:30303");
+
+        let policy = PeerPolicy::new(PeerPolicyOpts {
+            static_peers: vec![static_peer.clone()],
+            trusted_peers: vec![trusted.clone()],
+            max_inbound: Some(1),
+            max_outbound: Some(2),
+            netrestrict: vec!["10.0.0.0/8".parse().unwrap()],
+        });
+
+        let peers = [
+            peer(&trusted, "8.8.8.8:30303", true),
+            peer(&local[0], "10.0.0.2:30303", true),
+            peer(&local[1], "10.0.0.3:40000", true),
+            peer(&local[2], "10.0.0.4:30303", false),
+            peer(&remote, "8.8.4.4:30303", false),
+        ];
+        assert_eq!(
+            policy.actions(&peers),
+            PeerActions {
+                connect: vec![static_peer.clone()],
+                kick: vec![local[1].peer_id(), remote.peer_id()],
+            }
+        );
+
+        let peers = [peer(&static_peer, "10.0.0.1:30303", true)];
+        assert_eq!(policy.actions(&peers), PeerActions::default());
+    }
+}
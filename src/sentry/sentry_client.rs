@@ -31,6 +31,33 @@ pub struct MessageFromPeer {
     pub from_peer_id: Option<PeerId>,
 }
 
+/// Connected peer as reported by the sentry.
+#[derive(Clone, Debug)]
+pub struct PeerInfo {
+    pub id: String,
+    pub name: String,
+    pub enode: String,
+    pub enr: String,
+    pub caps: Vec<String>,
+    pub local_addr: String,
+    pub remote_addr: String,
+    pub inbound: bool,
+    pub trusted: bool,
+    pub is_static: bool,
+}
+
+/// Local devp2p node run by the sentry.
+#[derive(Clone, Debug, Default)]
+pub struct NodeInfo {
+    pub id: String,
+    pub name: String,
+    pub enode: String,
+    pub enr: String,
+    pub discovery_port: u32,
+    pub listener_port: u32,
+    pub listener_addr: String,
+}
+
 pub type MessageFromPeerStream =
     Pin<Box<dyn Stream<Item = anyhow::Result<MessageFromPeer>> + Send>>;
 
@@ -50,4 +77,11 @@ pub trait SentryClient: Send + Debug {
         &mut self,
         filter_ids: &[EthMessageId],
     ) -> anyhow::Result<MessageFromPeerStream>;
+
+    async fn peers(&mut self) -> anyhow::Result<Vec<PeerInfo>>;
+
+    async fn node_info(&mut self) -> anyhow::Result<NodeInfo>;
+
+    /// Adds a static peer by its enode URL, returns whether the sentry accepted it.
+    async fn add_peer(&mut self, enode: String) -> anyhow::Result<bool>;
 }
@@ -165,6 +165,50 @@ impl SentryClient for SentryClientImpl {
         });
         Ok(Box::pin(stream))
     }
+
+    async fn peers(&mut self) -> anyhow::Result<Vec<PeerInfo>> {
+        let response = self.client.peers(tonic::Request::new(())).await?;
+        let reply: grpc_sentry::PeersReply = response.into_inner();
+        Ok(reply
+            .peers
+            .into_iter()
+            .map(|peer| PeerInfo {
+                id: peer.id,
+                name: peer.name,
+                enode: peer.enode,
+                enr: peer.enr,
+                caps: peer.caps,
+                local_addr: peer.conn_local_addr,
+                remote_addr: peer.conn_remote_addr,
+                inbound: peer.conn_is_inbound,
+                trusted: peer.conn_is_trusted,
+                is_static: peer.conn_is_static,
+            })
+            .collect())
+    }
+
+    async fn node_info(&mut self) -> anyhow::Result<NodeInfo> {
+        let response = self.client.node_info(tonic::Request::new(())).await?;
+        let reply: grpc_types::NodeInfoReply = response.into_inner();
+        let ports = reply.ports.unwrap_or_default();
+        Ok(NodeInfo {
+            id: reply.id,
+            name: reply.name,
+            enode: reply.enode,
+            enr: reply.enr,
+            discovery_port: ports.discovery,
+            listener_port: ports.listener,
+            listener_addr: reply.listener_addr,
+        })
+    }
+
+    async fn add_peer(&mut self, enode: String) -> anyhow::Result<bool> {
+        let request = grpc_sentry::AddPeerRequest { url: enode };
+        let response = self.client.add_peer(tonic::Request::new(request)).await?;
+        let reply: grpc_sentry::AddPeerReply = response.into_inner();
+        debug!("SentryClient add_peer replied with: {:?}", reply);
+        Ok(reply.success)
+    }
 }
 
 fn tonic_stream_fuse_on_error<T: 'static + Send>(
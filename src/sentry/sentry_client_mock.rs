@@ -1,6 +1,9 @@
 use super::{
     messages::{EthMessageId, Message},
-    sentry_client::{MessageFromPeer, MessageFromPeerStream, PeerFilter, SentryClient, Status},
+    sentry_client::{
+        MessageFromPeer, MessageFromPeerStream, NodeInfo, PeerFilter, PeerInfo, SentryClient,
+        Status,
+    },
 };
 use crate::{
     models::{BlockHeader, BlockNumber},
@@ -134,6 +137,18 @@ impl SentryClient for SentryClientMock {
             anyhow::bail!("SentryClientMock::receive_messages supports only one receiver")
         }
     }
+
+    async fn peers(&mut self) -> anyhow::Result<Vec<PeerInfo>> {
+        Ok(vec![])
+    }
+
+    async fn node_info(&mut self) -> anyhow::Result<NodeInfo> {
+        Ok(NodeInfo::default())
+    }
+
+    async fn add_peer(&mut self, _enode: String) -> anyhow::Result<bool> {
+        Ok(true)
+    }
 }
 
 impl Default for SentryClientMock {
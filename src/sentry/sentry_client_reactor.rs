@@ -3,6 +3,7 @@ use super::{
     sentry_client::*,
     sentry_client_connector,
 };
+use crate::task_group::TaskGroup;
 use futures_core::{Future, Stream};
 use futures_util::{FutureExt, TryStreamExt};
 use parking_lot::RwLock;
@@ -14,10 +15,7 @@ use std::{
     sync::Arc,
 };
 use strum::IntoEnumIterator;
-use tokio::{
-    sync::{broadcast, mpsc, Mutex},
-    task::JoinHandle,
-};
+use tokio::sync::{broadcast, mpsc, Mutex};
 use tokio_stream::{
     wrappers::{errors::BroadcastStreamRecvError, BroadcastStream, ReceiverStream},
     StreamExt, StreamMap,
@@ -33,7 +31,7 @@ pub struct SentryClientReactor {
     send_message_sender: mpsc::Sender<SentryCommand>,
     receive_messages_senders: ReceiveMessagesSenders,
     event_loop: Mutex<Option<SentryClientReactorEventLoop>>,
-    event_loop_handle: Option<JoinHandle<()>>,
+    tasks: TaskGroup,
     stop_signal_sender: mpsc::Sender<()>,
 }
 
@@ -101,7 +99,7 @@ impl SentryClientReactor {
             send_message_sender,
             receive_messages_senders: Arc::clone(&receive_messages_senders),
             event_loop: Mutex::new(Some(event_loop)),
-            event_loop_handle: None,
+            tasks: TaskGroup::new(),
             stop_signal_sender,
         }
     }
@@ -116,20 +114,23 @@ impl SentryClientReactor {
             .try_lock()?
             .take()
             .ok_or_else(|| anyhow::format_err!("already started once"))?;
-        let handle = tokio::spawn(async move {
+        // the loop is aborted along with the reactor, should it be dropped without a stop()
+        self.tasks.spawn("sentry client reactor", async move {
             let result = event_loop.run().await;
-            if let Err(error) = result {
+            if let Err(error) = &result {
                 error!("SentryClientReactor loop died: {:?}", error);
             }
+            result
         });
-        self.event_loop_handle = Some(handle);
         Ok(())
     }
 
     pub async fn stop(&mut self) -> anyhow::Result<()> {
-        if let Some(handle) = self.event_loop_handle.take() {
+        if !self.tasks.is_empty() {
             self.send_stop_signal();
-            handle.await?;
+            while let Some((_, result)) = self.tasks.join_next().await {
+                result?;
+            }
         }
         Ok(())
     }
@@ -240,14 +241,6 @@ impl SentryClientReactor {
     }
 }
 
-impl Drop for SentryClientReactor {
-    fn drop(&mut self) {
-        if self.event_loop_handle.is_some() {
-            self.send_stop_signal();
-        }
-    }
-}
-
 impl Debug for SentryClientReactor {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("SentryClientReactor")
@@ -0,0 +1,267 @@
+use super::{
+    messages::{EthMessageId, Message},
+    sentry_client::{
+        MessageFromPeer, MessageFromPeerStream, NodeInfo, PeerFilter, PeerInfo, SentryClient,
+        Status,
+    },
+};
+use crate::{
+    models::{BlockHeader, BlockNumber},
+    sentry::{
+        block_id::BlockId,
+        messages::{BlockHeadersMessage, GetBlockHeadersMessage},
+        sentry_client::PeerId,
+    },
+};
+use std::collections::HashSet;
+use tokio::sync::broadcast;
+use tokio_stream::{wrappers, StreamExt};
+
+/// How a simulated peer answers header requests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PeerBehavior {
+    /// Answers with the canonical headers.
+    Honest,
+    /// Answers with canonical headers whose block numbers skip after the first one.
+    WrongHeaders,
+    /// Never answers.
+    Stalling,
+    /// Answers with the canonical headers twice.
+    Duplicates,
+    /// Answers with the headers of a competing branch.
+    ReorgSpam,
+}
+
+#[derive(Debug)]
+struct SimulatedPeer {
+    id: PeerId,
+    behavior: PeerBehavior,
+    is_penalized: bool,
+}
+
+/// In-process sentry serving a chain of headers from peers with the given behaviors.
+///
+/// Penalized peers are disconnected and don't answer anymore.
+/// The message stream ends once the latest answer for every canonical header was canonical,
+/// so that the downloader stops when it had a chance to converge.
+#[derive(Debug)]
+pub struct SentryClientSimulated {
+    message_sender: Option<broadcast::Sender<MessageFromPeer>>,
+    message_receiver: Option<broadcast::Receiver<MessageFromPeer>>,
+    chain: Vec<BlockHeader>,
+    fork: Vec<BlockHeader>,
+    peers: Vec<SimulatedPeer>,
+    next_peer_index: usize,
+    is_canonical_answer: Vec<bool>,
+}
+
+impl SentryClientSimulated {
+    /// `chain` and `fork` are as many consecutive headers starting from the same block,
+    /// `fork` is served by the [`PeerBehavior::ReorgSpam`] peers.
+    pub fn new(
+        chain: Vec<BlockHeader>,
+        fork: Vec<BlockHeader>,
+        behaviors: &[PeerBehavior],
+    ) -> Self {
+        let (message_sender, message_receiver) = broadcast::channel(1024);
+        let peers = behaviors
+            .iter()
+            .enumerate()
+            .map(|(i, &behavior)| SimulatedPeer {
+                id: PeerId::from_low_u64_be(i as u64 + 1),
+                behavior,
+                is_penalized: false,
+            })
+            .collect();
+        let is_canonical_answer = vec![false; chain.len()];
+        Self {
+            message_sender: Some(message_sender),
+            message_receiver: Some(message_receiver),
+            chain,
+            fork,
+            peers,
+            next_peer_index: 0,
+            is_canonical_answer,
+        }
+    }
+
+    fn stop_receiving_messages(&mut self) {
+        self.message_sender = None;
+    }
+
+    fn is_converged(&self) -> bool {
+        self.is_canonical_answer
+            .iter()
+            .all(|&is_canonical| is_canonical)
+    }
+
+    fn select_peer(&mut self, peer_filter: PeerFilter) -> Option<usize> {
+        if let PeerFilter::PeerId(peer_id) = peer_filter {
+            return self
+                .peers
+                .iter()
+                .position(|peer| (peer.id == peer_id) && !peer.is_penalized);
+        }
+
+        // round-robin among the connected peers
+        let count = self.peers.len();
+        for _ in 0..count {
+            let index = self.next_peer_index % count;
+            self.next_peer_index = (self.next_peer_index + 1) % count;
+            if !self.peers[index].is_penalized {
+                return Some(index);
+            }
+        }
+        None
+    }
+
+    fn headers_range(
+        headers: &[BlockHeader],
+        request: &GetBlockHeadersMessage,
+    ) -> Option<(usize, usize)> {
+        let BlockId::Number(start_block_num) = request.params.start_block else {
+            return None;
+        };
+        let first_block_num = headers.first()?.number;
+        if (start_block_num < first_block_num)
+            || (request.params.skip != 0)
+            || (request.params.reverse != 0)
+        {
+            return None;
+        }
+
+        let start = (start_block_num.0 - first_block_num.0) as usize;
+        let end = std::cmp::min(start + request.params.limit as usize, headers.len());
+        if start >= end {
+            return None;
+        }
+        Some((start, end))
+    }
+
+    fn answer(
+        &mut self,
+        peer_index: usize,
+        request: &GetBlockHeadersMessage,
+    ) -> Vec<Vec<BlockHeader>> {
+        let behavior = self.peers[peer_index].behavior;
+        let headers = if behavior == PeerBehavior::ReorgSpam {
+            &self.fork
+        } else {
+            &self.chain
+        };
+        let Some((start, end)) = Self::headers_range(headers, request) else {
+            return Vec::new();
+        };
+        let mut answer = headers[start..end].to_vec();
+
+        match behavior {
+            PeerBehavior::Honest => (),
+            PeerBehavior::WrongHeaders => {
+                for header in answer.iter_mut().skip(1) {
+                    header.number = BlockNumber(header.number.0 + 1);
+                }
+            }
+            PeerBehavior::Stalling => return Vec::new(),
+            PeerBehavior::Duplicates => {
+                self.is_canonical_answer[start..end].fill(true);
+                return vec![answer.clone(), answer];
+            }
+            PeerBehavior::ReorgSpam => (),
+        }
+
+        self.is_canonical_answer[start..end].fill(behavior == PeerBehavior::Honest);
+        vec![answer]
+    }
+}
+
+#[async_trait::async_trait]
+impl SentryClient for SentryClientSimulated {
+    async fn set_status(&mut self, _status: Status) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn penalize_peer(&mut self, peer_id: PeerId) -> anyhow::Result<()> {
+        if let Some(peer) = self.peers.iter_mut().find(|peer| peer.id == peer_id) {
+            peer.is_penalized = true;
+        }
+        Ok(())
+    }
+
+    async fn send_message(
+        &mut self,
+        message: Message,
+        peer_filter: PeerFilter,
+    ) -> anyhow::Result<u32> {
+        if self.message_sender.is_none() {
+            return Ok(0);
+        }
+
+        let request = match message {
+            Message::GetBlockHeaders(request) => request,
+            _ => anyhow::bail!(
+                "SentryClientSimulated::send_message unsupported message {:?}",
+                message
+            ),
+        };
+        let Some(peer_index) = self.select_peer(peer_filter) else {
+            return Ok(0);
+        };
+
+        let from_peer_id = self.peers[peer_index].id;
+        for headers in self.answer(peer_index, &request) {
+            let response = BlockHeadersMessage {
+                request_id: request.request_id,
+                headers,
+            };
+            let response_message = MessageFromPeer {
+                message: Message::BlockHeaders(response),
+                from_peer_id: Some(from_peer_id),
+            };
+            self.message_sender
+                .as_ref()
+                .unwrap()
+                .send(response_message)?;
+        }
+
+        if self.is_converged() {
+            self.stop_receiving_messages();
+        }
+        Ok(1)
+    }
+
+    async fn receive_messages(
+        &mut self,
+        filter_ids: &[EthMessageId],
+    ) -> anyhow::Result<MessageFromPeerStream> {
+        let filter_ids_set = filter_ids
+            .iter()
+            .cloned()
+            .collect::<HashSet<EthMessageId>>();
+
+        if let Some(receiver) = self.message_receiver.take() {
+            let stream = wrappers::BroadcastStream::new(receiver)
+                .filter_map(|res| res.ok()) // ignore BroadcastStreamRecvError
+                .filter(move |message_from_peer| {
+                    filter_ids_set.is_empty()
+                        || filter_ids_set.contains(&message_from_peer.message.eth_id())
+                })
+                .map(Ok);
+
+            Ok(Box::pin(stream))
+        } else {
+            anyhow::bail!("SentryClientSimulated::receive_messages supports only one receiver")
+        }
+    }
+
+    async fn peers(&mut self) -> anyhow::Result<Vec<PeerInfo>> {
+        Ok(vec![])
+    }
+
+    async fn node_info(&mut self) -> anyhow::Result<NodeInfo> {
+        Ok(NodeInfo::default())
+    }
+
+    async fn add_peer(&mut self, _enode: String) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+}
@@ -1,14 +1,15 @@
+use super::health::{reconnect_delay, SentryHealth, HEALTH_CHECK_TICK, HEALTH_CHECK_TIMEOUT};
 use crate::{
-    models::{Block, BlockNumber, H256},
+    models::{Block, BlockNumber, H256, U256},
     sentry::chain_config::ChainConfig,
     sentry2::types::*,
 };
 use async_trait::async_trait;
 use ethereum_interfaces::sentry as grpc_sentry;
 use futures_util::{FutureExt, StreamExt};
-use std::{collections::HashSet, pin::Pin, sync::Arc};
-use tokio::sync::RwLock as AsyncMutex;
-use tracing::{debug, instrument, warn};
+use std::{collections::HashSet, pin::Pin, sync::Arc, time::Instant};
+use tokio::sync::{mpsc, RwLock as AsyncMutex};
+use tracing::{debug, info, warn};
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Status {
@@ -17,12 +18,6 @@ pub struct Status {
     pub total_difficulty: H256,
 }
 
-impl From<Status> for grpc_sentry::StatusData {
-    fn from(_status: Status) -> Self {
-        todo!();
-    }
-}
-
 pub struct HeaderDownloader {
     pub bad_headers: Arc<AsyncMutex<HashSet<H256>>>,
 }
@@ -31,9 +26,18 @@ pub struct BodyDownaloder {}
 
 pub type SentryClient = grpc_sentry::sentry_client::SentryClient<tonic::transport::Channel>;
 
+/// A block that became the head of the canonical chain.
+#[derive(Debug, Clone)]
+pub struct CanonicalBlockEvent {
+    pub block: Block,
+    pub total_difficulty: U256,
+}
+
 #[derive(Clone)]
 pub struct Coordinator {
     pub sentries: Vec<SentryClient>,
+    /// Health of `sentries`, by index. Only the active ones are used for sending.
+    pub sentry_health: Arc<AsyncMutex<Vec<SentryHealth>>>,
     pub header_downloader: Arc<HeaderDownloader>,
     pub body_downloader: Arc<BodyDownaloder>,
     pub status: Arc<AsyncMutex<Status>>,
@@ -53,8 +57,10 @@ impl Coordinator {
         genesis_hash: H256,
         network_id: u64,
     ) -> Self {
+        let sentry_health = vec![SentryHealth::new(); sentries.len()];
         Self {
             sentries,
+            sentry_health: Arc::new(AsyncMutex::new(sentry_health)),
             header_downloader,
             body_downloader: Arc::new(BodyDownaloder {}),
             chain_config: None,
@@ -64,6 +70,127 @@ impl Coordinator {
             status,
         }
     }
+
+    async fn status_data(&self) -> grpc_sentry::StatusData {
+        let status = *self.status.read().await;
+        grpc_sentry::StatusData {
+            network_id: self.network_id,
+            total_difficulty: Some(status.total_difficulty.into()),
+            best_hash: Some(status.hash.into()),
+            fork_data: Some(grpc_sentry::Forks {
+                genesis: Some(self.genesis_hash.into()),
+                forks: self.forks.clone(),
+            }),
+            max_block: status.height,
+        }
+    }
+
+    /// Sentries which passed the last health check, along with their index.
+    async fn active_sentries(&self) -> Vec<(usize, SentryClient)> {
+        let health = self.sentry_health.read().await;
+        self.sentries
+            .iter()
+            .cloned()
+            .enumerate()
+            .filter(|(index, _)| health[*index].is_active())
+            .collect()
+    }
+
+    async fn on_sentry_failure(&self, index: usize, error: &anyhow::Error) {
+        let mut health = self.sentry_health.write().await;
+        if health[index].is_active() {
+            warn!(
+                "Sentry {} is unavailable, removing it from the active set: {:?}",
+                index, error
+            );
+        }
+        health[index].on_failure();
+    }
+
+    /// Runs the due health checks, the recovered sentries are re-added to the active set.
+    pub async fn check_sentries(&self) {
+        let now = Instant::now();
+        let due = {
+            let health = self.sentry_health.read().await;
+            (0..self.sentries.len())
+                .filter(|&index| health[index].is_check_due(now))
+                .collect::<Vec<_>>()
+        };
+
+        for index in due {
+            let mut s = self.sentries[index].clone();
+            let check = async move {
+                s.hand_shake(tonic::Request::new(())).await?;
+                let peer_count = s
+                    .peer_count(grpc_sentry::PeerCountRequest {})
+                    .await?
+                    .into_inner()
+                    .count;
+                Ok::<_, anyhow::Error>(peer_count)
+            };
+            let result = tokio::time::timeout(HEALTH_CHECK_TIMEOUT, check)
+                .await
+                .map_err(anyhow::Error::from)
+                .and_then(|result| result);
+
+            match result {
+                Ok(peer_count) => {
+                    let recovered = self.sentry_health.write().await[index].on_success();
+                    if recovered {
+                        info!("Sentry {} is back with {} peers", index, peer_count);
+                        // a restarted sentry doesn't know our head, if we ever set one
+                        if self.status.read().await.hash.is_zero() {
+                            continue;
+                        }
+                        let status_data = self.status_data().await;
+                        if let Err(error) =
+                            self.sentries[index].clone().set_status(status_data).await
+                        {
+                            self.on_sentry_failure(index, &error.into()).await;
+                        }
+                    }
+                }
+                Err(error) => self.on_sentry_failure(index, &error).await,
+            }
+        }
+    }
+
+    /// Health-checks the sentries until the task is dropped.
+    pub async fn run_health_checks(&self) -> anyhow::Result<()> {
+        loop {
+            self.check_sentries().await;
+            tokio::time::sleep(HEALTH_CHECK_TICK).await;
+        }
+    }
+
+    /// Announces the blocks coming from the canonization channel to the peers
+    /// until the channel is closed.
+    pub async fn relay_canonical_blocks(
+        &mut self,
+        mut events: mpsc::Receiver<CanonicalBlockEvent>,
+    ) -> anyhow::Result<()> {
+        while let Some(event) = events.recv().await {
+            let hash = event.block.header.hash();
+            let number = event.block.header.number;
+
+            if let Err(error) = self
+                .broadcast_block(event.block, event.total_difficulty)
+                .await
+            {
+                warn!("Failed to broadcast block {}: {:?}", number.0, error);
+            }
+            if let Err(error) = self.propagate_new_block_hashes(vec![(hash, number)]).await {
+                warn!("Failed to announce block {}: {:?}", number.0, error);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Per the eth protocol, the full block is sent to a square root of the peers,
+/// the rest learn about it from the hash announcement.
+fn full_block_peers_count(peer_count: u64) -> u64 {
+    ((peer_count as f64).sqrt() as u64).max(1)
 }
 
 pub type SentryInboundStream = futures_util::stream::Map<
@@ -75,12 +202,17 @@ pub type SentryInboundStream = futures_util::stream::Map<
 #[allow(unreachable_code)]
 impl SentryCoordinator for Coordinator {
     async fn set_status(&mut self) -> anyhow::Result<()> {
-        let status_data: grpc_sentry::StatusData = (*self.status.read().await).into();
+        let status_data = self.status_data().await;
         let mut futs = Vec::new();
-        for sentry in self.sentries.iter_mut() {
-            futs.push(sentry.set_status(status_data.clone()))
+        for (index, mut sentry) in self.active_sentries().await {
+            let status_data = status_data.clone();
+            futs.push(async move { (index, sentry.set_status(status_data).await) });
+        }
+        for (index, result) in futures_util::future::join_all(futs).await {
+            if let Err(error) = result {
+                self.on_sentry_failure(index, &error.into()).await;
+            }
         }
-        futures_util::future::join_all(futs).await;
 
         Ok(())
     }
@@ -111,46 +243,45 @@ impl SentryCoordinator for Coordinator {
     }
     async fn recv(&mut self, msg_ids: Vec<i32>) -> anyhow::Result<CoordinatorStream> {
         Ok(futures_util::stream::select_all(
-            futures_util::future::join_all(
-                self.sentries
-                    .iter()
-                    .map(|s| recv_sentry(s, msg_ids.clone()))
-                    .collect::<Vec<_>>(),
-            )
-            .await,
+            self.sentries
+                .iter()
+                .map(|s| recv_sentry(s.clone(), msg_ids.clone())),
         ))
     }
 
     async fn recv_headers(&mut self) -> anyhow::Result<CoordinatorStream> {
+        let ids = vec![grpc_sentry::MessageId::from(MessageId::BlockHeaders) as i32];
         Ok(futures_util::stream::select_all(
-            futures_util::future::join_all(
-                self.sentries
-                    .iter()
-                    .map(|s| {
-                        recv_sentry(
-                            s,
-                            vec![grpc_sentry::MessageId::from(MessageId::BlockHeaders) as i32],
-                        )
-                    })
-                    .collect::<Vec<_>>(),
-            )
-            .await,
+            self.sentries
+                .iter()
+                .map(|s| recv_sentry(s.clone(), ids.clone())),
         ))
     }
 
     async fn broadcast_block(
         &mut self,
-        _block: Block,
-        _total_difficulty: u128,
+        block: Block,
+        total_difficulty: U256,
     ) -> anyhow::Result<()> {
-        let _fut = async move || {};
-        Ok(())
+        let peer_count = self.peer_count().await?;
+        if peer_count == 0 {
+            return Ok(());
+        }
+
+        let msg = Message::NewBlock(Box::new(NewBlock::new(block, total_difficulty)));
+        let predicate = PeerFilter::Random(full_block_peers_count(peer_count));
+        self.send_message(msg, predicate).await
     }
     async fn propagate_new_block_hashes(
         &mut self,
-        _block_hashes: Vec<(H256, BlockNumber)>,
+        block_hashes: Vec<(H256, BlockNumber)>,
     ) -> anyhow::Result<()> {
-        Ok(())
+        if block_hashes.is_empty() {
+            return Ok(());
+        }
+
+        let msg = Message::NewBlockHashes(NewBlockHashes::new(block_hashes));
+        self.send_message(msg, PeerFilter::All).await
     }
 
     async fn propagate_transactions(&mut self, _transactions: Vec<H256>) -> anyhow::Result<()> {
@@ -175,23 +306,19 @@ impl SentryCoordinator for Coordinator {
     }
 
     async fn penalize(&mut self, penalties: Vec<Penalty>) -> anyhow::Result<()> {
-        let sentry_penalize = async move |mut s: SentryClient,
-                                          penalty: Penalty|
-                    -> Result<tonic::Response<()>, tonic::Status> {
-            s.penalize_peer(grpc_sentry::PenalizePeerRequest {
-                peer_id: Some(penalty.peer_id),
-                penalty: 0,
-            })
-            .await
-        };
-
         let mut futures = Vec::new();
-        self.sentries.iter().for_each(|s| {
-            penalties.iter().for_each(|p| {
-                futures.push(sentry_penalize(s.clone(), p.clone()));
-            });
-        });
-        futures_util::future::join_all(futures).await;
+        for (index, s) in self.active_sentries().await {
+            for p in penalties.iter() {
+                let mut s = s.clone();
+                let request = grpc_sentry::PenalizePeerRequest::from(p.clone());
+                futures.push(async move { (index, s.penalize_peer(request).await) });
+            }
+        }
+        for (index, result) in futures_util::future::join_all(futures).await {
+            if let Err(error) = result {
+                self.on_sentry_failure(index, &error.into()).await;
+            }
+        }
         Ok(())
     }
 
@@ -201,76 +328,88 @@ impl SentryCoordinator for Coordinator {
             data: rlp::encode(&msg).into(),
         };
 
-        let fut = async move |mut s: SentryClient,
-                              filter: PeerFilter,
-                              req: grpc_sentry::OutboundMessageData|
-                    -> anyhow::Result<()> {
-            s.hand_shake(tonic::Request::new(())).await?;
-            match filter {
-                PeerFilter::All => s.send_message_to_all(req).boxed(),
-                PeerFilter::PeerId(peer_id) => s
-                    .send_message_by_id(grpc_sentry::SendMessageByIdRequest {
-                        data: Some(req),
-                        peer_id: Some(peer_id),
-                    })
-                    .boxed(),
-                PeerFilter::MinBlock(min_block) => s
-                    .send_message_by_min_block(grpc_sentry::SendMessageByMinBlockRequest {
-                        data: Some(req),
-                        min_block,
-                    })
-                    .boxed(),
-                PeerFilter::Random(max_peers) => s
-                    .send_message_to_random_peers(grpc_sentry::SendMessageToRandomPeersRequest {
-                        data: Some(req),
-                        max_peers,
-                    })
-                    .boxed(),
+        let sentries = self.active_sentries().await;
+        if sentries.is_empty() {
+            anyhow::bail!("No active sentries to send {:?} to", msg.id());
+        }
+
+        // one sentry going down must not fail the whole sync
+        let mut last_error = None;
+        let mut delivered = false;
+        for (index, s) in sentries {
+            match send_to_sentry(s, predicate.clone(), data.clone()).await {
+                Ok(()) => delivered = true,
+                Err(error) => {
+                    self.on_sentry_failure(index, &error).await;
+                    last_error = Some(error);
+                }
             }
-            .await?;
-            Ok(())
-        };
-        for s in self.sentries.iter() {
-            fut(s.clone(), predicate.clone(), data.clone()).await?;
         }
 
-        Ok(())
+        match last_error {
+            Some(error) if !delivered => Err(error),
+            _ => Ok(()),
+        }
     }
 
     async fn peer_count(&mut self) -> anyhow::Result<u64> {
-        let peer_count: u64 = futures_util::future::join_all(
-            self.sentries
-                .iter()
-                .map(
-                    async move |s| -> anyhow::Result<grpc_sentry::PeerCountReply> {
-                        let mut s = s.clone();
-                        s.hand_shake(tonic::Request::new(())).await?;
-                        Ok(s.peer_count(grpc_sentry::PeerCountRequest {})
+        let replies = futures_util::future::join_all(self.active_sentries().await.into_iter().map(
+            |(index, mut s)| async move {
+                let reply = async {
+                    s.hand_shake(tonic::Request::new(())).await?;
+                    Ok::<_, anyhow::Error>(
+                        s.peer_count(grpc_sentry::PeerCountRequest {})
                             .await?
-                            .into_inner())
-                    },
-                )
-                .collect::<Vec<_>>(),
-        )
-        .await
-        .into_iter()
-        .map(move |r| if let Ok(r) = r { r.count } else { 0 })
-        .sum();
+                            .into_inner(),
+                    )
+                }
+                .await;
+                (index, reply)
+            },
+        ))
+        .await;
+
+        let mut peer_count = 0;
+        for (index, reply) in replies {
+            match reply {
+                Ok(reply) => peer_count += reply.count,
+                Err(error) => self.on_sentry_failure(index, &error).await,
+            }
+        }
 
         Ok(peer_count)
     }
 }
-async fn recv_sentry(s: &SentryClient, ids: Vec<i32>) -> SingleSentryStream {
-    let mut s = s.clone();
-    s.hand_shake(tonic::Request::new(())).await.unwrap();
-    debug!("Handshake with sentry {:?} done", s);
-
-    poll_sentry_stream(
-        s.messages(grpc_sentry::MessagesRequest { ids })
-            .await
-            .unwrap()
-            .into_inner(),
-    )
+
+async fn send_to_sentry(
+    mut s: SentryClient,
+    filter: PeerFilter,
+    req: grpc_sentry::OutboundMessageData,
+) -> anyhow::Result<()> {
+    s.hand_shake(tonic::Request::new(())).await?;
+    match filter {
+        PeerFilter::All => s.send_message_to_all(req).boxed(),
+        PeerFilter::PeerId(peer_id) => s
+            .send_message_by_id(grpc_sentry::SendMessageByIdRequest {
+                data: Some(req),
+                peer_id: Some(peer_id.into()),
+            })
+            .boxed(),
+        PeerFilter::MinBlock(min_block) => s
+            .send_message_by_min_block(grpc_sentry::SendMessageByMinBlockRequest {
+                data: Some(req),
+                min_block,
+            })
+            .boxed(),
+        PeerFilter::Random(max_peers) => s
+            .send_message_to_random_peers(grpc_sentry::SendMessageToRandomPeersRequest {
+                data: Some(req),
+                max_peers,
+            })
+            .boxed(),
+    }
+    .await?;
+    Ok(())
 }
 
 pub type SingleSentryStream =
@@ -278,18 +417,41 @@ pub type SingleSentryStream =
 
 pub type CoordinatorStream = futures_util::stream::SelectAll<SingleSentryStream>;
 
-#[instrument(level = "debug", name = "poll_sentry_stream")]
-fn poll_sentry_stream(
-    mut stream: tonic::Streaming<grpc_sentry::InboundMessage>,
-) -> SingleSentryStream {
+/// Subscribes to the sentry messages, and re-subscribes with a backoff
+/// if the sentry goes away, so the stream only ends when dropped.
+fn recv_sentry(s: SentryClient, ids: Vec<i32>) -> SingleSentryStream {
     Box::pin(async_stream::stream! {
-        debug!("Starting to poll SingleSentryStream");
-        while let Some(msg) = stream.next().await {
-            debug!("Polling: Received message {:?}", msg);
-            match msg {
-                Ok(message) => yield message,
-                _ => continue,
+        let mut attempt = 0;
+        loop {
+            let mut s = s.clone();
+            let ids = ids.clone();
+            let subscription = async move {
+                s.hand_shake(tonic::Request::new(())).await?;
+                debug!("Handshake with sentry {:?} done", s);
+                Ok::<_, tonic::Status>(s.messages(grpc_sentry::MessagesRequest { ids }).await?.into_inner())
+            }
+            .await;
+
+            match subscription {
+                Ok(mut stream) => {
+                    attempt = 0;
+                    while let Some(msg) = stream.next().await {
+                        match msg {
+                            Ok(message) => yield message,
+                            Err(status) => {
+                                warn!("Sentry message stream failed: {:?}", status);
+                                break;
+                            }
+                        }
+                    }
+                }
+                Err(status) => warn!("Failed to subscribe to sentry messages: {:?}", status),
             }
+
+            let delay = reconnect_delay(attempt);
+            attempt += 1;
+            debug!("Re-subscribing to sentry messages in {:?}", delay);
+            tokio::time::sleep(delay).await;
         }
     })
 }
@@ -301,7 +463,7 @@ pub trait SentryCoordinator: Send + Sync {
     async fn send_header_request(&mut self, req: HeaderRequest) -> anyhow::Result<()>;
     async fn recv(&mut self, msg_ids: Vec<i32>) -> anyhow::Result<CoordinatorStream>;
     async fn recv_headers(&mut self) -> anyhow::Result<CoordinatorStream>;
-    async fn broadcast_block(&mut self, block: Block, total_difficulty: u128)
+    async fn broadcast_block(&mut self, block: Block, total_difficulty: U256)
         -> anyhow::Result<()>;
     async fn propagate_new_block_hashes(
         &mut self,
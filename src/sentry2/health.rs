@@ -0,0 +1,65 @@
+use std::time::{Duration, Instant};
+
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+pub const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often the due checks are looked for.
+pub const HEALTH_CHECK_TICK: Duration = Duration::from_secs(1);
+
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Exponential backoff delay before the next connection attempt.
+pub fn reconnect_delay(attempt: u32) -> Duration {
+    std::cmp::min(
+        MIN_RECONNECT_DELAY.saturating_mul(1 << std::cmp::min(attempt, 16)),
+        MAX_RECONNECT_DELAY,
+    )
+}
+
+/// Tracks if a sentry is responsive, and when to check it next.
+#[derive(Debug, Clone)]
+pub struct SentryHealth {
+    is_active: bool,
+    failures: u32,
+    next_check: Instant,
+}
+
+impl SentryHealth {
+    pub fn new() -> Self {
+        Self {
+            is_active: true,
+            failures: 0,
+            next_check: Instant::now(),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.is_active
+    }
+
+    pub fn is_check_due(&self, now: Instant) -> bool {
+        now >= self.next_check
+    }
+
+    /// Returns true if the sentry was inactive before.
+    pub fn on_success(&mut self) -> bool {
+        let was_active = self.is_active;
+        self.is_active = true;
+        self.failures = 0;
+        self.next_check = Instant::now() + HEALTH_CHECK_INTERVAL;
+        !was_active
+    }
+
+    /// Takes the sentry out of the active set until a health check succeeds.
+    pub fn on_failure(&mut self) {
+        self.is_active = false;
+        self.next_check = Instant::now() + reconnect_delay(self.failures);
+        self.failures = self.failures.saturating_add(1);
+    }
+}
+
+impl Default for SentryHealth {
+    fn default() -> Self {
+        Self::new()
+    }
+}
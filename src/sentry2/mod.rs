@@ -1,4 +1,6 @@
-mod coordinator;
+pub mod coordinator;
+mod health;
+pub mod relay;
 mod sentry;
 pub mod types;
 
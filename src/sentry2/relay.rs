@@ -0,0 +1,93 @@
+use super::coordinator::CanonicalBlockEvent;
+use crate::{
+    accessors::chain,
+    kv::{mdbx::MdbxEnvironment, tables},
+    models::*,
+    stagedsync::ChainEvent,
+};
+use mdbx::EnvironmentKind;
+use std::{ops::Deref, sync::Arc};
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc,
+};
+use tracing::*;
+
+/// Heads further than that from the previous one come from a catch-up sync cycle,
+/// the peers already have these blocks.
+const MAX_RELAY_DISTANCE: u64 = 8;
+
+fn read_block<E: EnvironmentKind>(
+    db: &MdbxEnvironment<E>,
+    number: BlockNumber,
+    hash: H256,
+) -> anyhow::Result<Option<CanonicalBlockEvent>> {
+    let tx = db.begin()?;
+    let Some(header) = tx.get(tables::Header, (number, hash))? else {
+        return Ok(None);
+    };
+    let Some(body) = chain::block_body::read_without_senders(&tx, hash, number)? else {
+        return Ok(None);
+    };
+    let Some(total_difficulty) = chain::td::read(&tx, hash, number)? else {
+        return Ok(None);
+    };
+
+    Ok(Some(CanonicalBlockEvent {
+        block: Block {
+            header,
+            transactions: body.transactions,
+            ommers: body.ommers,
+        },
+        total_difficulty,
+    }))
+}
+
+/// Turns the heads reached by staged sync into [`CanonicalBlockEvent`]s, until either side
+/// goes away.
+///
+/// Only the heads following closely the previous one are relayed, so that neither the first
+/// cycle nor catching up after a pause floods the peers with stale blocks.
+pub async fn canonical_blocks<DB, E>(
+    db: Arc<DB>,
+    mut events: broadcast::Receiver<ChainEvent>,
+    blocks: mpsc::Sender<CanonicalBlockEvent>,
+) -> anyhow::Result<()>
+where
+    DB: Deref<Target = MdbxEnvironment<E>> + Send + Sync,
+    E: EnvironmentKind,
+{
+    let mut last_head: Option<BlockNumber> = None;
+    loop {
+        match events.recv().await {
+            Ok(ChainEvent::Head { number, hash }) => {
+                let follows = last_head
+                    .map(|last| number > last && number.0 - last.0 <= MAX_RELAY_DISTANCE)
+                    .unwrap_or(false);
+                last_head = Some(number);
+                if !follows {
+                    continue;
+                }
+
+                match read_block(&db, number, hash)? {
+                    Some(event) => {
+                        if blocks.send(event).await.is_err() {
+                            break;
+                        }
+                    }
+                    None => debug!("Head block {} is not in the database", number),
+                }
+            }
+            Ok(ChainEvent::Unwind { to, .. }) => {
+                last_head = last_head.map(|last| std::cmp::min(last, to));
+            }
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Block relay missed {} chain events", skipped);
+                last_head = None;
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use crate::models::{Block, BlockNumber, H256};
+use crate::models::{Block, BlockNumber, H256, U256};
 use rlp_derive::*;
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum BlockId {
@@ -16,7 +16,7 @@ pub struct BlockHashAndNumber {
 pub struct NewBlockHashes(pub Vec<BlockHashAndNumber>);
 
 impl NewBlockHashes {
-    fn new(block_hashes: Vec<(H256, BlockNumber)>) -> Self {
+    pub fn new(block_hashes: Vec<(H256, BlockNumber)>) -> Self {
         Self(
             block_hashes
                 .into_iter()
@@ -29,11 +29,11 @@ impl NewBlockHashes {
 #[derive(Debug, Clone, PartialEq, RlpEncodable, RlpDecodable)]
 pub struct NewBlock {
     pub block: Block,
-    pub total_difficulty: u128,
+    pub total_difficulty: U256,
 }
 
 impl NewBlock {
-    pub fn new(block: Block, total_difficulty: u128) -> Self {
+    pub fn new(block: Block, total_difficulty: U256) -> Self {
         Self {
             block,
             total_difficulty,
@@ -1,6 +1,7 @@
-use ethereum_interfaces::{sentry as grpc_sentry, types::H512};
+use crate::models::H256;
+use ethereum_interfaces::sentry as grpc_sentry;
 
-pub type PeerId = H512;
+pub type PeerId = H256;
 
 #[derive(Debug, Clone)]
 pub enum PenaltyKind {
@@ -22,7 +23,7 @@ pub struct Penalty {
 impl From<Penalty> for grpc_sentry::PenalizePeerRequest {
     fn from(penalty: Penalty) -> Self {
         grpc_sentry::PenalizePeerRequest {
-            peer_id: Some(penalty.peer_id),
+            peer_id: Some(penalty.peer_id.into()),
             penalty: 0,
         }
     }
@@ -1,12 +1,36 @@
 pub mod stage;
 pub mod stages;
 
-use self::stage::{Stage, StageInput, UnwindInput};
-use crate::{kv::mdbx::MdbxEnvironment, models::BlockNumber, stagedsync::stage::*};
-use mdbx::EnvironmentKind;
-use std::time::{Duration, Instant};
+use self::{
+    stage::{Stage, StageInput, UnwindInput},
+    stages::{unwind_order, validate_order},
+};
+use crate::{
+    accessors,
+    kv::{
+        mdbx::{MdbxEnvironment, MdbxTransaction},
+        tables::{self, StageStatsEntry},
+        KvError,
+    },
+    models::{BlockNumber, H256},
+    stagedsync::stage::*,
+};
+use anyhow::format_err;
+use mdbx::{EnvironmentKind, RW};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
 use tracing::*;
 
+/// Changes of the canonical chain, sent once they are committed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChainEvent {
+    /// Blocks after `to` were unwound. Read transactions with ids below `txn_id` still see
+    /// them.
+    Unwind { to: BlockNumber, txn_id: u64 },
+    /// Sync cycle completed, leaving `number` as the executed head of the canonical chain.
+    Head { number: BlockNumber, hash: H256 },
+}
+
 /// Staged synchronization framework
 ///
 /// As the name suggests, the gist of this framework is splitting sync into logical _stages_ that are consecutively executed one after another.
@@ -28,6 +52,8 @@ where
     max_block: Option<BlockNumber>,
     exit_after_sync: bool,
     delay_after_sync: Option<Duration>,
+    fsync_on_stage_boundary: bool,
+    chain_events: broadcast::Sender<ChainEvent>,
 }
 
 impl<'db, E> Default for StagedSync<'db, E>
@@ -50,6 +76,8 @@ where
             max_block: None,
             exit_after_sync: false,
             delay_after_sync: None,
+            fsync_on_stage_boundary: false,
+            chain_events: broadcast::channel(16).0,
         }
     }
 
@@ -80,12 +108,29 @@ where
         self
     }
 
+    /// Commit and fsync after every stage, for databases opened without fsync on commit.
+    pub fn set_fsync_on_stage_boundary(&mut self, v: bool) -> &mut Self {
+        self.fsync_on_stage_boundary = v;
+        self
+    }
+
+    pub fn subscribe_chain_events(&self) -> broadcast::Receiver<ChainEvent> {
+        self.chain_events.subscribe()
+    }
+
     /// Run staged sync loop.
     /// Invokes each loaded stage, and does unwinds if necessary.
     ///
     /// NOTE: it should never return, except if the loop or any stage fails with error.
-    pub async fn run(&mut self, db: &'db MdbxEnvironment<E>) -> anyhow::Result<()> {
+    pub async fn run(&mut self, db: &'db MdbxEnvironment<E>) -> Result<(), StageError> {
         let num_stages = self.stages.len();
+        let stage_ids = self
+            .stages
+            .iter()
+            .map(|stage| stage.id())
+            .collect::<Vec<_>>();
+        validate_order(&stage_ids)?;
+        let unwind_order = unwind_order(&stage_ids)?;
 
         let mut unwind_to = None;
         'run_loop: loop {
@@ -93,13 +138,14 @@ where
 
             // Start with unwinding if it's been requested.
             if let Some(to) = unwind_to.take() {
-                // Unwind stages in reverse order.
-                for (stage_index, stage) in self.stages.iter_mut().enumerate().rev() {
+                // Unwind dependent stages before the stages they consume.
+                for &stage_index in &unwind_order {
+                    let stage = &mut self.stages[stage_index];
                     let stage_id = stage.id();
 
                     // Unwind magic happens here.
                     // Encapsulated into a future for tracing instrumentation.
-                    let res: anyhow::Result<()> = async {
+                    let res: Result<(), StageError> = async {
                         let mut stage_progress = stage_id.get_progress(&tx)?.unwrap_or_default();
 
                         if stage_progress > to {
@@ -135,6 +181,8 @@ where
                     .instrument(span!(
                         Level::INFO,
                         "",
+                        stage = %stage_id,
+                        unwind_to = %to,
                         " Unwinding {}/{} {} ",
                         stage_index + 1,
                         num_stages,
@@ -145,7 +193,13 @@ where
                     res?;
                 }
 
+                let txn_id = tx.id();
                 tx.commit()?;
+                if self.fsync_on_stage_boundary {
+                    fsync(db)?;
+                }
+                // Nobody may be listening.
+                let _ = self.chain_events.send(ChainEvent::Unwind { to, txn_id });
             } else {
                 // Now that we're done with unwind, let's roll.
 
@@ -161,7 +215,26 @@ where
                     let stage_id = stage.id();
 
                     let start_time = Instant::now();
+                    let started_at = SystemTime::now();
                     let start_progress = stage_id.get_progress(&tx)?;
+                    let start_db_size = tx.db_size()?;
+
+                    // Stage must not run ahead of the stages it consumes.
+                    for dependency in stage_id.dependencies() {
+                        if stage_ids.contains(dependency) {
+                            let dependency_progress = dependency.get_progress(&tx)?;
+                            if dependency_progress < start_progress {
+                                return Err(format_err!(
+                                    "stage {} @ {:?} is ahead of its dependency {} @ {:?}",
+                                    stage_id,
+                                    start_progress,
+                                    dependency,
+                                    dependency_progress
+                                )
+                                .into());
+                            }
+                        }
+                    }
 
                     // Re-invoke the stage until it reports `StageOutput::done`.
                     let done_progress = loop {
@@ -169,7 +242,7 @@ where
 
                         let stage_id = stage.id();
 
-                        let exec_output: anyhow::Result<_> = async {
+                        let exec_output: Result<_, StageError> = async {
                             if restarted {
                                 debug!(
                                     "Invoking stage @ {}",
@@ -206,6 +279,7 @@ where
                                     stage_progress,
                                     ..
                                 } => {
+                                    Span::current().record("progress", &stage_progress.0);
                                     if *done {
                                         info!(
                                             "DONE @ {} in {}",
@@ -241,6 +315,9 @@ where
                         .instrument(span!(
                             Level::INFO,
                             "",
+                            stage = %stage_id,
+                            from = ?prev_progress,
+                            progress = field::Empty,
                             " {}/{} {} ",
                             stage_index + 1,
                             num_stages,
@@ -291,11 +368,42 @@ where
                             }
                         }
                     };
-                    timings.push((stage_id, Instant::now() - start_time));
+                    let duration = Instant::now() - start_time;
+                    if start_progress != Some(done_progress) {
+                        save_stats(
+                            &tx,
+                            StageStatsEntry {
+                                stage: stage_id.to_string(),
+                                from: start_progress,
+                                to: done_progress,
+                                started_at: started_at
+                                    .duration_since(UNIX_EPOCH)
+                                    .map(|d| d.as_secs())
+                                    .unwrap_or_default(),
+                                duration_ms: duration.as_millis() as u64,
+                                db_growth: tx.db_size()? as i64 - start_db_size as i64,
+                            },
+                        )?;
+                    }
+                    timings.push((stage_id, duration));
+
+                    if self.fsync_on_stage_boundary {
+                        tx.commit()?;
+                        fsync(db)?;
+                        tx = db.begin_mutable()?;
+                    }
 
                     previous_stage = Some((stage_id, done_progress))
                 }
+                let head = accessors::chain::chain_head::read(&tx)?;
                 tx.commit()?;
+                if self.fsync_on_stage_boundary {
+                    fsync(db)?;
+                }
+                if let Some(head) = head {
+                    let (number, hash) = head.latest;
+                    let _ = self.chain_events.send(ChainEvent::Head { number, hash });
+                }
 
                 let t = timings
                     .into_iter()
@@ -320,6 +428,20 @@ where
     }
 }
 
+fn save_stats<E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, RW, E>,
+    entry: StageStatsEntry,
+) -> anyhow::Result<()> {
+    let mut cursor = tx.cursor(tables::StageStats)?;
+    let id = cursor.last()?.map(|(id, _)| id + 1).unwrap_or_default();
+    Ok(cursor.append(id, entry)?)
+}
+
+fn fsync<E: EnvironmentKind>(db: &MdbxEnvironment<E>) -> Result<(), KvError> {
+    debug!("Flushing database to disk");
+    db.sync_all(true)
+}
+
 pub fn format_duration(dur: Duration, subsec_millis: bool) -> String {
     let mut secs = dur.as_secs();
     let mut minutes = secs / 60;
@@ -1,9 +1,15 @@
 use super::stages::StageId;
-use crate::{kv::mdbx::MdbxTransaction, models::*};
+use crate::{
+    consensus::ValidationError,
+    downloader::DownloadError,
+    kv::{mdbx::MdbxTransaction, KvError},
+    models::*,
+};
 use async_trait::async_trait;
 use auto_impl::auto_impl;
 use mdbx::{EnvironmentKind, RW};
 use std::{fmt::Debug, time::Instant};
+use thiserror::Error;
 
 #[derive(Clone, Copy, Debug)]
 pub struct StageInput {
@@ -35,20 +41,45 @@ pub struct UnwindOutput {
     pub stage_progress: BlockNumber,
 }
 
+/// Failure of a [`Stage`], matched by the staged sync to decide between unwinding and aborting.
+#[derive(Debug, Error)]
+pub enum StageError {
+    #[error("invalid block #{block} ({hash:?}): {error}")]
+    Validation {
+        block: BlockNumber,
+        hash: H256,
+        #[source]
+        error: ValidationError,
+    },
+    #[error(transparent)]
+    Download(#[from] DownloadError),
+    #[error(transparent)]
+    Kv(#[from] KvError),
+    /// Failures of the helpers stages are built from, which report [`anyhow::Error`].
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<::mdbx::Error> for StageError {
+    fn from(e: ::mdbx::Error) -> Self {
+        Self::Kv(e.into())
+    }
+}
+
 #[async_trait]
 #[auto_impl(&mut, Box)]
 pub trait Stage<'db, E>: Send + Sync + Debug
 where
     E: EnvironmentKind,
 {
-    /// ID of the sync stage. Should not be empty and should be unique. It is recommended to prefix it with reverse domain to avoid clashes (`com.example.my-stage`).
+    /// ID of the sync stage, must be declared in [`STAGES`](super::stages::STAGES) registry.
     fn id(&self) -> StageId;
     /// Called when the stage is executed. The main logic of the stage should be here.
     async fn execute<'tx>(
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: StageInput,
-    ) -> anyhow::Result<ExecOutput>
+    ) -> Result<ExecOutput, StageError>
     where
         'db: 'tx;
     /// Called when the stage should be unwound. The unwind logic should be there.
@@ -56,7 +87,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: UnwindInput,
-    ) -> anyhow::Result<UnwindOutput>
+    ) -> Result<UnwindOutput, StageError>
     where
         'db: 'tx;
 }
@@ -1,18 +1,23 @@
 use crate::{
-    kv::{mdbx::MdbxTransaction, tables},
+    kv::{
+        mdbx::MdbxTransaction,
+        tables::{self, PruneMarks},
+        KvError,
+    },
     models::*,
 };
+use anyhow::{ensure, format_err};
 use mdbx::{EnvironmentKind, TransactionKind, RW};
-use std::fmt::Display;
+use std::{collections::HashSet, fmt::Display};
 use tracing::*;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct StageId(pub &'static str);
 
 pub const HEADERS: StageId = StageId("Headers");
 pub const BLOCK_HASHES: StageId = StageId("BlockHashes");
 pub const BODIES: StageId = StageId("Bodies");
-pub const SENDERS: StageId = StageId("SenderRecovery");
+pub const SENDERS: StageId = StageId("Senders");
 pub const TOTAL_GAS_INDEX: StageId = StageId("TotalGasIndex");
 pub const TOTAL_TX_INDEX: StageId = StageId("TotalTxIndex");
 pub const EXECUTION: StageId = StageId("Execution");
@@ -24,8 +29,190 @@ pub const LOG_INDEX: StageId = StageId("LogIndex");
 pub const CALL_TRACES: StageId = StageId("CallTraces");
 pub const TX_LOOKUP: StageId = StageId("TxLookup");
 pub const TX_POOL: StageId = StageId("TxPool");
+pub const PRUNE: StageId = StageId("Prune");
 pub const FINISH: StageId = StageId("Finish");
 
+/// Stages named and measured the same as in Erigon, so the progress saved in the `SyncStage`
+/// table reads the same with either client. The other stages are specific to Martinez.
+pub const ERIGON_STAGES: &[StageId] = &[
+    HEADERS,
+    BLOCK_HASHES,
+    BODIES,
+    SENDERS,
+    EXECUTION,
+    HASH_STATE,
+    INTERMEDIATE_HASHES,
+    ACCOUNT_HISTORY_INDEX,
+    STORAGE_HISTORY_INDEX,
+    LOG_INDEX,
+    CALL_TRACES,
+    TX_LOOKUP,
+    TX_POOL,
+    FINISH,
+];
+
+/// Former names of the stages renamed to match Erigon, progress saved under them is moved
+/// by a migration.
+pub const RENAMED_STAGES: &[(&str, StageId)] = &[("SenderRecovery", SENDERS)];
+
+/// Known stage and the stages whose output it consumes.
+#[derive(Clone, Copy, Debug)]
+pub struct StageInfo {
+    pub id: StageId,
+    pub dependencies: &'static [StageId],
+}
+
+/// Registry of all known stages in pipeline order, each one listed after its dependencies.
+pub const STAGES: &[StageInfo] = &[
+    StageInfo {
+        id: HEADERS,
+        dependencies: &[],
+    },
+    StageInfo {
+        id: TOTAL_GAS_INDEX,
+        dependencies: &[HEADERS],
+    },
+    StageInfo {
+        id: BLOCK_HASHES,
+        dependencies: &[HEADERS],
+    },
+    StageInfo {
+        id: BODIES,
+        dependencies: &[BLOCK_HASHES],
+    },
+    StageInfo {
+        id: TOTAL_TX_INDEX,
+        dependencies: &[BODIES],
+    },
+    StageInfo {
+        id: SENDERS,
+        dependencies: &[BODIES],
+    },
+    StageInfo {
+        id: EXECUTION,
+        dependencies: &[BLOCK_HASHES, SENDERS],
+    },
+    StageInfo {
+        id: HASH_STATE,
+        dependencies: &[EXECUTION],
+    },
+    StageInfo {
+        id: INTERMEDIATE_HASHES,
+        dependencies: &[HASH_STATE],
+    },
+    StageInfo {
+        id: ACCOUNT_HISTORY_INDEX,
+        dependencies: &[EXECUTION],
+    },
+    StageInfo {
+        id: STORAGE_HISTORY_INDEX,
+        dependencies: &[EXECUTION],
+    },
+    StageInfo {
+        id: LOG_INDEX,
+        dependencies: &[EXECUTION],
+    },
+    StageInfo {
+        id: CALL_TRACES,
+        dependencies: &[EXECUTION],
+    },
+    StageInfo {
+        id: TX_LOOKUP,
+        dependencies: &[BODIES],
+    },
+    StageInfo {
+        id: TX_POOL,
+        dependencies: &[SENDERS],
+    },
+    StageInfo {
+        id: PRUNE,
+        dependencies: &[
+            EXECUTION,
+            ACCOUNT_HISTORY_INDEX,
+            STORAGE_HISTORY_INDEX,
+            LOG_INDEX,
+            CALL_TRACES,
+            TX_LOOKUP,
+        ],
+    },
+    StageInfo {
+        id: FINISH,
+        dependencies: &[
+            HEADERS,
+            TOTAL_GAS_INDEX,
+            BLOCK_HASHES,
+            BODIES,
+            TOTAL_TX_INDEX,
+            SENDERS,
+            EXECUTION,
+            HASH_STATE,
+            INTERMEDIATE_HASHES,
+            ACCOUNT_HISTORY_INDEX,
+            STORAGE_HISTORY_INDEX,
+            LOG_INDEX,
+            CALL_TRACES,
+            TX_LOOKUP,
+            TX_POOL,
+            PRUNE,
+        ],
+    },
+];
+
+/// Checks that every stage in `pipeline` comes after its dependencies present in the pipeline.
+/// Dependencies that the pipeline does not run are not checked.
+pub fn validate_order(pipeline: &[StageId]) -> anyhow::Result<()> {
+    let mut seen = HashSet::new();
+    for (index, stage) in pipeline.iter().enumerate() {
+        let info = stage
+            .info()
+            .ok_or_else(|| format_err!("stage {} is not registered", stage))?;
+        ensure!(seen.insert(*stage), "stage {} is added twice", stage);
+        for dependency in info.dependencies {
+            ensure!(
+                seen.contains(dependency) || !pipeline[index..].contains(dependency),
+                "stage {} must come after its dependency {}",
+                stage,
+                dependency
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Indices into `pipeline` in the order stages should be unwound: every stage is unwound
+/// before the stages it depends on.
+pub fn unwind_order(pipeline: &[StageId]) -> anyhow::Result<Vec<usize>> {
+    let dependencies = pipeline
+        .iter()
+        .map(|stage| {
+            Ok(stage
+                .info()
+                .ok_or_else(|| format_err!("stage {} is not registered", stage))?
+                .dependencies)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut order = Vec::with_capacity(pipeline.len());
+    let mut unwound = vec![false; pipeline.len()];
+    while order.len() < pipeline.len() {
+        // Latest stage none of the remaining stages depend on.
+        let next = (0..pipeline.len())
+            .rev()
+            .find(|&index| {
+                !unwound[index]
+                    && !(0..pipeline.len()).any(|other| {
+                        !unwound[other] && dependencies[other].contains(&pipeline[index])
+                    })
+            })
+            .ok_or_else(|| format_err!("stage dependencies are cyclic"))?;
+        unwound[next] = true;
+        order.push(next);
+    }
+
+    Ok(order)
+}
+
 impl AsRef<str> for StageId {
     fn as_ref(&self) -> &str {
         self.0
@@ -45,11 +232,42 @@ impl Display for StageId {
 }
 
 impl StageId {
+    /// Registered stage saved under `name`, former names included.
+    pub fn from_name(name: &str) -> Option<StageId> {
+        STAGES
+            .iter()
+            .map(|info| info.id)
+            .find(|id| id.0 == name)
+            .or_else(|| {
+                RENAMED_STAGES
+                    .iter()
+                    .find(|(old, _)| *old == name)
+                    .map(|&(_, id)| id)
+            })
+    }
+
+    /// Whether Erigon runs the same stage under the same name.
+    pub fn is_erigon_compatible(&self) -> bool {
+        ERIGON_STAGES.contains(self)
+    }
+
+    /// Registry entry of the stage, `None` if the stage is not known.
+    pub fn info(&self) -> Option<&'static StageInfo> {
+        STAGES.iter().find(|info| info.id == *self)
+    }
+
+    /// Stages whose progress this stage must not run ahead of.
+    pub fn dependencies(&self) -> &'static [StageId] {
+        self.info()
+            .map(|info| info.dependencies)
+            .unwrap_or_default()
+    }
+
     #[instrument]
     pub fn get_progress<'db, K, E>(
         &self,
         tx: &MdbxTransaction<'db, K, E>,
-    ) -> anyhow::Result<Option<BlockNumber>>
+    ) -> Result<Option<BlockNumber>, KvError>
     where
         K: TransactionKind,
         E: EnvironmentKind,
@@ -62,10 +280,95 @@ impl StageId {
         &self,
         tx: &MdbxTransaction<'db, RW, E>,
         block: BlockNumber,
-    ) -> anyhow::Result<()>
+    ) -> Result<(), KvError>
     where
         E: EnvironmentKind,
     {
         tx.set(tables::SyncStage, *self, block)
     }
+
+    /// Blocks pruned from the tables this stage fills, empty if nothing was pruned.
+    pub fn get_prune_marks<'db, K, E>(
+        &self,
+        tx: &MdbxTransaction<'db, K, E>,
+    ) -> Result<PruneMarks, KvError>
+    where
+        K: TransactionKind,
+        E: EnvironmentKind,
+    {
+        Ok(tx.get(tables::PruneProgress, *self)?.unwrap_or_default())
+    }
+
+    pub fn save_prune_marks<'db, E>(
+        &self,
+        tx: &MdbxTransaction<'db, RW, E>,
+        marks: PruneMarks,
+    ) -> Result<(), KvError>
+    where
+        E: EnvironmentKind,
+    {
+        tx.set(tables::PruneProgress, *self, marks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_is_ordered() {
+        validate_order(&STAGES.iter().map(|info| info.id).collect::<Vec<_>>()).unwrap();
+    }
+
+    #[test]
+    fn names() {
+        for stage in ERIGON_STAGES {
+            assert!(stage.info().is_some(), "{} is not registered", stage);
+        }
+        assert_eq!(StageId::from_name("Senders"), Some(SENDERS));
+        assert_eq!(StageId::from_name("SenderRecovery"), Some(SENDERS));
+        assert_eq!(StageId::from_name("Unknown"), None);
+        assert!(!PRUNE.is_erigon_compatible());
+    }
+
+    #[test]
+    fn order() {
+        assert!(validate_order(&[HEADERS, BLOCK_HASHES, SENDERS, EXECUTION]).is_ok());
+        assert!(validate_order(&[HEADERS, EXECUTION, SENDERS]).is_err());
+        assert!(validate_order(&[HEADERS, HEADERS]).is_err());
+        assert!(validate_order(&[HEADERS, StageId("Unknown")]).is_err());
+
+        let pipeline = [
+            HEADERS,
+            TOTAL_GAS_INDEX,
+            BLOCK_HASHES,
+            BODIES,
+            SENDERS,
+            EXECUTION,
+            TX_LOOKUP,
+            FINISH,
+        ];
+        let order = unwind_order(&pipeline)
+            .unwrap()
+            .into_iter()
+            .map(|index| pipeline[index])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            [
+                FINISH,
+                TX_LOOKUP,
+                EXECUTION,
+                SENDERS,
+                BODIES,
+                BLOCK_HASHES,
+                TOTAL_GAS_INDEX,
+                HEADERS
+            ]
+        );
+
+        // Execution still has to be unwound before senders it consumes.
+        let order = unwind_order(&[HEADERS, BLOCK_HASHES, EXECUTION, SENDERS]).unwrap();
+        assert_eq!(order, [2, 3, 1, 0]);
+    }
 }
@@ -31,7 +31,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: StageInput,
-    ) -> anyhow::Result<ExecOutput>
+    ) -> Result<ExecOutput, StageError>
     where
         'db: 'tx,
     {
@@ -65,7 +65,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: UnwindInput,
-    ) -> anyhow::Result<UnwindOutput>
+    ) -> Result<UnwindOutput, StageError>
     where
         'db: 'tx,
     {
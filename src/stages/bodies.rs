@@ -0,0 +1,143 @@
+use crate::{
+    accessors::chain,
+    downloader::{
+        body_downloader::{AnnouncedBodies, BodyDownloader},
+        opts::Opts,
+    },
+    kv::{mdbx::*, tables},
+    models::*,
+    sentry::sentry_client_reactor::SentryClientReactorShared,
+    stagedsync::{stage::*, stages::BODIES},
+    StageId,
+};
+use anyhow::format_err;
+use async_trait::async_trait;
+use tracing::*;
+
+/// Download of block bodies
+#[derive(Debug)]
+pub struct BodyDownload {
+    downloader: BodyDownloader,
+    batch_size: u64,
+}
+
+impl BodyDownload {
+    pub fn new(opts: &Opts, sentry: SentryClientReactorShared, announced: AnnouncedBodies) -> Self {
+        Self {
+            downloader: BodyDownloader::new(sentry, announced),
+            batch_size: opts.bodies_batch_size as u64,
+        }
+    }
+}
+
+#[async_trait]
+impl<'db, E> Stage<'db, E> for BodyDownload
+where
+    E: EnvironmentKind,
+{
+    fn id(&self) -> StageId {
+        BODIES
+    }
+
+    async fn execute<'tx>(
+        &mut self,
+        tx: &'tx mut MdbxTransaction<'db, RW, E>,
+        input: StageInput,
+    ) -> Result<ExecOutput, StageError>
+    where
+        'db: 'tx,
+    {
+        let past_progress = input.stage_progress.unwrap_or(BlockNumber(0));
+        let target = input
+            .previous_stage
+            .map(|(_, b)| b)
+            .unwrap_or(past_progress);
+        if target <= past_progress {
+            return Ok(ExecOutput::Progress {
+                stage_progress: past_progress,
+                done: true,
+            });
+        }
+        let highest_block = std::cmp::min(target, past_progress + self.batch_size);
+
+        let mut headers = Vec::with_capacity((highest_block.0 - past_progress.0) as usize);
+        for number in past_progress.0 + 1..=highest_block.0 {
+            let number = BlockNumber(number);
+            let hash = tx
+                .get(tables::CanonicalHeader, number)?
+                .ok_or_else(|| format_err!("No canonical hash for block {}", number))?;
+            let header = tx
+                .get(tables::Header, (number, hash))?
+                .ok_or_else(|| format_err!("No header for block {}/{:?}", number, hash))?;
+            headers.push((number, hash, header));
+        }
+
+        let keys = headers
+            .iter()
+            .map(|&(number, hash, _)| (number, hash))
+            .collect::<Vec<_>>();
+        let bodies = self.downloader.download(headers).await?;
+
+        let prev_hash = tx
+            .get(tables::CanonicalHeader, past_progress)?
+            .ok_or_else(|| format_err!("No canonical hash for block {}", past_progress))?;
+        let prev_body = chain::storage_body::read(tx, prev_hash, past_progress)?
+            .ok_or_else(|| format_err!("No body for block {}", past_progress))?;
+        let mut next_tx_id = prev_body.base_tx_id + prev_body.tx_amount;
+
+        let mut body_cur = tx.cursor(tables::BlockBody)?;
+        let mut tx_cur = tx.cursor(tables::BlockTransaction)?;
+        for ((number, hash), body) in keys.into_iter().zip(bodies) {
+            body_cur.append(
+                (number, hash),
+                BodyForStorage {
+                    base_tx_id: next_tx_id,
+                    tx_amount: body.transactions.len() as u64,
+                    uncles: chain::ommers::write(tx, &body.ommers)?,
+                },
+            )?;
+
+            for transaction in body.transactions {
+                tx_cur.append(next_tx_id, transaction)?;
+                next_tx_id.0 += 1;
+            }
+        }
+
+        info!("Downloaded bodies up to block {}", highest_block);
+
+        Ok(ExecOutput::Progress {
+            stage_progress: highest_block,
+            done: highest_block == target,
+        })
+    }
+
+    async fn unwind<'tx>(
+        &mut self,
+        tx: &'tx mut MdbxTransaction<'db, RW, E>,
+        input: UnwindInput,
+    ) -> Result<UnwindOutput, StageError>
+    where
+        'db: 'tx,
+    {
+        let mut block_body_cur = tx.cursor(tables::BlockBody)?;
+        let mut block_tx_cur = tx.cursor(tables::BlockTransaction)?;
+        while let Some(((block_num, _), body)) = block_body_cur.last()? {
+            if block_num <= input.unwind_to {
+                break;
+            }
+
+            block_body_cur.delete_current()?;
+            chain::ommers::delete(tx, &body.uncles)?;
+
+            for i in 0..body.tx_amount {
+                if block_tx_cur.seek_exact(body.base_tx_id + i)?.is_some() {
+                    block_tx_cur.delete_current()?;
+                }
+            }
+        }
+
+        Ok(UnwindOutput {
+            stage_progress: input.unwind_to,
+        })
+    }
+}
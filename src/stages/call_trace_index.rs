@@ -1,5 +1,5 @@
 use crate::{
-    bitmapdb::{self, CHUNK_LIMIT},
+    bitmapdb,
     etl::collector::*,
     kv::{
         mdbx::*,
@@ -41,7 +41,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: StageInput,
-    ) -> anyhow::Result<ExecOutput>
+    ) -> Result<ExecOutput, StageError>
     where
         'db: 'tx,
     {
@@ -74,8 +74,12 @@ where
 
         let mut highest_block = starting_block;
         let mut last_flush = starting_block;
-        while let Some((block_number, CallTraceSetEntry { address, from, to })) =
-            walker.next().transpose()?
+        while let Some((
+            block_number,
+            CallTraceSetEntry {
+                address, from, to, ..
+            },
+        )) = walker.next().transpose()?
         {
             if block_number > max_block {
                 break;
@@ -117,7 +121,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: UnwindInput,
-    ) -> anyhow::Result<UnwindOutput>
+    ) -> Result<UnwindOutput, StageError>
     where
         'db: 'tx,
     {
@@ -186,28 +190,9 @@ where
             err => Err(err),
         })
     {
-        let (address, mut total_bitmap) = res?;
-
-        if !total_bitmap.is_empty() {
-            if let Some((_, last_bitmap)) = cursor.seek_exact(BitmapKey {
-                inner: address,
-                block_number: BlockNumber(u64::MAX),
-            })? {
-                total_bitmap |= last_bitmap;
-            }
+        let (address, bitmap) = res?;
 
-            for (block_number, bitmap) in
-                bitmapdb::Chunks::new(total_bitmap, CHUNK_LIMIT).with_keys()
-            {
-                cursor.put(
-                    BitmapKey {
-                        inner: address,
-                        block_number,
-                    },
-                    bitmap,
-                )?;
-            }
-        }
+        bitmapdb::append(cursor, address, bitmap)?;
     }
 
     Ok(())
@@ -222,35 +207,7 @@ where
     T: Table<Key = BitmapKey<Address>, Value = croaring::Treemap>,
 {
     for address in addresses {
-        let mut bm = cursor
-            .seek_exact(BitmapKey {
-                inner: address,
-                block_number: BlockNumber(u64::MAX),
-            })?
-            .map(|(_, bm)| bm);
-
-        while let Some(b) = bm {
-            cursor.delete_current()?;
-
-            let new_bm = b
-                .iter()
-                .take_while(|&v| v <= *unwind_to)
-                .collect::<croaring::Treemap>();
-
-            if new_bm.cardinality() > 0 {
-                cursor.upsert(
-                    BitmapKey {
-                        inner: address,
-                        block_number: BlockNumber(u64::MAX),
-                    },
-                    new_bm,
-                )?;
-            }
-
-            bm = cursor.prev()?.and_then(
-                |(BitmapKey { inner, .. }, b)| if inner == address { Some(b) } else { None },
-            );
-        }
+        bitmapdb::truncate(cursor, address, unwind_to)?;
     }
 
     Ok(())
@@ -278,6 +235,7 @@ mod tests {
                     address: address.into(),
                     from: i % 2 == 0,
                     to: i % 2 == 1,
+                    stats: None,
                 },
             )
             .unwrap();
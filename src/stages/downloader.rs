@@ -1,7 +1,7 @@
 use crate::{
     downloader::{
-        sentry_status_provider::SentryStatusProvider, ui::ui_system::UISystem, HeadersDownloader,
-        HeadersDownloaderRunState,
+        opts::Opts, sentry_status_provider::SentryStatusProvider, ui::ui_system::UISystem,
+        HeaderSliceStore, HeadersDownloader, HeadersDownloaderRunState,
     },
     kv::mdbx::*,
     models::BlockNumber,
@@ -11,7 +11,8 @@ use crate::{
 };
 use async_trait::async_trait;
 use std::sync::Arc;
-use tokio::sync::Mutex as AsyncMutex;
+use tokio::sync::{Mutex as AsyncMutex, Notify};
+use tracing::*;
 
 /// Download of headers
 #[derive(Debug)]
@@ -20,29 +21,48 @@ pub struct HeaderDownload {
     batch_size: usize,
     sentry_status_provider: SentryStatusProvider,
     previous_run_state: Arc<AsyncMutex<Option<HeadersDownloaderRunState>>>,
+    restart_signal: Option<Arc<Notify>>,
 }
 
 impl HeaderDownload {
     pub fn new(
         chain_config: ChainConfig,
-        mem_limit: usize,
-        batch_size: usize,
+        opts: &Opts,
         sentry: SentryClientReactorShared,
         sentry_status_provider: SentryStatusProvider,
     ) -> anyhow::Result<Self> {
         let verifier = crate::downloader::header_slice_verifier::make_ethash_verifier();
 
-        let downloader = HeadersDownloader::new(chain_config, verifier, mem_limit, sentry)?;
+        let downloader = HeadersDownloader::new(
+            chain_config,
+            verifier,
+            opts.headers_mem_limit(),
+            opts.headers_request_batching(),
+            sentry,
+        )?;
 
         let instance = Self {
             downloader,
-            batch_size,
+            batch_size: opts.headers_batch_size,
             sentry_status_provider,
             previous_run_state: Arc::new(AsyncMutex::new(None)),
+            restart_signal: None,
         };
         Ok(instance)
     }
 
+    /// When notified, the running download is abandoned and started over from the stage progress.
+    pub fn set_restart_signal(&mut self, signal: Arc<Notify>) -> &mut Self {
+        self.restart_signal = Some(signal);
+        self
+    }
+
+    /// Downloaded slices are kept in `slice_store`, and picked up again after a restart.
+    pub fn set_slice_store(&mut self, slice_store: Arc<HeaderSliceStore>) -> &mut Self {
+        self.downloader.set_slice_store(slice_store);
+        self
+    }
+
     async fn load_previous_run_state(&self) -> Option<HeadersDownloaderRunState> {
         self.previous_run_state.lock().await.clone()
     }
@@ -52,6 +72,10 @@ impl HeaderDownload {
     }
 }
 
+async fn stop_ui(ui_system: &AsyncMutex<UISystem>) -> anyhow::Result<()> {
+    ui_system.try_lock()?.stop().await
+}
+
 #[async_trait]
 impl<'db, E> Stage<'db, E> for HeaderDownload
 where
@@ -65,7 +89,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: StageInput,
-    ) -> anyhow::Result<ExecOutput>
+    ) -> Result<ExecOutput, StageError>
     where
         'db: 'tx,
     {
@@ -88,18 +112,30 @@ where
         ui_system.start()?;
         let ui_system = Arc::new(AsyncMutex::new(ui_system));
 
-        let report = self
-            .downloader
-            .run(
-                tx,
-                start_block_num,
-                self.batch_size,
-                previous_run_state,
-                ui_system.clone(),
-            )
-            .await?;
+        let run = self.downloader.run(
+            tx,
+            start_block_num,
+            self.batch_size,
+            previous_run_state,
+            ui_system.clone(),
+        );
+        let report = match self.restart_signal.clone() {
+            Some(restart_signal) => tokio::select! {
+                report = run => report?,
+                _ = restart_signal.notified() => {
+                    stop_ui(&ui_system).await?;
+                    warn!("HeaderDownload: restarting the downloader from {}", past_progress);
+                    *self.previous_run_state.lock().await = None;
+                    return Ok(ExecOutput::Progress {
+                        stage_progress: past_progress,
+                        done: false,
+                    });
+                }
+            },
+            None => run.await?,
+        };
 
-        ui_system.try_lock()?.stop().await?;
+        stop_ui(&ui_system).await?;
 
         if let Some(unwind_request) = &report.run_state.unwind_request {
             let unwind_to = unwind_request.unwind_to_block_num;
@@ -128,7 +164,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: UnwindInput,
-    ) -> anyhow::Result<UnwindOutput>
+    ) -> Result<UnwindOutput, StageError>
     where
         'db: 'tx,
     {
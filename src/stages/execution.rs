@@ -1,8 +1,10 @@
 use crate::{
-    accessors,
-    consensus::engine_factory,
+    accessors::{self, code_cache::CodeCache, header_cache::HeaderCache},
+    changeset,
+    consensus::{engine_factory, ValidationError},
     execution::{
         analysis_cache::AnalysisCache,
+        block_hashes::BlockHashes,
         processor::ExecutionProcessor,
         tracer::{CallTracer, CallTracerFlags},
     },
@@ -12,13 +14,21 @@ use crate::{
         tables::{self, CallTraceSetEntry},
     },
     models::*,
-    stagedsync::{format_duration, stage::*, stages::EXECUTION},
+    stagedsync::{
+        format_duration,
+        stage::*,
+        stages::{ACCOUNT_HISTORY_INDEX, EXECUTION, STORAGE_HISTORY_INDEX},
+    },
     upsert_storage_value, Buffer,
 };
-use anyhow::{format_err, Context};
+use anyhow::format_err;
 use async_trait::async_trait;
 use mdbx::{EnvironmentKind, RW};
-use std::time::{Duration, Instant};
+use std::{
+    collections::HashSet,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use tracing::*;
 
 /// Execution of blocks through EVM
@@ -30,9 +40,19 @@ pub struct Execution {
     pub batch_until: Option<BlockNumber>,
     pub commit_every: Option<Duration>,
     pub prune_from: BlockNumber,
+    /// Record value transferred and gas used per address in call traces.
+    pub call_trace_stats: bool,
+    /// Shared cache for header reads, e.g. by BLOCKHASH.
+    pub header_cache: Option<Arc<HeaderCache>>,
+    /// Shared cache for contract code reads.
+    pub code_cache: Option<Arc<CodeCache>>,
 }
 
 #[allow(clippy::too_many_arguments)]
+#[instrument(
+    skip_all,
+    fields(from = %starting_block, max_block = %max_block, batch_size = batch_size)
+)]
 fn execute_batch_of_blocks<E: EnvironmentKind>(
     tx: &MdbxTransaction<'_, RW, E>,
     chain_config: ChainSpec,
@@ -44,10 +64,20 @@ fn execute_batch_of_blocks<E: EnvironmentKind>(
     starting_block: BlockNumber,
     first_started_at: (Instant, Option<BlockNumber>),
     prune_from: BlockNumber,
-) -> anyhow::Result<BlockNumber> {
+    call_trace_stats: bool,
+    header_cache: Option<Arc<HeaderCache>>,
+    code_cache: Option<Arc<CodeCache>>,
+) -> Result<BlockNumber, StageError> {
     let mut buffer = Buffer::new(tx, prune_from, None);
-    let mut consensus_engine = engine_factory(chain_config.clone())?;
+    if let Some(header_cache) = header_cache {
+        buffer.set_header_cache(header_cache);
+    }
+    if let Some(code_cache) = &code_cache {
+        buffer.set_code_cache(code_cache.clone());
+    }
+    let mut consensus_engine = engine_factory(&chain_config)?;
     let mut analysis_cache = AnalysisCache::default();
+    let mut block_hashes = BlockHashes::default();
 
     let mut block_number = starting_block;
     let mut gas_since_start = 0;
@@ -62,23 +92,26 @@ fn execute_batch_of_blocks<E: EnvironmentKind>(
         .unwrap();
     let mut last_message = Instant::now();
     let mut printed_at_least_once = false;
-    loop {
-        let block_hash = tx
-            .get(tables::CanonicalHeader, block_number)?
-            .ok_or_else(|| format_err!("No canonical hash found for block {}", block_number))?;
-        let header = tx
-            .get(tables::Header, (block_number, block_hash))?
-            .ok_or_else(|| format_err!("Header not found: {}/{:?}", block_number, block_hash))?
-            .into();
-        let block = accessors::chain::block_body::read_with_senders(tx, block_hash, block_number)?
-            .ok_or_else(|| {
-                format_err!("Block body not found: {}/{:?}", block_number, block_hash)
-            })?;
-
-        let block_spec = chain_config.collect_block_spec(block_number);
-
-        let mut call_tracer = CallTracer::default();
-        let receipts = ExecutionProcessor::new(
+    for res in accessors::chain::canonical_blocks_with_senders(tx, starting_block..=max_block)? {
+        let (block_hash, header, block) = res?;
+        let header = PartialHeader::from(header);
+        block_number = header.number;
+
+        let block_spec = chain_config.collect_block_spec(block_number, header.timestamp);
+
+        let _block_span = debug_span!(
+            "block",
+            number = %block_number,
+            txs = block.transactions.len()
+        )
+        .entered();
+
+        let mut call_tracer = if call_trace_stats {
+            CallTracer::with_stats()
+        } else {
+            CallTracer::default()
+        };
+        let mut processor = ExecutionProcessor::new(
             &mut buffer,
             Some(&mut call_tracer),
             &mut analysis_cache,
@@ -86,21 +119,36 @@ fn execute_batch_of_blocks<E: EnvironmentKind>(
             &header,
             &block,
             &block_spec,
-        )
-        .execute_and_write_block()
-        .with_context(|| {
-            format!(
-                "Failed to execute block #{} ({:?})",
-                block_number, block_hash
-            )
+        );
+        processor.set_block_hashes(&mut block_hashes);
+        let receipts = processor.execute_and_write_block().map_err(|e| {
+            match e.downcast::<ValidationError>() {
+                Ok(error) => StageError::Validation {
+                    block: block_number,
+                    hash: block_hash,
+                    error,
+                },
+                Err(e) => StageError::Internal(e.context(format!(
+                    "Failed to execute block #{} ({:?})",
+                    block_number, block_hash
+                ))),
+            }
         })?;
 
         buffer.insert_receipts(block_number, receipts);
 
         {
             let mut c = tx.cursor(tables::CallTraceSet)?;
-            for (address, CallTracerFlags { from, to }) in call_tracer.into_sorted_iter() {
-                c.append_dup(header.number, CallTraceSetEntry { address, from, to })?;
+            for (address, CallTracerFlags { from, to, stats }) in call_tracer.into_sorted_iter() {
+                c.append_dup(
+                    header.number,
+                    CallTraceSetEntry {
+                        address,
+                        from,
+                        to,
+                        stats,
+                    },
+                )?;
             }
         }
 
@@ -162,6 +210,9 @@ fn execute_batch_of_blocks<E: EnvironmentKind>(
                     )
                 }
             );
+            if let Some(code_cache) = &code_cache {
+                debug!("Code cache hit rate: {}", code_cache.stats());
+            }
             printed_at_least_once = true;
             last_message = now;
             gas_since_last_message = 0;
@@ -170,8 +221,6 @@ fn execute_batch_of_blocks<E: EnvironmentKind>(
         if end_of_batch {
             break;
         }
-
-        block_number.0 += 1;
     }
 
     buffer.write_to_db()?;
@@ -192,7 +241,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: StageInput,
-    ) -> anyhow::Result<ExecOutput>
+    ) -> Result<ExecOutput, StageError>
     where
         'db: 'tx,
     {
@@ -222,8 +271,22 @@ where
                 starting_block,
                 input.first_started_at,
                 self.prune_from,
+                self.call_trace_stats,
+                self.header_cache.clone(),
+                self.code_cache.clone(),
             )?;
 
+            if starting_block < self.prune_from {
+                // Changesets of these blocks were not written.
+                let pruned_to = self.prune_from.min(executed_to + 1);
+                for stage in [ACCOUNT_HISTORY_INDEX, STORAGE_HISTORY_INDEX] {
+                    let marks = stage.get_prune_marks(tx)?.extend(starting_block, pruned_to);
+                    stage.save_prune_marks(tx, marks)?;
+                }
+            }
+
+            accessors::chain::chain_head::set_executed(tx, executed_to)?;
+
             let done = executed_to == max_block || self.exit_after_batch;
 
             ExecOutput::Progress {
@@ -242,50 +305,75 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: UnwindInput,
-    ) -> anyhow::Result<UnwindOutput>
+    ) -> Result<UnwindOutput, StageError>
     where
         'db: 'tx,
     {
-        info!("Unwinding accounts");
-        let mut account_cursor = tx.cursor(tables::Account)?;
-
-        let mut account_cs_cursor = tx.cursor(tables::AccountChangeSet)?;
+        for stage in [ACCOUNT_HISTORY_INDEX, STORAGE_HISTORY_INDEX] {
+            let marks = stage.get_prune_marks(tx)?;
+            if !marks.is_empty() && input.unwind_to.0 + 1 < marks.to.0 {
+                return Err(format_err!(
+                    "cannot unwind to block {}, changesets up to block {} are pruned",
+                    input.unwind_to,
+                    marks.to.0 - 1
+                )
+                .into());
+            }
+        }
 
-        while let Some((block_number, tables::AccountChange { address, account })) =
-            account_cs_cursor.last()?
+        info!("Unwinding state");
         {
-            if block_number <= input.unwind_to {
-                break;
+            let mut account_cursor = tx.cursor(tables::Account)?;
+            let mut storage_cursor = tx.cursor(tables::Storage)?;
+
+            // Changesets hold values from before the block, so the first change of an entry
+            // past the unwind point holds the value to restore.
+            let mut accounts = HashSet::new();
+            let mut slots = HashSet::new();
+            for changes in changeset::walk(tx, input.unwind_to + 1..=BlockNumber(u64::MAX))? {
+                let changes = changes?;
+                for tables::AccountChange { address, account } in changes.accounts {
+                    if !accounts.insert(address) {
+                        continue;
+                    }
+
+                    if let Some(account) = account {
+                        account_cursor.put(address, account)?;
+                    } else if account_cursor.seek_exact(address)?.is_some() {
+                        account_cursor.delete_current()?;
+                    }
+                }
+                for (address, tables::StorageChange { location, value }) in changes.storage {
+                    if slots.insert((address, location)) {
+                        upsert_storage_value(
+                            &mut storage_cursor,
+                            address,
+                            h256_to_u256(location),
+                            value,
+                        )?;
+                    }
+                }
             }
+        }
 
-            if let Some(account) = account {
-                account_cursor.put(address, account)?;
-            } else if account_cursor.seek(address)?.is_some() {
-                account_cursor.delete_current()?;
+        info!("Unwinding changesets");
+        let mut account_cs_cursor = tx.cursor(tables::AccountChangeSet)?;
+        while let Some((block_number, _)) = account_cs_cursor.last()? {
+            if block_number <= input.unwind_to {
+                break;
             }
 
             account_cs_cursor.delete_current()?;
         }
 
-        info!("Unwinding storage");
-        let mut storage_cursor = tx.cursor(tables::Storage)?;
-
         let mut storage_cs_cursor = tx.cursor(tables::StorageChangeSet)?;
-
-        while let Some((
-            tables::StorageChangeKey {
-                block_number,
-                address,
-            },
-            tables::StorageChange { location, value },
-        )) = storage_cs_cursor.last()?
+        while let Some((tables::StorageChangeKey { block_number, .. }, _)) =
+            storage_cs_cursor.last()?
         {
             if block_number <= input.unwind_to {
                 break;
             }
 
-            upsert_storage_value(&mut storage_cursor, address, h256_to_u256(location), value)?;
-
             storage_cs_cursor.delete_current()?;
         }
 
@@ -299,6 +387,16 @@ where
             log_cursor.delete_current()?;
         }
 
+        info!("Unwinding receipts");
+        let mut receipt_cursor = tx.cursor(tables::Receipt)?;
+        while let Some((block_number, _)) = receipt_cursor.last()? {
+            if block_number <= input.unwind_to {
+                break;
+            }
+
+            receipt_cursor.delete_current()?;
+        }
+
         info!("Unwinding call trace sets");
         let mut call_trace_set_cursor = tx.cursor(tables::CallTraceSet)?;
         while let Some((block_number, _)) = call_trace_set_cursor.last()? {
@@ -309,6 +407,8 @@ where
             call_trace_set_cursor.delete_current_duplicates()?;
         }
 
+        accessors::chain::chain_head::set_executed(tx, input.unwind_to)?;
+
         Ok(UnwindOutput {
             stage_progress: input.unwind_to,
         })
@@ -1,5 +1,6 @@
 use crate::{
-    crypto::keccak256,
+    changeset,
+    crypto::{keccak256, keccak256_batch},
     etl::collector::*,
     kv::{mdbx::*, tables},
     models::*,
@@ -10,11 +11,54 @@ use crate::{
 use anyhow::format_err;
 use async_trait::async_trait;
 use mdbx::{EnvironmentKind, RW};
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 use tempfile::TempDir;
 use tokio::pin;
 use tracing::*;
 
+/// Entries hashed at once during the clean promotion.
+const HASH_BATCH_SIZE: usize = 65536;
+
+fn push_hashed_accounts(
+    collector: &mut TableCollector<'_, tables::HashedAccount>,
+    batch: &mut Vec<(Address, Account)>,
+) {
+    let hashed_addresses = keccak256_batch(
+        &batch
+            .iter()
+            .map(|(address, _)| *address)
+            .collect::<Vec<_>>(),
+    );
+    for (hashed_address, (_, account)) in hashed_addresses.into_iter().zip(batch.drain(..)) {
+        collector.push(hashed_address, account);
+    }
+}
+
+fn push_hashed_storage(
+    collector: &mut TableCollector<'_, tables::HashedStorage>,
+    batch: &mut Vec<(Address, H256, U256)>,
+) {
+    let hashed_addresses = keccak256_batch(
+        &batch
+            .iter()
+            .map(|(address, _, _)| *address)
+            .collect::<Vec<_>>(),
+    );
+    let hashed_locations = keccak256_batch(
+        &batch
+            .iter()
+            .map(|(_, location, _)| *location)
+            .collect::<Vec<_>>(),
+    );
+    for ((hashed_address, hashed_location), (_, _, value)) in hashed_addresses
+        .into_iter()
+        .zip(hashed_locations)
+        .zip(batch.drain(..))
+    {
+        collector.push(hashed_address, (hashed_location, value));
+    }
+}
+
 pub fn promote_clean_accounts<'db, E>(
     txn: &MdbxTransaction<'db, RW, E>,
     temp_dir: &TempDir,
@@ -32,14 +76,19 @@ where
     let mut i = 0;
     let walker = src.walk(None);
     pin!(walker);
+    let mut batch = Vec::with_capacity(HASH_BATCH_SIZE);
     while let Some((address, account)) = walker.next().transpose()? {
-        collector_account.push(keccak256(address), account);
+        batch.push((address, account));
+        if batch.len() == HASH_BATCH_SIZE {
+            push_hashed_accounts(&mut collector_account, &mut batch);
+        }
 
         i += 1;
         if i % 5_000_000 == 0 {
             debug!("Converted {} entries", i);
         }
     }
+    push_hashed_accounts(&mut collector_account, &mut batch);
 
     debug!("Loading hashed entries");
     let mut dst = txn.cursor(tables::HashedAccount.erased())?;
@@ -65,14 +114,19 @@ where
     let mut i = 0;
     let walker = src.walk(None);
     pin!(walker);
+    let mut batch = Vec::with_capacity(HASH_BATCH_SIZE);
     while let Some((address, (location, value))) = walker.next().transpose()? {
-        collector_storage.push(keccak256(address), (keccak256(location), value));
+        batch.push((address, location, value));
+        if batch.len() == HASH_BATCH_SIZE {
+            push_hashed_storage(&mut collector_storage, &mut batch);
+        }
 
         i += 1;
         if i % 5_000_000 == 0 {
             debug!("Converted {} entries", i);
         }
     }
+    push_hashed_storage(&mut collector_storage, &mut batch);
 
     debug!("Loading hashed entries");
     let mut dst = txn.cursor(tables::HashedStorage.erased())?;
@@ -172,7 +226,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: StageInput,
-    ) -> anyhow::Result<ExecOutput>
+    ) -> Result<ExecOutput, StageError>
     where
         'db: 'tx,
     {
@@ -211,53 +265,40 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: UnwindInput,
-    ) -> anyhow::Result<UnwindOutput>
+    ) -> Result<UnwindOutput, StageError>
     where
         'db: 'tx,
     {
-        info!("Unwinding hashed accounts");
+        info!("Unwinding hashed state");
         let mut hashed_account_cur = tx.cursor(tables::HashedAccount)?;
-        let walker = tx.cursor(tables::AccountChangeSet)?.walk_back(None);
-        pin!(walker);
-        while let Some((block_number, tables::AccountChange { address, account })) =
-            walker.next().transpose()?
-        {
-            if block_number > input.unwind_to {
-                let hashed_address = keccak256(address);
+        let mut hashed_storage_cur = tx.cursor(tables::HashedStorage)?;
+
+        // The first change of an entry past the unwind point holds the value to restore.
+        let mut accounts = HashSet::new();
+        let mut slots = HashSet::new();
+        for changes in changeset::walk(tx, input.unwind_to + 1..=BlockNumber(u64::MAX))? {
+            let changes = changes?;
+            for tables::AccountChange { address, account } in changes.accounts {
+                if !accounts.insert(address) {
+                    continue;
+                }
 
+                let hashed_address = keccak256(address);
                 if let Some(account) = account {
                     hashed_account_cur.put(hashed_address, account)?
-                } else if hashed_account_cur.seek(hashed_address)?.is_some() {
+                } else if hashed_account_cur.seek_exact(hashed_address)?.is_some() {
                     hashed_account_cur.delete_current()?
                 }
-            } else {
-                break;
             }
-        }
-
-        info!("Unwinding hashed storage");
-        let mut hashed_storage_cur = tx.cursor(tables::HashedStorage)?;
-        let walker = tx.cursor(tables::StorageChangeSet)?.walk_back(None);
-        pin!(walker);
-        while let Some((
-            tables::StorageChangeKey {
-                block_number,
-                address,
-            },
-            tables::StorageChange { location, value },
-        )) = walker.next().transpose()?
-        {
-            if block_number > input.unwind_to {
-                let hashed_address = keccak256(address);
-                let hashed_location = keccak256(location);
-                upsert_hashed_storage_value(
-                    &mut hashed_storage_cur,
-                    hashed_address,
-                    hashed_location,
-                    value,
-                )?;
-            } else {
-                break;
+            for (address, tables::StorageChange { location, value }) in changes.storage {
+                if slots.insert((address, location)) {
+                    upsert_hashed_storage_value(
+                        &mut hashed_storage_cur,
+                        keccak256(address),
+                        keccak256(location),
+                        value,
+                    )?;
+                }
             }
         }
 
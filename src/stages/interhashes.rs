@@ -2,7 +2,7 @@ use crate::{
     kv::{mdbx::*, tables},
     models::*,
     stagedsync::{
-        stage::{ExecOutput, Stage, StageInput, UnwindInput, UnwindOutput},
+        stage::{ExecOutput, Stage, StageError, StageInput, UnwindInput, UnwindOutput},
         stages::*,
     },
     stages::stage_util::should_do_clean_promotion,
@@ -44,7 +44,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: StageInput,
-    ) -> anyhow::Result<ExecOutput>
+    ) -> Result<ExecOutput, StageError>
     where
         'db: 'tx,
     {
@@ -103,7 +103,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: UnwindInput,
-    ) -> anyhow::Result<UnwindOutput>
+    ) -> Result<UnwindOutput, StageError>
     where
         'db: 'tx,
     {
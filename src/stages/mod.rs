@@ -1,9 +1,11 @@
 mod block_hashes;
+mod bodies;
 mod call_trace_index;
 mod downloader;
 mod execution;
 mod hashstate;
 mod interhashes;
+mod prune;
 mod sender_recovery;
 mod stage_util;
 mod total_gas_index;
@@ -11,11 +13,13 @@ mod total_tx_index;
 mod tx_lookup;
 
 pub use block_hashes::BlockHashes;
+pub use bodies::BodyDownload;
 pub use call_trace_index::CallTraceIndex;
 pub use downloader::HeaderDownload;
 pub use execution::Execution;
 pub use hashstate::{promote_clean_accounts, promote_clean_storage, HashState};
 pub use interhashes::Interhashes;
+pub use prune::{Prune, PruneConfig};
 pub use sender_recovery::SenderRecovery;
 pub use total_gas_index::TotalGasIndex;
 pub use total_tx_index::TotalTxIndex;
@@ -0,0 +1,251 @@
+use crate::{
+    accessors, bitmapdb,
+    kv::{mdbx::*, tables, traits::*},
+    models::*,
+    stagedsync::{stage::*, stages::*},
+    StageId,
+};
+use anyhow::format_err;
+use async_trait::async_trait;
+use mdbx::EnvironmentKind;
+use tracing::*;
+
+/// Number of most recent blocks to keep the data of, for each index. `None` keeps all blocks.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PruneConfig {
+    /// Account and storage changesets and their history indices, which historical state is
+    /// read from. Execution can not be unwound deeper than that.
+    pub history: Option<u64>,
+    /// Receipts and logs.
+    pub receipts: Option<u64>,
+    /// Transaction hash to block number lookups.
+    pub tx_lookup: Option<u64>,
+    /// Addresses called in each block.
+    pub call_traces: Option<u64>,
+}
+
+impl PruneConfig {
+    /// Stages whose output is pruned, with the number of blocks to keep.
+    fn distances(&self) -> [(StageId, Option<u64>); 5] {
+        [
+            (ACCOUNT_HISTORY_INDEX, self.history),
+            (STORAGE_HISTORY_INDEX, self.history),
+            (EXECUTION, self.receipts),
+            (TX_LOOKUP, self.tx_lookup),
+            (CALL_TRACES, self.call_traces),
+        ]
+    }
+}
+
+/// Deletes entries of blocks before `to` from a table keyed by block first, returning the
+/// first block whose entries were deleted.
+fn prune_table<E, T>(
+    tx: &MdbxTransaction<'_, RW, E>,
+    table: T,
+    to: BlockNumber,
+    block_number: impl Fn(&T::Key) -> BlockNumber,
+) -> anyhow::Result<Option<BlockNumber>>
+where
+    E: EnvironmentKind,
+    T: Table,
+    T::Key: TableDecode,
+{
+    let mut cursor = tx.cursor(table)?;
+    let mut first = None;
+    while let Some((key, _)) = cursor.first()? {
+        let block_number = (block_number)(&key);
+        if block_number >= to {
+            break;
+        }
+        first.get_or_insert(block_number);
+        cursor.delete_current()?;
+    }
+
+    Ok(first)
+}
+
+/// Lookups are keyed by hash, so they are found through the transactions of each block.
+fn prune_tx_lookup<E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, RW, E>,
+    from: BlockNumber,
+    to: BlockNumber,
+) -> anyhow::Result<Option<BlockNumber>> {
+    if from >= to {
+        return Ok(None);
+    }
+
+    for res in accessors::chain::canonical_blocks(tx, from..=BlockNumber(to.0 - 1))? {
+        let (block_number, hash) = res?;
+        let body = accessors::chain::block_body::read_without_senders(tx, hash, block_number)?
+            .ok_or_else(|| format_err!("block body {}/{:?} not found", block_number, hash))?;
+        for transaction in &body.transactions {
+            tx.del(tables::BlockTransactionLookup, transaction.hash(), None)?;
+        }
+    }
+
+    Ok(Some(from))
+}
+
+/// Deletes data of the blocks which fell out of the configured distances, and records
+/// what is gone in [`tables::PruneProgress`].
+#[derive(Debug)]
+pub struct Prune {
+    pub config: PruneConfig,
+}
+
+#[async_trait]
+impl<'db, E> Stage<'db, E> for Prune
+where
+    E: EnvironmentKind,
+{
+    fn id(&self) -> StageId {
+        PRUNE
+    }
+
+    async fn execute<'tx>(
+        &mut self,
+        tx: &'tx mut MdbxTransaction<'db, RW, E>,
+        input: StageInput,
+    ) -> Result<ExecOutput, StageError>
+    where
+        'db: 'tx,
+    {
+        let max_block = input
+            .previous_stage
+            .map(|(_, v)| v)
+            .ok_or_else(|| format_err!("Cannot be the first stage"))?;
+
+        for (stage, distance) in self.config.distances() {
+            let Some(distance) = distance else {
+                continue;
+            };
+            let prune_to = BlockNumber((max_block.0 + 1).saturating_sub(distance));
+            let marks = stage.get_prune_marks(tx)?;
+            if !marks.is_empty() && prune_to <= marks.to {
+                continue;
+            }
+
+            let first_pruned = match stage {
+                ACCOUNT_HISTORY_INDEX => {
+                    bitmapdb::prune(&mut tx.cursor(tables::AccountHistory)?, prune_to)?;
+                    prune_table(tx, tables::AccountChangeSet, prune_to, |&block| block)?
+                }
+                STORAGE_HISTORY_INDEX => {
+                    bitmapdb::prune(&mut tx.cursor(tables::StorageHistory)?, prune_to)?;
+                    prune_table(tx, tables::StorageChangeSet, prune_to, |key| {
+                        key.block_number
+                    })?
+                }
+                EXECUTION => {
+                    let receipts = prune_table(tx, tables::Receipt, prune_to, |&block| block)?;
+                    let logs = prune_table(tx, tables::Log, prune_to, |&(block, _)| block)?;
+                    receipts.into_iter().chain(logs).min()
+                }
+                TX_LOOKUP => prune_tx_lookup(tx, marks.to, prune_to)?,
+                CALL_TRACES => prune_table(tx, tables::CallTraceSet, prune_to, |&block| block)?,
+                other => unreachable!("{} is not pruned", other),
+            };
+
+            if let Some(first_pruned) = first_pruned {
+                let marks = marks.extend(first_pruned, prune_to);
+                debug!("Pruned {} up to block {}", stage, marks.to);
+                stage.save_prune_marks(tx, marks)?;
+            }
+        }
+
+        Ok(ExecOutput::Progress {
+            stage_progress: max_block,
+            done: true,
+        })
+    }
+
+    /// Pruned data does not come back, marks stay in place.
+    async fn unwind<'tx>(
+        &mut self,
+        _: &'tx mut MdbxTransaction<'db, RW, E>,
+        input: UnwindInput,
+    ) -> Result<UnwindOutput, StageError>
+    where
+        'db: 'tx,
+    {
+        Ok(UnwindOutput {
+            stage_progress: input.unwind_to,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv::{new_mem_database, tables::CallTraceSetEntry};
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn prune_call_traces() {
+        let db = new_mem_database().unwrap();
+        let mut tx = db.begin_mutable().unwrap();
+
+        for i in 1..=10 {
+            tx.set(
+                tables::CallTraceSet,
+                BlockNumber(i),
+                CallTraceSetEntry {
+                    address: Address::repeat_byte(i as u8),
+                    from: true,
+                    to: false,
+                    stats: None,
+                },
+            )
+            .unwrap();
+            tx.set(tables::Receipt, BlockNumber(i), vec![]).unwrap();
+        }
+
+        let mut stage = Prune {
+            config: PruneConfig {
+                call_traces: Some(3),
+                ..Default::default()
+            },
+        };
+        for (tip, to) in [(10, 8), (12, 10)] {
+            stage
+                .execute(
+                    &mut tx,
+                    StageInput {
+                        restarted: false,
+                        first_started_at: (Instant::now(), None),
+                        previous_stage: Some((TX_LOOKUP, BlockNumber(tip))),
+                        stage_progress: None,
+                    },
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(
+                CALL_TRACES.get_prune_marks(&tx).unwrap(),
+                tables::PruneMarks {
+                    from: BlockNumber(1),
+                    to: BlockNumber(to),
+                }
+            );
+            assert_eq!(
+                tx.cursor(tables::CallTraceSet).unwrap().first().unwrap(),
+                Some((
+                    BlockNumber(to),
+                    CallTraceSetEntry {
+                        address: Address::repeat_byte(to as u8),
+                        from: true,
+                        to: false,
+                        stats: None,
+                    }
+                ))
+            );
+        }
+
+        // Receipts are kept.
+        assert!(EXECUTION.get_prune_marks(&tx).unwrap().is_empty());
+        assert_eq!(
+            tx.cursor(tables::Receipt).unwrap().first().unwrap(),
+            Some((BlockNumber(1), vec![]))
+        );
+    }
+}
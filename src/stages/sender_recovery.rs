@@ -34,7 +34,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: StageInput,
-    ) -> anyhow::Result<ExecOutput>
+    ) -> Result<ExecOutput, StageError>
     where
         'db: 'tx,
     {
@@ -62,7 +62,7 @@ where
                 let txs = tx
                     .cursor(tables::BlockTransaction.erased())?
                     .walk(Some(body.base_tx_id.encode().to_vec()))
-                    .take(body.tx_amount.try_into()?)
+                    .take(body.tx_amount.try_into().map_err(anyhow::Error::from)?)
                     .map(|res| res.map(|(_, tx)| tx))
                     .collect::<anyhow::Result<Vec<_>>>()?;
                 batch_txs += txs.len();
@@ -84,13 +84,10 @@ where
                         let senders = txs
                             .into_iter()
                             .map(|encoded_tx| {
-                                let tx = ErasedTable::<tables::BlockTransaction>::decode_value(
-                                    &encoded_tx,
-                                )?;
-                                let sender = tx.recover_sender()?;
-                                Ok::<_, anyhow::Error>(sender)
+                                ErasedTable::<tables::BlockTransaction>::decode_value(&encoded_tx)
                             })
-                            .collect::<anyhow::Result<Vec<Address>>>();
+                            .collect::<anyhow::Result<Vec<_>>>()
+                            .and_then(|txs| MessageWithSignature::recover_senders(&txs));
 
                         Some(senders.map(|senders| {
                             (
@@ -167,7 +164,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: UnwindInput,
-    ) -> anyhow::Result<UnwindOutput>
+    ) -> Result<UnwindOutput, StageError>
     where
         'db: 'tx,
     {
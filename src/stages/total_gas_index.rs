@@ -1,4 +1,5 @@
 use crate::{
+    accessors,
     kv::{mdbx::*, tables},
     stagedsync::{stage::*, stages::*},
     StageId,
@@ -23,7 +24,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: StageInput,
-    ) -> anyhow::Result<ExecOutput>
+    ) -> Result<ExecOutput, StageError>
     where
         'db: 'tx,
     {
@@ -40,16 +41,13 @@ where
         if max_block >= starting_block {
             let mut gas = cumulative_index_cur.seek_exact(prev_progress)?.unwrap().1;
 
-            for block_num in starting_block..=max_block {
+            for res in accessors::chain::canonical_headers(tx, starting_block..=max_block)? {
+                let (_, header) = res?;
+                let block_num = header.number;
                 if block_num.0 % 500_000 == 0 {
                     info!("Building total gas index for block {}", block_num);
                 }
 
-                let canonical_hash = tx.get(tables::CanonicalHeader, block_num)?.unwrap();
-                let header = tx
-                    .get(tables::Header, (block_num, canonical_hash))?
-                    .unwrap();
-
                 gas += header.gas_used;
 
                 cumulative_index_cur.append(block_num, gas)?;
@@ -66,7 +64,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: UnwindInput,
-    ) -> anyhow::Result<UnwindOutput>
+    ) -> Result<UnwindOutput, StageError>
     where
         'db: 'tx,
     {
@@ -1,4 +1,5 @@
 use crate::{
+    accessors,
     kv::{mdbx::*, tables},
     stagedsync::{stage::*, stages::*},
     StageId,
@@ -24,7 +25,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: StageInput,
-    ) -> anyhow::Result<ExecOutput>
+    ) -> Result<ExecOutput, StageError>
     where
         'db: 'tx,
     {
@@ -41,15 +42,17 @@ where
         if max_block >= starting_block {
             let mut tx_num = cumulative_index_cur.seek_exact(prev_progress)?.unwrap().1;
 
-            for block_num in starting_block..=max_block {
+            for res in accessors::chain::canonical_blocks(tx, starting_block..=max_block)? {
+                let (block_num, canonical_hash) = res?;
                 if block_num.0 % 500_000 == 0 {
                     info!("Building total tx index for block {}", block_num);
                 }
 
-                let canonical_hash = tx.get(tables::CanonicalHeader, block_num)?.unwrap();
                 let body = tx
                     .get(tables::BlockBody, (block_num, canonical_hash))?
-                    .unwrap();
+                    .ok_or_else(|| {
+                        format_err!("body {}/{:?} not found", block_num, canonical_hash)
+                    })?;
 
                 tx_num += body.tx_amount as u64;
 
@@ -67,7 +70,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: UnwindInput,
-    ) -> anyhow::Result<UnwindOutput>
+    ) -> Result<UnwindOutput, StageError>
     where
         'db: 'tx,
     {
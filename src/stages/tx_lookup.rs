@@ -31,7 +31,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: StageInput,
-    ) -> anyhow::Result<ExecOutput>
+    ) -> Result<ExecOutput, StageError>
     where
         'db: 'tx,
     {
@@ -56,7 +56,7 @@ where
             let walker_block_txs = tx
                 .cursor(tables::BlockTransaction)?
                 .walk(Some(tx_base_id))
-                .take(tx_count.try_into()?);
+                .take(tx_count.try_into().map_err(anyhow::Error::from)?);
             pin!(walker_block_txs);
 
             while let Some((_, tx)) = walker_block_txs.next().transpose()? {
@@ -79,7 +79,7 @@ where
         &mut self,
         tx: &'tx mut MdbxTransaction<'db, RW, E>,
         input: UnwindInput,
-    ) -> anyhow::Result<UnwindOutput>
+    ) -> Result<UnwindOutput, StageError>
     where
         'db: 'tx,
     {
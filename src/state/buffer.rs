@@ -1,5 +1,6 @@
 use crate::{
-    accessors, h256_to_u256,
+    accessors::{self, code_cache::CodeCache, header_cache::HeaderCache},
+    h256_to_u256,
     kv::{
         mdbx::*,
         tables::{self, AccountChange, StorageChange, StorageChangeKey},
@@ -9,7 +10,10 @@ use crate::{
     u256_to_h256, State,
 };
 use bytes::Bytes;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
 use tokio::pin;
 use tracing::*;
 
@@ -36,6 +40,8 @@ where
 
     prune_from: BlockNumber,
     historical_block: Option<BlockNumber>,
+    header_cache: Option<Arc<HeaderCache>>,
+    code_cache: Option<Arc<CodeCache>>,
 
     accounts: HashMap<Address, Option<Account>>,
 
@@ -47,6 +53,7 @@ where
 
     hash_to_code: BTreeMap<H256, Bytes>,
     logs: BTreeMap<(BlockNumber, TxIndex), Vec<Log>>,
+    receipts: BTreeMap<BlockNumber, Vec<tables::StoredReceipt>>,
 
     // Current block stuff
     block_number: BlockNumber,
@@ -68,18 +75,41 @@ where
             txn,
             prune_from,
             historical_block,
+            header_cache: None,
+            code_cache: None,
             accounts: Default::default(),
             storage: Default::default(),
             account_changes: Default::default(),
             storage_changes: Default::default(),
             hash_to_code: Default::default(),
             logs: Default::default(),
+            receipts: Default::default(),
             block_number: Default::default(),
             changed_storage: Default::default(),
         }
     }
 
+    /// Serve header reads through `header_cache`.
+    pub fn set_header_cache(&mut self, header_cache: Arc<HeaderCache>) {
+        self.header_cache = Some(header_cache);
+    }
+
+    /// Serve code reads through `code_cache`.
+    pub fn set_code_cache(&mut self, code_cache: Arc<CodeCache>) {
+        self.code_cache = Some(code_cache);
+    }
+
     pub fn insert_receipts(&mut self, block_number: BlockNumber, receipts: Vec<Receipt>) {
+        self.receipts.insert(
+            block_number,
+            receipts
+                .iter()
+                .map(|receipt| tables::StoredReceipt {
+                    success: receipt.success,
+                    cumulative_gas_used: receipt.cumulative_gas_used,
+                })
+                .collect(),
+        );
         for (i, receipt) in receipts.into_iter().enumerate() {
             self.logs
                 .insert((block_number, TxIndex(i.try_into().unwrap())), receipt.logs);
@@ -104,6 +134,8 @@ where
     fn read_code(&self, code_hash: H256) -> anyhow::Result<Bytes> {
         if let Some(code) = self.hash_to_code.get(&code_hash).cloned() {
             Ok(code)
+        } else if let Some(code_cache) = &self.code_cache {
+            Ok(code_cache.code(self.txn, code_hash)?.unwrap_or_default())
         } else {
             Ok(self
                 .txn
@@ -185,7 +217,11 @@ where
         block_number: BlockNumber,
         block_hash: H256,
     ) -> anyhow::Result<Option<BlockHeader>> {
-        self.txn.get(tables::Header, (block_number, block_hash))
+        if let Some(header_cache) = &self.header_cache {
+            return header_cache.header(self.txn, block_number, block_hash);
+        }
+
+        Ok(self.txn.get(tables::Header, (block_number, block_hash))?)
     }
 
     fn read_body(
@@ -316,6 +352,12 @@ where
             log_table.append((block_number, idx), logs)?;
         }
 
+        debug!("Writing receipts");
+        let mut receipt_table = self.txn.cursor(tables::Receipt)?;
+        for (block_number, receipts) in std::mem::take(&mut self.receipts) {
+            receipt_table.append(block_number, receipts)?;
+        }
+
         debug!("History write complete");
 
         Ok(())
@@ -363,8 +405,19 @@ where
                 storage_table.delete_current_duplicates()?;
             }
 
-            for (&k, &v) in &overlay_storage.slots {
-                upsert_storage_value(&mut storage_table, address, k, v)?;
+            // Slots are written in table order, so that the cursor moves forward only.
+            let mut slots = overlay_storage.slots.iter().collect::<Vec<_>>();
+            slots.sort_unstable_by_key(|&(&k, _)| k);
+
+            for (&k, &v) in slots {
+                if overlay_storage.erased {
+                    // No slot of the address is left to replace.
+                    if v != 0 {
+                        storage_table.upsert(address, (u256_to_h256(k), v))?;
+                    }
+                } else {
+                    upsert_storage_value(&mut storage_table, address, k, v)?;
+                }
 
                 written_slots += 1;
                 if written_slots % 500_000 == 0 {
@@ -381,7 +434,7 @@ where
         debug!("Writing code");
         let mut code_table = self.txn.cursor(tables::Code)?;
         for (code_hash, code) in self.hash_to_code {
-            code_table.upsert(code_hash, code)?;
+            code_table.upsert(code_hash, code.into())?;
         }
 
         Ok(())
@@ -453,4 +506,62 @@ mod tests {
         .unwrap();
         assert_eq!(db_value_b, value_b);
     }
+
+    #[test]
+    fn erased_storage_rewrite() {
+        let db = new_mem_database().unwrap();
+        let txn = db.begin_mutable().unwrap();
+
+        let address: Address = hex!("be00000000000000000000000000000000000000").into();
+        let next_address: Address = hex!("bf00000000000000000000000000000000000000").into();
+        for a in [address, next_address] {
+            for location in 1..=3_u64 {
+                txn.set(
+                    tables::Storage,
+                    a,
+                    (H256::from_low_u64_be(location), location.as_u256()),
+                )
+                .unwrap();
+            }
+        }
+
+        let mut buffer = Buffer::new(&txn, 0.into(), None);
+        buffer.begin_block(1.into());
+        buffer.erase_storage(address).unwrap();
+        for location in [9_u64, 2, 5, 7] {
+            buffer
+                .update_storage(
+                    address,
+                    location.as_u256(),
+                    U256::ZERO,
+                    (location * 10).as_u256(),
+                )
+                .unwrap();
+        }
+        buffer
+            .update_storage(address, 5.as_u256(), 50.as_u256(), U256::ZERO)
+            .unwrap();
+        buffer.write_to_db().unwrap();
+
+        let entries = txn
+            .cursor(tables::Storage)
+            .unwrap()
+            .walk(None)
+            .map(|entry| {
+                let (a, (location, value)) = entry.unwrap();
+                (a, h256_to_u256(location), value)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            [
+                (address, 2.as_u256(), 20.as_u256()),
+                (address, 7.as_u256(), 70.as_u256()),
+                (address, 9.as_u256(), 90.as_u256()),
+                (next_address, 1.as_u256(), 1.as_u256()),
+                (next_address, 2.as_u256(), 2.as_u256()),
+                (next_address, 3.as_u256(), 3.as_u256()),
+            ]
+        );
+    }
 }
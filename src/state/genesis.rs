@@ -1,10 +1,20 @@
 use crate::{
-    kv::{mdbx::MdbxTransaction, tables},
+    accessors,
+    chain::protocol_param::param,
+    crypto::keccak256,
+    kv::{
+        mdbx::MdbxTransaction,
+        tables::{self, HeaderKey},
+    },
     models::*,
+    stagedsync::stages::EXECUTION,
     state::*,
 };
+use anyhow::{bail, format_err};
 use mdbx::{EnvironmentKind, RW};
+use std::collections::BTreeSet;
 use tempfile::TempDir;
+use tracing::*;
 
 #[derive(Clone, Debug)]
 pub struct GenesisState {
@@ -20,16 +30,7 @@ impl GenesisState {
 impl GenesisState {
     pub fn initial_state(&self) -> InMemoryState {
         let mut state_buffer = InMemoryState::new();
-        // Allocate accounts
-        if let Some(balances) = self.chain_spec.balances.get(&BlockNumber(0)) {
-            for (&address, &balance) in balances {
-                let current_account = Account {
-                    balance,
-                    ..Default::default()
-                };
-                state_buffer.update_account(address, None, Some(current_account));
-            }
-        }
+        allocate_accounts(&mut state_buffer, &self.chain_spec).unwrap();
         state_buffer
     }
 
@@ -51,7 +52,7 @@ impl GenesisState {
             extra_data: seal.extra_data(),
             mix_hash: seal.mix_hash(),
             nonce: seal.nonce(),
-            base_fee_per_gas: None,
+            base_fee_per_gas: genesis_base_fee(&self.chain_spec),
 
             receipts_root: EMPTY_ROOT,
             ommers_hash: EMPTY_LIST_HASH,
@@ -60,6 +61,49 @@ impl GenesisState {
     }
 }
 
+fn allocate_accounts<S: State>(state: &mut S, chainspec: &ChainSpec) -> anyhow::Result<()> {
+    let genesis = &chainspec.genesis;
+    let balances = chainspec.balances.get(&genesis.number);
+    let addresses = balances
+        .into_iter()
+        .flat_map(|balances| balances.keys())
+        .chain(genesis.accounts.keys())
+        .copied()
+        .collect::<BTreeSet<_>>();
+
+    for address in addresses {
+        let balance = balances
+            .and_then(|balances| balances.get(&address))
+            .copied()
+            .unwrap_or_default();
+        let mut account = Account {
+            balance,
+            ..Default::default()
+        };
+
+        if let Some(genesis_account) = genesis.accounts.get(&address) {
+            account.nonce = genesis_account.nonce;
+            if !genesis_account.code.is_empty() {
+                account.code_hash = keccak256(&genesis_account.code);
+                state.update_code(account.code_hash, genesis_account.code.clone())?;
+            }
+            for (&location, &value) in &genesis_account.storage {
+                state.update_storage(address, location, U256::ZERO, value)?;
+            }
+        }
+
+        state.update_account(address, None, Some(account));
+    }
+
+    Ok(())
+}
+
+/// Base fee of the genesis block, if it is the EIP-1559 fork block.
+fn genesis_base_fee(chainspec: &ChainSpec) -> Option<U256> {
+    (chainspec.consensus.eip1559_block == Some(chainspec.genesis.number))
+        .then(|| param::INITIAL_BASE_FEE.into())
+}
+
 pub fn initialize_genesis<'db, E>(
     txn: &MdbxTransaction<'db, RW, E>,
     etl_temp_dir: &TempDir,
@@ -75,20 +119,7 @@ where
 
     let mut state_buffer = Buffer::new(txn, genesis, None);
     state_buffer.begin_block(genesis);
-    // Allocate accounts
-    if let Some(balances) = chainspec.balances.get(&genesis) {
-        for (&address, &balance) in balances {
-            state_buffer.update_account(
-                address,
-                None,
-                Some(Account {
-                    balance,
-                    ..Default::default()
-                }),
-            );
-        }
-    }
-
+    allocate_accounts(&mut state_buffer, &chainspec)?;
     state_buffer.write_to_db()?;
 
     crate::stages::promote_clean_accounts(txn, etl_temp_dir)?;
@@ -108,7 +139,7 @@ where
         extra_data: chainspec.genesis.seal.extra_data(),
         mix_hash: chainspec.genesis.seal.mix_hash(),
         nonce: chainspec.genesis.seal.nonce(),
-        base_fee_per_gas: None,
+        base_fee_per_gas: genesis_base_fee(&chainspec),
 
         receipts_root: EMPTY_ROOT,
         ommers_hash: EMPTY_LIST_HASH,
@@ -139,12 +170,139 @@ where
     txn.set(tables::TotalTx, genesis, 0)?;
 
     txn.set(tables::LastHeader, Default::default(), block_hash)?;
+    accessors::chain::chain_head::set_latest(txn, (genesis, block_hash))?;
 
     txn.set(tables::Config, block_hash, chainspec)?;
 
     Ok(true)
 }
 
+/// Fork activations replacing the ones of the chain spec, to update the spec of an existing
+/// database in a controlled way.
+#[derive(Clone, Debug, Default, clap::Parser)]
+pub struct SpecOverrides {
+    #[clap(long = "override.berlin", help = "Block to activate Berlin at.")]
+    pub berlin: Option<BlockNumber>,
+    #[clap(
+        long = "override.london",
+        help = "Block to activate London and EIP-1559 at."
+    )]
+    pub london: Option<BlockNumber>,
+    #[clap(
+        long = "override.shanghai",
+        help = "Timestamp to activate Shanghai at."
+    )]
+    pub shanghai: Option<u64>,
+}
+
+impl SpecOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.berlin.is_none() && self.london.is_none() && self.shanghai.is_none()
+    }
+
+    pub fn apply(&self, chainspec: &mut ChainSpec) {
+        if let Some(berlin) = self.berlin {
+            chainspec.upgrades.berlin = Some(berlin);
+        }
+        if let Some(london) = self.london {
+            chainspec.upgrades.london = Some(london);
+            chainspec.consensus.eip1559_block = Some(london);
+        }
+        if let Some(shanghai) = self.shanghai {
+            chainspec.upgrades.shanghai = Some(shanghai);
+        }
+    }
+}
+
+/// Checks `chainspec` against the snapshot in the `Config` table, taken when the database was
+/// initialized or last updated, and replaces the snapshot.
+///
+/// Fails if the genesis differs, or if a rescheduled fork is active at the chain head, since
+/// the blocks above it were processed with the old schedule.
+pub fn check_chain_spec<E>(
+    txn: &MdbxTransaction<'_, RW, E>,
+    chainspec: &ChainSpec,
+    genesis_hash: H256,
+) -> anyhow::Result<()>
+where
+    E: EnvironmentKind,
+{
+    let genesis = chainspec.genesis.number;
+    let stored_genesis_hash = txn
+        .get(tables::CanonicalHeader, genesis)?
+        .ok_or_else(|| format_err!("Genesis block absent"))?;
+    if stored_genesis_hash != genesis_hash {
+        let stored_chain = txn
+            .get(tables::Config, stored_genesis_hash)?
+            .map(|stored| stored.name)
+            .unwrap_or_else(|| "unknown chain".to_string());
+        bail!(
+            "database holds {} with genesis {:?}, while {} with genesis {:?} was selected, use another datadir",
+            stored_chain,
+            stored_genesis_hash,
+            chainspec.name,
+            genesis_hash
+        );
+    }
+
+    let Some(stored) = txn.get(tables::Config, genesis_hash)? else {
+        info!("Storing chain spec of {}", chainspec.name);
+        txn.set(tables::Config, genesis_hash, chainspec.clone())?;
+        return Ok(());
+    };
+    if stored == *chainspec {
+        return Ok(());
+    }
+
+    let changes = stored.fork_schedule_changes(chainspec);
+    if !changes.is_empty() {
+        let (head, head_hash) = executed_head(txn)?;
+        let head_timestamp = txn
+            .get(tables::Header, (head, head_hash))?
+            .map(|header| header.timestamp)
+            .unwrap_or_default();
+
+        let mut passed = vec![];
+        for change in changes {
+            if change.is_active_at(head, head_timestamp) {
+                passed.push(change.to_string());
+            } else {
+                warn!("Fork schedule changed: {}", change);
+            }
+        }
+        if !passed.is_empty() {
+            bail!(
+                "fork schedule changed below the chain head {}: {}",
+                head,
+                passed.join(", ")
+            );
+        }
+    }
+
+    info!("Updating stored chain spec of {}", chainspec.name);
+    txn.set(tables::Config, genesis_hash, chainspec.clone())?;
+
+    Ok(())
+}
+
+/// Highest executed block. Databases created before the chain head was tracked only have the
+/// execution stage progress.
+fn executed_head<E>(txn: &MdbxTransaction<'_, RW, E>) -> anyhow::Result<HeaderKey>
+where
+    E: EnvironmentKind,
+{
+    if let Some(head) = accessors::chain::chain_head::read(txn)? {
+        return Ok(head.latest);
+    }
+
+    let head = EXECUTION.get_progress(txn)?.unwrap_or_default();
+    let head_hash = txn
+        .get(tables::CanonicalHeader, head)?
+        .ok_or_else(|| format_err!("no canonical hash for executed block {}", head))?;
+
+    Ok((head, head_hash))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -190,4 +348,64 @@ mod tests {
             hex!("d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3").into()
         );
     }
+
+    #[test]
+    fn check_stored_chain_spec() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let temp_dir = TempDir::new().unwrap();
+        let mainnet = crate::res::chainspec::MAINNET.clone();
+        initialize_genesis(&tx, &temp_dir, mainnet.clone()).unwrap();
+        let genesis_hash = tx.get(tables::CanonicalHeader, 0.into()).unwrap().unwrap();
+
+        check_chain_spec(&tx, &mainnet, genesis_hash).unwrap();
+        assert!(
+            check_chain_spec(&tx, &crate::res::chainspec::ROPSTEN, H256::repeat_byte(1)).is_err()
+        );
+
+        // forks above the head may move
+        let mut overridden = mainnet.clone();
+        SpecOverrides {
+            shanghai: Some(1_700_000_000),
+            ..Default::default()
+        }
+        .apply(&mut overridden);
+        check_chain_spec(&tx, &overridden, genesis_hash).unwrap();
+        assert_eq!(
+            tx.get(tables::Config, genesis_hash).unwrap().unwrap(),
+            overridden
+        );
+
+        // forks at the head may not
+        let mut overridden = mainnet;
+        overridden.upgrades.homestead = Some(0.into());
+        assert!(check_chain_spec(&tx, &overridden, genesis_hash).is_err());
+    }
+
+    #[test]
+    fn check_chain_spec_without_chain_head() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let temp_dir = TempDir::new().unwrap();
+        let mainnet = crate::res::chainspec::MAINNET.clone();
+        initialize_genesis(&tx, &temp_dir, mainnet.clone()).unwrap();
+        let genesis_hash = tx.get(tables::CanonicalHeader, 0.into()).unwrap().unwrap();
+
+        tx.del(tables::ChainHead, Default::default(), None).unwrap();
+        tx.set(
+            tables::CanonicalHeader,
+            BlockNumber(2_000_000),
+            H256::repeat_byte(2),
+        )
+        .unwrap();
+        EXECUTION
+            .save_progress(&tx, BlockNumber(2_000_000))
+            .unwrap();
+
+        let mut overridden = mainnet;
+        overridden.upgrades.tangerine = Some(1_950_000.into());
+        assert!(check_chain_spec(&tx, &overridden, genesis_hash).is_err());
+    }
 }
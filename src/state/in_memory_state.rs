@@ -1,4 +1,4 @@
-use crate::{crypto::*, models::*, util::*, State};
+use crate::{codec::encode_compact_u256, crypto::*, models::*, util::*, State};
 use bytes::Bytes;
 use std::{collections::HashMap, convert::TryInto};
 
@@ -55,10 +55,8 @@ impl InMemoryState {
         if let Some(storage) = self.storage.get(&address) {
             if !storage.is_empty() {
                 return trie_root(storage.iter().map(|(&location, &value)| {
-                    let value = u256_to_h256(value);
-                    let zv = zeroless_view(&value);
                     let encoded_location = keccak256(u256_to_h256(location));
-                    let encoded_value = rlp::encode(&zv);
+                    let encoded_value = rlp::encode(&&*encode_compact_u256(value));
                     (encoded_location, encoded_value)
                 }));
             }
@@ -143,6 +141,18 @@ impl InMemoryState {
         self.difficulty[block_number].entry(hash).insert_entry(d);
     }
 
+    /// Whether both the header and the body of the block were inserted.
+    pub fn contains_block(&self, block_number: BlockNumber, block_hash: H256) -> bool {
+        let block_number = block_number.0 as usize;
+        self.headers
+            .get(block_number)
+            .map_or(false, |header_map| header_map.contains_key(&block_hash))
+            && self
+                .bodies
+                .get(block_number)
+                .map_or(false, |body_map| body_map.contains_key(&block_hash))
+    }
+
     pub fn read_body_with_senders(
         &self,
         block_number: BlockNumber,
@@ -205,6 +205,21 @@ impl<'storage, 'r, S: State> IntraBlockState<'r, S> {
             self.journal.push(Delta::Selfdestruct { address });
         }
     }
+
+    /// SELFDESTRUCT of `address` in favour of `beneficiary`.
+    ///
+    /// The beneficiary is credited before the balance is cleared, so destructing to self burns
+    /// the balance, and a missing beneficiary is created even if nothing is sent. The account
+    /// itself, even if created within the transaction, lives on until the end of the
+    /// transaction, and counts once for the refund however many times it is destructed.
+    pub fn selfdestruct(&mut self, address: Address, beneficiary: Address) -> anyhow::Result<()> {
+        self.record_selfdestruct(address);
+        let balance = self.get_balance(address)?;
+        self.add_to_balance(beneficiary, balance)?;
+        self.set_balance(address, 0)?;
+
+        Ok(())
+    }
     pub fn destruct_selfdestructs(&mut self) -> anyhow::Result<()> {
         for address in self.self_destructs.iter().copied().collect::<Vec<_>>() {
             self.destruct(address)?;
@@ -212,9 +227,17 @@ impl<'storage, 'r, S: State> IntraBlockState<'r, S> {
 
         Ok(())
     }
+
+    /// Deletes the empty accounts touched by the transaction, as of Spurious Dragon.
+    ///
+    /// Zero-value transfers touch their recipient too, while zero-value calls to missing
+    /// accounts do not bring them into existence in the first place. Touched accounts which
+    /// do not exist, such as those destructed already or a missing RIPEMD after a failed
+    /// call, are left alone.
+    // https://eips.ethereum.org/EIPS/eip-161
     pub fn destruct_touched_dead(&mut self) -> anyhow::Result<()> {
         for address in self.touched.iter().copied().collect::<Vec<_>>() {
-            if self.is_dead(address)? {
+            if self.exists(address)? && self.is_dead(address)? {
                 self.destruct(address)?;
             }
         }
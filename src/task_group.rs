@@ -0,0 +1,178 @@
+use anyhow::format_err;
+use futures_util::{stream::FuturesUnordered, FutureExt, StreamExt};
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::task::{JoinError, JoinHandle};
+use tracing::*;
+
+struct Task {
+    name: &'static str,
+    handle: JoinHandle<anyhow::Result<()>>,
+}
+
+impl Future for Task {
+    type Output = (&'static str, Result<anyhow::Result<()>, JoinError>);
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let name = self.name;
+        self.handle.poll_unpin(cx).map(|res| (name, res))
+    }
+}
+
+/// Owns the background tasks of a component and ties their lifetime to it.
+///
+/// A task that fails or panics brings down the whole group: the remaining tasks are aborted and
+/// the error is handed to whoever drives the group. Dropping the group aborts whatever is still
+/// running, so no task outlives the pipeline that spawned it.
+#[derive(Default)]
+pub struct TaskGroup {
+    tasks: FuturesUnordered<Task>,
+}
+
+impl TaskGroup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn spawn<F>(&mut self, name: &'static str, task: F)
+    where
+        F: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        debug!("Spawning task {}", name);
+        self.tasks.push(Task {
+            name,
+            handle: tokio::spawn(task),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Waits for the next task to finish and returns its name and outcome, panics and
+    /// cancellations are reported as errors. Returns `None` once the group is empty.
+    pub async fn join_next(&mut self) -> Option<(&'static str, anyhow::Result<()>)> {
+        let (name, res) = self.tasks.next().await?;
+        let res = match res {
+            Ok(res) => res,
+            Err(e) if e.is_panic() => Err(format_err!("task {} panicked", name)),
+            Err(_) => Err(format_err!("task {} was cancelled", name)),
+        };
+        Some((name, res))
+    }
+
+    /// Drives `fut` to completion while supervising the tasks of the group.
+    ///
+    /// Tasks finishing successfully are simply forgotten. The first one to fail cancels `fut`
+    /// along with every other task and its error is returned. Once `fut` completes, the tasks
+    /// still running are shut down.
+    pub async fn run_until<F, T>(&mut self, fut: F) -> anyhow::Result<T>
+    where
+        F: Future<Output = anyhow::Result<T>>,
+    {
+        tokio::pin!(fut);
+        let res = loop {
+            tokio::select! {
+                res = &mut fut => break res,
+                Some((name, res)) = self.join_next() => {
+                    if let Err(e) = res {
+                        error!("Task {} failed, shutting down: {:?}", name, e);
+                        break Err(e.context(format!("task {} failed", name)));
+                    }
+                    debug!("Task {} finished", name);
+                }
+            }
+        };
+        self.shutdown().await;
+        res
+    }
+
+    /// Aborts all tasks and waits for them to wind down.
+    pub async fn shutdown(&mut self) {
+        for task in self.tasks.iter() {
+            task.handle.abort();
+        }
+        while let Some((name, res)) = self.tasks.next().await {
+            if let Ok(Err(e)) = res {
+                debug!("Task {} stopped with error during shutdown: {:?}", name, e);
+            }
+        }
+    }
+}
+
+impl Drop for TaskGroup {
+    fn drop(&mut self) {
+        for task in self.tasks.iter() {
+            task.handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    struct SetOnDrop(Arc<AtomicBool>);
+
+    impl Drop for SetOnDrop {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn pending_task(dropped: &Arc<AtomicBool>) -> impl Future<Output = anyhow::Result<()>> {
+        let guard = SetOnDrop(dropped.clone());
+        async move {
+            let _guard = guard;
+            futures_util::future::pending::<()>().await;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn panic_cancels_group() {
+        let dropped = Arc::new(AtomicBool::new(false));
+
+        let mut tasks = TaskGroup::new();
+        tasks.spawn("pending", pending_task(&dropped));
+        tasks.spawn("finished", async { Ok(()) });
+        tasks.spawn("panicking", async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            panic!("boom")
+        });
+
+        let err = tasks
+            .run_until(futures_util::future::pending::<anyhow::Result<()>>())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "task panicking failed");
+        assert!(tasks.is_empty());
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn completion_stops_group() {
+        let dropped = Arc::new(AtomicBool::new(false));
+
+        let mut tasks = TaskGroup::new();
+        tasks.spawn("pending", pending_task(&dropped));
+
+        assert_eq!(tasks.run_until(async { Ok(42) }).await.unwrap(), 42);
+        assert!(tasks.is_empty());
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+}
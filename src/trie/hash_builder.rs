@@ -7,13 +7,14 @@ use crate::{
         util::{assert_subset, prefix_length},
     },
 };
+use bytes::Bytes;
 use ethereum_types::H256;
 use rlp::RlpStream;
-use std::{boxed::Box, cmp};
+use std::{boxed::Box, cmp, collections::BTreeMap};
 
 const RLP_EMPTY_STRING_CODE: u8 = 0x80;
 
-fn encode_path(nibbles: &[u8], terminating: bool) -> Vec<u8> {
+pub(crate) fn encode_path(nibbles: &[u8], terminating: bool) -> Vec<u8> {
     let mut res = vec![0u8; nibbles.len() / 2 + 1];
     let odd = nibbles.len() % 2 != 0;
     let mut i = 0usize;
@@ -53,6 +54,40 @@ fn node_ref(rlp: &[u8]) -> Vec<u8> {
 
 type NodeCollector<'nc> = Box<dyn FnMut(&[u8], &Node) + Send + Sync + 'nc>;
 
+/// Keeps RLP of the nodes lying on the paths to the target keys.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ProofRetainer {
+    targets: Vec<Vec<u8>>,
+    nodes: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl ProofRetainer {
+    pub(crate) fn new(targets: Vec<Vec<u8>>) -> Self {
+        Self {
+            targets,
+            nodes: BTreeMap::new(),
+        }
+    }
+
+    fn retain(&mut self, path: &[u8], rlp: &[u8]) {
+        if self.targets.iter().any(|target| target.starts_with(path)) {
+            self.nodes.insert(path.to_vec(), rlp.to_vec());
+        }
+    }
+
+    /// Proof for the target key, starting from the root node.
+    /// Nodes embedded into their parents are not a part of the proof.
+    pub(crate) fn proof(&self, target: &[u8]) -> Vec<Bytes> {
+        self.nodes
+            .iter()
+            .filter(|(path, rlp)| {
+                target.starts_with(path) && (path.is_empty() || rlp.len() >= KECCAK_LENGTH)
+            })
+            .map(|(_, rlp)| Bytes::copy_from_slice(rlp))
+            .collect()
+    }
+}
+
 #[derive(Clone)]
 enum HashBuilderValue {
     Bytes(Vec<u8>),
@@ -61,6 +96,7 @@ enum HashBuilderValue {
 
 pub(crate) struct HashBuilder<'nc> {
     pub(crate) node_collector: Option<NodeCollector<'nc>>,
+    pub(crate) proof_retainer: Option<ProofRetainer>,
     key: Vec<u8>,
     value: HashBuilderValue,
     is_in_db_trie: bool,
@@ -75,6 +111,7 @@ impl<'nc> HashBuilder<'nc> {
     pub(crate) fn new() -> Self {
         Self {
             node_collector: None,
+            proof_retainer: None,
             key: vec![],
             value: HashBuilderValue::Bytes(vec![]),
             is_in_db_trie: false,
@@ -176,11 +213,11 @@ impl<'nc> HashBuilder<'nc> {
                 let value = self.value.clone();
                 match value {
                     HashBuilderValue::Bytes(ref leaf_value) => {
-                        let x = node_ref(
-                            self.leaf_node_rlp(short_node_key.as_slice(), leaf_value)
-                                .as_slice(),
-                        );
-                        self.stack.push(x);
+                        let rlp = self.leaf_node_rlp(short_node_key.as_slice(), leaf_value);
+                        if let Some(retainer) = &mut self.proof_retainer {
+                            retainer.retain(&current[..len_from], &rlp);
+                        }
+                        self.stack.push(node_ref(rlp.as_slice()));
                     }
                     HashBuilderValue::Hash(ref hash) => {
                         self.stack.push(wrap_hash(hash));
@@ -208,11 +245,11 @@ impl<'nc> HashBuilder<'nc> {
                 }
 
                 let stack_last = self.stack.pop().unwrap();
-                let new_stack_last = node_ref(
-                    self.extension_node_rlp(short_node_key.as_slice(), stack_last.as_slice())
-                        .as_slice(),
-                );
-                self.stack.push(new_stack_last);
+                let rlp = self.extension_node_rlp(short_node_key.as_slice(), stack_last.as_slice());
+                if let Some(retainer) = &mut self.proof_retainer {
+                    retainer.retain(&current[..len_from], &rlp);
+                }
+                self.stack.push(node_ref(rlp.as_slice()));
 
                 self.hash_masks.resize(len_from, 0u16);
                 self.tree_masks.resize(len_from, 0u16);
@@ -224,6 +261,9 @@ impl<'nc> HashBuilder<'nc> {
 
             if !succeeding.is_empty() || preceding_exists {
                 let child_hashes = self.branch_ref(self.groups[len], self.hash_masks[len]);
+                if let Some(retainer) = &mut self.proof_retainer {
+                    retainer.retain(&current[..len], &self.rlp_buffer);
+                }
 
                 let have_node_collector = self.node_collector.is_some();
                 if have_node_collector {
@@ -1,5 +1,6 @@
 #![allow(clippy::question_mark)]
 use crate::{
+    changeset,
     crypto::keccak256,
     etl::collector::{TableCollector, OPTIMAL_BUFFER_CAPACITY},
     kv::{mdbx::*, tables, traits::*},
@@ -54,7 +55,7 @@ impl CursorSubNode {
         self.node.as_ref().unwrap().hash_mask() & (1u16 << self.nibble) != 0
     }
 
-    fn hash(&self) -> Option<H256> {
+    pub(crate) fn hash(&self) -> Option<H256> {
         if !self.hash_flag() {
             return None;
         }
@@ -86,12 +87,31 @@ fn increment_key(unpacked: &[u8]) -> Option<Vec<u8>> {
     None
 }
 
-struct Cursor<'cu, 'tx, 'ps, T>
+/// Trie nodes consumed by a cursor in a write transaction are deleted, since they are going to be regenerated.
+/// Read-only traversal (e.g. proof generation) leaves the trie tables intact.
+pub(crate) trait TrieCursorKind: TransactionKind {
+    fn consume<T: Table>(cursor: &mut MdbxCursor<'_, Self, T>) -> Result<()>;
+}
+
+impl TrieCursorKind for RW {
+    fn consume<T: Table>(cursor: &mut MdbxCursor<'_, Self, T>) -> Result<()> {
+        Ok(cursor.delete_current()?)
+    }
+}
+
+impl TrieCursorKind for RO {
+    fn consume<T: Table>(_: &mut MdbxCursor<'_, Self, T>) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub(crate) struct Cursor<'cu, 'tx, 'ps, K, T>
 where
+    K: TrieCursorKind,
     T: Table<Key = Vec<u8>, SeekKey = Vec<u8>, Value = Vec<u8>>,
     'tx: 'cu,
 {
-    cursor: Mutex<&'cu mut MdbxCursor<'tx, RW, T>>,
+    cursor: Mutex<&'cu mut MdbxCursor<'tx, K, T>>,
     changed: &'ps mut PrefixSet,
     prefix: Vec<u8>,
     stack: Vec<CursorSubNode>,
@@ -99,16 +119,17 @@ where
     _marker: PhantomData<&'tx T>,
 }
 
-impl<'cu, 'tx, 'ps, T> Cursor<'cu, 'tx, 'ps, T>
+impl<'cu, 'tx, 'ps, K, T> Cursor<'cu, 'tx, 'ps, K, T>
 where
+    K: TrieCursorKind,
     T: Table<Key = Vec<u8>, SeekKey = Vec<u8>, Value = Vec<u8>>,
     'tx: 'cu,
 {
-    fn new(
-        cursor: &'cu mut MdbxCursor<'tx, RW, T>,
+    pub(crate) fn new(
+        cursor: &'cu mut MdbxCursor<'tx, K, T>,
         changed: &'ps mut PrefixSet,
         prefix: &[u8],
-    ) -> Result<Cursor<'cu, 'tx, 'ps, T>> {
+    ) -> Result<Cursor<'cu, 'tx, 'ps, K, T>> {
         let mut new_cursor = Self {
             cursor: Mutex::new(cursor),
             changed,
@@ -121,7 +142,7 @@ where
         Ok(new_cursor)
     }
 
-    fn next(&mut self) -> Result<()> {
+    pub(crate) fn next(&mut self) -> Result<()> {
         if self.stack.is_empty() {
             return Ok(()); // end-of-tree
         }
@@ -140,7 +161,7 @@ where
         Ok(())
     }
 
-    fn key(&self) -> Option<Vec<u8>> {
+    pub(crate) fn key(&self) -> Option<Vec<u8>> {
         if self.stack.is_empty() {
             None
         } else {
@@ -155,18 +176,18 @@ where
         self.stack.last().unwrap().hash()
     }
 
-    fn children_are_in_trie(&self) -> bool {
+    pub(crate) fn children_are_in_trie(&self) -> bool {
         if self.stack.is_empty() {
             return false;
         }
         self.stack.last().unwrap().tree_flag()
     }
 
-    fn can_skip_state(&self) -> bool {
+    pub(crate) fn can_skip_state(&self) -> bool {
         self.can_skip_state
     }
 
-    fn first_uncovered_prefix(&self) -> Option<Vec<u8>> {
+    pub(crate) fn first_uncovered_prefix(&self) -> Option<Vec<u8>> {
         let mut k = self.key();
 
         if self.can_skip_state && k.is_some() {
@@ -225,7 +246,7 @@ where
         self.update_skip_state();
 
         if entry.is_some() && (!self.can_skip_state || nibble != -1) {
-            self.cursor.lock().delete_current()?;
+            K::consume(&mut self.cursor.lock())?;
         }
 
         Ok(())
@@ -283,7 +304,7 @@ where
         }
     }
 
-    fn changed_mut(&mut self) -> &mut PrefixSet {
+    pub(crate) fn changed_mut(&mut self) -> &mut PrefixSet {
         self.changed
     }
 }
@@ -480,34 +501,25 @@ where
     K: TransactionKind,
     E: EnvironmentKind,
 {
-    let starting_key = from + 1;
-
     let mut out = PrefixSet::new();
 
-    let mut account_changes = txn.cursor(tables::AccountChangeSet)?;
-    let mut data = account_changes.seek(starting_key)?;
-    while data.is_some() {
-        let address = data.unwrap().1.address;
-        let hashed_address = keccak256(address);
-        out.insert(unpack_nibbles(hashed_address.as_bytes()).as_slice());
-        data = account_changes.next()?;
-    }
-
-    let mut storage_changes = txn.cursor(tables::StorageChangeSet)?;
-    let mut data = storage_changes.seek(starting_key)?;
-    while data.is_some() {
-        let address = data.as_ref().unwrap().0.address;
-        let location = data.as_ref().unwrap().1.location;
-        let hashed_address = keccak256(address);
-        let hashed_location = keccak256(location);
-
-        let hashed_key = [
-            hashed_address.as_bytes(),
-            unpack_nibbles(hashed_location.as_bytes()).as_slice(),
-        ]
-        .concat();
-        out.insert(hashed_key.as_slice());
-        data = storage_changes.next()?;
+    for changes in changeset::walk(txn, from + 1..=BlockNumber(u64::MAX))? {
+        let changes = changes?;
+        for change in changes.accounts {
+            let hashed_address = keccak256(change.address);
+            out.insert(unpack_nibbles(hashed_address.as_bytes()).as_slice());
+        }
+        for (address, change) in changes.storage {
+            let hashed_address = keccak256(address);
+            let hashed_location = keccak256(change.location);
+
+            let hashed_key = [
+                hashed_address.as_bytes(),
+                unpack_nibbles(hashed_location.as_bytes()).as_slice(),
+            ]
+            .concat();
+            out.insert(hashed_key.as_slice());
+        }
     }
 
     Ok(out)
@@ -1718,6 +1730,7 @@ mod tests {
 mod property_test {
     use super::*;
     use crate::{
+        codec::encode_compact_u256,
         crypto::{keccak256, trie_root},
         h256_to_u256,
         kv::{
@@ -1727,7 +1740,6 @@ mod property_test {
         },
         models::{Account, BlockNumber, EMPTY_ROOT},
         trie::regenerate_intermediate_hashes,
-        u256_to_h256, zeroless_view,
     };
     use anyhow::Result;
     use mdbx::{EnvironmentKind, RW};
@@ -1830,7 +1842,7 @@ mod property_test {
             trie_root(storage.iter().map(|(k, v)| {
                 (
                     keccak256(k.to_fixed_bytes()),
-                    rlp::encode(&zeroless_view(&u256_to_h256(*v))),
+                    rlp::encode(&&*encode_compact_u256(*v)),
                 )
             }))
         }
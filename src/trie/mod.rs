@@ -2,6 +2,10 @@ mod hash_builder;
 mod intermediate_hashes;
 mod node;
 mod prefix_set;
+mod proof;
 mod util;
 
+pub(crate) use hash_builder::{encode_path, unpack_nibbles};
 pub use intermediate_hashes::{increment_intermediate_hashes, regenerate_intermediate_hashes};
+pub(crate) use proof::{decode_path, prove_hashed, walk_trie};
+pub use proof::{prove_account, verify_account_proof, verify_proof, AccountProof, StorageProof};
@@ -0,0 +1,480 @@
+use crate::{
+    crypto::keccak256,
+    kv::{mdbx::*, tables},
+    models::*,
+    trie::{
+        hash_builder::{unpack_nibbles, HashBuilder, ProofRetainer},
+        intermediate_hashes::Cursor,
+        prefix_set::PrefixSet,
+    },
+};
+use anyhow::{bail, format_err, Result};
+use bytes::Bytes;
+use rlp::Rlp;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Merkle proof of a single storage slot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StorageProof {
+    pub key: H256,
+    pub value: U256,
+    pub proof: Vec<Bytes>,
+}
+
+/// Merkle proof of an account and a set of its storage slots, as served by `eth_getProof` (EIP-1186).
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountProof {
+    pub address: Address,
+    pub account: Option<Account>,
+    pub storage_hash: H256,
+    pub proof: Vec<Bytes>,
+    pub storage_proofs: Vec<StorageProof>,
+}
+
+fn calculate_storage_root<E: EnvironmentKind>(
+    txn: &MdbxTransaction<'_, RO, E>,
+    hashed_address: H256,
+    changed: &mut PrefixSet,
+    retainer: Option<ProofRetainer>,
+) -> Result<(H256, Option<ProofRetainer>)> {
+    let mut state = txn.cursor(tables::HashedStorage)?;
+    let mut trie_db_cursor = txn.cursor(tables::TrieStorage)?;
+
+    let mut hb = HashBuilder::new();
+    hb.proof_retainer = retainer;
+
+    let mut trie = Cursor::new(&mut trie_db_cursor, changed, hashed_address.as_bytes())?;
+    while trie.key().is_some() {
+        if trie.can_skip_state() {
+            hb.add_branch_node(
+                trie.key().unwrap(),
+                trie.hash().as_ref().unwrap(),
+                trie.children_are_in_trie(),
+            );
+        }
+
+        let uncovered = if let Some(uncovered) = trie.first_uncovered_prefix() {
+            uncovered
+        } else {
+            break;
+        };
+
+        trie.next()?;
+
+        let mut seek_key = uncovered;
+        seek_key.resize(32, 0);
+
+        let mut storage =
+            state.seek_both_range(hashed_address, H256::from_slice(seek_key.as_slice()))?;
+        while let Some((location, value)) = storage {
+            let unpacked_loc = unpack_nibbles(location.as_bytes());
+            if trie.key().is_some() && trie.key().unwrap() < unpacked_loc {
+                break;
+            }
+            hb.add_leaf(unpacked_loc, rlp::encode(&value).as_ref());
+            storage = state.next_dup()?.map(|(_, v)| v);
+        }
+    }
+
+    let root = hb.root_hash();
+    Ok((root, hb.proof_retainer.take()))
+}
+
+/// Generates Merkle proofs for the account and its storage slots against the current hashed state.
+///
+/// Hashed state and intermediate hashes only exist for the latest block,
+/// so the caller must make sure that's the block the proof is requested for.
+pub fn prove_account<E: EnvironmentKind>(
+    txn: &MdbxTransaction<'_, RO, E>,
+    address: Address,
+    storage_keys: &[H256],
+) -> Result<AccountProof> {
+    let hashed_address = keccak256(address);
+    let account_key = unpack_nibbles(hashed_address.as_bytes());
+    let account = txn.get(tables::HashedAccount, hashed_address)?;
+
+    let storage_targets = storage_keys
+        .iter()
+        .map(|key| unpack_nibbles(keccak256(key).as_bytes()))
+        .collect::<Vec<_>>();
+
+    let mut storage_changed = PrefixSet::new();
+    for target in &storage_targets {
+        storage_changed.insert(&[hashed_address.as_bytes(), target.as_slice()].concat());
+    }
+    let (storage_hash, storage_retainer) = calculate_storage_root(
+        txn,
+        hashed_address,
+        &mut storage_changed,
+        Some(ProofRetainer::new(storage_targets.clone())),
+    )?;
+    let storage_retainer = storage_retainer.unwrap();
+
+    let mut storage_proofs = Vec::with_capacity(storage_keys.len());
+    let mut storage_cursor = txn.cursor(tables::HashedStorage)?;
+    for (key, target) in storage_keys.iter().zip(&storage_targets) {
+        let hashed_location = keccak256(key);
+        let value = storage_cursor
+            .seek_both_range(hashed_address, hashed_location)?
+            .filter(|&(location, _)| location == hashed_location)
+            .map(|(_, value)| value)
+            .unwrap_or(U256::ZERO);
+
+        storage_proofs.push(StorageProof {
+            key: *key,
+            value,
+            proof: storage_retainer.proof(target),
+        });
+    }
+
+    let mut changed = PrefixSet::new();
+    changed.insert(&account_key);
+    let retainer = retain_account_nodes(
+        txn,
+        &mut changed,
+        ProofRetainer::new(vec![account_key.clone()]),
+        |hashed_key| {
+            Ok(if hashed_key == hashed_address {
+                storage_hash
+            } else {
+                calculate_storage_root(txn, hashed_key, &mut PrefixSet::new(), None)?.0
+            })
+        },
+    )?;
+
+    Ok(AccountProof {
+        address,
+        account,
+        storage_hash,
+        proof: retainer.proof(&account_key),
+        storage_proofs,
+    })
+}
+
+/// Walks the account trie, keeping the nodes on the paths to the retainer's targets.
+fn retain_account_nodes<E: EnvironmentKind>(
+    txn: &MdbxTransaction<'_, RO, E>,
+    changed: &mut PrefixSet,
+    retainer: ProofRetainer,
+    mut storage_root: impl FnMut(H256) -> Result<H256>,
+) -> Result<ProofRetainer> {
+    let mut state = txn.cursor(tables::HashedAccount)?;
+    let mut trie_db_cursor = txn.cursor(tables::TrieAccount)?;
+
+    let mut hb = HashBuilder::new();
+    hb.proof_retainer = Some(retainer);
+
+    let mut trie = Cursor::new(&mut trie_db_cursor, changed, &[])?;
+    while trie.key().is_some() {
+        if trie.can_skip_state() {
+            hb.add_branch_node(
+                trie.key().unwrap(),
+                trie.hash().as_ref().unwrap(),
+                trie.children_are_in_trie(),
+            );
+        }
+
+        let uncovered = if let Some(uncovered) = trie.first_uncovered_prefix() {
+            uncovered
+        } else {
+            break;
+        };
+
+        trie.next()?;
+
+        let mut seek_key = uncovered;
+        seek_key.resize(32, 0);
+
+        let mut acc = state.seek(H256::from_slice(seek_key.as_slice()))?;
+        while let Some((hashed_key, account)) = acc {
+            let unpacked_key = unpack_nibbles(hashed_key.as_bytes());
+            if trie.key().is_some() && trie.key().unwrap() < unpacked_key {
+                break;
+            }
+
+            hb.add_leaf(
+                unpacked_key,
+                rlp::encode(&account.to_rlp(storage_root(hashed_key)?)).as_ref(),
+            );
+
+            acc = state.next()?;
+        }
+    }
+    hb.root_hash();
+
+    Ok(hb.proof_retainer.unwrap())
+}
+
+/// Trie nodes on the paths to the hashed accounts and to the hashed storage slots of each of them.
+///
+/// Unlike [`prove_account`], keys need not be known by their preimages, so any key of the hashed
+/// state may be targeted.
+pub(crate) fn prove_hashed<E: EnvironmentKind>(
+    txn: &MdbxTransaction<'_, RO, E>,
+    targets: &BTreeMap<H256, BTreeSet<H256>>,
+) -> Result<BTreeSet<Bytes>> {
+    let mut nodes = BTreeSet::new();
+
+    let mut changed = PrefixSet::new();
+    let mut account_targets = Vec::with_capacity(targets.len());
+    for (&hashed_address, locations) in targets {
+        let account_key = unpack_nibbles(hashed_address.as_bytes());
+        changed.insert(&account_key);
+        account_targets.push(account_key);
+
+        if locations.is_empty() {
+            continue;
+        }
+
+        let storage_targets = locations
+            .iter()
+            .map(|location| unpack_nibbles(location.as_bytes()))
+            .collect::<Vec<_>>();
+        let mut storage_changed = PrefixSet::new();
+        for target in &storage_targets {
+            storage_changed.insert(&[hashed_address.as_bytes(), target.as_slice()].concat());
+        }
+        let (_, retainer) = calculate_storage_root(
+            txn,
+            hashed_address,
+            &mut storage_changed,
+            Some(ProofRetainer::new(storage_targets.clone())),
+        )?;
+        let retainer = retainer.unwrap();
+        for target in &storage_targets {
+            nodes.extend(retainer.proof(target));
+        }
+    }
+
+    let retainer = retain_account_nodes(
+        txn,
+        &mut changed,
+        ProofRetainer::new(account_targets.clone()),
+        |hashed_key| Ok(calculate_storage_root(txn, hashed_key, &mut PrefixSet::new(), None)?.0),
+    )?;
+    for target in &account_targets {
+        nodes.extend(retainer.proof(target));
+    }
+
+    Ok(nodes)
+}
+
+enum NodeRef {
+    Hash(H256),
+    Embedded(Vec<u8>),
+}
+
+impl NodeRef {
+    fn from_rlp(rlp: &Rlp) -> Result<Self> {
+        Ok(if rlp.is_data() && rlp.size() == KECCAK_LENGTH {
+            Self::Hash(H256::from_slice(rlp.data()?))
+        } else {
+            Self::Embedded(rlp.as_raw().to_vec())
+        })
+    }
+}
+
+pub(crate) fn decode_path(compact: &[u8]) -> Result<(Vec<u8>, bool)> {
+    let Some(&first) = compact.first() else {
+        bail!("empty node path");
+    };
+
+    let flag = first >> 4;
+    let mut nibbles = Vec::with_capacity(compact.len() * 2);
+    if flag & 1 != 0 {
+        nibbles.push(first & 0x0F);
+    }
+    nibbles.extend_from_slice(&unpack_nibbles(&compact[1..]));
+
+    Ok((nibbles, flag & 2 != 0))
+}
+
+/// Walks the trie from the root down to the key, fetching hashed nodes on the way.
+pub(crate) fn walk_trie(
+    root: H256,
+    key: H256,
+    mut fetch: impl FnMut(H256, usize) -> Result<Vec<u8>>,
+) -> Result<Option<Vec<u8>>> {
+    if root == EMPTY_ROOT {
+        return Ok(None);
+    }
+
+    let path = unpack_nibbles(key.as_bytes());
+    let mut pos = 0;
+    let mut next = NodeRef::Hash(root);
+
+    loop {
+        let node = match next {
+            NodeRef::Hash(hash) => {
+                let node = fetch(hash, pos)?;
+                if keccak256(&node) != hash {
+                    bail!("node hash mismatch at depth {}", pos);
+                }
+                node
+            }
+            NodeRef::Embedded(node) => node,
+        };
+
+        let rlp = Rlp::new(&node);
+        match rlp.item_count()? {
+            17 => {
+                if pos == path.len() {
+                    bail!("branch node at the end of the path");
+                }
+                let child = rlp.at(path[pos] as usize)?;
+                pos += 1;
+                if child.is_empty() {
+                    return Ok(None);
+                }
+                next = NodeRef::from_rlp(&child)?;
+            }
+            2 => {
+                let (partial, is_leaf) = decode_path(rlp.at(0)?.data()?)?;
+                if !path[pos..].starts_with(&partial) {
+                    return Ok(None);
+                }
+                pos += partial.len();
+
+                if is_leaf {
+                    if pos != path.len() {
+                        return Ok(None);
+                    }
+                    return Ok(Some(rlp.at(1)?.data()?.to_vec()));
+                }
+                next = NodeRef::from_rlp(&rlp.at(1)?)?;
+            }
+            other => bail!("invalid node with {} items", other),
+        }
+    }
+}
+
+/// Walks the proof from the root down to the key.
+///
+/// Returns the RLP-encoded leaf value, or `None` if the proof shows that the key is not in the trie.
+pub fn verify_proof(root: H256, key: H256, proof: &[Bytes]) -> Result<Option<Vec<u8>>> {
+    let mut proof = proof.iter();
+    walk_trie(root, key, |_, depth| {
+        proof
+            .next()
+            .map(|node| node.to_vec())
+            .ok_or_else(|| format_err!("proof ends at depth {}", depth))
+    })
+}
+
+/// Verifies account and storage proofs against the state root.
+pub fn verify_account_proof(state_root: H256, proof: &AccountProof) -> Result<()> {
+    let expected = proof
+        .account
+        .map(|account| rlp::encode(&account.to_rlp(proof.storage_hash)).to_vec());
+    let got = verify_proof(state_root, keccak256(proof.address), &proof.proof)?;
+    if got != expected {
+        bail!("account proof mismatch for {:?}", proof.address);
+    }
+
+    for storage_proof in &proof.storage_proofs {
+        let expected = if storage_proof.value == U256::ZERO {
+            None
+        } else {
+            Some(rlp::encode(&storage_proof.value).to_vec())
+        };
+        let got = verify_proof(
+            proof.storage_hash,
+            keccak256(storage_proof.key),
+            &storage_proof.proof,
+        )?;
+        if got != expected {
+            bail!(
+                "storage proof mismatch for {:?} at {:?}",
+                proof.address,
+                storage_proof.key
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        kv::new_mem_database, trie::regenerate_intermediate_hashes, upsert_hashed_storage_value,
+    };
+    use tempfile::TempDir;
+
+    fn int_to_address(i: u64) -> Address {
+        Address::from_low_u64_be(i)
+    }
+
+    #[test]
+    fn account_and_storage_proofs() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = new_mem_database().unwrap();
+
+        let contract = int_to_address(7);
+        let state_root = {
+            let txn = db.begin_mutable().unwrap();
+            let mut hashed_accounts = txn.cursor(tables::HashedAccount).unwrap();
+            for i in 0..1000 {
+                hashed_accounts
+                    .upsert(
+                        keccak256(int_to_address(i)),
+                        Account {
+                            nonce: i,
+                            balance: U256::from(u128::from(i) * ETHER),
+                            ..Default::default()
+                        },
+                    )
+                    .unwrap();
+            }
+
+            let mut hashed_storage = txn.cursor(tables::HashedStorage).unwrap();
+            for i in 1..100_u64 {
+                upsert_hashed_storage_value(
+                    &mut hashed_storage,
+                    keccak256(contract),
+                    keccak256(H256::from_low_u64_be(i)),
+                    U256::from(i),
+                )
+                .unwrap();
+            }
+
+            let root = regenerate_intermediate_hashes(&txn, &temp_dir, None).unwrap();
+            txn.commit().unwrap();
+            root
+        };
+
+        let txn = db.begin().unwrap();
+
+        let proof = prove_account(
+            &txn,
+            contract,
+            &[H256::from_low_u64_be(5), H256::from_low_u64_be(500)],
+        )
+        .unwrap();
+        assert_eq!(proof.account.unwrap().nonce, 7);
+        assert_ne!(proof.storage_hash, EMPTY_ROOT);
+        assert_eq!(proof.storage_proofs[0].value, U256::from(5_u64));
+        assert_eq!(proof.storage_proofs[1].value, U256::ZERO);
+        verify_account_proof(state_root, &proof).unwrap();
+
+        let mut forged = proof.clone();
+        forged.storage_proofs[0].value = U256::from(6_u64);
+        assert!(verify_account_proof(state_root, &forged).is_err());
+
+        let missing = prove_account(&txn, int_to_address(100_000), &[]).unwrap();
+        assert_eq!(missing.account, None);
+        assert_eq!(missing.storage_hash, EMPTY_ROOT);
+        verify_account_proof(state_root, &missing).unwrap();
+    }
+
+    #[test]
+    fn empty_state() {
+        let db = new_mem_database().unwrap();
+        let txn = db.begin().unwrap();
+
+        let proof = prove_account(&txn, int_to_address(1), &[H256::zero()]).unwrap();
+        assert_eq!(proof.proof, Vec::<Bytes>::new());
+        verify_account_proof(EMPTY_ROOT, &proof).unwrap();
+    }
+}
@@ -1,7 +1,6 @@
 use bytes::{Bytes, BytesMut};
 use ethereum_types::*;
 use ethnum::U256;
-use num_traits::Zero;
 use serde::{
     de::{self, Error},
     Deserialize,
@@ -48,11 +47,6 @@ pub fn h256_to_u256(v: impl Borrow<H256>) -> U256 {
     U256::from_be_bytes(v.borrow().0)
 }
 
-pub fn zeroless_view(v: &impl AsRef<[u8]>) -> &[u8] {
-    let v = v.as_ref();
-    &v[v.iter().take_while(|b| b.is_zero()).count()..]
-}
-
 pub fn hex_to_bytes(s: &str) -> Result<Bytes, hex::FromHexError> {
     hex::decode(s).map(From::from)
 }
@@ -118,7 +112,6 @@ mod tests {
     use super::*;
     use bytes::Buf;
     use bytes_literal::bytes;
-    use hex_literal::hex;
 
     #[test]
     fn padding() {
@@ -148,20 +141,4 @@ mod tests {
         repeatedly_padded = left_pad(repeatedly_padded, 4);
         assert_eq!(repeatedly_padded, bytes!("000000b8"));
     }
-
-    #[test]
-    fn zeroless_view_test() {
-        assert_eq!(
-            zeroless_view(&H256::from(hex!(
-                "0000000000000000000000000000000000000000000000000000000000000000"
-            ))),
-            &bytes!("") as &[u8]
-        );
-        assert_eq!(
-            zeroless_view(&H256::from(hex!(
-                "000000000000000000000000000000000000000000000000000000000004bc00"
-            ))),
-            &bytes!("04bc00") as &[u8]
-        );
-    }
 }
@@ -0,0 +1,346 @@
+//! Block witnesses for stateless execution.
+//!
+//! A witness carries every trie node, contract code and ancestor header that block execution touches,
+//! which is enough to re-execute the block without access to the state database.
+use crate::{
+    crypto::keccak256,
+    execution::execute_block,
+    kv::{mdbx::*, tables},
+    models::*,
+    trie::prove_hashed,
+    u256_to_h256, Buffer,
+};
+use anyhow::format_err;
+use bytes::Bytes;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use std::collections::{BTreeMap, BTreeSet};
+
+mod recorder;
+mod trie;
+mod verifier;
+
+pub use self::{recorder::*, verifier::*};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BlockWitness {
+    /// Ancestor headers, starting with the parent of the witnessed block.
+    /// The parent's state root is the root of the witness trie.
+    pub headers: Vec<BlockHeader>,
+    /// Account and storage trie nodes, in no particular order.
+    pub nodes: Vec<Bytes>,
+    pub codes: Vec<Bytes>,
+}
+
+impl Encodable for BlockWitness {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(3);
+        s.append_list(&self.headers);
+        s.begin_list(self.nodes.len());
+        for node in &self.nodes {
+            s.append(&node.as_ref());
+        }
+        s.begin_list(self.codes.len());
+        for code in &self.codes {
+            s.append(&code.as_ref());
+        }
+    }
+}
+
+impl Decodable for BlockWitness {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 3 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+
+        let decode_blobs = |rlp: Rlp| {
+            rlp.iter()
+                .map(|item| item.data().map(Bytes::copy_from_slice))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(Self {
+            headers: rlp.list_at(0)?,
+            nodes: decode_blobs(rlp.at(1)?)?,
+            codes: decode_blobs(rlp.at(2)?)?,
+        })
+    }
+}
+
+/// Executes the block on top of the current state and collects its witness.
+///
+/// Plain state, hashed state and intermediate hashes must all be at the block's parent.
+pub fn generate_witness<E: EnvironmentKind>(
+    txn: &MdbxTransaction<'_, RO, E>,
+    chain_spec: &ChainSpec,
+    header: &PartialHeader,
+    block: &BlockBodyWithSenders,
+) -> anyhow::Result<BlockWitness> {
+    let parent_number = BlockNumber(
+        header
+            .number
+            .0
+            .checked_sub(1)
+            .ok_or_else(|| format_err!("Genesis block has no parent to build a witness on"))?,
+    );
+    let parent = txn
+        .get(tables::Header, (parent_number, header.parent_hash))?
+        .ok_or_else(|| {
+            format_err!(
+                "Parent header not found: {}/{:?}",
+                parent_number,
+                header.parent_hash
+            )
+        })?;
+
+    let mut buffer = Buffer::new(txn, BlockNumber(0), None);
+    let mut recorder = StateRecorder::new(&mut buffer);
+    execute_block(&mut recorder, chain_spec, header, block)?;
+    let accessed = recorder.into_accessed();
+
+    // Deleting a key may merge its branch node into the sibling subtrie, which lies on the path
+    // to one of the neighbouring keys.
+    let mut targets = BTreeMap::<H256, BTreeSet<H256>>::new();
+    let mut account_cursor = txn.cursor(tables::HashedAccount)?;
+    let mut storage_cursor = txn.cursor(tables::HashedStorage)?;
+    for address in accessed.accounts {
+        let hashed_address = keccak256(address);
+        for neighbour in account_neighbours(&mut account_cursor, hashed_address)? {
+            targets.entry(neighbour).or_default();
+        }
+
+        let mut locations = BTreeSet::new();
+        for &location in accessed.storage.get(&address).into_iter().flatten() {
+            let hashed_location = keccak256(u256_to_h256(location));
+            locations.extend(storage_neighbours(
+                &mut storage_cursor,
+                hashed_address,
+                hashed_location,
+            )?);
+            locations.insert(hashed_location);
+        }
+        targets.entry(hashed_address).or_default().extend(locations);
+    }
+    let nodes = prove_hashed(txn, &targets)?;
+
+    // intermediate headers link the accessed ones to the parent
+    let mut headers = vec![parent];
+    if let Some(&lowest) = accessed.headers.keys().next() {
+        while headers.last().unwrap().number > lowest {
+            let child = headers.last().unwrap();
+            let number = BlockNumber(child.number.0 - 1);
+            let header = txn
+                .get(tables::Header, (number, child.parent_hash))?
+                .ok_or_else(|| {
+                    format_err!("Header not found: {}/{:?}", number, child.parent_hash)
+                })?;
+            headers.push(header);
+        }
+    }
+
+    Ok(BlockWitness {
+        headers,
+        nodes: nodes.into_iter().collect(),
+        codes: accessed.codes.into_values().collect(),
+    })
+}
+
+fn account_neighbours<K: TransactionKind>(
+    cursor: &mut MdbxCursor<'_, K, tables::HashedAccount>,
+    key: H256,
+) -> anyhow::Result<Vec<H256>> {
+    let next = match cursor.seek(key)? {
+        Some((found, _)) if found == key => cursor.next()?,
+        other => other,
+    };
+    let previous = match cursor.seek(key)? {
+        Some(_) => cursor.prev()?,
+        None => cursor.last()?,
+    };
+
+    Ok(next
+        .into_iter()
+        .chain(previous)
+        .map(|(key, _)| key)
+        .collect())
+}
+
+fn storage_neighbours<K: TransactionKind>(
+    cursor: &mut MdbxCursor<'_, K, tables::HashedStorage>,
+    address: H256,
+    location: H256,
+) -> anyhow::Result<Vec<H256>> {
+    let next = match cursor.seek_both_range(address, location)? {
+        Some((found, _)) if found == location => cursor.next_dup()?.map(|(_, value)| value),
+        other => other,
+    };
+    let previous = match cursor.seek_both_range(address, location)? {
+        Some(_) => cursor.prev_dup()?.map(|(_, value)| value),
+        None => match cursor.seek_exact(address)? {
+            Some(_) => cursor.last_dup()?,
+            None => None,
+        },
+    };
+
+    Ok(next
+        .into_iter()
+        .chain(previous)
+        .map(|(location, _)| location)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crypto::trie_root, kv::new_mem_database, res::chainspec::MAINNET,
+        trie::regenerate_intermediate_hashes,
+    };
+    use hex_literal::hex;
+    use tempfile::TempDir;
+
+    #[test]
+    fn witness_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = new_mem_database().unwrap();
+
+        let sender = hex!("b685342b8c54347aad148e1f22eff3eb3eb29391").into();
+        let recipient = hex!("5a0b54d5dc17e0aadc383d2db43b0a0d3e029c4c").into();
+        let miner = hex!("8d12a197cb00d4747a1fe03395095ce2a5cc6819").into();
+
+        let parent = {
+            let txn = db.begin_mutable().unwrap();
+            let sender_account = Account {
+                balance: ETHER.into(),
+                ..Account::default()
+            };
+            for i in 0..100_u64 {
+                let address = Address::from_low_u64_be(i + 1);
+                let account = Account {
+                    nonce: i,
+                    ..Account::default()
+                };
+                txn.set(tables::Account, address, account).unwrap();
+                txn.set(tables::HashedAccount, keccak256(address), account)
+                    .unwrap();
+            }
+            txn.set(tables::Account, sender, sender_account).unwrap();
+            txn.set(tables::HashedAccount, keccak256(sender), sender_account)
+                .unwrap();
+
+            let parent = BlockHeader {
+                state_root: regenerate_intermediate_hashes(&txn, &temp_dir, None).unwrap(),
+                ..BlockHeader::empty()
+            };
+            txn.set(
+                tables::Header,
+                (parent.number, parent.hash()),
+                parent.clone(),
+            )
+            .unwrap();
+            txn.commit().unwrap();
+            parent
+        };
+
+        let fee = U256::from(21_000 * 20 * GIGA);
+        let post_state = (0..100_u64)
+            .map(|i| {
+                (
+                    Address::from_low_u64_be(i + 1),
+                    Account {
+                        nonce: i,
+                        ..Account::default()
+                    },
+                )
+            })
+            .chain([
+                (
+                    sender,
+                    Account {
+                        nonce: 1,
+                        balance: U256::from(ETHER) - fee - 1000.as_u256(),
+                        ..Account::default()
+                    },
+                ),
+                (
+                    recipient,
+                    Account {
+                        balance: 1000.as_u256(),
+                        ..Account::default()
+                    },
+                ),
+                (
+                    miner,
+                    Account {
+                        balance: U256::from(5 * ETHER) + fee,
+                        ..Account::default()
+                    },
+                ),
+            ])
+            .map(|(address, account)| {
+                (keccak256(address), rlp::encode(&account.to_rlp(EMPTY_ROOT)))
+            });
+
+        let header = PartialHeader {
+            parent_hash: parent.hash(),
+            number: BlockNumber(1),
+            beneficiary: miner,
+            state_root: trie_root(post_state),
+            gas_limit: 100_000,
+            gas_used: 21_000,
+            ..PartialHeader::empty()
+        };
+        let body = BlockBodyWithSenders {
+            transactions: vec![MessageWithSender {
+                message: Message::Legacy {
+                    chain_id: None,
+                    nonce: 0,
+                    gas_price: U256::from(20 * GIGA),
+                    gas_limit: 21_000,
+                    action: TransactionAction::Call(recipient),
+                    value: 1000.as_u256(),
+                    input: Bytes::new(),
+                },
+                sender,
+            }],
+            ommers: vec![],
+        };
+
+        let witness = generate_witness(&db.begin().unwrap(), &MAINNET, &header, &body).unwrap();
+        assert_eq!(witness.headers, vec![parent]);
+        assert!(!witness.nodes.is_empty());
+
+        let decoded = rlp::decode::<BlockWitness>(&rlp::encode(&witness)).unwrap();
+        assert_eq!(decoded, witness);
+
+        let receipts = verify_witness(&witness, &MAINNET, &header, &body).unwrap();
+        assert_eq!(receipts.len(), 1);
+
+        let mut incomplete = witness.clone();
+        incomplete
+            .nodes
+            .retain(|node| keccak256(node) != witness.headers[0].state_root);
+        assert!(verify_witness(&incomplete, &MAINNET, &header, &body).is_err());
+
+        let mut unrelated = witness.clone();
+        unrelated.headers[0].gas_limit += 1;
+        assert!(verify_witness(&unrelated, &MAINNET, &header, &body).is_err());
+
+        let wrong_root = PartialHeader {
+            state_root: H256::repeat_byte(1),
+            ..header.clone()
+        };
+        assert!(verify_witness(&witness, &MAINNET, &wrong_root, &body).is_err());
+    }
+
+    #[test]
+    fn genesis_has_no_witness() {
+        let db = new_mem_database().unwrap();
+        assert!(generate_witness(
+            &db.begin().unwrap(),
+            &MAINNET,
+            &PartialHeader::empty(),
+            &BlockBodyWithSenders::default()
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{models::*, State};
+use bytes::Bytes;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Parts of the state accessed during block execution.
+#[derive(Debug, Default)]
+pub struct AccessedState {
+    pub accounts: BTreeSet<Address>,
+    pub storage: BTreeMap<Address, BTreeSet<U256>>,
+    pub codes: BTreeMap<H256, Bytes>,
+    pub headers: BTreeMap<BlockNumber, BlockHeader>,
+}
+
+/// State wrapper that records every account, storage slot, code and header read through it.
+#[derive(Debug)]
+pub struct StateRecorder<S: State> {
+    inner: S,
+    accessed: Mutex<AccessedState>,
+}
+
+impl<S: State> StateRecorder<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            accessed: Default::default(),
+        }
+    }
+
+    pub fn into_accessed(self) -> AccessedState {
+        self.accessed.into_inner()
+    }
+}
+
+impl<S: State> State for StateRecorder<S> {
+    fn read_account(&self, address: Address) -> anyhow::Result<Option<Account>> {
+        self.accessed.lock().accounts.insert(address);
+        self.inner.read_account(address)
+    }
+
+    fn read_code(&self, code_hash: H256) -> anyhow::Result<Bytes> {
+        let code = self.inner.read_code(code_hash)?;
+        self.accessed.lock().codes.insert(code_hash, code.clone());
+        Ok(code)
+    }
+
+    fn read_storage(&self, address: Address, location: U256) -> anyhow::Result<U256> {
+        let mut accessed = self.accessed.lock();
+        accessed.accounts.insert(address);
+        accessed
+            .storage
+            .entry(address)
+            .or_default()
+            .insert(location);
+        drop(accessed);
+
+        self.inner.read_storage(address, location)
+    }
+
+    fn erase_storage(&mut self, address: Address) -> anyhow::Result<()> {
+        self.inner.erase_storage(address)
+    }
+
+    fn read_header(
+        &self,
+        block_number: BlockNumber,
+        block_hash: H256,
+    ) -> anyhow::Result<Option<BlockHeader>> {
+        let header = self.inner.read_header(block_number, block_hash)?;
+        if let Some(header) = &header {
+            self.accessed
+                .lock()
+                .headers
+                .insert(block_number, header.clone());
+        }
+        Ok(header)
+    }
+
+    fn read_body(
+        &self,
+        block_number: BlockNumber,
+        block_hash: H256,
+    ) -> anyhow::Result<Option<BlockBody>> {
+        self.inner.read_body(block_number, block_hash)
+    }
+
+    fn total_difficulty(
+        &self,
+        block_number: BlockNumber,
+        block_hash: H256,
+    ) -> anyhow::Result<Option<U256>> {
+        self.inner.total_difficulty(block_number, block_hash)
+    }
+
+    fn begin_block(&mut self, block_number: BlockNumber) {
+        self.inner.begin_block(block_number)
+    }
+
+    fn update_account(
+        &mut self,
+        address: Address,
+        initial: Option<Account>,
+        current: Option<Account>,
+    ) {
+        self.inner.update_account(address, initial, current)
+    }
+
+    fn update_code(&mut self, code_hash: H256, code: Bytes) -> anyhow::Result<()> {
+        self.inner.update_code(code_hash, code)
+    }
+
+    fn update_storage(
+        &mut self,
+        address: Address,
+        location: U256,
+        initial: U256,
+        current: U256,
+    ) -> anyhow::Result<()> {
+        self.inner
+            .update_storage(address, location, initial, current)
+    }
+}
@@ -0,0 +1,400 @@
+//! Merkle Patricia trie over the nodes of a witness.
+//!
+//! Subtries the block does not touch stay unresolved and are only known by their hashes,
+//! which is enough to compute the root after the block's writes.
+use crate::{
+    crypto::keccak256,
+    models::*,
+    trie::{decode_path, encode_path, unpack_nibbles},
+};
+use anyhow::{bail, format_err};
+use bytes::Bytes;
+use rlp::{Rlp, RlpStream};
+use std::{collections::HashMap, mem};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Empty,
+    /// Node that was not needed yet.
+    Hash(H256),
+    Leaf {
+        path: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Extension {
+        path: Vec<u8>,
+        child: Box<Node>,
+    },
+    /// State tries have keys of the same length, so branches never hold values.
+    Branch(Box<[Node; 16]>),
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        Self::Empty
+    }
+}
+
+impl Node {
+    fn decode(rlp: &Rlp) -> anyhow::Result<Self> {
+        Ok(match rlp.item_count()? {
+            17 => {
+                let mut children = Box::<[Node; 16]>::default();
+                for (i, child) in children.iter_mut().enumerate() {
+                    *child = Self::decode_ref(&rlp.at(i)?)?;
+                }
+                Self::Branch(children)
+            }
+            2 => {
+                let (path, is_leaf) = decode_path(rlp.at(0)?.data()?)?;
+                if is_leaf {
+                    Self::Leaf {
+                        path,
+                        value: rlp.at(1)?.data()?.to_vec(),
+                    }
+                } else {
+                    Self::Extension {
+                        path,
+                        child: Box::new(Self::decode_ref(&rlp.at(1)?)?),
+                    }
+                }
+            }
+            other => bail!("invalid node with {} items", other),
+        })
+    }
+
+    fn decode_ref(rlp: &Rlp) -> anyhow::Result<Self> {
+        Ok(if rlp.is_empty() {
+            Self::Empty
+        } else if rlp.is_data() && rlp.size() == KECCAK_LENGTH {
+            Self::Hash(H256::from_slice(rlp.data()?))
+        } else if rlp.is_list() {
+            Self::decode(rlp)?
+        } else {
+            bail!("invalid node reference")
+        })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut s = RlpStream::new();
+        match self {
+            Self::Leaf { path, value } => {
+                s.begin_list(2);
+                s.append(&encode_path(path, true));
+                s.append(value);
+            }
+            Self::Extension { path, child } => {
+                s.begin_list(2);
+                s.append(&encode_path(path, false));
+                child.append_ref(&mut s);
+            }
+            Self::Branch(children) => {
+                s.begin_list(17);
+                for child in children.iter() {
+                    child.append_ref(&mut s);
+                }
+                s.append_empty_data();
+            }
+            Self::Empty | Self::Hash(_) => unreachable!("only resolved nodes are encoded"),
+        }
+        s.out().to_vec()
+    }
+
+    /// Nodes shorter than a hash are embedded into their parents.
+    fn append_ref(&self, s: &mut RlpStream) {
+        match self {
+            Self::Empty => {
+                s.append_empty_data();
+            }
+            Self::Hash(hash) => {
+                s.append(hash);
+            }
+            node => {
+                let rlp = node.encode();
+                if rlp.len() < KECCAK_LENGTH {
+                    s.append_raw(&rlp, 1);
+                } else {
+                    s.append(&keccak256(&rlp));
+                }
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        matches!(self, Self::Empty)
+    }
+}
+
+/// Wraps `node` into an extension, unless `path` is empty.
+fn extend(path: &[u8], node: Node) -> Node {
+    if path.is_empty() {
+        return node;
+    }
+
+    match node {
+        Node::Empty => Node::Empty,
+        Node::Leaf { path: rest, value } => Node::Leaf {
+            path: [path, &rest].concat(),
+            value,
+        },
+        Node::Extension { path: rest, child } => Node::Extension {
+            path: [path, &rest].concat(),
+            child,
+        },
+        node => Node::Extension {
+            path: path.to_vec(),
+            child: Box::new(node),
+        },
+    }
+}
+
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+#[derive(Debug)]
+pub(super) struct PartialTrie<'w> {
+    nodes: &'w HashMap<H256, Bytes>,
+    root: Node,
+}
+
+impl<'w> PartialTrie<'w> {
+    pub(super) fn new(root: H256, nodes: &'w HashMap<H256, Bytes>) -> Self {
+        Self {
+            nodes,
+            root: if root == EMPTY_ROOT {
+                Node::Empty
+            } else {
+                Node::Hash(root)
+            },
+        }
+    }
+
+    pub(super) fn root_hash(&self) -> H256 {
+        match &self.root {
+            Node::Empty => EMPTY_ROOT,
+            Node::Hash(hash) => *hash,
+            node => keccak256(node.encode()),
+        }
+    }
+
+    pub(super) fn insert(&mut self, key: H256, value: Vec<u8>) -> anyhow::Result<()> {
+        let root = mem::take(&mut self.root);
+        self.root = self.insert_at(root, &unpack_nibbles(key.as_bytes()), value)?;
+
+        Ok(())
+    }
+
+    pub(super) fn remove(&mut self, key: H256) -> anyhow::Result<()> {
+        let root = mem::take(&mut self.root);
+        self.root = self.remove_at(root, &unpack_nibbles(key.as_bytes()))?;
+
+        Ok(())
+    }
+
+    fn resolve(&self, hash: H256) -> anyhow::Result<Node> {
+        let node = self
+            .nodes
+            .get(&hash)
+            .ok_or_else(|| format_err!("trie node {:?} is missing from the witness", hash))?;
+
+        Node::decode(&Rlp::new(node))
+    }
+
+    fn insert_at(&self, node: Node, path: &[u8], value: Vec<u8>) -> anyhow::Result<Node> {
+        Ok(match node {
+            Node::Empty => Node::Leaf {
+                path: path.to_vec(),
+                value,
+            },
+            Node::Hash(hash) => self.insert_at(self.resolve(hash)?, path, value)?,
+            Node::Leaf {
+                path: leaf_path,
+                value: leaf_value,
+            } => {
+                let common = common_prefix(&leaf_path, path);
+                if common == path.len() {
+                    Node::Leaf {
+                        path: leaf_path,
+                        value,
+                    }
+                } else {
+                    let mut children = Box::<[Node; 16]>::default();
+                    children[leaf_path[common] as usize] = Node::Leaf {
+                        path: leaf_path[common + 1..].to_vec(),
+                        value: leaf_value,
+                    };
+                    children[path[common] as usize] = Node::Leaf {
+                        path: path[common + 1..].to_vec(),
+                        value,
+                    };
+                    extend(&path[..common], Node::Branch(children))
+                }
+            }
+            Node::Extension {
+                path: extension_path,
+                child,
+            } => {
+                let common = common_prefix(&extension_path, path);
+                if common == extension_path.len() {
+                    Node::Extension {
+                        child: Box::new(self.insert_at(*child, &path[common..], value)?),
+                        path: extension_path,
+                    }
+                } else {
+                    let mut children = Box::<[Node; 16]>::default();
+                    children[extension_path[common] as usize] =
+                        extend(&extension_path[common + 1..], *child);
+                    children[path[common] as usize] = Node::Leaf {
+                        path: path[common + 1..].to_vec(),
+                        value,
+                    };
+                    extend(&path[..common], Node::Branch(children))
+                }
+            }
+            Node::Branch(mut children) => {
+                let Some((&nibble, rest)) = path.split_first() else {
+                    bail!("key ends at a branch node");
+                };
+                let child = mem::take(&mut children[nibble as usize]);
+                children[nibble as usize] = self.insert_at(child, rest, value)?;
+                Node::Branch(children)
+            }
+        })
+    }
+
+    fn remove_at(&self, node: Node, path: &[u8]) -> anyhow::Result<Node> {
+        Ok(match node {
+            Node::Empty => Node::Empty,
+            Node::Hash(hash) => self.remove_at(self.resolve(hash)?, path)?,
+            Node::Leaf {
+                path: leaf_path,
+                value,
+            } => {
+                if leaf_path == path {
+                    Node::Empty
+                } else {
+                    Node::Leaf {
+                        path: leaf_path,
+                        value,
+                    }
+                }
+            }
+            Node::Extension {
+                path: extension_path,
+                child,
+            } => {
+                if let Some(rest) = path.strip_prefix(extension_path.as_slice()) {
+                    let child = self.remove_at(*child, rest)?;
+                    extend(&extension_path, child)
+                } else {
+                    Node::Extension {
+                        path: extension_path,
+                        child,
+                    }
+                }
+            }
+            Node::Branch(mut children) => {
+                let Some((&nibble, rest)) = path.split_first() else {
+                    bail!("key ends at a branch node");
+                };
+                let child = mem::take(&mut children[nibble as usize]);
+                children[nibble as usize] = self.remove_at(child, rest)?;
+
+                let mut remaining = children
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, child)| !child.is_empty());
+                match (remaining.next(), remaining.next()) {
+                    (None, _) => Node::Empty,
+                    // a branch with a single child is merged into it
+                    (Some((last, _)), None) => {
+                        let child = match mem::take(&mut children[last]) {
+                            Node::Hash(hash) => self.resolve(hash)?,
+                            child => child,
+                        };
+                        extend(&[last as u8], child)
+                    }
+                    _ => Node::Branch(children),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::trie_root;
+
+    fn entries(count: u64) -> Vec<(H256, Vec<u8>)> {
+        (0..count)
+            .map(|i| (keccak256(i.to_be_bytes()), rlp::encode(&(i + 1)).to_vec()))
+            .collect()
+    }
+
+    /// Hashed nodes of the trie, as a witness would carry them.
+    fn collect_nodes(node: &Node, nodes: &mut HashMap<H256, Bytes>) {
+        match node {
+            Node::Extension { child, .. } => collect_nodes(child, nodes),
+            Node::Branch(children) => {
+                for child in children.iter() {
+                    collect_nodes(child, nodes);
+                }
+            }
+            _ => {}
+        }
+        if !matches!(node, Node::Empty | Node::Hash(_)) {
+            let rlp = node.encode();
+            nodes.insert(keccak256(&rlp), rlp.into());
+        }
+    }
+
+    #[test]
+    fn matches_full_trie() {
+        let nodes = HashMap::new();
+        let mut trie = PartialTrie::new(EMPTY_ROOT, &nodes);
+        assert_eq!(trie.root_hash(), EMPTY_ROOT);
+
+        let entries = entries(100);
+        for (key, value) in &entries {
+            trie.insert(*key, value.clone()).unwrap();
+        }
+        assert_eq!(trie.root_hash(), trie_root(entries.clone()));
+
+        for (key, _) in &entries[..50] {
+            trie.remove(*key).unwrap();
+        }
+        trie.remove(H256::repeat_byte(1)).unwrap();
+        assert_eq!(trie.root_hash(), trie_root(entries[50..].to_vec()));
+
+        for (key, _) in &entries[50..] {
+            trie.remove(*key).unwrap();
+        }
+        assert_eq!(trie.root_hash(), EMPTY_ROOT);
+    }
+
+    #[test]
+    fn updates_from_witness_nodes() {
+        let entries = entries(20);
+
+        let empty = HashMap::new();
+        let mut full = PartialTrie::new(EMPTY_ROOT, &empty);
+        for (key, value) in &entries {
+            full.insert(*key, value.clone()).unwrap();
+        }
+        let mut nodes = HashMap::new();
+        collect_nodes(&full.root, &mut nodes);
+
+        let mut trie = PartialTrie::new(full.root_hash(), &nodes);
+        trie.remove(entries[0].0).unwrap();
+        trie.insert(entries[1].0, vec![0x42]).unwrap();
+
+        let mut expected = entries[1..].to_vec();
+        expected[0].1 = vec![0x42];
+        assert_eq!(trie.root_hash(), trie_root(expected));
+
+        let mut incomplete = PartialTrie::new(full.root_hash(), &empty);
+        assert!(incomplete.remove(entries[0].0).is_err());
+    }
+}
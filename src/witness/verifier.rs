@@ -0,0 +1,284 @@
+use super::{trie::PartialTrie, BlockWitness};
+use crate::{
+    crypto::{keccak256, ordered_trie_root},
+    execution::execute_block,
+    models::*,
+    trie::walk_trie,
+    u256_to_h256, State,
+};
+use anyhow::{bail, format_err};
+use bytes::Bytes;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// State backed solely by a block witness.
+///
+/// Reads are resolved by walking the partial trie contained in the witness,
+/// writes are kept in memory so that they are visible to the rest of the block.
+#[derive(Debug)]
+pub struct WitnessState {
+    state_root: H256,
+    nodes: HashMap<H256, Bytes>,
+    codes: HashMap<H256, Bytes>,
+    headers: HashMap<H256, BlockHeader>,
+
+    accounts: HashMap<Address, Option<Account>>,
+    storage: HashMap<Address, HashMap<U256, U256>>,
+    erased: HashSet<Address>,
+}
+
+impl WitnessState {
+    pub fn new(witness: &BlockWitness) -> anyhow::Result<Self> {
+        let parent = witness
+            .headers
+            .first()
+            .ok_or_else(|| format_err!("witness has no parent header"))?;
+
+        Ok(Self {
+            state_root: parent.state_root,
+            nodes: witness
+                .nodes
+                .iter()
+                .map(|node| (keccak256(node), node.clone()))
+                .collect(),
+            codes: witness
+                .codes
+                .iter()
+                .map(|code| (keccak256(code), code.clone()))
+                .collect(),
+            headers: witness
+                .headers
+                .iter()
+                .map(|header| (header.hash(), header.clone()))
+                .collect(),
+            accounts: Default::default(),
+            storage: Default::default(),
+            erased: Default::default(),
+        })
+    }
+
+    fn lookup(&self, root: H256, key: H256) -> anyhow::Result<Option<Vec<u8>>> {
+        walk_trie(root, key, |hash, _| {
+            self.nodes
+                .get(&hash)
+                .map(|node| node.to_vec())
+                .ok_or_else(|| format_err!("trie node {:?} is missing from the witness", hash))
+        })
+    }
+
+    fn read_rlp_account(&self, address: Address) -> anyhow::Result<Option<RlpAccount>> {
+        self.lookup(self.state_root, keccak256(address))?
+            .map(|v| rlp::decode::<RlpAccount>(&v))
+            .transpose()
+            .map_err(From::from)
+    }
+
+    /// State root after the writes of the executed block.
+    fn post_state_root(&self) -> anyhow::Result<H256> {
+        let mut trie = PartialTrie::new(self.state_root, &self.nodes);
+
+        let addresses = self
+            .accounts
+            .keys()
+            .chain(self.storage.keys())
+            .chain(&self.erased)
+            .copied()
+            .collect::<BTreeSet<_>>();
+        for address in addresses {
+            let previous = self.read_rlp_account(address)?;
+            let hashed_address = keccak256(address);
+            let Some(account) = self.read_account(address)? else {
+                trie.remove(hashed_address)?;
+                continue;
+            };
+
+            let storage_root = if self.erased.contains(&address) {
+                EMPTY_ROOT
+            } else {
+                previous
+                    .map(|account| account.storage_root)
+                    .unwrap_or(EMPTY_ROOT)
+            };
+            let mut storage = PartialTrie::new(storage_root, &self.nodes);
+            for (&location, value) in self.storage.get(&address).into_iter().flatten() {
+                let key = keccak256(u256_to_h256(location));
+                if *value == U256::ZERO {
+                    storage.remove(key)?;
+                } else {
+                    storage.insert(key, rlp::encode(value).to_vec())?;
+                }
+            }
+
+            trie.insert(
+                hashed_address,
+                rlp::encode(&account.to_rlp(storage.root_hash())).to_vec(),
+            )?;
+        }
+
+        Ok(trie.root_hash())
+    }
+}
+
+impl State for WitnessState {
+    fn read_account(&self, address: Address) -> anyhow::Result<Option<Account>> {
+        if let Some(account) = self.accounts.get(&address) {
+            return Ok(*account);
+        }
+
+        Ok(self.read_rlp_account(address)?.map(|account| Account {
+            nonce: account.nonce,
+            balance: account.balance,
+            code_hash: account.code_hash,
+        }))
+    }
+
+    fn read_code(&self, code_hash: H256) -> anyhow::Result<Bytes> {
+        if code_hash == EMPTY_HASH {
+            return Ok(Bytes::new());
+        }
+
+        self.codes
+            .get(&code_hash)
+            .cloned()
+            .ok_or_else(|| format_err!("code {:?} is missing from the witness", code_hash))
+    }
+
+    fn read_storage(&self, address: Address, location: U256) -> anyhow::Result<U256> {
+        if let Some(value) = self
+            .storage
+            .get(&address)
+            .and_then(|slots| slots.get(&location))
+        {
+            return Ok(*value);
+        }
+
+        if self.erased.contains(&address) {
+            return Ok(U256::ZERO);
+        }
+
+        let Some(account) = self.read_rlp_account(address)? else {
+            return Ok(U256::ZERO);
+        };
+
+        Ok(self
+            .lookup(account.storage_root, keccak256(u256_to_h256(location)))?
+            .map(|v| rlp::decode::<U256>(&v))
+            .transpose()?
+            .unwrap_or(U256::ZERO))
+    }
+
+    fn erase_storage(&mut self, address: Address) -> anyhow::Result<()> {
+        self.storage.remove(&address);
+        self.erased.insert(address);
+
+        Ok(())
+    }
+
+    fn read_header(
+        &self,
+        block_number: BlockNumber,
+        block_hash: H256,
+    ) -> anyhow::Result<Option<BlockHeader>> {
+        Ok(self
+            .headers
+            .get(&block_hash)
+            .filter(|header| header.number == block_number)
+            .cloned())
+    }
+
+    fn read_body(&self, _: BlockNumber, _: H256) -> anyhow::Result<Option<BlockBody>> {
+        Ok(None)
+    }
+
+    fn total_difficulty(&self, _: BlockNumber, _: H256) -> anyhow::Result<Option<U256>> {
+        Ok(None)
+    }
+
+    fn begin_block(&mut self, _: BlockNumber) {}
+
+    fn update_account(&mut self, address: Address, _: Option<Account>, current: Option<Account>) {
+        if current.is_none() {
+            self.storage.remove(&address);
+            self.erased.insert(address);
+        }
+        self.accounts.insert(address, current);
+    }
+
+    fn update_code(&mut self, code_hash: H256, code: Bytes) -> anyhow::Result<()> {
+        self.codes.insert(code_hash, code);
+
+        Ok(())
+    }
+
+    fn update_storage(
+        &mut self,
+        address: Address,
+        location: U256,
+        _: U256,
+        current: U256,
+    ) -> anyhow::Result<()> {
+        self.storage
+            .entry(address)
+            .or_default()
+            .insert(location, current);
+
+        Ok(())
+    }
+}
+
+/// Re-executes the block using nothing but the witness.
+///
+/// Ancestor headers must form a chain ending in the block's parent, whose state root anchors the witness trie.
+/// Execution fails if the witness lacks any trie node or code the block needs, verification fails if the
+/// state or receipts root computed from the witness differs from the header's.
+pub fn verify_witness(
+    witness: &BlockWitness,
+    chain_spec: &ChainSpec,
+    header: &PartialHeader,
+    block: &BlockBodyWithSenders,
+) -> anyhow::Result<Vec<Receipt>> {
+    let mut expected_hash = header.parent_hash;
+    let mut expected_number = header.number.0.checked_sub(1);
+    for ancestor in &witness.headers {
+        if Some(ancestor.number.0) != expected_number || ancestor.hash() != expected_hash {
+            bail!(
+                "witness header #{} ({:?}) is not an ancestor of block #{}",
+                ancestor.number,
+                ancestor.hash(),
+                header.number
+            );
+        }
+        expected_hash = ancestor.parent_hash;
+        expected_number = ancestor.number.0.checked_sub(1);
+    }
+
+    let mut state = WitnessState::new(witness)?;
+    let receipts = execute_block(&mut state, chain_spec, header, block)?;
+
+    let state_root = state.post_state_root()?;
+    if state_root != header.state_root {
+        bail!(
+            "state root mismatch for block #{}: header {:?}, computed {:?}",
+            header.number,
+            header.state_root,
+            state_root
+        );
+    }
+
+    // receipts of earlier blocks carry intermediate state roots, which are not kept
+    let revision = chain_spec
+        .collect_block_spec(header.number, header.timestamp)
+        .revision;
+    if revision >= Revision::Byzantium {
+        let receipts_root = ordered_trie_root(&receipts);
+        if receipts_root != header.receipts_root {
+            bail!(
+                "receipts root mismatch for block #{}: header {:?}, computed {:?}",
+                header.number,
+                header.receipts_root,
+                receipts_root
+            );
+        }
+    }
+
+    Ok(receipts)
+}